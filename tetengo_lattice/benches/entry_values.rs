@@ -0,0 +1,241 @@
+/*!
+ * A benchmark of the entry values.
+ *
+ * Compares the entries sharing their values with `Rc<dyn Any>` to the ones
+ * holding their values inline.
+ *
+ * Run with `cargo bench -p tetengo_lattice --bench entry_values --features inline-values`.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::hint::black_box;
+use std::mem::size_of;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use tetengo_lattice::{
+    Entry, HashMapVocabulary, InlineValue, Input, Node, StringInput, Vocabulary,
+};
+
+const KEY_COUNT: usize = 64;
+
+const ENTRIES_PER_KEY: usize = 16;
+
+const ITERATION_COUNT: usize = 200;
+
+fn main() {
+    println!("size_of::<Entry>():       {:>4} bytes", size_of::<Entry>());
+    println!(
+        "size_of::<InlineValue>(): {:>4} bytes",
+        size_of::<InlineValue>()
+    );
+    println!();
+
+    let keys = (0..KEY_COUNT)
+        .map(|i| format!("key{}", i))
+        .collect::<Vec<_>>();
+    let values = (0..KEY_COUNT * ENTRIES_PER_KEY)
+        .map(|i| &*Box::leak(format!("value{}", i).into_boxed_str()))
+        .collect::<Vec<&'static str>>();
+
+    let (shared_entry_map, inline_entry_map) = bench_creation(&keys, &values);
+    bench_find_entries(&keys, &shared_entry_map, &inline_entry_map);
+    bench_find_connection(&shared_entry_map, &inline_entry_map);
+    bench_value(&shared_entry_map, &inline_entry_map);
+}
+
+// Measures creating the entries.
+#[allow(clippy::type_complexity)]
+fn bench_creation(
+    keys: &[String],
+    values: &[&'static str],
+) -> (Vec<(String, Vec<Entry>)>, Vec<(String, Vec<Entry>)>) {
+    let make_entries = |make_entry: &dyn Fn(Rc<dyn Input>, &'static str, i32) -> Entry| {
+        keys.iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let key_input: Rc<dyn Input> = Rc::new(StringInput::new(key.clone()));
+                let entries = (0..ENTRIES_PER_KEY)
+                    .map(|j| {
+                        make_entry(key_input.clone(), values[i * ENTRIES_PER_KEY + j], j as i32)
+                    })
+                    .collect::<Vec<_>>();
+                (key.clone(), entries)
+            })
+            .collect::<Vec<_>>()
+    };
+    let make_shared_entry =
+        |key: Rc<dyn Input>, value: &'static str, cost: i32| Entry::new(key, Rc::new(value), cost);
+    let make_inline_entry = |key: Rc<dyn Input>, value: &'static str, cost: i32| {
+        Entry::new_with_inline_value(key, InlineValue::Str(value), cost)
+    };
+
+    report(
+        "entry creation",
+        measure(|| {
+            drop(black_box(make_entries(&make_shared_entry)));
+        }),
+        measure(|| {
+            drop(black_box(make_entries(&make_inline_entry)));
+        }),
+    );
+
+    (
+        make_entries(&make_shared_entry),
+        make_entries(&make_inline_entry),
+    )
+}
+
+// Measures finding the entries of each key, which clones them.
+fn bench_find_entries(
+    keys: &[String],
+    shared_entry_map: &[(String, Vec<Entry>)],
+    inline_entry_map: &[(String, Vec<Entry>)],
+) {
+    let inputs = keys
+        .iter()
+        .map(|key| StringInput::new(key.clone()))
+        .collect::<Vec<_>>();
+
+    report(
+        "HashMapVocabulary::find_entries",
+        measure_find_entries(&inputs, shared_entry_map),
+        measure_find_entries(&inputs, inline_entry_map),
+    );
+}
+
+fn measure_find_entries(inputs: &[StringInput], entry_map: &[(String, Vec<Entry>)]) -> Duration {
+    let vocabulary = HashMapVocabulary::new(
+        entry_map.to_vec(),
+        Vec::new(),
+        &entry_hash_value,
+        &entry_equal,
+    );
+    measure(|| {
+        for input in inputs {
+            drop(black_box(vocabulary.find_entries(input).unwrap()));
+        }
+    })
+}
+
+// Measures finding the connections between the entries of adjacent keys.
+fn bench_find_connection(
+    shared_entry_map: &[(String, Vec<Entry>)],
+    inline_entry_map: &[(String, Vec<Entry>)],
+) {
+    report(
+        "HashMapVocabulary::find_connection",
+        measure_find_connection(shared_entry_map),
+        measure_find_connection(inline_entry_map),
+    );
+}
+
+fn measure_find_connection(entry_map: &[(String, Vec<Entry>)]) -> Duration {
+    let connections = entry_map
+        .windows(2)
+        .flat_map(|pair| {
+            let (from_entries, to_entries) = (&pair[0].1, &pair[1].1);
+            from_entries.iter().flat_map(move |from| {
+                to_entries
+                    .iter()
+                    .map(move |to| ((from.clone(), to.clone()), from.cost() + to.cost()))
+            })
+        })
+        .collect::<Vec<_>>();
+    let vocabulary = HashMapVocabulary::new(
+        entry_map.to_vec(),
+        connections,
+        &entry_hash_value,
+        &entry_equal,
+    );
+    let nodes = entry_map
+        .iter()
+        .map(|(_, entries)| {
+            entries
+                .iter()
+                .map(|entry| Node::new_with_entry(entry, 0, 0, Rc::new(Vec::new()), 0, 0).unwrap())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    measure(|| {
+        for (from_nodes, (_, to_entries)) in nodes.iter().zip(&entry_map[1..]) {
+            for from in from_nodes {
+                for to in to_entries {
+                    let _ = black_box(vocabulary.find_connection(from, to).unwrap());
+                }
+            }
+        }
+    })
+}
+
+// Measures reading the values through the entries.
+fn bench_value(
+    shared_entry_map: &[(String, Vec<Entry>)],
+    inline_entry_map: &[(String, Vec<Entry>)],
+) {
+    report(
+        "Entry::value",
+        measure_value(shared_entry_map),
+        measure_value(inline_entry_map),
+    );
+}
+
+fn measure_value(entry_map: &[(String, Vec<Entry>)]) -> Duration {
+    measure(|| {
+        for (_, entries) in entry_map {
+            for entry in entries {
+                let _ = black_box(entry.value().and_then(|v| v.downcast_ref::<&str>()));
+            }
+        }
+    })
+}
+
+fn entry_hash_value(entry: &Entry) -> u64 {
+    entry.key().map_or(0, |key| key.hash_value())
+        ^ entry
+            .value()
+            .and_then(|value| value.downcast_ref::<&str>())
+            .map_or(0, |value| {
+                let mut hasher = DefaultHasher::new();
+                value.hash(&mut hasher);
+                hasher.finish()
+            })
+}
+
+fn entry_equal(one: &Entry, another: &Entry) -> bool {
+    match (one.key(), another.key()) {
+        (Some(one_key), Some(another_key)) => {
+            one_key.equal_to(another_key)
+                && one.value().and_then(|value| value.downcast_ref::<&str>())
+                    == another
+                        .value()
+                        .and_then(|value| value.downcast_ref::<&str>())
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+// Returns the best time of the iterations.
+fn measure(mut f: impl FnMut()) -> Duration {
+    (0..ITERATION_COUNT)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn report(name: &str, shared_duration: Duration, inline_duration: Duration) {
+    println!("{}", name);
+    println!("    Rc<dyn Any>:  {:>10.1?}", shared_duration);
+    println!(
+        "    inline:       {:>10.1?} ({:+.1}%)",
+        inline_duration,
+        (inline_duration.as_secs_f64() / shared_duration.as_secs_f64() - 1.0) * 100.0
+    );
+}
@@ -0,0 +1,110 @@
+/*!
+ * A benchmark of the N-best paths on a long lattice.
+ *
+ * Measures building a lattice of 10,000 steps and enumerating its 50 best
+ * paths. The nodes are pushed back with their entries, so the vocabulary
+ * lookups of the input suffixes are not measured.
+ *
+ * Run with `cargo bench -p tetengo_lattice --bench n_best_paths`.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::any::Any;
+use std::hint::black_box;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use tetengo_lattice::{
+    Connection, Constraint, Entry, Input, Lattice, NBestIterator, Node, StringInput, Vocabulary,
+};
+
+const STEP_COUNT: usize = 10_000;
+
+const ENTRIES_PER_STEP: usize = 2;
+
+const PATH_COUNT: usize = 50;
+
+const ITERATION_COUNT: usize = 5;
+
+// A vocabulary connecting any nodes with a constant cost.
+#[derive(Debug)]
+struct ConstantVocabulary;
+
+impl Vocabulary for ConstantVocabulary {
+    fn find_entries(&self, _key: &dyn Input) -> Result<Vec<Entry>> {
+        Ok(Vec::new())
+    }
+
+    fn find_connection(&self, _from: &Node, _to: &Entry) -> Result<Connection> {
+        Ok(Connection::new(1))
+    }
+}
+
+fn main() {
+    let vocabulary = ConstantVocabulary;
+    let key: Rc<dyn Input> = Rc::new(StringInput::new(String::from("a")));
+    let entries = (0..ENTRIES_PER_STEP)
+        .map(|i| {
+            let value: Rc<dyn Any> = Rc::new(i);
+            Entry::new(key.clone(), value, i as i32)
+        })
+        .collect::<Vec<_>>();
+
+    let build_lattice = || {
+        let mut lattice = Lattice::new(&vocabulary);
+        for _ in 0..STEP_COUNT {
+            let _ = lattice
+                .push_back_with_entries(
+                    Box::new(StringInput::new(String::from("a"))),
+                    entries.clone(),
+                )
+                .unwrap();
+        }
+        let eos_node = lattice.settle().unwrap();
+        (lattice, eos_node)
+    };
+
+    let build_duration = measure(|| {
+        drop(black_box(build_lattice()));
+    });
+    println!(
+        "building a lattice of {} steps and {} nodes",
+        STEP_COUNT,
+        STEP_COUNT * ENTRIES_PER_STEP
+    );
+    println!("    total:        {:>10.1?}", build_duration);
+    println!(
+        "    per node:     {:>10.1?}",
+        build_duration / (STEP_COUNT * ENTRIES_PER_STEP) as u32
+    );
+
+    let (lattice, eos_node) = build_lattice();
+    let n_best_duration = measure(|| {
+        let paths = NBestIterator::new(&lattice, eos_node.clone(), Box::new(Constraint::new()))
+            .take(PATH_COUNT)
+            .collect::<Vec<_>>();
+        assert_eq!(paths.len(), PATH_COUNT);
+        drop(black_box(paths));
+    });
+    println!("enumerating the {} best paths", PATH_COUNT);
+    println!("    total:        {:>10.1?}", n_best_duration);
+    println!(
+        "    per path:     {:>10.1?}",
+        n_best_duration / PATH_COUNT as u32
+    );
+}
+
+// Returns the best time of the iterations.
+fn measure(mut f: impl FnMut()) -> Duration {
+    (0..ITERATION_COUNT)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
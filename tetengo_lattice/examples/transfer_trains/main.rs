@@ -16,9 +16,9 @@ use std::process::exit;
 use anyhow::Result;
 use unicode_width::UnicodeWidthStr;
 
-use tetengo_lattice::{Constraint, Lattice, NBestIterator, Node, StringInput};
+use tetengo_lattice::{Constraint, EntryView, Lattice, NBestIterator, Node, ScheduleEntry};
 
-use crate::timetable::{Section, Timetable};
+use crate::timetable::Timetable;
 
 fn main() {
     if let Err(e) = main_core() {
@@ -45,12 +45,12 @@ fn main_core() -> Result<()> {
         };
 
         let ((_, departure_time), _) = departure_and_arrival;
-        let vocabulary = timetable.create_vocabulary(departure_time);
+        let vocabulary = timetable.create_vocabulary(departure_time)?;
         let mut lattice = Lattice::new(vocabulary.as_ref());
         build_lattice(departure_and_arrival, &timetable, &mut lattice)?;
         let eos_node = lattice.settle()?;
 
-        let trips = enumerate_trips(&lattice, eos_node, 5);
+        let trips = enumerate_trips(&lattice, eos_node, 5, &timetable);
 
         print_trips(&trips, &timetable);
     }
@@ -147,14 +147,11 @@ fn build_lattice(
     timetable: &Timetable,
     lattice: &mut Lattice<'_>,
 ) -> Result<()> {
-    for i in departure_station_index..arrival_station_index {
-        let key = format!(
-            "{}-{}/",
-            timetable.stations()[i].telegram_code(),
-            timetable.stations()[i + 1].telegram_code()
-        );
-        let input = Box::new(StringInput::new(key));
-        lattice.push_back(input)?;
+    for input in timetable
+        .schedule()
+        .section_inputs(departure_station_index, arrival_station_index)?
+    {
+        let _delta = lattice.push_back(Box::new(input))?;
     }
     Ok(())
 }
@@ -175,7 +172,12 @@ struct Trip {
     pub(crate) cost: i32,
 }
 
-fn enumerate_trips(lattice: &Lattice<'_>, eos_node: Node, trip_capacity: usize) -> Vec<Trip> {
+fn enumerate_trips(
+    lattice: &Lattice<'_>,
+    eos_node: Node,
+    trip_capacity: usize,
+    timetable: &Timetable,
+) -> Vec<Trip> {
     let iter = NBestIterator::new(lattice, eos_node, Box::new(Constraint::new()));
     let mut trips = Vec::with_capacity(trip_capacity);
     let mut duplication_checker = HashSet::<String>::new();
@@ -189,15 +191,13 @@ fn enumerate_trips(lattice: &Lattice<'_>, eos_node: Node, trip_capacity: usize)
             cost: 0,
         };
         for node in path.nodes() {
-            let section = if let Some(node_value) = node.value() {
-                if let Some(section) = node_value.downcast_ref::<Section>() {
-                    section
-                } else {
-                    continue;
-                }
-            } else {
+            let Some(section) = EntryView::<ScheduleEntry<usize>>::from_node(node)
+                .ok()
+                .and_then(|view| view.value())
+            else {
                 continue;
             };
+            let train = &timetable.trains()[*section.payload()];
 
             if trip.sections.is_empty()
                 || trip
@@ -205,18 +205,14 @@ fn enumerate_trips(lattice: &Lattice<'_>, eos_node: Node, trip_capacity: usize)
                     .last()
                     .unwrap_or_else(|| unreachable!("trip.sections must not empty."))
                     .train_number
-                    != section.train().number()
+                    != train.number()
             {
                 trip.sections.push(TripSection {
-                    train_number: section.train().number().to_string(),
-                    train_name: section.train().name().to_string(),
-                    departure_time: section.train().stops()[section.from()]
-                        .departure_time()
-                        .unwrap_or_else(|| unreachable!("departure_time must not None.")),
+                    train_number: train.number().to_string(),
+                    train_name: train.name().to_string(),
+                    departure_time: section.departure(),
                     departure_station: section.from(),
-                    arrival_time: section.train().stops()[section.to()]
-                        .arrival_time()
-                        .unwrap_or_else(|| unreachable!("arrival_time must not None.")),
+                    arrival_time: section.arrival(),
                     arrival_station: section.to(),
                 });
             } else {
@@ -224,9 +220,7 @@ fn enumerate_trips(lattice: &Lattice<'_>, eos_node: Node, trip_capacity: usize)
                     .sections
                     .last_mut()
                     .unwrap_or_else(|| unreachable!("trip.sections must not empty."));
-                last_section.arrival_time = section.train().stops()[section.to()]
-                    .arrival_time()
-                    .unwrap_or_else(|| unreachable!("arrival_time must not None."));
+                last_section.arrival_time = section.arrival();
                 last_section.arrival_station = section.to();
             }
         }
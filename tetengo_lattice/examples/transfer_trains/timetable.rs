@@ -4,14 +4,11 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use std::collections::HashMap;
-use std::hash::{DefaultHasher, Hash, Hasher};
 use std::io::{BufRead, Lines};
-use std::rc::Rc;
 
 use anyhow::Result;
 
-use tetengo_lattice::{Entry, HashMapVocabulary, StringInput, Vocabulary};
+use tetengo_lattice::{Schedule, ScheduleEntry, Vocabulary, WaitingCost};
 
 /**
  * A timetable error.
@@ -233,60 +230,6 @@ impl Train {
     }
 }
 
-/**
- * A section.
- */
-#[derive(Clone, Debug)]
-pub(crate) struct Section {
-    train: Rc<Train>,
-    from: usize,
-    to: usize,
-}
-
-impl Section {
-    /**
-     * Creates a section.
-     *
-     * # Arguments
-     * * `train` - A train.
-     * * `from`  - A departure station index.
-     * * `to`    - An arrival station index.
-     */
-    pub(crate) const fn new(train: Rc<Train>, from: usize, to: usize) -> Self {
-        Self { train, from, to }
-    }
-
-    /**
-     * Returns the train.
-     *
-     * # Returns
-     * The train.
-     */
-    pub(crate) fn train(&self) -> &Train {
-        self.train.as_ref()
-    }
-
-    /**
-     * Returns the departure station index.
-     *
-     * # Returns
-     * The departure station index.
-     */
-    pub(crate) const fn from(&self) -> usize {
-        self.from
-    }
-
-    /**
-     * Returns the arrival station index.
-     *
-     * # Returns
-     * The arrival station index.
-     */
-    pub(crate) const fn to(&self) -> usize {
-        self.to
-    }
-}
-
 #[derive(Debug)]
 struct TimetableValue {
     stations: Vec<Station>,
@@ -305,6 +248,7 @@ impl TimetableValue {
 #[derive(Debug)]
 pub(crate) struct Timetable {
     value: TimetableValue,
+    schedule: Schedule<usize>,
 }
 
 impl Timetable {
@@ -315,9 +259,9 @@ impl Timetable {
      * * `reader` - A reader.
      */
     pub(crate) fn new(reader: Box<dyn BufRead>) -> Result<Self> {
-        Ok(Self {
-            value: Self::build_timetable(reader)?,
-        })
+        let value = Self::build_timetable(reader)?;
+        let schedule = Self::build_schedule(&value)?;
+        Ok(Self { value, schedule })
     }
 
     fn build_timetable(mut reader: Box<dyn BufRead>) -> Result<TimetableValue> {
@@ -510,6 +454,28 @@ impl Timetable {
         self.value.stations.len()
     }
 
+    /**
+     * Returns the trains.
+     *
+     * # Returns
+     * The trains.
+     */
+    pub(crate) fn trains(&self) -> &[Train] {
+        self.value.trains.as_slice()
+    }
+
+    /**
+     * Returns the schedule.
+     *
+     * The payload of a schedule entry is the index of the train.
+     *
+     * # Returns
+     * The schedule.
+     */
+    pub(crate) const fn schedule(&self) -> &Schedule<usize> {
+        &self.schedule
+    }
+
     /**
      * Creates a vocabulary.
      *
@@ -518,39 +484,48 @@ impl Timetable {
      *
      * # Returns
      * A vocabulary.
+     *
+     * # Errors
+     * * When the vocabulary cannot be created.
      */
-    pub(crate) fn create_vocabulary(&self, departure_time: usize) -> Box<dyn Vocabulary> {
-        let entries = Self::build_entries(&self.value);
-        let connections = Self::build_connections(&entries, departure_time);
-        Box::new(HashMapVocabulary::new(
-            entries,
-            connections,
-            &Self::entry_hash_value,
-            &Self::entry_equal_to,
-        ))
-    }
-
-    fn build_entries(timetable: &TimetableValue) -> Vec<(String, Vec<Entry>)> {
-        let mut map = HashMap::<String, Vec<Entry>>::new();
-        for train in &timetable.trains {
+    pub(crate) fn create_vocabulary(&self, departure_time: usize) -> Result<Box<dyn Vocabulary>> {
+        self.schedule
+            .create_vocabulary(departure_time, &WaitingCost::new(60, 1, 240, 90))
+    }
+
+    fn build_schedule(timetable: &TimetableValue) -> Result<Schedule<usize>> {
+        let mut schedule = Schedule::new(
+            timetable
+                .stations
+                .iter()
+                .map(|station| station.telegram_code().to_string())
+                .collect(),
+        );
+        for (i, train) in timetable.trains.iter().enumerate() {
             for from in 0..timetable.stations.len() - 1 {
                 for to in from + 1..timetable.stations.len() {
                     if !Self::all_passing(train.stops(), from, to) {
                         continue;
                     }
 
-                    let section_name = Self::make_section_name(&timetable.stations, from, to);
-                    let found = map.entry(section_name.clone()).or_default();
-                    let section = Section::new(Rc::new(train.clone()), from, to);
-                    found.push(Entry::new(
-                        Rc::new(StringInput::new(section_name)),
-                        Rc::new(section),
-                        Self::make_section_duration(train.stops(), from, to) as i32,
-                    ));
+                    let departure_time =
+                        train.stops()[from].departure_time().unwrap_or_else(|| {
+                            unreachable!("departure_time must be set.");
+                        });
+                    let arrival_time = train.stops()[to].arrival_time().unwrap_or_else(|| {
+                        unreachable!("arrival_time must be set.");
+                    });
+                    schedule.add_entry(ScheduleEntry::new(
+                        from,
+                        to,
+                        departure_time,
+                        arrival_time,
+                        i,
+                    ))?;
                 }
             }
         }
-        map.into_iter().collect::<Vec<_>>()
+        Ok(schedule)
     }
 
     fn all_passing(stops: &[Stop], from: usize, to: usize) -> bool {
@@ -568,101 +543,6 @@ impl Timetable {
         true
     }
 
-    fn make_section_name(stations: &[Station], from: usize, to: usize) -> String {
-        let mut name = String::new();
-        for i in from..to {
-            name += &format!(
-                "{}-{}/",
-                stations[i].telegram_code(),
-                stations[i + 1].telegram_code()
-            );
-        }
-        name
-    }
-
-    fn make_section_duration(stops: &[Stop], from: usize, to: usize) -> usize {
-        let departure_time = stops[from].departure_time().unwrap_or_else(|| {
-            unreachable!("departure_time must be set.");
-        });
-        let arrival_time = stops[to].arrival_time().unwrap_or_else(|| {
-            unreachable!("arrival_time must be set.");
-        });
-        Self::diff_time(arrival_time, departure_time) as usize
-    }
-
-    fn build_connections(
-        entries: &[(String, Vec<Entry>)],
-        departure_time: usize,
-    ) -> Vec<((Entry, Entry), i32)> {
-        let mut connections = Vec::<((Entry, Entry), i32)>::new();
-
-        for (_, from_entries) in entries {
-            for (_, to_entries) in entries {
-                for from_entry in from_entries {
-                    for to_entry in to_entries {
-                        let from_value = from_entry
-                            .value()
-                            .unwrap_or_else(|| {
-                                unreachable!("from_entry.value() must not be empty.")
-                            })
-                            .downcast_ref::<Section>()
-                            .unwrap_or_else(|| unreachable!("from_entry.value() must be Section."));
-                        let to_value = to_entry
-                            .value()
-                            .unwrap_or_else(|| unreachable!("to_entry.value() must not be empty."))
-                            .downcast_ref::<Section>()
-                            .unwrap_or_else(|| unreachable!("to_entry.value() must be Section."));
-                        if from_value.to() != to_value.from() {
-                            continue;
-                        }
-
-                        let from_arrival_time = from_value.train().stops()[from_value.to()]
-                            .arrival_time()
-                            .unwrap_or_else(|| {
-                                unreachable!("from arrival_time must be set.");
-                            });
-                        let to_departure_time = to_value.train().stops()[to_value.from()]
-                            .departure_time()
-                            .unwrap_or_else(|| {
-                                unreachable!("to departure_time must be set.");
-                            });
-                        let cost = Self::diff_time(to_departure_time, from_arrival_time) as i32;
-                        if cost > 60 {
-                            continue;
-                        }
-                        if from_value.train().number() != to_value.train().number() {
-                            connections.push(((from_entry.clone(), to_entry.clone()), cost + 1));
-                        } else {
-                            connections.push(((from_entry.clone(), to_entry.clone()), cost));
-                        }
-                    }
-                }
-            }
-        }
-
-        for (_, entries) in entries {
-            for entry in entries {
-                let section = entry
-                    .value()
-                    .unwrap_or_else(|| unreachable!("entry.value() must not be empty."))
-                    .downcast_ref::<Section>()
-                    .unwrap_or_else(|| unreachable!("entry.value() must be Section."));
-                let section_departure_time = section.train().stops()[section.from()]
-                    .departure_time()
-                    .unwrap_or_else(|| {
-                        unreachable!("departure_time() must be set.");
-                    });
-                let bos_cost = Self::diff_time(section_departure_time, departure_time) as i32;
-                if bos_cost <= 240 {
-                    connections.push(((Entry::BosEos, entry.clone()), bos_cost * 9 / 10));
-                }
-                connections.push(((entry.clone(), Entry::BosEos), 0));
-            }
-        }
-
-        connections
-    }
-
     const fn add_time(time: usize, duration: isize) -> usize {
         assert!(time < 1440);
         assert!(-1440 < duration && duration < 1440);
@@ -674,73 +554,4 @@ impl Timetable {
         assert!(time2 < 1440);
         (time1 as isize + 1440 - time2 as isize) % 1440
     }
-
-    fn entry_hash_value(entry: &Entry) -> u64 {
-        let mut hasher = DefaultHasher::new();
-
-        hasher.write_u64(if let Some(key) = entry.key() {
-            key.hash_value()
-        } else {
-            0
-        });
-        let section = if let Some(value) = entry.value() {
-            value.downcast_ref::<Section>()
-        } else {
-            None
-        };
-        if let Some(section) = section {
-            section.train().number().hash(&mut hasher);
-            section.train().name().hash(&mut hasher);
-            section.from().hash(&mut hasher);
-            section.to().hash(&mut hasher);
-        } else {
-            "".hash(&mut hasher);
-            "".hash(&mut hasher);
-            0usize.hash(&mut hasher);
-            0usize.hash(&mut hasher);
-        }
-        hasher.finish()
-    }
-
-    fn entry_equal_to(one: &Entry, another: &Entry) -> bool {
-        if let Some(one_value) = one.value() {
-            if let Some(another_value) = another.value() {
-                let Some(one_section) = one_value.downcast_ref::<Section>() else {
-                    unreachable!("one.value() must be Section.");
-                };
-                let Some(another_section) = another_value.downcast_ref::<Section>() else {
-                    unreachable!("another.value() must be Section.");
-                };
-                let is_equal = if let Some(one_key) = one.key() {
-                    if let Some(another_key) = another.key() {
-                        one_key.equal_to(another_key)
-                    } else {
-                        false
-                    }
-                } else {
-                    another.key().is_none()
-                } && one_section.train().number()
-                    == another_section.train().number()
-                    && one_section.train().name() == another_section.train().name()
-                    && one_section.from() == another_section.from()
-                    && one_section.to() == another_section.to();
-                is_equal
-            } else {
-                false
-            }
-        } else if another.value().is_none() {
-            let is_equal = if let Some(one_key) = one.key() {
-                if let Some(another_key) = another.key() {
-                    one_key.equal_to(another_key)
-                } else {
-                    false
-                }
-            } else {
-                another.key().is_none()
-            };
-            is_equal
-        } else {
-            false
-        }
-    }
 }
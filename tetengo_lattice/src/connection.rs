@@ -34,6 +34,36 @@ impl Connection {
     }
 }
 
+/**
+ * A connection lookup.
+ *
+ * Finds the connection costs by the context IDs. Implement it to plug a
+ * connection backend, such as a connection matrix, a function or a learned
+ * model, into a `MatrixVocabulary`.
+ *
+ * Any `Fn(u16, u16) -> i32` is a connection lookup.
+ */
+pub trait ConnectionLookup {
+    /**
+     * Returns the connection cost.
+     *
+     * # Arguments
+     * * `right_id` - A right context ID of an origin.
+     * * `left_id`  - A left context ID of a destination.
+     *
+     * # Returns
+     * The connection cost. Or `i32::MAX` when the origin cannot connect to the
+     * destination.
+     */
+    fn cost(&self, right_id: u16, left_id: u16) -> i32;
+}
+
+impl<F: Fn(u16, u16) -> i32> ConnectionLookup for F {
+    fn cost(&self, right_id: u16, left_id: u16) -> i32 {
+        self(right_id, left_id)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -49,4 +79,10 @@ mod tests {
 
         assert_eq!(connection_.cost(), 42);
     }
+
+    #[test]
+    fn connection_lookup_cost() {
+        let lookup = |right_id: u16, left_id: u16| (right_id * 10 + left_id) as i32;
+        assert_eq!(ConnectionLookup::cost(&lookup, 4, 2), 42);
+    }
 }
@@ -0,0 +1,263 @@
+/*!
+ * Cost conversions.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use anyhow::Result;
+
+/**
+ * A cost error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum CostError {
+    /**
+     * The scale is not a positive finite number.
+     */
+    #[error("the scale is not a positive finite number")]
+    InvalidScale,
+
+    /**
+     * The max cost is not positive or is i32::MAX.
+     */
+    #[error("the max cost is not positive or is i32::MAX")]
+    InvalidMaxCost,
+}
+
+/**
+ * A cost scale.
+ *
+ * Converts probabilities and log-probabilities to costs and vice versa. The
+ * cost of a probability `p` is `-ln(p) * scale`, rounded to the nearest
+ * integer.
+ *
+ * The finite costs are clamped into `-max_cost..=max_cost`, so that a sum of
+ * some costs does not overflow. The probability 0 is converted to `i32::MAX`,
+ * which the lattice regards as unreachable.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CostScale {
+    scale: f64,
+    max_cost: i32,
+}
+
+impl CostScale {
+    /// The default max cost.
+    pub const DEFAULT_MAX_COST: i32 = 0x100000;
+
+    /**
+     * Creates a cost scale.
+     *
+     * # Arguments
+     * * `scale` - A scale. The cost of a probability `p` is `-ln(p) * scale`.
+     *
+     * # Errors
+     * * When `scale` is not a positive finite number.
+     */
+    pub fn new(scale: f64) -> Result<Self> {
+        Self::new_with_max_cost(scale, Self::DEFAULT_MAX_COST)
+    }
+
+    /**
+     * Creates a cost scale with a max cost.
+     *
+     * # Arguments
+     * * `scale`    - A scale. The cost of a probability `p` is `-ln(p) * scale`.
+     * * `max_cost` - A max cost of the finite costs.
+     *
+     * # Errors
+     * * When `scale` is not a positive finite number.
+     * * When `max_cost` is not positive or is `i32::MAX`.
+     */
+    pub fn new_with_max_cost(scale: f64, max_cost: i32) -> Result<Self> {
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(CostError::InvalidScale.into());
+        }
+        if max_cost <= 0 || max_cost == i32::MAX {
+            return Err(CostError::InvalidMaxCost.into());
+        }
+        Ok(Self { scale, max_cost })
+    }
+
+    /**
+     * Returns the scale.
+     *
+     * # Returns
+     * The scale.
+     */
+    pub const fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /**
+     * Returns the max cost.
+     *
+     * # Returns
+     * The max cost of the finite costs.
+     */
+    pub const fn max_cost(&self) -> i32 {
+        self.max_cost
+    }
+
+    /**
+     * Converts a probability to a cost.
+     *
+     * # Arguments
+     * * `probability` - A probability.
+     *
+     * # Returns
+     * The cost. Or `i32::MAX` when `probability` is not positive or is NaN.
+     */
+    pub fn cost_of_probability(&self, probability: f64) -> i32 {
+        self.cost_of_log_probability(probability.ln())
+    }
+
+    /**
+     * Converts a natural log-probability to a cost.
+     *
+     * # Arguments
+     * * `log_probability` - A natural log-probability.
+     *
+     * # Returns
+     * The cost. Or `i32::MAX` when `log_probability` is negative infinity or
+     * NaN.
+     */
+    pub fn cost_of_log_probability(&self, log_probability: f64) -> i32 {
+        if log_probability.is_nan() || log_probability == f64::NEG_INFINITY {
+            return i32::MAX;
+        }
+        let max_cost = self.max_cost as f64;
+        (-log_probability * self.scale)
+            .round()
+            .clamp(-max_cost, max_cost) as i32
+    }
+
+    /**
+     * Converts a cost to a probability.
+     *
+     * # Arguments
+     * * `cost` - A cost.
+     *
+     * # Returns
+     * The probability. Or 0 when `cost` is `i32::MAX`.
+     */
+    pub fn probability_of_cost(&self, cost: i32) -> f64 {
+        self.log_probability_of_cost(cost).exp()
+    }
+
+    /**
+     * Converts a cost to a natural log-probability.
+     *
+     * # Arguments
+     * * `cost` - A cost.
+     *
+     * # Returns
+     * The natural log-probability. Or negative infinity when `cost` is
+     * `i32::MAX`.
+     */
+    pub fn log_probability_of_cost(&self, cost: i32) -> f64 {
+        if cost == i32::MAX {
+            return f64::NEG_INFINITY;
+        }
+        -(cost as f64) / self.scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new() {
+        {
+            let _scale = CostScale::new(100.0).unwrap();
+        }
+        {
+            for scale in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+                let error = CostScale::new(scale).unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<CostError>().unwrap(),
+                    CostError::InvalidScale
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn new_with_max_cost() {
+        {
+            let _scale = CostScale::new_with_max_cost(100.0, 1000).unwrap();
+        }
+        {
+            for max_cost in [0, -1, i32::MAX] {
+                let error = CostScale::new_with_max_cost(100.0, max_cost).unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<CostError>().unwrap(),
+                    CostError::InvalidMaxCost
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn scale() {
+        assert_eq!(CostScale::new(100.0).unwrap().scale(), 100.0);
+    }
+
+    #[test]
+    fn max_cost() {
+        assert_eq!(
+            CostScale::new(100.0).unwrap().max_cost(),
+            CostScale::DEFAULT_MAX_COST
+        );
+        assert_eq!(
+            CostScale::new_with_max_cost(100.0, 1000)
+                .unwrap()
+                .max_cost(),
+            1000
+        );
+    }
+
+    #[test]
+    fn cost_of_probability() {
+        let scale = CostScale::new_with_max_cost(100.0, 1000).unwrap();
+
+        assert_eq!(scale.cost_of_probability(1.0), 0);
+        assert_eq!(scale.cost_of_probability(0.5), 69);
+        assert_eq!(scale.cost_of_probability(1e-100), 1000);
+        assert_eq!(scale.cost_of_probability(0.0), i32::MAX);
+        assert_eq!(scale.cost_of_probability(-0.5), i32::MAX);
+        assert_eq!(scale.cost_of_probability(f64::NAN), i32::MAX);
+    }
+
+    #[test]
+    fn cost_of_log_probability() {
+        let scale = CostScale::new_with_max_cost(100.0, 1000).unwrap();
+
+        assert_eq!(scale.cost_of_log_probability(0.0), 0);
+        assert_eq!(scale.cost_of_log_probability(-2.5), 250);
+        assert_eq!(scale.cost_of_log_probability(2.5), -250);
+        assert_eq!(scale.cost_of_log_probability(-1e300), 1000);
+        assert_eq!(scale.cost_of_log_probability(1e300), -1000);
+        assert_eq!(scale.cost_of_log_probability(f64::NEG_INFINITY), i32::MAX);
+        assert_eq!(scale.cost_of_log_probability(f64::INFINITY), -1000);
+    }
+
+    #[test]
+    fn probability_of_cost() {
+        let scale = CostScale::new(100.0).unwrap();
+
+        assert_eq!(scale.probability_of_cost(0), 1.0);
+        assert!((scale.probability_of_cost(69) - 0.5).abs() < 0.01);
+        assert_eq!(scale.probability_of_cost(i32::MAX), 0.0);
+    }
+
+    #[test]
+    fn log_probability_of_cost() {
+        let scale = CostScale::new(100.0).unwrap();
+
+        assert_eq!(scale.log_probability_of_cost(0), 0.0);
+        assert_eq!(scale.log_probability_of_cost(250), -2.5);
+        assert_eq!(scale.log_probability_of_cost(i32::MAX), f64::NEG_INFINITY);
+    }
+}
@@ -0,0 +1,92 @@
+/*!
+ * A cost aggregation.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+/**
+ * A cost aggregation.
+ *
+ * Determines how a lattice aggregates the costs of the paths reaching a node
+ * into the path cost of the node.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum CostAggregation {
+    /**
+     * The min-plus aggregation.
+     *
+     * The path cost of a node is the cost of the best path reaching it. This
+     * is the Viterbi decoding.
+     */
+    #[default]
+    MinPlus,
+
+    /**
+     * The log-sum aggregation.
+     *
+     * The path cost of a node is `-ln(Σ exp(-cost / temperature)) *
+     * temperature` over all the paths reaching it, rounded to the nearest
+     * integer. So the path cost of the EOS node represents the total
+     * probability of all the paths. The best preceding node is the one with
+     * the least aggregated cost.
+     */
+    LogSum {
+        /// A temperature.
+        temperature: f64,
+    },
+}
+
+impl CostAggregation {
+    /**
+     * Aggregates costs.
+     *
+     * # Arguments
+     * * `costs` - Costs. `i32::MAX` means unreachable.
+     *
+     * # Returns
+     * The aggregated cost. Or `i32::MAX` when all the costs are unreachable.
+     */
+    pub fn aggregate(&self, costs: impl Iterator<Item = i32> + Clone) -> i32 {
+        let reachable_costs = costs.filter(|&cost| cost != i32::MAX);
+        match self {
+            CostAggregation::MinPlus => reachable_costs.min().unwrap_or(i32::MAX),
+            CostAggregation::LogSum { temperature } => {
+                let Some(min) = reachable_costs.clone().min() else {
+                    return i32::MAX;
+                };
+                // The costs are shifted by the min so that exp() does not
+                // underflow.
+                let sum = reachable_costs
+                    .map(|cost| (-((cost - min) as f64) / temperature).exp())
+                    .sum::<f64>();
+                let aggregated = (min as f64 - sum.ln() * temperature).round();
+                aggregated.clamp(i32::MIN as f64, (i32::MAX - 1) as f64) as i32
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregate() {
+        {
+            let aggregation = CostAggregation::MinPlus;
+
+            assert_eq!(aggregation.aggregate([42, 24, i32::MAX].into_iter()), 24);
+            assert_eq!(aggregation.aggregate([i32::MAX].into_iter()), i32::MAX);
+            assert_eq!(aggregation.aggregate([].into_iter()), i32::MAX);
+        }
+        {
+            let aggregation = CostAggregation::LogSum { temperature: 100.0 };
+
+            assert_eq!(aggregation.aggregate([42].into_iter()), 42);
+            assert_eq!(aggregation.aggregate([100, 100].into_iter()), 31);
+            assert_eq!(aggregation.aggregate([100, 100, i32::MAX].into_iter()), 31);
+            assert_eq!(aggregation.aggregate([i32::MAX].into_iter()), i32::MAX);
+            assert_eq!(aggregation.aggregate([].into_iter()), i32::MAX);
+        }
+    }
+}
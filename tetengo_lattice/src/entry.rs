@@ -5,10 +5,61 @@
  */
 
 use std::any::Any;
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ptr;
 use std::rc::Rc;
 
+#[cfg(feature = "inline-values")]
+use crate::inline_value::InlineValue;
 use crate::input::Input;
+use crate::lattice::Lattice;
+
+// A value of an entry, shared or held in place.
+#[derive(Clone, Debug)]
+pub(crate) enum EntryValue {
+    Shared(Rc<dyn Any>),
+    #[cfg(feature = "inline-values")]
+    Inline(InlineValue),
+}
+
+impl EntryValue {
+    pub(crate) fn as_any(&self) -> &dyn Any {
+        match self {
+            EntryValue::Shared(value) => value.as_ref(),
+            #[cfg(feature = "inline-values")]
+            EntryValue::Inline(value) => value.as_any(),
+        }
+    }
+}
+
+// The shared values are identical when they are the same object, and the
+// inline ones when they are equal.
+impl Eq for EntryValue {}
+
+impl PartialEq for EntryValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (EntryValue::Shared(one), EntryValue::Shared(another)) => {
+                ptr::addr_eq(Rc::as_ptr(one), Rc::as_ptr(another))
+            }
+            #[cfg(feature = "inline-values")]
+            (EntryValue::Inline(one), EntryValue::Inline(another)) => one == another,
+            #[cfg(feature = "inline-values")]
+            _ => false,
+        }
+    }
+}
+
+impl Hash for EntryValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            EntryValue::Shared(value) => Rc::as_ptr(value).cast::<()>().hash(state),
+            #[cfg(feature = "inline-values")]
+            EntryValue::Inline(value) => value.hash(state),
+        }
+    }
+}
 
 /**
  * A middle entry.
@@ -16,8 +67,10 @@ use crate::input::Input;
 #[derive(Debug)]
 pub struct Middle {
     key: Rc<dyn Input>,
-    value: Rc<dyn Any>,
+    value: EntryValue,
     cost: i32,
+    context_ids: Option<(u16, u16)>,
+    provenance: Option<u16>,
 }
 
 impl Clone for Middle {
@@ -26,12 +79,17 @@ impl Clone for Middle {
             key: self.key.clone(),
             value: self.value.clone(),
             cost: self.cost,
+            context_ids: self.context_ids,
+            provenance: self.provenance,
         }
     }
 }
 
 /**
  * An entry.
+ *
+ * The key and the value are shared among the clones, so cloning an entry does
+ * not copy them.
  */
 #[derive(Clone, Debug)]
 pub enum Entry {
@@ -52,7 +110,80 @@ impl Entry {
      * * `cost`  - A cost.
      */
     pub fn new(key: Rc<dyn Input>, value: Rc<dyn Any>, cost: i32) -> Self {
-        Entry::Middle(Middle { key, value, cost })
+        Entry::Middle(Middle {
+            key,
+            value: EntryValue::Shared(value),
+            cost,
+            context_ids: None,
+            provenance: None,
+        })
+    }
+
+    /**
+     * Creates an entry with context IDs.
+     *
+     * The context IDs classify the entries for the connections. See
+     * `MatrixVocabulary`.
+     *
+     * # Arguments
+     * * `key`      - A box of a key.
+     * * `value`    - A box of a value.
+     * * `cost`     - A cost.
+     * * `left_id`  - A left context ID.
+     * * `right_id` - A right context ID.
+     */
+    pub fn new_with_context_ids(
+        key: Rc<dyn Input>,
+        value: Rc<dyn Any>,
+        cost: i32,
+        left_id: u16,
+        right_id: u16,
+    ) -> Self {
+        Entry::Middle(Middle {
+            key,
+            value: EntryValue::Shared(value),
+            cost,
+            context_ids: Some((left_id, right_id)),
+            provenance: None,
+        })
+    }
+
+    /**
+     * Creates an entry with an inline value.
+     *
+     * The value is held in the entry in place, so making the entry does not
+     * allocate it. `value` returns the wrapped value of the inline value.
+     *
+     * # Arguments
+     * * `key`   - A box of a key.
+     * * `value` - An inline value.
+     * * `cost`  - A cost.
+     */
+    #[cfg(feature = "inline-values")]
+    pub fn new_with_inline_value(key: Rc<dyn Input>, value: InlineValue, cost: i32) -> Self {
+        Entry::Middle(Middle {
+            key,
+            value: EntryValue::Inline(value),
+            cost,
+            context_ids: None,
+            provenance: None,
+        })
+    }
+
+    pub(crate) fn new_with_entry_value(
+        key: Rc<dyn Input>,
+        value: EntryValue,
+        cost: i32,
+        context_ids: Option<(u16, u16)>,
+        provenance: Option<u16>,
+    ) -> Self {
+        Entry::Middle(Middle {
+            key,
+            value,
+            cost,
+            context_ids,
+            provenance,
+        })
     }
 
     /**
@@ -84,11 +215,11 @@ impl Entry {
     pub fn value(&self) -> Option<&dyn Any> {
         match self {
             Entry::BosEos => None,
-            Entry::Middle(entry) => Some(entry.value.as_ref()),
+            Entry::Middle(entry) => Some(entry.value.as_any()),
         }
     }
 
-    pub(crate) fn value_rc(&self) -> Option<Rc<dyn Any>> {
+    pub(crate) fn entry_value(&self) -> Option<EntryValue> {
         match self {
             Entry::BosEos => None,
             Entry::Middle(entry) => Some(entry.value.clone()),
@@ -107,11 +238,148 @@ impl Entry {
             Entry::Middle(entry) => entry.cost,
         }
     }
+
+    /**
+     * Returns the left context ID.
+     *
+     * # Returns
+     * The left context ID. Or None when the entry is BOS/EOS or has no context
+     * IDs.
+     */
+    pub const fn left_id(&self) -> Option<u16> {
+        match self {
+            Entry::Middle(Middle {
+                context_ids: Some((left_id, _)),
+                ..
+            }) => Some(*left_id),
+            _ => None,
+        }
+    }
+
+    /**
+     * Returns the right context ID.
+     *
+     * # Returns
+     * The right context ID. Or None when the entry is BOS/EOS or has no context
+     * IDs.
+     */
+    pub const fn right_id(&self) -> Option<u16> {
+        match self {
+            Entry::Middle(Middle {
+                context_ids: Some((_, right_id)),
+                ..
+            }) => Some(*right_id),
+            _ => None,
+        }
+    }
+
+    /**
+     * Returns the provenance.
+     *
+     * # Returns
+     * The provenance tag identifying the source which produced this entry. Or
+     * None when the entry is BOS/EOS or has no provenance.
+     */
+    pub const fn provenance(&self) -> Option<u16> {
+        match self {
+            Entry::BosEos => None,
+            Entry::Middle(entry) => entry.provenance,
+        }
+    }
+
+    /**
+     * Returns this entry with a provenance.
+     *
+     * The provenance is a tag identifying the source which produced the entry,
+     * such as a dictionary. It is carried over to the node made of the entry.
+     * The BOS/EOS entry has no provenance.
+     *
+     * # Arguments
+     * * `provenance` - A provenance tag.
+     *
+     * # Returns
+     * This entry with the provenance.
+     */
+    pub fn with_provenance(self, provenance: u16) -> Self {
+        match self {
+            Entry::BosEos => Entry::BosEos,
+            Entry::Middle(entry) => Entry::Middle(Middle {
+                provenance: Some(provenance),
+                ..entry
+            }),
+        }
+    }
+
+    pub(crate) fn with_cost(self, cost: i32) -> Self {
+        match self {
+            Entry::BosEos => Entry::BosEos,
+            Entry::Middle(entry) => Entry::Middle(Middle { cost, ..entry }),
+        }
+    }
+
+    pub(crate) const fn context_ids(&self) -> Option<(u16, u16)> {
+        match self {
+            Entry::BosEos => None,
+            Entry::Middle(entry) => entry.context_ids,
+        }
+    }
+
+    /**
+     * Returns a displayable view of this entry.
+     *
+     * The value is formatted with the value formatter of the lattice.
+     *
+     * # Arguments
+     * * `lattice` - A lattice.
+     *
+     * # Returns
+     * A displayable view.
+     */
+    pub const fn display<'b>(&'b self, lattice: &'b Lattice<'b>) -> EntryDisplay<'b> {
+        EntryDisplay {
+            entry: self,
+            lattice,
+        }
+    }
+}
+
+/**
+ * A displayable view of an entry.
+ */
+#[derive(Clone, Copy)]
+pub struct EntryDisplay<'a> {
+    entry: &'a Entry,
+    lattice: &'a Lattice<'a>,
+}
+
+impl Debug for EntryDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.entry {
+            Entry::BosEos => f.debug_struct("BosEos").finish(),
+            Entry::Middle(entry) => f
+                .debug_struct("Middle")
+                .field("value", &self.lattice.format_value(entry.value.as_any()))
+                .field("cost", &entry.cost)
+                .finish(),
+        }
+    }
+}
+
+impl Display for EntryDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.entry {
+            Entry::BosEos => write!(f, "BOS/EOS"),
+            Entry::Middle(entry) => {
+                write!(f, "{}", self.lattice.format_value(entry.value.as_any()))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::string_input::StringInput;
+    use crate::unicode_vocabulary::UnicodeWordVocabulary;
 
     use super::*;
 
@@ -133,6 +401,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn new_with_context_ids() {
+        let _entry = Entry::new_with_context_ids(
+            Rc::new(StringInput::new(String::from("みずほ"))),
+            Rc::new(String::from("瑞穂")),
+            42,
+            3,
+            4,
+        );
+    }
+
+    #[cfg(feature = "inline-values")]
+    #[test]
+    fn new_with_inline_value() {
+        let entry = Entry::new_with_inline_value(
+            Rc::new(StringInput::new(String::from("みずほ"))),
+            InlineValue::Str("瑞穂"),
+            42,
+        );
+
+        assert_eq!(entry.value().unwrap().downcast_ref::<&str>(), Some(&"瑞穂"));
+        assert_eq!(entry.cost(), 42);
+        assert!(entry.left_id().is_none());
+    }
+
     #[test]
     fn clone() {
         let entry1 = Entry::new(
@@ -200,4 +493,118 @@ mod tests {
 
         assert_eq!(entry.cost(), 42);
     }
+
+    #[test]
+    fn left_id() {
+        {
+            let entry = Entry::new_with_context_ids(
+                Rc::new(StringInput::new(String::from("みずほ"))),
+                Rc::new(String::from("瑞穂")),
+                42,
+                3,
+                4,
+            );
+
+            assert_eq!(entry.left_id(), Some(3));
+        }
+        {
+            let entry = Entry::new(
+                Rc::new(StringInput::new(String::from("みずほ"))),
+                Rc::new(String::from("瑞穂")),
+                42,
+            );
+
+            assert!(entry.left_id().is_none());
+        }
+        {
+            assert!(Entry::BosEos.left_id().is_none());
+        }
+    }
+
+    #[test]
+    fn right_id() {
+        {
+            let entry = Entry::new_with_context_ids(
+                Rc::new(StringInput::new(String::from("みずほ"))),
+                Rc::new(String::from("瑞穂")),
+                42,
+                3,
+                4,
+            );
+
+            assert_eq!(entry.right_id(), Some(4));
+        }
+        {
+            let entry = Entry::new(
+                Rc::new(StringInput::new(String::from("みずほ"))),
+                Rc::new(String::from("瑞穂")),
+                42,
+            );
+
+            assert!(entry.right_id().is_none());
+        }
+        {
+            assert!(Entry::BosEos.right_id().is_none());
+        }
+    }
+
+    #[test]
+    fn provenance() {
+        {
+            let entry = Entry::new(
+                Rc::new(StringInput::new(String::from("みずほ"))),
+                Rc::new(String::from("瑞穂")),
+                42,
+            );
+
+            assert!(entry.provenance().is_none());
+        }
+        {
+            assert!(Entry::BosEos.provenance().is_none());
+        }
+    }
+
+    #[test]
+    fn with_provenance() {
+        {
+            let entry = Entry::new_with_context_ids(
+                Rc::new(StringInput::new(String::from("みずほ"))),
+                Rc::new(String::from("瑞穂")),
+                42,
+                3,
+                4,
+            )
+            .with_provenance(2);
+
+            assert_eq!(entry.provenance(), Some(2));
+            assert_eq!(entry.cost(), 42);
+            assert_eq!(entry.left_id(), Some(3));
+            assert_eq!(entry.clone().provenance(), Some(2));
+        }
+        {
+            assert!(Entry::BosEos.with_provenance(2).provenance().is_none());
+        }
+    }
+
+    #[test]
+    fn display() {
+        let vocabulary = UnicodeWordVocabulary::new();
+        let mut lattice = Lattice::new(&vocabulary);
+        let entry = Entry::new(
+            Rc::new(StringInput::new(String::from("みずほ"))),
+            Rc::new(String::from("瑞穂")),
+            42,
+        );
+
+        assert_eq!(Entry::BosEos.display(&lattice).to_string(), "BOS/EOS");
+        assert_eq!(entry.display(&lattice).to_string(), "Any { .. }");
+
+        let value_formatter = |value: &dyn Any| value.downcast_ref::<String>().unwrap().to_string();
+        lattice.set_value_formatter(&value_formatter);
+        assert_eq!(entry.display(&lattice).to_string(), "瑞穂");
+        assert_eq!(
+            format!("{:?}", entry.display(&lattice)),
+            "Middle { value: \"瑞穂\", cost: 42 }"
+        );
+    }
 }
@@ -0,0 +1,308 @@
+/*!
+ * An entry view.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::fmt::{self, Debug, Formatter};
+
+use anyhow::Result;
+
+use crate::entry::Entry;
+use crate::input::Input;
+use crate::node::Node;
+
+/**
+ * An entry view error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum EntryViewError {
+    /**
+     * The value is not of the viewed type.
+     */
+    #[error("the value is not of the viewed type")]
+    MismatchedValueType,
+}
+
+/**
+ * An entry view.
+ *
+ * Borrows the key, the value, the cost and the provenance of an entry or a
+ * node, with the value already downcast to `V`.
+ *
+ * # Type Parameters
+ * * `V` - A value type.
+ */
+pub struct EntryView<'a, V> {
+    key: Option<&'a dyn Input>,
+    value: Option<&'a V>,
+    cost: i32,
+    provenance: Option<u16>,
+}
+
+impl<'a, V: 'static> EntryView<'a, V> {
+    /**
+     * Creates a BOS/EOS entry view.
+     *
+     * # Returns
+     * A BOS/EOS entry view.
+     */
+    pub const fn bos_eos() -> Self {
+        Self {
+            key: None,
+            value: None,
+            cost: 0,
+            provenance: None,
+        }
+    }
+
+    /**
+     * Creates an entry view of an entry.
+     *
+     * # Arguments
+     * * `entry` - An entry.
+     *
+     * # Returns
+     * An entry view.
+     *
+     * # Errors
+     * * When the value of the entry is not of `V`.
+     */
+    pub fn from_entry(entry: &'a Entry) -> Result<Self> {
+        Ok(Self {
+            key: entry.key(),
+            value: Self::downcast(entry.value())?,
+            cost: entry.cost(),
+            provenance: entry.provenance(),
+        })
+    }
+
+    /**
+     * Creates an entry view of a node.
+     *
+     * # Arguments
+     * * `node` - A node.
+     *
+     * # Returns
+     * An entry view.
+     *
+     * # Errors
+     * * When the value of the node is not of `V`.
+     */
+    pub fn from_node(node: &'a Node) -> Result<Self> {
+        Ok(Self {
+            key: node.key(),
+            value: Self::downcast(node.value())?,
+            cost: node.node_cost(),
+            provenance: node.provenance(),
+        })
+    }
+
+    fn downcast(value: Option<&'a dyn std::any::Any>) -> Result<Option<&'a V>> {
+        match value {
+            Some(value) => match value.downcast_ref::<V>() {
+                Some(value) => Ok(Some(value)),
+                None => Err(EntryViewError::MismatchedValueType.into()),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /**
+     * Returns the key.
+     *
+     * # Returns
+     * The key. Or None when the entry is BOS/EOS.
+     */
+    pub const fn key(&self) -> Option<&'a dyn Input> {
+        self.key
+    }
+
+    /**
+     * Returns the value.
+     *
+     * # Returns
+     * The value. Or None when the entry is BOS/EOS.
+     */
+    pub const fn value(&self) -> Option<&'a V> {
+        self.value
+    }
+
+    /**
+     * Returns the cost.
+     *
+     * # Returns
+     * The cost.
+     */
+    pub const fn cost(&self) -> i32 {
+        self.cost
+    }
+
+    /**
+     * Returns the provenance.
+     *
+     * # Returns
+     * The provenance tag. Or None when the entry is BOS/EOS or has no
+     * provenance.
+     */
+    pub const fn provenance(&self) -> Option<u16> {
+        self.provenance
+    }
+
+    /**
+     * Returns `true` if the entry is BOS/EOS.
+     *
+     * # Returns
+     * `true` if the entry is BOS/EOS.
+     */
+    pub const fn is_bos_eos(&self) -> bool {
+        self.key.is_none()
+    }
+}
+
+impl<V> Clone for EntryView<'_, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for EntryView<'_, V> {}
+
+impl<V: Debug> Debug for EntryView<'_, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntryView")
+            .field("key", &self.key)
+            .field("value", &self.value)
+            .field("cost", &self.cost)
+            .field("provenance", &self.provenance)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::string_input::StringInput;
+
+    use super::*;
+
+    fn make_entry() -> Entry {
+        Entry::new(
+            Rc::new(StringInput::new(String::from("みずほ"))),
+            Rc::new(String::from("瑞穂")),
+            42,
+        )
+    }
+
+    #[test]
+    fn bos_eos() {
+        let view = EntryView::<String>::bos_eos();
+
+        assert!(view.key().is_none());
+        assert!(view.value().is_none());
+        assert_eq!(view.cost(), 0);
+        assert!(view.is_bos_eos());
+    }
+
+    #[test]
+    fn from_entry() {
+        {
+            let entry = make_entry();
+            let view = EntryView::<String>::from_entry(&entry).unwrap();
+
+            assert!(view
+                .key()
+                .unwrap()
+                .equal_to(&StringInput::new(String::from("みずほ"))));
+            assert_eq!(view.value().unwrap(), "瑞穂");
+            assert_eq!(view.cost(), 42);
+            assert!(!view.is_bos_eos());
+        }
+        {
+            let view = EntryView::<String>::from_entry(&Entry::BosEos).unwrap();
+
+            assert!(view.is_bos_eos());
+        }
+        {
+            let entry = make_entry();
+            let error = EntryView::<i32>::from_entry(&entry).unwrap_err();
+
+            assert!(matches!(
+                error.downcast_ref::<EntryViewError>(),
+                Some(EntryViewError::MismatchedValueType)
+            ));
+        }
+    }
+
+    #[test]
+    fn from_node() {
+        {
+            let entry = make_entry();
+            let node = Node::new_with_entry(&entry, 0, 1, Rc::new(vec![3, 1, 4]), 5, 9).unwrap();
+            let view = EntryView::<String>::from_node(&node).unwrap();
+
+            assert_eq!(view.value().unwrap(), "瑞穂");
+            assert_eq!(view.cost(), 42);
+        }
+        {
+            let node = Node::bos(Rc::new(Vec::new()));
+            let view = EntryView::<String>::from_node(&node).unwrap();
+
+            assert!(view.is_bos_eos());
+        }
+    }
+
+    #[test]
+    fn key() {
+        let entry = make_entry();
+        let view = EntryView::<String>::from_entry(&entry).unwrap();
+
+        assert!(view.key().is_some());
+    }
+
+    #[test]
+    fn value() {
+        let entry = make_entry();
+        let view = EntryView::<String>::from_entry(&entry).unwrap();
+
+        assert_eq!(view.value().unwrap(), "瑞穂");
+    }
+
+    #[test]
+    fn cost() {
+        let entry = make_entry();
+        let view = EntryView::<String>::from_entry(&entry).unwrap();
+
+        assert_eq!(view.cost(), 42);
+    }
+
+    #[test]
+    fn provenance() {
+        {
+            let entry = make_entry().with_provenance(3);
+            let view = EntryView::<String>::from_entry(&entry).unwrap();
+
+            assert_eq!(view.provenance(), Some(3));
+        }
+        {
+            let entry = make_entry();
+            let view = EntryView::<String>::from_entry(&entry).unwrap();
+
+            assert!(view.provenance().is_none());
+        }
+        {
+            assert!(EntryView::<String>::bos_eos().provenance().is_none());
+        }
+    }
+
+    #[test]
+    fn is_bos_eos() {
+        let entry = make_entry();
+
+        assert!(!EntryView::<String>::from_entry(&entry)
+            .unwrap()
+            .is_bos_eos());
+        assert!(EntryView::<String>::bos_eos().is_bos_eos());
+    }
+}
@@ -5,7 +5,7 @@
  */
 
 use std::any::type_name_of_val;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
 
@@ -16,20 +16,32 @@ use crate::entry::Entry;
 use crate::node::Node;
 use crate::string_input::StringInput;
 use crate::vocabulary::Vocabulary;
+use crate::vocabulary_report::VocabularyReport;
 
 type EntryMap = HashMap<String, Vec<Entry>>;
 
+// The hash value is calculated once, so that the hash function is not called
+// again when the map grows.
 #[derive(Clone)]
 struct HashableEntry<'a> {
     entry: Entry,
-    hash_value: &'a dyn Fn(&Entry) -> u64,
+    hash_value: u64,
     equal: &'a dyn Fn(&Entry, &Entry) -> bool,
 }
 
 impl<'a> HashableEntry<'a> {
-    const fn new(
+    fn new(
         entry: Entry,
-        hash_value: &'a dyn Fn(&Entry) -> u64,
+        hash_value: &dyn Fn(&Entry) -> u64,
+        equal: &'a dyn Fn(&Entry, &Entry) -> bool,
+    ) -> Self {
+        let hash_value = hash_value(&entry);
+        Self::new_with_hash_value(entry, hash_value, equal)
+    }
+
+    const fn new_with_hash_value(
+        entry: Entry,
+        hash_value: u64,
         equal: &'a dyn Fn(&Entry, &Entry) -> bool,
     ) -> Self {
         Self {
@@ -44,7 +56,7 @@ impl Debug for HashableEntry<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("HashableEntry")
             .field("entry", &self.entry)
-            .field("hash_value", &type_name_of_val(&self.hash_value))
+            .field("hash_value", &self.hash_value)
             .field("equal", &type_name_of_val(&self.equal))
             .finish()
     }
@@ -54,8 +66,7 @@ impl Eq for HashableEntry<'_> {}
 
 impl Hash for HashableEntry<'_> {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        let hash_value = (self.hash_value)(&self.entry);
-        hash_value.hash(state);
+        self.hash_value.hash(state);
     }
 }
 
@@ -67,13 +78,51 @@ impl PartialEq for HashableEntry<'_> {
 
 type ConnectionMap<'a> = HashMap<(HashableEntry<'a>, HashableEntry<'a>), i32>;
 
+type ConnectionCost<'a> = &'a dyn Fn(Option<usize>, Option<usize>) -> i32;
+
+// The connections, either materialized as the entry pairs or given by a cost
+// function of the entry indexes.
+#[derive(Clone)]
+enum Connections<'a> {
+    Map(ConnectionMap<'a>),
+    Function {
+        entry_indexes: HashMap<HashableEntry<'a>, usize>,
+        connection_cost: ConnectionCost<'a>,
+    },
+}
+
+impl Debug for Connections<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Connections::Map(connection_map) => f.debug_tuple("Map").field(connection_map).finish(),
+            Connections::Function {
+                entry_indexes,
+                connection_cost,
+            } => f
+                .debug_struct("Function")
+                .field("entry_indexes", entry_indexes)
+                .field("connection_cost", &type_name_of_val(connection_cost))
+                .finish(),
+        }
+    }
+}
+
+/**
+ * A pair of the entries of a connection, each of which is paired with its hash
+ * value.
+ */
+pub type PrehashedConnection = ((Entry, u64), (Entry, u64));
+
 /**
  * A hash map vocabulary.
+ *
+ * The keys are compared with `Input::equal_to`. When the keys are
+ * `StringInput`s, normalize them in the same normalization form as the inputs.
  */
 #[derive(Clone)]
 pub struct HashMapVocabulary<'a> {
     entry_map: EntryMap,
-    connection_map: ConnectionMap<'a>,
+    connections: Connections<'a>,
     entry_hash_value: &'a dyn Fn(&Entry) -> u64,
     entry_equal: &'a dyn Fn(&Entry, &Entry) -> bool,
 }
@@ -82,7 +131,7 @@ impl Debug for HashMapVocabulary<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("HashMapVocabulary")
             .field("entry_map", &self.entry_map)
-            .field("connection_map", &self.connection_map)
+            .field("connections", &self.connections)
             .field(
                 "entry_hash_value",
                 &type_name_of_val(&self.entry_hash_value),
@@ -107,19 +156,247 @@ impl<'a> HashMapVocabulary<'a> {
         connections: Vec<((Entry, Entry), i32)>,
         entry_hash_value: &'a dyn Fn(&Entry) -> u64,
         entry_equal: &'a dyn Fn(&Entry, &Entry) -> bool,
+    ) -> Self {
+        Self::from_iter(entries, connections, entry_hash_value, entry_equal)
+    }
+
+    /**
+     * Creates a hash map vocabulary from iterators.
+     *
+     * The entries and the connections are moved into the maps without being
+     * collected into vectors first.
+     *
+     * # Arguments
+     * * `entries`          - Entries.
+     * * `connections`      - Connections.
+     * * `entry_hash_value` - A hash function for an entry.
+     * * `entry_equal`      - An equality function for entries.
+     */
+    pub fn from_iter(
+        entries: impl IntoIterator<Item = (String, Vec<Entry>)>,
+        connections: impl IntoIterator<Item = ((Entry, Entry), i32)>,
+        entry_hash_value: &'a dyn Fn(&Entry) -> u64,
+        entry_equal: &'a dyn Fn(&Entry, &Entry) -> bool,
+    ) -> Self {
+        Self::from_prehashed_iter(
+            entries,
+            connections.into_iter().map(|((from, to), cost)| {
+                let from_hash_value = entry_hash_value(&from);
+                let to_hash_value = entry_hash_value(&to);
+                (((from, from_hash_value), (to, to_hash_value)), cost)
+            }),
+            entry_hash_value,
+            entry_equal,
+        )
+    }
+
+    /**
+     * Creates a hash map vocabulary from iterators with precomputed hash
+     * values.
+     *
+     * The entries of the connections come with their hash values, such as the
+     * ones already known from a trie lookup, so `entry_hash_value` is not
+     * called for them. It is still used to look up the connections, so the
+     * precomputed hash values must be the same as its return values.
+     *
+     * # Arguments
+     * * `entries`          - Entries.
+     * * `connections`      - Connections, each entry of which is paired with
+     *                        its hash value.
+     * * `entry_hash_value` - A hash function for an entry.
+     * * `entry_equal`      - An equality function for entries.
+     */
+    pub fn from_prehashed_iter(
+        entries: impl IntoIterator<Item = (String, Vec<Entry>)>,
+        connections: impl IntoIterator<Item = (PrehashedConnection, i32)>,
+        entry_hash_value: &'a dyn Fn(&Entry) -> u64,
+        entry_equal: &'a dyn Fn(&Entry, &Entry) -> bool,
     ) -> Self {
         let entry_map = Self::make_entry_map(entries);
-        let connection_map = Self::make_connection_map(connections, entry_hash_value, entry_equal);
+        let connection_map = Self::make_connection_map(connections, entry_equal);
         HashMapVocabulary {
             entry_map,
-            connection_map,
+            connections: Connections::Map(connection_map),
             entry_hash_value,
             entry_equal,
         }
     }
 
-    fn make_entry_map(entries: Vec<(String, Vec<Entry>)>) -> EntryMap {
-        let mut entry_map = EntryMap::new();
+    /**
+     * Creates a hash map vocabulary with a connection cost function.
+     *
+     * The connections are not materialized as pairs of entries. The entries
+     * are indexed from 0 in the order of `entries` and of the entries for
+     * each key, and the connection cost is obtained by calling
+     * `connection_cost` with the indexes of the origin and the destination,
+     * or None for BOS/EOS. So a table of the costs keyed by the entry indexes
+     * can be looked up without duplicating the entries for each connection.
+     *
+     * An entry equal to a preceding one has the index of the preceding one.
+     * The connection cost is `i32::MAX` when the origin or the destination is
+     * not in the entries.
+     *
+     * # Arguments
+     * * `entries`          - Entries.
+     * * `connection_cost`  - A connection cost function of the entry indexes.
+     * * `entry_hash_value` - A hash function for an entry.
+     * * `entry_equal`      - An equality function for entries.
+     */
+    pub fn new_with_connection_cost(
+        entries: Vec<(String, Vec<Entry>)>,
+        connection_cost: ConnectionCost<'a>,
+        entry_hash_value: &'a dyn Fn(&Entry) -> u64,
+        entry_equal: &'a dyn Fn(&Entry, &Entry) -> bool,
+    ) -> Self {
+        let mut entry_indexes = HashMap::new();
+        for (index, entry) in entries.iter().flat_map(|(_, entries)| entries).enumerate() {
+            let _index = entry_indexes
+                .entry(HashableEntry::new(
+                    entry.clone(),
+                    entry_hash_value,
+                    entry_equal,
+                ))
+                .or_insert(index);
+        }
+        HashMapVocabulary {
+            entry_map: Self::make_entry_map(entries),
+            connections: Connections::Function {
+                entry_indexes,
+                connection_cost,
+            },
+            entry_hash_value,
+            entry_equal,
+        }
+    }
+
+    /**
+     * Returns the index of an entry.
+     *
+     * # Arguments
+     * * `entry` - An entry.
+     *
+     * # Returns
+     * The index of the entry passed to the connection cost function. Or None
+     * when the entry is BOS/EOS or not in the entries, or when this vocabulary
+     * is not created with a connection cost function.
+     */
+    pub fn entry_index(&self, entry: &Entry) -> Option<usize> {
+        let Connections::Function { entry_indexes, .. } = &self.connections else {
+            return None;
+        };
+        if matches!(entry, Entry::BosEos) {
+            return None;
+        }
+        entry_indexes
+            .get(&HashableEntry::new(
+                entry.clone(),
+                self.entry_hash_value,
+                self.entry_equal,
+            ))
+            .copied()
+    }
+
+    /**
+     * Validates this vocabulary.
+     *
+     * The entries in the report are in the order of their keys. With a
+     * connection cost function, the function is called for all the pairs of
+     * the entries.
+     *
+     * # Returns
+     * A vocabulary report.
+     */
+    pub fn validate(&self) -> VocabularyReport {
+        let hashable = |entry: &Entry| {
+            HashableEntry::new(entry.clone(), self.entry_hash_value, self.entry_equal)
+        };
+        let bos_eos = hashable(&Entry::BosEos);
+
+        let mut keys = self.entry_map.keys().collect::<Vec<_>>();
+        keys.sort();
+        let mut entries = Vec::new();
+        let mut known_entries = HashSet::from([bos_eos.clone()]);
+        let mut duplicate_entries = Vec::new();
+        for key in keys {
+            for entry in &self.entry_map[key] {
+                if known_entries.insert(hashable(entry)) {
+                    entries.push(entry);
+                } else {
+                    duplicate_entries.push(entry.clone());
+                }
+            }
+        }
+
+        let mut froms = HashSet::new();
+        let mut tos = HashSet::new();
+        let mut bos_tos = HashSet::new();
+        let mut eos_froms = HashSet::new();
+        let mut dangling_connection_count = 0;
+        let connected_pairs = match &self.connections {
+            Connections::Map(connection_map) => connection_map
+                .keys()
+                .map(|(from, to)| (from, to))
+                .collect::<Vec<_>>(),
+            Connections::Function {
+                entry_indexes,
+                connection_cost,
+            } => {
+                let indexed_entries = [(&bos_eos, None)]
+                    .into_iter()
+                    .chain(
+                        entry_indexes
+                            .iter()
+                            .map(|(entry, &index)| (entry, Some(index))),
+                    )
+                    .collect::<Vec<_>>();
+                let mut connected_pairs = Vec::new();
+                for &(from, from_index) in &indexed_entries {
+                    for &(to, to_index) in &indexed_entries {
+                        if connection_cost(from_index, to_index) != i32::MAX {
+                            connected_pairs.push((from, to));
+                        }
+                    }
+                }
+                connected_pairs
+            }
+        };
+        for (from, to) in connected_pairs {
+            if !known_entries.contains(from) || !known_entries.contains(to) {
+                dangling_connection_count += 1;
+                continue;
+            }
+            if *from == bos_eos {
+                let _inserted = bos_tos.insert(to);
+            } else {
+                let _inserted = froms.insert(from);
+            }
+            if *to == bos_eos {
+                let _inserted = eos_froms.insert(from);
+            } else {
+                let _inserted = tos.insert(to);
+            }
+        }
+
+        let select = |predicate: &dyn Fn(&HashableEntry<'_>) -> bool| {
+            entries
+                .iter()
+                .filter(|&&entry| predicate(&hashable(entry)))
+                .map(|&entry| entry.clone())
+                .collect::<Vec<_>>()
+        };
+        VocabularyReport::new(
+            select(&|entry| !bos_tos.contains(entry)),
+            select(&|entry| !eos_froms.contains(entry)),
+            select(&|entry| !bos_tos.contains(entry) && !tos.contains(entry)),
+            select(&|entry| !eos_froms.contains(entry) && !froms.contains(entry)),
+            duplicate_entries,
+            dangling_connection_count,
+        )
+    }
+
+    fn make_entry_map(entries: impl IntoIterator<Item = (String, Vec<Entry>)>) -> EntryMap {
+        let entries = entries.into_iter();
+        let mut entry_map = EntryMap::with_capacity(entries.size_hint().0);
         for (key, entries) in entries {
             let _prev_value = entry_map.insert(key, entries);
         }
@@ -127,14 +404,14 @@ impl<'a> HashMapVocabulary<'a> {
     }
 
     fn make_connection_map(
-        connections: Vec<((Entry, Entry), i32)>,
-        entry_hash_value: &'a dyn Fn(&Entry) -> u64,
+        connections: impl IntoIterator<Item = (PrehashedConnection, i32)>,
         entry_equal: &'a dyn Fn(&Entry, &Entry) -> bool,
     ) -> ConnectionMap<'a> {
-        let mut connection_map = ConnectionMap::new();
-        for ((from, to), cost) in connections {
-            let from = HashableEntry::new(from, entry_hash_value, entry_equal);
-            let to = HashableEntry::new(to, entry_hash_value, entry_equal);
+        let connections = connections.into_iter();
+        let mut connection_map = ConnectionMap::with_capacity(connections.size_hint().0);
+        for (((from, from_hash_value), (to, to_hash_value)), cost) in connections {
+            let from = HashableEntry::new_with_hash_value(from, from_hash_value, entry_equal);
+            let to = HashableEntry::new_with_hash_value(to, to_hash_value, entry_equal);
             let _prev_value = connection_map.insert((from, to), cost);
         }
         connection_map
@@ -154,27 +431,31 @@ impl Vocabulary for HashMapVocabulary<'_> {
     }
 
     fn find_connection(&self, from: &Node, to: &Entry) -> Result<Connection> {
-        let from_entry = match from {
-            Node::Middle(_) => {
-                let Some(from_key) = from.key_rc() else {
+        match &self.connections {
+            Connections::Map(connection_map) => {
+                let key = (
+                    HashableEntry::new(Entry::from(from), self.entry_hash_value, self.entry_equal),
+                    HashableEntry::new(to.clone(), self.entry_hash_value, self.entry_equal),
+                );
+                let Some(found) = connection_map.get(&key) else {
                     return Ok(Connection::new(i32::MAX));
                 };
-                let Some(from_value) = from.value_rc() else {
+                Ok(Connection::new(*found))
+            }
+            Connections::Function {
+                connection_cost, ..
+            } => {
+                let from = Entry::from(from);
+                let index_of = |entry: &Entry| match entry {
+                    Entry::BosEos => Some(None),
+                    Entry::Middle(_) => self.entry_index(entry).map(Some),
+                };
+                let (Some(from_index), Some(to_index)) = (index_of(&from), index_of(to)) else {
                     return Ok(Connection::new(i32::MAX));
                 };
-                Entry::new(from_key, from_value, from.node_cost())
+                Ok(Connection::new(connection_cost(from_index, to_index)))
             }
-            Node::Bos(_) => Entry::BosEos,
-            Node::Eos(_) => Entry::BosEos,
-        };
-        let key = (
-            HashableEntry::new(from_entry, self.entry_hash_value, self.entry_equal),
-            HashableEntry::new(to.clone(), self.entry_hash_value, self.entry_equal),
-        );
-        let Some(found) = self.connection_map.get(&key) else {
-            return Ok(Connection::new(i32::MAX));
-        };
-        Ok(Connection::new(*found))
+        }
     }
 }
 
@@ -269,6 +550,269 @@ mod tests {
         }
     }
 
+    fn mizuho() -> Entry {
+        Entry::new(
+            Rc::new(StringInput::new(String::from("みずほ"))),
+            Rc::new(String::from("瑞穂")),
+            42,
+        )
+    }
+
+    fn sakura() -> Entry {
+        Entry::new(
+            Rc::new(StringInput::new(String::from("さくら"))),
+            Rc::new(String::from("桜")),
+            24,
+        )
+    }
+
+    #[test]
+    fn from_iter() {
+        let entries = [
+            (String::from("みずほ"), vec![mizuho()]),
+            (String::from("さくら"), vec![sakura()]),
+        ];
+        let connections = [((mizuho(), sakura()), 4242)];
+        let vocaburary =
+            HashMapVocabulary::from_iter(entries, connections, &entry_hash_value, &entry_equal);
+
+        let entries_mizuho = vocaburary
+            .find_entries(&StringInput::new(String::from("みずほ")))
+            .unwrap();
+        assert_eq!(entries_mizuho.len(), 1);
+        let connection = vocaburary
+            .find_connection(&make_node(&entries_mizuho[0]), &sakura())
+            .unwrap();
+        assert_eq!(connection.cost(), 4242);
+    }
+
+    #[test]
+    fn from_prehashed_iter() {
+        let hash_value_call_count = std::cell::Cell::new(0);
+        let counting_entry_hash_value = |entry: &Entry| {
+            hash_value_call_count.set(hash_value_call_count.get() + 1);
+            entry_hash_value(entry)
+        };
+        let connections = [(
+            (
+                (mizuho(), entry_hash_value(&mizuho())),
+                (sakura(), entry_hash_value(&sakura())),
+            ),
+            4242,
+        )];
+        let vocaburary = HashMapVocabulary::from_prehashed_iter(
+            [(String::from("みずほ"), vec![mizuho()])],
+            connections,
+            &counting_entry_hash_value,
+            &entry_equal,
+        );
+        assert_eq!(hash_value_call_count.get(), 0);
+
+        let connection = vocaburary
+            .find_connection(&make_node(&mizuho()), &sakura())
+            .unwrap();
+        assert_eq!(connection.cost(), 4242);
+        assert_eq!(hash_value_call_count.get(), 2);
+    }
+
+    #[test]
+    fn new_with_connection_cost() {
+        let entries = vec![
+            (String::from("みずほ"), vec![mizuho()]),
+            (String::from("さくら"), vec![sakura()]),
+        ];
+        let costs = [[i32::MAX, 4242], [2424, i32::MAX]];
+        let connection_cost = |from: Option<usize>, to: Option<usize>| match (from, to) {
+            (Some(from), Some(to)) => costs[from][to],
+            (None, None) => 999,
+            _ => 0,
+        };
+        let vocaburary = HashMapVocabulary::new_with_connection_cost(
+            entries,
+            &connection_cost,
+            &entry_hash_value,
+            &entry_equal,
+        );
+
+        let entries_mizuho = vocaburary
+            .find_entries(&StringInput::new(String::from("みずほ")))
+            .unwrap();
+        assert_eq!(entries_mizuho.len(), 1);
+        {
+            let connection = vocaburary
+                .find_connection(&make_node(&entries_mizuho[0]), &sakura())
+                .unwrap();
+            assert_eq!(connection.cost(), 4242);
+        }
+        {
+            let connection = vocaburary
+                .find_connection(&make_node(&sakura()), &mizuho())
+                .unwrap();
+            assert_eq!(connection.cost(), 2424);
+        }
+        {
+            let connection = vocaburary
+                .find_connection(&make_node(&mizuho()), &mizuho())
+                .unwrap();
+            assert_eq!(connection.cost(), i32::MAX);
+        }
+        {
+            let connection = vocaburary
+                .find_connection(&Node::bos(Rc::new(Vec::new())), &Entry::BosEos)
+                .unwrap();
+            assert_eq!(connection.cost(), 999);
+        }
+        {
+            let connection = vocaburary
+                .find_connection(&Node::bos(Rc::new(Vec::new())), &mizuho())
+                .unwrap();
+            assert_eq!(connection.cost(), 0);
+        }
+        {
+            let hikari = Entry::new(
+                Rc::new(StringInput::new(String::from("ひかり"))),
+                Rc::new(String::from("光")),
+                10,
+            );
+            let connection = vocaburary
+                .find_connection(&make_node(&mizuho()), &hikari)
+                .unwrap();
+            assert_eq!(connection.cost(), i32::MAX);
+        }
+    }
+
+    #[test]
+    fn entry_index() {
+        {
+            let connection_cost = |_: Option<usize>, _: Option<usize>| 0;
+            let vocaburary = HashMapVocabulary::new_with_connection_cost(
+                vec![
+                    (String::from("みずほ"), vec![mizuho()]),
+                    (String::from("さくら"), vec![sakura(), sakura()]),
+                ],
+                &connection_cost,
+                &entry_hash_value,
+                &entry_equal,
+            );
+
+            assert_eq!(vocaburary.entry_index(&mizuho()), Some(0));
+            assert_eq!(vocaburary.entry_index(&sakura()), Some(1));
+            assert!(vocaburary.entry_index(&Entry::BosEos).is_none());
+        }
+        {
+            let vocaburary = HashMapVocabulary::new(
+                vec![(String::from("みずほ"), vec![mizuho()])],
+                Vec::new(),
+                &entry_hash_value,
+                &entry_equal,
+            );
+
+            assert!(vocaburary.entry_index(&mizuho()).is_none());
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let entry = |key: &str, value: &str| {
+            Entry::new(
+                Rc::new(StringInput::new(String::from(key))),
+                Rc::new(String::from(value)),
+                0,
+            )
+        };
+        let keys = |entries: &[Entry]| {
+            entries
+                .iter()
+                .map(|entry| {
+                    entry
+                        .key()
+                        .unwrap()
+                        .downcast_ref::<StringInput>()
+                        .unwrap()
+                        .value()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+        };
+        {
+            let vocabulary =
+                HashMapVocabulary::new(Vec::new(), Vec::new(), &entry_hash_value, &entry_equal);
+
+            assert!(vocabulary.validate().is_clean());
+        }
+        {
+            let entries = vec![
+                (String::from("d"), vec![entry("d", "D")]),
+                (String::from("a"), vec![entry("a", "A"), entry("a", "A2")]),
+                (String::from("b"), vec![entry("b", "B")]),
+                (String::from("c"), vec![entry("c", "C")]),
+            ];
+            let connections = vec![
+                ((Entry::BosEos, entry("a", "A")), 1),
+                ((entry("a", "A"), entry("b", "B")), 2),
+                ((entry("b", "B"), Entry::BosEos), 3),
+                ((entry("c", "C"), entry("b", "B")), 4),
+                ((Entry::BosEos, entry("d", "D")), 5),
+                ((entry("a", "A"), entry("x", "X")), 6),
+            ];
+            let vocabulary =
+                HashMapVocabulary::new(entries, connections, &entry_hash_value, &entry_equal);
+
+            let report = vocabulary.validate();
+            assert_eq!(keys(report.entries_without_bos_connection()), ["b", "c"]);
+            assert_eq!(
+                keys(report.entries_without_eos_connection()),
+                ["a", "c", "d"]
+            );
+            assert_eq!(keys(report.unreachable_entries()), ["c"]);
+            assert_eq!(keys(report.dead_end_entries()), ["d"]);
+            assert_eq!(keys(report.duplicate_entries()), ["a"]);
+            assert_eq!(
+                report.duplicate_entries()[0]
+                    .value()
+                    .unwrap()
+                    .downcast_ref::<String>()
+                    .unwrap(),
+                "A2"
+            );
+            assert_eq!(report.dangling_connection_count(), 1);
+            assert!(!report.is_clean());
+        }
+        {
+            let entries = vec![
+                (String::from("d"), vec![entry("d", "D")]),
+                (String::from("a"), vec![entry("a", "A"), entry("a", "A2")]),
+                (String::from("b"), vec![entry("b", "B")]),
+                (String::from("c"), vec![entry("c", "C")]),
+            ];
+            let connection_cost = |from: Option<usize>, to: Option<usize>| match (from, to) {
+                (None, Some(1)) => 1,
+                (Some(1), Some(3)) => 2,
+                (Some(3), None) => 3,
+                (Some(4), Some(3)) => 4,
+                (None, Some(0)) => 5,
+                _ => i32::MAX,
+            };
+            let vocabulary = HashMapVocabulary::new_with_connection_cost(
+                entries,
+                &connection_cost,
+                &entry_hash_value,
+                &entry_equal,
+            );
+
+            let report = vocabulary.validate();
+            assert_eq!(keys(report.entries_without_bos_connection()), ["b", "c"]);
+            assert_eq!(
+                keys(report.entries_without_eos_connection()),
+                ["a", "c", "d"]
+            );
+            assert_eq!(keys(report.unreachable_entries()), ["c"]);
+            assert_eq!(keys(report.dead_end_entries()), ["d"]);
+            assert_eq!(keys(report.duplicate_entries()), ["a"]);
+            assert_eq!(report.dangling_connection_count(), 0);
+        }
+    }
+
     #[test]
     fn find_entries() {
         {
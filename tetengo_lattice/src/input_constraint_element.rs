@@ -0,0 +1,81 @@
+/*!
+ * An input constraint element.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use crate::constraint_element::ConstraintElement;
+use crate::input::Input;
+use crate::node::Node;
+
+/**
+ * An input constraint element.
+ *
+ * Matches any node whose key is equal to the expected input, regardless of
+ * its value. So a region can be constrained to be segmented as the input
+ * without a concrete node.
+ */
+#[derive(Debug)]
+pub struct InputConstraintElement {
+    expected: Box<dyn Input>,
+}
+
+impl InputConstraintElement {
+    /**
+     * Creates an input constraint element.
+     *
+     * # Arguments
+     * * `expected` - An expected input.
+     */
+    pub fn new(expected: Box<dyn Input>) -> Self {
+        Self { expected }
+    }
+}
+
+impl ConstraintElement for InputConstraintElement {
+    fn matches(&self, node: &Node) -> i32 {
+        match node.key() {
+            Some(key) if key.equal_to(self.expected.as_ref()) => 0,
+            _ => -1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::string_input::StringInput;
+
+    use super::*;
+
+    fn make_node(key: &str, value: i32) -> Node {
+        Node::new(
+            Rc::new(StringInput::new(String::from(key))),
+            Rc::new(value),
+            0,
+            1,
+            Rc::new(vec![3, 1, 4, 1, 5, 9, 2, 6]),
+            5,
+            24,
+            2424,
+        )
+    }
+
+    #[test]
+    fn new() {
+        let _element =
+            InputConstraintElement::new(Box::new(StringInput::new(String::from("mizuho"))));
+    }
+
+    #[test]
+    fn matches() {
+        let element =
+            InputConstraintElement::new(Box::new(StringInput::new(String::from("mizuho"))));
+
+        assert_eq!(element.matches(&make_node("mizuho", 42)), 0);
+        assert_eq!(element.matches(&make_node("mizuho", 24)), 0);
+        assert!(element.matches(&make_node("sakura", 42)) < 0);
+        assert!(element.matches(&Node::bos(Rc::new(Vec::new()))) < 0);
+    }
+}
@@ -4,14 +4,33 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
+use std::any::{type_name_of_val, Any};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::ptr;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Result;
+#[cfg(feature = "json")]
+use serde_json::json;
 
-use crate::entry::Entry;
+#[cfg(feature = "json")]
+use crate::constraint::Constraint;
+use crate::cost_aggregation::CostAggregation;
+use crate::entry::{Entry, EntryValue};
 use crate::input::Input;
-use crate::node::Node;
+use crate::lattice_limits::LatticeLimits;
+use crate::lattice_stats::LatticeStats;
+#[cfg(feature = "json")]
+use crate::n_best_iterator::NBestIterator;
+use crate::node::{Node, NodeError};
+use crate::node_id::NodeId;
+use crate::path::Path;
+use crate::step_delta::StepDelta;
 use crate::vocabulary::Vocabulary;
 
 /**
@@ -36,6 +55,63 @@ pub enum LatticeError {
      */
     #[error("No input.")]
     NoInput,
+
+    /**
+     * No initial node.
+     */
+    #[error("No initial node.")]
+    NoInitialNode,
+
+    /**
+     * Too many consecutive epsilon steps.
+     */
+    #[error("Too many consecutive epsilon steps.")]
+    TooManyEpsilonSteps,
+
+    /**
+     * The temperature is not a positive finite number.
+     */
+    #[error("The temperature is not a positive finite number.")]
+    InvalidTemperature,
+
+    /**
+     * No path reaches the EOS node.
+     */
+    #[error("No path reaches the EOS node.")]
+    NoPath,
+
+    /**
+     * The step count exceeds the limit.
+     */
+    #[error("The step count exceeds the limit {max_step_count}.")]
+    TooManySteps {
+        /// The max step count.
+        max_step_count: usize,
+    },
+
+    /**
+     * The node count of a step exceeds the limit.
+     */
+    #[error("The node count of a step exceeds the limit {max_step_node_count}.")]
+    TooManyNodesInStep {
+        /// The max node count of a step.
+        max_step_node_count: usize,
+    },
+
+    /**
+     * The total node count exceeds the limit.
+     */
+    #[error("The total node count exceeds the limit {max_node_count}.")]
+    TooManyNodes {
+        /// The max total node count.
+        max_node_count: usize,
+    },
+
+    /**
+     * The deadline is exceeded.
+     */
+    #[error("The deadline is exceeded.")]
+    DeadlineExceeded,
 }
 
 #[derive(Debug)]
@@ -58,33 +134,352 @@ impl GraphStep {
     }
 }
 
+#[derive(Clone, Debug)]
+struct EntryIdentity {
+    key_value: Option<(Rc<dyn Input>, EntryValue)>,
+    cost: i32,
+}
+
+impl EntryIdentity {
+    fn of_node(node: &Node) -> Self {
+        Self {
+            key_value: node.key_rc().zip(node.entry_value()),
+            cost: node.node_cost(),
+        }
+    }
+
+    fn of_entry(entry: &Entry) -> Self {
+        Self {
+            key_value: entry.key_rc().zip(entry.entry_value()),
+            cost: entry.cost(),
+        }
+    }
+}
+
+impl Eq for EntryIdentity {}
+
+impl PartialEq for EntryIdentity {
+    fn eq(&self, other: &Self) -> bool {
+        let key_value_eq = match (&self.key_value, &other.key_value) {
+            (Some((key, value)), Some((other_key, other_value))) => {
+                ptr::addr_eq(Rc::as_ptr(key), Rc::as_ptr(other_key)) && value == other_value
+            }
+            (None, None) => true,
+            _ => false,
+        };
+        key_value_eq && self.cost == other.cost
+    }
+}
+
+impl Hash for EntryIdentity {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        if let Some((key, value)) = &self.key_value {
+            Rc::as_ptr(key).cast::<()>().hash(state);
+            value.hash(state);
+        }
+        self.cost.hash(state);
+    }
+}
+
+type ConnectionCacheKey = (EntryIdentity, EntryIdentity);
+
+#[derive(Debug)]
+struct ConnectionCache {
+    capacity: usize,
+    map: HashMap<ConnectionCacheKey, i32>,
+    insertion_order: VecDeque<ConnectionCacheKey>,
+    hit_count: usize,
+    miss_count: usize,
+}
+
+impl ConnectionCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            map: HashMap::new(),
+            insertion_order: VecDeque::new(),
+            hit_count: 0,
+            miss_count: 0,
+        }
+    }
+
+    fn find(&mut self, key: &ConnectionCacheKey) -> Option<i32> {
+        let found = self.map.get(key).copied();
+        if found.is_some() {
+            self.hit_count += 1;
+        } else {
+            self.miss_count += 1;
+        }
+        found
+    }
+
+    fn insert(&mut self, key: ConnectionCacheKey, cost: i32) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.map.len() >= self.capacity {
+            let Some(oldest) = self.insertion_order.pop_front() else {
+                break;
+            };
+            let _removed = self.map.remove(&oldest);
+        }
+        if self.map.insert(key.clone(), cost).is_none() {
+            self.insertion_order.push_back(key);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.insertion_order.clear();
+    }
+}
+
+#[derive(Debug)]
+enum LatticeVocabulary<'a> {
+    Borrowed(&'a dyn Vocabulary),
+    Owned(Arc<dyn Vocabulary + Send + Sync>),
+}
+
+impl LatticeVocabulary<'_> {
+    fn get(&self) -> &dyn Vocabulary {
+        match self {
+            LatticeVocabulary::Borrowed(vocabulary) => *vocabulary,
+            LatticeVocabulary::Owned(vocabulary) => vocabulary.as_ref(),
+        }
+    }
+}
+
+type ValueFormatter<'a> = &'a dyn Fn(&dyn Any) -> String;
+
+type NodeCostAdjuster<'a> = &'a dyn Fn(&Entry) -> i32;
+
+type HigherOrderScorer<'a> = &'a dyn Fn(&[&Node], &Entry) -> i32;
+
 /**
  * A lattice.
  */
-#[derive(Debug)]
 pub struct Lattice<'a> {
-    vocabulary: &'a dyn Vocabulary,
+    vocabulary: LatticeVocabulary<'a>,
     input: Option<Box<dyn Input>>,
     graph: Vec<GraphStep>,
+    origin_step: usize,
+    connection_cache: RefCell<ConnectionCache>,
+    evaluated_edge_count: Cell<usize>,
+    unreachable_edge_count: Cell<usize>,
+    consecutive_epsilon_step_count: usize,
+    node_count: usize,
+    limits: LatticeLimits,
+    cost_aggregation: CostAggregation,
+    deadline: Option<Instant>,
+    value_formatter: Option<ValueFormatter<'a>>,
+    node_cost_adjuster: Option<NodeCostAdjuster<'a>>,
+    higher_order_scorer: Option<(usize, HigherOrderScorer<'a>)>,
+}
+
+impl Debug for Lattice<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Lattice")
+            .field("vocabulary", &self.vocabulary)
+            .field("input", &self.input)
+            .field("graph", &self.graph)
+            .field("origin_step", &self.origin_step)
+            .field("connection_cache", &self.connection_cache)
+            .field("evaluated_edge_count", &self.evaluated_edge_count)
+            .field("unreachable_edge_count", &self.unreachable_edge_count)
+            .field(
+                "consecutive_epsilon_step_count",
+                &self.consecutive_epsilon_step_count,
+            )
+            .field("node_count", &self.node_count)
+            .field("limits", &self.limits)
+            .field("cost_aggregation", &self.cost_aggregation)
+            .field("deadline", &self.deadline)
+            .field("value_formatter", &type_name_of_val(&self.value_formatter))
+            .field(
+                "node_cost_adjuster",
+                &type_name_of_val(&self.node_cost_adjuster),
+            )
+            .field(
+                "higher_order_scorer",
+                &type_name_of_val(&self.higher_order_scorer),
+            )
+            .finish()
+    }
 }
 
 impl<'a> Lattice<'a> {
+    /// A default connection cache capacity, which disables the cache.
+    pub const DEFAULT_CONNECTION_CACHE_CAPACITY: usize = 0;
+
+    /// The max count of consecutive epsilon steps.
+    pub const MAX_CONSECUTIVE_EPSILON_STEP_COUNT: usize = 8;
+
     /**
      * Creates a lattice.
      *
+     * The connection cache is disabled. Use
+     * `new_with_connection_cache_capacity()` to enable it.
+     *
      * # Arguments
      * * `vocabulary` - A vocabulary.
      */
     pub fn new(vocabulary: &'a dyn Vocabulary) -> Self {
+        Self::new_with_connection_cache_capacity(
+            vocabulary,
+            Self::DEFAULT_CONNECTION_CACHE_CAPACITY,
+        )
+    }
+
+    /**
+     * Creates a lattice with a connection cache capacity.
+     *
+     * The lattice caches the connections found by the vocabulary, keyed by
+     * the pointer identities of the shared keys and values of the origin node
+     * and the destination entry, and their costs. The other fields of the
+     * origin node, such as the preceding step and the path cost, are not
+     * part of the key.
+     *
+     * So the vocabulary must return the same connection for the same pair of
+     * them. When the vocabulary is modified between the steps, call
+     * `recost()`, which also clears the cache. Specify 0 as the capacity to
+     * disable the cache, as `new()` does.
+     *
+     * # Arguments
+     * * `vocabulary`                - A vocabulary.
+     * * `connection_cache_capacity` - A connection cache capacity.
+     */
+    pub fn new_with_connection_cache_capacity(
+        vocabulary: &'a dyn Vocabulary,
+        connection_cache_capacity: usize,
+    ) -> Self {
+        Self::new_with_lattice_vocabulary(
+            LatticeVocabulary::Borrowed(vocabulary),
+            connection_cache_capacity,
+        )
+    }
+
+    /**
+     * Creates a lattice owning a shared vocabulary.
+     *
+     * The lattice does not borrow the vocabulary, so it can be stored in a
+     * long-lived struct. The vocabulary can be shared among the lattices built
+     * on worker threads.
+     *
+     * # Arguments
+     * * `vocabulary` - A vocabulary.
+     */
+    pub fn new_owned(vocabulary: Arc<dyn Vocabulary + Send + Sync>) -> Self {
+        Self::new_with_lattice_vocabulary(
+            LatticeVocabulary::Owned(vocabulary),
+            Self::DEFAULT_CONNECTION_CACHE_CAPACITY,
+        )
+    }
+
+    /**
+     * Creates a lattice with a cost aggregation.
+     *
+     * The graph is built in the same way as the other constructors. Only the
+     * path costs of the nodes are aggregated with the cost aggregation.
+     *
+     * With `CostAggregation::LogSum`, the path cost of the EOS node is the
+     * total cost of all the paths, which is for the marginalization. The
+     * costs of the paths enumerated by an `NBestIterator` are not exact, and
+     * the paths may not be in the ascending order of the costs.
+     *
+     * # Arguments
+     * * `vocabulary`       - A vocabulary.
+     * * `cost_aggregation` - A cost aggregation.
+     *
+     * # Errors
+     * * When the temperature of `CostAggregation::LogSum` is not a positive
+     *   finite number.
+     */
+    pub fn new_with_cost_aggregation(
+        vocabulary: &'a dyn Vocabulary,
+        cost_aggregation: CostAggregation,
+    ) -> Result<Self> {
+        if let CostAggregation::LogSum { temperature } = cost_aggregation {
+            if !temperature.is_finite() || temperature <= 0.0 {
+                return Err(LatticeError::InvalidTemperature.into());
+            }
+        }
+
+        let mut self_ = Self::new(vocabulary);
+        self_.cost_aggregation = cost_aggregation;
+        Ok(self_)
+    }
+
+    fn new_with_lattice_vocabulary(
+        vocabulary: LatticeVocabulary<'a>,
+        connection_cache_capacity: usize,
+    ) -> Self {
         let mut self_ = Self {
             vocabulary,
             input: None,
             graph: Vec::new(),
+            origin_step: 0,
+            connection_cache: RefCell::new(ConnectionCache::new(connection_cache_capacity)),
+            evaluated_edge_count: Cell::new(0),
+            unreachable_edge_count: Cell::new(0),
+            consecutive_epsilon_step_count: 0,
+            node_count: 0,
+            limits: LatticeLimits::default(),
+            cost_aggregation: CostAggregation::default(),
+            deadline: None,
+            value_formatter: None,
+            node_cost_adjuster: None,
+            higher_order_scorer: None,
         };
-        self_.graph.push(Self::bos_step());
+        self_.push_step(Self::bos_step());
         self_
     }
 
+    /**
+     * Creates a lattice with initial nodes.
+     *
+     * The initial nodes are the hypotheses carried over from a preceding
+     * analysis, such as the last nodes of the N-best paths of the preceding
+     * sentence fragment. They make up the step 1, which follows the BOS and
+     * spans no input. The inputs pushed back are connected only to them, not to
+     * the BOS.
+     *
+     * The preceding edge cost of an initial node is its initial cost. So its
+     * path cost is the sum of the initial cost and the cost of the entry.
+     *
+     * # Arguments
+     * * `vocabulary`    - A vocabulary.
+     * * `initial_nodes` - Pairs of an entry and its initial cost.
+     *
+     * # Errors
+     * * When `initial_nodes` is empty.
+     * * When `initial_nodes` contains the BOS/EOS entry.
+     */
+    pub fn new_with_initial_nodes(
+        vocabulary: &'a dyn Vocabulary,
+        initial_nodes: Vec<(Entry, i32)>,
+    ) -> Result<Self> {
+        if initial_nodes.is_empty() {
+            return Err(LatticeError::NoInitialNode.into());
+        }
+
+        let mut self_ = Self::new(vocabulary);
+        let mut nodes = Vec::with_capacity(initial_nodes.len());
+        for (entry, initial_cost) in &initial_nodes {
+            let new_node = Node::new_with_entry(
+                entry,
+                nodes.len(),
+                0,
+                Rc::new(vec![*initial_cost]),
+                0,
+                Self::add_cost(*initial_cost, entry.cost()),
+            )?;
+            nodes.push(new_node);
+        }
+        self_.push_step(GraphStep::new(0, nodes));
+        self_.origin_step = 1;
+        Ok(self_)
+    }
+
     fn bos_step() -> GraphStep {
         let nodes = vec![Node::bos(Rc::new(Vec::new()))];
         GraphStep::new(0, nodes)
@@ -121,541 +516,2595 @@ impl<'a> Lattice<'a> {
     }
 
     /**
-     * Pushes back an input.
+     * Returns the nodes at the last step.
      *
-     * # Arguments
-     * * `input` - An input.
+     * # Returns
+     * The nodes at the last step.
+     */
+    pub fn last_step_nodes(&self) -> &[Node] {
+        match self.graph.last() {
+            Some(step) => step.nodes.as_slice(),
+            None => unreachable!("graph must not be empty."),
+        }
+    }
+
+    /**
+     * Returns the connection cache hit count.
      *
-     * # Errors
-     * * When no node is found for the input.
+     * # Returns
+     * The number of the connections found in the cache.
      */
-    pub fn push_back(&mut self, input: Box<dyn Input>) -> Result<()> {
-        if let Some(self_input) = &mut self.input {
-            self_input.append(input)?;
-        } else {
-            self.input = Some(input);
-        };
-        let self_input = match &self.input {
-            Some(self_input) => self_input,
-            None => unreachable!(),
-        };
+    pub fn connection_cache_hit_count(&self) -> usize {
+        self.connection_cache.borrow().hit_count
+    }
 
-        let mut nodes = Vec::new();
-        let mut node_preceding_edge_costs = Vec::new();
-        for i in 0..self.graph.len() {
-            let step = &self.graph[i];
+    /**
+     * Returns the connection cache miss count.
+     *
+     * # Returns
+     * The number of the connections not found in the cache.
+     */
+    pub fn connection_cache_miss_count(&self) -> usize {
+        self.connection_cache.borrow().miss_count
+    }
 
-            let node_key = match self_input
-                .create_subrange(step.input_tail(), self_input.length() - step.input_tail())
-            {
-                Ok(node_key) => node_key,
-                Err(e) => return Err(e),
-            };
-            let found = self.vocabulary.find_entries(node_key.as_ref())?;
-
-            let mut preceding_edge_cost_indexes = Vec::new();
-            for e in &found {
-                let preceding_edge_costs = self.preceding_edge_costs(step, e)?;
-                preceding_edge_cost_indexes.push(node_preceding_edge_costs.len());
-                node_preceding_edge_costs.push(preceding_edge_costs);
-            }
-
-            for j in 0..found.len() {
-                let entry = &found[j];
-                let preceding_edge_costs =
-                    &node_preceding_edge_costs[preceding_edge_cost_indexes[j]];
-                let best_preceding_node_index_ =
-                    Self::best_preceding_node_index(step, preceding_edge_costs.as_slice());
-                let best_preceding_path_cost = Self::add_cost(
-                    step.nodes[best_preceding_node_index_].path_cost(),
-                    preceding_edge_costs[best_preceding_node_index_],
-                );
-                let new_node = match Node::new_with_entry(
-                    entry,
-                    nodes.len(),
-                    i,
-                    preceding_edge_costs.clone(),
-                    best_preceding_node_index_,
-                    Self::add_cost(best_preceding_path_cost, entry.cost()),
-                ) {
-                    Ok(new_node) => new_node,
-                    Err(e) => return Err(e),
-                };
-                nodes.push(new_node);
+    /**
+     * Returns the statistics.
+     *
+     * # Returns
+     * The statistics of the graph and the edges evaluated so far.
+     */
+    pub fn stats(&self) -> LatticeStats {
+        let step_node_counts = self.graph.iter().map(|step| step.nodes().len()).collect();
+        let mut edge_cost_distribution = BTreeMap::new();
+        for node in self.graph.iter().flat_map(|step| step.nodes()) {
+            for &cost in node.preceding_edge_costs() {
+                *edge_cost_distribution.entry(cost).or_insert(0) += 1;
             }
         }
-        if nodes.is_empty() {
-            return Err(LatticeError::NoNodeIsFoundForTheInput.into());
-        }
+        LatticeStats::new(
+            step_node_counts,
+            self.evaluated_edge_count.get(),
+            self.unreachable_edge_count.get(),
+            edge_cost_distribution,
+        )
+    }
 
-        self.graph.push(GraphStep::new(self_input.length(), nodes));
+    /**
+     * Returns the cost aggregation.
+     *
+     * # Returns
+     * The cost aggregation.
+     */
+    pub const fn cost_aggregation(&self) -> CostAggregation {
+        self.cost_aggregation
+    }
 
-        Ok(())
+    /**
+     * Returns the limits.
+     *
+     * # Returns
+     * The limits.
+     */
+    pub const fn limits(&self) -> LatticeLimits {
+        self.limits
     }
 
     /**
-     * Settles this lattice.
+     * Sets limits.
      *
-     * You can modify the lattice after settlement.
-     * Modification of the lattice after settlement invalidate the EOS node.
+     * The subsequent `push_back`, `push_back_with_vocabulary`,
+     * `push_back_with_entries` and `push_back_epsilon_entries` calls fail when
+     * the new step exceeds the limits, leaving the lattice unchanged. The
+     * steps already in the lattice are not affected.
      *
-     * # Returns
-     * The EOS node.
+     * # Arguments
+     * * `limits` - Limits.
+     */
+    pub fn set_limits(&mut self, limits: LatticeLimits) {
+        self.limits = limits;
+    }
+
+    /**
+     * Returns the deadline.
      *
-     * # Errors
-     * * When no input pushed yet.
+     * # Returns
+     * The deadline. Or None when there is no deadline.
      */
-    pub fn settle(&mut self) -> Result<Node> {
-        let Some(graph_last) = self.graph.last() else {
-            return Err(LatticeError::NoInput.into());
-        };
-        let preceding_edge_costs = self.preceding_edge_costs(graph_last, &Entry::BosEos)?;
-        let best_preceding_node_index =
-            Self::best_preceding_node_index(graph_last, preceding_edge_costs.as_slice());
-        let best_preceding_path_cost = Self::add_cost(
-            graph_last.nodes()[best_preceding_node_index].path_cost(),
-            preceding_edge_costs[best_preceding_node_index],
-        );
+    pub const fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
 
-        let eos_node = Node::eos(
-            self.graph.len() - 1,
-            preceding_edge_costs,
-            best_preceding_node_index,
-            best_preceding_path_cost,
-        );
-        Ok(eos_node)
+    /**
+     * Sets a deadline.
+     *
+     * The subsequent `push_back`, `push_back_with_vocabulary`,
+     * `push_back_with_entries`, `push_back_epsilon_entries` and `settle` calls
+     * fail with `LatticeError::DeadlineExceeded` once the deadline passes,
+     * leaving the lattice unchanged. A `push_back` call examines the deadline
+     * before looking up the entries following each preceding step, so that a
+     * pathological input does not hang. The steps already in the lattice are
+     * kept, so the partial result can still be used.
+     *
+     * An `NBestIterator` created for this lattice stops at the deadline too.
+     *
+     * # Arguments
+     * * `deadline` - A deadline. Or None to remove the deadline.
+     */
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
     }
 
-    fn preceding_edge_costs(&self, step: &GraphStep, next_entry: &Entry) -> Result<Rc<Vec<i32>>> {
-        assert!(!step.nodes().is_empty());
-        let mut costs = Vec::with_capacity(step.nodes().len());
-        for node in step.nodes() {
-            let cost = self.vocabulary.find_connection(node, next_entry)?.cost();
-            costs.push(cost);
-        }
-        Ok(Rc::new(costs))
+    /**
+     * Sets a value formatter.
+     *
+     * The formatter is used to show the values of the nodes, the paths and the
+     * entries in the diagnostics.
+     *
+     * # Arguments
+     * * `value_formatter` - A value formatter.
+     */
+    pub fn set_value_formatter(&mut self, value_formatter: &'a dyn Fn(&dyn Any) -> String) {
+        self.value_formatter = Some(value_formatter);
     }
 
-    fn best_preceding_node_index(step: &GraphStep, edge_costs: &[i32]) -> usize {
-        assert!(!step.nodes().is_empty());
-        let mut min_index = 0;
-        for i in 1..step.nodes().len() {
-            if Self::add_cost(step.nodes()[i].path_cost(), edge_costs[i])
-                < Self::add_cost(step.nodes()[min_index].path_cost(), edge_costs[min_index])
-            {
-                min_index = i;
-            }
-        }
-        min_index
+    /**
+     * Sets a node cost adjuster.
+     *
+     * The adjuster is called with each entry found in the subsequent
+     * `push_back` and `push_back_with_entries` calls, and its return value is
+     * added to the cost of the entry before the node is created. So the
+     * adjustment is reflected in both the best path and the N-best paths.
+     * An adjustment of `i32::MAX` makes the node unreachable.
+     *
+     * The nodes already in the lattice are not affected.
+     *
+     * # Arguments
+     * * `node_cost_adjuster` - A node cost adjuster.
+     */
+    pub fn set_node_cost_adjuster(&mut self, node_cost_adjuster: &'a dyn Fn(&Entry) -> i32) {
+        self.node_cost_adjuster = Some(node_cost_adjuster);
     }
 
-    const fn add_cost(one: i32, another: i32) -> i32 {
-        if one == i32::MAX || another == i32::MAX {
-            i32::MAX
-        } else {
-            one + another
-        }
+    /**
+     * Sets a higher order scorer.
+     *
+     * The scorer is called with the context of a preceding node and an entry
+     * in the subsequent `push_back`, `push_back_with_entries`,
+     * `push_back_epsilon_entries` and `settle` calls, and its return value is
+     * added to the connection cost from the preceding node to the entry. The
+     * context is the nodes on the best path to the preceding node, at most
+     * `context_length` nodes ending with the preceding node. So a context
+     * length of 2 makes a trigram model. A score of `i32::MAX` makes the edge
+     * unreachable.
+     *
+     * Since only the best preceding paths are examined, the best path is
+     * approximate. An `NBestIterator` re-applies the scorer to the contexts of
+     * the actual paths, so the costs of the N-best paths are exact, though the
+     * paths may not be in the strict ascending order of the costs.
+     *
+     * The nodes already in the lattice are not affected.
+     *
+     * # Arguments
+     * * `context_length`      - The max length of the contexts.
+     * * `higher_order_scorer` - A higher order scorer.
+     */
+    pub fn set_higher_order_scorer(
+        &mut self,
+        context_length: usize,
+        higher_order_scorer: &'a dyn Fn(&[&Node], &Entry) -> i32,
+    ) {
+        self.higher_order_scorer = Some((context_length, higher_order_scorer));
     }
-}
+
+    /**
+     * Formats a value.
+     *
+     * # Arguments
+     * * `value` - A value.
+     *
+     * # Returns
+     * The formatted value. Or the debug representation of `dyn Any` when no
+     * value formatter is set.
+     */
+    pub fn format_value(&self, value: &dyn Any) -> String {
+        match self.value_formatter {
+            Some(value_formatter) => value_formatter(value),
+            None => format!("{:?}", value),
+        }
+    }
+
+    /**
+     * Returns the input.
+     *
+     * # Returns
+     * The input. Or None when no input is pushed yet.
+     */
+    pub fn input(&self) -> Option<&dyn Input> {
+        self.input.as_deref()
+    }
+
+    /**
+     * Returns the span of the input at the specified step.
+     *
+     * The span is the range of the input pushed back at the step. The span at
+     * the step 0 (the BOS step) is empty.
+     *
+     * # Arguments
+     * * `step` - A step.
+     *
+     * # Returns
+     * The offset and the tail of the span.
+     *
+     * # Errors
+     * * When step is too large.
+     */
+    pub fn step_span(&self, step: usize) -> Result<(usize, usize)> {
+        if step >= self.graph.len() {
+            return Err(LatticeError::StepIsTooLarge.into());
+        }
+        let offset = if step == 0 {
+            0
+        } else {
+            self.graph[step - 1].input_tail()
+        };
+        Ok((offset, self.graph[step].input_tail()))
+    }
+
+    /**
+     * Returns the ID of the node.
+     *
+     * # Arguments
+     * * `node` - A node.
+     *
+     * # Returns
+     * The node ID.
+     *
+     * # Errors
+     * * When the node is not found in this lattice.
+     */
+    pub fn node_id(&self, node: &Node) -> Result<NodeId> {
+        match node {
+            Node::Bos(_) => Ok(NodeId::new(0, 0)),
+            Node::Eos(_) => {
+                if node.preceding_step() >= self.graph.len() {
+                    return Err(NodeError::NodeNotFoundInLattice.into());
+                }
+                Ok(NodeId::new(node.preceding_step() + 1, usize::MAX))
+            }
+            Node::Middle(_) => {
+                let index_in_step = node.index_in_step();
+                for step in node.preceding_step() + 1..self.graph.len() {
+                    if self.graph[step].nodes().get(index_in_step) == Some(node) {
+                        return Ok(NodeId::new(step, index_in_step));
+                    }
+                }
+                Err(NodeError::NodeNotFoundInLattice.into())
+            }
+        }
+    }
+
+    /**
+     * Pushes back an input.
+     *
+     * # Arguments
+     * * `input` - An input.
+     *
+     * # Returns
+     * The delta describing the added step.
+     *
+     * # Errors
+     * * When no node is found for the input.
+     * * When the new step exceeds the limits.
+     * * When the deadline is exceeded.
+     */
+    pub fn push_back(&mut self, input: Box<dyn Input>) -> Result<StepDelta> {
+        self.push_back_with_entry_vocabulary(input, None)
+    }
+
+    /**
+     * Pushes back an input with a vocabulary.
+     *
+     * The entries of the new step are looked up in the given vocabulary
+     * instead of the vocabulary of this lattice. The connection costs are
+     * still obtained from the vocabulary of this lattice.
+     *
+     * # Arguments
+     * * `input`      - An input.
+     * * `vocabulary` - A vocabulary for the entries of the new step.
+     *
+     * # Returns
+     * The delta describing the added step.
+     *
+     * # Errors
+     * * When no node is found for the input.
+     * * When the new step exceeds the limits.
+     * * When the deadline is exceeded.
+     */
+    pub fn push_back_with_vocabulary(
+        &mut self,
+        input: Box<dyn Input>,
+        vocabulary: &dyn Vocabulary,
+    ) -> Result<StepDelta> {
+        self.push_back_with_entry_vocabulary(input, Some(vocabulary))
+    }
+
+    fn push_back_with_entry_vocabulary(
+        &mut self,
+        input: Box<dyn Input>,
+        entry_vocabulary: Option<&dyn Vocabulary>,
+    ) -> Result<StepDelta> {
+        self.check_deadline()?;
+        self.check_step_limit()?;
+        let input_length = self.input.as_ref().map(|input| input.length());
+        self.append_input(input)?;
+        let nodes = match self.new_step_nodes(entry_vocabulary) {
+            Ok(nodes) => nodes,
+            Err(e) => {
+                self.truncate_input(input_length)?;
+                return Err(e);
+            }
+        };
+
+        let delta = self.new_step_delta(&nodes);
+        let input_tail = match &self.input {
+            Some(self_input) => self_input.length(),
+            None => unreachable!(),
+        };
+        self.push_step(GraphStep::new(input_tail, nodes));
+        self.consecutive_epsilon_step_count = 0;
+
+        Ok(delta)
+    }
+
+    fn new_step_delta(&self, nodes: &[Node]) -> StepDelta {
+        let (best_node_index, best_path_cost) = nodes
+            .iter()
+            .map(Node::path_cost)
+            .enumerate()
+            .min_by_key(|&(_, path_cost)| path_cost)
+            .unwrap_or_else(|| unreachable!("nodes must not be empty."));
+        StepDelta::new(
+            self.graph.len(),
+            nodes.len(),
+            best_node_index,
+            best_path_cost,
+        )
+    }
+
+    fn new_step_nodes(&self, entry_vocabulary: Option<&dyn Vocabulary>) -> Result<Vec<Node>> {
+        let self_input = match &self.input {
+            Some(self_input) => self_input,
+            None => unreachable!(),
+        };
+
+        let mut nodes = Vec::new();
+        let mut found: Option<(usize, Vec<Entry>)> = None;
+        for i in self.origin_step..self.graph.len() {
+            let step = &self.graph[i];
+
+            // The steps after epsilon steps share the input tail.
+            if found.as_ref().map(|(input_tail, _)| *input_tail) != Some(step.input_tail()) {
+                self.check_deadline()?;
+                let node_key = match self_input
+                    .create_subrange(step.input_tail(), self_input.length() - step.input_tail())
+                {
+                    Ok(node_key) => node_key,
+                    Err(e) => return Err(e),
+                };
+                let vocabulary = entry_vocabulary.unwrap_or_else(|| self.vocabulary.get());
+                found = Some((
+                    step.input_tail(),
+                    vocabulary.find_entries(node_key.as_ref())?,
+                ));
+            }
+            let Some((_, found_entries)) = &found else {
+                unreachable!("found must be set.");
+            };
+
+            self.check_node_limits(nodes.len() + found_entries.len())?;
+            for entry in found_entries {
+                let new_node = self.new_node(entry, nodes.len(), i)?;
+                nodes.push(new_node);
+            }
+        }
+        if nodes.is_empty() {
+            return Err(LatticeError::NoNodeIsFoundForTheInput.into());
+        }
+        Ok(nodes)
+    }
+
+    /**
+     * Pushes back an input with its entries.
+     *
+     * The vocabulary is not looked up for the entries. The supplied entries
+     * become the nodes spanning the input. The connection costs are still
+     * obtained from the vocabulary.
+     *
+     * # Arguments
+     * * `input`   - An input.
+     * * `entries` - Entries spanning the input.
+     *
+     * # Returns
+     * The delta describing the added step.
+     *
+     * # Errors
+     * * When `entries` is empty.
+     * * When `entries` contains the BOS/EOS entry.
+     * * When finding a connection fails. The input is not appended.
+     * * When the new step exceeds the limits.
+     * * When the deadline is exceeded.
+     */
+    pub fn push_back_with_entries(
+        &mut self,
+        input: Box<dyn Input>,
+        entries: Vec<Entry>,
+    ) -> Result<StepDelta> {
+        if entries.is_empty() {
+            return Err(LatticeError::NoNodeIsFoundForTheInput.into());
+        }
+        if entries.iter().any(|entry| matches!(entry, Entry::BosEos)) {
+            return Err(NodeError::BosOrEosEntryNotAllowed.into());
+        }
+        self.check_deadline()?;
+        self.check_step_limit()?;
+        self.check_node_limits(entries.len())?;
+
+        let input_length = self.input.as_ref().map(|input| input.length());
+        self.append_input(input)?;
+        let nodes = match self.new_entry_nodes(&entries) {
+            Ok(nodes) => nodes,
+            Err(e) => {
+                self.truncate_input(input_length)?;
+                return Err(e);
+            }
+        };
+
+        let delta = self.new_step_delta(&nodes);
+        let input_tail = match &self.input {
+            Some(self_input) => self_input.length(),
+            None => unreachable!(),
+        };
+        self.push_step(GraphStep::new(input_tail, nodes));
+        self.consecutive_epsilon_step_count = 0;
+
+        Ok(delta)
+    }
+
+    fn new_entry_nodes(&self, entries: &[Entry]) -> Result<Vec<Node>> {
+        let preceding_step = self.graph.len() - 1;
+        let mut nodes = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let new_node = self.new_node(entry, nodes.len(), preceding_step)?;
+            nodes.push(new_node);
+        }
+        Ok(nodes)
+    }
+
+    /**
+     * Pushes back epsilon entries.
+     *
+     * The epsilon entries consume no input. They become the nodes of a new
+     * step preceded by the last step, and the new step has the same input tail
+     * as the last step. The nodes pushed back later may be preceded by either
+     * the last step or the new step, so the epsilon nodes are optional in the
+     * paths. But when no input is pushed back after them, EOS is preceded by
+     * the epsilon nodes only.
+     *
+     * At most `MAX_CONSECUTIVE_EPSILON_STEP_COUNT` epsilon steps can be
+     * pushed back without an input between them.
+     *
+     * # Arguments
+     * * `entries` - Epsilon entries.
+     *
+     * # Returns
+     * The delta describing the added step.
+     *
+     * # Errors
+     * * When `entries` is empty.
+     * * When `entries` contains the BOS/EOS entry.
+     * * When too many epsilon steps are pushed back consecutively.
+     * * When the new step exceeds the limits.
+     * * When the deadline is exceeded.
+     */
+    pub fn push_back_epsilon_entries(&mut self, entries: Vec<Entry>) -> Result<StepDelta> {
+        if entries.is_empty() {
+            return Err(LatticeError::NoNodeIsFoundForTheInput.into());
+        }
+        if entries.iter().any(|entry| matches!(entry, Entry::BosEos)) {
+            return Err(NodeError::BosOrEosEntryNotAllowed.into());
+        }
+        if self.consecutive_epsilon_step_count >= Self::MAX_CONSECUTIVE_EPSILON_STEP_COUNT {
+            return Err(LatticeError::TooManyEpsilonSteps.into());
+        }
+        self.check_deadline()?;
+        self.check_step_limit()?;
+        self.check_node_limits(entries.len())?;
+
+        let preceding_step = self.graph.len() - 1;
+        let input_tail = self.graph[preceding_step].input_tail();
+        let mut nodes = Vec::with_capacity(entries.len());
+        for entry in &entries {
+            let new_node = self.new_node(entry, nodes.len(), preceding_step)?;
+            nodes.push(new_node);
+        }
+
+        let delta = self.new_step_delta(&nodes);
+        self.push_step(GraphStep::new(input_tail, nodes));
+        self.consecutive_epsilon_step_count += 1;
+
+        Ok(delta)
+    }
+
+    /**
+     * Recosts this lattice.
+     *
+     * The connection costs are found by the vocabulary again, and the best
+     * preceding nodes and the path costs are recalculated from the first step.
+     * Call this after the vocabulary changes the connection costs. The
+     * connection cache is cleared.
+     *
+     * The node costs are kept as they are. The preceding edge costs of the
+     * initial nodes are kept too.
+     *
+     * Recosting the lattice invalidates the EOS node.
+     *
+     * # Errors
+     * * When finding a connection fails. The lattice is left partially
+     *   recosted.
+     */
+    pub fn recost(&mut self) -> Result<()> {
+        self.connection_cache.borrow_mut().clear();
+        for i in self.origin_step + 1..self.graph.len() {
+            let nodes = self.graph[i]
+                .nodes()
+                .iter()
+                .map(|node| {
+                    self.new_node_with_adjusted_entry(
+                        &Entry::from(node),
+                        node.index_in_step(),
+                        node.preceding_step(),
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?;
+            self.graph[i].nodes = nodes;
+        }
+        Ok(())
+    }
+
+    /**
+     * Settles this lattice.
+     *
+     * You can modify the lattice after settlement.
+     * Modification of the lattice after settlement invalidate the EOS node.
+     *
+     * # Returns
+     * The EOS node.
+     *
+     * # Errors
+     * * When no input pushed yet.
+     * * When the deadline is exceeded.
+     */
+    pub fn settle(&mut self) -> Result<Node> {
+        let eos_node = self.settle_with_budget(i32::MAX)?;
+        let Some(eos_node) = eos_node else {
+            unreachable!("The path cost of the EOS node must not exceed i32::MAX.");
+        };
+        Ok(eos_node)
+    }
+
+    /**
+     * Settles this lattice within a cost budget.
+     *
+     * When no node in the last step is reachable, the connections to EOS are
+     * not evaluated. Otherwise the EOS node is created only when its path cost
+     * is within the budget.
+     *
+     * # Arguments
+     * * `max_cost` - A max path cost.
+     *
+     * # Returns
+     * The EOS node. Or None when the best path cost exceeds `max_cost`.
+     *
+     * # Errors
+     * * When no input pushed yet.
+     * * When the deadline is exceeded.
+     */
+    pub fn settle_with_budget(&mut self, max_cost: i32) -> Result<Option<Node>> {
+        let Some(graph_last) = self.graph.last() else {
+            return Err(LatticeError::NoInput.into());
+        };
+        self.check_deadline()?;
+        if max_cost < i32::MAX
+            && graph_last
+                .nodes()
+                .iter()
+                .all(|node| node.path_cost() == i32::MAX)
+        {
+            return Ok(None);
+        }
+
+        let preceding_edge_costs = self.preceding_edge_costs(graph_last, &Entry::BosEos)?;
+        let best_preceding_node_index =
+            Self::best_preceding_node_index(graph_last, preceding_edge_costs.as_slice());
+        let best_preceding_path_cost =
+            self.preceding_path_cost(graph_last, preceding_edge_costs.as_slice());
+        if best_preceding_path_cost > max_cost {
+            return Ok(None);
+        }
+
+        let eos_node = Node::eos(
+            self.graph.len() - 1,
+            preceding_edge_costs,
+            best_preceding_node_index,
+            best_preceding_path_cost,
+        );
+        Ok(Some(eos_node))
+    }
+
+    /**
+     * Samples a path.
+     *
+     * A path is sampled with the probability proportional to
+     * `exp(-cost / temperature)`. The forward scores of all the nodes are
+     * calculated first, and then the nodes are sampled backward from the EOS
+     * node.
+     *
+     * A lower temperature makes the best path more likely. A higher one makes
+     * the paths more uniformly sampled.
+     *
+     * # Arguments
+     * * `eos_node`    - An EOS node.
+     * * `temperature` - A temperature.
+     * * `rng`         - A random number generator returning a number in `[0, 1)`.
+     *
+     * # Returns
+     * A path.
+     *
+     * # Errors
+     * * When `temperature` is not a positive finite number.
+     * * When no path reaches the EOS node.
+     */
+    pub fn sample_path(
+        &self,
+        eos_node: &Node,
+        temperature: f64,
+        rng: &mut dyn FnMut() -> f64,
+    ) -> Result<Path> {
+        if !temperature.is_finite() || temperature <= 0.0 {
+            return Err(LatticeError::InvalidTemperature.into());
+        }
+        let scaled = |cost: i32| {
+            if cost == i32::MAX {
+                f64::NEG_INFINITY
+            } else {
+                -(cost as f64) / temperature
+            }
+        };
+
+        let mut log_forward_scores = Vec::<Vec<f64>>::with_capacity(self.graph.len());
+        for step in &self.graph {
+            let scores = step
+                .nodes()
+                .iter()
+                .map(|node| {
+                    if node.is_bos() {
+                        return 0.0;
+                    }
+                    let preceding_scores = &log_forward_scores[node.preceding_step()];
+                    let log_sum = Self::log_sum_exp(
+                        preceding_scores
+                            .iter()
+                            .zip(node.preceding_edge_costs().iter())
+                            .map(|(&score, &edge_cost)| score + scaled(edge_cost)),
+                    );
+                    log_sum + scaled(node.node_cost())
+                })
+                .collect::<Vec<_>>();
+            log_forward_scores.push(scores);
+        }
+
+        let mut nodes = vec![eos_node.clone()];
+        let mut cost = eos_node.node_cost();
+        let mut node = eos_node.clone();
+        while !node.is_bos() {
+            let preceding_nodes = self.nodes_at(node.preceding_step())?;
+            let weights = log_forward_scores[node.preceding_step()]
+                .iter()
+                .zip(node.preceding_edge_costs().iter())
+                .map(|(&score, &edge_cost)| score + scaled(edge_cost))
+                .collect::<Vec<_>>();
+            let Some(index) = Self::sample_index(&weights, rng()) else {
+                return Err(LatticeError::NoPath.into());
+            };
+            let preceding_node = &preceding_nodes[index];
+            cost = Self::add_cost(
+                cost,
+                Self::add_cost(
+                    node.preceding_edge_costs()[index],
+                    preceding_node.node_cost(),
+                ),
+            );
+            nodes.push(preceding_node.clone());
+            node = preceding_node.clone();
+        }
+        nodes.reverse();
+
+        Ok(Path::new(nodes, cost))
+    }
+
+    /**
+     * Returns the JSON representation.
+     *
+     * The representation has the following members:
+     *
+     * * `steps`     - The steps, each of which has its `span` and `nodes`. A
+     *                 node has its `span`, `value`, `node_cost`, `path_cost`,
+     *                 `preceding_step`, `preceding_edge_costs`,
+     *                 `best_preceding_node` and `best`, which is `true` when
+     *                 the node is on the best path.
+     * * `eos`       - The EOS node.
+     * * `best_path` - The node IDs on the best path.
+     * * `paths`     - The top-k paths, each of which has its `cost` and the
+     *                 node IDs.
+     *
+     * A node ID is a pair of a step and an index in the step. The index of the
+     * EOS node is `null`.
+     *
+     * # Arguments
+     * * `eos_node`      - An EOS node.
+     * * `value_to_json` - A converter from a node value to a JSON value.
+     * * `top_k`         - The count of the paths. No path is listed when 0.
+     *
+     * # Returns
+     * The JSON representation.
+     *
+     * # Errors
+     * * When the EOS node is not found in this lattice.
+     */
+    #[cfg(feature = "json")]
+    pub fn to_json(
+        &self,
+        eos_node: &Node,
+        value_to_json: &dyn Fn(&dyn Any) -> serde_json::Value,
+        top_k: usize,
+    ) -> Result<serde_json::Value> {
+        let eos_node_id = self.node_id(eos_node)?;
+        let best_path = self.best_path_node_ids(eos_node)?;
+
+        let mut steps = Vec::with_capacity(self.graph.len());
+        for (step, graph_step) in self.graph.iter().enumerate() {
+            let (offset, tail) = self.step_span(step)?;
+            let nodes = graph_step
+                .nodes()
+                .iter()
+                .enumerate()
+                .map(|(index_in_step, node)| {
+                    let node_offset = if node.is_bos() {
+                        0
+                    } else {
+                        self.graph[node.preceding_step()].input_tail()
+                    };
+                    json!({
+                        "span": [node_offset, tail],
+                        "value": node.value().map_or(serde_json::Value::Null, value_to_json),
+                        "node_cost": node.node_cost(),
+                        "path_cost": node.path_cost(),
+                        "preceding_step": (!node.is_bos()).then_some(node.preceding_step()),
+                        "preceding_edge_costs": node.preceding_edge_costs(),
+                        "best_preceding_node": (!node.is_bos()).then_some(node.best_preceding_node()),
+                        "best": best_path.contains(&NodeId::new(step, index_in_step)),
+                    })
+                })
+                .collect::<Vec<_>>();
+            steps.push(json!({ "span": [offset, tail], "nodes": nodes }));
+        }
+
+        let paths = if top_k == 0 {
+            Vec::new()
+        } else {
+            NBestIterator::new(self, eos_node.clone(), Box::new(Constraint::new()))
+                .take(top_k)
+                .map(|path| {
+                    let node_ids = path
+                        .nodes()
+                        .iter()
+                        .map(|node| Ok(Self::node_id_to_json(self.node_id(node)?)))
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(json!({ "cost": path.cost(), "nodes": node_ids }))
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        Ok(json!({
+            "steps": steps,
+            "eos": {
+                "id": Self::node_id_to_json(eos_node_id),
+                "path_cost": eos_node.path_cost(),
+                "preceding_step": eos_node.preceding_step(),
+                "preceding_edge_costs": eos_node.preceding_edge_costs(),
+                "best_preceding_node": eos_node.best_preceding_node(),
+            },
+            "best_path": best_path
+                .into_iter()
+                .chain([eos_node_id])
+                .map(Self::node_id_to_json)
+                .collect::<Vec<_>>(),
+            "paths": paths,
+        }))
+    }
+
+    #[cfg(feature = "json")]
+    fn best_path_node_ids(&self, eos_node: &Node) -> Result<Vec<NodeId>> {
+        let mut node_ids = Vec::new();
+        let mut step = eos_node.preceding_step();
+        let mut index_in_step = eos_node.best_preceding_node();
+        loop {
+            let Some(node) = self
+                .graph
+                .get(step)
+                .and_then(|graph_step| graph_step.nodes().get(index_in_step))
+            else {
+                return Err(NodeError::NodeNotFoundInLattice.into());
+            };
+            node_ids.push(NodeId::new(step, index_in_step));
+            if node.is_bos() {
+                break;
+            }
+            step = node.preceding_step();
+            index_in_step = node.best_preceding_node();
+        }
+        node_ids.reverse();
+        Ok(node_ids)
+    }
+
+    #[cfg(feature = "json")]
+    fn node_id_to_json(node_id: NodeId) -> serde_json::Value {
+        let index_in_step =
+            (node_id.index_in_step() != usize::MAX).then_some(node_id.index_in_step());
+        json!([node_id.step(), index_in_step])
+    }
+
+    fn log_sum_exp(values: impl Iterator<Item = f64> + Clone) -> f64 {
+        let max = values.clone().fold(f64::NEG_INFINITY, f64::max);
+        if max == f64::NEG_INFINITY {
+            return f64::NEG_INFINITY;
+        }
+        max + values.map(|value| (value - max).exp()).sum::<f64>().ln()
+    }
+
+    fn sample_index(log_weights: &[f64], random: f64) -> Option<usize> {
+        let max = log_weights
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if max == f64::NEG_INFINITY {
+            return None;
+        }
+        let weights = log_weights
+            .iter()
+            .map(|&log_weight| (log_weight - max).exp())
+            .collect::<Vec<_>>();
+        let threshold = random.clamp(0.0, 1.0) * weights.iter().sum::<f64>();
+        let mut cumulative = 0.0;
+        let mut last_positive = None;
+        for (i, &weight) in weights.iter().enumerate() {
+            if weight <= 0.0 {
+                continue;
+            }
+            cumulative += weight;
+            if threshold < cumulative {
+                return Some(i);
+            }
+            last_positive = Some(i);
+        }
+        last_positive
+    }
+
+    fn push_step(&mut self, step: GraphStep) {
+        self.node_count += step.nodes().len();
+        self.graph.push(step);
+    }
+
+    fn check_deadline(&self) -> Result<()> {
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(LatticeError::DeadlineExceeded.into());
+        }
+        Ok(())
+    }
+
+    fn check_step_limit(&self) -> Result<()> {
+        if let Some(max_step_count) = self.limits.max_step_count() {
+            if self.graph.len() >= max_step_count {
+                return Err(LatticeError::TooManySteps { max_step_count }.into());
+            }
+        }
+        Ok(())
+    }
+
+    fn check_node_limits(&self, step_node_count: usize) -> Result<()> {
+        if let Some(max_step_node_count) = self.limits.max_step_node_count() {
+            if step_node_count > max_step_node_count {
+                return Err(LatticeError::TooManyNodesInStep {
+                    max_step_node_count,
+                }
+                .into());
+            }
+        }
+        if let Some(max_node_count) = self.limits.max_node_count() {
+            if self.node_count + step_node_count > max_node_count {
+                return Err(LatticeError::TooManyNodes { max_node_count }.into());
+            }
+        }
+        Ok(())
+    }
+
+    fn append_input(&mut self, input: Box<dyn Input>) -> Result<()> {
+        if let Some(self_input) = &mut self.input {
+            self_input.append(input)?;
+        } else {
+            self.input = Some(input);
+        };
+        Ok(())
+    }
+
+    // Truncates the input to the length before an input is appended.
+    fn truncate_input(&mut self, length: Option<usize>) -> Result<()> {
+        self.input = match (&self.input, length) {
+            (Some(self_input), Some(length)) => Some(self_input.create_subrange(0, length)?),
+            _ => None,
+        };
+        Ok(())
+    }
+
+    fn new_node(&self, entry: &Entry, index_in_step: usize, preceding_step: usize) -> Result<Node> {
+        let adjusted_entry = self.node_cost_adjuster.map(|node_cost_adjuster| {
+            entry
+                .clone()
+                .with_cost(Self::add_cost(entry.cost(), node_cost_adjuster(entry)))
+        });
+        let entry = adjusted_entry.as_ref().unwrap_or(entry);
+        self.new_node_with_adjusted_entry(entry, index_in_step, preceding_step)
+    }
+
+    fn new_node_with_adjusted_entry(
+        &self,
+        entry: &Entry,
+        index_in_step: usize,
+        preceding_step: usize,
+    ) -> Result<Node> {
+        let step = &self.graph[preceding_step];
+        let preceding_edge_costs = self.preceding_edge_costs(step, entry)?;
+        let best_preceding_node_index_ =
+            Self::best_preceding_node_index(step, preceding_edge_costs.as_slice());
+        let best_preceding_path_cost =
+            self.preceding_path_cost(step, preceding_edge_costs.as_slice());
+        Node::new_with_entry(
+            entry,
+            index_in_step,
+            preceding_step,
+            preceding_edge_costs,
+            best_preceding_node_index_,
+            Self::add_cost(best_preceding_path_cost, entry.cost()),
+        )
+    }
+
+    fn preceding_edge_costs(&self, step: &GraphStep, next_entry: &Entry) -> Result<Rc<Vec<i32>>> {
+        assert!(!step.nodes().is_empty());
+        let mut costs = Vec::with_capacity(step.nodes().len());
+        for node in step.nodes() {
+            let cost = self.add_higher_order_score(
+                self.find_connection_cost(node, next_entry)?,
+                node,
+                next_entry,
+            );
+            self.evaluated_edge_count
+                .set(self.evaluated_edge_count.get() + 1);
+            if cost == i32::MAX {
+                self.unreachable_edge_count
+                    .set(self.unreachable_edge_count.get() + 1);
+            }
+            costs.push(cost);
+        }
+        Ok(Rc::new(costs))
+    }
+
+    // Calculates the cost of the edge from the node to the entry again, without
+    // the connection cache.
+    pub(crate) fn recalculate_edge_cost(&self, from: &Node, to: &Entry) -> Result<i32> {
+        let cost = self.vocabulary.get().find_connection(from, to)?.cost();
+        Ok(self.add_higher_order_score(cost, from, to))
+    }
+
+    fn add_higher_order_score(&self, cost: i32, from: &Node, to: &Entry) -> i32 {
+        let Some((context_length, higher_order_scorer)) = self.higher_order_scorer else {
+            return cost;
+        };
+        let context = self.best_context_of(from, context_length);
+        Self::add_cost(cost, higher_order_scorer(&context, to))
+    }
+
+    // Returns the nodes on the best path ending with the node, in the order
+    // from BOS.
+    fn best_context_of<'n>(&'n self, node: &'n Node, context_length: usize) -> Vec<&'n Node> {
+        let mut context = Vec::with_capacity(context_length);
+        let mut node = node;
+        while context.len() < context_length {
+            context.push(node);
+            if node.is_bos() {
+                break;
+            }
+            node = &self.graph[node.preceding_step()].nodes()[node.best_preceding_node()];
+        }
+        context.reverse();
+        context
+    }
+
+    // Returns the difference of the higher order scores of the edges in the
+    // tail path, from EOS to a node, when the path preceding the node changes
+    // from the best one to the best one through the preceding node at the
+    // index. The contexts of the first edges in the tail path reach the
+    // preceding path.
+    pub(crate) fn higher_order_cost_delta(
+        &self,
+        tail_path: &[Node],
+        preceding_index: usize,
+    ) -> i32 {
+        let Some((context_length, higher_order_scorer)) = self.higher_order_scorer else {
+            return 0;
+        };
+        let Some(node) = tail_path.last() else {
+            return 0;
+        };
+        if context_length < 2 || node.is_bos() {
+            return 0;
+        }
+        let preceding_nodes = self.graph[node.preceding_step()].nodes();
+        let new_head = self.best_context_of(&preceding_nodes[preceding_index], context_length - 1);
+        let old_head = self.best_context_of(
+            &preceding_nodes[node.best_preceding_node()],
+            context_length - 1,
+        );
+
+        let mut delta = 0i64;
+        let mut tail = tail_path.iter().rev();
+        let mut context_tail = Vec::with_capacity(context_length);
+        context_tail.push(node);
+        let _node = tail.next();
+        for next_node in tail {
+            if context_tail.len() >= context_length {
+                break;
+            }
+            let next_entry = Entry::from(next_node);
+            let head_length = context_length - context_tail.len();
+            let new_score = higher_order_scorer(
+                &Self::join_context(&new_head, head_length, &context_tail),
+                &next_entry,
+            );
+            if new_score == i32::MAX {
+                return i32::MAX;
+            }
+            let old_score = higher_order_scorer(
+                &Self::join_context(&old_head, head_length, &context_tail),
+                &next_entry,
+            );
+            if old_score == i32::MAX {
+                // The tail path cost is already unreachable, and cannot be
+                // made reachable again by the difference.
+                return i32::MAX;
+            }
+            delta += i64::from(new_score) - i64::from(old_score);
+            context_tail.push(next_node);
+        }
+        // i32::MAX is left for the unreachable ones.
+        delta.clamp(i64::from(i32::MIN), i64::from(i32::MAX - 1)) as i32
+    }
+
+    fn join_context<'n>(head: &[&'n Node], head_length: usize, tail: &[&'n Node]) -> Vec<&'n Node> {
+        let mut context = head[head.len().saturating_sub(head_length)..].to_vec();
+        context.extend_from_slice(tail);
+        context
+    }
+
+    fn find_connection_cost(&self, from: &Node, to: &Entry) -> Result<i32> {
+        if self.connection_cache.borrow().capacity == 0 {
+            return Ok(self.vocabulary.get().find_connection(from, to)?.cost());
+        }
+
+        let key = (EntryIdentity::of_node(from), EntryIdentity::of_entry(to));
+        if let Some(cost) = self.connection_cache.borrow_mut().find(&key) {
+            return Ok(cost);
+        }
+        let cost = self.vocabulary.get().find_connection(from, to)?.cost();
+        self.connection_cache.borrow_mut().insert(key, cost);
+        Ok(cost)
+    }
+
+    fn best_preceding_node_index(step: &GraphStep, edge_costs: &[i32]) -> usize {
+        assert!(!step.nodes().is_empty());
+        let mut min_index = 0;
+        for i in 1..step.nodes().len() {
+            if Self::add_cost(step.nodes()[i].path_cost(), edge_costs[i])
+                < Self::add_cost(step.nodes()[min_index].path_cost(), edge_costs[min_index])
+            {
+                min_index = i;
+            }
+        }
+        min_index
+    }
+
+    // Aggregates the costs of the paths reaching the next node through the
+    // nodes in the step.
+    fn preceding_path_cost(&self, step: &GraphStep, edge_costs: &[i32]) -> i32 {
+        self.cost_aggregation.aggregate(
+            step.nodes()
+                .iter()
+                .zip(edge_costs)
+                .map(|(node, &edge_cost)| Self::add_cost(node.path_cost(), edge_cost)),
+        )
+    }
+
+    const fn add_cost(one: i32, another: i32) -> i32 {
+        if one == i32::MAX || another == i32::MAX {
+            i32::MAX
+        } else {
+            one + another
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
+    use crate::connection::Connection;
+    use crate::constraint::Constraint;
+    use crate::constraint_element::ConstraintElement;
     use crate::entry::Entry;
     use crate::hash_map_vocabulary::HashMapVocabulary;
+    use crate::n_best_iterator::NBestIterator;
+    use crate::node_constraint_element::NodeConstraintElement;
+    use crate::unicode_vocabulary::UnicodeWordVocabulary;
+
+    use super::*;
+
+    fn to_input(string: &str) -> Box<dyn Input> {
+        Box::new(crate::string_input::StringInput::new(string.to_string()))
+    }
+
+    /*
+                   +------------------mizuho/sakura/tsubame-------------------+
+                   |                path cost: 4270/3220/2990                 |
+                   |                                                          |
+                   +------------ariake/rapid811------------+                  |
+                   |          path cost: 2850/2010         |                  |
+                   |                                       |                  |
+        BOS--(Hakata)--kamome/local415--(Tosu)--local813--(Omuta)--local817--(Kumamoto)--EOS
+                     path cost: 1640/1370   |   pc: 2830           pc: 3160   |     path cost:3390
+                                            |                                 |
+                                            +------------local815-------------+
+                                                      path cost: 3550
+
+        (0) 3390  BOS - tsubame - EOS
+            [ sakura(3620),   local817(3760), local815(4050), mizuho(4670)   ]
+        (1) 3620  BOS - sakura - EOS
+            [ local817(3760), local815(4050), mizuho(4670)                   ]
+        (2) 3760  BOS - rapid811 - local817 - EOS
+            [ local815(4050), ariake(4600),   mizuho(4670),   local813(4680) ]
+        (3) 4050  BOS - local415 - local815 - EOS
+            [ kamome(4320),   ariake(4600),   mizuho(4670),   local813(4680) ]
+        (4) 4320  BOS - kamome - local815 - EOS
+            [ ariake(4600),   mizuho(4670),   local813(4680)                 ]
+        (5) 4600  BOS - ariake - local817 - EOS
+            [ mizuho(4670),   local813(4680)                                 ]
+        (6) 4670  BOS - mizuho - EOS
+            [ local813(4680)                                                 ]
+        (7) 4680  BOS - local415 - local813 - local817 - EOS
+            [ kamome(4950)                                                   ]
+        (8) 4950  BOS - kamome - local813 - local817 - EOS
+            [                                                                ]
+        (9) ----  -
+            [                                                                ]
+    */
+    fn entries() -> Vec<(String, Vec<Entry>)> {
+        vec![
+            (
+                String::from("[HakataTosu][TosuOmuta][OmutaKumamoto]"),
+                vec![
+                    Entry::new(
+                        Rc::from(to_input("Hakata-Tosu-Omuta-Kumamoto")),
+                        Rc::new("mizuho"),
+                        3670,
+                    ),
+                    Entry::new(
+                        Rc::from(to_input("Hakata-Tosu-Omuta-Kumamoto")),
+                        Rc::new("sakura"),
+                        2620,
+                    ),
+                    Entry::new(
+                        Rc::from(to_input("Hakata-Tosu-Omuta-Kumamoto")),
+                        Rc::new("tsubame"),
+                        2390,
+                    ),
+                ],
+            ),
+            (
+                String::from("[HakataTosu][TosuOmuta]"),
+                vec![
+                    Entry::new(
+                        Rc::from(to_input("Hakata-Tosu-Omuta")),
+                        Rc::new("ariake"),
+                        2150,
+                    ),
+                    Entry::new(
+                        Rc::from(to_input("Hakata-Tosu-Omuta")),
+                        Rc::new("rapid811"),
+                        1310,
+                    ),
+                ],
+            ),
+            (
+                String::from("[HakataTosu]"),
+                vec![
+                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new("kamome"), 840),
+                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new("local415"), 570),
+                ],
+            ),
+            (
+                String::from("[TosuOmuta]"),
+                vec![Entry::new(
+                    Rc::from(to_input("Tosu-Omuta")),
+                    Rc::new("local813"),
+                    860,
+                )],
+            ),
+            (
+                String::from("[TosuOmuta][OmutaKumamoto]"),
+                vec![Entry::new(
+                    Rc::from(to_input("Tosu-Omuta-Kumamoto")),
+                    Rc::new("local815"),
+                    1680,
+                )],
+            ),
+            (
+                String::from("[OmutaKumamoto]"),
+                vec![Entry::new(
+                    Rc::from(to_input("Omuta-Kumamoto")),
+                    Rc::new("local817"),
+                    950,
+                )],
+            ),
+        ]
+    }
+
+    fn connections() -> Vec<((Entry, Entry), i32)> {
+        vec![
+            (
+                (
+                    Entry::BosEos,
+                    Entry::new(
+                        Rc::from(to_input("Hakata-Tosu-Omuta-Kumamoto")),
+                        Rc::new(""),
+                        0,
+                    ),
+                ),
+                600,
+            ),
+            (
+                (
+                    Entry::BosEos,
+                    Entry::new(Rc::from(to_input("Hakata-Tosu-Omuta")), Rc::new(""), 0),
+                ),
+                700,
+            ),
+            (
+                (
+                    Entry::BosEos,
+                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new(""), 0),
+                ),
+                800,
+            ),
+            ((Entry::BosEos, Entry::BosEos), 8000),
+            (
+                (
+                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new(""), 0),
+                    Entry::new(Rc::from(to_input("Tosu-Omuta-Kumamoto")), Rc::new(""), 0),
+                ),
+                500,
+            ),
+            (
+                (
+                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new(""), 0),
+                    Entry::new(Rc::from(to_input("Tosu-Omuta")), Rc::new(""), 0),
+                ),
+                600,
+            ),
+            (
+                (
+                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new(""), 0),
+                    Entry::BosEos,
+                ),
+                6000,
+            ),
+            (
+                (
+                    Entry::new(Rc::from(to_input("Hakata-Tosu-Omuta")), Rc::new(""), 0),
+                    Entry::new(Rc::from(to_input("Omuta-Kumamoto")), Rc::new(""), 0),
+                ),
+                200,
+            ),
+            (
+                (
+                    Entry::new(Rc::from(to_input("Hakata-Tosu-Omuta")), Rc::new(""), 0),
+                    Entry::BosEos,
+                ),
+                2000,
+            ),
+            (
+                (
+                    Entry::new(Rc::from(to_input("Tosu-Omuta")), Rc::new(""), 0),
+                    Entry::new(Rc::from(to_input("Omuta-Kumamoto")), Rc::new(""), 0),
+                ),
+                300,
+            ),
+            (
+                (
+                    Entry::new(Rc::from(to_input("Tosu-Omuta")), Rc::new(""), 0),
+                    Entry::BosEos,
+                ),
+                3000,
+            ),
+            (
+                (
+                    Entry::new(
+                        Rc::from(to_input("Hakata-Tosu-Omuta-Kumamoto")),
+                        Rc::new(""),
+                        0,
+                    ),
+                    Entry::BosEos,
+                ),
+                400,
+            ),
+            (
+                (
+                    Entry::new(Rc::from(to_input("Tosu-Omuta-Kumamoto")), Rc::new(""), 0),
+                    Entry::BosEos,
+                ),
+                500,
+            ),
+            (
+                (
+                    Entry::new(Rc::from(to_input("Omuta-Kumamoto")), Rc::new(""), 0),
+                    Entry::BosEos,
+                ),
+                600,
+            ),
+        ]
+    }
+
+    fn entry_hash(entry: &Entry) -> u64 {
+        entry.key().map_or(0, |key| key.hash_value())
+    }
+
+    fn entry_equal_to(one: &Entry, other: &Entry) -> bool {
+        if one.key().is_none() && other.key().is_none() {
+            return true;
+        }
+        if let Some(one_key) = one.key() {
+            if let Some(other_key) = other.key() {
+                return one_key.equal_to(other_key);
+            }
+        }
+        false
+    }
+
+    fn create_vocabulary() -> Box<dyn Vocabulary> {
+        Box::new(HashMapVocabulary::new(
+            entries(),
+            connections(),
+            &entry_hash,
+            &entry_equal_to,
+        ))
+    }
+
+    fn create_empty_vocabulary() -> Box<dyn Vocabulary> {
+        Box::new(HashMapVocabulary::new(
+            Vec::new(),
+            Vec::new(),
+            &entry_hash,
+            &entry_equal_to,
+        ))
+    }
+
+    #[test]
+    fn new() {
+        let vocabulary = create_vocabulary();
+        let _lattice = Lattice::new(vocabulary.as_ref());
+    }
+
+    #[test]
+    fn new_owned() {
+        let vocabulary = Arc::new(UnicodeWordVocabulary::new());
+        let handles = (0..2)
+            .map(|_| {
+                let vocabulary = Arc::clone(&vocabulary);
+                std::thread::spawn(move || {
+                    let mut lattice = Lattice::new_owned(vocabulary);
+                    for c in "Hi, Kumamoto".chars() {
+                        let _delta = lattice.push_back(to_input(&c.to_string())).unwrap();
+                    }
+                    lattice.settle().unwrap().path_cost()
+                })
+            })
+            .collect::<Vec<_>>();
+        let path_costs = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(path_costs[0], path_costs[1]);
+    }
+
+    #[test]
+    fn new_with_connection_cache_capacity() {
+        let vocabulary = create_vocabulary();
+        let _lattice = Lattice::new_with_connection_cache_capacity(vocabulary.as_ref(), 42);
+    }
+
+    #[test]
+    fn new_with_cost_aggregation() {
+        let vocabulary = create_vocabulary();
+        {
+            let mut lattice =
+                Lattice::new_with_cost_aggregation(vocabulary.as_ref(), CostAggregation::MinPlus)
+                    .unwrap();
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _result = lattice.push_back(to_input("[TosuOmuta]"));
+            let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+            let eos_node = lattice.settle().unwrap();
+
+            assert_eq!(eos_node.path_cost(), 3390);
+        }
+        {
+            let mut lattice = Lattice::new_with_cost_aggregation(
+                vocabulary.as_ref(),
+                CostAggregation::LogSum {
+                    temperature: 1000.0,
+                },
+            )
+            .unwrap();
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _result = lattice.push_back(to_input("[TosuOmuta]"));
+            let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+            let eos_node = lattice.settle().unwrap();
+
+            assert!(eos_node.path_cost() < 3390);
+            assert_eq!(eos_node.preceding_step(), 3);
+        }
+        {
+            let mut lattice = Lattice::new_with_cost_aggregation(
+                vocabulary.as_ref(),
+                CostAggregation::LogSum { temperature: 0.01 },
+            )
+            .unwrap();
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _result = lattice.push_back(to_input("[TosuOmuta]"));
+            let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+            let eos_node = lattice.settle().unwrap();
+
+            assert_eq!(eos_node.path_cost(), 3390);
+        }
+        {
+            let result = Lattice::new_with_cost_aggregation(
+                vocabulary.as_ref(),
+                CostAggregation::LogSum { temperature: 0.0 },
+            );
+
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn new_with_initial_nodes() {
+        let vocabulary = create_vocabulary();
+        {
+            let initial_nodes = vec![
+                (
+                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new("kamome"), 840),
+                    100,
+                ),
+                (
+                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new("local415"), 570),
+                    500,
+                ),
+            ];
+            let mut lattice =
+                Lattice::new_with_initial_nodes(vocabulary.as_ref(), initial_nodes).unwrap();
+            assert_eq!(lattice.step_count(), 2);
+            assert_eq!(lattice.step_span(1).unwrap(), (0, 0));
+            {
+                let nodes = lattice.nodes_at(1).unwrap();
+                assert_eq!(nodes.len(), 2);
+                assert_eq!(nodes[0].preceding_edge_costs(), &[100]);
+                assert_eq!(nodes[0].path_cost(), 940);
+                assert_eq!(nodes[1].path_cost(), 1070);
+            }
+
+            let _delta = lattice.push_back(to_input("[TosuOmuta]")).unwrap();
+            let _delta = lattice.push_back(to_input("[OmutaKumamoto]")).unwrap();
+            {
+                let nodes = lattice.nodes_at(2).unwrap();
+                assert_eq!(nodes.len(), 1);
+                assert_eq!(nodes[0].preceding_step(), 1);
+                assert_eq!(nodes[0].best_preceding_node(), 0);
+                assert_eq!(nodes[0].path_cost(), 2400);
+            }
+            let eos_node = lattice.settle().unwrap();
+
+            let paths = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()))
+                .map(|path| {
+                    let values = path
+                        .nodes()
+                        .iter()
+                        .filter_map(|node| node.value())
+                        .map(|value| *value.downcast_ref::<&str>().unwrap())
+                        .collect::<Vec<_>>();
+                    (values, path.cost())
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(
+                paths,
+                [
+                    (vec!["kamome", "local815"], 3620),
+                    (vec!["local415", "local815"], 3750),
+                    (vec!["kamome", "local813", "local817"], 4250),
+                    (vec!["local415", "local813", "local817"], 4380),
+                ]
+            );
+        }
+        {
+            let result = Lattice::new_with_initial_nodes(vocabulary.as_ref(), Vec::new());
+            assert!(result.is_err());
+        }
+        {
+            let result =
+                Lattice::new_with_initial_nodes(vocabulary.as_ref(), vec![(Entry::BosEos, 0)]);
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn step_count() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        assert_eq!(lattice.step_count(), 1);
 
-    use super::*;
+        let result1 = lattice.push_back(to_input("[HakataTosu]"));
+        assert!(result1.is_ok());
+        assert_eq!(lattice.step_count(), 2);
 
-    fn to_input(string: &str) -> Box<dyn Input> {
-        Box::new(crate::string_input::StringInput::new(string.to_string()))
+        let result2 = lattice.push_back(to_input("[TosuOmuta]"));
+        assert!(result2.is_ok());
+        assert_eq!(lattice.step_count(), 3);
+
+        let result3 = lattice.push_back(to_input("[OmutaKumamoto]"));
+        assert!(result3.is_ok());
+        assert_eq!(lattice.step_count(), 4);
     }
 
-    /*
-                   +------------------mizuho/sakura/tsubame-------------------+
-                   |                path cost: 4270/3220/2990                 |
-                   |                                                          |
-                   +------------ariake/rapid811------------+                  |
-                   |          path cost: 2850/2010         |                  |
-                   |                                       |                  |
-        BOS--(Hakata)--kamome/local415--(Tosu)--local813--(Omuta)--local817--(Kumamoto)--EOS
-                     path cost: 1640/1370   |   pc: 2830           pc: 3160   |     path cost:3390
-                                            |                                 |
-                                            +------------local815-------------+
-                                                      path cost: 3550
+    #[test]
+    fn nodes_at() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let _result = lattice.push_back(to_input("[TosuOmuta]"));
+        let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
 
-        (0) 3390  BOS - tsubame - EOS
-            [ sakura(3620),   local817(3760), local815(4050), mizuho(4670)   ]
-        (1) 3620  BOS - sakura - EOS
-            [ local817(3760), local815(4050), mizuho(4670)                   ]
-        (2) 3760  BOS - rapid811 - local817 - EOS
-            [ local815(4050), ariake(4600),   mizuho(4670),   local813(4680) ]
-        (3) 4050  BOS - local415 - local815 - EOS
-            [ kamome(4320),   ariake(4600),   mizuho(4670),   local813(4680) ]
-        (4) 4320  BOS - kamome - local815 - EOS
-            [ ariake(4600),   mizuho(4670),   local813(4680)                 ]
-        (5) 4600  BOS - ariake - local817 - EOS
-            [ mizuho(4670),   local813(4680)                                 ]
-        (6) 4670  BOS - mizuho - EOS
-            [ local813(4680)                                                 ]
-        (7) 4680  BOS - local415 - local813 - local817 - EOS
-            [ kamome(4950)                                                   ]
-        (8) 4950  BOS - kamome - local813 - local817 - EOS
-            [                                                                ]
-        (9) ----  -
-            [                                                                ]
-    */
-    fn entries() -> Vec<(String, Vec<Entry>)> {
-        vec![
-            (
-                String::from("[HakataTosu][TosuOmuta][OmutaKumamoto]"),
-                vec![
-                    Entry::new(
-                        Rc::from(to_input("Hakata-Tosu-Omuta-Kumamoto")),
-                        Rc::new("mizuho"),
-                        3670,
-                    ),
-                    Entry::new(
-                        Rc::from(to_input("Hakata-Tosu-Omuta-Kumamoto")),
-                        Rc::new("sakura"),
-                        2620,
-                    ),
-                    Entry::new(
-                        Rc::from(to_input("Hakata-Tosu-Omuta-Kumamoto")),
-                        Rc::new("tsubame"),
-                        2390,
-                    ),
-                ],
-            ),
-            (
-                String::from("[HakataTosu][TosuOmuta]"),
-                vec![
-                    Entry::new(
-                        Rc::from(to_input("Hakata-Tosu-Omuta")),
-                        Rc::new("ariake"),
-                        2150,
-                    ),
-                    Entry::new(
-                        Rc::from(to_input("Hakata-Tosu-Omuta")),
-                        Rc::new("rapid811"),
-                        1310,
-                    ),
-                ],
-            ),
-            (
-                String::from("[HakataTosu]"),
-                vec![
-                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new("kamome"), 840),
-                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new("local415"), 570),
-                ],
-            ),
-            (
-                String::from("[TosuOmuta]"),
-                vec![Entry::new(
-                    Rc::from(to_input("Tosu-Omuta")),
-                    Rc::new("local813"),
-                    860,
-                )],
-            ),
-            (
-                String::from("[TosuOmuta][OmutaKumamoto]"),
-                vec![Entry::new(
-                    Rc::from(to_input("Tosu-Omuta-Kumamoto")),
-                    Rc::new("local815"),
-                    1680,
-                )],
-            ),
-            (
-                String::from("[OmutaKumamoto]"),
-                vec![Entry::new(
-                    Rc::from(to_input("Omuta-Kumamoto")),
-                    Rc::new("local817"),
-                    950,
-                )],
-            ),
-        ]
+        {
+            let nodes = lattice.nodes_at(0);
+            assert!(nodes.is_ok());
+            let nodes = nodes.unwrap();
+
+            assert_eq!(nodes.len(), 1);
+            let preceding_edge_costs = Rc::new(Vec::new());
+            assert_eq!(
+                nodes[0].value().is_some(),
+                Node::bos(preceding_edge_costs).value().is_some()
+            );
+            for (i, n) in nodes.iter().enumerate() {
+                assert_eq!(n.index_in_step(), i);
+            }
+        }
+        {
+            let nodes = lattice.nodes_at(1);
+            assert!(nodes.is_ok());
+            let nodes = nodes.unwrap();
+
+            assert_eq!(nodes.len(), 2);
+            assert_eq!(
+                nodes[0].value().unwrap().downcast_ref::<&str>().unwrap(),
+                &"kamome"
+            );
+            assert_eq!(
+                nodes[1].value().unwrap().downcast_ref::<&str>().unwrap(),
+                &"local415"
+            );
+            for (i, n) in nodes.iter().enumerate() {
+                assert_eq!(n.index_in_step(), i);
+            }
+        }
+        {
+            let nodes = lattice.nodes_at(2);
+            assert!(nodes.is_ok());
+            let nodes = nodes.unwrap();
+
+            assert_eq!(nodes.len(), 3);
+            assert_eq!(
+                nodes[0].value().unwrap().downcast_ref::<&str>().unwrap(),
+                &"ariake"
+            );
+            assert_eq!(
+                nodes[1].value().unwrap().downcast_ref::<&str>().unwrap(),
+                &"rapid811"
+            );
+            assert_eq!(
+                nodes[2].value().unwrap().downcast_ref::<&str>().unwrap(),
+                &"local813"
+            );
+            for (i, n) in nodes.iter().enumerate() {
+                assert_eq!(n.index_in_step(), i);
+            }
+        }
+        {
+            let nodes = lattice.nodes_at(3);
+            assert!(nodes.is_ok());
+            let nodes = nodes.unwrap();
+
+            assert_eq!(nodes.len(), 5);
+            assert_eq!(
+                nodes[0].value().unwrap().downcast_ref::<&str>().unwrap(),
+                &"mizuho"
+            );
+            assert_eq!(
+                nodes[1].value().unwrap().downcast_ref::<&str>().unwrap(),
+                &"sakura"
+            );
+            assert_eq!(
+                nodes[2].value().unwrap().downcast_ref::<&str>().unwrap(),
+                &"tsubame"
+            );
+            assert_eq!(
+                nodes[3].value().unwrap().downcast_ref::<&str>().unwrap(),
+                &"local815"
+            );
+            assert_eq!(
+                nodes[4].value().unwrap().downcast_ref::<&str>().unwrap(),
+                &"local817"
+            );
+            for (i, n) in nodes.iter().enumerate() {
+                assert_eq!(n.index_in_step(), i);
+            }
+        }
+        {
+            let nodes = lattice.nodes_at(4);
+            assert!(nodes.is_err());
+        }
+    }
+
+    #[test]
+    fn last_step_nodes() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+
+        assert_eq!(lattice.last_step_nodes().len(), 1);
+
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let _result = lattice.push_back(to_input("[TosuOmuta]"));
+
+        let nodes = lattice.last_step_nodes();
+        assert_eq!(nodes, lattice.nodes_at(lattice.step_count() - 1).unwrap());
+    }
+
+    #[test]
+    fn connection_cache_hit_count() {
+        let vocabulary = create_vocabulary();
+        {
+            let mut lattice =
+                Lattice::new_with_connection_cache_capacity(vocabulary.as_ref(), 10000);
+            assert_eq!(lattice.connection_cache_hit_count(), 0);
+
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _result = lattice.push_back(to_input("[TosuOmuta]"));
+            let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+            let eos_node = lattice.settle().unwrap();
+            assert_eq!(lattice.connection_cache_hit_count(), 0);
+
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _result = lattice.push_back(to_input("[TosuOmuta]"));
+            let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+            let _eos_node = lattice.settle().unwrap();
+            assert_eq!(lattice.connection_cache_hit_count(), 12);
+
+            let mut uncached_lattice = Lattice::new(vocabulary.as_ref());
+            let _result = uncached_lattice.push_back(to_input("[HakataTosu]"));
+            let _result = uncached_lattice.push_back(to_input("[TosuOmuta]"));
+            let _result = uncached_lattice.push_back(to_input("[OmutaKumamoto]"));
+            let uncached_eos_node = uncached_lattice.settle().unwrap();
+            assert_eq!(uncached_eos_node, eos_node);
+        }
+        {
+            let mut lattice = Lattice::new_with_connection_cache_capacity(vocabulary.as_ref(), 0);
+
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _eos_node = lattice.settle().unwrap();
+            assert_eq!(lattice.connection_cache_hit_count(), 0);
+        }
+    }
+
+    #[test]
+    fn connection_cache_miss_count() {
+        let vocabulary = create_vocabulary();
+        {
+            let mut lattice =
+                Lattice::new_with_connection_cache_capacity(vocabulary.as_ref(), 10000);
+            assert_eq!(lattice.connection_cache_miss_count(), 0);
+
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _result = lattice.push_back(to_input("[TosuOmuta]"));
+            let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+            let _eos_node = lattice.settle().unwrap();
+            assert_eq!(lattice.connection_cache_miss_count(), 19);
+        }
+        {
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _eos_node = lattice.settle().unwrap();
+            assert_eq!(lattice.connection_cache_miss_count(), 0);
+        }
     }
 
-    fn connections() -> Vec<((Entry, Entry), i32)> {
-        vec![
-            (
-                (
-                    Entry::BosEos,
-                    Entry::new(
-                        Rc::from(to_input("Hakata-Tosu-Omuta-Kumamoto")),
-                        Rc::new(""),
-                        0,
-                    ),
-                ),
-                600,
-            ),
-            (
-                (
-                    Entry::BosEos,
-                    Entry::new(Rc::from(to_input("Hakata-Tosu-Omuta")), Rc::new(""), 0),
-                ),
-                700,
-            ),
-            (
-                (
-                    Entry::BosEos,
-                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new(""), 0),
-                ),
-                800,
-            ),
-            ((Entry::BosEos, Entry::BosEos), 8000),
-            (
-                (
-                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new(""), 0),
-                    Entry::new(Rc::from(to_input("Tosu-Omuta-Kumamoto")), Rc::new(""), 0),
-                ),
-                500,
-            ),
-            (
-                (
-                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new(""), 0),
-                    Entry::new(Rc::from(to_input("Tosu-Omuta")), Rc::new(""), 0),
-                ),
-                600,
-            ),
-            (
-                (
-                    Entry::new(Rc::from(to_input("Hakata-Tosu")), Rc::new(""), 0),
-                    Entry::BosEos,
-                ),
-                6000,
-            ),
-            (
-                (
-                    Entry::new(Rc::from(to_input("Hakata-Tosu-Omuta")), Rc::new(""), 0),
-                    Entry::new(Rc::from(to_input("Omuta-Kumamoto")), Rc::new(""), 0),
-                ),
-                200,
-            ),
-            (
-                (
-                    Entry::new(Rc::from(to_input("Hakata-Tosu-Omuta")), Rc::new(""), 0),
-                    Entry::BosEos,
-                ),
-                2000,
-            ),
-            (
-                (
-                    Entry::new(Rc::from(to_input("Tosu-Omuta")), Rc::new(""), 0),
-                    Entry::new(Rc::from(to_input("Omuta-Kumamoto")), Rc::new(""), 0),
-                ),
-                300,
-            ),
-            (
-                (
-                    Entry::new(Rc::from(to_input("Tosu-Omuta")), Rc::new(""), 0),
-                    Entry::BosEos,
-                ),
-                3000,
-            ),
-            (
-                (
-                    Entry::new(
-                        Rc::from(to_input("Hakata-Tosu-Omuta-Kumamoto")),
-                        Rc::new(""),
-                        0,
-                    ),
-                    Entry::BosEos,
-                ),
-                400,
-            ),
-            (
-                (
-                    Entry::new(Rc::from(to_input("Tosu-Omuta-Kumamoto")), Rc::new(""), 0),
-                    Entry::BosEos,
-                ),
-                500,
-            ),
-            (
-                (
-                    Entry::new(Rc::from(to_input("Omuta-Kumamoto")), Rc::new(""), 0),
-                    Entry::BosEos,
-                ),
-                600,
-            ),
-        ]
+    #[test]
+    fn stats() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        {
+            let stats = lattice.stats();
+
+            assert_eq!(stats.step_node_counts(), [1]);
+            assert_eq!(stats.evaluated_edge_count(), 0);
+            assert!(stats.edge_cost_distribution().is_empty());
+        }
+
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let _result = lattice.push_back(to_input("[TosuOmuta]"));
+        let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+        let _eos_node = lattice.settle().unwrap();
+        {
+            let stats = lattice.stats();
+
+            assert_eq!(stats.step_node_counts(), [1, 2, 3, 5]);
+            assert_eq!(stats.node_count(), 11);
+            let edge_count = lattice
+                .graph
+                .iter()
+                .flat_map(|step| step.nodes())
+                .map(|node| node.preceding_edge_costs().len())
+                .sum::<usize>();
+            assert_eq!(
+                stats.edge_cost_distribution().values().sum::<usize>(),
+                edge_count
+            );
+            assert_eq!(
+                stats.evaluated_edge_count(),
+                edge_count + lattice.nodes_at(3).unwrap().len()
+            );
+            assert!(stats.unreachable_edge_count() <= stats.evaluated_edge_count());
+        }
     }
 
-    fn entry_hash(entry: &Entry) -> u64 {
-        entry.key().map_or(0, |key| key.hash_value())
+    #[test]
+    fn cost_aggregation() {
+        let vocabulary = create_vocabulary();
+        {
+            let lattice = Lattice::new(vocabulary.as_ref());
+
+            assert_eq!(lattice.cost_aggregation(), CostAggregation::MinPlus);
+        }
+        {
+            let lattice = Lattice::new_with_cost_aggregation(
+                vocabulary.as_ref(),
+                CostAggregation::LogSum { temperature: 1.0 },
+            )
+            .unwrap();
+
+            assert_eq!(
+                lattice.cost_aggregation(),
+                CostAggregation::LogSum { temperature: 1.0 }
+            );
+        }
     }
 
-    fn entry_equal_to(one: &Entry, other: &Entry) -> bool {
-        if one.key().is_none() && other.key().is_none() {
-            return true;
+    #[test]
+    fn limits() {
+        let vocabulary = create_vocabulary();
+        let lattice = Lattice::new(vocabulary.as_ref());
+
+        assert_eq!(lattice.limits(), LatticeLimits::default());
+    }
+
+    #[test]
+    fn set_limits() {
+        let vocabulary = create_vocabulary();
+        {
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+            lattice.set_limits(LatticeLimits::new(Some(3), None, None));
+            assert_eq!(lattice.limits().max_step_count(), Some(3));
+
+            let _delta = lattice.push_back(to_input("[HakataTosu]")).unwrap();
+            let _delta = lattice.push_back(to_input("[TosuOmuta]")).unwrap();
+            let error = lattice.push_back(to_input("[OmutaKumamoto]")).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<LatticeError>().unwrap(),
+                LatticeError::TooManySteps { max_step_count: 3 }
+            ));
+            assert_eq!(lattice.step_count(), 3);
         }
-        if let Some(one_key) = one.key() {
-            if let Some(other_key) = other.key() {
-                return one_key.equal_to(other_key);
-            }
+        {
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+            lattice.set_limits(LatticeLimits::new(None, Some(4), None));
+
+            let _delta = lattice.push_back(to_input("[HakataTosu]")).unwrap();
+            let _delta = lattice.push_back(to_input("[TosuOmuta]")).unwrap();
+            let error = lattice.push_back(to_input("[OmutaKumamoto]")).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<LatticeError>().unwrap(),
+                LatticeError::TooManyNodesInStep {
+                    max_step_node_count: 4
+                }
+            ));
+            assert_eq!(lattice.step_count(), 3);
+        }
+        {
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+            lattice.set_limits(LatticeLimits::new(None, None, Some(5)));
+
+            let _delta = lattice.push_back(to_input("[HakataTosu]")).unwrap();
+            let error = lattice.push_back(to_input("[TosuOmuta]")).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<LatticeError>().unwrap(),
+                LatticeError::TooManyNodes { max_node_count: 5 }
+            ));
+            assert_eq!(lattice.stats().node_count(), 3);
+
+            lattice.set_limits(LatticeLimits::default());
+            let _delta = lattice.push_back(to_input("[TosuOmuta]")).unwrap();
+            assert_eq!(lattice.stats().node_count(), 6);
         }
-        false
     }
 
-    fn create_vocabulary() -> Box<dyn Vocabulary> {
-        Box::new(HashMapVocabulary::new(
-            entries(),
-            connections(),
-            &entry_hash,
-            &entry_equal_to,
-        ))
+    #[test]
+    fn deadline() {
+        let vocabulary = create_vocabulary();
+        let lattice = Lattice::new(vocabulary.as_ref());
+
+        assert!(lattice.deadline().is_none());
     }
 
-    fn create_empty_vocabulary() -> Box<dyn Vocabulary> {
-        Box::new(HashMapVocabulary::new(
-            Vec::new(),
-            Vec::new(),
-            &entry_hash,
-            &entry_equal_to,
-        ))
+    #[test]
+    fn set_deadline() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        let _delta = lattice.push_back(to_input("[HakataTosu]")).unwrap();
+
+        let deadline = Instant::now();
+        lattice.set_deadline(Some(deadline));
+        assert_eq!(lattice.deadline(), Some(deadline));
+        {
+            let error = lattice.push_back(to_input("[TosuOmuta]")).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<LatticeError>().unwrap(),
+                LatticeError::DeadlineExceeded
+            ));
+            assert_eq!(lattice.step_count(), 2);
+            assert_eq!(lattice.input().unwrap().length(), 12);
+        }
+        {
+            let error = lattice.settle().unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<LatticeError>().unwrap(),
+                LatticeError::DeadlineExceeded
+            ));
+        }
+
+        lattice.set_deadline(None);
+        let _delta = lattice.push_back(to_input("[TosuOmuta]")).unwrap();
+        assert_eq!(lattice.settle().unwrap().path_cost(), 4010);
     }
 
     #[test]
-    fn new() {
+    fn set_value_formatter() {
         let vocabulary = create_vocabulary();
-        let _lattice = Lattice::new(vocabulary.as_ref());
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+
+        let value_formatter = |_: &dyn Any| String::from("value");
+        lattice.set_value_formatter(&value_formatter);
     }
 
     #[test]
-    fn step_count() {
+    fn set_node_cost_adjuster() {
+        let vocabulary = create_empty_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+
+        let node_cost_adjuster = |entry: &Entry| match entry
+            .value()
+            .unwrap()
+            .downcast_ref::<String>()
+            .unwrap()
+            .as_str()
+        {
+            "kamome" => 1000,
+            "local415" => -100,
+            _ => i32::MAX,
+        };
+        lattice.set_node_cost_adjuster(&node_cost_adjuster);
+
+        let entries = ["kamome", "local415", "ariake"]
+            .into_iter()
+            .map(|value| {
+                Entry::new(
+                    Rc::new(crate::string_input::StringInput::new(String::from(
+                        "[HakataTosu]",
+                    ))),
+                    Rc::new(String::from(value)),
+                    600,
+                )
+            })
+            .collect::<Vec<_>>();
+        let _delta = lattice
+            .push_back_with_entries(to_input("[HakataTosu]"), entries)
+            .unwrap();
+
+        let nodes = lattice.nodes_at(1).unwrap();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0].node_cost(), 1600);
+        assert_eq!(nodes[1].node_cost(), 500);
+        assert_eq!(nodes[2].node_cost(), i32::MAX);
+    }
+
+    #[test]
+    fn set_higher_order_scorer() {
         let vocabulary = create_vocabulary();
         let mut lattice = Lattice::new(vocabulary.as_ref());
-        assert_eq!(lattice.step_count(), 1);
 
-        let result1 = lattice.push_back(to_input("[HakataTosu]"));
-        assert!(result1.is_ok());
-        assert_eq!(lattice.step_count(), 2);
+        let value_of = |node: &Node| {
+            node.value()
+                .map_or("BOS", |value| *value.downcast_ref::<&str>().unwrap())
+        };
+        let higher_order_scorer = |context: &[&Node], entry: &Entry| {
+            assert!(!context.is_empty() && context.len() <= 2);
+            let context = context
+                .iter()
+                .map(|&node| value_of(node))
+                .collect::<Vec<_>>();
+            let next = entry
+                .value()
+                .map_or("EOS", |value| *value.downcast_ref::<&str>().unwrap());
+            match (context.as_slice(), next) {
+                (["local415", "local813"], "local817") => 2000,
+                (["BOS", "kamome"], "local815") => -1000,
+                (["kamome", "local815"], "EOS") => -500,
+                (["BOS", "tsubame"], "EOS") => i32::MAX,
+                _ => 0,
+            }
+        };
+        lattice.set_higher_order_scorer(2, &higher_order_scorer);
 
-        let result2 = lattice.push_back(to_input("[TosuOmuta]"));
-        assert!(result2.is_ok());
-        assert_eq!(lattice.step_count(), 3);
+        let _delta = lattice.push_back(to_input("[HakataTosu]")).unwrap();
+        let _delta = lattice.push_back(to_input("[TosuOmuta]")).unwrap();
+        let _delta = lattice.push_back(to_input("[OmutaKumamoto]")).unwrap();
+        let eos_node = lattice.settle().unwrap();
 
-        let result3 = lattice.push_back(to_input("[OmutaKumamoto]"));
-        assert!(result3.is_ok());
-        assert_eq!(lattice.step_count(), 4);
+        let exact_cost_of = |nodes: &[Node]| {
+            let mut cost = 0;
+            for i in 1..nodes.len() {
+                let entry = Entry::from(&nodes[i]);
+                let context = nodes[i.saturating_sub(2)..i].iter().collect::<Vec<_>>();
+                let score = higher_order_scorer(&context, &entry);
+                let connection_cost = vocabulary
+                    .find_connection(&nodes[i - 1], &entry)
+                    .unwrap()
+                    .cost();
+                cost = Lattice::add_cost(
+                    cost,
+                    Lattice::add_cost(
+                        Lattice::add_cost(connection_cost, score),
+                        nodes[i].node_cost(),
+                    ),
+                );
+            }
+            cost
+        };
+        // The costs are exact, but the order is not strictly ascending.
+        let paths = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()))
+            .map(|path| {
+                assert_eq!(path.cost(), exact_cost_of(path.nodes()));
+                let values = path
+                    .nodes()
+                    .iter()
+                    .filter(|node| node.value().is_some())
+                    .map(value_of)
+                    .collect::<Vec<_>>();
+                (values, path.cost())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                (vec!["kamome", "local815"], 2820),
+                (vec!["sakura"], 3620),
+                (vec!["rapid811", "local817"], 3760),
+                (vec!["local415", "local815"], 4050),
+                (vec!["ariake", "local817"], 4600),
+                (vec!["mizuho"], 4670),
+                (vec!["local415", "local813", "local817"], 6680),
+                (vec!["kamome", "local813", "local817"], 4950),
+            ]
+        );
+    }
+
+    #[test]
+    fn higher_order_cost_delta() {
+        let vocabulary = create_vocabulary();
+        let value_of = |node: &Node| {
+            node.value()
+                .map_or("BOS", |value| *value.downcast_ref::<&str>().unwrap())
+        };
+
+        // Finds a node preceding EOS and two of its preceding nodes.
+        let (node_value, best_value, other_value) = {
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+            let _delta = lattice.push_back(to_input("[HakataTosu]")).unwrap();
+            let _delta = lattice.push_back(to_input("[TosuOmuta]")).unwrap();
+            let _delta = lattice.push_back(to_input("[OmutaKumamoto]")).unwrap();
+            let eos_node = lattice.settle().unwrap();
+            let node = lattice
+                .nodes_at(eos_node.preceding_step())
+                .unwrap()
+                .iter()
+                .find(|node| lattice.nodes_at(node.preceding_step()).unwrap().len() > 1)
+                .unwrap();
+            let preceding_nodes = lattice.nodes_at(node.preceding_step()).unwrap();
+            let other_index = usize::from(node.best_preceding_node() == 0);
+            (
+                value_of(node),
+                value_of(&preceding_nodes[node.best_preceding_node()]),
+                value_of(&preceding_nodes[other_index]),
+            )
+        };
+
+        let cost_delta = |best_score: i32, other_score: i32| {
+            let higher_order_scorer = |context: &[&Node], entry: &Entry| {
+                let context = context
+                    .iter()
+                    .map(|&node| value_of(node))
+                    .collect::<Vec<_>>();
+                match (context.as_slice(), entry.value()) {
+                    ([preceding, node], None) if *node == node_value => {
+                        if *preceding == best_value {
+                            best_score
+                        } else if *preceding == other_value {
+                            other_score
+                        } else {
+                            0
+                        }
+                    }
+                    _ => 0,
+                }
+            };
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+            lattice.set_higher_order_scorer(2, &higher_order_scorer);
+            let _delta = lattice.push_back(to_input("[HakataTosu]")).unwrap();
+            let _delta = lattice.push_back(to_input("[TosuOmuta]")).unwrap();
+            let _delta = lattice.push_back(to_input("[OmutaKumamoto]")).unwrap();
+            let eos_node = lattice.settle().unwrap();
+            let node = lattice
+                .nodes_at(eos_node.preceding_step())
+                .unwrap()
+                .iter()
+                .find(|node| value_of(node) == node_value)
+                .unwrap()
+                .clone();
+            let other_index = lattice
+                .nodes_at(node.preceding_step())
+                .unwrap()
+                .iter()
+                .position(|node| value_of(node) == other_value)
+                .unwrap();
+            lattice.higher_order_cost_delta(&[eos_node, node], other_index)
+        };
+
+        assert_eq!(cost_delta(100, -200), -300);
+        assert_eq!(cost_delta(i32::MAX, -200), i32::MAX);
+        assert_eq!(cost_delta(0, i32::MAX), i32::MAX);
+        assert_eq!(cost_delta(i32::MIN + 1, i32::MAX - 1), i32::MAX - 1);
+    }
+
+    #[test]
+    fn format_value() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        let value = String::from("Kumamoto");
+
+        assert_eq!(lattice.format_value(&value), "Any { .. }");
+
+        let value_formatter = |value: &dyn Any| {
+            value
+                .downcast_ref::<String>()
+                .map_or_else(|| String::from("?"), |value| value.to_uppercase())
+        };
+        lattice.set_value_formatter(&value_formatter);
+        assert_eq!(lattice.format_value(&value), "KUMAMOTO");
+        assert_eq!(lattice.format_value(&42), "?");
+    }
+
+    #[test]
+    fn input() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        assert!(lattice.input().is_none());
+
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let _result = lattice.push_back(to_input("[TosuOmuta]"));
+
+        let input = lattice.input().unwrap();
+        assert_eq!(
+            input
+                .downcast_ref::<crate::string_input::StringInput>()
+                .unwrap()
+                .value(),
+            "[HakataTosu][TosuOmuta]"
+        );
+    }
+
+    #[test]
+    fn step_span() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let _result = lattice.push_back(to_input("[TosuOmuta]"));
+        let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+
+        assert_eq!(lattice.step_span(0).unwrap(), (0, 0));
+        assert_eq!(lattice.step_span(1).unwrap(), (0, 12));
+        assert_eq!(lattice.step_span(2).unwrap(), (12, 23));
+        assert_eq!(lattice.step_span(3).unwrap(), (23, 38));
+        assert!(lattice.step_span(4).is_err());
     }
 
     #[test]
-    fn nodes_at() {
+    fn node_id() {
         let vocabulary = create_vocabulary();
         let mut lattice = Lattice::new(vocabulary.as_ref());
         let _result = lattice.push_back(to_input("[HakataTosu]"));
         let _result = lattice.push_back(to_input("[TosuOmuta]"));
-        let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
 
         {
-            let nodes = lattice.nodes_at(0);
-            assert!(nodes.is_ok());
-            let nodes = nodes.unwrap();
+            let bos = &lattice.nodes_at(0).unwrap()[0];
+            assert_eq!(lattice.node_id(bos).unwrap(), NodeId::new(0, 0));
+        }
+        {
+            let node = &lattice.nodes_at(2).unwrap()[1];
+            assert_eq!(lattice.node_id(node).unwrap(), NodeId::new(2, 1));
+        }
+        {
+            let eos = lattice.settle().unwrap();
+            assert_eq!(lattice.node_id(&eos).unwrap(), NodeId::new(3, usize::MAX));
+        }
+        {
+            let eos = Node::eos(3, Rc::new(vec![0]), 0, 0);
+            assert!(lattice.node_id(&eos).is_err());
+        }
+    }
 
-            assert_eq!(nodes.len(), 1);
-            let preceding_edge_costs = Rc::new(Vec::new());
-            assert_eq!(
-                nodes[0].value().is_some(),
-                Node::bos(preceding_edge_costs).value().is_some()
-            );
-            for (i, n) in nodes.iter().enumerate() {
-                assert_eq!(n.index_in_step(), i);
-            }
+    #[test]
+    fn push_back() {
+        {
+            let vocabulary = create_vocabulary();
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+
+            let result1 = lattice.push_back(to_input("[HakataTosu]"));
+            assert!(result1.is_ok());
+            let result2 = lattice.push_back(to_input("[TosuOmuta]"));
+            assert!(result2.is_ok());
+            let result3 = lattice.push_back(to_input("[OmutaKumamoto]"));
+            assert!(result3.is_ok());
+
+            let delta = result3.unwrap();
+            assert_eq!(delta.step(), 3);
+            let nodes = lattice.last_step_nodes();
+            assert_eq!(delta.node_count(), nodes.len());
+            let best_path_cost = nodes.iter().map(Node::path_cost).min().unwrap();
+            assert_eq!(delta.best_path_cost(), best_path_cost);
+            assert_eq!(nodes[delta.best_node_index()].path_cost(), best_path_cost);
         }
         {
-            let nodes = lattice.nodes_at(1);
-            assert!(nodes.is_ok());
-            let nodes = nodes.unwrap();
+            let vocabulary = create_empty_vocabulary();
+            let mut lattice = Lattice::new(vocabulary.as_ref());
 
-            assert_eq!(nodes.len(), 2);
-            assert_eq!(
-                nodes[0].value().unwrap().downcast_ref::<&str>().unwrap(),
-                &"kamome"
-            );
-            assert_eq!(
-                nodes[1].value().unwrap().downcast_ref::<&str>().unwrap(),
-                &"local415"
+            let result = lattice.push_back(to_input("[HakataTosu]"));
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn push_back_with_vocabulary() {
+        {
+            let vocabulary = create_vocabulary();
+            let step_entries = vocabulary
+                .find_entries(to_input("[HakataTosu]").as_ref())
+                .unwrap()
+                .into_iter()
+                .take(1)
+                .collect::<Vec<_>>();
+            let step_vocabulary = HashMapVocabulary::new(
+                vec![(String::from("[HakataTosu]"), step_entries.clone())],
+                Vec::new(),
+                &entry_hash,
+                &entry_equal_to,
             );
-            for (i, n) in nodes.iter().enumerate() {
-                assert_eq!(n.index_in_step(), i);
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+
+            let result =
+                lattice.push_back_with_vocabulary(to_input("[HakataTosu]"), &step_vocabulary);
+            assert!(result.is_ok());
+            let result = lattice.push_back(to_input("[TosuOmuta]"));
+            assert!(result.is_ok());
+
+            let mut expected_lattice = Lattice::new(vocabulary.as_ref());
+            let _delta = expected_lattice
+                .push_back_with_entries(to_input("[HakataTosu]"), step_entries)
+                .unwrap();
+            let _delta = expected_lattice.push_back(to_input("[TosuOmuta]")).unwrap();
+
+            for step in 1..3 {
+                let nodes = lattice.nodes_at(step).unwrap();
+                let expected_nodes = expected_lattice.nodes_at(step).unwrap();
+                assert_eq!(nodes.len(), expected_nodes.len());
+                for (node, expected_node) in nodes.iter().zip(expected_nodes) {
+                    assert_eq!(node.path_cost(), expected_node.path_cost());
+                }
             }
         }
         {
-            let nodes = lattice.nodes_at(2);
-            assert!(nodes.is_ok());
-            let nodes = nodes.unwrap();
+            let vocabulary = create_vocabulary();
+            let step_vocabulary = create_empty_vocabulary();
+            let mut lattice = Lattice::new(vocabulary.as_ref());
 
-            assert_eq!(nodes.len(), 3);
+            let result = lattice
+                .push_back_with_vocabulary(to_input("[HakataTosu]"), step_vocabulary.as_ref());
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn push_back_with_entries() {
+        {
+            let vocabulary = create_vocabulary();
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+
+            let entries = vocabulary
+                .find_entries(to_input("[HakataTosu]").as_ref())
+                .unwrap();
+            let result = lattice.push_back_with_entries(to_input("[HakataTosu]"), entries);
+            assert!(result.is_ok());
+
+            let eos_node = lattice.settle().unwrap();
+            assert_eq!(eos_node.preceding_step(), 1);
+            assert_eq!(eos_node.best_preceding_node(), 1);
+            assert_eq!(eos_node.path_cost(), 7370);
+        }
+        {
+            let vocabulary = create_empty_vocabulary();
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+
+            let entries = vec![
+                Entry::new(
+                    Rc::new(crate::string_input::StringInput::new(String::from(
+                        "[HakataTosu]",
+                    ))),
+                    Rc::new(String::from("kamome")),
+                    600,
+                ),
+                Entry::new(
+                    Rc::new(crate::string_input::StringInput::new(String::from(
+                        "[HakataTosu]",
+                    ))),
+                    Rc::new(String::from("local415")),
+                    800,
+                ),
+            ];
+            let result = lattice.push_back_with_entries(to_input("[HakataTosu]"), entries);
+            assert!(result.is_ok());
+
+            let delta = result.unwrap();
+            assert_eq!(delta.step(), 1);
+            let nodes = lattice.nodes_at(1).unwrap();
+            assert_eq!(nodes.len(), 2);
+            assert_eq!(delta.node_count(), 2);
             assert_eq!(
-                nodes[0].value().unwrap().downcast_ref::<&str>().unwrap(),
-                &"ariake"
+                delta.best_path_cost(),
+                nodes[delta.best_node_index()].path_cost()
             );
             assert_eq!(
-                nodes[1].value().unwrap().downcast_ref::<&str>().unwrap(),
-                &"rapid811"
+                nodes[0].value().unwrap().downcast_ref::<String>().unwrap(),
+                "kamome"
             );
+            assert_eq!(nodes[0].preceding_step(), 0);
+            assert_eq!(nodes[0].node_cost(), 600);
             assert_eq!(
-                nodes[2].value().unwrap().downcast_ref::<&str>().unwrap(),
-                &"local813"
+                nodes[1].value().unwrap().downcast_ref::<String>().unwrap(),
+                "local415"
             );
-            for (i, n) in nodes.iter().enumerate() {
-                assert_eq!(n.index_in_step(), i);
+            assert_eq!(nodes[1].index_in_step(), 1);
+            assert_eq!(nodes[1].node_cost(), 800);
+        }
+        {
+            let vocabulary = create_vocabulary();
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+
+            let result = lattice.push_back_with_entries(to_input("[HakataTosu]"), Vec::new());
+            assert!(result.is_err());
+            assert_eq!(lattice.step_count(), 1);
+        }
+        {
+            let vocabulary = create_vocabulary();
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+
+            let result =
+                lattice.push_back_with_entries(to_input("[HakataTosu]"), vec![Entry::BosEos]);
+            assert!(result.is_err());
+            assert_eq!(lattice.step_count(), 1);
+        }
+        {
+            #[derive(Debug)]
+            struct FailingVocabulary {
+                vocabulary: Box<dyn Vocabulary>,
+            }
+
+            impl Vocabulary for FailingVocabulary {
+                fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>> {
+                    self.vocabulary.find_entries(key)
+                }
+
+                fn find_connection(&self, from: &Node, to: &Entry) -> Result<Connection> {
+                    if to
+                        .value()
+                        .and_then(|value| value.downcast_ref::<String>())
+                        .is_some_and(|value| value == "failing")
+                    {
+                        return Err(anyhow::anyhow!("no connection"));
+                    }
+                    self.vocabulary.find_connection(from, to)
+                }
             }
+
+            let vocabulary = FailingVocabulary {
+                vocabulary: create_vocabulary(),
+            };
+            let mut lattice = Lattice::new(&vocabulary);
+
+            let entries = vec![Entry::new(
+                Rc::new(crate::string_input::StringInput::new(String::from(
+                    "[HakataTosu]",
+                ))),
+                Rc::new(String::from("failing")),
+                600,
+            )];
+            let result = lattice.push_back_with_entries(to_input("[HakataTosu]"), entries);
+            assert!(result.is_err());
+            assert_eq!(lattice.step_count(), 1);
+
+            let result = lattice.push_back(to_input("[HakataTosu]"));
+            assert!(result.is_ok());
+            let eos_node = lattice.settle().unwrap();
+            assert_eq!(eos_node.path_cost(), 7370);
         }
+    }
+
+    #[test]
+    fn push_back_epsilon_entries() {
+        let epsilon_entry = || {
+            Entry::new(
+                Rc::new(crate::string_input::StringInput::new(String::new())),
+                Rc::new(String::from("<sep>")),
+                10,
+            )
+        };
+        let values_and_costs = |lattice: &Lattice<'_>, eos_node: Node, constraint| {
+            NBestIterator::new(lattice, eos_node, Box::new(constraint))
+                .map(|path| {
+                    let values = path
+                        .nodes()
+                        .iter()
+                        .filter_map(|node| node.value())
+                        .map(|value| value.downcast_ref::<String>().unwrap().clone())
+                        .collect::<Vec<_>>();
+                    (values, path.cost())
+                })
+                .collect::<Vec<_>>()
+        };
+
         {
-            let nodes = lattice.nodes_at(3);
-            assert!(nodes.is_ok());
-            let nodes = nodes.unwrap();
+            let vocabulary = UnicodeWordVocabulary::new();
+            let mut lattice = Lattice::new(&vocabulary);
+            let _delta = lattice.push_back(to_input("a")).unwrap();
+            let delta = lattice
+                .push_back_epsilon_entries(vec![epsilon_entry()])
+                .unwrap();
+            assert_eq!(delta.step(), 2);
+            assert_eq!(delta.node_count(), 1);
+            assert_eq!(delta.best_node_index(), 0);
+            let _delta = lattice.push_back(to_input("b")).unwrap();
 
-            assert_eq!(nodes.len(), 5);
-            assert_eq!(
-                nodes[0].value().unwrap().downcast_ref::<&str>().unwrap(),
-                &"mizuho"
-            );
-            assert_eq!(
-                nodes[1].value().unwrap().downcast_ref::<&str>().unwrap(),
-                &"sakura"
-            );
+            assert_eq!(lattice.step_count(), 4);
+            assert_eq!(lattice.step_span(2).unwrap(), (1, 1));
+            {
+                let nodes = lattice.nodes_at(2).unwrap();
+                assert_eq!(nodes.len(), 1);
+                assert_eq!(nodes[0].preceding_step(), 1);
+                assert_eq!(nodes[0].input_span(&lattice).unwrap(), (1, 1));
+            }
+            {
+                let nodes = lattice.nodes_at(3).unwrap();
+                let preceding_steps = nodes.iter().map(Node::preceding_step).collect::<Vec<_>>();
+                assert_eq!(preceding_steps, [0, 1, 2]);
+            }
+
+            let eos_node = lattice.settle().unwrap();
+            let paths = values_and_costs(&lattice, eos_node.clone(), Constraint::new());
             assert_eq!(
-                nodes[2].value().unwrap().downcast_ref::<&str>().unwrap(),
-                &"tsubame"
+                paths,
+                [
+                    (vec![String::from("ab")], 1),
+                    (vec![String::from("a"), String::from("b")], 2),
+                    (
+                        vec![String::from("a"), String::from("<sep>"), String::from("b")],
+                        12
+                    ),
+                ]
             );
+
+            let path = NBestIterator::new(&lattice, eos_node.clone(), Box::new(Constraint::new()))
+                .nth(2)
+                .unwrap();
+            let mut pattern: Vec<Box<dyn ConstraintElement>> = Vec::new();
+            for node in path.nodes() {
+                pattern.push(Box::new(NodeConstraintElement::new(node.clone())));
+            }
+            let constrained_paths =
+                values_and_costs(&lattice, eos_node, Constraint::new_with_pattern(pattern));
             assert_eq!(
-                nodes[3].value().unwrap().downcast_ref::<&str>().unwrap(),
-                &"local815"
+                constrained_paths,
+                [(
+                    vec![String::from("a"), String::from("<sep>"), String::from("b")],
+                    12
+                )]
             );
+        }
+        {
+            let vocabulary = UnicodeWordVocabulary::new();
+            let mut lattice = Lattice::new(&vocabulary);
+            let _delta = lattice.push_back(to_input("a")).unwrap();
+            let _delta = lattice
+                .push_back_epsilon_entries(vec![epsilon_entry()])
+                .unwrap();
+
+            let eos_node = lattice.settle().unwrap();
+            assert_eq!(eos_node.preceding_step(), 2);
+            let paths = values_and_costs(&lattice, eos_node, Constraint::new());
             assert_eq!(
-                nodes[4].value().unwrap().downcast_ref::<&str>().unwrap(),
-                &"local817"
+                paths,
+                [(vec![String::from("a"), String::from("<sep>")], 11)]
             );
-            for (i, n) in nodes.iter().enumerate() {
-                assert_eq!(n.index_in_step(), i);
+        }
+        {
+            let vocabulary = UnicodeWordVocabulary::new();
+            let mut lattice = Lattice::new(&vocabulary);
+            let _delta = lattice.push_back(to_input("a")).unwrap();
+            for _ in 0..Lattice::MAX_CONSECUTIVE_EPSILON_STEP_COUNT {
+                let _delta = lattice
+                    .push_back_epsilon_entries(vec![epsilon_entry()])
+                    .unwrap();
             }
+            let error = lattice
+                .push_back_epsilon_entries(vec![epsilon_entry()])
+                .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<LatticeError>().unwrap(),
+                LatticeError::TooManyEpsilonSteps
+            ));
+
+            let _delta = lattice.push_back(to_input("b")).unwrap();
+            let _delta = lattice
+                .push_back_epsilon_entries(vec![epsilon_entry()])
+                .unwrap();
         }
         {
-            let nodes = lattice.nodes_at(4);
-            assert!(nodes.is_err());
+            let vocabulary = UnicodeWordVocabulary::new();
+            let mut lattice = Lattice::new(&vocabulary);
+
+            assert!(lattice.push_back_epsilon_entries(Vec::new()).is_err());
+            assert!(lattice
+                .push_back_epsilon_entries(vec![Entry::BosEos])
+                .is_err());
         }
     }
 
     #[test]
-    fn push_back() {
-        {
-            let vocabulary = create_vocabulary();
-            let mut lattice = Lattice::new(vocabulary.as_ref());
+    fn recost() {
+        #[derive(Debug)]
+        struct AdjustableVocabulary {
+            vocabulary: Box<dyn Vocabulary>,
+            mizuho_connection_cost: Cell<i32>,
+        }
 
-            let result1 = lattice.push_back(to_input("[HakataTosu]"));
-            assert!(result1.is_ok());
-            let result2 = lattice.push_back(to_input("[TosuOmuta]"));
-            assert!(result2.is_ok());
-            let result3 = lattice.push_back(to_input("[OmutaKumamoto]"));
-            assert!(result3.is_ok());
+        impl Vocabulary for AdjustableVocabulary {
+            fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>> {
+                self.vocabulary.find_entries(key)
+            }
+
+            fn find_connection(&self, from: &Node, to: &Entry) -> Result<Connection> {
+                let connection = self.vocabulary.find_connection(from, to)?;
+                if to
+                    .value()
+                    .and_then(|value| value.downcast_ref::<&str>())
+                    .is_some_and(|value| *value == "mizuho")
+                {
+                    return Ok(Connection::new(Lattice::add_cost(
+                        connection.cost(),
+                        self.mizuho_connection_cost.get(),
+                    )));
+                }
+                Ok(connection)
+            }
         }
-        {
-            let vocabulary = create_empty_vocabulary();
-            let mut lattice = Lattice::new(vocabulary.as_ref());
 
-            let result = lattice.push_back(to_input("[HakataTosu]"));
-            assert!(result.is_err());
+        let push_back_all = |lattice: &mut Lattice<'_>| {
+            let _delta = lattice.push_back(to_input("[HakataTosu]")).unwrap();
+            let _delta = lattice.push_back(to_input("[TosuOmuta]")).unwrap();
+            let _delta = lattice.push_back(to_input("[OmutaKumamoto]")).unwrap();
+        };
+
+        let vocabulary = AdjustableVocabulary {
+            vocabulary: create_vocabulary(),
+            mizuho_connection_cost: Cell::new(0),
+        };
+        let mut lattice = Lattice::new(&vocabulary);
+        push_back_all(&mut lattice);
+        let eos_node = lattice.settle().unwrap();
+        let best_path_cost = eos_node.path_cost();
+
+        vocabulary.mizuho_connection_cost.set(-10000);
+        lattice.recost().unwrap();
+        let eos_node = lattice.settle().unwrap();
+
+        let mut expected_lattice = Lattice::new(&vocabulary);
+        push_back_all(&mut expected_lattice);
+        let expected_eos_node = expected_lattice.settle().unwrap();
+
+        assert!(eos_node.path_cost() < best_path_cost);
+        assert_eq!(eos_node.path_cost(), expected_eos_node.path_cost());
+        assert_eq!(
+            eos_node.best_preceding_node(),
+            expected_eos_node.best_preceding_node()
+        );
+        for step in 0..lattice.step_count() {
+            let nodes = lattice.nodes_at(step).unwrap();
+            let expected_nodes = expected_lattice.nodes_at(step).unwrap();
+            assert_eq!(nodes, expected_nodes);
         }
     }
 
@@ -705,4 +3154,152 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn settle_with_budget() {
+        {
+            let vocabulary = create_vocabulary();
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+
+            assert!(lattice.settle_with_budget(8000).unwrap().is_some());
+            assert!(lattice.settle_with_budget(7999).unwrap().is_none());
+
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _result = lattice.push_back(to_input("[TosuOmuta]"));
+            let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+            {
+                let eos_node = lattice.settle_with_budget(3390).unwrap().unwrap();
+
+                assert_eq!(eos_node.preceding_step(), 3);
+                assert_eq!(eos_node.best_preceding_node(), 2);
+                assert_eq!(eos_node.path_cost(), 3390);
+            }
+            assert!(lattice.settle_with_budget(3389).unwrap().is_none());
+        }
+        {
+            let vocabulary =
+                HashMapVocabulary::new(entries(), Vec::new(), &entry_hash, &entry_equal_to);
+            let mut lattice = Lattice::new(&vocabulary);
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let evaluated_edge_count = lattice.stats().evaluated_edge_count();
+
+            assert!(lattice.settle_with_budget(0).unwrap().is_none());
+            assert_eq!(lattice.stats().evaluated_edge_count(), evaluated_edge_count);
+        }
+    }
+
+    #[test]
+    fn sample_path() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let _result = lattice.push_back(to_input("[TosuOmuta]"));
+        let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+        let eos_node = lattice.settle().unwrap();
+
+        let n_best_paths =
+            NBestIterator::new(&lattice, eos_node.clone(), Box::new(Constraint::new()))
+                .collect::<Vec<_>>();
+        let mut seed = 42u64;
+        let mut rng = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+
+        {
+            let path = lattice.sample_path(&eos_node, 0.001, &mut rng).unwrap();
+
+            assert_eq!(path.cost(), n_best_paths[0].cost());
+            assert_eq!(path.nodes(), n_best_paths[0].nodes());
+        }
+        {
+            let mut sampled_costs = Vec::new();
+            for _ in 0..100 {
+                let path = lattice.sample_path(&eos_node, 1000.0, &mut rng).unwrap();
+
+                assert!(path.nodes().first().unwrap().is_bos());
+                let n_best_path = n_best_paths
+                    .iter()
+                    .find(|n_best_path| n_best_path.nodes() == path.nodes())
+                    .unwrap();
+                assert_eq!(path.cost(), n_best_path.cost());
+                sampled_costs.push(path.cost());
+            }
+            sampled_costs.sort();
+            sampled_costs.dedup();
+            assert!(sampled_costs.len() > 1);
+        }
+        {
+            let error = lattice.sample_path(&eos_node, 0.0, &mut rng).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<LatticeError>().unwrap(),
+                LatticeError::InvalidTemperature
+            ));
+        }
+        {
+            let vocabulary = create_empty_vocabulary();
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let eos_node = lattice.settle().unwrap();
+
+            let error = lattice.sample_path(&eos_node, 1.0, &mut rng).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<LatticeError>().unwrap(),
+                LatticeError::NoPath
+            ));
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn to_json() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let _result = lattice.push_back(to_input("[TosuOmuta]"));
+        let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+        let eos_node = lattice.settle().unwrap();
+
+        let value_to_json = |value: &dyn Any| match value.downcast_ref::<&str>() {
+            Some(value) => serde_json::Value::from(*value),
+            None => serde_json::Value::Null,
+        };
+        {
+            let json = lattice.to_json(&eos_node, &value_to_json, 2).unwrap();
+
+            let steps = json["steps"].as_array().unwrap();
+            assert_eq!(steps.len(), 4);
+            assert_eq!(steps[0]["span"], serde_json::json!([0, 0]));
+            assert_eq!(steps[1]["span"], serde_json::json!([0, 12]));
+            assert_eq!(
+                steps[0]["nodes"][0]["preceding_step"],
+                serde_json::Value::Null
+            );
+            let best_values = steps
+                .iter()
+                .flat_map(|step| step["nodes"].as_array().unwrap())
+                .filter(|node| node["best"] == true)
+                .map(|node| node["value"].clone())
+                .collect::<Vec<_>>();
+            assert_eq!(best_values, [serde_json::Value::Null, "tsubame".into()]);
+            assert_eq!(json["eos"]["path_cost"], 3390);
+            assert_eq!(json["eos"]["id"], serde_json::json!([4, null]));
+            let best_path = json["best_path"].as_array().unwrap();
+            assert_eq!(best_path.len(), 3);
+            assert_eq!(best_path[0], serde_json::json!([0, 0]));
+            assert_eq!(best_path[2], serde_json::json!([4, null]));
+            let paths = json["paths"].as_array().unwrap();
+            assert_eq!(paths.len(), 2);
+            assert_eq!(paths[0]["cost"], 3390);
+            assert_eq!(paths[0]["nodes"], json["best_path"]);
+            assert_eq!(paths[1]["cost"], 3620);
+        }
+        {
+            let json = lattice.to_json(&eos_node, &value_to_json, 0).unwrap();
+
+            assert!(json["paths"].as_array().unwrap().is_empty());
+        }
+    }
 }
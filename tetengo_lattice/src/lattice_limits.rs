@@ -0,0 +1,106 @@
+/*!
+ * Lattice limits.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+/**
+ * Lattice limits.
+ *
+ * Bounds the resources used by a lattice. None means no limit.
+ */
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LatticeLimits {
+    max_step_count: Option<usize>,
+    max_step_node_count: Option<usize>,
+    max_node_count: Option<usize>,
+}
+
+impl LatticeLimits {
+    /**
+     * Creates lattice limits.
+     *
+     * # Arguments
+     * * `max_step_count`      - The max step count, including the BOS step.
+     * * `max_step_node_count` - The max node count of a step.
+     * * `max_node_count`      - The max total node count, including the BOS node.
+     */
+    pub const fn new(
+        max_step_count: Option<usize>,
+        max_step_node_count: Option<usize>,
+        max_node_count: Option<usize>,
+    ) -> Self {
+        Self {
+            max_step_count,
+            max_step_node_count,
+            max_node_count,
+        }
+    }
+
+    /**
+     * Returns the max step count.
+     *
+     * # Returns
+     * The max step count, including the BOS step.
+     */
+    pub const fn max_step_count(&self) -> Option<usize> {
+        self.max_step_count
+    }
+
+    /**
+     * Returns the max node count of a step.
+     *
+     * # Returns
+     * The max node count of a step.
+     */
+    pub const fn max_step_node_count(&self) -> Option<usize> {
+        self.max_step_node_count
+    }
+
+    /**
+     * Returns the max total node count.
+     *
+     * # Returns
+     * The max total node count, including the BOS node.
+     */
+    pub const fn max_node_count(&self) -> Option<usize> {
+        self.max_node_count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new() {
+        let _limits = LatticeLimits::new(Some(10), Some(20), Some(100));
+    }
+
+    #[test]
+    fn max_step_count() {
+        assert_eq!(
+            LatticeLimits::new(Some(10), Some(20), Some(100)).max_step_count(),
+            Some(10)
+        );
+        assert!(LatticeLimits::default().max_step_count().is_none());
+    }
+
+    #[test]
+    fn max_step_node_count() {
+        assert_eq!(
+            LatticeLimits::new(Some(10), Some(20), Some(100)).max_step_node_count(),
+            Some(20)
+        );
+        assert!(LatticeLimits::default().max_step_node_count().is_none());
+    }
+
+    #[test]
+    fn max_node_count() {
+        assert_eq!(
+            LatticeLimits::new(Some(10), Some(20), Some(100)).max_node_count(),
+            Some(100)
+        );
+        assert!(LatticeLimits::default().max_node_count().is_none());
+    }
+}
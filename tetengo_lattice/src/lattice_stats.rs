@@ -0,0 +1,149 @@
+/*!
+ * Lattice statistics.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::collections::BTreeMap;
+
+/**
+ * Lattice statistics.
+ *
+ * Helps to diagnose why certain paths do not appear in the N-best output.
+ */
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LatticeStats {
+    step_node_counts: Vec<usize>,
+    evaluated_edge_count: usize,
+    unreachable_edge_count: usize,
+    edge_cost_distribution: BTreeMap<i32, usize>,
+}
+
+impl LatticeStats {
+    /**
+     * Creates lattice statistics.
+     *
+     * # Arguments
+     * * `step_node_counts`       - The node counts of the steps.
+     * * `evaluated_edge_count`   - The count of the evaluated edges.
+     * * `unreachable_edge_count` - The count of the evaluated edges whose costs are `i32::MAX`.
+     * * `edge_cost_distribution` - The counts of the preceding edges by cost.
+     */
+    pub const fn new(
+        step_node_counts: Vec<usize>,
+        evaluated_edge_count: usize,
+        unreachable_edge_count: usize,
+        edge_cost_distribution: BTreeMap<i32, usize>,
+    ) -> Self {
+        Self {
+            step_node_counts,
+            evaluated_edge_count,
+            unreachable_edge_count,
+            edge_cost_distribution,
+        }
+    }
+
+    /**
+     * Returns the node counts of the steps.
+     *
+     * # Returns
+     * The node counts of the steps.
+     */
+    pub fn step_node_counts(&self) -> &[usize] {
+        self.step_node_counts.as_slice()
+    }
+
+    /**
+     * Returns the total node count.
+     *
+     * # Returns
+     * The total node count.
+     */
+    pub fn node_count(&self) -> usize {
+        self.step_node_counts.iter().sum()
+    }
+
+    /**
+     * Returns the count of the evaluated edges.
+     *
+     * Includes the edges to the EOS nodes evaluated in the settlements.
+     *
+     * # Returns
+     * The count of the evaluated edges.
+     */
+    pub const fn evaluated_edge_count(&self) -> usize {
+        self.evaluated_edge_count
+    }
+
+    /**
+     * Returns the count of the evaluated edges whose costs are `i32::MAX`.
+     *
+     * Such edges are unreachable and never appear in the paths.
+     *
+     * # Returns
+     * The count of the unreachable edges.
+     */
+    pub const fn unreachable_edge_count(&self) -> usize {
+        self.unreachable_edge_count
+    }
+
+    /**
+     * Returns the distribution of the preceding edge costs.
+     *
+     * Counts the preceding edges of the nodes in the lattice by cost.
+     *
+     * # Returns
+     * The counts of the preceding edges by cost.
+     */
+    pub const fn edge_cost_distribution(&self) -> &BTreeMap<i32, usize> {
+        &self.edge_cost_distribution
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_stats() -> LatticeStats {
+        LatticeStats::new(
+            vec![1, 2, 3],
+            12,
+            4,
+            BTreeMap::from([(10, 3), (20, 2), (i32::MAX, 3)]),
+        )
+    }
+
+    #[test]
+    fn new() {
+        let _stats = make_stats();
+    }
+
+    #[test]
+    fn step_node_counts() {
+        assert_eq!(make_stats().step_node_counts(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn node_count() {
+        assert_eq!(make_stats().node_count(), 6);
+        assert_eq!(LatticeStats::default().node_count(), 0);
+    }
+
+    #[test]
+    fn evaluated_edge_count() {
+        assert_eq!(make_stats().evaluated_edge_count(), 12);
+    }
+
+    #[test]
+    fn unreachable_edge_count() {
+        assert_eq!(make_stats().unreachable_edge_count(), 4);
+    }
+
+    #[test]
+    fn edge_cost_distribution() {
+        let stats = make_stats();
+
+        assert_eq!(stats.edge_cost_distribution().len(), 3);
+        assert_eq!(stats.edge_cost_distribution()[&i32::MAX], 3);
+    }
+}
@@ -0,0 +1,165 @@
+/*!
+ * A layered vocabulary.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use anyhow::Result;
+
+use crate::connection::Connection;
+use crate::entry::Entry;
+use crate::input::Input;
+use crate::node::Node;
+use crate::vocabulary::Vocabulary;
+
+/**
+ * A layered vocabulary.
+ *
+ * Merges vocabularies such as a base dictionary, a user dictionary and a
+ * domain dictionary.
+ *
+ * The entries found in all the layers are concatenated in the order of the
+ * layers. The cost offset of a layer is added to the costs of its entries.
+ * An entry without a provenance gets the index of its layer as the
+ * provenance, so the paths can tell which layer each node came from.
+ *
+ * A connection is resolved through the first layer which knows it, that is,
+ * which returns a cost other than `i32::MAX`. The connection cost is
+ * `i32::MAX` when no layer knows it.
+ */
+#[derive(Debug)]
+pub struct LayeredVocabulary {
+    layers: Vec<(Box<dyn Vocabulary>, i32)>,
+}
+
+impl LayeredVocabulary {
+    /**
+     * Creates a layered vocabulary.
+     *
+     * # Arguments
+     * * `layers` - Pairs of a vocabulary and its cost offset, in priority
+     *   order.
+     */
+    pub const fn new(layers: Vec<(Box<dyn Vocabulary>, i32)>) -> Self {
+        Self { layers }
+    }
+
+    /**
+     * Returns the layer count.
+     *
+     * # Returns
+     * The layer count.
+     */
+    pub fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+}
+
+impl Vocabulary for LayeredVocabulary {
+    fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>> {
+        let mut entries = Vec::new();
+        for (layer_index, (vocabulary, cost_offset)) in self.layers.iter().enumerate() {
+            for entry in vocabulary.find_entries(key)? {
+                let cost = entry.cost().saturating_add(*cost_offset);
+                let entry = match (entry.provenance(), u16::try_from(layer_index)) {
+                    (None, Ok(provenance)) => entry.with_provenance(provenance),
+                    _ => entry,
+                };
+                entries.push(entry.with_cost(cost));
+            }
+        }
+        Ok(entries)
+    }
+
+    fn find_connection(&self, from: &Node, to: &Entry) -> Result<Connection> {
+        for (vocabulary, _) in &self.layers {
+            let connection = vocabulary.find_connection(from, to)?;
+            if connection.cost() != i32::MAX {
+                return Ok(connection);
+            }
+        }
+        Ok(Connection::new(i32::MAX))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::string_input::StringInput;
+    use crate::unicode_vocabulary::UnicodeWordVocabulary;
+
+    use super::*;
+
+    fn make_vocabulary() -> LayeredVocabulary {
+        LayeredVocabulary::new(vec![
+            (
+                Box::new(UnicodeWordVocabulary::new_with_costs(1, i32::MAX)),
+                0,
+            ),
+            (Box::new(UnicodeWordVocabulary::new_with_costs(2, 5)), 10),
+            (Box::new(UnicodeWordVocabulary::new_with_costs(3, 7)), -1),
+        ])
+    }
+
+    #[test]
+    fn new() {
+        let _vocabulary = make_vocabulary();
+    }
+
+    #[test]
+    fn layer_count() {
+        assert_eq!(make_vocabulary().layer_count(), 3);
+        assert_eq!(LayeredVocabulary::new(Vec::new()).layer_count(), 0);
+    }
+
+    #[test]
+    fn find_entries() {
+        let vocabulary = make_vocabulary();
+        {
+            let entries = vocabulary
+                .find_entries(&StringInput::new(String::from("fox")))
+                .unwrap();
+
+            let costs = entries.iter().map(Entry::cost).collect::<Vec<_>>();
+            assert_eq!(costs, [1, 12, 2]);
+            let provenances = entries.iter().map(Entry::provenance).collect::<Vec<_>>();
+            assert_eq!(provenances, [Some(0), Some(1), Some(2)]);
+            assert!(entries.iter().all(|entry| {
+                entry.value().unwrap().downcast_ref::<String>().unwrap() == "fox"
+            }));
+        }
+        {
+            let entries = vocabulary
+                .find_entries(&StringInput::new(String::from("The fox")))
+                .unwrap();
+
+            assert!(entries.is_empty());
+        }
+    }
+
+    #[test]
+    fn find_connection() {
+        let entry = Entry::new(
+            Rc::new(StringInput::new(String::from("fox"))),
+            Rc::new(String::from("fox")),
+            1,
+        );
+        let bos = Node::bos(Rc::new(Vec::new()));
+        {
+            let vocabulary = make_vocabulary();
+            let connection = vocabulary.find_connection(&bos, &entry).unwrap();
+
+            assert_eq!(connection.cost(), 5);
+        }
+        {
+            let vocabulary = LayeredVocabulary::new(vec![(
+                Box::new(UnicodeWordVocabulary::new_with_costs(1, i32::MAX)),
+                0,
+            )]);
+            let connection = vocabulary.find_connection(&bos, &entry).unwrap();
+
+            assert_eq!(connection.cost(), i32::MAX);
+        }
+    }
+}
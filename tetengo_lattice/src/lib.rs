@@ -7,29 +7,84 @@
 pub mod connection;
 pub mod constraint;
 pub mod constraint_element;
+pub mod cost;
+pub mod cost_aggregation;
 pub mod entry;
+pub mod entry_view;
 pub mod hash_map_vocabulary;
+#[cfg(feature = "inline-values")]
+pub mod inline_value;
 pub mod input;
+pub mod input_constraint_element;
 pub mod lattice;
+pub mod lattice_limits;
+pub mod lattice_stats;
+pub mod layered_vocabulary;
+pub mod matrix_vocabulary;
+#[cfg(feature = "mecab")]
+pub mod mecab_vocabulary;
 pub mod n_best_iterator;
+pub mod n_best_tree;
 pub mod node;
+pub mod node_annotations;
 pub mod node_constraint_element;
+pub mod node_id;
 pub mod path;
+pub mod schedule;
+pub mod step_delta;
 pub mod string_input;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+#[cfg(feature = "trie")]
+pub mod trie_vocabulary;
+pub mod typed_hash_map_vocabulary;
+pub mod typed_vocabulary;
+pub mod unicode_vocabulary;
 pub mod vocabulary;
+pub mod vocabulary_report;
 pub mod wildcard_constraint_element;
 
-pub use connection::Connection;
+pub use connection::{Connection, ConnectionLookup};
 pub use constraint::Constraint;
 pub use constraint_element::ConstraintElement;
+pub use cost::{CostError, CostScale};
+pub use cost_aggregation::CostAggregation;
 pub use entry::Entry;
-pub use hash_map_vocabulary::HashMapVocabulary;
+pub use entry_view::{EntryView, EntryViewError};
+pub use hash_map_vocabulary::{HashMapVocabulary, PrehashedConnection};
+#[cfg(feature = "inline-values")]
+pub use inline_value::InlineValue;
 pub use input::{Input, InputError};
+pub use input_constraint_element::InputConstraintElement;
 pub use lattice::Lattice;
-pub use n_best_iterator::NBestIterator;
+pub use lattice_limits::LatticeLimits;
+pub use lattice_stats::LatticeStats;
+pub use layered_vocabulary::LayeredVocabulary;
+pub use matrix_vocabulary::{ConnectionMatrix, ConnectionMatrixError, MatrixVocabulary};
+#[cfg(feature = "mecab")]
+pub use mecab_vocabulary::{MecabVocabulary, MecabVocabularyError, MecabWord};
+#[cfg(feature = "json")]
+pub use n_best_iterator::NBestIteratorStateError;
+pub use n_best_iterator::{Admissibility, NBestIterator, NBestIteratorState};
+pub use n_best_tree::{NBestTree, NBestTreeNode};
 pub use node::{Node, NodeError};
+pub use node_annotations::NodeAnnotations;
 pub use node_constraint_element::NodeConstraintElement;
-pub use path::Path;
-pub use string_input::StringInput;
+pub use node_id::NodeId;
+pub use path::{CostKind, CostMismatch, Path, PathCostBreakdown, PathDiffSegment, TransitionCost};
+pub use schedule::{Schedule, ScheduleEntry, ScheduleError, WaitingCost, MINUTES_PER_DAY};
+pub use step_delta::StepDelta;
+pub use string_input::{NormalizationForm, StringInput};
+#[cfg(feature = "test-support")]
+pub use test_support::{
+    CostDistribution, Random, SyntheticVocabulary, SyntheticVocabularyBuilder,
+    SyntheticVocabularyError,
+};
+#[cfg(feature = "trie")]
+pub use trie_vocabulary::{TrieVocabulary, TrieVocabularyError};
+pub use typed_hash_map_vocabulary::TypedHashMapVocabulary;
+pub use typed_vocabulary::{TypedVocabulary, TypedVocabularyAdapter};
+pub use unicode_vocabulary::UnicodeWordVocabulary;
 pub use vocabulary::Vocabulary;
+pub use vocabulary_report::VocabularyReport;
 pub use wildcard_constraint_element::WildcardConstraintElement;
@@ -0,0 +1,425 @@
+/*!
+ * A matrix vocabulary.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::any::type_name_of_val;
+use std::fmt::{self, Debug, Formatter};
+
+use anyhow::Result;
+
+use crate::connection::{Connection, ConnectionLookup};
+use crate::entry::Entry;
+use crate::input::Input;
+use crate::node::Node;
+use crate::vocabulary::Vocabulary;
+
+/**
+ * A connection matrix error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum ConnectionMatrixError {
+    /**
+     * The cost count does not match the matrix size.
+     */
+    #[error("the cost count does not match the matrix size")]
+    SizeMismatch,
+}
+
+/**
+ * A connection matrix.
+ *
+ * Holds the connection costs indexed by the right context ID of the origin
+ * and the left context ID of the destination.
+ */
+#[derive(Clone, Debug)]
+pub struct ConnectionMatrix {
+    right_id_count: usize,
+    left_id_count: usize,
+    costs: Vec<i32>,
+}
+
+impl ConnectionMatrix {
+    /**
+     * Creates a connection matrix.
+     *
+     * The cost of the connection from a right context ID `r` to a left context
+     * ID `l` is `costs[r + right_id_count * l]`.
+     *
+     * # Arguments
+     * * `right_id_count` - The count of the right context IDs of the origins.
+     * * `left_id_count`  - The count of the left context IDs of the destinations.
+     * * `costs`          - Costs.
+     *
+     * # Errors
+     * * When the cost count is not `right_id_count * left_id_count`.
+     */
+    pub fn new(right_id_count: usize, left_id_count: usize, costs: Vec<i32>) -> Result<Self> {
+        if costs.len() != right_id_count * left_id_count {
+            return Err(ConnectionMatrixError::SizeMismatch.into());
+        }
+        Ok(Self {
+            right_id_count,
+            left_id_count,
+            costs,
+        })
+    }
+
+    /**
+     * Returns the count of the right context IDs of the origins.
+     *
+     * # Returns
+     * The count of the right context IDs.
+     */
+    pub const fn right_id_count(&self) -> usize {
+        self.right_id_count
+    }
+
+    /**
+     * Returns the count of the left context IDs of the destinations.
+     *
+     * # Returns
+     * The count of the left context IDs.
+     */
+    pub const fn left_id_count(&self) -> usize {
+        self.left_id_count
+    }
+
+    /**
+     * Returns the connection cost.
+     *
+     * # Arguments
+     * * `right_id` - A right context ID of an origin.
+     * * `left_id`  - A left context ID of a destination.
+     *
+     * # Returns
+     * The connection cost. Or None when the IDs are out of the matrix.
+     */
+    pub fn cost(&self, right_id: u16, left_id: u16) -> Option<i32> {
+        let (right_id, left_id) = (right_id as usize, left_id as usize);
+        if right_id >= self.right_id_count || left_id >= self.left_id_count {
+            return None;
+        }
+        Some(self.costs[right_id + self.right_id_count * left_id])
+    }
+}
+
+impl ConnectionLookup for ConnectionMatrix {
+    fn cost(&self, right_id: u16, left_id: u16) -> i32 {
+        ConnectionMatrix::cost(self, right_id, left_id).unwrap_or(i32::MAX)
+    }
+}
+
+/**
+ * A matrix vocabulary.
+ *
+ * Finds the entries with another vocabulary and the connections with a
+ * connection lookup, such as a connection matrix, indexed by the context IDs
+ * of the entries. The connection data stays small even when the vocabulary is
+ * large.
+ *
+ * The context IDs of BOS and EOS are `BOS_EOS_CONTEXT_ID`. The connection cost
+ * is `i32::MAX` when the entries have no context ID.
+ *
+ * # Type Parameters
+ * * `L` - A connection lookup type.
+ */
+pub struct MatrixVocabulary<L: ConnectionLookup = ConnectionMatrix> {
+    entry_vocabulary: Box<dyn Vocabulary>,
+    connection_lookup: L,
+}
+
+impl MatrixVocabulary {
+    /// The context ID of BOS and EOS.
+    pub const BOS_EOS_CONTEXT_ID: u16 = 0;
+
+    /**
+     * Creates a matrix vocabulary.
+     *
+     * The connection cost is `i32::MAX` when the context IDs are out of the
+     * matrix.
+     *
+     * # Arguments
+     * * `entry_vocabulary`  - A vocabulary to find entries. Its connections are
+     *   not used.
+     * * `connection_matrix` - A connection matrix.
+     */
+    pub const fn new(
+        entry_vocabulary: Box<dyn Vocabulary>,
+        connection_matrix: ConnectionMatrix,
+    ) -> Self {
+        Self::new_with_connection_lookup(entry_vocabulary, connection_matrix)
+    }
+
+    /**
+     * Returns the connection matrix.
+     *
+     * # Returns
+     * The connection matrix.
+     */
+    pub const fn connection_matrix(&self) -> &ConnectionMatrix {
+        &self.connection_lookup
+    }
+}
+
+impl<L: ConnectionLookup> MatrixVocabulary<L> {
+    /**
+     * Creates a matrix vocabulary with a connection lookup.
+     *
+     * # Arguments
+     * * `entry_vocabulary`  - A vocabulary to find entries. Its connections are
+     *   not used.
+     * * `connection_lookup` - A connection lookup.
+     */
+    pub const fn new_with_connection_lookup(
+        entry_vocabulary: Box<dyn Vocabulary>,
+        connection_lookup: L,
+    ) -> Self {
+        Self {
+            entry_vocabulary,
+            connection_lookup,
+        }
+    }
+
+    /**
+     * Returns the connection lookup.
+     *
+     * # Returns
+     * The connection lookup.
+     */
+    pub const fn connection_lookup(&self) -> &L {
+        &self.connection_lookup
+    }
+}
+
+impl<L: ConnectionLookup> Debug for MatrixVocabulary<L> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MatrixVocabulary")
+            .field("entry_vocabulary", &self.entry_vocabulary)
+            .field(
+                "connection_lookup",
+                &type_name_of_val(&self.connection_lookup),
+            )
+            .finish()
+    }
+}
+
+impl<L: ConnectionLookup> Vocabulary for MatrixVocabulary<L> {
+    fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>> {
+        self.entry_vocabulary.find_entries(key)
+    }
+
+    fn find_connection(&self, from: &Node, to: &Entry) -> Result<Connection> {
+        let right_id = match from {
+            Node::Middle(_) => from.right_id(),
+            Node::Bos(_) | Node::Eos(_) => Some(<MatrixVocabulary>::BOS_EOS_CONTEXT_ID),
+        };
+        let left_id = match to {
+            Entry::Middle(_) => to.left_id(),
+            Entry::BosEos => Some(<MatrixVocabulary>::BOS_EOS_CONTEXT_ID),
+        };
+        let cost = right_id
+            .zip(left_id)
+            .map_or(i32::MAX, |(right_id, left_id)| {
+                self.connection_lookup.cost(right_id, left_id)
+            });
+        Ok(Connection::new(cost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::constraint::Constraint;
+    use crate::hash_map_vocabulary::HashMapVocabulary;
+    use crate::lattice::Lattice;
+    use crate::n_best_iterator::NBestIterator;
+    use crate::string_input::StringInput;
+
+    use super::*;
+
+    const NOUN: u16 = 1;
+
+    const PARTICLE: u16 = 2;
+
+    fn entry(surface: &str, cost: i32, context_id: u16) -> Entry {
+        Entry::new_with_context_ids(
+            Rc::new(StringInput::new(String::from(surface))),
+            Rc::new(String::from(surface)),
+            cost,
+            context_id,
+            context_id,
+        )
+    }
+
+    fn make_connection_matrix() -> ConnectionMatrix {
+        #[rustfmt::skip]
+        let costs = vec![
+            // from: BOS/EOS, NOUN, PARTICLE
+            0,    0,    0,    // to: BOS/EOS
+            10,   50,   10,   // to: NOUN
+            100,  10,   100,  // to: PARTICLE
+        ];
+        ConnectionMatrix::new(3, 3, costs).unwrap()
+    }
+
+    fn make_vocabulary() -> MatrixVocabulary {
+        let entry_vocabulary = HashMapVocabulary::new(
+            vec![
+                (String::from("にわ"), vec![entry("にわ", 10, NOUN)]),
+                (String::from("に"), vec![entry("に", 10, PARTICLE)]),
+                (String::from("わ"), vec![entry("わ", 10, NOUN)]),
+            ],
+            Vec::new(),
+            &|entry| entry.key().map_or(0, |key| key.hash_value()),
+            &|one, other| match (one.key(), other.key()) {
+                (Some(one_key), Some(other_key)) => one_key.equal_to(other_key),
+                (None, None) => true,
+                _ => false,
+            },
+        );
+        MatrixVocabulary::new(Box::new(entry_vocabulary), make_connection_matrix())
+    }
+
+    #[test]
+    fn new() {
+        let _vocabulary = make_vocabulary();
+    }
+
+    #[test]
+    fn connection_matrix() {
+        let vocabulary = make_vocabulary();
+
+        assert_eq!(vocabulary.connection_matrix().right_id_count(), 3);
+        assert_eq!(vocabulary.connection_matrix().left_id_count(), 3);
+    }
+
+    #[test]
+    fn new_with_connection_lookup() {
+        let entry_vocabulary = make_vocabulary().entry_vocabulary;
+        let _vocabulary = MatrixVocabulary::new_with_connection_lookup(
+            entry_vocabulary,
+            |right_id: u16, left_id: u16| (right_id * 10 + left_id) as i32,
+        );
+    }
+
+    #[test]
+    fn connection_lookup() {
+        let vocabulary = make_vocabulary();
+
+        assert_eq!(
+            ConnectionLookup::cost(vocabulary.connection_lookup(), NOUN, PARTICLE),
+            10
+        );
+        assert_eq!(
+            ConnectionLookup::cost(vocabulary.connection_lookup(), NOUN, 3),
+            i32::MAX
+        );
+    }
+
+    #[test]
+    fn find_entries() {
+        let vocabulary = make_vocabulary();
+
+        let entries = vocabulary
+            .find_entries(&StringInput::new(String::from("にわ")))
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].left_id(), Some(NOUN));
+    }
+
+    #[test]
+    fn find_connection() {
+        let vocabulary = make_vocabulary();
+        let bos = Node::bos(Rc::new(Vec::new()));
+        let noun =
+            Node::new_with_entry(&entry("わ", 10, NOUN), 0, 0, Rc::new(vec![0]), 0, 0).unwrap();
+
+        assert_eq!(
+            vocabulary
+                .find_connection(&bos, &entry("に", 10, PARTICLE))
+                .unwrap()
+                .cost(),
+            100
+        );
+        assert_eq!(
+            vocabulary
+                .find_connection(&noun, &entry("に", 10, PARTICLE))
+                .unwrap()
+                .cost(),
+            10
+        );
+        assert_eq!(
+            vocabulary
+                .find_connection(&noun, &Entry::BosEos)
+                .unwrap()
+                .cost(),
+            0
+        );
+        assert_eq!(
+            vocabulary
+                .find_connection(&noun, &entry("に", 10, 3))
+                .unwrap()
+                .cost(),
+            i32::MAX
+        );
+        {
+            let entry_vocabulary = make_vocabulary().entry_vocabulary;
+            let vocabulary = MatrixVocabulary::new_with_connection_lookup(
+                entry_vocabulary,
+                |right_id: u16, left_id: u16| (right_id * 10 + left_id) as i32,
+            );
+            assert_eq!(
+                vocabulary
+                    .find_connection(&noun, &entry("に", 10, PARTICLE))
+                    .unwrap()
+                    .cost(),
+                12
+            );
+            assert_eq!(
+                vocabulary
+                    .find_connection(&bos, &entry("に", 10, 3))
+                    .unwrap()
+                    .cost(),
+                (MatrixVocabulary::BOS_EOS_CONTEXT_ID * 10 + 3) as i32
+            );
+        }
+        let entry_without_ids = Entry::new(
+            Rc::new(StringInput::new(String::from("に"))),
+            Rc::new(String::from("に")),
+            10,
+        );
+        assert_eq!(
+            vocabulary
+                .find_connection(&noun, &entry_without_ids)
+                .unwrap()
+                .cost(),
+            i32::MAX
+        );
+    }
+
+    #[test]
+    fn lattice() {
+        let vocabulary = make_vocabulary();
+        let mut lattice = Lattice::new(&vocabulary);
+        for input in ["に", "わ"] {
+            let _delta = lattice
+                .push_back(Box::new(StringInput::new(String::from(input))))
+                .unwrap();
+        }
+        let eos_node = lattice.settle().unwrap();
+
+        let path = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()))
+            .next()
+            .unwrap();
+        let values = path
+            .nodes()
+            .iter()
+            .filter_map(|node| node.value())
+            .map(|value| value.downcast_ref::<String>().unwrap().as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(values, ["にわ"]);
+    }
+}
@@ -0,0 +1,937 @@
+/*!
+ * A MeCab vocabulary.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::io::Read;
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use crate::connection::Connection;
+use crate::entry::Entry;
+use crate::input::Input;
+use crate::node::Node;
+use crate::string_input::StringInput;
+use crate::vocabulary::Vocabulary;
+
+/**
+ * A MeCab vocabulary error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum MecabVocabularyError {
+    /**
+     * The dictionary is broken.
+     */
+    #[error("the dictionary is broken")]
+    BrokenDictionary,
+
+    /**
+     * The charset of the dictionary is not supported.
+     */
+    #[error("the charset of the dictionary is not supported")]
+    UnsupportedCharset,
+
+    /**
+     * The connection matrix is broken.
+     */
+    #[error("the connection matrix is broken")]
+    BrokenMatrix,
+
+    /**
+     * The character property is broken.
+     */
+    #[error("the character property is broken")]
+    BrokenCharProperty,
+}
+
+/**
+ * A MeCab word.
+ *
+ * The value of the entries that a MeCab vocabulary finds.
+ */
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MecabWord {
+    surface: String,
+    left_id: u16,
+    right_id: u16,
+    pos_id: u16,
+    feature: String,
+}
+
+impl MecabWord {
+    /**
+     * Creates a MeCab word.
+     *
+     * # Arguments
+     * * `surface`  - A surface.
+     * * `left_id`  - A left context ID.
+     * * `right_id` - A right context ID.
+     * * `pos_id`   - A part-of-speech ID.
+     * * `feature`  - A feature.
+     */
+    pub const fn new(
+        surface: String,
+        left_id: u16,
+        right_id: u16,
+        pos_id: u16,
+        feature: String,
+    ) -> Self {
+        Self {
+            surface,
+            left_id,
+            right_id,
+            pos_id,
+            feature,
+        }
+    }
+
+    /**
+     * Returns the surface.
+     *
+     * # Returns
+     * The surface.
+     */
+    pub fn surface(&self) -> &str {
+        self.surface.as_str()
+    }
+
+    /**
+     * Returns the left context ID.
+     *
+     * # Returns
+     * The left context ID.
+     */
+    pub const fn left_id(&self) -> u16 {
+        self.left_id
+    }
+
+    /**
+     * Returns the right context ID.
+     *
+     * # Returns
+     * The right context ID.
+     */
+    pub const fn right_id(&self) -> u16 {
+        self.right_id
+    }
+
+    /**
+     * Returns the part-of-speech ID.
+     *
+     * # Returns
+     * The part-of-speech ID.
+     */
+    pub const fn pos_id(&self) -> u16 {
+        self.pos_id
+    }
+
+    /**
+     * Returns the feature.
+     *
+     * # Returns
+     * The feature. For example, "名詞,固有名詞,地域,一般,*,*,熊本,クマモト,クマモト".
+     */
+    pub fn feature(&self) -> &str {
+        self.feature.as_str()
+    }
+}
+
+const DICTIONARY_MAGIC_ID: u32 = 0xEF718F77;
+
+const DICTIONARY_VERSION: u32 = 102;
+
+const DICTIONARY_HEADER_SIZE: usize = size_of::<u32>() * 10 + 32;
+
+const DOUBLE_ARRAY_UNIT_SIZE: usize = size_of::<i32>() + size_of::<u32>();
+
+const TOKEN_SIZE: usize = size_of::<u16>() * 4 + size_of::<u32>() * 2;
+
+#[derive(Clone, Copy, Debug)]
+struct Token {
+    left_id: u16,
+    right_id: u16,
+    pos_id: u16,
+    cost: i16,
+    feature: u32,
+}
+
+#[derive(Debug)]
+struct Dictionary {
+    double_array: Vec<(i32, u32)>,
+    tokens: Vec<Token>,
+    features: Vec<u8>,
+}
+
+impl Dictionary {
+    fn new(reader: &mut dyn Read) -> Result<Self> {
+        let mut bytes = Vec::new();
+        let _ = reader.read_to_end(&mut bytes)?;
+        if bytes.len() < DICTIONARY_HEADER_SIZE {
+            return Err(MecabVocabularyError::BrokenDictionary.into());
+        }
+
+        let magic = read_u32(&bytes, 0);
+        if (magic ^ DICTIONARY_MAGIC_ID) as usize != bytes.len() {
+            return Err(MecabVocabularyError::BrokenDictionary.into());
+        }
+        if read_u32(&bytes, 4) != DICTIONARY_VERSION {
+            return Err(MecabVocabularyError::BrokenDictionary.into());
+        }
+        let double_array_size = read_u32(&bytes, 24) as usize;
+        let token_size = read_u32(&bytes, 28) as usize;
+        let feature_size = read_u32(&bytes, 32) as usize;
+        if double_array_size % DOUBLE_ARRAY_UNIT_SIZE != 0
+            || token_size % TOKEN_SIZE != 0
+            || DICTIONARY_HEADER_SIZE + double_array_size + token_size + feature_size > bytes.len()
+        {
+            return Err(MecabVocabularyError::BrokenDictionary.into());
+        }
+        let charset = null_terminated(&bytes[40..DICTIONARY_HEADER_SIZE]);
+        if !charset.eq_ignore_ascii_case(b"utf-8") && !charset.eq_ignore_ascii_case(b"utf8") {
+            return Err(MecabVocabularyError::UnsupportedCharset.into());
+        }
+
+        let double_array_offset = DICTIONARY_HEADER_SIZE;
+        let double_array = (0..double_array_size / DOUBLE_ARRAY_UNIT_SIZE)
+            .map(|i| {
+                let offset = double_array_offset + DOUBLE_ARRAY_UNIT_SIZE * i;
+                (
+                    read_u32(&bytes, offset) as i32,
+                    read_u32(&bytes, offset + 4),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let token_offset = double_array_offset + double_array_size;
+        let tokens = (0..token_size / TOKEN_SIZE)
+            .map(|i| {
+                let offset = token_offset + TOKEN_SIZE * i;
+                Token {
+                    left_id: read_u16(&bytes, offset),
+                    right_id: read_u16(&bytes, offset + 2),
+                    pos_id: read_u16(&bytes, offset + 4),
+                    cost: read_u16(&bytes, offset + 6) as i16,
+                    feature: read_u32(&bytes, offset + 8),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let feature_offset = token_offset + token_size;
+        let features = bytes[feature_offset..feature_offset + feature_size].to_vec();
+
+        Ok(Self {
+            double_array,
+            tokens,
+            features,
+        })
+    }
+
+    fn exact_match(&self, key: &[u8]) -> Option<u32> {
+        let mut base = self.double_array.first()?.0;
+        for &c in key {
+            let index = base as usize + c as usize + 1;
+            let &(next_base, check) = self.double_array.get(index)?;
+            if check != base as u32 {
+                return None;
+            }
+            base = next_base;
+        }
+        let &(value, check) = self.double_array.get(base as usize)?;
+        if check != base as u32 || value >= 0 {
+            return None;
+        }
+        Some((-value - 1) as u32)
+    }
+
+    fn words_of(&self, surface: &str, key: &[u8]) -> Result<Vec<(MecabWord, i32)>> {
+        let Some(value) = self.exact_match(key) else {
+            return Ok(Vec::new());
+        };
+        let first = (value >> 8) as usize;
+        let last = first + (value & 0xFF) as usize;
+        let Some(tokens) = self.tokens.get(first..last) else {
+            return Err(MecabVocabularyError::BrokenDictionary.into());
+        };
+        tokens
+            .iter()
+            .map(|token| {
+                let Some(feature) = self.features.get(token.feature as usize..) else {
+                    return Err(MecabVocabularyError::BrokenDictionary.into());
+                };
+                let feature = String::from_utf8(null_terminated(feature).to_vec())?;
+                Ok((
+                    MecabWord::new(
+                        surface.to_string(),
+                        token.left_id,
+                        token.right_id,
+                        token.pos_id,
+                        feature,
+                    ),
+                    token.cost as i32,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct Matrix {
+    left_size: usize,
+    right_size: usize,
+    costs: Vec<i16>,
+}
+
+impl Matrix {
+    fn new(reader: &mut dyn Read) -> Result<Self> {
+        let mut bytes = Vec::new();
+        let _ = reader.read_to_end(&mut bytes)?;
+        if bytes.len() < size_of::<u16>() * 2 {
+            return Err(MecabVocabularyError::BrokenMatrix.into());
+        }
+
+        let left_size = read_u16(&bytes, 0) as usize;
+        let right_size = read_u16(&bytes, 2) as usize;
+        if bytes.len() != size_of::<u16>() * (2 + left_size * right_size) {
+            return Err(MecabVocabularyError::BrokenMatrix.into());
+        }
+        let costs = (0..left_size * right_size)
+            .map(|i| read_u16(&bytes, size_of::<u16>() * (2 + i)) as i16)
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            left_size,
+            right_size,
+            costs,
+        })
+    }
+
+    fn cost(&self, right_id_of_left: u16, left_id_of_right: u16) -> Result<i32> {
+        let (right_id_of_left, left_id_of_right) =
+            (right_id_of_left as usize, left_id_of_right as usize);
+        if right_id_of_left >= self.left_size || left_id_of_right >= self.right_size {
+            return Err(MecabVocabularyError::BrokenMatrix.into());
+        }
+        Ok(self.costs[right_id_of_left + self.left_size * left_id_of_right] as i32)
+    }
+}
+
+const CHAR_CATEGORY_NAME_SIZE: usize = 32;
+
+const CHAR_INFO_COUNT: usize = 0xFFFF;
+
+const MAX_GROUPING_SIZE: usize = 24;
+
+#[derive(Clone, Copy, Debug)]
+struct CharInfo(u32);
+
+impl CharInfo {
+    const fn types(&self) -> u32 {
+        self.0 & 0x0003FFFF
+    }
+
+    const fn default_type(&self) -> usize {
+        ((self.0 >> 18) & 0xFF) as usize
+    }
+
+    const fn length(&self) -> usize {
+        ((self.0 >> 26) & 0x0F) as usize
+    }
+
+    const fn group(&self) -> bool {
+        (self.0 >> 30) & 0x01 != 0
+    }
+
+    const fn is_kind_of(&self, other: &CharInfo) -> bool {
+        self.types() & other.types() != 0
+    }
+}
+
+#[derive(Debug)]
+struct CharProperty {
+    category_names: Vec<String>,
+    char_infos: Vec<CharInfo>,
+}
+
+impl CharProperty {
+    fn new(reader: &mut dyn Read) -> Result<Self> {
+        let mut bytes = Vec::new();
+        let _ = reader.read_to_end(&mut bytes)?;
+        if bytes.len() < size_of::<u32>() {
+            return Err(MecabVocabularyError::BrokenCharProperty.into());
+        }
+
+        let category_count = read_u32(&bytes, 0) as usize;
+        let char_info_offset = size_of::<u32>() + CHAR_CATEGORY_NAME_SIZE * category_count;
+        if bytes.len() != char_info_offset + size_of::<u32>() * CHAR_INFO_COUNT {
+            return Err(MecabVocabularyError::BrokenCharProperty.into());
+        }
+        let category_names = (0..category_count)
+            .map(|i| {
+                let offset = size_of::<u32>() + CHAR_CATEGORY_NAME_SIZE * i;
+                let name = null_terminated(&bytes[offset..offset + CHAR_CATEGORY_NAME_SIZE]);
+                String::from_utf8(name.to_vec())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let char_infos = (0..CHAR_INFO_COUNT)
+            .map(|i| CharInfo(read_u32(&bytes, char_info_offset + size_of::<u32>() * i)))
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            category_names,
+            char_infos,
+        })
+    }
+
+    fn char_info(&self, c: char) -> CharInfo {
+        self.char_infos
+            .get(c as usize)
+            .copied()
+            .unwrap_or(self.char_infos[0])
+    }
+
+    fn category_name(&self, char_info: &CharInfo) -> Option<&str> {
+        self.category_names
+            .get(char_info.default_type())
+            .map(String::as_str)
+    }
+}
+
+#[derive(Debug)]
+struct UnknownWordDefinition {
+    char_property: CharProperty,
+    dictionary: Dictionary,
+}
+
+impl UnknownWordDefinition {
+    fn words_of(&self, surface: &str) -> Result<Vec<(MecabWord, i32)>> {
+        let mut chars = surface.chars();
+        let Some(first) = chars.next() else {
+            return Ok(Vec::new());
+        };
+        let char_info = self.char_property.char_info(first);
+        if !chars.all(|c| self.char_property.char_info(c).is_kind_of(&char_info)) {
+            return Ok(Vec::new());
+        }
+        let length = surface.chars().count();
+        if length > char_info.length() && !(char_info.group() && length <= MAX_GROUPING_SIZE) {
+            return Ok(Vec::new());
+        }
+
+        let Some(category_name) = self.char_property.category_name(&char_info) else {
+            return Err(MecabVocabularyError::BrokenCharProperty.into());
+        };
+        self.dictionary.words_of(surface, category_name.as_bytes())
+    }
+}
+
+/**
+ * A MeCab vocabulary.
+ *
+ * The vocabulary reads the binary dictionary files compiled by mecab-dict-index,
+ * such as IPAdic. Only the dictionaries in UTF-8 are supported.
+ *
+ * The keys must be `StringInput`s. The value of an entry is a `MecabWord`, and
+ * the cost of an entry is the word cost in the dictionary. The keys are compared
+ * with the surfaces as raw strings, so the inputs should be normalized in NFC
+ * with `StringInput::new_normalized`.
+ *
+ * When the character property and the unknown word dictionary are given, the
+ * vocabulary also finds unknown words. An unknown word is a key whose
+ * characters are all in the category of its first character, and whose length
+ * is within the one defined for the category. The invoke flag of the category
+ * is not considered since the known words following the key are not known yet.
+ */
+#[derive(Debug)]
+pub struct MecabVocabulary {
+    dictionary: Dictionary,
+    matrix: Matrix,
+    unknown_word_definition: Option<UnknownWordDefinition>,
+}
+
+impl MecabVocabulary {
+    /**
+     * Creates a MeCab vocabulary.
+     *
+     * # Arguments
+     * * `sys_dic`    - A reader of sys.dic.
+     * * `matrix_bin` - A reader of matrix.bin.
+     *
+     * # Errors
+     * * When it fails to read the files.
+     * * When the files are broken.
+     */
+    pub fn new(sys_dic: &mut dyn Read, matrix_bin: &mut dyn Read) -> Result<Self> {
+        Ok(Self {
+            dictionary: Dictionary::new(sys_dic)?,
+            matrix: Matrix::new(matrix_bin)?,
+            unknown_word_definition: None,
+        })
+    }
+
+    /**
+     * Creates a MeCab vocabulary with an unknown word definition.
+     *
+     * # Arguments
+     * * `sys_dic`    - A reader of sys.dic.
+     * * `matrix_bin` - A reader of matrix.bin.
+     * * `char_bin`   - A reader of char.bin.
+     * * `unk_dic`    - A reader of unk.dic.
+     *
+     * # Errors
+     * * When it fails to read the files.
+     * * When the files are broken.
+     */
+    pub fn new_with_unknown_words(
+        sys_dic: &mut dyn Read,
+        matrix_bin: &mut dyn Read,
+        char_bin: &mut dyn Read,
+        unk_dic: &mut dyn Read,
+    ) -> Result<Self> {
+        Ok(Self {
+            dictionary: Dictionary::new(sys_dic)?,
+            matrix: Matrix::new(matrix_bin)?,
+            unknown_word_definition: Some(UnknownWordDefinition {
+                char_property: CharProperty::new(char_bin)?,
+                dictionary: Dictionary::new(unk_dic)?,
+            }),
+        })
+    }
+}
+
+impl Vocabulary for MecabVocabulary {
+    fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>> {
+        let Some(key) = key.downcast_ref::<StringInput>() else {
+            return Ok(Vec::new());
+        };
+
+        let mut words = self
+            .dictionary
+            .words_of(key.value(), key.value().as_bytes())?;
+        if let Some(unknown_word_definition) = &self.unknown_word_definition {
+            words.extend(unknown_word_definition.words_of(key.value())?);
+        }
+
+        let entry_key: Rc<dyn Input> = Rc::new(key.clone());
+        Ok(words
+            .into_iter()
+            .map(|(word, cost)| Entry::new(entry_key.clone(), Rc::new(word), cost))
+            .collect())
+    }
+
+    fn find_connection(&self, from: &Node, to: &Entry) -> Result<Connection> {
+        let right_id_of_from = match from.value() {
+            Some(value) => match value.downcast_ref::<MecabWord>() {
+                Some(word) => word.right_id(),
+                None => return Ok(Connection::new(i32::MAX)),
+            },
+            None => 0,
+        };
+        let left_id_of_to = match to.value() {
+            Some(value) => match value.downcast_ref::<MecabWord>() {
+                Some(word) => word.left_id(),
+                None => return Ok(Connection::new(i32::MAX)),
+            },
+            None => 0,
+        };
+        Ok(Connection::new(
+            self.matrix.cost(right_id_of_from, left_id_of_to)?,
+        ))
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+fn null_terminated(bytes: &[u8]) -> &[u8] {
+    let length = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    &bytes[..length]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::constraint::Constraint;
+    use crate::lattice::Lattice;
+    use crate::n_best_iterator::NBestIterator;
+
+    use super::*;
+
+    fn build_double_array(keys: &[(&[u8], i32)]) -> Vec<(i32, u32)> {
+        let mut units = vec![(0, 0)];
+        let mut used = vec![true];
+        build_double_array_node(keys, 0, 0, &mut units, &mut used);
+        units
+    }
+
+    fn build_double_array_node(
+        keys: &[(&[u8], i32)],
+        depth: usize,
+        index: usize,
+        units: &mut Vec<(i32, u32)>,
+        used: &mut Vec<bool>,
+    ) {
+        let mut children = Vec::<(usize, Vec<(&[u8], i32)>)>::new();
+        for &(key, value) in keys {
+            let code = key.get(depth).map_or(0, |&c| c as usize + 1);
+            match children.last_mut() {
+                Some((last_code, subkeys)) if *last_code == code => subkeys.push((key, value)),
+                _ => children.push((code, vec![(key, value)])),
+            }
+        }
+        let base = (1..)
+            .find(|base| {
+                children
+                    .iter()
+                    .all(|(code, _)| !used.get(base + code).copied().unwrap_or(false))
+            })
+            .unwrap();
+        let size = base + children.last().unwrap().0 + 1;
+        if units.len() < size {
+            units.resize(size, (0, 0));
+            used.resize(size, false);
+        }
+        units[index].0 = base as i32;
+        for (code, _) in &children {
+            used[base + code] = true;
+            units[base + code].1 = base as u32;
+        }
+        for (code, subkeys) in &children {
+            if *code == 0 {
+                units[base].0 = -subkeys[0].1 - 1;
+            } else {
+                build_double_array_node(subkeys, depth + 1, base + code, units, used);
+            }
+        }
+    }
+
+    type TestToken<'a> = (u16, u16, i16, &'a str);
+
+    fn make_dictionary(words: &[(&str, &[TestToken<'_>])]) -> Vec<u8> {
+        let mut keys = Vec::new();
+        let mut tokens = Vec::new();
+        let mut features = Vec::new();
+        for &(surface, word_tokens) in words {
+            keys.push((
+                surface.as_bytes(),
+                ((tokens.len() << 8) | word_tokens.len()) as i32,
+            ));
+            for &(left_id, right_id, cost, feature) in word_tokens {
+                tokens.push((left_id, right_id, cost, features.len() as u32));
+                features.extend_from_slice(feature.as_bytes());
+                features.push(0);
+            }
+        }
+        keys.sort();
+        let double_array = build_double_array(&keys);
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&DICTIONARY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&(tokens.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(
+            &((DOUBLE_ARRAY_UNIT_SIZE * double_array.len()) as u32).to_le_bytes(),
+        );
+        bytes.extend_from_slice(&((TOKEN_SIZE * tokens.len()) as u32).to_le_bytes());
+        bytes.extend_from_slice(&(features.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&0u32.to_le_bytes());
+        let mut charset = [0u8; 32];
+        charset[..5].copy_from_slice(b"UTF-8");
+        bytes.extend_from_slice(&charset);
+        for (base, check) in double_array {
+            bytes.extend_from_slice(&base.to_le_bytes());
+            bytes.extend_from_slice(&check.to_le_bytes());
+        }
+        for (i, (left_id, right_id, cost, feature)) in tokens.into_iter().enumerate() {
+            bytes.extend_from_slice(&left_id.to_le_bytes());
+            bytes.extend_from_slice(&right_id.to_le_bytes());
+            bytes.extend_from_slice(&(i as u16).to_le_bytes());
+            bytes.extend_from_slice(&cost.to_le_bytes());
+            bytes.extend_from_slice(&feature.to_le_bytes());
+            bytes.extend_from_slice(&0u32.to_le_bytes());
+        }
+        bytes.extend_from_slice(&features);
+
+        let magic = DICTIONARY_MAGIC_ID ^ bytes.len() as u32;
+        bytes[..4].copy_from_slice(&magic.to_le_bytes());
+        bytes
+    }
+
+    fn sys_dic() -> Vec<u8> {
+        make_dictionary(&[
+            ("くま", &[(1, 1, 100, "名詞,熊"), (2, 2, 300, "名詞,隈")]),
+            ("くまもと", &[(1, 1, 500, "名詞,熊本")]),
+            ("もと", &[(1, 1, 200, "名詞,元")]),
+        ])
+    }
+
+    fn unk_dic() -> Vec<u8> {
+        make_dictionary(&[
+            ("ALPHA", &[(1, 1, 1000, "名詞,英字")]),
+            ("DEFAULT", &[(1, 1, 2000, "記号")]),
+        ])
+    }
+
+    #[rustfmt::skip]
+    const MATRIX_COSTS: &[i16] = &[
+        //  right_id of left
+        //   0    1    2
+             0,  10,  20, // left_id of right 0
+            30,  40,  50, // left_id of right 1
+            60,  70,  80, // left_id of right 2
+    ];
+
+    fn matrix_bin() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&3u16.to_le_bytes());
+        bytes.extend_from_slice(&3u16.to_le_bytes());
+        for cost in MATRIX_COSTS {
+            bytes.extend_from_slice(&cost.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn char_bin() -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&2u32.to_le_bytes());
+        for name in ["DEFAULT", "ALPHA"] {
+            let mut padded = [0u8; CHAR_CATEGORY_NAME_SIZE];
+            padded[..name.len()].copy_from_slice(name.as_bytes());
+            bytes.extend_from_slice(&padded);
+        }
+        for c in 0..CHAR_INFO_COUNT as u32 {
+            let char_info: u32 = if char::from_u32(c).is_some_and(|c| c.is_ascii_alphabetic()) {
+                (1 << 1) | (1 << 18) | (3 << 26) | (1 << 30)
+            } else {
+                1 | (1 << 26)
+            };
+            bytes.extend_from_slice(&char_info.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn make_vocabulary() -> MecabVocabulary {
+        MecabVocabulary::new(&mut Cursor::new(sys_dic()), &mut Cursor::new(matrix_bin())).unwrap()
+    }
+
+    fn make_vocabulary_with_unknown_words() -> MecabVocabulary {
+        MecabVocabulary::new_with_unknown_words(
+            &mut Cursor::new(sys_dic()),
+            &mut Cursor::new(matrix_bin()),
+            &mut Cursor::new(char_bin()),
+            &mut Cursor::new(unk_dic()),
+        )
+        .unwrap()
+    }
+
+    fn word_of(entry: &Entry) -> &MecabWord {
+        entry.value().unwrap().downcast_ref::<MecabWord>().unwrap()
+    }
+
+    #[test]
+    fn new() {
+        {
+            let _vocabulary = make_vocabulary();
+        }
+        {
+            let mut broken_sys_dic = sys_dic();
+            let _ = broken_sys_dic.pop();
+            let vocabulary = MecabVocabulary::new(
+                &mut Cursor::new(broken_sys_dic),
+                &mut Cursor::new(matrix_bin()),
+            );
+            assert!(vocabulary.is_err());
+        }
+        {
+            let mut broken_matrix_bin = matrix_bin();
+            let _ = broken_matrix_bin.pop();
+            let vocabulary = MecabVocabulary::new(
+                &mut Cursor::new(sys_dic()),
+                &mut Cursor::new(broken_matrix_bin),
+            );
+            assert!(vocabulary.is_err());
+        }
+        {
+            let mut euc_jp_sys_dic = sys_dic();
+            euc_jp_sys_dic[40..46].copy_from_slice(b"EUC-JP");
+            let vocabulary = MecabVocabulary::new(
+                &mut Cursor::new(euc_jp_sys_dic),
+                &mut Cursor::new(matrix_bin()),
+            );
+            assert!(vocabulary.is_err());
+        }
+    }
+
+    #[test]
+    fn new_with_unknown_words() {
+        {
+            let _vocabulary = make_vocabulary_with_unknown_words();
+        }
+        {
+            let mut broken_char_bin = char_bin();
+            let _ = broken_char_bin.pop();
+            let vocabulary = MecabVocabulary::new_with_unknown_words(
+                &mut Cursor::new(sys_dic()),
+                &mut Cursor::new(matrix_bin()),
+                &mut Cursor::new(broken_char_bin),
+                &mut Cursor::new(unk_dic()),
+            );
+            assert!(vocabulary.is_err());
+        }
+    }
+
+    #[test]
+    fn find_entries() {
+        {
+            let vocabulary = make_vocabulary();
+
+            {
+                let found = vocabulary
+                    .find_entries(&StringInput::new(String::from("くま")))
+                    .unwrap();
+                assert_eq!(found.len(), 2);
+                assert_eq!(
+                    word_of(&found[0]),
+                    &MecabWord::new(String::from("くま"), 1, 1, 0, String::from("名詞,熊"))
+                );
+                assert_eq!(found[0].cost(), 100);
+                assert_eq!(
+                    word_of(&found[1]),
+                    &MecabWord::new(String::from("くま"), 2, 2, 1, String::from("名詞,隈"))
+                );
+                assert_eq!(found[1].cost(), 300);
+            }
+            {
+                let found = vocabulary
+                    .find_entries(&StringInput::new(String::from("くまもと")))
+                    .unwrap();
+                assert_eq!(found.len(), 1);
+                assert_eq!(word_of(&found[0]).feature(), "名詞,熊本");
+                assert_eq!(found[0].cost(), 500);
+            }
+            {
+                let found = vocabulary
+                    .find_entries(&StringInput::new(String::from("くまも")))
+                    .unwrap();
+                assert!(found.is_empty());
+            }
+            {
+                let found = vocabulary
+                    .find_entries(&StringInput::new(String::from("abc")))
+                    .unwrap();
+                assert!(found.is_empty());
+            }
+        }
+        {
+            let vocabulary = make_vocabulary_with_unknown_words();
+
+            {
+                let found = vocabulary
+                    .find_entries(&StringInput::new(String::from("abc")))
+                    .unwrap();
+                assert_eq!(found.len(), 1);
+                assert_eq!(word_of(&found[0]).surface(), "abc");
+                assert_eq!(word_of(&found[0]).feature(), "名詞,英字");
+                assert_eq!(found[0].cost(), 1000);
+            }
+            {
+                let found = vocabulary
+                    .find_entries(&StringInput::new(String::from("ab1")))
+                    .unwrap();
+                assert!(found.is_empty());
+            }
+            {
+                let found = vocabulary
+                    .find_entries(&StringInput::new(String::from("も")))
+                    .unwrap();
+                assert_eq!(found.len(), 1);
+                assert_eq!(word_of(&found[0]).feature(), "記号");
+            }
+            {
+                let found = vocabulary
+                    .find_entries(&StringInput::new(String::from("くま")))
+                    .unwrap();
+                assert_eq!(found.len(), 2);
+            }
+        }
+    }
+
+    #[test]
+    fn find_connection() {
+        let vocabulary = make_vocabulary();
+
+        let kuma = vocabulary
+            .find_entries(&StringInput::new(String::from("くま")))
+            .unwrap();
+        let moto = vocabulary
+            .find_entries(&StringInput::new(String::from("もと")))
+            .unwrap();
+        let kuma_node = Node::new_with_entry(&kuma[1], 0, 0, Rc::new(vec![0]), 0, 0).unwrap();
+
+        {
+            let connection = vocabulary
+                .find_connection(&Node::bos(Rc::new(Vec::new())), &kuma[1])
+                .unwrap();
+            assert_eq!(connection.cost(), 60);
+        }
+        {
+            let connection = vocabulary.find_connection(&kuma_node, &moto[0]).unwrap();
+            assert_eq!(connection.cost(), 50);
+        }
+        {
+            let connection = vocabulary
+                .find_connection(&kuma_node, &Entry::BosEos)
+                .unwrap();
+            assert_eq!(connection.cost(), 20);
+        }
+    }
+
+    #[test]
+    fn lattice() {
+        let vocabulary = make_vocabulary();
+        let mut lattice = Lattice::new(&vocabulary);
+        let _delta = lattice
+            .push_back(Box::new(StringInput::new(String::from("くま"))))
+            .unwrap();
+        let _delta = lattice
+            .push_back(Box::new(StringInput::new(String::from("もと"))))
+            .unwrap();
+        let eos_node = lattice.settle().unwrap();
+
+        let paths = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()))
+            .map(|path| {
+                let features = path
+                    .nodes()
+                    .iter()
+                    .filter_map(|node| node.value())
+                    .map(|value| value.downcast_ref::<MecabWord>().unwrap().feature())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                (features, path.cost())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            [
+                (String::from("名詞,熊/名詞,元"), 30 + 100 + 40 + 200 + 10),
+                (String::from("名詞,熊本"), 30 + 500 + 10),
+                (String::from("名詞,隈/名詞,元"), 60 + 300 + 50 + 200 + 10),
+            ]
+        );
+    }
+}
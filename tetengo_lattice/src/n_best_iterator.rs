@@ -4,28 +4,62 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
+use std::any::type_name_of_val;
 use std::cmp::{Ordering, Reverse};
 use std::collections::BinaryHeap;
+#[cfg(feature = "json")]
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::rc::Rc;
+use std::time::Instant;
+
+#[cfg(feature = "json")]
+use anyhow::Result;
+#[cfg(feature = "json")]
+use serde_json::json;
 
 use crate::constraint::Constraint;
 use crate::lattice::Lattice;
 use crate::node::Node;
 use crate::path::Path;
 
+/**
+ * An admissibility of a tail path.
+ */
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Admissibility {
+    /**
+     * The tail path may be expanded further.
+     */
+    Continue,
+
+    /**
+     * The tail path and all the paths having it are pruned.
+     */
+    Prune,
+}
+
+type PathFilter<'a> = &'a dyn Fn(&[Node]) -> Admissibility;
+
 /**
  * An N-best lattice path iterator.
  */
-#[derive(Debug)]
 pub struct NBestIterator<'a> {
     lattice: &'a Lattice<'a>,
+    eos_node: Node,
     caps: BinaryHeap<Reverse<Cap>>,
     constraint: Box<Constraint<'a>>,
+    path_filter: Option<PathFilter<'a>>,
+    deadline: Option<Instant>,
+    deadline_exceeded: bool,
 }
 
 impl<'a> NBestIterator<'a> {
     /**
      * Creates an iterator.
      *
+     * The iterator stops at the deadline of the lattice, if any.
+     *
      * # Arguments
      * * `lattice`    - A lattice.
      * * `eos_node`   - An EOS node.
@@ -35,35 +69,163 @@ impl<'a> NBestIterator<'a> {
         let mut caps = BinaryHeap::new();
         let tail_path_cost = eos_node.node_cost();
         let whole_path_cost = eos_node.path_cost();
-        caps.push(Reverse(Cap::new(
-            vec![eos_node],
-            tail_path_cost,
-            whole_path_cost,
-        )));
+        caps.push(Reverse(Cap::new(None, tail_path_cost, whole_path_cost)));
         Self {
             lattice,
+            eos_node,
             caps,
             constraint,
+            path_filter: None,
+            deadline: lattice.deadline(),
+            deadline_exceeded: false,
+        }
+    }
+
+    /**
+     * Sets a path filter.
+     *
+     * The filter is called with the nodes of each tail path, from EOS toward
+     * BOS, while the caps are expanded. A tail path judged as `Prune` is not
+     * expanded any further, so the paths having it are never constructed. The
+     * filter must judge the longer tail paths having a pruned one as `Prune`
+     * too, as a constraint does.
+     *
+     * # Arguments
+     * * `path_filter` - A path filter.
+     *
+     * # Returns
+     * This iterator.
+     */
+    pub fn with_path_filter(mut self, path_filter: PathFilter<'a>) -> Self {
+        self.path_filter = Some(path_filter);
+        self
+    }
+
+    /**
+     * Sets a deadline.
+     *
+     * Once the deadline passes, the iteration ends even when some paths are
+     * left, so the paths enumerated so far are the partial result. The
+     * deadline of the lattice is overridden.
+     *
+     * # Arguments
+     * * `deadline` - A deadline. Or None to remove the deadline.
+     *
+     * # Returns
+     * This iterator.
+     */
+    pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /**
+     * Returns true when the iteration has ended at the deadline.
+     *
+     * # Returns
+     * True when the iteration has ended at the deadline with some paths left.
+     */
+    pub const fn deadline_exceeded(&self) -> bool {
+        self.deadline_exceeded
+    }
+
+    /**
+     * Saves the iteration state.
+     *
+     * The state holds the candidates not yet enumerated. It does not borrow the
+     * lattice.
+     *
+     * # Returns
+     * The iteration state.
+     */
+    pub fn save_state(&self) -> NBestIteratorState {
+        NBestIteratorState {
+            caps: self.caps.clone(),
         }
     }
 
+    /**
+     * Restores the iteration state.
+     *
+     * The state must be saved from an iterator for the same lattice, and the
+     * lattice must not be modified since then.
+     *
+     * # Arguments
+     * * `state` - An iteration state.
+     */
+    pub fn restore_state(&mut self, state: NBestIteratorState) {
+        self.caps = state.caps;
+    }
+
+    /**
+     * Skips paths.
+     *
+     * Unlike `Iterator::skip`, the skipped paths are not constructed. The caps
+     * are expanded just as `next` does.
+     *
+     * # Arguments
+     * * `n` - A count of the paths to skip.
+     *
+     * # Returns
+     * The count of the skipped paths. Less than `n` when the iterator reaches
+     * the end.
+     */
+    pub fn advance_by_cheap(&mut self, n: usize) -> usize {
+        for i in 0..n {
+            if self.caps.is_empty() || self.open_next_cap().is_none() {
+                return i;
+            }
+        }
+        n
+    }
+
+    fn open_next_cap(&mut self) -> Option<(Vec<Node>, i32)> {
+        let path = Self::open_cap(
+            self.lattice,
+            &self.eos_node,
+            &mut self.caps,
+            self.constraint.as_ref(),
+            self.path_filter,
+            self.deadline,
+        );
+        // The caps are left only when the deadline is exceeded.
+        if path.is_none() && !self.caps.is_empty() {
+            self.deadline_exceeded = true;
+        }
+        path
+    }
+
+    // Returns the nodes of the path from EOS to BOS and the path cost.
     fn open_cap(
         lattice: &Lattice<'a>,
+        eos_node: &Node,
         caps: &mut BinaryHeap<Reverse<Cap>>,
         constraint: &Constraint<'a>,
-    ) -> Option<Path> {
+        path_filter: Option<PathFilter<'a>>,
+        deadline: Option<Instant>,
+    ) -> Option<(Vec<Node>, i32)> {
+        let admits = |tail_path: &[Node]| {
+            constraint.matches_tail(tail_path)
+                && path_filter
+                    .is_none_or(|path_filter| path_filter(tail_path) == Admissibility::Continue)
+        };
         let mut path = None;
         while !caps.is_empty() {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return None;
+            }
             let Some(opened) = caps.pop() else {
                 unreachable!("caps must not be empty.");
             };
             let opened = opened.0;
 
-            let mut next_path = opened.tail_path().to_vec();
+            let mut next_path = opened.tail_path(lattice, eos_node);
+            let mut next_link = opened.tail_path_link().cloned();
             let mut tail_path_cost = opened.tail_path_cost();
             let mut nonconforming_path = false;
-            let Some(mut node) = opened.tail_path().last() else {
-                unreachable!("tail_path must not be empty.");
+            let mut node = match opened.tail_path_link() {
+                Some(link) => link.node(lattice),
+                None => eos_node,
             };
             while !node.is_bos() {
                 let Ok(preceding_nodes) = lattice.nodes_at(node.preceding_step()) else {
@@ -73,28 +235,37 @@ impl<'a> NBestIterator<'a> {
                     if i == node.best_preceding_node() {
                         continue;
                     }
-                    let mut cap_tail_path = next_path.clone();
-                    cap_tail_path.push(preceding_node.clone());
-                    if !constraint.matches_tail(&cap_tail_path) {
+                    next_path.push(preceding_node.clone());
+                    let tail_admitted = admits(&next_path);
+                    let _popped = next_path.pop();
+                    if !tail_admitted {
                         continue;
                     }
                     let preceding_edge_cost = node.preceding_edge_costs()[i];
+                    let branched_tail_path_cost = Self::add_cost(
+                        tail_path_cost,
+                        lattice.higher_order_cost_delta(&next_path, i),
+                    );
                     let cap_tail_path_cost = Self::add_cost(
-                        Self::add_cost(tail_path_cost, preceding_edge_cost),
+                        Self::add_cost(branched_tail_path_cost, preceding_edge_cost),
                         preceding_node.node_cost(),
                     );
                     if cap_tail_path_cost == i32::MAX {
                         continue;
                     }
                     let cap_whole_path_cost = Self::add_cost(
-                        Self::add_cost(tail_path_cost, preceding_edge_cost),
+                        Self::add_cost(branched_tail_path_cost, preceding_edge_cost),
                         preceding_node.path_cost(),
                     );
                     if cap_whole_path_cost == i32::MAX {
                         continue;
                     }
                     caps.push(Reverse(Cap::new(
-                        cap_tail_path,
+                        Some(Rc::new(TailPathLink::new(
+                            node.preceding_step(),
+                            i,
+                            next_link.clone(),
+                        ))),
                         cap_tail_path_cost,
                         cap_whole_path_cost,
                     )));
@@ -104,10 +275,15 @@ impl<'a> NBestIterator<'a> {
                     node.preceding_edge_costs()[node.best_preceding_node()];
                 let best_preceding_node = &preceding_nodes[node.best_preceding_node()];
                 next_path.push(best_preceding_node.clone());
-                if !constraint.matches_tail(&next_path) {
+                if !admits(&next_path) {
                     nonconforming_path = true;
                     break;
                 }
+                next_link = Some(Rc::new(TailPathLink::new(
+                    node.preceding_step(),
+                    node.best_preceding_node(),
+                    next_link,
+                )));
                 tail_path_cost = Self::add_cost(
                     tail_path_cost,
                     Self::add_cost(best_preceding_edge_cost, best_preceding_node.node_cost()),
@@ -118,8 +294,7 @@ impl<'a> NBestIterator<'a> {
 
             if !nonconforming_path {
                 assert!(constraint.matches(&next_path));
-                let reversed_next_path = next_path.iter().rev().cloned().collect();
-                path = Some(Path::new(reversed_next_path, opened.whole_path_cost()));
+                path = Some((next_path, opened.whole_path_cost()));
                 break;
             }
         }
@@ -136,6 +311,20 @@ impl<'a> NBestIterator<'a> {
     }
 }
 
+impl Debug for NBestIterator<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NBestIterator")
+            .field("lattice", &self.lattice)
+            .field("eos_node", &self.eos_node)
+            .field("caps", &self.caps)
+            .field("constraint", &self.constraint)
+            .field("path_filter", &type_name_of_val(&self.path_filter))
+            .field("deadline", &self.deadline)
+            .field("deadline_exceeded", &self.deadline_exceeded)
+            .finish()
+    }
+}
+
 impl Iterator for NBestIterator<'_> {
     type Item = Path;
 
@@ -143,29 +332,262 @@ impl Iterator for NBestIterator<'_> {
         if self.caps.is_empty() {
             None
         } else {
-            Self::open_cap(self.lattice, &mut self.caps, self.constraint.as_ref())
+            let (mut nodes, cost) = self.open_next_cap()?;
+            nodes.reverse();
+            Some(Path::new(nodes, cost))
         }
     }
 }
 
-#[derive(Debug, Eq)]
+/**
+ * An N-best iterator state error.
+ */
+#[cfg(feature = "json")]
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum NBestIteratorStateError {
+    /**
+     * The JSON representation is invalid.
+     */
+    #[error("invalid JSON representation")]
+    InvalidJson,
+}
+
+/**
+ * An iteration state of an N-best lattice path iterator.
+ *
+ * The state is held in memory. With the `json` feature, it can be converted
+ * to and from the JSON representation.
+ */
+#[derive(Clone, Debug)]
+pub struct NBestIteratorState {
+    caps: BinaryHeap<Reverse<Cap>>,
+}
+
+#[cfg(feature = "json")]
+impl NBestIteratorState {
+    /**
+     * Returns the JSON representation.
+     *
+     * The representation has the following members:
+     *
+     * * `links` - The nodes in the tail paths, each of which has its `step`,
+     *             `index_in_step` and `following`, which is the index of the
+     *             following node toward EOS in `links`, or `null`. A node
+     *             appears after the node it refers to.
+     * * `caps`  - The candidates not yet enumerated, each of which has its
+     *             `tail_path_link`, which is the index of the head node of the
+     *             tail path in `links`, or `null`, and `tail_path_cost` and
+     *             `whole_path_cost`.
+     *
+     * The nodes shared among the tail paths appear once in `links`.
+     *
+     * # Returns
+     * The JSON representation.
+     */
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut link_indexes = HashMap::new();
+        let mut links = Vec::new();
+        let caps = self
+            .caps
+            .iter()
+            .map(|Reverse(cap)| {
+                let tail_path_link = cap
+                    .tail_path_link()
+                    .map(|link| Self::add_link_json(link, &mut link_indexes, &mut links));
+                json!({
+                    "tail_path_link": tail_path_link,
+                    "tail_path_cost": cap.tail_path_cost(),
+                    "whole_path_cost": cap.whole_path_cost(),
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "links": links,
+            "caps": caps,
+        })
+    }
+
+    // Adds the link and its following links not added yet, and returns the index of the link.
+    fn add_link_json(
+        link: &Rc<TailPathLink>,
+        link_indexes: &mut HashMap<*const TailPathLink, usize>,
+        links: &mut Vec<serde_json::Value>,
+    ) -> usize {
+        let mut unadded = Vec::new();
+        let mut following_index = None;
+        let mut next = Some(link);
+        while let Some(l) = next {
+            if let Some(&index) = link_indexes.get(&Rc::as_ptr(l)) {
+                following_index = Some(index);
+                break;
+            }
+            unadded.push(l);
+            next = l.following.as_ref();
+        }
+        for l in unadded.into_iter().rev() {
+            let index = links.len();
+            links.push(json!({
+                "step": l.step,
+                "index_in_step": l.index_in_step,
+                "following": following_index,
+            }));
+            let _ = link_indexes.insert(Rc::as_ptr(l), index);
+            following_index = Some(index);
+        }
+        match following_index {
+            Some(index) => index,
+            None => unreachable!("link must be added."),
+        }
+    }
+
+    /**
+     * Creates an iteration state from the JSON representation.
+     *
+     * # Arguments
+     * * `json` - A JSON representation returned by `to_json`.
+     *
+     * # Returns
+     * An iteration state.
+     *
+     * # Errors
+     * * When `json` is not a valid representation.
+     */
+    pub fn from_json(json: &serde_json::Value) -> Result<Self> {
+        let mut links = Vec::<Rc<TailPathLink>>::new();
+        for link in Self::array_member(json, "links")? {
+            let following = match link.get("following") {
+                Some(serde_json::Value::Null) => None,
+                Some(following) => {
+                    let index = Self::usize_of(following)?;
+                    Some(
+                        links
+                            .get(index)
+                            .cloned()
+                            .ok_or(NBestIteratorStateError::InvalidJson)?,
+                    )
+                }
+                None => return Err(NBestIteratorStateError::InvalidJson.into()),
+            };
+            links.push(Rc::new(TailPathLink::new(
+                Self::usize_member(link, "step")?,
+                Self::usize_member(link, "index_in_step")?,
+                following,
+            )));
+        }
+
+        let mut caps = BinaryHeap::new();
+        for cap in Self::array_member(json, "caps")? {
+            let tail_path_link = match cap.get("tail_path_link") {
+                Some(serde_json::Value::Null) => None,
+                Some(tail_path_link) => Some(
+                    links
+                        .get(Self::usize_of(tail_path_link)?)
+                        .cloned()
+                        .ok_or(NBestIteratorStateError::InvalidJson)?,
+                ),
+                None => return Err(NBestIteratorStateError::InvalidJson.into()),
+            };
+            caps.push(Reverse(Cap::new(
+                tail_path_link,
+                Self::i32_member(cap, "tail_path_cost")?,
+                Self::i32_member(cap, "whole_path_cost")?,
+            )));
+        }
+        Ok(Self { caps })
+    }
+
+    fn array_member<'j>(
+        json: &'j serde_json::Value,
+        name: &str,
+    ) -> Result<&'j Vec<serde_json::Value>> {
+        Ok(json
+            .get(name)
+            .and_then(serde_json::Value::as_array)
+            .ok_or(NBestIteratorStateError::InvalidJson)?)
+    }
+
+    fn usize_member(json: &serde_json::Value, name: &str) -> Result<usize> {
+        Self::usize_of(json.get(name).ok_or(NBestIteratorStateError::InvalidJson)?)
+    }
+
+    fn usize_of(json: &serde_json::Value) -> Result<usize> {
+        Ok(json
+            .as_u64()
+            .and_then(|value| usize::try_from(value).ok())
+            .ok_or(NBestIteratorStateError::InvalidJson)?)
+    }
+
+    fn i32_member(json: &serde_json::Value, name: &str) -> Result<i32> {
+        Ok(json
+            .get(name)
+            .and_then(serde_json::Value::as_i64)
+            .and_then(|value| i32::try_from(value).ok())
+            .ok_or(NBestIteratorStateError::InvalidJson)?)
+    }
+}
+
+// A node in a tail path, referred by its step and its index in the step. The
+// link to the following node toward EOS is shared among the caps, so that a cap
+// is made without copying its tail path.
+#[derive(Debug, Eq, PartialEq)]
+struct TailPathLink {
+    step: usize,
+    index_in_step: usize,
+    following: Option<Rc<TailPathLink>>,
+}
+
+impl TailPathLink {
+    const fn new(step: usize, index_in_step: usize, following: Option<Rc<TailPathLink>>) -> Self {
+        Self {
+            step,
+            index_in_step,
+            following,
+        }
+    }
+
+    fn node<'l>(&self, lattice: &'l Lattice<'_>) -> &'l Node {
+        let Ok(nodes) = lattice.nodes_at(self.step) else {
+            unreachable!("step must be within the steps in lattice.");
+        };
+        &nodes[self.index_in_step]
+    }
+}
+
+#[derive(Clone, Debug, Eq)]
 struct Cap {
-    tail_path: Vec<Node>,
+    tail_path_link: Option<Rc<TailPathLink>>,
     tail_path_cost: i32,
     whole_path_cost: i32,
 }
 
 impl Cap {
-    const fn new(tail_path: Vec<Node>, tail_path_cost: i32, whole_path_cost: i32) -> Self {
+    const fn new(
+        tail_path_link: Option<Rc<TailPathLink>>,
+        tail_path_cost: i32,
+        whole_path_cost: i32,
+    ) -> Self {
         Cap {
-            tail_path,
+            tail_path_link,
             tail_path_cost,
             whole_path_cost,
         }
     }
 
-    fn tail_path(&self) -> &[Node] {
-        self.tail_path.as_slice()
+    fn tail_path_link(&self) -> Option<&Rc<TailPathLink>> {
+        self.tail_path_link.as_ref()
+    }
+
+    // Returns the nodes of the tail path from EOS.
+    fn tail_path(&self, lattice: &Lattice<'_>, eos_node: &Node) -> Vec<Node> {
+        let mut tail_path = Vec::new();
+        let mut link = self.tail_path_link.as_deref();
+        while let Some(l) = link {
+            tail_path.push(l.node(lattice).clone());
+            link = l.following.as_deref();
+        }
+        tail_path.push(eos_node.clone());
+        tail_path.reverse();
+        tail_path
     }
 
     const fn tail_path_cost(&self) -> i32 {
@@ -479,6 +901,82 @@ mod tests {
         let _iterator = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()));
     }
 
+    #[test]
+    fn with_path_filter() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let _result = lattice.push_back(to_input("[TosuOmuta]"));
+        let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+        let eos_node = lattice.settle().unwrap();
+
+        // At most one transfer.
+        let path_filter = |reverse_tail_path: &[Node]| {
+            assert!(!reverse_tail_path[0].is_bos() && reverse_tail_path[0].value().is_none());
+            let train_count = reverse_tail_path
+                .iter()
+                .filter(|node| node.value().is_some())
+                .count();
+            if train_count <= 2 {
+                Admissibility::Continue
+            } else {
+                Admissibility::Prune
+            }
+        };
+        let iterator = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()))
+            .with_path_filter(&path_filter);
+
+        let costs = iterator.map(|path| path.cost()).collect::<Vec<_>>();
+        assert_eq!(costs, [3390, 3620, 3760, 4050, 4320, 4600, 4670]);
+    }
+
+    #[test]
+    fn with_deadline() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let _result = lattice.push_back(to_input("[TosuOmuta]"));
+        let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+        let eos_node = lattice.settle().unwrap();
+        {
+            let mut iterator =
+                NBestIterator::new(&lattice, eos_node.clone(), Box::new(Constraint::new()))
+                    .with_deadline(Some(Instant::now()));
+
+            assert!(iterator.next().is_none());
+            assert!(iterator.deadline_exceeded());
+        }
+        {
+            lattice.set_deadline(Some(Instant::now()));
+            let mut iterator =
+                NBestIterator::new(&lattice, eos_node.clone(), Box::new(Constraint::new()));
+
+            assert!(iterator.next().is_none());
+            assert!(iterator.deadline_exceeded());
+        }
+        {
+            let mut iterator =
+                NBestIterator::new(&lattice, eos_node.clone(), Box::new(Constraint::new()))
+                    .with_deadline(None);
+
+            assert_eq!(iterator.next().unwrap().cost(), 3390);
+            assert!(!iterator.deadline_exceeded());
+        }
+    }
+
+    #[test]
+    fn deadline_exceeded() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let eos_node = lattice.settle().unwrap();
+        let mut iterator = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()));
+
+        assert!(!iterator.deadline_exceeded());
+        while iterator.next().is_some() {}
+        assert!(!iterator.deadline_exceeded());
+    }
+
     #[test]
     fn next() {
         {
@@ -880,65 +1378,247 @@ mod tests {
 
         #[test]
         fn new() {
-            let preceding_edge_costs = Rc::new(vec![3, 1, 4, 1, 5, 9, 2, 6]);
-            let node = Node::eos(1, preceding_edge_costs, 5, 42);
-            let nodes = vec![node];
-            let _cap = Cap::new(nodes, 24, 42);
+            let _cap = Cap::new(None, 24, 42);
         }
 
         #[test]
         fn ord() {
-            let preceding_edge_costs1 = Rc::new(vec![3, 1, 4, 1, 5, 9, 2, 6]);
-            let node1 = Node::eos(1, preceding_edge_costs1, 5, 42);
-            let nodes1 = vec![node1];
-            let cap1 = Cap::new(nodes1, 24, 42);
-
-            let preceding_edge_costs2 = Rc::new(vec![3, 1, 4, 1, 5, 9, 2, 6]);
-            let node2 = Node::eos(1, preceding_edge_costs2, 5, 42);
-            let nodes2 = vec![node2];
-            let cap2 = Cap::new(nodes2, 24, 42);
-
-            let preceding_edge_costs3 = Rc::new(vec![2, 7, 1, 8, 2, 8]);
-            let node3 = Node::eos(2, preceding_edge_costs3, 3, 31);
-            let nodes3 = vec![node3];
-            let cap3 = Cap::new(nodes3, 12, 4242);
+            let cap1 = Cap::new(None, 24, 42);
+            let cap2 = Cap::new(Some(Rc::new(TailPathLink::new(1, 0, None))), 24, 42);
+            let cap3 = Cap::new(None, 12, 4242);
 
             assert!(cap1 == cap2);
             assert!(cap1 < cap3);
         }
 
+        #[test]
+        fn tail_path_link() {
+            let link = Rc::new(TailPathLink::new(1, 0, None));
+            let cap = Cap::new(Some(link.clone()), 24, 42);
+
+            assert!(Rc::ptr_eq(cap.tail_path_link().unwrap(), &link));
+            assert!(Cap::new(None, 24, 42).tail_path_link().is_none());
+        }
+
         #[test]
         fn tail_path() {
-            let preceding_edge_costs = Rc::new(vec![3, 1, 4, 1, 5, 9, 2, 6]);
-            let node = Node::eos(1, preceding_edge_costs.clone(), 5, 42);
-            let nodes = vec![node];
-            let cap = Cap::new(nodes, 24, 42);
+            let vocabulary = create_vocabulary();
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _result = lattice.push_back(to_input("[TosuOmuta]"));
+            let eos_node = lattice.settle().unwrap();
+
+            {
+                let cap = Cap::new(None, 24, 42);
+
+                assert_eq!(cap.tail_path(&lattice, &eos_node), [eos_node.clone()]);
+            }
+            {
+                let following = Rc::new(TailPathLink::new(2, 1, None));
+                let link = Rc::new(TailPathLink::new(1, 0, Some(following)));
+                let cap = Cap::new(Some(link), 24, 42);
 
-            assert_eq!(cap.tail_path().len(), 1);
-            assert_eq!(
-                cap.tail_path()[0].preceding_edge_costs(),
-                preceding_edge_costs.as_slice()
-            );
+                assert_eq!(
+                    cap.tail_path(&lattice, &eos_node),
+                    [
+                        eos_node.clone(),
+                        lattice.nodes_at(2).unwrap()[1].clone(),
+                        lattice.nodes_at(1).unwrap()[0].clone(),
+                    ]
+                );
+            }
         }
 
         #[test]
         fn tail_path_cost() {
-            let preceding_edge_costs = Rc::new(vec![3, 1, 4, 1, 5, 9, 2, 6]);
-            let node = Node::eos(1, preceding_edge_costs, 5, 42);
-            let nodes = vec![node];
-            let cap = Cap::new(nodes, 24, 42);
+            let cap = Cap::new(None, 24, 42);
 
             assert_eq!(cap.tail_path_cost(), 24);
         }
 
         #[test]
         fn whole_path_cost() {
-            let preceding_edge_costs = Rc::new(vec![3, 1, 4, 1, 5, 9, 2, 6]);
-            let node = Node::eos(1, preceding_edge_costs, 5, 42);
-            let nodes = vec![node];
-            let cap = Cap::new(nodes, 24, 42);
+            let cap = Cap::new(None, 24, 42);
 
             assert_eq!(cap.whole_path_cost(), 42);
         }
     }
+
+    #[test]
+    fn advance_by_cheap() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let _result = lattice.push_back(to_input("[TosuOmuta]"));
+        let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+
+        let eos_node = lattice.settle().unwrap();
+        let all_costs = NBestIterator::new(&lattice, eos_node.clone(), Box::new(Constraint::new()))
+            .map(|path| path.cost())
+            .collect::<Vec<_>>();
+        assert_eq!(all_costs.len(), 9);
+
+        {
+            let mut iterator =
+                NBestIterator::new(&lattice, eos_node.clone(), Box::new(Constraint::new()));
+
+            assert_eq!(iterator.advance_by_cheap(0), 0);
+            assert_eq!(iterator.advance_by_cheap(3), 3);
+            let costs = iterator.map(|path| path.cost()).collect::<Vec<_>>();
+            assert_eq!(costs, all_costs[3..]);
+        }
+        {
+            let mut iterator = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()));
+
+            assert_eq!(iterator.advance_by_cheap(100), 9);
+            assert!(iterator.next().is_none());
+            assert_eq!(iterator.advance_by_cheap(1), 0);
+        }
+    }
+
+    #[test]
+    fn save_state() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let _result = lattice.push_back(to_input("[TosuOmuta]"));
+        let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+
+        let eos_node = lattice.settle().unwrap();
+        let mut iterator = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()));
+        let _path = iterator.next().unwrap();
+
+        let state = iterator.save_state();
+        let costs = iterator.map(|path| path.cost()).collect::<Vec<_>>();
+        assert_eq!(costs.len(), 8);
+        assert!(!state.caps.is_empty());
+    }
+
+    #[test]
+    fn restore_state() {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        let _result = lattice.push_back(to_input("[HakataTosu]"));
+        let _result = lattice.push_back(to_input("[TosuOmuta]"));
+        let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+
+        let eos_node = lattice.settle().unwrap();
+        let mut iterator =
+            NBestIterator::new(&lattice, eos_node.clone(), Box::new(Constraint::new()));
+        let _path = iterator.next().unwrap();
+        let _path = iterator.next().unwrap();
+        let state = iterator.save_state();
+        let expected_costs = iterator.map(|path| path.cost()).collect::<Vec<_>>();
+
+        let mut restored_iterator =
+            NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()));
+        restored_iterator.restore_state(state);
+        let costs = restored_iterator
+            .map(|path| path.cost())
+            .collect::<Vec<_>>();
+
+        assert!(!costs.is_empty());
+        assert_eq!(costs, expected_costs);
+    }
+
+    #[cfg(feature = "json")]
+    mod n_best_iterator_state {
+        use super::*;
+
+        #[test]
+        fn to_json() {
+            let vocabulary = create_vocabulary();
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _result = lattice.push_back(to_input("[TosuOmuta]"));
+            let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+
+            let eos_node = lattice.settle().unwrap();
+            let mut iterator = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()));
+            {
+                let json = iterator.save_state().to_json();
+
+                assert_eq!(
+                    json,
+                    serde_json::json!({
+                        "links": [],
+                        "caps": [
+                            { "tail_path_link": null, "tail_path_cost": 0, "whole_path_cost": 3390 },
+                        ],
+                    })
+                );
+            }
+            {
+                let _path = iterator.next().unwrap();
+                let json = iterator.save_state().to_json();
+
+                let links = json["links"].as_array().unwrap();
+                let caps = json["caps"].as_array().unwrap();
+                assert_eq!(caps.len(), 4);
+                assert!(links.len() < 4 + caps.len());
+                for (i, link) in links.iter().enumerate() {
+                    assert!(link["following"]
+                        .as_u64()
+                        .map_or(true, |following| (following as usize) < i));
+                }
+            }
+        }
+
+        #[test]
+        fn from_json() {
+            let vocabulary = create_vocabulary();
+            let mut lattice = Lattice::new(vocabulary.as_ref());
+            let _result = lattice.push_back(to_input("[HakataTosu]"));
+            let _result = lattice.push_back(to_input("[TosuOmuta]"));
+            let _result = lattice.push_back(to_input("[OmutaKumamoto]"));
+
+            let eos_node = lattice.settle().unwrap();
+            {
+                let mut iterator =
+                    NBestIterator::new(&lattice, eos_node.clone(), Box::new(Constraint::new()));
+                let _path = iterator.next().unwrap();
+                let _path = iterator.next().unwrap();
+                let json = iterator.save_state().to_json();
+                let expected_paths = iterator.collect::<Vec<_>>();
+
+                let state = NBestIteratorState::from_json(&json).unwrap();
+                let mut restored_iterator =
+                    NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()));
+                restored_iterator.restore_state(state);
+                let paths = restored_iterator.collect::<Vec<_>>();
+
+                assert_eq!(paths.len(), 7);
+                assert_eq!(paths, expected_paths);
+            }
+            {
+                let json = serde_json::json!({ "links": [] });
+
+                let error = NBestIteratorState::from_json(&json).unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<NBestIteratorStateError>().unwrap(),
+                    NBestIteratorStateError::InvalidJson
+                ));
+            }
+            {
+                let json = serde_json::json!({
+                    "links": [
+                        { "step": 1, "index_in_step": 0, "following": 0 },
+                    ],
+                    "caps": [],
+                });
+
+                assert!(NBestIteratorState::from_json(&json).is_err());
+            }
+            {
+                let json = serde_json::json!({
+                    "links": [],
+                    "caps": [
+                        { "tail_path_link": 0, "tail_path_cost": 0, "whole_path_cost": 0 },
+                    ],
+                });
+
+                assert!(NBestIteratorState::from_json(&json).is_err());
+            }
+        }
+    }
 }
@@ -0,0 +1,477 @@
+/*!
+ * An N-best tree.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::fmt::Write;
+
+use anyhow::Result;
+
+use crate::constraint::Constraint;
+use crate::lattice::Lattice;
+use crate::n_best_iterator::NBestIterator;
+use crate::node::Node;
+use crate::node_id::NodeId;
+
+/**
+ * An N-best tree.
+ *
+ * Merges the N-best paths from EOS toward BOS. The paths sharing a tail share
+ * the tree nodes, so a tree node with two or more children is a divergence
+ * point where the paths take different preceding nodes.
+ *
+ * The paths are drawn from an N-best iterator one by one, so only the paths
+ * within the limit are evaluated.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NBestTree {
+    root: NBestTreeNode,
+    path_count: usize,
+}
+
+impl NBestTree {
+    /**
+     * Builds an N-best tree.
+     *
+     * # Arguments
+     * * `lattice`  - A lattice.
+     * * `eos_node` - An EOS node.
+     * * `limit`    - The maximum count of the paths.
+     *
+     * # Returns
+     * An N-best tree.
+     *
+     * # Errors
+     * * When a node in a path is not found in the lattice.
+     */
+    pub fn build(lattice: &Lattice<'_>, eos_node: &Node, limit: usize) -> Result<Self> {
+        let mut root = NBestTreeNode::new(
+            lattice.node_id(eos_node)?,
+            eos_node.display(lattice).to_string(),
+            eos_node.node_cost(),
+            eos_node.path_cost(),
+            0,
+        );
+        let mut path_count = 0;
+        let iterator = NBestIterator::new(lattice, eos_node.clone(), Box::new(Constraint::new()));
+        for path in iterator.take(limit) {
+            let mut tree_node = &mut root;
+            for node in path.nodes().iter().rev().skip(1) {
+                let node_id = lattice.node_id(node)?;
+                let child_index = match tree_node
+                    .children
+                    .iter()
+                    .position(|child| child.node_id == node_id)
+                {
+                    Some(child_index) => child_index,
+                    None => {
+                        let cost_delta = path.cost() - tree_node.best_path_cost;
+                        tree_node.children.push(NBestTreeNode::new(
+                            node_id,
+                            node.display(lattice).to_string(),
+                            node.node_cost(),
+                            path.cost(),
+                            cost_delta,
+                        ));
+                        tree_node.children.len() - 1
+                    }
+                };
+                tree_node = &mut tree_node.children[child_index];
+            }
+            path_count += 1;
+        }
+        Ok(Self { root, path_count })
+    }
+
+    /**
+     * Returns the root.
+     *
+     * # Returns
+     * The root, which is the EOS node.
+     */
+    pub const fn root(&self) -> &NBestTreeNode {
+        &self.root
+    }
+
+    /**
+     * Returns the count of the paths.
+     *
+     * # Returns
+     * The count of the paths merged into this tree.
+     */
+    pub const fn path_count(&self) -> usize {
+        self.path_count
+    }
+
+    /**
+     * Returns the JSON representation.
+     *
+     * The index in the step of the EOS node is `null`.
+     *
+     * # Returns
+     * The JSON representation.
+     */
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        let _ = write!(json, "{{\"path_count\":{},\"root\":", self.path_count);
+        self.root.write_json(&mut json);
+        json.push('}');
+        json
+    }
+}
+
+/**
+ * A node of an N-best tree.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NBestTreeNode {
+    node_id: NodeId,
+    label: String,
+    node_cost: i32,
+    best_path_cost: i32,
+    cost_delta: i32,
+    children: Vec<NBestTreeNode>,
+}
+
+impl NBestTreeNode {
+    const fn new(
+        node_id: NodeId,
+        label: String,
+        node_cost: i32,
+        best_path_cost: i32,
+        cost_delta: i32,
+    ) -> Self {
+        Self {
+            node_id,
+            label,
+            node_cost,
+            best_path_cost,
+            cost_delta,
+            children: Vec::new(),
+        }
+    }
+
+    /**
+     * Returns the node ID.
+     *
+     * # Returns
+     * The node ID of the lattice node.
+     */
+    pub const fn node_id(&self) -> NodeId {
+        self.node_id
+    }
+
+    /**
+     * Returns the label.
+     *
+     * # Returns
+     * The label formatted by the value formatter of the lattice.
+     */
+    pub fn label(&self) -> &str {
+        self.label.as_str()
+    }
+
+    /**
+     * Returns the node cost.
+     *
+     * # Returns
+     * The node cost.
+     */
+    pub const fn node_cost(&self) -> i32 {
+        self.node_cost
+    }
+
+    /**
+     * Returns the best path cost.
+     *
+     * # Returns
+     * The cost of the best path through this node.
+     */
+    pub const fn best_path_cost(&self) -> i32 {
+        self.best_path_cost
+    }
+
+    /**
+     * Returns the cost delta.
+     *
+     * # Returns
+     * The best path cost minus the one of the parent. 0 for the root and the
+     * best children.
+     */
+    pub const fn cost_delta(&self) -> i32 {
+        self.cost_delta
+    }
+
+    /**
+     * Returns the children.
+     *
+     * The children are the preceding nodes in the paths, in ascending order of
+     * the best path cost.
+     *
+     * # Returns
+     * The children.
+     */
+    pub fn children(&self) -> &[NBestTreeNode] {
+        self.children.as_slice()
+    }
+
+    /**
+     * Returns `true` if this node is a divergence point.
+     *
+     * # Returns
+     * `true` if this node has two or more children.
+     */
+    pub fn is_divergence_point(&self) -> bool {
+        self.children.len() >= 2
+    }
+
+    fn write_json(&self, json: &mut String) {
+        let _ = write!(json, "{{\"step\":{},", self.node_id.step());
+        if self.node_id.index_in_step() == usize::MAX {
+            json.push_str("\"index_in_step\":null,");
+        } else {
+            let _ = write!(json, "\"index_in_step\":{},", self.node_id.index_in_step());
+        }
+        json.push_str("\"label\":");
+        Self::write_json_string(json, &self.label);
+        let _ = write!(
+            json,
+            ",\"node_cost\":{},\"best_path_cost\":{},\"cost_delta\":{},\"children\":[",
+            self.node_cost, self.best_path_cost, self.cost_delta
+        );
+        for (i, child) in self.children.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            child.write_json(json);
+        }
+        json.push_str("]}");
+    }
+
+    fn write_json_string(json: &mut String, string: &str) {
+        json.push('"');
+        for c in string.chars() {
+            match c {
+                '"' => json.push_str("\\\""),
+                '\\' => json.push_str("\\\\"),
+                '\n' => json.push_str("\\n"),
+                '\r' => json.push_str("\\r"),
+                '\t' => json.push_str("\\t"),
+                c if c < ' ' => {
+                    let _ = write!(json, "\\u{:04x}", c as u32);
+                }
+                c => json.push(c),
+            }
+        }
+        json.push('"');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::rc::Rc;
+
+    use crate::entry::Entry;
+    use crate::hash_map_vocabulary::HashMapVocabulary;
+    use crate::string_input::StringInput;
+    use crate::vocabulary::Vocabulary;
+
+    use super::*;
+
+    fn make_entry(key: &str, value: &str, cost: i32) -> Entry {
+        Entry::new(
+            Rc::new(StringInput::new(String::from(key))),
+            Rc::new(String::from(value)),
+            cost,
+        )
+    }
+
+    fn entry_hash(entry: &Entry) -> u64 {
+        entry.key().map_or(0, |key| key.hash_value())
+    }
+
+    fn entry_equal_to(one: &Entry, other: &Entry) -> bool {
+        match (one.key(), other.key()) {
+            (Some(one_key), Some(other_key)) => one_key.equal_to(other_key),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    fn create_vocabulary() -> Box<dyn Vocabulary> {
+        let entries = vec![
+            (
+                String::from("a"),
+                vec![make_entry("a", "A1", 1), make_entry("a", "A2", 3)],
+            ),
+            (String::from("b"), vec![make_entry("b", "B", 2)]),
+            (String::from("ab"), vec![make_entry("ab", "AB", 10)]),
+        ];
+        let connections = vec![
+            ((Entry::BosEos, make_entry("a", "A1", 1)), 1),
+            ((make_entry("a", "A1", 1), make_entry("b", "B", 2)), 1),
+            ((make_entry("b", "B", 2), Entry::BosEos), 1),
+            ((Entry::BosEos, make_entry("ab", "AB", 10)), 1),
+            ((make_entry("ab", "AB", 10), Entry::BosEos), 1),
+        ];
+        Box::new(HashMapVocabulary::new(
+            entries,
+            connections,
+            &entry_hash,
+            &entry_equal_to,
+        ))
+    }
+
+    fn format_value(value: &dyn Any) -> String {
+        value.downcast_ref::<String>().unwrap().clone()
+    }
+
+    fn build_tree(limit: usize) -> NBestTree {
+        let vocabulary = create_vocabulary();
+        let mut lattice = Lattice::new(vocabulary.as_ref());
+        lattice.set_value_formatter(&format_value);
+        let _delta = lattice
+            .push_back(Box::new(StringInput::new(String::from("a"))))
+            .unwrap();
+        let _delta = lattice
+            .push_back(Box::new(StringInput::new(String::from("b"))))
+            .unwrap();
+        let eos_node = lattice.settle().unwrap();
+        NBestTree::build(&lattice, &eos_node, limit).unwrap()
+    }
+
+    fn labels(tree_nodes: &[NBestTreeNode]) -> Vec<&str> {
+        tree_nodes.iter().map(NBestTreeNode::label).collect()
+    }
+
+    mod n_best_tree {
+        use super::*;
+
+        #[test]
+        fn build() {
+            {
+                let tree = build_tree(10);
+
+                let root = tree.root();
+                assert_eq!(root.label(), "EOS");
+                assert_eq!(labels(root.children()), vec!["B", "AB"]);
+                let b = &root.children()[0];
+                assert_eq!(labels(b.children()), vec!["A1", "A2"]);
+                assert_eq!(labels(b.children()[0].children()), vec!["BOS"]);
+                assert_eq!(labels(root.children()[1].children()), vec!["BOS"]);
+            }
+            {
+                let tree = build_tree(1);
+
+                let root = tree.root();
+                assert_eq!(labels(root.children()), vec!["B"]);
+                assert_eq!(labels(root.children()[0].children()), vec!["A1"]);
+            }
+        }
+
+        #[test]
+        fn root() {
+            let tree = build_tree(10);
+
+            let root = tree.root();
+            assert_eq!(root.node_id(), NodeId::new(3, usize::MAX));
+            assert_eq!(root.best_path_cost(), 6);
+            assert_eq!(root.cost_delta(), 0);
+        }
+
+        #[test]
+        fn path_count() {
+            assert_eq!(build_tree(10).path_count(), 3);
+            assert_eq!(build_tree(2).path_count(), 2);
+            assert_eq!(build_tree(0).path_count(), 0);
+        }
+
+        #[test]
+        fn to_json() {
+            let tree = build_tree(1);
+
+            assert_eq!(
+                tree.to_json(),
+                concat!(
+                    r#"{"path_count":1,"root":"#,
+                    r#"{"step":3,"index_in_step":null,"label":"EOS","node_cost":0,"#,
+                    r#""best_path_cost":6,"cost_delta":0,"children":["#,
+                    r#"{"step":2,"index_in_step":1,"label":"B","node_cost":2,"#,
+                    r#""best_path_cost":6,"cost_delta":0,"children":["#,
+                    r#"{"step":1,"index_in_step":0,"label":"A1","node_cost":1,"#,
+                    r#""best_path_cost":6,"cost_delta":0,"children":["#,
+                    r#"{"step":0,"index_in_step":0,"label":"BOS","node_cost":0,"#,
+                    r#""best_path_cost":6,"cost_delta":0,"children":[]}]}]}]}}"#,
+                )
+            );
+        }
+    }
+
+    mod n_best_tree_node {
+        use super::*;
+
+        #[test]
+        fn node_id() {
+            let tree = build_tree(10);
+
+            let b = &tree.root().children()[0];
+            assert_eq!(b.node_id(), NodeId::new(2, 1));
+        }
+
+        #[test]
+        fn label() {
+            let tree = build_tree(10);
+
+            assert_eq!(tree.root().children()[1].label(), "AB");
+        }
+
+        #[test]
+        fn node_cost() {
+            let tree = build_tree(10);
+
+            assert_eq!(tree.root().children()[1].node_cost(), 10);
+        }
+
+        #[test]
+        fn best_path_cost() {
+            let tree = build_tree(10);
+
+            let b = &tree.root().children()[0];
+            assert_eq!(b.best_path_cost(), 6);
+            assert_eq!(b.children()[1].best_path_cost(), 8);
+            assert_eq!(tree.root().children()[1].best_path_cost(), 12);
+        }
+
+        #[test]
+        fn cost_delta() {
+            let tree = build_tree(10);
+
+            let b = &tree.root().children()[0];
+            assert_eq!(b.cost_delta(), 0);
+            assert_eq!(b.children()[0].cost_delta(), 0);
+            assert_eq!(b.children()[1].cost_delta(), 2);
+            assert_eq!(tree.root().children()[1].cost_delta(), 6);
+        }
+
+        #[test]
+        fn children() {
+            let tree = build_tree(10);
+
+            assert_eq!(tree.root().children().len(), 2);
+            assert!(tree.root().children()[1].children()[0]
+                .children()
+                .is_empty());
+        }
+
+        #[test]
+        fn is_divergence_point() {
+            let tree = build_tree(10);
+
+            assert!(tree.root().is_divergence_point());
+            assert!(tree.root().children()[0].is_divergence_point());
+            assert!(!tree.root().children()[1].is_divergence_point());
+        }
+    }
+}
@@ -5,13 +5,14 @@
  */
 
 use std::any::Any;
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display, Formatter};
 use std::rc::Rc;
 
 use anyhow::Result;
 
-use crate::entry::Entry;
+use crate::entry::{Entry, EntryValue};
 use crate::input::Input;
+use crate::lattice::Lattice;
 
 /**
  * A node error.
@@ -23,6 +24,12 @@ pub enum NodeError {
      */
     #[error("BOS or EOS entry is not allowed")]
     BosOrEosEntryNotAllowed,
+
+    /**
+     * The node is not found in the lattice.
+     */
+    #[error("the node is not found in the lattice")]
+    NodeNotFoundInLattice,
 }
 
 /**
@@ -49,13 +56,15 @@ pub struct Eos {
 #[derive(Clone, Debug)]
 pub struct Middle {
     key: Rc<dyn Input>,
-    value: Rc<dyn Any>,
+    value: EntryValue,
     index_in_step: usize,
     preceding_step: usize,
     preceding_edge_costs: Rc<Vec<i32>>,
     best_preceding_node: usize,
     node_cost: i32,
     path_cost: i32,
+    context_ids: Option<(u16, u16)>,
+    provenance: Option<u16>,
 }
 
 impl Eq for Middle {}
@@ -148,13 +157,15 @@ impl Node {
     ) -> Self {
         Node::Middle(Middle {
             key,
-            value,
+            value: EntryValue::Shared(value),
             index_in_step,
             preceding_step,
             preceding_edge_costs,
             best_preceding_node,
             node_cost,
             path_cost,
+            context_ids: None,
+            provenance: None,
         })
     }
 
@@ -175,7 +186,7 @@ impl Node {
         let Some(key) = entry.key_rc() else {
             return Err(NodeError::BosOrEosEntryNotAllowed.into());
         };
-        let Some(value) = entry.value_rc() else {
+        let Some(value) = entry.entry_value() else {
             return Err(NodeError::BosOrEosEntryNotAllowed.into());
         };
         Ok(Node::Middle(Middle {
@@ -187,6 +198,8 @@ impl Node {
             best_preceding_node,
             node_cost: entry.cost(),
             path_cost,
+            context_ids: entry.context_ids(),
+            provenance: entry.provenance(),
         }))
     }
 
@@ -222,14 +235,14 @@ impl Node {
         match self {
             Node::Bos(_) => Entry::BosEos.value(),
             Node::Eos(_) => Entry::BosEos.value(),
-            Node::Middle(middle) => Some(middle.value.as_ref()),
+            Node::Middle(middle) => Some(middle.value.as_any()),
         }
     }
 
-    pub(crate) fn value_rc(&self) -> Option<Rc<dyn Any>> {
+    pub(crate) fn entry_value(&self) -> Option<EntryValue> {
         match self {
-            Node::Bos(_) => Entry::BosEos.value_rc(),
-            Node::Eos(_) => Entry::BosEos.value_rc(),
+            Node::Bos(_) => Entry::BosEos.entry_value(),
+            Node::Eos(_) => Entry::BosEos.entry_value(),
             Node::Middle(middle) => Some(middle.value.clone()),
         }
     }
@@ -304,6 +317,54 @@ impl Node {
         }
     }
 
+    /**
+     * Returns the left context ID.
+     *
+     * # Returns
+     * The left context ID. Or None when the node is BOS/EOS or has no context
+     * IDs.
+     */
+    pub const fn left_id(&self) -> Option<u16> {
+        match self {
+            Node::Middle(Middle {
+                context_ids: Some((left_id, _)),
+                ..
+            }) => Some(*left_id),
+            _ => None,
+        }
+    }
+
+    /**
+     * Returns the right context ID.
+     *
+     * # Returns
+     * The right context ID. Or None when the node is BOS/EOS or has no context
+     * IDs.
+     */
+    pub const fn right_id(&self) -> Option<u16> {
+        match self {
+            Node::Middle(Middle {
+                context_ids: Some((_, right_id)),
+                ..
+            }) => Some(*right_id),
+            _ => None,
+        }
+    }
+
+    /**
+     * Returns the provenance.
+     *
+     * # Returns
+     * The provenance tag of the entry which this node is made of. Or None when
+     * the node is BOS/EOS or has no provenance.
+     */
+    pub const fn provenance(&self) -> Option<u16> {
+        match self {
+            Node::Middle(middle) => middle.provenance,
+            _ => None,
+        }
+    }
+
     /**
      * Returns the path cost.
      *
@@ -318,6 +379,33 @@ impl Node {
         }
     }
 
+    /**
+     * Returns the span of the input which this node corresponds to.
+     *
+     * # Arguments
+     * * `lattice` - The lattice which this node belongs to.
+     *
+     * # Returns
+     * The offset and the tail of the span.
+     *
+     * # Errors
+     * * When this node is not found in the lattice.
+     */
+    pub fn input_span(&self, lattice: &Lattice<'_>) -> Result<(usize, usize)> {
+        match self {
+            Node::Bos(_) => Ok((0, 0)),
+            Node::Eos(eos) => {
+                let (_, tail) = lattice.step_span(eos.preceding_step)?;
+                Ok((tail, tail))
+            }
+            Node::Middle(middle) => {
+                let (_, offset) = lattice.step_span(middle.preceding_step)?;
+                let (_, tail) = lattice.step_span(lattice.node_id(self)?.step())?;
+                Ok((offset, tail))
+            }
+        }
+    }
+
     /**
      * Returns `true` if this node is the BOS.
      *
@@ -327,11 +415,104 @@ impl Node {
     pub const fn is_bos(&self) -> bool {
         matches!(self, Node::Bos(_))
     }
+
+    /**
+     * Returns a displayable view of this node.
+     *
+     * The value is formatted with the value formatter of the lattice.
+     *
+     * # Arguments
+     * * `lattice` - A lattice.
+     *
+     * # Returns
+     * A displayable view.
+     */
+    pub const fn display<'b>(&'b self, lattice: &'b Lattice<'b>) -> NodeDisplay<'b> {
+        NodeDisplay {
+            node: self,
+            lattice,
+        }
+    }
+}
+
+impl From<&Node> for Entry {
+    /**
+     * Creates an entry from a node.
+     *
+     * The entry shares the key and the value with the node, and has the
+     * context IDs and the provenance of the node. BOS and EOS become the
+     * BOS/EOS entry.
+     *
+     * # Arguments
+     * * `node` - A node.
+     */
+    fn from(node: &Node) -> Self {
+        match node {
+            Node::Bos(_) | Node::Eos(_) => Entry::BosEos,
+            Node::Middle(middle) => Entry::new_with_entry_value(
+                middle.key.clone(),
+                middle.value.clone(),
+                middle.node_cost,
+                middle.context_ids,
+                middle.provenance,
+            ),
+        }
+    }
+}
+
+/**
+ * A displayable view of a node.
+ */
+#[derive(Clone, Copy)]
+pub struct NodeDisplay<'a> {
+    node: &'a Node,
+    lattice: &'a Lattice<'a>,
+}
+
+impl Debug for NodeDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.node {
+            Node::Bos(_) => f
+                .debug_struct("Bos")
+                .field("path_cost", &self.node.path_cost())
+                .finish(),
+            Node::Eos(eos) => f
+                .debug_struct("Eos")
+                .field("preceding_step", &eos.preceding_step)
+                .field("best_preceding_node", &eos.best_preceding_node)
+                .field("path_cost", &eos.path_cost)
+                .finish(),
+            Node::Middle(middle) => f
+                .debug_struct("Middle")
+                .field("value", &self.lattice.format_value(middle.value.as_any()))
+                .field("index_in_step", &middle.index_in_step)
+                .field("preceding_step", &middle.preceding_step)
+                .field("best_preceding_node", &middle.best_preceding_node)
+                .field("node_cost", &middle.node_cost)
+                .field("path_cost", &middle.path_cost)
+                .finish(),
+        }
+    }
+}
+
+impl Display for NodeDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.node {
+            Node::Bos(_) => write!(f, "BOS"),
+            Node::Eos(_) => write!(f, "EOS"),
+            Node::Middle(middle) => {
+                write!(f, "{}", self.lattice.format_value(middle.value.as_any()))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "inline-values")]
+    use crate::inline_value::InlineValue;
     use crate::string_input::StringInput;
+    use crate::unicode_vocabulary::UnicodeWordVocabulary;
 
     use super::*;
 
@@ -411,6 +592,21 @@ mod tests {
 
             assert!(node.is_err());
         }
+        #[cfg(feature = "inline-values")]
+        {
+            let entry = Entry::new_with_inline_value(
+                Rc::new(StringInput::new(String::from("mizuho"))),
+                InlineValue::I32(42),
+                24,
+            );
+            let node = Node::new_with_entry(&entry, 53, 1, Rc::new(vec![0]), 5, 2424).unwrap();
+
+            assert_eq!(node.value().unwrap().downcast_ref::<i32>(), Some(&42));
+            assert_eq!(
+                Entry::from(&node).value().unwrap().downcast_ref::<i32>(),
+                Some(&42)
+            );
+        }
     }
 
     #[test]
@@ -553,6 +749,89 @@ mod tests {
         assert_eq!(node.node_cost(), 24);
     }
 
+    #[test]
+    fn left_id() {
+        {
+            let entry = Entry::new_with_context_ids(
+                Rc::new(StringInput::new(String::from("mizuho"))),
+                Rc::new(42),
+                24,
+                3,
+                4,
+            );
+            let node =
+                Node::new_with_entry(&entry, 53, 1, Rc::new(vec![3, 1, 4]), 2, 2424).unwrap();
+
+            assert_eq!(node.left_id(), Some(3));
+        }
+        {
+            let node = Node::bos(Rc::new(Vec::new()));
+
+            assert!(node.left_id().is_none());
+        }
+    }
+
+    #[test]
+    fn right_id() {
+        {
+            let entry = Entry::new_with_context_ids(
+                Rc::new(StringInput::new(String::from("mizuho"))),
+                Rc::new(42),
+                24,
+                3,
+                4,
+            );
+            let node =
+                Node::new_with_entry(&entry, 53, 1, Rc::new(vec![3, 1, 4]), 2, 2424).unwrap();
+
+            assert_eq!(node.right_id(), Some(4));
+        }
+        {
+            let entry = Entry::new(
+                Rc::new(StringInput::new(String::from("mizuho"))),
+                Rc::new(42),
+                24,
+            );
+            let node =
+                Node::new_with_entry(&entry, 53, 1, Rc::new(vec![3, 1, 4]), 2, 2424).unwrap();
+
+            assert!(node.right_id().is_none());
+        }
+    }
+
+    #[test]
+    fn provenance() {
+        {
+            let entry = Entry::new(
+                Rc::new(StringInput::new(String::from("mizuho"))),
+                Rc::new(42),
+                24,
+            )
+            .with_provenance(7);
+            let node =
+                Node::new_with_entry(&entry, 53, 1, Rc::new(vec![3, 1, 4]), 2, 2424).unwrap();
+
+            assert_eq!(node.provenance(), Some(7));
+        }
+        {
+            let node = Node::new(
+                Rc::new(StringInput::new(String::from("mizuho"))),
+                Rc::new(42),
+                53,
+                1,
+                Rc::new(vec![3, 1, 4]),
+                2,
+                24,
+                2424,
+            );
+
+            assert!(node.provenance().is_none());
+        }
+        {
+            assert!(Node::bos(Rc::new(Vec::new())).provenance().is_none());
+        }
+    }
+
     #[test]
     fn path_cost() {
         let key = StringInput::new(String::from("mizuho"));
@@ -572,6 +851,47 @@ mod tests {
         assert_eq!(node.path_cost(), 2424);
     }
 
+    #[test]
+    fn input_span() {
+        let vocabulary = UnicodeWordVocabulary::new();
+        let mut lattice = Lattice::new(&vocabulary);
+        for input in ["The", " ", "fox"] {
+            let _delta = lattice
+                .push_back(Box::new(StringInput::new(String::from(input))))
+                .unwrap();
+        }
+
+        {
+            let bos = &lattice.nodes_at(0).unwrap()[0];
+            assert_eq!(bos.input_span(&lattice).unwrap(), (0, 0));
+        }
+        {
+            let node = &lattice.nodes_at(1).unwrap()[0];
+            assert_eq!(node.input_span(&lattice).unwrap(), (0, 3));
+        }
+        {
+            let node = &lattice.nodes_at(3).unwrap()[0];
+            assert_eq!(node.input_span(&lattice).unwrap(), (4, 7));
+        }
+        {
+            let eos = lattice.settle().unwrap();
+            assert_eq!(eos.input_span(&lattice).unwrap(), (7, 7));
+        }
+        {
+            let node = Node::new(
+                Rc::new(StringInput::new(String::from("fox"))),
+                Rc::new(String::from("fox")),
+                53,
+                2,
+                Rc::new(vec![0]),
+                0,
+                1,
+                3,
+            );
+            assert!(node.input_span(&lattice).is_err());
+        }
+    }
+
     #[test]
     fn is_bos() {
         {
@@ -641,4 +961,82 @@ mod tests {
         assert_ne!(bos, node1);
         assert_eq!(node1, node2);
     }
+
+    #[test]
+    fn display() {
+        let vocabulary = UnicodeWordVocabulary::new();
+        let mut lattice = Lattice::new(&vocabulary);
+        let value_formatter = |value: &dyn Any| value.downcast_ref::<String>().unwrap().to_string();
+        lattice.set_value_formatter(&value_formatter);
+        let node = Node::new(
+            Rc::new(StringInput::new(String::from("mizuho"))),
+            Rc::new(String::from("瑞穂")),
+            53,
+            1,
+            Rc::new(vec![0]),
+            0,
+            24,
+            42,
+        );
+
+        assert_eq!(
+            Node::bos(Rc::new(Vec::new())).display(&lattice).to_string(),
+            "BOS"
+        );
+        assert_eq!(
+            Node::eos(1, Rc::new(vec![0]), 0, 42)
+                .display(&lattice)
+                .to_string(),
+            "EOS"
+        );
+        assert_eq!(node.display(&lattice).to_string(), "瑞穂");
+        assert_eq!(
+            format!("{:?}", node.display(&lattice)),
+            "Middle { value: \"瑞穂\", index_in_step: 53, preceding_step: 1, \
+             best_preceding_node: 0, node_cost: 24, path_cost: 42 }"
+        );
+    }
+
+    #[test]
+    fn entry_from() {
+        {
+            let entry = Entry::from(&Node::bos(Rc::new(Vec::new())));
+
+            assert!(matches!(entry, Entry::BosEos));
+        }
+        {
+            let node = Node::new_with_entry(
+                &Entry::new_with_context_ids(
+                    Rc::new(StringInput::new(String::from("mizuho"))),
+                    Rc::new(42),
+                    24,
+                    3,
+                    4,
+                )
+                .with_provenance(7),
+                53,
+                1,
+                Rc::new(vec![0]),
+                0,
+                2424,
+            )
+            .unwrap();
+            let entry = Entry::from(&node);
+
+            assert_eq!(
+                entry
+                    .key()
+                    .unwrap()
+                    .downcast_ref::<StringInput>()
+                    .unwrap()
+                    .value(),
+                "mizuho"
+            );
+            assert_eq!(entry.value().unwrap().downcast_ref::<i32>(), Some(&42));
+            assert_eq!(entry.cost(), 24);
+            assert_eq!(entry.left_id(), Some(3));
+            assert_eq!(entry.right_id(), Some(4));
+            assert_eq!(entry.provenance(), Some(7));
+        }
+    }
 }
@@ -0,0 +1,283 @@
+/*!
+ * Node annotations.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use anyhow::Result;
+
+use crate::lattice::Lattice;
+use crate::node::Node;
+use crate::node_id::NodeId;
+use crate::path::Path;
+
+/**
+ * Node annotations.
+ *
+ * Associates external data with the nodes in a lattice without modifying
+ * them. The annotations are keyed by the node IDs the lattice issues.
+ *
+ * # Type Parameters
+ * * `T` - An annotation type.
+ */
+#[derive(Clone, Debug)]
+pub struct NodeAnnotations<T> {
+    map: HashMap<NodeId, T>,
+}
+
+impl<T> NodeAnnotations<T> {
+    /**
+     * Creates node annotations.
+     */
+    pub fn new() -> Self {
+        Self {
+            map: HashMap::new(),
+        }
+    }
+
+    /**
+     * Returns `true` if there is no annotation.
+     *
+     * # Returns
+     * `true` if there is no annotation.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /**
+     * Returns the annotation count.
+     *
+     * # Returns
+     * The annotation count.
+     */
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /**
+     * Returns the annotation of the node.
+     *
+     * # Arguments
+     * * `node_id` - A node ID.
+     *
+     * # Returns
+     * The annotation. Or None when the node has no annotation.
+     */
+    pub fn get(&self, node_id: NodeId) -> Option<&T> {
+        self.map.get(&node_id)
+    }
+
+    /**
+     * Returns the mutable annotation of the node.
+     *
+     * # Arguments
+     * * `node_id` - A node ID.
+     *
+     * # Returns
+     * The mutable annotation. Or None when the node has no annotation.
+     */
+    pub fn get_mut(&mut self, node_id: NodeId) -> Option<&mut T> {
+        self.map.get_mut(&node_id)
+    }
+
+    /**
+     * Inserts an annotation of the node.
+     *
+     * # Arguments
+     * * `node_id`    - A node ID.
+     * * `annotation` - An annotation.
+     *
+     * # Returns
+     * The previous annotation. Or None when the node had no annotation.
+     */
+    pub fn insert(&mut self, node_id: NodeId, annotation: T) -> Option<T> {
+        self.map.insert(node_id, annotation)
+    }
+
+    /**
+     * Removes the annotation of the node.
+     *
+     * # Arguments
+     * * `node_id` - A node ID.
+     *
+     * # Returns
+     * The removed annotation. Or None when the node had no annotation.
+     */
+    pub fn remove(&mut self, node_id: NodeId) -> Option<T> {
+        self.map.remove(&node_id)
+    }
+
+    /**
+     * Returns the annotation of the node in the lattice.
+     *
+     * # Arguments
+     * * `lattice` - A lattice.
+     * * `node`    - A node.
+     *
+     * # Returns
+     * The annotation. Or None when the node has no annotation.
+     *
+     * # Errors
+     * * When the node is not found in the lattice.
+     */
+    pub fn of_node(&self, lattice: &Lattice<'_>, node: &Node) -> Result<Option<&T>> {
+        Ok(self.get(lattice.node_id(node)?))
+    }
+
+    /**
+     * Returns the annotations of the nodes in the path.
+     *
+     * # Arguments
+     * * `lattice` - A lattice.
+     * * `path`    - A path.
+     *
+     * # Returns
+     * The annotations in the order of the nodes in the path.
+     *
+     * # Errors
+     * * When a node in the path is not found in the lattice.
+     */
+    pub fn of_path(&self, lattice: &Lattice<'_>, path: &Path) -> Result<Vec<Option<&T>>> {
+        path.nodes()
+            .iter()
+            .map(|node| self.of_node(lattice, node))
+            .collect()
+    }
+}
+
+impl<T> Default for NodeAnnotations<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constraint::Constraint;
+    use crate::n_best_iterator::NBestIterator;
+    use crate::string_input::StringInput;
+    use crate::unicode_vocabulary::UnicodeWordVocabulary;
+
+    use super::*;
+
+    fn make_lattice(vocabulary: &UnicodeWordVocabulary) -> Lattice<'_> {
+        let mut lattice = Lattice::new(vocabulary);
+        for input in ["The", " ", "fox"] {
+            let _delta = lattice
+                .push_back(Box::new(StringInput::new(String::from(input))))
+                .unwrap();
+        }
+        lattice
+    }
+
+    #[test]
+    fn new() {
+        let annotations = NodeAnnotations::<String>::new();
+
+        assert!(annotations.is_empty());
+    }
+
+    #[test]
+    fn is_empty() {
+        let mut annotations = NodeAnnotations::new();
+        assert!(annotations.is_empty());
+
+        let _prev_value = annotations.insert(NodeId::new(1, 0), "noun");
+        assert!(!annotations.is_empty());
+    }
+
+    #[test]
+    fn len() {
+        let mut annotations = NodeAnnotations::new();
+        assert_eq!(annotations.len(), 0);
+
+        let _prev_value = annotations.insert(NodeId::new(1, 0), "det");
+        let _prev_value = annotations.insert(NodeId::new(3, 0), "noun");
+        assert_eq!(annotations.len(), 2);
+    }
+
+    #[test]
+    fn get() {
+        let mut annotations = NodeAnnotations::new();
+        let _prev_value = annotations.insert(NodeId::new(1, 0), "det");
+
+        assert_eq!(annotations.get(NodeId::new(1, 0)), Some(&"det"));
+        assert!(annotations.get(NodeId::new(3, 0)).is_none());
+    }
+
+    #[test]
+    fn get_mut() {
+        let mut annotations = NodeAnnotations::new();
+        let _prev_value = annotations.insert(NodeId::new(1, 0), String::from("det"));
+
+        annotations
+            .get_mut(NodeId::new(1, 0))
+            .unwrap()
+            .push_str("erminer");
+
+        assert_eq!(annotations.get(NodeId::new(1, 0)).unwrap(), "determiner");
+        assert!(annotations.get_mut(NodeId::new(3, 0)).is_none());
+    }
+
+    #[test]
+    fn insert() {
+        let mut annotations = NodeAnnotations::new();
+
+        assert!(annotations.insert(NodeId::new(1, 0), "det").is_none());
+        assert_eq!(annotations.insert(NodeId::new(1, 0), "noun"), Some("det"));
+        assert_eq!(annotations.get(NodeId::new(1, 0)), Some(&"noun"));
+    }
+
+    #[test]
+    fn remove() {
+        let mut annotations = NodeAnnotations::new();
+        let _prev_value = annotations.insert(NodeId::new(1, 0), "det");
+
+        assert_eq!(annotations.remove(NodeId::new(1, 0)), Some("det"));
+        assert!(annotations.remove(NodeId::new(1, 0)).is_none());
+        assert!(annotations.is_empty());
+    }
+
+    #[test]
+    fn of_node() {
+        let vocabulary = UnicodeWordVocabulary::new();
+        let lattice = make_lattice(&vocabulary);
+        let mut annotations = NodeAnnotations::new();
+        let node = &lattice.nodes_at(3).unwrap()[0];
+        let _prev_value = annotations.insert(lattice.node_id(node).unwrap(), "noun");
+
+        assert_eq!(annotations.of_node(&lattice, node).unwrap(), Some(&"noun"));
+        assert!(annotations
+            .of_node(&lattice, &lattice.nodes_at(1).unwrap()[0])
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn of_path() {
+        let vocabulary = UnicodeWordVocabulary::new();
+        let mut lattice = make_lattice(&vocabulary);
+        let mut annotations = NodeAnnotations::new();
+        let _prev_value = annotations.insert(
+            lattice.node_id(&lattice.nodes_at(1).unwrap()[0]).unwrap(),
+            "det",
+        );
+        let _prev_value = annotations.insert(
+            lattice.node_id(&lattice.nodes_at(3).unwrap()[0]).unwrap(),
+            "noun",
+        );
+        let eos_node = lattice.settle().unwrap();
+
+        let mut iterator = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()));
+        let path = iterator.next().unwrap();
+
+        assert_eq!(
+            annotations.of_path(&lattice, &path).unwrap(),
+            [None, Some(&"det"), None, Some(&"noun"), None]
+        );
+    }
+}
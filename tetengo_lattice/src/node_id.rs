@@ -0,0 +1,79 @@
+/*!
+ * A node ID.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+/**
+ * A node ID.
+ *
+ * A node ID identifies a node in a lattice. It is stable within the lattice
+ * since the lattice only appends steps.
+ */
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NodeId {
+    step: usize,
+    index_in_step: usize,
+}
+
+impl NodeId {
+    /**
+     * Creates a node ID.
+     *
+     * # Arguments
+     * * `step`          - A step.
+     * * `index_in_step` - An index in the step.
+     */
+    pub const fn new(step: usize, index_in_step: usize) -> Self {
+        Self {
+            step,
+            index_in_step,
+        }
+    }
+
+    /**
+     * Returns the step.
+     *
+     * The step of the EOS node is the one next to its preceding step.
+     *
+     * # Returns
+     * The step.
+     */
+    pub const fn step(&self) -> usize {
+        self.step
+    }
+
+    /**
+     * Returns the index in the step.
+     *
+     * # Returns
+     * The index in the step. Or `usize::MAX` for the EOS node.
+     */
+    pub const fn index_in_step(&self) -> usize {
+        self.index_in_step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new() {
+        let _node_id = NodeId::new(2, 4);
+    }
+
+    #[test]
+    fn step() {
+        let node_id = NodeId::new(2, 4);
+
+        assert_eq!(node_id.step(), 2);
+    }
+
+    #[test]
+    fn index_in_step() {
+        let node_id = NodeId::new(2, 4);
+
+        assert_eq!(node_id.index_in_step(), 4);
+    }
+}
@@ -4,10 +4,28 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use crate::node::Node;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+use anyhow::Result;
+
+use crate::entry::Entry;
+use crate::input::Input;
+use crate::lattice::Lattice;
+use crate::node::{Node, NodeError};
 
 /**
  * A path.
+ *
+ * The paths are equal when their nodes and costs are equal. They are ordered
+ * by their costs, and then by their lengths. The paths with the same cost and
+ * length are ordered by the positions of their nodes in the lattice.
+ *
+ * The ordering is consistent with the equality only among the paths in a
+ * single lattice. The paths in different lattices can be ordered as equal
+ * even when their nodes are not equal.
  */
 #[derive(Debug, Default)]
 pub struct Path {
@@ -56,14 +74,577 @@ impl Path {
     pub const fn cost(&self) -> i32 {
         self.cost
     }
+
+    /**
+     * Returns the cost breakdown.
+     *
+     * The costs are accumulated in the same way as the lattice does. An edge
+     * cost not found in the preceding edge costs of the node is regarded as
+     * `i32::MAX`.
+     *
+     * # Returns
+     * The cost breakdown.
+     */
+    pub fn cost_breakdown(&self) -> PathCostBreakdown {
+        let mut node_cost = self.nodes.first().map_or(0, Node::node_cost);
+        let mut edge_cost = 0;
+        let mut transitions = Vec::with_capacity(self.nodes.len().saturating_sub(1));
+        for pair in self.nodes.windows(2) {
+            let transition = TransitionCost {
+                edge_cost: pair[1]
+                    .preceding_edge_costs()
+                    .get(pair[0].index_in_step())
+                    .copied()
+                    .unwrap_or(i32::MAX),
+                node_cost: pair[1].node_cost(),
+            };
+            edge_cost = add_cost(edge_cost, transition.edge_cost);
+            node_cost = add_cost(node_cost, transition.node_cost);
+            transitions.push(transition);
+        }
+        PathCostBreakdown {
+            node_cost,
+            edge_cost,
+            transitions,
+        }
+    }
+
+    /**
+     * Verifies the costs of this path against a lattice.
+     *
+     * The node costs are compared with the ones of the nodes in the lattice.
+     * The edge costs are recalculated with the vocabulary of the lattice,
+     * bypassing the connection cache. Then the cost of this path is compared
+     * with the sum of them. It detects the vocabularies whose connections are
+     * not deterministic.
+     *
+     * When the lattice has a higher order scorer, the edge costs are
+     * calculated with the contexts on the best paths, as the lattice does. So
+     * the cost of a path other than the best one may mismatch.
+     *
+     * # Arguments
+     * * `lattice` - The lattice which this path comes from.
+     *
+     * # Errors
+     * * When a cost mismatches. The error is `CostMismatch` describing the
+     *   first mismatch.
+     * * When a node is not found in the lattice.
+     * * When finding a connection fails.
+     */
+    pub fn verify_against(&self, lattice: &Lattice<'_>) -> Result<()> {
+        let mut cost = 0;
+        for (i, node) in self.nodes.iter().enumerate() {
+            if i > 0 {
+                let preceding_node = &self.nodes[i - 1];
+                let expected = lattice.recalculate_edge_cost(preceding_node, &Entry::from(node))?;
+                let actual = node
+                    .preceding_edge_costs()
+                    .get(preceding_node.index_in_step())
+                    .copied()
+                    .unwrap_or(i32::MAX);
+                if actual != expected {
+                    return Err(CostMismatch::new(CostKind::Edge, i, expected, actual).into());
+                }
+                cost = add_cost(cost, expected);
+            }
+
+            let step = match node {
+                Node::Bos(_) => Some(0),
+                Node::Eos(_) => None,
+                Node::Middle(_) => self.nodes.get(i + 1).map(Node::preceding_step),
+            };
+            let expected = if let Some(step) = step {
+                lattice
+                    .nodes_at(step)?
+                    .get(node.index_in_step())
+                    .ok_or(NodeError::NodeNotFoundInLattice)?
+                    .node_cost()
+            } else {
+                node.node_cost()
+            };
+            if node.node_cost() != expected {
+                return Err(
+                    CostMismatch::new(CostKind::Node, i, expected, node.node_cost()).into(),
+                );
+            }
+            cost = add_cost(cost, expected);
+        }
+        if !self.nodes.is_empty() && self.cost != cost {
+            return Err(
+                CostMismatch::new(CostKind::Path, self.nodes.len() - 1, cost, self.cost).into(),
+            );
+        }
+        Ok(())
+    }
+
+    /**
+     * Compares this path with another.
+     *
+     * The middle nodes of the paths are aligned by their spans of steps. A
+     * segment ends where both paths have a node boundary at the same step. A
+     * segment is shared when both paths have the same single node in it.
+     *
+     * Both paths must come from the same lattice. The input span of a step
+     * span `(offset, tail)` is obtained with `Lattice::step_span`.
+     *
+     * # Arguments
+     * * `other` - Another path.
+     *
+     * # Returns
+     * The segments in the order of the steps.
+     */
+    pub fn diff(&self, other: &Path) -> Vec<PathDiffSegment> {
+        let one = middle_step_spans(&self.nodes);
+        let another = middle_step_spans(&other.nodes);
+        let mut segments = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < one.len() || j < another.len() {
+            let (first_i, first_j) = (i, j);
+            loop {
+                match (one.get(i), another.get(j)) {
+                    (Some((_, (_, one_tail))), Some((_, (_, another_tail)))) => {
+                        match one_tail.cmp(another_tail) {
+                            Ordering::Equal => {
+                                i += 1;
+                                j += 1;
+                                break;
+                            }
+                            Ordering::Less => i += 1,
+                            Ordering::Greater => j += 1,
+                        }
+                    }
+                    (Some(_), None) => i += 1,
+                    (None, Some(_)) => j += 1,
+                    (None, None) => break,
+                }
+            }
+
+            let nodes = node_range(&one[first_i..i]);
+            let other_nodes = node_range(&another[first_j..j]);
+            let offset = one
+                .get(first_i)
+                .into_iter()
+                .chain(another.get(first_j))
+                .map(|&(_, (offset, _))| offset)
+                .min()
+                .unwrap_or_default();
+            let tail = one[first_i..i]
+                .last()
+                .into_iter()
+                .chain(another[first_j..j].last())
+                .map(|&(_, (_, tail))| tail)
+                .max()
+                .unwrap_or_default();
+            let shared = nodes.len() == 1
+                && other_nodes.len() == 1
+                && one[first_i].1 == another[first_j].1
+                && self.nodes[nodes.start].index_in_step()
+                    == other.nodes[other_nodes.start].index_in_step();
+            segments.push(PathDiffSegment {
+                step_span: (offset, tail),
+                nodes,
+                other_nodes,
+                shared,
+            });
+        }
+        segments
+    }
+
+    /**
+     * Returns the surface spans of the middle nodes.
+     *
+     * A surface span is the offset and the tail of the span of the input which
+     * the node corresponds to, and the key of the node. The spans are the
+     * ranges in the input accumulated in the lattice, so they can be used to
+     * map the nodes back to the original input.
+     *
+     * # Arguments
+     * * `lattice` - The lattice which this path comes from.
+     *
+     * # Returns
+     * The surface spans in the order of the nodes. BOS and EOS are excluded.
+     *
+     * # Errors
+     * * When a node is not found in the lattice.
+     */
+    pub fn surface_spans(&self, lattice: &Lattice<'_>) -> Result<Vec<(usize, usize, &dyn Input)>> {
+        let mut spans = Vec::with_capacity(self.nodes.len().saturating_sub(2));
+        for node in &self.nodes {
+            let Some(key) = node.key() else {
+                continue;
+            };
+            let (offset, tail) = node.input_span(lattice)?;
+            spans.push((offset, tail, key));
+        }
+        Ok(spans)
+    }
+
+    /**
+     * Returns a displayable view of this path.
+     *
+     * The values are formatted with the value formatter of the lattice.
+     *
+     * # Arguments
+     * * `lattice` - A lattice.
+     *
+     * # Returns
+     * A displayable view.
+     */
+    pub const fn display<'b>(&'b self, lattice: &'b Lattice<'b>) -> PathDisplay<'b> {
+        PathDisplay {
+            path: self,
+            lattice,
+        }
+    }
+}
+
+impl Eq for Path {}
+
+impl PartialEq for Path {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost && self.nodes == other.nodes
+    }
+}
+
+impl Ord for Path {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cost
+            .cmp(&other.cost)
+            .then_with(|| self.nodes.len().cmp(&other.nodes.len()))
+            .then_with(|| {
+                self.nodes
+                    .iter()
+                    .map(node_position)
+                    .cmp(other.nodes.iter().map(node_position))
+            })
+    }
+}
+
+impl PartialOrd for Path {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hash for Path {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.cost.hash(state);
+        self.nodes.len().hash(state);
+        for node in &self.nodes {
+            node_position(node).hash(state);
+            node.key().map(|key| key.hash_value()).hash(state);
+        }
+    }
+}
+
+// The preceding step and the index in the step do not identify a node by
+// themselves, since the nodes in different steps may share them. But the step
+// of a node in a path is the preceding step of the next node, so the positions
+// of all the nodes identify the nodes of a path in a lattice.
+fn node_position(node: &Node) -> (usize, usize) {
+    (node.preceding_step(), node.index_in_step())
+}
+
+fn middle_step_spans(nodes: &[Node]) -> Vec<(usize, (usize, usize))> {
+    nodes
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| matches!(pair[0], Node::Middle(_)))
+        .map(|(i, pair)| (i, (pair[0].preceding_step(), pair[1].preceding_step())))
+        .collect()
+}
+
+fn node_range(step_spans: &[(usize, (usize, usize))]) -> Range<usize> {
+    match (step_spans.first(), step_spans.last()) {
+        (Some(&(first, _)), Some(&(last, _))) => first..last + 1,
+        _ => 0..0,
+    }
+}
+
+/**
+ * A segment of a difference between two paths.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PathDiffSegment {
+    step_span: (usize, usize),
+    nodes: Range<usize>,
+    other_nodes: Range<usize>,
+    shared: bool,
+}
+
+impl PathDiffSegment {
+    /**
+     * Returns the span of steps.
+     *
+     * # Returns
+     * The preceding step of the first nodes and the step of the last nodes.
+     */
+    pub const fn step_span(&self) -> (usize, usize) {
+        self.step_span
+    }
+
+    /**
+     * Returns the indices of the nodes of the path in this segment.
+     *
+     * # Returns
+     * The range of the indices in the nodes of the path.
+     */
+    pub fn nodes(&self) -> Range<usize> {
+        self.nodes.clone()
+    }
+
+    /**
+     * Returns the indices of the nodes of the other path in this segment.
+     *
+     * # Returns
+     * The range of the indices in the nodes of the other path.
+     */
+    pub fn other_nodes(&self) -> Range<usize> {
+        self.other_nodes.clone()
+    }
+
+    /**
+     * Returns `true` if both paths have the same node in this segment.
+     *
+     * # Returns
+     * `true` if this segment is shared.
+     */
+    pub const fn is_shared(&self) -> bool {
+        self.shared
+    }
+}
+
+/**
+ * A cost breakdown of a path.
+ */
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PathCostBreakdown {
+    node_cost: i32,
+    edge_cost: i32,
+    transitions: Vec<TransitionCost>,
+}
+
+impl PathCostBreakdown {
+    /**
+     * Returns the sum of the node costs.
+     *
+     * # Returns
+     * The sum of the node costs.
+     */
+    pub const fn node_cost(&self) -> i32 {
+        self.node_cost
+    }
+
+    /**
+     * Returns the sum of the edge costs.
+     *
+     * # Returns
+     * The sum of the edge costs.
+     */
+    pub const fn edge_cost(&self) -> i32 {
+        self.edge_cost
+    }
+
+    /**
+     * Returns the total cost.
+     *
+     * # Returns
+     * The total cost.
+     */
+    pub const fn total(&self) -> i32 {
+        add_cost(self.node_cost, self.edge_cost)
+    }
+
+    /**
+     * Returns the transition costs.
+     *
+     * # Returns
+     * The transition costs in the order of the nodes in the path.
+     */
+    pub fn transitions(&self) -> &[TransitionCost] {
+        self.transitions.as_slice()
+    }
+}
+
+/**
+ * A cost of a transition to a node.
+ */
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TransitionCost {
+    edge_cost: i32,
+    node_cost: i32,
+}
+
+impl TransitionCost {
+    /**
+     * Returns the cost of the edge from the preceding node.
+     *
+     * # Returns
+     * The edge cost.
+     */
+    pub const fn edge_cost(&self) -> i32 {
+        self.edge_cost
+    }
+
+    /**
+     * Returns the cost of the node.
+     *
+     * # Returns
+     * The node cost.
+     */
+    pub const fn node_cost(&self) -> i32 {
+        self.node_cost
+    }
+}
+
+/**
+ * A kind of a cost.
+ */
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CostKind {
+    /// The cost of a node.
+    Node,
+
+    /// The cost of the edge from the preceding node.
+    Edge,
+
+    /// The cost of the whole path.
+    Path,
+}
+
+/**
+ * A cost mismatch of a path.
+ */
+#[derive(Clone, Copy, Debug, Eq, PartialEq, thiserror::Error)]
+#[error("the {kind:?} cost at the node {index} is {actual}, but {expected} is expected")]
+pub struct CostMismatch {
+    kind: CostKind,
+    index: usize,
+    expected: i32,
+    actual: i32,
+}
+
+impl CostMismatch {
+    /**
+     * Creates a cost mismatch.
+     *
+     * # Arguments
+     * * `kind`     - A kind of the cost.
+     * * `index`    - An index of the node in the path.
+     * * `expected` - An expected cost.
+     * * `actual`   - An actual cost.
+     */
+    pub const fn new(kind: CostKind, index: usize, expected: i32, actual: i32) -> Self {
+        Self {
+            kind,
+            index,
+            expected,
+            actual,
+        }
+    }
+
+    /**
+     * Returns the kind of the cost.
+     *
+     * # Returns
+     * The kind of the cost.
+     */
+    pub const fn kind(&self) -> CostKind {
+        self.kind
+    }
+
+    /**
+     * Returns the index of the node.
+     *
+     * # Returns
+     * The index of the node in the path. The last index for the cost of the
+     * whole path.
+     */
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    /**
+     * Returns the expected cost.
+     *
+     * # Returns
+     * The cost recalculated with the lattice.
+     */
+    pub const fn expected(&self) -> i32 {
+        self.expected
+    }
+
+    /**
+     * Returns the actual cost.
+     *
+     * # Returns
+     * The cost in the path.
+     */
+    pub const fn actual(&self) -> i32 {
+        self.actual
+    }
+}
+
+const fn add_cost(one: i32, another: i32) -> i32 {
+    if one == i32::MAX || another == i32::MAX {
+        i32::MAX
+    } else {
+        one + another
+    }
+}
+
+/**
+ * A displayable view of a path.
+ */
+#[derive(Clone, Copy)]
+pub struct PathDisplay<'a> {
+    path: &'a Path,
+    lattice: &'a Lattice<'a>,
+}
+
+impl Debug for PathDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Path")
+            .field(
+                "nodes",
+                &self
+                    .path
+                    .nodes
+                    .iter()
+                    .map(|node| node.display(self.lattice))
+                    .collect::<Vec<_>>(),
+            )
+            .field("cost", &self.path.cost)
+            .finish()
+    }
+}
+
+impl Display for PathDisplay<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, node) in self.path.nodes.iter().enumerate() {
+            if i > 0 {
+                write!(f, " -> ")?;
+            }
+            write!(f, "{}", node.display(self.lattice))?;
+        }
+        write!(f, " ({})", self.path.cost)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::any::Any;
+    use std::cell::Cell;
+    use std::collections::HashSet;
     use std::rc::Rc;
     use std::sync::LazyLock;
 
+    use crate::connection::Connection;
+    use crate::constraint::Constraint;
+    use crate::n_best_iterator::NBestIterator;
     use crate::string_input::StringInput;
+    use crate::unicode_vocabulary::UnicodeWordVocabulary;
+    use crate::vocabulary::Vocabulary;
 
     use super::*;
 
@@ -147,4 +728,354 @@ mod tests {
         let path = Path::new(make_nodes(), 42);
         assert_eq!(path.cost(), 42);
     }
+
+    #[test]
+    fn cost_breakdown() {
+        {
+            let path = Path::new(Vec::new(), 0);
+            let breakdown = path.cost_breakdown();
+
+            assert_eq!(breakdown.node_cost(), 0);
+            assert_eq!(breakdown.edge_cost(), 0);
+            assert_eq!(breakdown.total(), 0);
+            assert!(breakdown.transitions().is_empty());
+        }
+        {
+            let nodes = vec![
+                Node::bos(Rc::new(BOS_PRECEDING_EDGE_COSTS)),
+                Node::new(
+                    Rc::new(StringInput::new(String::from("mizuho"))),
+                    Rc::new(NODE_VALUE),
+                    0,
+                    0,
+                    Rc::new(vec![3]),
+                    0,
+                    10,
+                    13,
+                ),
+                Node::new(
+                    Rc::new(StringInput::new(String::from("sakura"))),
+                    Rc::new(NODE_VALUE),
+                    1,
+                    1,
+                    Rc::new(vec![5]),
+                    0,
+                    20,
+                    38,
+                ),
+                Node::eos(2, Rc::new(vec![7, 8]), 1, 46),
+            ];
+            let path = Path::new(nodes, 46);
+            let breakdown = path.cost_breakdown();
+
+            assert_eq!(breakdown.node_cost(), 30);
+            assert_eq!(breakdown.edge_cost(), 16);
+            assert_eq!(breakdown.total(), path.cost());
+            let transitions = breakdown
+                .transitions()
+                .iter()
+                .map(|transition| (transition.edge_cost(), transition.node_cost()))
+                .collect::<Vec<_>>();
+            assert_eq!(transitions, [(3, 10), (5, 20), (8, 0)]);
+        }
+        {
+            let path = Path::new(make_nodes(), 42);
+            let breakdown = path.cost_breakdown();
+
+            assert_eq!(breakdown.node_cost(), 0);
+            assert_eq!(breakdown.edge_cost(), 4);
+            assert_eq!(breakdown.transitions().len(), 4);
+        }
+        {
+            let vocabulary = UnicodeWordVocabulary::new();
+            let mut lattice = Lattice::new(&vocabulary);
+            for c in "Hi, Kumamoto".chars() {
+                let _delta = lattice
+                    .push_back(Box::new(StringInput::new(c.to_string())))
+                    .unwrap();
+            }
+            let eos_node = lattice.settle().unwrap();
+            let path = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()))
+                .next()
+                .unwrap();
+            let breakdown = path.cost_breakdown();
+
+            let nodes = path.nodes();
+            let expected_transitions = nodes
+                .windows(2)
+                .map(|pair| {
+                    (
+                        pair[1].preceding_edge_costs()[pair[0].index_in_step()],
+                        pair[1].node_cost(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let transitions = breakdown
+                .transitions()
+                .iter()
+                .map(|transition| (transition.edge_cost(), transition.node_cost()))
+                .collect::<Vec<_>>();
+            assert_eq!(transitions, expected_transitions);
+            assert_eq!(
+                breakdown.node_cost(),
+                nodes.iter().map(Node::node_cost).sum::<i32>()
+            );
+            assert_eq!(
+                breakdown.edge_cost(),
+                expected_transitions
+                    .iter()
+                    .map(|&(edge_cost, _)| edge_cost)
+                    .sum::<i32>()
+            );
+            assert_eq!(breakdown.total(), path.cost());
+        }
+    }
+
+    #[test]
+    fn verify_against() {
+        #[derive(Debug)]
+        struct UnstableVocabulary {
+            vocabulary: UnicodeWordVocabulary,
+            connection_cost: Cell<i32>,
+        }
+
+        impl Vocabulary for UnstableVocabulary {
+            fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>> {
+                self.vocabulary.find_entries(key)
+            }
+
+            fn find_connection(&self, _: &Node, _: &Entry) -> Result<Connection> {
+                Ok(Connection::new(self.connection_cost.get()))
+            }
+        }
+
+        let vocabulary = UnstableVocabulary {
+            vocabulary: UnicodeWordVocabulary::new(),
+            connection_cost: Cell::new(10),
+        };
+        let mut lattice = Lattice::new(&vocabulary);
+        for c in "Hi, Kumamoto".chars() {
+            let _delta = lattice
+                .push_back(Box::new(StringInput::new(c.to_string())))
+                .unwrap();
+        }
+        let eos_node = lattice.settle().unwrap();
+        let path = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()))
+            .next()
+            .unwrap();
+
+        path.verify_against(&lattice).unwrap();
+        Path::new(Vec::new(), 0).verify_against(&lattice).unwrap();
+
+        {
+            let tampered_path = Path::new(path.nodes().to_vec(), path.cost() + 1);
+            let error = tampered_path.verify_against(&lattice).unwrap_err();
+            assert_eq!(
+                *error.downcast_ref::<CostMismatch>().unwrap(),
+                CostMismatch::new(
+                    CostKind::Path,
+                    path.nodes().len() - 1,
+                    path.cost(),
+                    path.cost() + 1
+                )
+            );
+        }
+        {
+            vocabulary.connection_cost.set(20);
+            let error = path.verify_against(&lattice).unwrap_err();
+            let mismatch = error.downcast_ref::<CostMismatch>().unwrap();
+            assert_eq!(mismatch.kind(), CostKind::Edge);
+            assert_eq!(mismatch.index(), 1);
+            assert_eq!(mismatch.expected(), 20);
+            assert_eq!(mismatch.actual(), 10);
+        }
+    }
+
+    #[test]
+    fn diff() {
+        let node = |key: &str, index_in_step: usize, preceding_step: usize| {
+            Node::new(
+                Rc::new(StringInput::new(String::from(key))),
+                Rc::new(NODE_VALUE),
+                index_in_step,
+                preceding_step,
+                Rc::new(PRECEDING_EDGE_COSTS.clone()),
+                0,
+                0,
+                0,
+            )
+        };
+        let path1 = Path::new(
+            vec![
+                Node::bos(Rc::new(BOS_PRECEDING_EDGE_COSTS)),
+                node("kamome", 0, 0),
+                node("local815", 0, 2),
+                Node::eos(3, Rc::new(PRECEDING_EDGE_COSTS.clone()), 0, 0),
+            ],
+            42,
+        );
+        let path2 = Path::new(
+            vec![
+                Node::bos(Rc::new(BOS_PRECEDING_EDGE_COSTS)),
+                node("local415", 1, 0),
+                node("local417", 0, 1),
+                node("local815", 0, 2),
+                Node::eos(3, Rc::new(PRECEDING_EDGE_COSTS.clone()), 0, 0),
+            ],
+            42,
+        );
+        let path3 = Path::new(
+            vec![
+                Node::bos(Rc::new(BOS_PRECEDING_EDGE_COSTS)),
+                node("kamome", 0, 0),
+                node("local813", 1, 2),
+                Node::eos(3, Rc::new(PRECEDING_EDGE_COSTS.clone()), 0, 0),
+            ],
+            42,
+        );
+
+        {
+            let segments = path1.diff(&path2);
+
+            assert_eq!(segments.len(), 2);
+            assert_eq!(segments[0].step_span(), (0, 2));
+            assert_eq!(segments[0].nodes(), 1..2);
+            assert_eq!(segments[0].other_nodes(), 1..3);
+            assert!(!segments[0].is_shared());
+            assert_eq!(segments[1].step_span(), (2, 3));
+            assert_eq!(segments[1].nodes(), 2..3);
+            assert_eq!(segments[1].other_nodes(), 3..4);
+            assert!(segments[1].is_shared());
+        }
+        {
+            let segments = path1.diff(&path3);
+
+            let shared = segments
+                .iter()
+                .map(PathDiffSegment::is_shared)
+                .collect::<Vec<_>>();
+            assert_eq!(shared, [true, false]);
+        }
+        {
+            let segments = path2.diff(&path2);
+
+            assert_eq!(segments.len(), 3);
+            assert!(segments.iter().all(PathDiffSegment::is_shared));
+        }
+        {
+            let segments = path1.diff(&Path::new(Vec::new(), 0));
+
+            assert_eq!(segments.len(), 1);
+            assert_eq!(segments[0].step_span(), (0, 3));
+            assert_eq!(segments[0].nodes(), 1..3);
+            assert!(segments[0].other_nodes().is_empty());
+            assert!(!segments[0].is_shared());
+        }
+    }
+
+    #[test]
+    fn surface_spans() {
+        let vocabulary = UnicodeWordVocabulary::new();
+        let mut lattice = Lattice::new(&vocabulary);
+        for c in "Hi, Kumamoto".chars() {
+            let _delta = lattice
+                .push_back(Box::new(StringInput::new(c.to_string())))
+                .unwrap();
+        }
+        let eos_node = lattice.settle().unwrap();
+        let path = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()))
+            .next()
+            .unwrap();
+
+        let spans = path
+            .surface_spans(&lattice)
+            .unwrap()
+            .into_iter()
+            .map(|(offset, tail, key)| {
+                (
+                    offset,
+                    tail,
+                    key.downcast_ref::<StringInput>().unwrap().value(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            spans,
+            [(0, 2, "Hi"), (2, 3, ","), (3, 4, " "), (4, 12, "Kumamoto")]
+        );
+
+        assert!(Path::new(Vec::new(), 0)
+            .surface_spans(&lattice)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn display() {
+        let vocabulary = UnicodeWordVocabulary::new();
+        let mut lattice = Lattice::new(&vocabulary);
+        let value_formatter =
+            |value: &dyn Any| format!("<{}>", value.downcast_ref::<i32>().unwrap());
+        lattice.set_value_formatter(&value_formatter);
+        let path = Path::new(make_nodes(), 42);
+
+        assert_eq!(
+            path.display(&lattice).to_string(),
+            "BOS -> <42> -> <42> -> <42> -> EOS (42)"
+        );
+        assert!(format!("{:?}", path.display(&lattice)).contains("value: \"<42>\""));
+    }
+
+    #[test]
+    fn eq() {
+        assert_eq!(Path::new(make_nodes(), 42), Path::new(make_nodes(), 42));
+        assert_ne!(Path::new(make_nodes(), 42), Path::new(make_nodes(), 24));
+        assert_ne!(
+            Path::new(make_nodes(), 42),
+            Path::new(make_nodes()[..3].to_vec(), 42)
+        );
+        assert_eq!(Path::new(Vec::new(), 0), Path::default());
+    }
+
+    #[test]
+    fn cmp() {
+        let mut shorter_nodes = make_nodes();
+        let _removed = shorter_nodes.remove(2);
+        let mut swapped_nodes = make_nodes();
+        swapped_nodes.swap(1, 2);
+
+        let mut paths = [
+            Path::new(make_nodes(), 42),
+            Path::new(swapped_nodes, 42),
+            Path::new(make_nodes(), 24),
+            Path::new(shorter_nodes, 42),
+        ];
+        paths.sort();
+
+        assert_eq!(
+            paths.iter().map(|path| path.cost()).collect::<Vec<_>>(),
+            [24, 42, 42, 42]
+        );
+        assert_eq!(paths[1].nodes().len(), 4);
+        assert_eq!(paths[2], Path::new(make_nodes(), 42));
+        assert_eq!(
+            Path::new(make_nodes(), 42).cmp(&Path::new(make_nodes(), 42)),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn hash() {
+        let paths = [
+            Path::new(make_nodes(), 42),
+            Path::new(make_nodes(), 42),
+            Path::new(make_nodes(), 24),
+            Path::new(Vec::new(), 0),
+        ]
+        .into_iter()
+        .collect::<HashSet<_>>();
+
+        assert_eq!(paths.len(), 3);
+        assert!(paths.contains(&Path::new(make_nodes(), 24)));
+    }
 }
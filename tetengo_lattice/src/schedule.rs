@@ -0,0 +1,737 @@
+/*!
+ * A schedule.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use crate::entry::Entry;
+use crate::entry_view::EntryView;
+use crate::string_input::StringInput;
+use crate::typed_hash_map_vocabulary::TypedHashMapVocabulary;
+use crate::typed_vocabulary::TypedVocabularyAdapter;
+use crate::vocabulary::Vocabulary;
+
+/// The minute count of a day.
+pub const MINUTES_PER_DAY: usize = 1440;
+
+/**
+ * A schedule error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum ScheduleError {
+    /**
+     * A station index is out of range.
+     */
+    #[error("a station index is out of range")]
+    StationIndexOutOfRange,
+
+    /**
+     * The departure station is not before the arrival station.
+     */
+    #[error("the departure station is not before the arrival station")]
+    InvalidSection,
+
+    /**
+     * A time is not less than `MINUTES_PER_DAY`.
+     */
+    #[error("a time is not less than MINUTES_PER_DAY")]
+    InvalidTime,
+}
+
+/**
+ * A schedule entry.
+ *
+ * A ride from a departure station to an arrival station without stopping at
+ * the stations between them.
+ *
+ * # Type Parameters
+ * * `P` - A payload type.
+ */
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ScheduleEntry<P> {
+    from: usize,
+    to: usize,
+    departure: usize,
+    arrival: usize,
+    payload: P,
+}
+
+impl<P> ScheduleEntry<P> {
+    /**
+     * Creates a schedule entry.
+     *
+     * # Arguments
+     * * `from`      - A departure station index.
+     * * `to`        - An arrival station index.
+     * * `departure` - A departure time in minutes from midnight.
+     * * `arrival`   - An arrival time in minutes from midnight.
+     * * `payload`   - A payload.
+     */
+    pub const fn new(from: usize, to: usize, departure: usize, arrival: usize, payload: P) -> Self {
+        Self {
+            from,
+            to,
+            departure,
+            arrival,
+            payload,
+        }
+    }
+
+    /**
+     * Returns the departure station index.
+     *
+     * # Returns
+     * The departure station index.
+     */
+    pub const fn from(&self) -> usize {
+        self.from
+    }
+
+    /**
+     * Returns the arrival station index.
+     *
+     * # Returns
+     * The arrival station index.
+     */
+    pub const fn to(&self) -> usize {
+        self.to
+    }
+
+    /**
+     * Returns the departure time.
+     *
+     * # Returns
+     * The departure time in minutes from midnight.
+     */
+    pub const fn departure(&self) -> usize {
+        self.departure
+    }
+
+    /**
+     * Returns the arrival time.
+     *
+     * # Returns
+     * The arrival time in minutes from midnight.
+     */
+    pub const fn arrival(&self) -> usize {
+        self.arrival
+    }
+
+    /**
+     * Returns the payload.
+     *
+     * # Returns
+     * The payload.
+     */
+    pub const fn payload(&self) -> &P {
+        &self.payload
+    }
+
+    /**
+     * Returns the duration.
+     *
+     * An arrival time earlier than the departure time is regarded as the one
+     * of the next day.
+     *
+     * # Returns
+     * The duration in minutes.
+     */
+    pub const fn duration(&self) -> usize {
+        elapsed_time(self.departure, self.arrival)
+    }
+}
+
+/**
+ * A waiting cost.
+ *
+ * Describes how the waiting times are turned into the connection costs.
+ */
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct WaitingCost {
+    max_waiting_time: usize,
+    transfer_penalty: i32,
+    max_initial_waiting_time: usize,
+    initial_waiting_percentage: i32,
+}
+
+impl WaitingCost {
+    /**
+     * Creates a waiting cost.
+     *
+     * # Arguments
+     * * `max_waiting_time`           - The max waiting time between two entries.
+     * * `transfer_penalty`           - The cost added when the payloads of two entries differ.
+     * * `max_initial_waiting_time`   - The max waiting time before the first entry.
+     * * `initial_waiting_percentage` - The percentage of the waiting time before the first entry counted as the cost.
+     */
+    pub const fn new(
+        max_waiting_time: usize,
+        transfer_penalty: i32,
+        max_initial_waiting_time: usize,
+        initial_waiting_percentage: i32,
+    ) -> Self {
+        Self {
+            max_waiting_time,
+            transfer_penalty,
+            max_initial_waiting_time,
+            initial_waiting_percentage,
+        }
+    }
+
+    /**
+     * Returns the max waiting time between two entries.
+     *
+     * Two entries are not connected when the waiting time is longer.
+     *
+     * # Returns
+     * The max waiting time in minutes.
+     */
+    pub const fn max_waiting_time(&self) -> usize {
+        self.max_waiting_time
+    }
+
+    /**
+     * Returns the transfer penalty.
+     *
+     * # Returns
+     * The cost added when the payloads of two entries differ.
+     */
+    pub const fn transfer_penalty(&self) -> i32 {
+        self.transfer_penalty
+    }
+
+    /**
+     * Returns the max waiting time before the first entry.
+     *
+     * An entry does not begin a path when the waiting time from the departure
+     * time is longer.
+     *
+     * # Returns
+     * The max waiting time in minutes.
+     */
+    pub const fn max_initial_waiting_time(&self) -> usize {
+        self.max_initial_waiting_time
+    }
+
+    /**
+     * Returns the percentage of the waiting time before the first entry.
+     *
+     * # Returns
+     * The percentage of the waiting time before the first entry counted as the
+     * cost.
+     */
+    pub const fn initial_waiting_percentage(&self) -> i32 {
+        self.initial_waiting_percentage
+    }
+}
+
+/**
+ * A schedule.
+ *
+ * Makes a time-expanded vocabulary from the schedule entries. The key of an
+ * entry is the concatenation of the section keys between its stations, such as
+ * `"A-B/B-C/"` for an entry from the station A to the station C. So push back
+ * the section keys from the departure station to the arrival station to a
+ * lattice.
+ *
+ * # Type Parameters
+ * * `P` - A payload type.
+ */
+#[derive(Clone, Debug)]
+pub struct Schedule<P> {
+    station_codes: Vec<String>,
+    entries: Vec<ScheduleEntry<P>>,
+}
+
+impl<P: Clone + Debug + Eq + Hash + 'static> Schedule<P> {
+    /**
+     * Creates a schedule.
+     *
+     * # Arguments
+     * * `station_codes` - The codes of the stations in the order along the line.
+     */
+    pub const fn new(station_codes: Vec<String>) -> Self {
+        Self {
+            station_codes,
+            entries: Vec::new(),
+        }
+    }
+
+    /**
+     * Returns the station codes.
+     *
+     * # Returns
+     * The station codes.
+     */
+    pub fn station_codes(&self) -> &[String] {
+        self.station_codes.as_slice()
+    }
+
+    /**
+     * Returns the entries.
+     *
+     * # Returns
+     * The entries.
+     */
+    pub fn entries(&self) -> &[ScheduleEntry<P>] {
+        self.entries.as_slice()
+    }
+
+    /**
+     * Adds an entry.
+     *
+     * # Arguments
+     * * `entry` - An entry.
+     *
+     * # Errors
+     * * When the stations of the entry are out of range or not in order.
+     * * When the times of the entry are not less than `MINUTES_PER_DAY`.
+     */
+    pub fn add_entry(&mut self, entry: ScheduleEntry<P>) -> Result<()> {
+        self.check_section(entry.from(), entry.to())?;
+        if entry.departure() >= MINUTES_PER_DAY || entry.arrival() >= MINUTES_PER_DAY {
+            return Err(ScheduleError::InvalidTime.into());
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /**
+     * Returns the key of a section.
+     *
+     * # Arguments
+     * * `from` - A departure station index.
+     * * `to`   - An arrival station index.
+     *
+     * # Returns
+     * The key.
+     *
+     * # Errors
+     * * When the stations are out of range or not in order.
+     */
+    pub fn section_key(&self, from: usize, to: usize) -> Result<String> {
+        self.check_section(from, to)?;
+        let mut key = String::new();
+        for i in from..to {
+            key += &format!("{}-{}/", self.station_codes[i], self.station_codes[i + 1]);
+        }
+        Ok(key)
+    }
+
+    /**
+     * Returns the inputs of a section.
+     *
+     * Push them back to a lattice in order.
+     *
+     * # Arguments
+     * * `from` - A departure station index.
+     * * `to`   - An arrival station index.
+     *
+     * # Returns
+     * The inputs, one for each pair of the adjacent stations.
+     *
+     * # Errors
+     * * When the stations are out of range or not in order.
+     */
+    pub fn section_inputs(&self, from: usize, to: usize) -> Result<Vec<StringInput>> {
+        self.check_section(from, to)?;
+        (from..to)
+            .map(|i| Ok(StringInput::new(self.section_key(i, i + 1)?)))
+            .collect()
+    }
+
+    fn check_section(&self, from: usize, to: usize) -> Result<()> {
+        if to >= self.station_codes.len() {
+            return Err(ScheduleError::StationIndexOutOfRange.into());
+        }
+        if from >= to {
+            return Err(ScheduleError::InvalidSection.into());
+        }
+        Ok(())
+    }
+
+    /**
+     * Creates a vocabulary.
+     *
+     * The cost of an entry is its duration. The cost of a connection between
+     * two entries is the waiting time between them, plus the transfer penalty
+     * when their payloads differ. The cost of a connection from BOS is the
+     * percentage of the waiting time from the departure time.
+     *
+     * # Arguments
+     * * `departure_time` - A departure time in minutes from midnight.
+     * * `waiting_cost`   - A waiting cost.
+     *
+     * # Returns
+     * A vocabulary whose entry values are `ScheduleEntry<P>`.
+     *
+     * # Errors
+     * * When the departure time is not less than `MINUTES_PER_DAY`.
+     */
+    pub fn create_vocabulary(
+        &self,
+        departure_time: usize,
+        waiting_cost: &WaitingCost,
+    ) -> Result<Box<dyn Vocabulary>> {
+        if departure_time >= MINUTES_PER_DAY {
+            return Err(ScheduleError::InvalidTime.into());
+        }
+        let entries = self.build_entries()?;
+        let connections = Self::build_connections(&entries, departure_time, waiting_cost);
+        let vocabulary = TypedHashMapVocabulary::new(
+            entries,
+            connections,
+            &Self::entry_hash_value,
+            &Self::entry_equal_to,
+        )?;
+        Ok(Box::new(TypedVocabularyAdapter::new(vocabulary)))
+    }
+
+    fn build_entries(&self) -> Result<Vec<(String, Vec<Entry>)>> {
+        let mut entries = Vec::<(String, Vec<Entry>)>::new();
+        let mut indices = HashMap::<String, usize>::new();
+        for schedule_entry in &self.entries {
+            let key = self.section_key(schedule_entry.from(), schedule_entry.to())?;
+            let index = *indices.entry(key.clone()).or_insert_with(|| {
+                entries.push((key.clone(), Vec::new()));
+                entries.len() - 1
+            });
+            entries[index].1.push(Entry::new(
+                Rc::new(StringInput::new(key)),
+                Rc::new(schedule_entry.clone()),
+                schedule_entry.duration() as i32,
+            ));
+        }
+        Ok(entries)
+    }
+
+    fn build_connections(
+        entries: &[(String, Vec<Entry>)],
+        departure_time: usize,
+        waiting_cost: &WaitingCost,
+    ) -> Vec<((Entry, Entry), i32)> {
+        let mut connections = Vec::<((Entry, Entry), i32)>::new();
+
+        for (_, from_entries) in entries {
+            for (_, to_entries) in entries {
+                for from_entry in from_entries {
+                    for to_entry in to_entries {
+                        let from_value = Self::schedule_entry_of(from_entry);
+                        let to_value = Self::schedule_entry_of(to_entry);
+                        if from_value.to() != to_value.from() {
+                            continue;
+                        }
+
+                        let waiting_time = elapsed_time(from_value.arrival(), to_value.departure());
+                        if waiting_time > waiting_cost.max_waiting_time() {
+                            continue;
+                        }
+                        let cost = if from_value.payload() == to_value.payload() {
+                            waiting_time as i32
+                        } else {
+                            waiting_time as i32 + waiting_cost.transfer_penalty()
+                        };
+                        connections.push(((from_entry.clone(), to_entry.clone()), cost));
+                    }
+                }
+            }
+        }
+
+        for (_, entries) in entries {
+            for entry in entries {
+                let schedule_entry = Self::schedule_entry_of(entry);
+                let waiting_time = elapsed_time(departure_time, schedule_entry.departure());
+                if waiting_time <= waiting_cost.max_initial_waiting_time() {
+                    connections.push((
+                        (Entry::BosEos, entry.clone()),
+                        waiting_time as i32 * waiting_cost.initial_waiting_percentage() / 100,
+                    ));
+                }
+                connections.push(((entry.clone(), Entry::BosEos), 0));
+            }
+        }
+
+        connections
+    }
+
+    fn schedule_entry_of(entry: &Entry) -> &ScheduleEntry<P> {
+        EntryView::<ScheduleEntry<P>>::from_entry(entry)
+            .ok()
+            .and_then(|view| view.value())
+            .unwrap_or_else(|| unreachable!("entry.value() must be ScheduleEntry."))
+    }
+
+    fn entry_hash_value(entry: &EntryView<'_, ScheduleEntry<P>>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u64(entry.key().map_or(0, |key| key.hash_value()));
+        entry.value().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn entry_equal_to(
+        one: &EntryView<'_, ScheduleEntry<P>>,
+        another: &EntryView<'_, ScheduleEntry<P>>,
+    ) -> bool {
+        let is_key_equal = match (one.key(), another.key()) {
+            (Some(one_key), Some(another_key)) => one_key.equal_to(another_key),
+            (None, None) => true,
+            _ => false,
+        };
+        is_key_equal && one.value() == another.value()
+    }
+}
+
+const fn elapsed_time(from_time: usize, to_time: usize) -> usize {
+    (to_time + MINUTES_PER_DAY - from_time) % MINUTES_PER_DAY
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constraint::Constraint;
+    use crate::lattice::Lattice;
+    use crate::n_best_iterator::NBestIterator;
+
+    use super::*;
+
+    fn make_schedule() -> Schedule<&'static str> {
+        let mut schedule = Schedule::new(vec![
+            String::from("A"),
+            String::from("B"),
+            String::from("C"),
+        ]);
+        schedule
+            .add_entry(ScheduleEntry::new(0, 2, 600, 630, "rapid"))
+            .unwrap();
+        schedule
+            .add_entry(ScheduleEntry::new(0, 1, 605, 615, "local"))
+            .unwrap();
+        schedule
+            .add_entry(ScheduleEntry::new(1, 2, 615, 625, "local"))
+            .unwrap();
+        schedule
+            .add_entry(ScheduleEntry::new(1, 2, 620, 628, "express"))
+            .unwrap();
+        schedule
+    }
+
+    mod schedule_entry {
+        use super::*;
+
+        #[test]
+        fn new() {
+            let _entry = ScheduleEntry::new(0, 2, 600, 630, "rapid");
+        }
+
+        #[test]
+        fn from() {
+            assert_eq!(ScheduleEntry::new(0, 2, 600, 630, "rapid").from(), 0);
+        }
+
+        #[test]
+        fn to() {
+            assert_eq!(ScheduleEntry::new(0, 2, 600, 630, "rapid").to(), 2);
+        }
+
+        #[test]
+        fn departure() {
+            assert_eq!(ScheduleEntry::new(0, 2, 600, 630, "rapid").departure(), 600);
+        }
+
+        #[test]
+        fn arrival() {
+            assert_eq!(ScheduleEntry::new(0, 2, 600, 630, "rapid").arrival(), 630);
+        }
+
+        #[test]
+        fn payload() {
+            assert_eq!(
+                *ScheduleEntry::new(0, 2, 600, 630, "rapid").payload(),
+                "rapid"
+            );
+        }
+
+        #[test]
+        fn duration() {
+            assert_eq!(ScheduleEntry::new(0, 2, 600, 630, "rapid").duration(), 30);
+            assert_eq!(ScheduleEntry::new(0, 2, 1430, 10, "night").duration(), 20);
+        }
+    }
+
+    mod waiting_cost {
+        use super::*;
+
+        #[test]
+        fn new() {
+            let _waiting_cost = WaitingCost::new(60, 1, 240, 90);
+        }
+
+        #[test]
+        fn max_waiting_time() {
+            assert_eq!(WaitingCost::new(60, 1, 240, 90).max_waiting_time(), 60);
+        }
+
+        #[test]
+        fn transfer_penalty() {
+            assert_eq!(WaitingCost::new(60, 1, 240, 90).transfer_penalty(), 1);
+        }
+
+        #[test]
+        fn max_initial_waiting_time() {
+            assert_eq!(
+                WaitingCost::new(60, 1, 240, 90).max_initial_waiting_time(),
+                240
+            );
+        }
+
+        #[test]
+        fn initial_waiting_percentage() {
+            assert_eq!(
+                WaitingCost::new(60, 1, 240, 90).initial_waiting_percentage(),
+                90
+            );
+        }
+    }
+
+    mod schedule {
+        use super::*;
+
+        #[test]
+        fn new() {
+            let _schedule = Schedule::<&str>::new(vec![String::from("A"), String::from("B")]);
+        }
+
+        #[test]
+        fn station_codes() {
+            let schedule = make_schedule();
+
+            assert_eq!(schedule.station_codes(), ["A", "B", "C"]);
+        }
+
+        #[test]
+        fn entries() {
+            let schedule = make_schedule();
+
+            assert_eq!(schedule.entries().len(), 4);
+            assert_eq!(*schedule.entries()[1].payload(), "local");
+        }
+
+        #[test]
+        fn add_entry() {
+            let mut schedule = make_schedule();
+
+            {
+                let error = schedule
+                    .add_entry(ScheduleEntry::new(1, 3, 600, 630, "rapid"))
+                    .unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<ScheduleError>().unwrap(),
+                    ScheduleError::StationIndexOutOfRange
+                ));
+            }
+            {
+                let error = schedule
+                    .add_entry(ScheduleEntry::new(1, 1, 600, 630, "rapid"))
+                    .unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<ScheduleError>().unwrap(),
+                    ScheduleError::InvalidSection
+                ));
+            }
+            {
+                let error = schedule
+                    .add_entry(ScheduleEntry::new(0, 1, 600, MINUTES_PER_DAY, "rapid"))
+                    .unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<ScheduleError>().unwrap(),
+                    ScheduleError::InvalidTime
+                ));
+            }
+            assert_eq!(schedule.entries().len(), 4);
+        }
+
+        #[test]
+        fn section_key() {
+            let schedule = make_schedule();
+
+            assert_eq!(schedule.section_key(0, 1).unwrap(), "A-B/");
+            assert_eq!(schedule.section_key(0, 2).unwrap(), "A-B/B-C/");
+            assert!(schedule.section_key(2, 1).is_err());
+        }
+
+        #[test]
+        fn section_inputs() {
+            let schedule = make_schedule();
+
+            let inputs = schedule.section_inputs(0, 2).unwrap();
+            assert_eq!(
+                inputs.iter().map(StringInput::value).collect::<Vec<_>>(),
+                ["A-B/", "B-C/"]
+            );
+        }
+
+        #[test]
+        fn create_vocabulary() {
+            let schedule = make_schedule();
+            let waiting_cost = WaitingCost::new(60, 1, 240, 90);
+
+            {
+                let vocabulary = schedule.create_vocabulary(600, &waiting_cost).unwrap();
+                let mut lattice = Lattice::new(vocabulary.as_ref());
+                for input in schedule.section_inputs(0, 2).unwrap() {
+                    let _delta = lattice.push_back(Box::new(input)).unwrap();
+                }
+                let eos_node = lattice.settle().unwrap();
+
+                let paths = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()))
+                    .map(|path| {
+                        let payloads = path
+                            .nodes()
+                            .iter()
+                            .filter_map(|node| {
+                                EntryView::<ScheduleEntry<&str>>::from_node(node)
+                                    .unwrap()
+                                    .value()
+                                    .map(|entry| *entry.payload())
+                            })
+                            .collect::<Vec<_>>();
+                        (payloads, path.cost())
+                    })
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    paths,
+                    [
+                        (vec!["local", "local"], 24),
+                        (vec!["local", "express"], 28),
+                        (vec!["rapid"], 30),
+                    ]
+                );
+            }
+            {
+                let vocabulary = schedule.create_vocabulary(610, &waiting_cost).unwrap();
+                let mut lattice = Lattice::new(vocabulary.as_ref());
+                for input in schedule.section_inputs(1, 2).unwrap() {
+                    let _delta = lattice.push_back(Box::new(input)).unwrap();
+                }
+                let eos_node = lattice.settle().unwrap();
+
+                assert_eq!(eos_node.path_cost(), 14);
+            }
+            {
+                let error = schedule
+                    .create_vocabulary(MINUTES_PER_DAY, &waiting_cost)
+                    .unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<ScheduleError>().unwrap(),
+                    ScheduleError::InvalidTime
+                ));
+            }
+        }
+    }
+}
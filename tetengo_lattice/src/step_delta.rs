@@ -0,0 +1,114 @@
+/*!
+ * A step delta.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+/**
+ * A step delta.
+ *
+ * Describes the step added to a lattice by a push back.
+ */
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StepDelta {
+    step: usize,
+    node_count: usize,
+    best_node_index: usize,
+    best_path_cost: i32,
+}
+
+impl StepDelta {
+    /**
+     * Creates a step delta.
+     *
+     * # Arguments
+     * * `step`            - The added step.
+     * * `node_count`      - The count of the added nodes.
+     * * `best_node_index` - The index of the added node with the best path cost.
+     * * `best_path_cost`  - The best path cost.
+     */
+    pub const fn new(
+        step: usize,
+        node_count: usize,
+        best_node_index: usize,
+        best_path_cost: i32,
+    ) -> Self {
+        Self {
+            step,
+            node_count,
+            best_node_index,
+            best_path_cost,
+        }
+    }
+
+    /**
+     * Returns the added step.
+     *
+     * # Returns
+     * The added step.
+     */
+    pub const fn step(&self) -> usize {
+        self.step
+    }
+
+    /**
+     * Returns the count of the added nodes.
+     *
+     * # Returns
+     * The count of the added nodes.
+     */
+    pub const fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    /**
+     * Returns the index of the added node with the best path cost.
+     *
+     * # Returns
+     * The index in the step of the added node with the best path cost.
+     */
+    pub const fn best_node_index(&self) -> usize {
+        self.best_node_index
+    }
+
+    /**
+     * Returns the best path cost.
+     *
+     * # Returns
+     * The best path cost from BOS to the added nodes. Or `i32::MAX` when no
+     * added node is reachable.
+     */
+    pub const fn best_path_cost(&self) -> i32 {
+        self.best_path_cost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new() {
+        let _delta = StepDelta::new(3, 1, 4, 42);
+    }
+
+    #[test]
+    fn step() {
+        assert_eq!(StepDelta::new(3, 1, 4, 42).step(), 3);
+    }
+
+    #[test]
+    fn node_count() {
+        assert_eq!(StepDelta::new(3, 1, 4, 42).node_count(), 1);
+    }
+
+    #[test]
+    fn best_node_index() {
+        assert_eq!(StepDelta::new(3, 1, 4, 42).best_node_index(), 4);
+    }
+
+    #[test]
+    fn best_path_cost() {
+        assert_eq!(StepDelta::new(3, 1, 4, 42).best_path_cost(), 42);
+    }
+}
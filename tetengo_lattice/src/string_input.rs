@@ -9,15 +9,71 @@ use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
 use anyhow::Result;
+use unicode_normalization::UnicodeNormalization;
 
 use crate::input::{Input, InputError};
 
+/**
+ * A Unicode normalization form.
+ */
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NormalizationForm {
+    /**
+     * NFC.
+     */
+    Nfc,
+
+    /**
+     * NFD.
+     */
+    Nfd,
+
+    /**
+     * NFKC.
+     */
+    Nfkc,
+
+    /**
+     * NFKD.
+     */
+    Nfkd,
+}
+
+impl NormalizationForm {
+    /**
+     * Normalizes a string.
+     *
+     * # Arguments
+     * * `value` - A string.
+     *
+     * # Returns
+     * The normalized string.
+     */
+    pub fn normalize(self, value: &str) -> String {
+        match self {
+            Self::Nfc => value.nfc().collect(),
+            Self::Nfd => value.nfd().collect(),
+            Self::Nfkc => value.nfkc().collect(),
+            Self::Nfkd => value.nfkd().collect(),
+        }
+    }
+}
+
 /**
  * A string input.
+ *
+ * The string inputs are compared as raw strings. So the same text in
+ * different normalization forms, such as NFC and NFD, never matches.
+ *
+ * A string input created with `new_normalized` normalizes its value, and keeps
+ * it normalized when another input is appended. The vocabularies should make
+ * their keys in the same normalization form as the inputs. NFC is recommended,
+ * since the dictionaries such as IPAdic are in NFC.
  */
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug)]
 pub struct StringInput {
     value: String,
+    normalization_form: Option<NormalizationForm>,
 }
 
 impl StringInput {
@@ -28,7 +84,24 @@ impl StringInput {
      * * `value` - A value.
      */
     pub const fn new(value: String) -> Self {
-        Self { value }
+        Self {
+            value,
+            normalization_form: None,
+        }
+    }
+
+    /**
+     * Creates a string input key normalized in a normalization form.
+     *
+     * # Arguments
+     * * `value`              - A value.
+     * * `normalization_form` - A normalization form.
+     */
+    pub fn new_normalized(value: &str, normalization_form: NormalizationForm) -> Self {
+        Self {
+            value: normalization_form.normalize(value),
+            normalization_form: Some(normalization_form),
+        }
     }
 
     /**
@@ -44,12 +117,38 @@ impl StringInput {
     /**
      * Returns the value.
      *
+     * The value is not normalized again after it is modified.
+     *
      * # Returns
      * The value.
      */
     pub fn value_mut(&mut self) -> &mut String {
         &mut self.value
     }
+
+    /**
+     * Returns the normalization form.
+     *
+     * # Returns
+     * The normalization form. Or `None` when the value is not normalized.
+     */
+    pub const fn normalization_form(&self) -> Option<NormalizationForm> {
+        self.normalization_form
+    }
+}
+
+impl PartialEq for StringInput {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for StringInput {}
+
+impl Hash for StringInput {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
 }
 
 impl Input for StringInput {
@@ -75,9 +174,10 @@ impl Input for StringInput {
             return Err(InputError::RangeOutOfBounds.into());
         }
 
-        Ok(Box::new(StringInput::new(
-            self.value[offset..offset + length].to_string(),
-        )))
+        Ok(Box::new(StringInput {
+            value: self.value[offset..offset + length].to_string(),
+            normalization_form: self.normalization_form,
+        }))
     }
 
     fn append(&mut self, another: Box<dyn Input>) -> Result<()> {
@@ -86,6 +186,9 @@ impl Input for StringInput {
         };
 
         self.value += another.value();
+        if let Some(normalization_form) = self.normalization_form {
+            self.value = normalization_form.normalize(&self.value);
+        }
 
         Ok(())
     }
@@ -136,11 +239,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn normalize() {
+        assert_eq!(NormalizationForm::Nfc.normalize("e\u{0301}"), "\u{00E9}");
+        assert_eq!(NormalizationForm::Nfd.normalize("\u{00E9}"), "e\u{0301}");
+        assert_eq!(NormalizationForm::Nfkc.normalize("ｶﾞ"), "ガ");
+        assert_eq!(NormalizationForm::Nfkd.normalize("ｶﾞ"), "カ\u{3099}");
+    }
+
     #[test]
     fn new() {
         let _input = StringInput::new(String::from("hoge"));
     }
 
+    #[test]
+    fn new_normalized() {
+        let input = StringInput::new_normalized("e\u{0301}", NormalizationForm::Nfc);
+
+        assert_eq!(input.value(), "\u{00E9}");
+    }
+
     #[test]
     fn value() {
         let input = StringInput::new(String::from("hoge"));
@@ -156,6 +274,17 @@ mod tests {
         assert_eq!(input.value_mut(), "fuga");
     }
 
+    #[test]
+    fn normalization_form() {
+        assert!(StringInput::new(String::from("hoge"))
+            .normalization_form()
+            .is_none());
+        assert_eq!(
+            StringInput::new_normalized("hoge", NormalizationForm::Nfc).normalization_form(),
+            Some(NormalizationForm::Nfc)
+        );
+    }
+
     #[test]
     fn equal_to() {
         {
@@ -178,6 +307,19 @@ mod tests {
 
             assert!(!input1.equal_to(&input2));
         }
+        {
+            let input1 = StringInput::new(String::from("\u{00E9}"));
+            let input2 = StringInput::new(String::from("e\u{0301}"));
+
+            assert!(!input1.equal_to(&input2));
+        }
+        {
+            let input1 = StringInput::new(String::from("\u{00E9}"));
+            let input2 = StringInput::new_normalized("e\u{0301}", NormalizationForm::Nfc);
+
+            assert!(input1.equal_to(&input2));
+            assert!(input2.equal_to(&input1));
+        }
     }
 
     #[test]
@@ -194,6 +336,12 @@ mod tests {
 
             assert_ne!(input1.hash_value(), input2.hash_value());
         }
+        {
+            let input1 = StringInput::new(String::from("\u{00E9}"));
+            let input2 = StringInput::new_normalized("e\u{0301}", NormalizationForm::Nfc);
+
+            assert_eq!(input1.hash_value(), input2.hash_value());
+        }
     }
 
     #[test]
@@ -244,6 +392,18 @@ mod tests {
             let subrange = input.create_subrange(5, 0);
             assert!(subrange.is_err());
         }
+        {
+            let input = StringInput::new_normalized("hoge", NormalizationForm::Nfc);
+
+            let subrange = input.create_subrange(1, 2).unwrap();
+            assert_eq!(
+                subrange
+                    .downcast_ref::<StringInput>()
+                    .unwrap()
+                    .normalization_form(),
+                Some(NormalizationForm::Nfc)
+            );
+        }
     }
 
     #[test]
@@ -263,6 +423,15 @@ mod tests {
             let result = input.append(Box::new(AnotherInput {}));
             assert!(result.is_err());
         }
+        {
+            let mut input = StringInput::new_normalized("e", NormalizationForm::Nfc);
+
+            input
+                .append(Box::new(StringInput::new(String::from("\u{0301}"))))
+                .unwrap();
+
+            assert_eq!(input.value(), "\u{00E9}");
+        }
     }
 
     #[test]
@@ -0,0 +1,692 @@
+/*!
+ * A test support.
+ *
+ * Generates synthetic vocabularies and inputs, so that lattices and N-best
+ * iterators can be stressed reproducibly without real dictionaries.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use crate::connection::Connection;
+use crate::entry::Entry;
+use crate::input::Input;
+use crate::node::Node;
+use crate::string_input::StringInput;
+use crate::vocabulary::Vocabulary;
+
+/**
+ * A synthetic vocabulary error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum SyntheticVocabularyError {
+    /**
+     * The branching factor is out of range.
+     */
+    #[error("the branching factor is out of range")]
+    InvalidBranchingFactor,
+
+    /**
+     * The max key length is 0.
+     */
+    #[error("the max key length is 0")]
+    InvalidMaxKeyLength,
+
+    /**
+     * The entry count range is empty.
+     */
+    #[error("the entry count range is empty")]
+    InvalidEntryCountRange,
+
+    /**
+     * The cost range is empty.
+     */
+    #[error("the cost range is empty")]
+    InvalidCostRange,
+}
+
+/**
+ * A random number generator.
+ *
+ * A xorshift generator. It yields the same sequence for the same seed on all
+ * the platforms.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct Random(u64);
+
+impl Random {
+    /**
+     * Creates a random number generator.
+     *
+     * # Arguments
+     * * `seed` - A seed.
+     */
+    pub const fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E3779B97F4A7C15) | 1)
+    }
+
+    /**
+     * Returns the next number.
+     *
+     * # Returns
+     * The next number.
+     */
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /**
+     * Returns the next number in a range.
+     *
+     * # Arguments
+     * * `min` - The min number.
+     * * `max` - The max number.
+     *
+     * # Returns
+     * The next number between `min` and `max`, both inclusive. Or `min` when
+     * `max` is less than `min`.
+     */
+    pub fn between(&mut self, min: i64, max: i64) -> i64 {
+        if max <= min {
+            return min;
+        }
+        let width = (max - min) as u64 + 1;
+        min + (self.next_u64() % width) as i64
+    }
+}
+
+/**
+ * A cost distribution.
+ */
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CostDistribution {
+    /**
+     * Always the cost.
+     */
+    Constant(i32),
+
+    /**
+     * The costs uniformly distributed between `min` and `max`, both inclusive.
+     */
+    Uniform {
+        /// The min cost.
+        min: i32,
+        /// The max cost.
+        max: i32,
+    },
+
+    /**
+     * The costs between `min` and `max`, both inclusive, biased toward `min`.
+     *
+     * A cost is the less of two uniformly distributed costs, so that a few
+     * entries or connections are much cheaper than the others as in the real
+     * dictionaries.
+     */
+    LowBiased {
+        /// The min cost.
+        min: i32,
+        /// The max cost.
+        max: i32,
+    },
+}
+
+impl CostDistribution {
+    /**
+     * Samples a cost.
+     *
+     * # Arguments
+     * * `random` - A random number generator.
+     *
+     * # Returns
+     * A cost.
+     */
+    pub fn sample(&self, random: &mut Random) -> i32 {
+        match *self {
+            CostDistribution::Constant(cost) => cost,
+            CostDistribution::Uniform { min, max } => random.between(min as i64, max as i64) as i32,
+            CostDistribution::LowBiased { min, max } => {
+                let one = random.between(min as i64, max as i64);
+                let another = random.between(min as i64, max as i64);
+                one.min(another) as i32
+            }
+        }
+    }
+
+    const fn is_valid(&self) -> bool {
+        match *self {
+            CostDistribution::Constant(_) => true,
+            CostDistribution::Uniform { min, max } | CostDistribution::LowBiased { min, max } => {
+                min <= max
+            }
+        }
+    }
+}
+
+/**
+ * A synthetic vocabulary builder.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct SyntheticVocabularyBuilder {
+    branching_factor: usize,
+    key_count: usize,
+    max_key_length: usize,
+    entry_count_range: (usize, usize),
+    entry_cost: CostDistribution,
+    connection_cost: CostDistribution,
+    seed: u64,
+}
+
+impl SyntheticVocabularyBuilder {
+    /**
+     * Sets a branching factor.
+     *
+     * The keys consist of as many kinds of symbols as the branching factor.
+     *
+     * # Arguments
+     * * `branching_factor` - A branching factor.
+     */
+    pub const fn branching_factor(mut self, branching_factor: usize) -> Self {
+        self.branching_factor = branching_factor;
+        self
+    }
+
+    /**
+     * Sets a key count.
+     *
+     * The keys of the single symbols are always made in addition to them, so
+     * that any input has a path.
+     *
+     * # Arguments
+     * * `key_count` - A count of the keys of one or more symbols.
+     */
+    pub const fn key_count(mut self, key_count: usize) -> Self {
+        self.key_count = key_count;
+        self
+    }
+
+    /**
+     * Sets a max key length.
+     *
+     * # Arguments
+     * * `max_key_length` - A max key length in symbols.
+     */
+    pub const fn max_key_length(mut self, max_key_length: usize) -> Self {
+        self.max_key_length = max_key_length;
+        self
+    }
+
+    /**
+     * Sets an entry count range.
+     *
+     * # Arguments
+     * * `min` - The min count of the entries per key.
+     * * `max` - The max count of the entries per key.
+     */
+    pub const fn entry_count_range(mut self, min: usize, max: usize) -> Self {
+        self.entry_count_range = (min, max);
+        self
+    }
+
+    /**
+     * Sets an entry cost distribution.
+     *
+     * # Arguments
+     * * `entry_cost` - An entry cost distribution.
+     */
+    pub const fn entry_cost(mut self, entry_cost: CostDistribution) -> Self {
+        self.entry_cost = entry_cost;
+        self
+    }
+
+    /**
+     * Sets a connection cost distribution.
+     *
+     * # Arguments
+     * * `connection_cost` - A connection cost distribution.
+     */
+    pub const fn connection_cost(mut self, connection_cost: CostDistribution) -> Self {
+        self.connection_cost = connection_cost;
+        self
+    }
+
+    /**
+     * Sets a seed.
+     *
+     * # Arguments
+     * * `seed` - A seed.
+     */
+    pub const fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /**
+     * Builds a synthetic vocabulary.
+     *
+     * # Returns
+     * A synthetic vocabulary.
+     *
+     * # Errors
+     * * When the branching factor is 0 or greater than
+     *   `SyntheticVocabulary::MAX_BRANCHING_FACTOR`.
+     * * When the max key length is 0.
+     * * When the entry count range is empty, or its min is 0.
+     * * When a cost range is empty.
+     */
+    pub fn build(self) -> Result<SyntheticVocabulary> {
+        if self.branching_factor == 0
+            || self.branching_factor > SyntheticVocabulary::MAX_BRANCHING_FACTOR
+        {
+            return Err(SyntheticVocabularyError::InvalidBranchingFactor.into());
+        }
+        if self.max_key_length == 0 {
+            return Err(SyntheticVocabularyError::InvalidMaxKeyLength.into());
+        }
+        let (min_entry_count, max_entry_count) = self.entry_count_range;
+        if min_entry_count == 0 || min_entry_count > max_entry_count {
+            return Err(SyntheticVocabularyError::InvalidEntryCountRange.into());
+        }
+        if !self.entry_cost.is_valid() || !self.connection_cost.is_valid() {
+            return Err(SyntheticVocabularyError::InvalidCostRange.into());
+        }
+
+        let mut random = Random::new(self.seed);
+        let mut keys = (0..self.branching_factor)
+            .map(SyntheticVocabulary::symbol)
+            .collect::<Vec<_>>();
+        for _ in 0..self.key_count {
+            let length = random.between(1, self.max_key_length as i64) as usize;
+            keys.push(
+                (0..length)
+                    .map(|_| {
+                        let symbol_index = random.between(0, self.branching_factor as i64 - 1);
+                        SyntheticVocabulary::symbol(symbol_index as usize)
+                    })
+                    .collect(),
+            );
+        }
+        keys.sort();
+        keys.dedup();
+
+        let mut entries = HashMap::with_capacity(keys.len());
+        for key in &keys {
+            let entry_count = random.between(min_entry_count as i64, max_entry_count as i64);
+            let key_input = Rc::new(StringInput::new(key.clone()));
+            let key_entries = (0..entry_count)
+                .map(|i| {
+                    Entry::new(
+                        key_input.clone(),
+                        Rc::new(format!("{}/{}", key, i)),
+                        self.entry_cost.sample(&mut random),
+                    )
+                })
+                .collect::<Vec<_>>();
+            let _previous = entries.insert(key.clone(), key_entries);
+        }
+
+        Ok(SyntheticVocabulary {
+            keys,
+            entries,
+            connection_cost: self.connection_cost,
+            seed: self.seed,
+        })
+    }
+}
+
+/**
+ * A synthetic vocabulary.
+ *
+ * The keys are the strings of the symbols, which are the CJK unified
+ * ideographs from U+4E00. The value of an entry is a `String` of its key and
+ * its index in the key.
+ *
+ * The connection costs are not stored. The cost of a connection is sampled
+ * with the seed mixed with the values of its origin and destination, so the
+ * same connection always has the same cost.
+ *
+ * The keys must be `StringInput`s.
+ */
+#[derive(Clone, Debug)]
+pub struct SyntheticVocabulary {
+    keys: Vec<String>,
+    entries: HashMap<String, Vec<Entry>>,
+    connection_cost: CostDistribution,
+    seed: u64,
+}
+
+impl SyntheticVocabulary {
+    /// The max branching factor.
+    pub const MAX_BRANCHING_FACTOR: usize = 0x5200;
+
+    const FIRST_SYMBOL: u32 = 0x4E00;
+
+    /**
+     * Creates a synthetic vocabulary builder.
+     *
+     * # Returns
+     * A synthetic vocabulary builder.
+     */
+    pub const fn builder() -> SyntheticVocabularyBuilder {
+        SyntheticVocabularyBuilder {
+            branching_factor: 8,
+            key_count: 64,
+            max_key_length: 3,
+            entry_count_range: (1, 3),
+            entry_cost: CostDistribution::Uniform { min: 0, max: 1000 },
+            connection_cost: CostDistribution::Uniform { min: 0, max: 1000 },
+            seed: 0,
+        }
+    }
+
+    /**
+     * Returns the keys.
+     *
+     * # Returns
+     * The keys in the ascending order.
+     */
+    pub fn keys(&self) -> &[String] {
+        self.keys.as_slice()
+    }
+
+    /**
+     * Generates an input.
+     *
+     * The input is a concatenation of the keys chosen at random. It is split
+     * into the symbols, so that each of them is pushed back into a lattice as
+     * a `StringInput`.
+     *
+     * # Arguments
+     * * `key_count` - A count of the keys to concatenate.
+     * * `seed`      - A seed.
+     *
+     * # Returns
+     * The symbols of the input.
+     */
+    pub fn generate_input(&self, key_count: usize, seed: u64) -> Vec<String> {
+        let mut random = Random::new(seed);
+        let mut symbols = Vec::new();
+        for _ in 0..key_count {
+            let key_index = random.between(0, self.keys.len() as i64 - 1) as usize;
+            symbols.extend(self.keys[key_index].chars().map(String::from));
+        }
+        symbols
+    }
+
+    fn symbol(index: usize) -> String {
+        let Some(symbol) = char::from_u32(Self::FIRST_SYMBOL + index as u32) else {
+            unreachable!("The symbol index must be less than MAX_BRANCHING_FACTOR.");
+        };
+        String::from(symbol)
+    }
+
+    fn mix(hash: u64, bytes: &[u8]) -> u64 {
+        // FNV-1a, which is stable among the platforms unlike `DefaultHasher`.
+        bytes.iter().fold(hash, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001B3)
+        })
+    }
+}
+
+impl Vocabulary for SyntheticVocabulary {
+    fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>> {
+        let Some(key) = key.downcast_ref::<StringInput>() else {
+            return Ok(Vec::new());
+        };
+        Ok(self.entries.get(key.value()).cloned().unwrap_or_default())
+    }
+
+    fn find_connection(&self, from: &Node, to: &Entry) -> Result<Connection> {
+        let value_of = |value: Option<&dyn std::any::Any>| {
+            value
+                .and_then(|value| value.downcast_ref::<String>())
+                .map(String::as_bytes)
+                .unwrap_or_default()
+                .to_vec()
+        };
+        let hash = Self::mix(0xCBF29CE484222325 ^ self.seed, &value_of(from.value()));
+        let hash = Self::mix(Self::mix(hash, &[0xFF]), &value_of(to.value()));
+        Ok(Connection::new(
+            self.connection_cost.sample(&mut Random::new(hash)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constraint::Constraint;
+    use crate::lattice::Lattice;
+    use crate::n_best_iterator::NBestIterator;
+
+    use super::*;
+
+    mod random {
+        use super::*;
+
+        #[test]
+        fn new() {
+            let _random = Random::new(42);
+        }
+
+        #[test]
+        fn next_u64() {
+            let mut random1 = Random::new(42);
+            let mut random2 = Random::new(42);
+            let mut random3 = Random::new(24);
+
+            let numbers1 = (0..8).map(|_| random1.next_u64()).collect::<Vec<_>>();
+            let numbers2 = (0..8).map(|_| random2.next_u64()).collect::<Vec<_>>();
+            let numbers3 = (0..8).map(|_| random3.next_u64()).collect::<Vec<_>>();
+            assert_eq!(numbers1, numbers2);
+            assert_ne!(numbers1, numbers3);
+        }
+
+        #[test]
+        fn between() {
+            let mut random = Random::new(42);
+
+            for _ in 0..100 {
+                let number = random.between(-3, 5);
+                assert!((-3..=5).contains(&number));
+            }
+            assert_eq!(random.between(7, 7), 7);
+            assert_eq!(random.between(7, 3), 7);
+        }
+    }
+
+    mod cost_distribution {
+        use super::*;
+
+        #[test]
+        fn sample() {
+            let mut random = Random::new(42);
+
+            assert_eq!(CostDistribution::Constant(42).sample(&mut random), 42);
+            for _ in 0..100 {
+                let cost = CostDistribution::Uniform { min: 10, max: 20 }.sample(&mut random);
+                assert!((10..=20).contains(&cost));
+            }
+
+            let uniform_sum = (0..1000)
+                .map(|_| CostDistribution::Uniform { min: 0, max: 1000 }.sample(&mut random))
+                .sum::<i32>();
+            let low_biased_sum = (0..1000)
+                .map(|_| CostDistribution::LowBiased { min: 0, max: 1000 }.sample(&mut random))
+                .sum::<i32>();
+            assert!(low_biased_sum < uniform_sum);
+        }
+    }
+
+    mod synthetic_vocabulary_builder {
+        use super::*;
+
+        #[test]
+        fn build() {
+            {
+                let vocabulary = SyntheticVocabulary::builder()
+                    .branching_factor(4)
+                    .key_count(16)
+                    .max_key_length(2)
+                    .entry_count_range(2, 2)
+                    .entry_cost(CostDistribution::Constant(100))
+                    .connection_cost(CostDistribution::LowBiased { min: 0, max: 50 })
+                    .seed(42)
+                    .build()
+                    .unwrap();
+
+                assert!(vocabulary.keys().len() >= 4);
+                assert!(vocabulary.keys().len() <= 4 + 16);
+                assert!(vocabulary.keys().iter().all(|key| key.chars().count() <= 2));
+            }
+            {
+                let result = SyntheticVocabulary::builder().branching_factor(0).build();
+                assert!(result.is_err());
+            }
+            {
+                let result = SyntheticVocabulary::builder()
+                    .branching_factor(SyntheticVocabulary::MAX_BRANCHING_FACTOR + 1)
+                    .build();
+                assert!(result.is_err());
+            }
+            {
+                let result = SyntheticVocabulary::builder().max_key_length(0).build();
+                assert!(result.is_err());
+            }
+            {
+                let result = SyntheticVocabulary::builder()
+                    .entry_count_range(3, 2)
+                    .build();
+                assert!(result.is_err());
+            }
+            {
+                let result = SyntheticVocabulary::builder()
+                    .entry_cost(CostDistribution::Uniform { min: 2, max: 1 })
+                    .build();
+                assert!(result.is_err());
+            }
+        }
+    }
+
+    mod synthetic_vocabulary {
+        use super::*;
+
+        #[test]
+        fn builder() {
+            let _builder = SyntheticVocabulary::builder();
+        }
+
+        #[test]
+        fn keys() {
+            let vocabulary1 = SyntheticVocabulary::builder().seed(42).build().unwrap();
+            let vocabulary2 = SyntheticVocabulary::builder().seed(42).build().unwrap();
+
+            assert_eq!(vocabulary1.keys(), vocabulary2.keys());
+            assert!(vocabulary1.keys().is_sorted());
+            for i in 0..8 {
+                assert!(vocabulary1.keys().contains(&SyntheticVocabulary::symbol(i)));
+            }
+        }
+
+        #[test]
+        fn generate_input() {
+            let vocabulary = SyntheticVocabulary::builder().seed(42).build().unwrap();
+
+            let input1 = vocabulary.generate_input(10, 24);
+            let input2 = vocabulary.generate_input(10, 24);
+            assert_eq!(input1, input2);
+            assert!(input1.len() >= 10);
+            assert!(input1.iter().all(|symbol| symbol.chars().count() == 1));
+        }
+
+        #[test]
+        fn find_entries() {
+            let vocabulary = SyntheticVocabulary::builder()
+                .entry_count_range(2, 3)
+                .seed(42)
+                .build()
+                .unwrap();
+
+            for key in vocabulary.keys() {
+                let entries = vocabulary
+                    .find_entries(&StringInput::new(key.clone()))
+                    .unwrap();
+                assert!((2..=3).contains(&entries.len()));
+                assert!(entries.iter().all(|entry| {
+                    entry
+                        .value()
+                        .unwrap()
+                        .downcast_ref::<String>()
+                        .unwrap()
+                        .starts_with(key.as_str())
+                }));
+            }
+            assert!(vocabulary
+                .find_entries(&StringInput::new(String::from("a")))
+                .unwrap()
+                .is_empty());
+        }
+
+        #[test]
+        fn find_connection() {
+            let vocabulary = SyntheticVocabulary::builder()
+                .connection_cost(CostDistribution::Uniform { min: 0, max: 100 })
+                .seed(42)
+                .build()
+                .unwrap();
+
+            let key = &vocabulary.keys()[0];
+            let entries = vocabulary
+                .find_entries(&StringInput::new(key.clone()))
+                .unwrap();
+            let bos = Node::bos(Rc::new(Vec::new()));
+            let cost1 = vocabulary
+                .find_connection(&bos, &entries[0])
+                .unwrap()
+                .cost();
+            let cost2 = vocabulary
+                .find_connection(&bos, &entries[0])
+                .unwrap()
+                .cost();
+            assert_eq!(cost1, cost2);
+            assert!((0..=100).contains(&cost1));
+        }
+
+        #[test]
+        fn lattice() {
+            let vocabulary = SyntheticVocabulary::builder()
+                .branching_factor(16)
+                .key_count(256)
+                .max_key_length(4)
+                .entry_count_range(1, 4)
+                .entry_cost(CostDistribution::LowBiased { min: 0, max: 5000 })
+                .seed(42)
+                .build()
+                .unwrap();
+
+            let run = || {
+                let mut lattice = Lattice::new(&vocabulary);
+                for symbol in vocabulary.generate_input(20, 24) {
+                    let _delta = lattice
+                        .push_back(Box::new(StringInput::new(symbol)))
+                        .unwrap();
+                }
+                let eos_node = lattice.settle().unwrap();
+                NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()))
+                    .take(5)
+                    .map(|path| path.cost())
+                    .collect::<Vec<_>>()
+            };
+            let costs1 = run();
+            let costs2 = run();
+            assert_eq!(costs1.len(), 5);
+            assert!(costs1.is_sorted());
+            assert_eq!(costs1, costs2);
+        }
+    }
+}
@@ -0,0 +1,354 @@
+/*!
+ * A trie vocabulary.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::cell::RefCell;
+use std::fmt::{self, Debug, Formatter};
+use std::io::{Read, Seek, SeekFrom};
+use std::rc::Rc;
+
+use anyhow::Result;
+use tetengo_trie::{MemoryStorage, Trie, ValueDeserializer};
+
+use crate::connection::Connection;
+use crate::entry::Entry;
+use crate::input::Input;
+use crate::node::Node;
+use crate::string_input::StringInput;
+use crate::vocabulary::Vocabulary;
+
+/**
+ * A trie vocabulary error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum TrieVocabularyError {
+    /**
+     * The trie value is broken.
+     */
+    #[error("the trie value is broken")]
+    BrokenTrieValue,
+
+    /**
+     * The lex CSV row is broken.
+     */
+    #[error("the lex CSV row is broken")]
+    BrokenLexCsvRow,
+}
+
+trait LexCsvReader: Read + Seek {}
+
+impl<R: Read + Seek> LexCsvReader for R {}
+
+type WordOffsetTrie = Trie<String, Vec<(usize, usize)>>;
+
+/**
+ * A trie vocabulary.
+ *
+ * Finds the entries with the trie.bin built by the make_dict example of
+ * tetengo_trie and the original lex CSV. The trie maps the keys to the
+ * `(offset, length)` pairs of the rows in the lex CSV, and the rows are read
+ * from the lex CSV only when they are found.
+ *
+ * The key of the entries is the found key, and the value is the row without
+ * the line break. The left context ID, the right context ID and the cost of
+ * the entries are the 2nd, 3rd and 4th columns of the row. The rows truncated
+ * by make_dict are skipped.
+ *
+ * The connections all cost 0. Wrap this vocabulary in a `MatrixVocabulary` to
+ * use a connection matrix.
+ */
+pub struct TrieVocabulary {
+    trie: WordOffsetTrie,
+    lex_csv: RefCell<Box<dyn LexCsvReader>>,
+}
+
+impl TrieVocabulary {
+    /**
+     * Creates a trie vocabulary.
+     *
+     * # Arguments
+     * * `trie_bin` - A reader of trie.bin.
+     * * `lex_csv`  - A reader of the lex CSV.
+     *
+     * # Errors
+     * * When it fails to read trie.bin.
+     * * When trie.bin is broken.
+     */
+    pub fn new(trie_bin: &mut dyn Read, lex_csv: impl Read + Seek + 'static) -> Result<Self> {
+        let mut value_deserializer = ValueDeserializer::new(Box::new(deserialize_value));
+        let storage = Box::new(MemoryStorage::new_with_reader(
+            trie_bin,
+            &mut value_deserializer,
+        )?);
+        Ok(Self {
+            trie: WordOffsetTrie::builder_with_storage(storage).build(),
+            lex_csv: RefCell::new(Box::new(lex_csv)),
+        })
+    }
+
+    fn read_row(&self, offset: usize, length: usize) -> Result<String> {
+        let mut lex_csv = self.lex_csv.borrow_mut();
+        let _position = lex_csv.seek(SeekFrom::Start(offset as u64))?;
+        let mut bytes = vec![0u8; length];
+        lex_csv.read_exact(&mut bytes)?;
+        let row = String::from_utf8(bytes)
+            .map_err(|_| TrieVocabularyError::BrokenLexCsvRow)?
+            .trim_end_matches(['\r', '\n'])
+            .to_string();
+        Ok(row)
+    }
+}
+
+impl Debug for TrieVocabulary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrieVocabulary")
+            .field("trie", &self.trie)
+            .field("lex_csv", &"Box<dyn LexCsvReader>")
+            .finish()
+    }
+}
+
+impl Vocabulary for TrieVocabulary {
+    fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>> {
+        let Some(key) = key.downcast_ref::<StringInput>() else {
+            return Ok(Vec::new());
+        };
+        let Some(offsets) = self.trie.find(&key.value().to_string())? else {
+            return Ok(Vec::new());
+        };
+
+        let entry_key: Rc<dyn Input> = Rc::new(key.clone());
+        let mut entries = Vec::with_capacity(offsets.len());
+        for &(offset, length) in offsets.iter() {
+            if offset == 0 && length == 0 {
+                continue;
+            }
+            let row = self.read_row(offset, length)?;
+            let (left_id, right_id, cost) = context_ids_and_cost_of(&row)?;
+            entries.push(Entry::new_with_context_ids(
+                entry_key.clone(),
+                Rc::new(row),
+                cost,
+                left_id,
+                right_id,
+            ));
+        }
+        Ok(entries)
+    }
+
+    fn find_connection(&self, _from: &Node, _to: &Entry) -> Result<Connection> {
+        Ok(Connection::new(0))
+    }
+}
+
+const VALUE_CAPACITY: usize = 4;
+
+fn deserialize_value(bytes: &[u8]) -> Result<Vec<(usize, usize)>> {
+    if bytes.len() != size_of::<u32>() * (1 + VALUE_CAPACITY * 2) {
+        return Err(TrieVocabularyError::BrokenTrieValue.into());
+    }
+    let u32_at = |index: usize| {
+        let offset = index * size_of::<u32>();
+        u32::from_be_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ]) as usize
+    };
+
+    let size = u32_at(0);
+    Ok((0..size)
+        .map(|i| {
+            if i < VALUE_CAPACITY {
+                (u32_at(1 + i * 2), u32_at(2 + i * 2))
+            } else {
+                (0, 0)
+            }
+        })
+        .collect())
+}
+
+fn context_ids_and_cost_of(row: &str) -> Result<(u16, u16, i32)> {
+    let columns = split(row, ',');
+    if columns.len() < 4 {
+        return Err(TrieVocabularyError::BrokenLexCsvRow.into());
+    }
+    let left_id = columns[1]
+        .parse::<u16>()
+        .map_err(|_| TrieVocabularyError::BrokenLexCsvRow)?;
+    let right_id = columns[2]
+        .parse::<u16>()
+        .map_err(|_| TrieVocabularyError::BrokenLexCsvRow)?;
+    let cost = columns[3]
+        .parse::<i32>()
+        .map_err(|_| TrieVocabularyError::BrokenLexCsvRow)?;
+    Ok((left_id, right_id, cost))
+}
+
+fn split(string: &str, delimiter: char) -> Vec<&str> {
+    let mut elements = Vec::new();
+
+    let mut first = 0usize;
+    loop {
+        if string[first..].starts_with('"') {
+            if let Some(length) = string[first + 1..].find('"') {
+                let last = first + 1 + length;
+                elements.push(&string[first + 1..last]);
+                if last + 1 >= string.len() {
+                    break;
+                }
+                first = last + 2;
+            } else {
+                elements.push(&string[first + 1..]);
+                break;
+            }
+        } else if let Some(length) = string[first..].find(delimiter) {
+            let last = first + length;
+            elements.push(&string[first..last]);
+            first = last + 1;
+        } else {
+            elements.push(&string[first..]);
+            break;
+        }
+    }
+
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use tetengo_trie::{Serializer, StringSerializer, ValueSerializer};
+
+    use super::*;
+
+    const LEX_CSV: &str = "\
+みず,10,11,100,名詞\n\
+水,10,11,200,名詞\n\
+\",\",20,21,300,補助記号\n\
+";
+
+    fn make_trie_bin() -> Vec<u8> {
+        let rows = LEX_CSV.split_inclusive('\n').collect::<Vec<_>>();
+        let offset_of = |index: usize| rows[..index].iter().map(|row| row.len()).sum::<usize>();
+        let elements = vec![
+            (String::from(","), vec![(offset_of(2), rows[2].len())]),
+            (
+                String::from("みず"),
+                vec![
+                    (offset_of(0), rows[0].len()),
+                    (offset_of(1), rows[1].len()),
+                    (0, 0),
+                ],
+            ),
+        ];
+        let trie = WordOffsetTrie::builder()
+            .elements(elements)
+            .key_serializer(StringSerializer::new(true))
+            .build()
+            .unwrap();
+
+        let mut trie_bin = Vec::new();
+        let mut value_serializer = ValueSerializer::new(
+            Box::new(|value: &Vec<(usize, usize)>| {
+                let mut serialized = (value.len() as u32).to_be_bytes().to_vec();
+                for i in 0..VALUE_CAPACITY {
+                    let (offset, length) = value.get(i).copied().unwrap_or((0, 0));
+                    serialized.extend((offset as u32).to_be_bytes());
+                    serialized.extend((length as u32).to_be_bytes());
+                }
+                serialized
+            }),
+            size_of::<u32>() * (1 + VALUE_CAPACITY * 2),
+        );
+        trie.storage()
+            .serialize(&mut trie_bin, &mut value_serializer)
+            .unwrap();
+        trie_bin
+    }
+
+    fn make_vocabulary() -> TrieVocabulary {
+        TrieVocabulary::new(
+            &mut Cursor::new(make_trie_bin()),
+            Cursor::new(LEX_CSV.as_bytes().to_vec()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn new() {
+        {
+            let _vocabulary = make_vocabulary();
+        }
+        {
+            let result = TrieVocabulary::new(
+                &mut Cursor::new(vec![0xFFu8, 0xFF]),
+                Cursor::new(LEX_CSV.as_bytes().to_vec()),
+            );
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn find_entries() {
+        let vocabulary = make_vocabulary();
+
+        {
+            let entries = vocabulary
+                .find_entries(&StringInput::new(String::from("みず")))
+                .unwrap();
+            assert_eq!(entries.len(), 2);
+            assert_eq!(
+                entries[0]
+                    .value()
+                    .unwrap()
+                    .downcast_ref::<String>()
+                    .unwrap(),
+                "みず,10,11,100,名詞"
+            );
+            assert_eq!(entries[0].left_id(), Some(10));
+            assert_eq!(entries[0].right_id(), Some(11));
+            assert_eq!(entries[0].cost(), 100);
+            assert_eq!(
+                entries[1]
+                    .value()
+                    .unwrap()
+                    .downcast_ref::<String>()
+                    .unwrap(),
+                "水,10,11,200,名詞"
+            );
+            assert_eq!(entries[1].cost(), 200);
+        }
+        {
+            let entries = vocabulary
+                .find_entries(&StringInput::new(String::from(",")))
+                .unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].left_id(), Some(20));
+            assert_eq!(entries[0].right_id(), Some(21));
+            assert_eq!(entries[0].cost(), 300);
+        }
+        {
+            let entries = vocabulary
+                .find_entries(&StringInput::new(String::from("み")))
+                .unwrap();
+            assert!(entries.is_empty());
+        }
+    }
+
+    #[test]
+    fn find_connection() {
+        let vocabulary = make_vocabulary();
+
+        let entries = vocabulary
+            .find_entries(&StringInput::new(String::from("みず")))
+            .unwrap();
+        let bos = Node::bos(Rc::new(Vec::new()));
+        let connection = vocabulary.find_connection(&bos, &entries[0]).unwrap();
+        assert_eq!(connection.cost(), 0);
+    }
+}
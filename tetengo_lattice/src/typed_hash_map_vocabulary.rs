@@ -0,0 +1,297 @@
+/*!
+ * A typed hash map vocabulary.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::any::type_name_of_val;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use anyhow::Result;
+
+use crate::connection::Connection;
+use crate::entry::Entry;
+use crate::entry_view::EntryView;
+use crate::input::Input;
+use crate::string_input::StringInput;
+use crate::typed_vocabulary::TypedVocabulary;
+
+type EntryMap = HashMap<String, Vec<Entry>>;
+
+type ConnectionMap = HashMap<(u64, u64), Vec<((Entry, Entry), i32)>>;
+
+type EntryHashValue<'a, V> = &'a dyn Fn(&EntryView<'_, V>) -> u64;
+
+type EntryEqual<'a, V> = &'a dyn Fn(&EntryView<'_, V>, &EntryView<'_, V>) -> bool;
+
+/**
+ * A typed hash map vocabulary.
+ *
+ * The values of all the entries are of `V`, so that the hash and equality
+ * functions and the consumers receive them without downcasting.
+ *
+ * The keys are compared with `Input::equal_to`. When the keys are
+ * `StringInput`s, normalize them in the same normalization form as the inputs.
+ *
+ * # Type Parameters
+ * * `V` - A value type.
+ */
+pub struct TypedHashMapVocabulary<'a, V> {
+    entry_map: EntryMap,
+    connection_map: ConnectionMap,
+    entry_hash_value: EntryHashValue<'a, V>,
+    entry_equal: EntryEqual<'a, V>,
+}
+
+impl<V> Debug for TypedHashMapVocabulary<'_, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedHashMapVocabulary")
+            .field("entry_map", &self.entry_map)
+            .field("connection_map", &self.connection_map)
+            .field(
+                "entry_hash_value",
+                &type_name_of_val(&self.entry_hash_value),
+            )
+            .field("entry_equal", &type_name_of_val(&self.entry_equal))
+            .finish()
+    }
+}
+
+impl<'a, V: 'static> TypedHashMapVocabulary<'a, V> {
+    /**
+     * Creates a typed hash map vocabulary.
+     *
+     * # Arguments
+     * * `entries`          - Entries.
+     * * `connections`      - Connections.
+     * * `entry_hash_value` - A hash function for an entry view.
+     * * `entry_equal`      - An equality function for entry views.
+     *
+     * # Errors
+     * * When the value of an entry is not of `V`.
+     */
+    pub fn new(
+        entries: Vec<(String, Vec<Entry>)>,
+        connections: Vec<((Entry, Entry), i32)>,
+        entry_hash_value: EntryHashValue<'a, V>,
+        entry_equal: EntryEqual<'a, V>,
+    ) -> Result<Self> {
+        let entry_map = Self::make_entry_map(entries)?;
+        let connection_map = Self::make_connection_map(connections, entry_hash_value)?;
+        Ok(TypedHashMapVocabulary {
+            entry_map,
+            connection_map,
+            entry_hash_value,
+            entry_equal,
+        })
+    }
+
+    fn make_entry_map(entries: Vec<(String, Vec<Entry>)>) -> Result<EntryMap> {
+        let mut entry_map = EntryMap::new();
+        for (key, entries) in entries {
+            for entry in &entries {
+                let _view = EntryView::<V>::from_entry(entry)?;
+            }
+            let _prev_value = entry_map.insert(key, entries);
+        }
+        Ok(entry_map)
+    }
+
+    fn make_connection_map(
+        connections: Vec<((Entry, Entry), i32)>,
+        entry_hash_value: EntryHashValue<'a, V>,
+    ) -> Result<ConnectionMap> {
+        let mut connection_map = ConnectionMap::new();
+        for ((from, to), cost) in connections {
+            let hash_values = (
+                entry_hash_value(&EntryView::from_entry(&from)?),
+                entry_hash_value(&EntryView::from_entry(&to)?),
+            );
+            let bucket = connection_map.entry(hash_values).or_default();
+            bucket.push(((from, to), cost));
+        }
+        Ok(connection_map)
+    }
+
+    fn matches(&self, entry: &Entry, view: &EntryView<'_, V>) -> bool {
+        EntryView::from_entry(entry).is_ok_and(|entry| (self.entry_equal)(&entry, view))
+    }
+}
+
+impl<V: 'static> TypedVocabulary<V> for TypedHashMapVocabulary<'_, V> {
+    fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>> {
+        let Some(key) = key.downcast_ref::<StringInput>() else {
+            return Ok(Vec::new());
+        };
+        let Some(found) = self.entry_map.get(key.value()) else {
+            return Ok(Vec::new());
+        };
+
+        Ok(found.clone())
+    }
+
+    fn find_connection(
+        &self,
+        from: &EntryView<'_, V>,
+        to: &EntryView<'_, V>,
+    ) -> Result<Connection> {
+        let hash_values = ((self.entry_hash_value)(from), (self.entry_hash_value)(to));
+        let Some(bucket) = self.connection_map.get(&hash_values) else {
+            return Ok(Connection::new(i32::MAX));
+        };
+        let found = bucket.iter().rev().find(|((bucket_from, bucket_to), _)| {
+            self.matches(bucket_from, from) && self.matches(bucket_to, to)
+        });
+        Ok(Connection::new(found.map_or(i32::MAX, |(_, cost)| *cost)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::entry_view::EntryViewError;
+
+    use super::*;
+
+    fn entry_hash_value(entry: &EntryView<'_, String>) -> u64 {
+        let Some(key) = entry.key() else {
+            return 0;
+        };
+        key.hash_value()
+    }
+
+    fn entry_equal(one: &EntryView<'_, String>, other: &EntryView<'_, String>) -> bool {
+        match (one.key(), other.key()) {
+            (Some(one_key), Some(other_key)) => {
+                one_key.equal_to(other_key) && one.value() == other.value()
+            }
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    fn make_entry(key: &str, value: &str, cost: i32) -> Entry {
+        Entry::new(
+            Rc::new(StringInput::new(String::from(key))),
+            Rc::new(String::from(value)),
+            cost,
+        )
+    }
+
+    fn make_vocabulary() -> TypedHashMapVocabulary<'static, String> {
+        let entries = vec![
+            (
+                String::from("みずほ"),
+                vec![make_entry("みずほ", "瑞穂", 42)],
+            ),
+            (
+                String::from("さくら"),
+                vec![
+                    make_entry("さくら", "桜", 24),
+                    make_entry("さくら", "さくら", 2424),
+                ],
+            ),
+        ];
+        let connections = vec![
+            (
+                (
+                    make_entry("みずほ", "瑞穂", 42),
+                    make_entry("さくら", "桜", 24),
+                ),
+                4242,
+            ),
+            ((Entry::BosEos, make_entry("みずほ", "瑞穂", 42)), 999),
+        ];
+        TypedHashMapVocabulary::new(entries, connections, &entry_hash_value, &entry_equal).unwrap()
+    }
+
+    #[test]
+    fn new() {
+        {
+            let _vocabulary = TypedHashMapVocabulary::new(
+                Vec::new(),
+                Vec::new(),
+                &entry_hash_value,
+                &entry_equal,
+            )
+            .unwrap();
+        }
+        {
+            let _vocabulary = make_vocabulary();
+        }
+        {
+            let entries = vec![(
+                String::from("みずほ"),
+                vec![Entry::new(
+                    Rc::new(StringInput::new(String::from("みずほ"))),
+                    Rc::new(42),
+                    42,
+                )],
+            )];
+            let error =
+                TypedHashMapVocabulary::new(entries, Vec::new(), &entry_hash_value, &entry_equal)
+                    .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<EntryViewError>(),
+                Some(EntryViewError::MismatchedValueType)
+            ));
+        }
+    }
+
+    #[test]
+    fn find_entries() {
+        let vocabulary = make_vocabulary();
+
+        {
+            let found = vocabulary
+                .find_entries(&StringInput::new(String::from("みずほ")))
+                .unwrap();
+            assert_eq!(found.len(), 1);
+            let view = EntryView::<String>::from_entry(&found[0]).unwrap();
+            assert_eq!(view.value().unwrap(), "瑞穂");
+            assert_eq!(view.cost(), 42);
+        }
+        {
+            let found = vocabulary
+                .find_entries(&StringInput::new(String::from("さくら")))
+                .unwrap();
+            assert_eq!(found.len(), 2);
+        }
+        {
+            let found = vocabulary
+                .find_entries(&StringInput::new(String::from("のぞみ")))
+                .unwrap();
+            assert!(found.is_empty());
+        }
+    }
+
+    #[test]
+    fn find_connection() {
+        let vocabulary = make_vocabulary();
+
+        let mizuho = make_entry("みずほ", "瑞穂", 42);
+        let sakura1 = make_entry("さくら", "桜", 24);
+        let sakura2 = make_entry("さくら", "さくら", 2424);
+        let view = |entry| EntryView::<String>::from_entry(entry).unwrap();
+        {
+            let connection = vocabulary
+                .find_connection(&view(&mizuho), &view(&sakura1))
+                .unwrap();
+            assert_eq!(connection.cost(), 4242);
+        }
+        {
+            let connection = vocabulary
+                .find_connection(&view(&mizuho), &view(&sakura2))
+                .unwrap();
+            assert_eq!(connection.cost(), i32::MAX);
+        }
+        {
+            let connection = vocabulary
+                .find_connection(&EntryView::bos_eos(), &view(&mizuho))
+                .unwrap();
+            assert_eq!(connection.cost(), 999);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+/*!
+ * A typed vocabulary.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use anyhow::Result;
+
+use crate::connection::Connection;
+use crate::entry::Entry;
+use crate::entry_view::EntryView;
+use crate::input::Input;
+use crate::node::Node;
+use crate::vocabulary::Vocabulary;
+
+/**
+ * A typed vocabulary.
+ *
+ * A vocabulary whose entries all hold values of a single type.
+ *
+ * # Type Parameters
+ * * `V` - A value type.
+ */
+pub trait TypedVocabulary<V>: Debug {
+    /**
+     * Finds entries.
+     *
+     * The values of the entries must be of `V`.
+     *
+     * # Arguments
+     * * `key` - A key.
+     *
+     * # Returns
+     * Entries.
+     *
+     * # Errors
+     * * When finding entries fails.
+     */
+    fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>>;
+
+    /**
+     * Finds a connection between an origin entry and a destination entry.
+     *
+     * # Arguments
+     * * `from` - An origin entry view.
+     * * `to`   - A destination entry view.
+     *
+     * # Returns
+     * A connection between the origin entry and the destination entry.
+     *
+     * # Errors
+     * * When finding a connection fails.
+     */
+    fn find_connection(&self, from: &EntryView<'_, V>, to: &EntryView<'_, V>)
+        -> Result<Connection>;
+}
+
+/**
+ * A typed vocabulary adapter.
+ *
+ * Makes a typed vocabulary usable as a `Vocabulary`.
+ *
+ * # Type Parameters
+ * * `V` - A value type.
+ * * `T` - A typed vocabulary type.
+ */
+pub struct TypedVocabularyAdapter<V, T> {
+    typed_vocabulary: T,
+    _phantom: PhantomData<fn() -> V>,
+}
+
+impl<V, T: Debug> Debug for TypedVocabularyAdapter<V, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedVocabularyAdapter")
+            .field("typed_vocabulary", &self.typed_vocabulary)
+            .finish()
+    }
+}
+
+impl<V: 'static, T: TypedVocabulary<V>> TypedVocabularyAdapter<V, T> {
+    /**
+     * Creates a typed vocabulary adapter.
+     *
+     * # Arguments
+     * * `typed_vocabulary` - A typed vocabulary.
+     */
+    pub const fn new(typed_vocabulary: T) -> Self {
+        Self {
+            typed_vocabulary,
+            _phantom: PhantomData,
+        }
+    }
+
+    /**
+     * Returns the typed vocabulary.
+     *
+     * # Returns
+     * The typed vocabulary.
+     */
+    pub const fn typed_vocabulary(&self) -> &T {
+        &self.typed_vocabulary
+    }
+}
+
+impl<V: 'static, T: TypedVocabulary<V>> Vocabulary for TypedVocabularyAdapter<V, T> {
+    fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>> {
+        self.typed_vocabulary.find_entries(key)
+    }
+
+    fn find_connection(&self, from: &Node, to: &Entry) -> Result<Connection> {
+        self.typed_vocabulary
+            .find_connection(&EntryView::from_node(from)?, &EntryView::from_entry(to)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::entry_view::EntryViewError;
+    use crate::string_input::StringInput;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct ConcreteTypedVocabulary {}
+
+    impl TypedVocabulary<i32> for ConcreteTypedVocabulary {
+        fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>> {
+            let Some(key) = key.downcast_ref::<StringInput>() else {
+                return Ok(Vec::new());
+            };
+            Ok(vec![Entry::new(Rc::new(key.clone()), Rc::new(42), 24)])
+        }
+
+        fn find_connection(
+            &self,
+            from: &EntryView<'_, i32>,
+            to: &EntryView<'_, i32>,
+        ) -> Result<Connection> {
+            Ok(Connection::new(
+                from.value().copied().unwrap_or(0) + to.value().copied().unwrap_or(0),
+            ))
+        }
+    }
+
+    #[test]
+    fn new() {
+        let _adapter = TypedVocabularyAdapter::new(ConcreteTypedVocabulary {});
+    }
+
+    #[test]
+    fn typed_vocabulary() {
+        let adapter = TypedVocabularyAdapter::new(ConcreteTypedVocabulary {});
+
+        let _typed_vocabulary: &ConcreteTypedVocabulary = adapter.typed_vocabulary();
+    }
+
+    #[test]
+    fn find_entries() {
+        let adapter = TypedVocabularyAdapter::new(ConcreteTypedVocabulary {});
+        let vocabulary: &dyn Vocabulary = &adapter;
+
+        let entries = vocabulary
+            .find_entries(&StringInput::new(String::from("みずほ")))
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].value().unwrap().downcast_ref::<i32>().unwrap(),
+            &42
+        );
+    }
+
+    #[test]
+    fn find_connection() {
+        let adapter = TypedVocabularyAdapter::new(ConcreteTypedVocabulary {});
+        let vocabulary: &dyn Vocabulary = &adapter;
+
+        let bos = Node::bos(Rc::new(Vec::new()));
+        {
+            let entry = Entry::new(
+                Rc::new(StringInput::new(String::from("みずほ"))),
+                Rc::new(42),
+                24,
+            );
+            let connection = vocabulary.find_connection(&bos, &entry).unwrap();
+            assert_eq!(connection.cost(), 42);
+        }
+        {
+            let entry = Entry::new(
+                Rc::new(StringInput::new(String::from("みずほ"))),
+                Rc::new(String::from("瑞穂")),
+                24,
+            );
+            let error = vocabulary.find_connection(&bos, &entry).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<EntryViewError>(),
+                Some(EntryViewError::MismatchedValueType)
+            ));
+        }
+    }
+}
@@ -0,0 +1,229 @@
+/*!
+ * A Unicode word vocabulary.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::rc::Rc;
+
+use anyhow::Result;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::connection::Connection;
+use crate::entry::Entry;
+use crate::input::Input;
+use crate::node::Node;
+use crate::string_input::StringInput;
+use crate::vocabulary::Vocabulary;
+
+/**
+ * A Unicode word vocabulary.
+ *
+ * The vocabulary segments text by the word boundaries defined in Unicode
+ * Standard Annex #29. A key is found as an entry only when the whole key
+ * forms a single segment. Words, spaces and punctuations are all segments.
+ *
+ * All the entries have the same cost, and so do all the connections. Thus the
+ * best path in a lattice consists of the fewest segments.
+ *
+ * The keys must be `StringInput`s. Push the text into a lattice character by
+ * character so that the lattice can find the segment boundaries.
+ *
+ * The value of an entry is a `String` of the segment.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct UnicodeWordVocabulary {
+    entry_cost: i32,
+    connection_cost: i32,
+}
+
+impl UnicodeWordVocabulary {
+    /// The default entry cost.
+    pub const DEFAULT_ENTRY_COST: i32 = 1;
+
+    /// The default connection cost.
+    pub const DEFAULT_CONNECTION_COST: i32 = 0;
+
+    /**
+     * Creates a Unicode word vocabulary.
+     */
+    pub const fn new() -> Self {
+        Self::new_with_costs(Self::DEFAULT_ENTRY_COST, Self::DEFAULT_CONNECTION_COST)
+    }
+
+    /**
+     * Creates a Unicode word vocabulary with costs.
+     *
+     * # Arguments
+     * * `entry_cost`      - An entry cost.
+     * * `connection_cost` - A connection cost.
+     */
+    pub const fn new_with_costs(entry_cost: i32, connection_cost: i32) -> Self {
+        Self {
+            entry_cost,
+            connection_cost,
+        }
+    }
+
+    /**
+     * Returns the entry cost.
+     *
+     * # Returns
+     * The entry cost.
+     */
+    pub const fn entry_cost(&self) -> i32 {
+        self.entry_cost
+    }
+
+    /**
+     * Returns the connection cost.
+     *
+     * # Returns
+     * The connection cost.
+     */
+    pub const fn connection_cost(&self) -> i32 {
+        self.connection_cost
+    }
+}
+
+impl Default for UnicodeWordVocabulary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vocabulary for UnicodeWordVocabulary {
+    fn find_entries(&self, key: &dyn Input) -> Result<Vec<Entry>> {
+        let Some(key) = key.downcast_ref::<StringInput>() else {
+            return Ok(Vec::new());
+        };
+        let mut segments = key.value().split_word_bounds();
+        let (Some(segment), None) = (segments.next(), segments.next()) else {
+            return Ok(Vec::new());
+        };
+
+        Ok(vec![Entry::new(
+            Rc::new(key.clone()),
+            Rc::new(segment.to_string()),
+            self.entry_cost,
+        )])
+    }
+
+    fn find_connection(&self, _: &Node, _: &Entry) -> Result<Connection> {
+        Ok(Connection::new(self.connection_cost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constraint::Constraint;
+    use crate::lattice::Lattice;
+    use crate::n_best_iterator::NBestIterator;
+
+    use super::*;
+
+    fn value_of(node: &Node) -> &str {
+        node.value().unwrap().downcast_ref::<String>().unwrap()
+    }
+
+    #[test]
+    fn new() {
+        let vocabulary = UnicodeWordVocabulary::new();
+
+        assert_eq!(
+            vocabulary.entry_cost(),
+            UnicodeWordVocabulary::DEFAULT_ENTRY_COST
+        );
+        assert_eq!(
+            vocabulary.connection_cost(),
+            UnicodeWordVocabulary::DEFAULT_CONNECTION_COST
+        );
+    }
+
+    #[test]
+    fn new_with_costs() {
+        let vocabulary = UnicodeWordVocabulary::new_with_costs(42, 24);
+
+        assert_eq!(vocabulary.entry_cost(), 42);
+        assert_eq!(vocabulary.connection_cost(), 24);
+    }
+
+    #[test]
+    fn find_entries() {
+        let vocabulary = UnicodeWordVocabulary::new_with_costs(42, 24);
+
+        {
+            let found = vocabulary
+                .find_entries(&StringInput::new(String::from("Kumamoto")))
+                .unwrap();
+            assert_eq!(found.len(), 1);
+            assert_eq!(
+                found[0]
+                    .key()
+                    .unwrap()
+                    .downcast_ref::<StringInput>()
+                    .unwrap()
+                    .value(),
+                "Kumamoto"
+            );
+            assert_eq!(
+                found[0].value().unwrap().downcast_ref::<String>().unwrap(),
+                "Kumamoto"
+            );
+            assert_eq!(found[0].cost(), 42);
+        }
+        {
+            let found = vocabulary
+                .find_entries(&StringInput::new(String::from(" ")))
+                .unwrap();
+            assert_eq!(found.len(), 1);
+        }
+        {
+            let found = vocabulary
+                .find_entries(&StringInput::new(String::from("Kumamoto Tamana")))
+                .unwrap();
+            assert!(found.is_empty());
+        }
+        {
+            let found = vocabulary
+                .find_entries(&StringInput::new(String::new()))
+                .unwrap();
+            assert!(found.is_empty());
+        }
+    }
+
+    #[test]
+    fn find_connection() {
+        let vocabulary = UnicodeWordVocabulary::new_with_costs(42, 24);
+
+        let connection = vocabulary
+            .find_connection(&Node::bos(Rc::new(Vec::new())), &Entry::BosEos)
+            .unwrap();
+
+        assert_eq!(connection.cost(), 24);
+    }
+
+    #[test]
+    fn lattice() {
+        let vocabulary = UnicodeWordVocabulary::new();
+        let mut lattice = Lattice::new(&vocabulary);
+        for c in "The quick fox.".chars() {
+            let _delta = lattice
+                .push_back(Box::new(StringInput::new(c.to_string())))
+                .unwrap();
+        }
+        let eos_node = lattice.settle().unwrap();
+
+        let mut iterator = NBestIterator::new(&lattice, eos_node, Box::new(Constraint::new()));
+        let path = iterator.next().unwrap();
+
+        let segments = path
+            .nodes()
+            .iter()
+            .filter(|node| node.value().is_some())
+            .map(value_of)
+            .collect::<Vec<_>>();
+        assert_eq!(segments, ["The", " ", "quick", " ", "fox", "."]);
+        assert_eq!(path.cost(), 6);
+    }
+}
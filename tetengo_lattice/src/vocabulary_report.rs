@@ -0,0 +1,235 @@
+/*!
+ * A vocabulary report.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use crate::entry::Entry;
+
+/**
+ * A vocabulary report.
+ *
+ * Lists the entries and the connections which may silently remove paths from
+ * the N-best output.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct VocabularyReport {
+    entries_without_bos_connection: Vec<Entry>,
+    entries_without_eos_connection: Vec<Entry>,
+    unreachable_entries: Vec<Entry>,
+    dead_end_entries: Vec<Entry>,
+    duplicate_entries: Vec<Entry>,
+    dangling_connection_count: usize,
+}
+
+impl VocabularyReport {
+    /**
+     * Creates a vocabulary report.
+     *
+     * # Arguments
+     * * `entries_without_bos_connection` - The entries with no connection from BOS.
+     * * `entries_without_eos_connection` - The entries with no connection to EOS.
+     * * `unreachable_entries`            - The entries with no incoming connection.
+     * * `dead_end_entries`               - The entries with no outgoing connection.
+     * * `duplicate_entries`              - The entries equal to a preceding one.
+     * * `dangling_connection_count`      - The count of the connections from or to unknown entries.
+     */
+    pub const fn new(
+        entries_without_bos_connection: Vec<Entry>,
+        entries_without_eos_connection: Vec<Entry>,
+        unreachable_entries: Vec<Entry>,
+        dead_end_entries: Vec<Entry>,
+        duplicate_entries: Vec<Entry>,
+        dangling_connection_count: usize,
+    ) -> Self {
+        Self {
+            entries_without_bos_connection,
+            entries_without_eos_connection,
+            unreachable_entries,
+            dead_end_entries,
+            duplicate_entries,
+            dangling_connection_count,
+        }
+    }
+
+    /**
+     * Returns the entries with no connection from BOS.
+     *
+     * Such entries cannot begin a path.
+     *
+     * # Returns
+     * The entries with no connection from BOS.
+     */
+    pub fn entries_without_bos_connection(&self) -> &[Entry] {
+        self.entries_without_bos_connection.as_slice()
+    }
+
+    /**
+     * Returns the entries with no connection to EOS.
+     *
+     * Such entries cannot end a path.
+     *
+     * # Returns
+     * The entries with no connection to EOS.
+     */
+    pub fn entries_without_eos_connection(&self) -> &[Entry] {
+        self.entries_without_eos_connection.as_slice()
+    }
+
+    /**
+     * Returns the entries with no incoming connection.
+     *
+     * Neither BOS nor any entry connects to such entries, so they never appear
+     * in the paths.
+     *
+     * # Returns
+     * The unreachable entries.
+     */
+    pub fn unreachable_entries(&self) -> &[Entry] {
+        self.unreachable_entries.as_slice()
+    }
+
+    /**
+     * Returns the entries with no outgoing connection.
+     *
+     * Such entries connect to neither EOS nor any entry, so they never appear
+     * in the paths.
+     *
+     * # Returns
+     * The dead end entries.
+     */
+    pub fn dead_end_entries(&self) -> &[Entry] {
+        self.dead_end_entries.as_slice()
+    }
+
+    /**
+     * Returns the entries equal to a preceding one.
+     *
+     * The connections cannot distinguish such entries.
+     *
+     * # Returns
+     * The duplicate entries.
+     */
+    pub fn duplicate_entries(&self) -> &[Entry] {
+        self.duplicate_entries.as_slice()
+    }
+
+    /**
+     * Returns the count of the connections from or to unknown entries.
+     *
+     * # Returns
+     * The count of the dangling connections.
+     */
+    pub const fn dangling_connection_count(&self) -> usize {
+        self.dangling_connection_count
+    }
+
+    /**
+     * Returns `true` if the report has no entry nor connection which removes
+     * paths.
+     *
+     * The entries without connections from BOS or to EOS are not regarded as
+     * problems, since most entries cannot begin or end a sequence.
+     *
+     * # Returns
+     * `true` if the vocabulary is clean.
+     */
+    pub fn is_clean(&self) -> bool {
+        self.unreachable_entries.is_empty()
+            && self.dead_end_entries.is_empty()
+            && self.duplicate_entries.is_empty()
+            && self.dangling_connection_count == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::string_input::StringInput;
+
+    use super::*;
+
+    fn entry(key: &str) -> Entry {
+        Entry::new(
+            Rc::new(StringInput::new(String::from(key))),
+            Rc::new(String::from(key)),
+            0,
+        )
+    }
+
+    fn make_report() -> VocabularyReport {
+        VocabularyReport::new(
+            vec![entry("a")],
+            vec![entry("b")],
+            vec![entry("c")],
+            vec![entry("d")],
+            vec![entry("e")],
+            3,
+        )
+    }
+
+    fn keys(entries: &[Entry]) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|entry| {
+                entry
+                    .key()
+                    .unwrap()
+                    .downcast_ref::<StringInput>()
+                    .unwrap()
+                    .value()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn new() {
+        let _report = make_report();
+    }
+
+    #[test]
+    fn entries_without_bos_connection() {
+        assert_eq!(keys(make_report().entries_without_bos_connection()), ["a"]);
+    }
+
+    #[test]
+    fn entries_without_eos_connection() {
+        assert_eq!(keys(make_report().entries_without_eos_connection()), ["b"]);
+    }
+
+    #[test]
+    fn unreachable_entries() {
+        assert_eq!(keys(make_report().unreachable_entries()), ["c"]);
+    }
+
+    #[test]
+    fn dead_end_entries() {
+        assert_eq!(keys(make_report().dead_end_entries()), ["d"]);
+    }
+
+    #[test]
+    fn duplicate_entries() {
+        assert_eq!(keys(make_report().duplicate_entries()), ["e"]);
+    }
+
+    #[test]
+    fn dangling_connection_count() {
+        assert_eq!(make_report().dangling_connection_count(), 3);
+    }
+
+    #[test]
+    fn is_clean() {
+        assert!(!make_report().is_clean());
+        assert!(VocabularyReport::default().is_clean());
+        assert!(VocabularyReport::new(
+            vec![entry("a")],
+            vec![entry("b")],
+            vec![],
+            vec![],
+            vec![],
+            0
+        )
+        .is_clean());
+    }
+}
@@ -0,0 +1,81 @@
+/*!
+ * A benchmark of building a trie from a skewed key set.
+ *
+ * The keys are random strings of kana whose characters are chosen with a
+ * skewed distribution, like the keys of a Japanese lexicon. So the double
+ * array is densely filled at its head.
+ *
+ * Run with `cargo bench -p tetengo_trie --bench build`. Add
+ * `--no-default-features --features std` to measure the storage scan instead
+ * of the bitset scan.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use tetengo_trie::Trie;
+
+const KEY_COUNT: usize = 200_000;
+
+const MAX_KEY_LENGTH: usize = 8;
+
+const ITERATION_COUNT: usize = 3;
+
+fn main() {
+    let mut elements = make_keys()
+        .into_iter()
+        .enumerate()
+        .map(|(i, key)| (key, i as i32))
+        .collect::<Vec<_>>();
+    elements.sort();
+    elements.dedup_by(|one, another| one.0 == another.0);
+
+    let duration = measure(|| {
+        let trie = Trie::<String, i32>::builder()
+            .elements(elements.clone())
+            .assume_sorted()
+            .build()
+            .unwrap();
+        drop(black_box(trie));
+    });
+    println!("building a trie of {} keys", elements.len());
+    println!("    total:        {:>10.1?}", duration);
+}
+
+// Makes the keys with a linear congruential generator, so that the key set is
+// the same in every run.
+fn make_keys() -> Vec<String> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = |bound: usize| {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (state >> 33) as usize % bound
+    };
+    (0..KEY_COUNT)
+        .map(|_| {
+            let length = 1 + next(MAX_KEY_LENGTH);
+            (0..length)
+                .map(|_| {
+                    // The square of a uniform random number skews the characters to the head.
+                    let index = next(96);
+                    char::from_u32(0x3041 + (index * index / 96) as u32).unwrap_or('?')
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Returns the best time of the iterations.
+fn measure(mut f: impl FnMut()) -> Duration {
+    (0..ITERATION_COUNT)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
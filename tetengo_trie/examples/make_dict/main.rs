@@ -7,13 +7,17 @@
 use std::collections::HashMap;
 use std::env;
 use std::fs::File;
-use std::io::{BufRead, BufReader, BufWriter};
+use std::io::{BufReader, BufWriter};
 use std::path::Path;
 use std::process::exit;
+use std::time::Instant;
 
 use anyhow::Result;
 
-use tetengo_trie::{BuldingObserverSet, Serializer, StringSerializer, Trie, ValueSerializer};
+use tetengo_trie::{
+    BuldingObserverSet, CsvLexiconSource, JsonlLexiconSource, LexiconSource, Serializer,
+    StringSerializer, Trie, TsvLexiconSource, ValueSerializer,
+};
 
 fn main() {
     if let Err(e) = main_core() {
@@ -22,105 +26,149 @@ fn main() {
     }
 }
 
+const USAGE: &str = "\
+Usage: make_dict lexicon trie.bin [--format unidic|csv|tsv|jsonl] [--keys keys]
+
+  --format  The format of the lexicon. (default: unidic)
+  --keys    The comma-separated column indices for csv and tsv, or the
+            comma-separated member names for jsonl.";
+
 fn main_core() -> Result<()> {
-    let args = env::args().collect::<Vec<_>>();
-    if args.len() <= 2 {
-        eprintln!("Usage: make_dict UniDic_lex.csv trie.bin");
+    let (args, format, keys) = parse_args(env::args().skip(1).collect())?;
+    if args.len() < 2 {
+        eprintln!("{}", USAGE);
         return Ok(());
     }
 
-    let word_offset_map = load_lex_csv(Path::new(&args[1]))?;
+    let reader = BufReader::new(File::open(Path::new(&args[0]))?);
+    let mut lexicon_source = make_lexicon_source(reader, format, keys.as_deref())?;
+    let word_offset_map = load_lexicon(lexicon_source.as_mut())?;
     let trie = build_trie(word_offset_map)?;
-    serialize_trie(&trie, Path::new(&args[2]))?;
+    serialize_trie(&trie, Path::new(&args[1]))?;
 
     Ok(())
 }
 
 #[derive(Debug, thiserror::Error)]
 enum DictMakingError {
-    #[error("Invalid UniDic lex.csv format.")]
-    InvalidUnidicLexCsvFormat,
-}
+    #[error("Unknown format: {0}")]
+    UnknownFormat(String),
 
-type WordOffsetMap = HashMap<String, Vec<(usize, usize)>>;
+    #[error("Invalid keys: {0}")]
+    InvalidKeys(String),
 
-fn load_lex_csv(lex_csv_path: &Path) -> Result<WordOffsetMap> {
-    let file = File::open(lex_csv_path)?;
+    #[error("Invalid arguments.\n{USAGE}")]
+    InvalidArguments,
+}
 
-    let mut word_offset_map = WordOffsetMap::new();
+#[derive(Clone, Copy, Debug)]
+enum Format {
+    Unidic,
+    Csv,
+    Tsv,
+    Jsonl,
+}
 
-    eprintln!("Loading UniDic lex.csv...");
-    let mut line_head = 0usize;
-    let buf_reader = BufReader::new(file);
-    for (i, line) in buf_reader.lines().enumerate() {
-        let Ok(line) = line else {
-            eprintln!("{:8}: Can't read this line.", i);
-            return Err(DictMakingError::InvalidUnidicLexCsvFormat.into());
-        };
-        if line.is_empty() {
-            line_head += line.len() + 1;
-            continue;
-        }
-        let elements = split(&line, ',');
-        if elements.len() != 33 {
-            eprintln!("{:8}: {}", i, elements[0]);
-            return Err(DictMakingError::InvalidUnidicLexCsvFormat.into());
+fn parse_args(args: Vec<String>) -> Result<(Vec<String>, Format, Option<String>)> {
+    let mut rest = Vec::new();
+    let mut format = Format::Unidic;
+    let mut keys = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("unidic") => Format::Unidic,
+                    Some("csv") => Format::Csv,
+                    Some("tsv") => Format::Tsv,
+                    Some("jsonl") => Format::Jsonl,
+                    Some(other) => {
+                        return Err(DictMakingError::UnknownFormat(other.to_string()).into())
+                    }
+                    None => return Err(DictMakingError::InvalidArguments.into()),
+                };
+            }
+            "--keys" => {
+                let Some(value) = args.next() else {
+                    return Err(DictMakingError::InvalidArguments.into());
+                };
+                keys = Some(value);
+            }
+            _ => rest.push(arg),
         }
+    }
+    Ok((rest, format, keys))
+}
 
-        if elements[16] == "記号" && elements[23] == "補助" {
-            insert_word_offset_to_map(elements[0], line_head, line.len() + 1, &mut word_offset_map);
-        } else {
-            insert_word_offset_to_map(
-                elements[12],
-                line_head,
-                line.len() + 1,
-                &mut word_offset_map,
-            );
-            insert_word_offset_to_map(
-                elements[24],
-                line_head,
-                line.len() + 1,
-                &mut word_offset_map,
-            );
+fn make_lexicon_source(
+    reader: BufReader<File>,
+    format: Format,
+    keys: Option<&str>,
+) -> Result<Box<dyn LexiconSource>> {
+    let lexicon_source: Box<dyn LexiconSource> = match format {
+        Format::Unidic => Box::new(CsvLexiconSource::new_with_key_selector(
+            reader,
+            Box::new(select_unidic_keys),
+        )),
+        Format::Csv => Box::new(CsvLexiconSource::new(reader, &parse_key_columns(keys)?)),
+        Format::Tsv => Box::new(TsvLexiconSource::new(reader, &parse_key_columns(keys)?)),
+        Format::Jsonl => {
+            let keys = keys.unwrap_or("surface");
+            Box::new(JsonlLexiconSource::new(
+                reader,
+                &keys.split(',').collect::<Vec<_>>(),
+            ))
         }
+    };
+    Ok(lexicon_source)
+}
 
-        if i % 10000 == 0 {
-            eprint!("{:8}: {}    \r", i, elements[0]);
-        }
+fn parse_key_columns(keys: Option<&str>) -> Result<Vec<usize>> {
+    let Some(keys) = keys else {
+        return Ok(vec![0]);
+    };
+    keys.split(',')
+        .map(|key| {
+            key.parse::<usize>()
+                .map_err(|_| DictMakingError::InvalidKeys(keys.to_string()).into())
+        })
+        .collect()
+}
 
-        line_head += line.len() + 1;
+fn select_unidic_keys(columns: &[&str]) -> Option<Vec<String>> {
+    if columns.len() != 33 {
+        return None;
+    }
+    if columns[16] == "記号" && columns[23] == "補助" {
+        Some(vec![columns[0].to_string()])
+    } else {
+        Some(vec![columns[12].to_string(), columns[24].to_string()])
     }
-    eprintln!("Done.        ");
-
-    Ok(word_offset_map)
 }
 
-fn split(string: &str, delimiter: char) -> Vec<&str> {
-    let mut elements = Vec::new();
-
-    let mut first = 0usize;
-    loop {
-        if first < string.len() && string[first..].starts_with('"') {
-            if let Some(length) = string[first + 1..].find('"') {
-                let last = first + 1 + length;
-                elements.push(&string[first + 1..last]);
-                debug_assert!(string[last + 1..].starts_with(delimiter));
-                first = last + 2;
-            } else {
-                elements.push(&string[first + 1..]);
-                break;
-            }
-        } else if let Some(length) = string[first..].find(delimiter) {
-            let last = first + length;
-            elements.push(&string[first..last]);
-            first = last + 1;
-        } else {
-            elements.push(&string[first..]);
-            break;
+type WordOffsetMap = HashMap<String, Vec<(usize, usize)>>;
+
+fn load_lexicon(lexicon_source: &mut dyn LexiconSource) -> Result<WordOffsetMap> {
+    let mut word_offset_map = WordOffsetMap::new();
+
+    eprintln!("Loading the lexicon...");
+    let mut i = 0usize;
+    while let Some(record) = lexicon_source.next_record()? {
+        insert_word_offset_to_map(
+            record.key(),
+            record.offset(),
+            record.length(),
+            &mut word_offset_map,
+        );
+
+        if i % 10000 == 0 {
+            eprint!("{:8}: {}    \r", i, record.key());
         }
+        i += 1;
     }
+    eprintln!("Done.        ");
 
-    elements
+    Ok(word_offset_map)
 }
 
 const VALUE_CAPACITY: usize = 4usize;
@@ -141,12 +189,14 @@ type DictTrie = Trie<String, Vec<(usize, usize)>>;
 
 fn build_trie(word_offset_map: WordOffsetMap) -> Result<DictTrie> {
     eprintln!("Building trie...");
+    let started = Instant::now();
     let mut word_offset_vector = word_offset_map.into_iter().collect::<Vec<_>>();
     word_offset_vector.sort();
     let mut index = 0usize;
     let trie = DictTrie::builder()
         .elements(word_offset_vector)
         .key_serializer(StringSerializer::new(true))
+        .assume_sorted()
         .build_with_observer_set(&mut BuldingObserverSet::new(
             &mut |key| {
                 if index % 10000 == 0 {
@@ -156,7 +206,7 @@ fn build_trie(word_offset_map: WordOffsetMap) -> Result<DictTrie> {
             },
             &mut || {},
         ));
-    eprintln!("Done.        ");
+    eprintln!("Done. ({:.2}s)", started.elapsed().as_secs_f64());
     trie
 }
 
@@ -0,0 +1,196 @@
+/*!
+ * A trie tool.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::env;
+use std::fmt::{Debug, Display};
+use std::fs::File;
+use std::io::{stdout, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process::exit;
+
+use anyhow::Result;
+
+use tetengo_trie::{
+    Deserializer, MemoryStorage, StringDeserializer, Trie, ValueDeserializer, ValueSerializer,
+};
+
+fn main() {
+    if let Err(e) = main_core() {
+        eprintln!("Error: {}", e);
+        exit(1);
+    }
+}
+
+const USAGE: &str = "\
+Usage: trie_tool build input.tsv trie.bin [--value-type string|integer]
+       trie_tool lookup trie.bin key [--value-type string|integer]
+       trie_tool prefix trie.bin prefix [--value-type string|integer]
+       trie_tool dump trie.bin [--value-type string|integer]
+       trie_tool stats trie.bin [--value-type string|integer]";
+
+fn main_core() -> Result<()> {
+    let (args, value_type) = parse_args(env::args().skip(1).collect())?;
+    let Some((command, operands)) = args.split_first() else {
+        eprintln!("{}", USAGE);
+        return Ok(());
+    };
+    match value_type {
+        ValueType::String => run::<String>(command, operands),
+        ValueType::Integer => run::<i32>(command, operands),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum TrieToolError {
+    #[error("Unknown value type: {0}")]
+    UnknownValueType(String),
+
+    #[error("Invalid arguments.\n{USAGE}")]
+    InvalidArguments,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ValueType {
+    String,
+    Integer,
+}
+
+fn parse_args(args: Vec<String>) -> Result<(Vec<String>, ValueType)> {
+    let mut rest = Vec::new();
+    let mut value_type = ValueType::String;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg != "--value-type" {
+            rest.push(arg);
+            continue;
+        }
+        value_type = match args.next().as_deref() {
+            Some("string") => ValueType::String,
+            Some("integer") => ValueType::Integer,
+            Some(other) => return Err(TrieToolError::UnknownValueType(other.to_string()).into()),
+            None => return Err(TrieToolError::InvalidArguments.into()),
+        };
+    }
+    Ok((rest, value_type))
+}
+
+trait ToolValue: Clone + Debug + Display + Sized + 'static {
+    const FIXED_SIZE: usize;
+
+    fn parse(text: &str) -> Result<Self>;
+
+    fn serialize(&self) -> Vec<u8>;
+
+    fn deserialize(bytes: &[u8]) -> Result<Self>;
+}
+
+impl ToolValue for String {
+    const FIXED_SIZE: usize = 0;
+
+    fn parse(text: &str) -> Result<Self> {
+        Ok(text.to_string())
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self> {
+        Ok(String::from_utf8(bytes.to_vec())?)
+    }
+}
+
+impl ToolValue for i32 {
+    const FIXED_SIZE: usize = size_of::<i32>();
+
+    fn parse(text: &str) -> Result<Self> {
+        Ok(text.parse()?)
+    }
+
+    fn serialize(&self) -> Vec<u8> {
+        self.to_be_bytes().to_vec()
+    }
+
+    fn deserialize(bytes: &[u8]) -> Result<Self> {
+        Ok(i32::from_be_bytes(bytes.try_into()?))
+    }
+}
+
+type ToolTrie<Value> = Trie<String, Value>;
+
+fn run<Value: ToolValue>(command: &str, operands: &[String]) -> Result<()> {
+    match (command, operands) {
+        ("build", [input_tsv, trie_bin]) => {
+            build::<Value>(Path::new(input_tsv), Path::new(trie_bin))
+        }
+        ("lookup", [trie_bin, key]) => lookup(&load_trie::<Value>(Path::new(trie_bin))?, key),
+        ("prefix", [trie_bin, prefix]) => {
+            list_prefixed(&load_trie::<Value>(Path::new(trie_bin))?, prefix)
+        }
+        ("dump", [trie_bin]) => dump(&load_trie::<Value>(Path::new(trie_bin))?),
+        ("stats", [trie_bin]) => show_stats(&load_trie::<Value>(Path::new(trie_bin))?),
+        _ => Err(TrieToolError::InvalidArguments.into()),
+    }
+}
+
+fn build<Value: ToolValue>(input_tsv_path: &Path, trie_bin_path: &Path) -> Result<()> {
+    eprintln!("Loading {}...", input_tsv_path.display());
+    let mut input_tsv = File::open(input_tsv_path)?;
+    let trie = ToolTrie::<Value>::load_tsv(&mut input_tsv, &mut |text| Value::parse(text))?;
+
+    eprintln!("Serializing the trie...");
+    let mut buf_writer = BufWriter::new(File::create(trie_bin_path)?);
+    let mut serializer = ValueSerializer::new(Box::new(Value::serialize), Value::FIXED_SIZE);
+    trie.storage().serialize(&mut buf_writer, &mut serializer)?;
+    buf_writer.flush()?;
+    eprintln!("Done. {} keys.", trie.size()?);
+    Ok(())
+}
+
+fn load_trie<Value: ToolValue>(trie_bin_path: &Path) -> Result<ToolTrie<Value>> {
+    let mut buf_reader = BufReader::new(File::open(trie_bin_path)?);
+    let mut value_deserializer =
+        ValueDeserializer::new(Box::new(|bytes: &[u8]| Value::deserialize(bytes)));
+    let storage = Box::new(MemoryStorage::new_with_reader(
+        &mut buf_reader,
+        &mut value_deserializer,
+    )?);
+    Ok(ToolTrie::<Value>::builder_with_storage(storage).build())
+}
+
+fn lookup<Value: ToolValue>(trie: &ToolTrie<Value>, key: &str) -> Result<()> {
+    match trie.find(&key.to_string())? {
+        Some(value) => println!("{}", value),
+        None => println!("ERROR: Not found."),
+    }
+    Ok(())
+}
+
+fn list_prefixed<Value: ToolValue>(trie: &ToolTrie<Value>, prefix: &str) -> Result<()> {
+    let Some(subtrie) = trie.subtrie(&prefix.to_string())? else {
+        return Ok(());
+    };
+    for (suffix, value) in subtrie.iter_suffixes(StringDeserializer::new(true)) {
+        println!("{}{}\t{}", prefix, suffix, value);
+    }
+    Ok(())
+}
+
+fn dump<Value: ToolValue>(trie: &ToolTrie<Value>) -> Result<()> {
+    let mut writer = BufWriter::new(stdout().lock());
+    trie.dump_tsv(&mut writer, &mut |value| value.to_string())?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn show_stats<Value: ToolValue>(trie: &ToolTrie<Value>) -> Result<()> {
+    let storage = trie.storage();
+    println!("keys:            {}", trie.size()?);
+    println!("values:          {}", storage.value_count()?);
+    println!("base check size: {}", storage.base_check_size()?);
+    println!("filling rate:    {:.3}", storage.filling_rate()?);
+    Ok(())
+}
@@ -0,0 +1,23 @@
+/*!
+ * A fuzz target of MemoryStorage::new_with_reader
+ */
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use tetengo_trie::{MemoryStorage, Trie, ValueDeserializer};
+
+fuzz_target!(|data: &[u8]| {
+    let mut value_deserializer = ValueDeserializer::new(Box::new(|bytes: &[u8]| {
+        Ok(i32::from_be_bytes(bytes.try_into()?))
+    }));
+    let Ok(storage) = MemoryStorage::new_with_reader(&mut &data[..], &mut value_deserializer)
+    else {
+        return;
+    };
+    let trie = Trie::<&str, i32>::builder_with_storage(Box::new(storage)).build();
+    for key in ["", "a", "ab", "熊本"] {
+        let _result = trie.find(&key);
+    }
+});
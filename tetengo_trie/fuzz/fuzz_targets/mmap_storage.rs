@@ -0,0 +1,32 @@
+/*!
+ * A fuzz target of MmapStorage::builder
+ */
+
+#![no_main]
+
+use std::io::Write;
+use std::rc::Rc;
+
+use libfuzzer_sys::fuzz_target;
+
+use tetengo_trie::{FileMapping, MmapStorage, Trie, ValueDeserializer};
+
+fuzz_target!(|data: &[u8]| {
+    let mut file = tempfile::tempfile().unwrap();
+    file.write_all(data).unwrap();
+    let Ok(file_mapping) = FileMapping::new(file) else {
+        return;
+    };
+    let value_deserializer = ValueDeserializer::new(Box::new(|bytes: &[u8]| {
+        Ok(i32::from_be_bytes(bytes.try_into()?))
+    }));
+    let Ok(storage) =
+        MmapStorage::builder(Rc::new(file_mapping), 0, data.len(), value_deserializer).build()
+    else {
+        return;
+    };
+    let trie = Trie::<&str, i32>::builder_with_storage(Box::new(storage)).build();
+    for key in ["", "a", "ab", "熊本"] {
+        let _result = trie.find(&key);
+    }
+});
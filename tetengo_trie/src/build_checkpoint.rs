@@ -0,0 +1,292 @@
+/*!
+ * A build checkpoint.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::Read;
+
+use anyhow::Result;
+
+use crate::byte_writer::ByteWriter;
+
+/**
+ * A build checkpoint error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum BuildCheckpointError {
+    /**
+     * The checkpoint is broken.
+     */
+    #[error("the checkpoint is broken")]
+    BrokenCheckpoint,
+
+    /**
+     * The checkpoint does not match the elements or the builder settings.
+     */
+    #[error("the checkpoint does not match the elements or the builder settings")]
+    MismatchedCheckpoint,
+}
+
+/**
+ * A build checkpoint.
+ *
+ * A snapshot of a partially built double array. A trie builder resumes the
+ * build from it with `TrieBuilder::resume_from`, skipping the elements already
+ * added.
+ *
+ * A checkpoint is serialized in the following format. All the integers are in
+ * big endian.
+ *
+ * 1. The element count (u32).
+ * 2. The double array density factor (u32).
+ * 3. The added element count (u32).
+ * 4. The base-check array size (u32) and the base-check array (u32 each).
+ * 5. The used base count (u32) and the used bases (i32 each).
+ * 6. The occupied slot word count (u32) and the occupied slot words (u64
+ *    each).
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BuildCheckpoint {
+    element_count: usize,
+    density_factor: usize,
+    added_element_count: usize,
+    base_check_array: Vec<u32>,
+    bases: Vec<i32>,
+    occupied_slot_words: Vec<u64>,
+}
+
+impl BuildCheckpoint {
+    pub(crate) const fn new(
+        element_count: usize,
+        density_factor: usize,
+        added_element_count: usize,
+        base_check_array: Vec<u32>,
+        bases: Vec<i32>,
+        occupied_slot_words: Vec<u64>,
+    ) -> Self {
+        Self {
+            element_count,
+            density_factor,
+            added_element_count,
+            base_check_array,
+            bases,
+            occupied_slot_words,
+        }
+    }
+
+    /**
+     * Creates a build checkpoint.
+     *
+     * # Arguments
+     * * `reader` - A reader.
+     *
+     * # Errors
+     * * When it fails to read the checkpoint.
+     * * When the checkpoint is broken.
+     */
+    #[cfg(feature = "std")]
+    pub fn new_with_reader(reader: &mut dyn Read) -> Result<Self> {
+        let element_count = read_u32(reader)? as usize;
+        let density_factor = read_u32(reader)? as usize;
+        let added_element_count = read_u32(reader)? as usize;
+        if added_element_count > element_count {
+            return Err(BuildCheckpointError::BrokenCheckpoint.into());
+        }
+
+        let base_check_array_size = read_u32(reader)? as usize;
+        let mut base_check_array =
+            Vec::with_capacity(base_check_array_size.min(PREALLOCATION_LIMIT));
+        for _ in 0..base_check_array_size {
+            base_check_array.push(read_u32(reader)?);
+        }
+        let base_count = read_u32(reader)? as usize;
+        let mut bases = Vec::with_capacity(base_count.min(PREALLOCATION_LIMIT));
+        for _ in 0..base_count {
+            bases.push(read_u32(reader)? as i32);
+        }
+        let occupied_slot_word_count = read_u32(reader)? as usize;
+        let mut occupied_slot_words =
+            Vec::with_capacity(occupied_slot_word_count.min(PREALLOCATION_LIMIT));
+        for _ in 0..occupied_slot_word_count {
+            let high = read_u32(reader)? as u64;
+            let low = read_u32(reader)? as u64;
+            occupied_slot_words.push((high << 32) | low);
+        }
+
+        Ok(Self::new(
+            element_count,
+            density_factor,
+            added_element_count,
+            base_check_array,
+            bases,
+            occupied_slot_words,
+        ))
+    }
+
+    /**
+     * Returns the element count.
+     *
+     * # Returns
+     * The count of all the elements to build.
+     */
+    pub const fn element_count(&self) -> usize {
+        self.element_count
+    }
+
+    /**
+     * Returns the added element count.
+     *
+     * # Returns
+     * The count of the elements already added to the double array.
+     */
+    pub const fn added_element_count(&self) -> usize {
+        self.added_element_count
+    }
+
+    pub(crate) const fn density_factor(&self) -> usize {
+        self.density_factor
+    }
+
+    pub(crate) fn bases(&self) -> &[i32] {
+        self.bases.as_slice()
+    }
+
+    pub(crate) fn occupied_slot_words(&self) -> &[u64] {
+        self.occupied_slot_words.as_slice()
+    }
+
+    pub(crate) fn into_base_check_array(self) -> Vec<u32> {
+        self.base_check_array
+    }
+
+    /**
+     * Serializes this checkpoint.
+     *
+     * # Arguments
+     * * `writer` - A writer.
+     *
+     * # Errors
+     * * When it fails to write the checkpoint.
+     */
+    pub fn serialize(&self, writer: &mut dyn ByteWriter) -> Result<()> {
+        write_u32(writer, self.element_count)?;
+        write_u32(writer, self.density_factor)?;
+        write_u32(writer, self.added_element_count)?;
+        write_u32(writer, self.base_check_array.len())?;
+        for &base_check in &self.base_check_array {
+            writer.write_bytes(&base_check.to_be_bytes())?;
+        }
+        write_u32(writer, self.bases.len())?;
+        for &base in &self.bases {
+            writer.write_bytes(&base.to_be_bytes())?;
+        }
+        write_u32(writer, self.occupied_slot_words.len())?;
+        for &word in &self.occupied_slot_words {
+            writer.write_bytes(&word.to_be_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+const PREALLOCATION_LIMIT: usize = 0x10000;
+
+fn write_u32(writer: &mut dyn ByteWriter, value: usize) -> Result<()> {
+    debug_assert!(value <= u32::MAX as usize);
+    writer.write_bytes(&(value as u32).to_be_bytes())
+}
+
+#[cfg(feature = "std")]
+fn read_u32(reader: &mut dyn Read) -> Result<u32> {
+    let mut bytes = [0u8; size_of::<u32>()];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|_| BuildCheckpointError::BrokenCheckpoint)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn make_checkpoint() -> BuildCheckpoint {
+        BuildCheckpoint::new(
+            42,
+            1000,
+            24,
+            vec![0x00000AFF, 0x000001FE, 0xFFFFFF00],
+            vec![-1, 10],
+            vec![0x0123456789ABCDEF, u64::MAX],
+        )
+    }
+
+    #[test]
+    fn new_with_reader() {
+        {
+            let mut serialized = Vec::new();
+            make_checkpoint().serialize(&mut serialized).unwrap();
+
+            let checkpoint =
+                BuildCheckpoint::new_with_reader(&mut Cursor::new(serialized)).unwrap();
+            assert_eq!(checkpoint, make_checkpoint());
+        }
+        {
+            let mut serialized = Vec::new();
+            make_checkpoint().serialize(&mut serialized).unwrap();
+            serialized.truncate(serialized.len() - 1);
+
+            let error = BuildCheckpoint::new_with_reader(&mut Cursor::new(serialized)).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<BuildCheckpointError>().unwrap(),
+                BuildCheckpointError::BrokenCheckpoint
+            ));
+        }
+        {
+            let mut serialized = Vec::new();
+            BuildCheckpoint::new(1, 1000, 2, Vec::new(), Vec::new(), Vec::new())
+                .serialize(&mut serialized)
+                .unwrap();
+
+            assert!(BuildCheckpoint::new_with_reader(&mut Cursor::new(serialized)).is_err());
+        }
+    }
+
+    #[test]
+    fn element_count() {
+        assert_eq!(make_checkpoint().element_count(), 42);
+    }
+
+    #[test]
+    fn added_element_count() {
+        assert_eq!(make_checkpoint().added_element_count(), 24);
+    }
+
+    #[test]
+    fn serialize() {
+        let mut serialized = Vec::new();
+        make_checkpoint().serialize(&mut serialized).unwrap();
+
+        let expected = [
+            &[0x00u8, 0x00, 0x00, 0x2A][..],
+            &[0x00, 0x00, 0x03, 0xE8],
+            &[0x00, 0x00, 0x00, 0x18],
+            &[0x00, 0x00, 0x00, 0x03],
+            &[0x00, 0x00, 0x0A, 0xFF],
+            &[0x00, 0x00, 0x01, 0xFE],
+            &[0xFF, 0xFF, 0xFF, 0x00],
+            &[0x00, 0x00, 0x00, 0x02],
+            &[0xFF, 0xFF, 0xFF, 0xFF],
+            &[0x00, 0x00, 0x00, 0x0A],
+            &[0x00, 0x00, 0x00, 0x02],
+            &[0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF],
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
+        ]
+        .concat();
+        assert_eq!(serialized, expected);
+    }
+}
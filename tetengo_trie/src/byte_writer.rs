@@ -0,0 +1,76 @@
+/*!
+ * A byte writer.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use anyhow::Result;
+
+/**
+ * A byte writer.
+ *
+ * A minimal writer to which storages are serialized.
+ *
+ * With the `std` feature, all the `std::io::Write` implementations are byte
+ * writers. Without it, `Vec<u8>` is.
+ */
+pub trait ByteWriter {
+    /**
+     * Writes bytes.
+     *
+     * # Arguments
+     * * `bytes` - Bytes.
+     *
+     * # Errors
+     * * When it fails to write all the bytes.
+     */
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()>;
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write + ?Sized> ByteWriter for W {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write_all(bytes)?;
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl ByteWriter for Vec<u8> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn write_bytes() {
+        {
+            let mut writer = Vec::<u8>::new();
+
+            writer.write_bytes(&[0x01, 0x23]).unwrap();
+            writer.write_bytes(&[0x45]).unwrap();
+
+            assert_eq!(writer, [0x01, 0x23, 0x45]);
+        }
+        #[cfg(feature = "std")]
+        {
+            let mut writer = Cursor::new(Vec::<u8>::new());
+            let byte_writer: &mut dyn ByteWriter = &mut writer;
+
+            byte_writer.write_bytes(&[0x01, 0x23]).unwrap();
+
+            assert_eq!(writer.get_ref().as_slice(), [0x01, 0x23]);
+        }
+    }
+}
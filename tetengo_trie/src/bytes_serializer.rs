@@ -0,0 +1,271 @@
+/*!
+ * A byte sequence serializer/deserializer.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use alloc::vec::Vec;
+
+use anyhow::Result;
+
+use crate::serializer::{
+    DeserializationError, Deserializer, DeserializerOf, Serializer, SerializerOf,
+};
+
+/**
+ * A byte sequence serializer.
+ *
+ * When the argument `fe_escape` of the constructor is true, binary bytes are
+ * serialized as following, so that the serialized key never contains
+ * `KEY_TERMINATOR`:
+ *
+ * |original byte|serialized byte|
+ * |-|-|
+ * |0x00     |0xFE       (0b11111110)            |
+ * |0x01-0xFC|0x01-0xFC  (0b00000001-0b11111100) |
+ * |0xFD     |0xFD, 0xFD (0b11111101, 0b11111101)|
+ * |0xFE     |0xFD, 0xFE (0b11111101, 0b11111110)|
+ * |0xFF     |0xFF       (0b11111111)            |
+ *
+ * When it is false, the bytes are serialized as they are. A trie rejects such
+ * keys containing 0x00.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BytesSerializer {
+    fe_escape: bool,
+}
+
+impl Serializer for BytesSerializer {
+    type Object<'a> = Vec<u8>;
+
+    fn new(fe_escape: bool) -> Self {
+        BytesSerializer { fe_escape }
+    }
+
+    fn serialize(&self, object: &Self::Object<'_>) -> Vec<u8> {
+        let mut serialized = Vec::with_capacity(object.len());
+        self.serialize_into(object, &mut serialized);
+        serialized
+    }
+
+    fn serialize_into(&self, object: &Self::Object<'_>, buffer: &mut Vec<u8>) {
+        if self.fe_escape {
+            escape_into(object, buffer);
+        } else {
+            buffer.extend_from_slice(object);
+        }
+    }
+
+    fn serialized_len(&self, object: &Self::Object<'_>) -> usize {
+        if self.fe_escape {
+            object.len() + object.iter().filter(|&&byte| is_prefixed(byte)).count()
+        } else {
+            object.len()
+        }
+    }
+
+    fn escaped_byte_count(&self, object: &Self::Object<'_>) -> usize {
+        if self.fe_escape {
+            object.iter().filter(|&&byte| is_escaped(byte)).count()
+        } else {
+            0
+        }
+    }
+}
+
+/**
+ * A byte sequence deserialization error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum BytesDeserializationError {
+    /**
+     * Invalid serialized content.
+     */
+    #[error("invalid serialized content")]
+    InvalidSerializedContent,
+}
+
+impl DeserializationError for BytesDeserializationError {}
+
+/**
+ * A byte sequence deserializer.
+ *
+ * When the argument `fe_escape` of the constructor is true, the bytes escaped
+ * by `BytesSerializer` are restored.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BytesDeserializer {
+    fe_escape: bool,
+}
+
+impl Deserializer for BytesDeserializer {
+    type Object = Vec<u8>;
+
+    fn new(fe_escape: bool) -> Self {
+        BytesDeserializer { fe_escape }
+    }
+
+    fn deserialize(&self, serialized: &[u8]) -> Result<Self::Object> {
+        if self.fe_escape {
+            unescape(serialized)
+        } else {
+            Ok(serialized.to_vec())
+        }
+    }
+}
+
+fn escape_into(bytes: &[u8], escaped: &mut Vec<u8>) {
+    escaped.reserve(bytes.len());
+    for &byte in bytes {
+        match byte {
+            0x00u8 => escaped.push(0xFEu8),
+            0xFDu8 | 0xFEu8 => escaped.extend_from_slice(&[0xFDu8, byte]),
+            _ => escaped.push(byte),
+        }
+    }
+}
+
+const fn is_escaped(byte: u8) -> bool {
+    byte == 0x00u8 || is_prefixed(byte)
+}
+
+const fn is_prefixed(byte: u8) -> bool {
+    byte == 0xFDu8 || byte == 0xFEu8
+}
+
+fn unescape(serialized: &[u8]) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(serialized.len());
+    let mut serialized_iter = serialized.iter();
+    while let Some(&byte) = serialized_iter.next() {
+        match byte {
+            0x00u8 => return Err(BytesDeserializationError::InvalidSerializedContent.into()),
+            0xFDu8 => match serialized_iter.next() {
+                Some(&byte2) if byte2 == 0xFDu8 || byte2 == 0xFEu8 => bytes.push(byte2),
+                _ => return Err(BytesDeserializationError::InvalidSerializedContent.into()),
+            },
+            0xFEu8 => bytes.push(0x00u8),
+            _ => bytes.push(byte),
+        }
+    }
+    Ok(bytes)
+}
+
+impl SerializerOf<Vec<u8>> for () {
+    type Type = BytesSerializer;
+}
+
+impl DeserializerOf<Vec<u8>> for () {
+    type Type = BytesDeserializer;
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use crate::serializer::KEY_TERMINATOR;
+
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        {
+            let serializer = <() as SerializerOf<Vec<u8>>>::Type::new(false);
+
+            let object = vec![0x00u8, 0x12u8, 0xFDu8, 0xFEu8, 0xFFu8];
+            let serialized = serializer.serialize(&object);
+            assert_eq!(serialized, object);
+        }
+        {
+            let serializer = <() as SerializerOf<Vec<u8>>>::Type::new(true);
+
+            let object = vec![0x00u8, 0x12u8, 0xFDu8, 0xFEu8, 0xFFu8];
+            let expected_serialized = vec![0xFEu8, 0x12u8, 0xFDu8, 0xFDu8, 0xFDu8, 0xFEu8, 0xFFu8];
+            let serialized = serializer.serialize(&object);
+            assert_eq!(serialized, expected_serialized);
+            assert!(!serialized.iter().any(|&b| b == KEY_TERMINATOR));
+        }
+    }
+
+    #[test]
+    fn serialized_len() {
+        {
+            let serializer = <() as SerializerOf<Vec<u8>>>::Type::new(false);
+
+            let object = vec![0x00u8, 0x12u8, 0xFDu8, 0xFEu8, 0xFFu8];
+            assert_eq!(serializer.serialized_len(&object), 5);
+        }
+        {
+            let serializer = <() as SerializerOf<Vec<u8>>>::Type::new(true);
+
+            let object = vec![0x00u8, 0x12u8, 0xFDu8, 0xFEu8, 0xFFu8];
+            assert_eq!(serializer.serialized_len(&object), 7);
+            assert_eq!(
+                serializer.serialized_len(&object),
+                serializer.serialize(&object).len()
+            );
+        }
+    }
+
+    #[test]
+    fn escaped_byte_count() {
+        {
+            let serializer = <() as SerializerOf<Vec<u8>>>::Type::new(false);
+
+            let object = vec![0x00u8, 0x12u8, 0xFDu8, 0xFEu8, 0xFFu8];
+            assert_eq!(serializer.escaped_byte_count(&object), 0);
+        }
+        {
+            let serializer = <() as SerializerOf<Vec<u8>>>::Type::new(true);
+
+            let object = vec![0x00u8, 0x12u8, 0xFDu8, 0xFEu8, 0xFFu8];
+            assert_eq!(serializer.escaped_byte_count(&object), 3);
+        }
+    }
+
+    #[test]
+    fn deserialize() {
+        {
+            let deserializer = <() as DeserializerOf<Vec<u8>>>::Type::new(false);
+
+            let serialized = vec![0x00u8, 0x12u8, 0xFDu8, 0xFEu8, 0xFFu8];
+            let object = deserializer.deserialize(&serialized).unwrap();
+            assert_eq!(object, serialized);
+        }
+        {
+            let deserializer = <() as DeserializerOf<Vec<u8>>>::Type::new(true);
+
+            let serialized = vec![0xFEu8, 0x12u8, 0xFDu8, 0xFDu8, 0xFDu8, 0xFEu8, 0xFFu8];
+            let expected_object = vec![0x00u8, 0x12u8, 0xFDu8, 0xFEu8, 0xFFu8];
+            let object = deserializer.deserialize(&serialized).unwrap();
+            assert_eq!(object, expected_object);
+        }
+        {
+            let deserializer = <() as DeserializerOf<Vec<u8>>>::Type::new(true);
+
+            for serialized in [
+                vec![0x12u8, 0xFDu8],
+                vec![0xFDu8, 0x12u8],
+                vec![0x12u8, 0x00u8],
+            ] {
+                assert!(if let Err(e) = deserializer.deserialize(&serialized) {
+                    matches!(
+                        e.downcast_ref::<BytesDeserializationError>(),
+                        Some(BytesDeserializationError::InvalidSerializedContent)
+                    )
+                } else {
+                    false
+                });
+            }
+        }
+        {
+            let serializer = <() as SerializerOf<Vec<u8>>>::Type::new(true);
+            let deserializer = <() as DeserializerOf<Vec<u8>>>::Type::new(true);
+
+            let object = (0..=0xFFu8).collect::<Vec<_>>();
+            let round_tripped = deserializer
+                .deserialize(&serializer.serialize(&object))
+                .unwrap();
+            assert_eq!(round_tripped, object);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+/*!
+ * Completion hints.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use alloc::vec::Vec;
+
+/**
+ * Completion hints.
+ *
+ * Holds the max score of the values under each node of a trie, so that
+ * `Trie::top_completions_with_hints` visits the subtrees in the descending
+ * order of their max scores and stops after the k best completions.
+ *
+ * The hints are made by `Trie::completion_hints`. They are valid for the trie
+ * and its subtries until a value is replaced.
+ */
+#[derive(Clone, Debug)]
+pub struct CompletionHints {
+    max_scores: Vec<Option<i64>>,
+}
+
+impl CompletionHints {
+    pub(super) const fn new(max_scores: Vec<Option<i64>>) -> Self {
+        Self { max_scores }
+    }
+
+    pub(super) fn max_scores(&self) -> &[Option<i64>] {
+        &self.max_scores
+    }
+}
@@ -0,0 +1,351 @@
+/*!
+ * A Darts double array.
+ *
+ * Imports the double arrays made by Darts, the double array library used by
+ * MeCab and others.
+ *
+ * # Layouts
+ *
+ * A Darts double array is an array of units, each of which is a pair of a
+ * signed 32-bit base and an unsigned 32-bit check. A node is at the slot whose
+ * base is `b`:
+ *
+ * * The child for a byte `c` is at `b + c + 1`, and its check is `b`.
+ * * The value of the key ending at the node is at `b`. Its check is `b`, and
+ *   its base is `-value - 1`.
+ *
+ * A double array of this crate is an array of `(base << 8) | check` elements,
+ * as described in `MemoryStorage::from_raw_parts`:
+ *
+ * * The child for a byte `c` is at `base + c`, and its check is `c`.
+ * * The value of the key ending at the node is in the value array. The child
+ *   for `KEY_TERMINATOR` (0x00) is at `base`, and its base is the value index.
+ *
+ * Darts puts the value one slot before the child for the byte 0x00, while this
+ * crate puts it at the same slot. So the slots cannot be kept as they are. The
+ * keys and values are enumerated from the Darts units, and laid out again.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use anyhow::Result;
+
+use crate::double_array::{BuildingObserverSet, DEFAULT_DENSITY_FACTOR};
+use crate::double_array_builder;
+use crate::memory_storage::MemoryStorage;
+use crate::storage::StorageMut;
+
+/**
+ * A Darts error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum DartsError {
+    /**
+     * The byte count is not a multiple of the unit size.
+     */
+    #[error("the byte count is not a multiple of the unit size")]
+    BrokenUnitArray,
+
+    /**
+     * The units are cyclic.
+     */
+    #[error("the units are cyclic")]
+    CyclicUnits,
+}
+
+/**
+ * A Darts unit.
+ */
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct DartsUnit {
+    base: i32,
+    check: u32,
+}
+
+impl DartsUnit {
+    /**
+     * The byte count of a unit.
+     */
+    pub const SIZE: usize = size_of::<i32>() + size_of::<u32>();
+
+    /**
+     * Creates a Darts unit.
+     *
+     * # Arguments
+     * * `base`  - A base.
+     * * `check` - A check.
+     */
+    pub const fn new(base: i32, check: u32) -> Self {
+        Self { base, check }
+    }
+
+    /**
+     * Returns the base.
+     *
+     * # Returns
+     * The base.
+     */
+    pub const fn base(&self) -> i32 {
+        self.base
+    }
+
+    /**
+     * Returns the check.
+     *
+     * # Returns
+     * The check.
+     */
+    pub const fn check(&self) -> u32 {
+        self.check
+    }
+}
+
+/**
+ * A Darts double array.
+ */
+#[derive(Clone, Debug)]
+pub struct DartsDoubleArray {
+    units: Vec<DartsUnit>,
+}
+
+impl DartsDoubleArray {
+    /**
+     * Creates a Darts double array.
+     *
+     * # Arguments
+     * * `units` - Units.
+     */
+    pub const fn new(units: Vec<DartsUnit>) -> Self {
+        Self { units }
+    }
+
+    /**
+     * Creates a Darts double array from bytes.
+     *
+     * Each unit is a little-endian base followed by a little-endian check, as
+     * Darts writes on the common platforms.
+     *
+     * # Arguments
+     * * `bytes` - Bytes.
+     *
+     * # Errors
+     * * When the byte count is not a multiple of the unit size.
+     */
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() % DartsUnit::SIZE != 0 {
+            return Err(DartsError::BrokenUnitArray.into());
+        }
+        let units = bytes
+            .chunks_exact(DartsUnit::SIZE)
+            .map(|unit| {
+                let (base, check) = unit.split_at(size_of::<i32>());
+                DartsUnit::new(
+                    i32::from_le_bytes([base[0], base[1], base[2], base[3]]),
+                    u32::from_le_bytes([check[0], check[1], check[2], check[3]]),
+                )
+            })
+            .collect();
+        Ok(Self { units })
+    }
+
+    /**
+     * Returns the units.
+     *
+     * # Returns
+     * The units.
+     */
+    pub fn units(&self) -> &[DartsUnit] {
+        self.units.as_slice()
+    }
+
+    /**
+     * Returns the entries.
+     *
+     * # Returns
+     * The pairs of a key and a value, in the ascending order of the keys.
+     *
+     * # Errors
+     * * When the units are cyclic.
+     */
+    pub fn entries(&self) -> Result<Vec<(Vec<u8>, i32)>> {
+        let Some(root) = self.units.first() else {
+            return Ok(Vec::new());
+        };
+
+        let mut entries = Vec::new();
+        let mut stack = vec![(root.base(), Vec::new())];
+        while let Some((base, key)) = stack.pop() {
+            // A key longer than the unit count must pass through a node twice.
+            if key.len() > self.units.len() {
+                return Err(DartsError::CyclicUnits.into());
+            }
+            if let Some(unit) = self.child_of(base, 0) {
+                if unit.base() < 0 {
+                    entries.push((key.clone(), -(unit.base() + 1)));
+                }
+            }
+            for c in (1..=0xFFu8).rev() {
+                let Some(unit) = self.child_of(base, c as i32 + 1) else {
+                    continue;
+                };
+                if unit.base() < 0 {
+                    continue;
+                }
+                let mut child_key = key.clone();
+                child_key.push(c);
+                stack.push((unit.base(), child_key));
+            }
+        }
+        Ok(entries)
+    }
+
+    /**
+     * Converts this double array into a memory storage.
+     *
+     * # Returns
+     * A memory storage having the same keys and values.
+     *
+     * # Errors
+     * * When the units are cyclic.
+     */
+    pub fn to_storage(&self) -> Result<MemoryStorage<i32>> {
+        let entries = self.entries()?;
+        let elements = entries
+            .iter()
+            .enumerate()
+            .map(|(i, (key, _))| (key.as_slice(), i as i32))
+            .collect::<Vec<_>>();
+        let mut storage = double_array_builder::build::<i32>(
+            elements,
+            &mut BuildingObserverSet::new(&mut |_| {}, &mut || {}),
+            DEFAULT_DENSITY_FACTOR,
+            true,
+            None,
+        )?;
+        for (i, (_, value)) in entries.into_iter().enumerate() {
+            storage.add_value_at(i, value)?;
+        }
+        Ok(*storage)
+    }
+
+    fn child_of(&self, base: i32, offset: i32) -> Option<&DartsUnit> {
+        let index = usize::try_from(base.checked_add(offset)?).ok()?;
+        let unit = self.units.get(index)?;
+        if unit.check() != base as u32 {
+            return None;
+        }
+        Some(unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use crate::trie::Trie;
+
+    use super::*;
+
+    // "a": 5, "ab": 7
+    fn make_units() -> Vec<DartsUnit> {
+        let mut units = vec![DartsUnit::default(); 301];
+        units[0] = DartsUnit::new(1, 0);
+        units[1 + b'a' as usize + 1] = DartsUnit::new(200, 1);
+        units[200] = DartsUnit::new(-6, 200);
+        units[200 + b'b' as usize + 1] = DartsUnit::new(300, 200);
+        units[300] = DartsUnit::new(-8, 300);
+        units
+    }
+
+    mod darts_unit {
+        use super::*;
+
+        #[test]
+        fn new() {
+            let _unit = DartsUnit::new(42, 24);
+        }
+
+        #[test]
+        fn base() {
+            assert_eq!(DartsUnit::new(42, 24).base(), 42);
+        }
+
+        #[test]
+        fn check() {
+            assert_eq!(DartsUnit::new(42, 24).check(), 24);
+        }
+    }
+
+    mod darts_double_array {
+        use super::*;
+
+        #[test]
+        fn new() {
+            let _double_array = DartsDoubleArray::new(make_units());
+        }
+
+        #[test]
+        fn from_bytes() {
+            {
+                let mut bytes = Vec::new();
+                for unit in make_units() {
+                    bytes.extend_from_slice(&unit.base().to_le_bytes());
+                    bytes.extend_from_slice(&unit.check().to_le_bytes());
+                }
+                let double_array = DartsDoubleArray::from_bytes(&bytes).unwrap();
+
+                assert_eq!(double_array.units(), make_units().as_slice());
+            }
+            {
+                let result = DartsDoubleArray::from_bytes(&[0u8; 9]);
+                assert!(result.is_err());
+            }
+        }
+
+        #[test]
+        fn units() {
+            let double_array = DartsDoubleArray::new(make_units());
+
+            assert_eq!(double_array.units().len(), 301);
+        }
+
+        #[test]
+        fn entries() {
+            {
+                let double_array = DartsDoubleArray::new(make_units());
+
+                assert_eq!(
+                    double_array.entries().unwrap(),
+                    [(b"a".to_vec(), 5), (b"ab".to_vec(), 7)]
+                );
+            }
+            {
+                let double_array = DartsDoubleArray::new(Vec::new());
+
+                assert!(double_array.entries().unwrap().is_empty());
+            }
+            {
+                let mut units = make_units();
+                units[200 + b'b' as usize + 1] = DartsUnit::new(200, 200);
+                let double_array = DartsDoubleArray::new(units);
+
+                assert!(double_array.entries().is_err());
+            }
+        }
+
+        #[test]
+        fn to_storage() {
+            let double_array = DartsDoubleArray::new(make_units());
+            let storage = double_array.to_storage().unwrap();
+            let trie = Trie::<&str, i32>::builder_with_storage(Box::new(storage)).build();
+
+            assert_eq!(trie.size().unwrap(), 2);
+            assert_eq!(*trie.find(&"a").unwrap().unwrap(), 5);
+            assert_eq!(*trie.find(&"ab").unwrap().unwrap(), 7);
+            assert!(trie.find(&"b").unwrap().is_none());
+        }
+    }
+}
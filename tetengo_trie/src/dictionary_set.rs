@@ -0,0 +1,428 @@
+/*!
+ * A dictionary set.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Debug;
+#[cfg(feature = "std")]
+use std::io::Read;
+
+use anyhow::Result;
+
+#[cfg(feature = "std")]
+use crate::memory_storage::MemoryStorage;
+use crate::serializer::{Serializer, SerializerOf};
+use crate::storage::StorageRead;
+use crate::trie::Trie;
+#[cfg(feature = "std")]
+use crate::value_serializer::ValueDeserializer;
+
+/**
+ * A dictionary set error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum DictionarySetError {
+    /**
+     * No dictionary has the name.
+     */
+    #[error("no dictionary has the name")]
+    UnknownDictionary,
+}
+
+/**
+ * A dictionary set.
+ *
+ * Owns several tries keyed by their names, such as a system dictionary, a user
+ * dictionary and an emoji dictionary. A dictionary can be replaced or reloaded
+ * while the others are kept as they are.
+ *
+ * The dictionaries keep the order in which they are inserted.
+ *
+ * # Type Parameters
+ * * `Key`           - A key type.
+ * * `Value`         - A value type.
+ * * `KeySerializer` - A key serializer type.
+ */
+#[derive(Debug)]
+pub struct DictionarySet<
+    Key,
+    Value: Debug,
+    KeySerializer: Serializer = <() as SerializerOf<Key>>::Type,
+> {
+    dictionaries: Vec<(String, Trie<Key, Value, KeySerializer>)>,
+}
+
+impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer + Clone>
+    DictionarySet<Key, Value, KeySerializer>
+{
+    /**
+     * Creates an empty dictionary set.
+     */
+    pub const fn new() -> Self {
+        Self {
+            dictionaries: Vec::new(),
+        }
+    }
+
+    /**
+     * Returns the names of the dictionaries.
+     *
+     * # Returns
+     * The names in the order of the insertion.
+     */
+    pub fn names(&self) -> Vec<&str> {
+        self.dictionaries
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /**
+     * Returns the dictionary.
+     *
+     * # Arguments
+     * * `name` - A name.
+     *
+     * # Returns
+     * The dictionary. Or None when no dictionary has the name.
+     */
+    pub fn get(&self, name: &str) -> Option<&Trie<Key, Value, KeySerializer>> {
+        self.dictionaries
+            .iter()
+            .find(|(dictionary_name, _)| dictionary_name == name)
+            .map(|(_, dictionary)| dictionary)
+    }
+
+    /**
+     * Inserts a dictionary.
+     *
+     * When a dictionary with the same name exists, it is replaced keeping its
+     * position.
+     *
+     * # Arguments
+     * * `name`       - A name.
+     * * `dictionary` - A dictionary.
+     *
+     * # Returns
+     * The replaced dictionary. Or None when no dictionary had the name.
+     */
+    pub fn insert(
+        &mut self,
+        name: &str,
+        dictionary: Trie<Key, Value, KeySerializer>,
+    ) -> Option<Trie<Key, Value, KeySerializer>> {
+        match self.position_of(name) {
+            Some(position) => Some(core::mem::replace(
+                &mut self.dictionaries[position].1,
+                dictionary,
+            )),
+            None => {
+                self.dictionaries.push((name.to_string(), dictionary));
+                None
+            }
+        }
+    }
+
+    /**
+     * Removes a dictionary.
+     *
+     * # Arguments
+     * * `name` - A name.
+     *
+     * # Returns
+     * The removed dictionary. Or None when no dictionary has the name.
+     */
+    pub fn remove(&mut self, name: &str) -> Option<Trie<Key, Value, KeySerializer>> {
+        let position = self.position_of(name)?;
+        Some(self.dictionaries.remove(position).1)
+    }
+
+    /**
+     * Reloads a dictionary from a storage.
+     *
+     * The key serializer and the max key length of the current dictionary are
+     * used for the reloaded one.
+     *
+     * # Arguments
+     * * `name`    - A name.
+     * * `storage` - A storage.
+     *
+     * # Errors
+     * * When no dictionary has the name.
+     */
+    pub fn reload(&mut self, name: &str, storage: Box<dyn StorageRead<Value>>) -> Result<()> {
+        let Some(position) = self.position_of(name) else {
+            return Err(DictionarySetError::UnknownDictionary.into());
+        };
+        let dictionary = &mut self.dictionaries[position].1;
+        let mut builder =
+            Trie::builder_with_storage(storage).key_serializer(dictionary.key_serializer().clone());
+        if let Some(max_key_len) = dictionary.max_key_len() {
+            builder = builder.max_key_len(max_key_len);
+        }
+        *dictionary = builder.build();
+        Ok(())
+    }
+
+    /**
+     * Reloads a dictionary from a reader.
+     *
+     * The current dictionary is kept when it fails to read the new one.
+     *
+     * # Arguments
+     * * `name`               - A name.
+     * * `reader`             - A reader.
+     * * `value_deserializer` - A deserializer for value objects.
+     *
+     * # Errors
+     * * When no dictionary has the name.
+     * * When it fails to read the dictionary.
+     */
+    #[cfg(feature = "std")]
+    pub fn reload_with_reader(
+        &mut self,
+        name: &str,
+        reader: &mut dyn Read,
+        value_deserializer: &mut ValueDeserializer<Value>,
+    ) -> Result<()> {
+        if self.position_of(name).is_none() {
+            return Err(DictionarySetError::UnknownDictionary.into());
+        }
+        let storage = MemoryStorage::new_with_reader(reader, value_deserializer)?;
+        self.reload(name, Box::new(storage))
+    }
+
+    /**
+     * Finds the value objects corresponding to the given key in all the
+     * dictionaries.
+     *
+     * # Arguments
+     * * `key` - A key.
+     *
+     * # Returns
+     * The pairs of the dictionary names and the value objects, in the order of
+     * the dictionaries. The dictionaries without the key are skipped.
+     *
+     * # Errors
+     * * When it fails to access the storage.
+     */
+    pub fn find(&self, key: &KeySerializer::Object<'_>) -> Result<Vec<(&str, Rc<Value>)>> {
+        let mut found = Vec::new();
+        for (name, dictionary) in &self.dictionaries {
+            if let Some(value) = dictionary.find(key)? {
+                found.push((name.as_str(), value));
+            }
+        }
+        Ok(found)
+    }
+
+    fn position_of(&self, name: &str) -> Option<usize> {
+        self.dictionaries
+            .iter()
+            .position(|(dictionary_name, _)| dictionary_name == name)
+    }
+}
+
+impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer + Clone> Default
+    for DictionarySet<Key, Value, KeySerializer>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::value_serializer::ValueSerializer;
+
+    use super::*;
+
+    fn make_trie(elements: Vec<(&'static str, i32)>) -> Trie<&'static str, i32> {
+        Trie::<&str, i32>::builder()
+            .elements(elements)
+            .build()
+            .unwrap()
+    }
+
+    fn serialize(trie: &Trie<&'static str, i32>) -> Vec<u8> {
+        let mut serialized = Vec::new();
+        let mut value_serializer =
+            ValueSerializer::new(Box::new(|value: &i32| value.to_be_bytes().to_vec()), 4);
+        trie.storage()
+            .serialize(&mut serialized, &mut value_serializer)
+            .unwrap();
+        serialized
+    }
+
+    fn value_deserializer() -> ValueDeserializer<i32> {
+        ValueDeserializer::new(Box::new(|bytes: &[u8]| {
+            Ok(i32::from_be_bytes(bytes.try_into()?))
+        }))
+    }
+
+    fn make_dictionary_set() -> DictionarySet<&'static str, i32> {
+        let mut dictionary_set = DictionarySet::new();
+        let _replaced =
+            dictionary_set.insert("system", make_trie(vec![("Kumamoto", 42), ("Tamana", 24)]));
+        let _replaced = dictionary_set.insert("user", make_trie(vec![("Kumamoto", 4242)]));
+        dictionary_set
+    }
+
+    #[test]
+    fn new() {
+        let dictionary_set = DictionarySet::<&str, i32>::new();
+
+        assert!(dictionary_set.names().is_empty());
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(make_dictionary_set().names(), ["system", "user"]);
+    }
+
+    #[test]
+    fn get() {
+        let dictionary_set = make_dictionary_set();
+
+        assert_eq!(
+            *dictionary_set
+                .get("user")
+                .unwrap()
+                .find(&"Kumamoto")
+                .unwrap()
+                .unwrap(),
+            4242
+        );
+        assert!(dictionary_set.get("emoji").is_none());
+    }
+
+    #[test]
+    fn insert() {
+        let mut dictionary_set = make_dictionary_set();
+
+        assert!(dictionary_set
+            .insert("emoji", make_trie(vec![("Uto", 1)]))
+            .is_none());
+        let replaced = dictionary_set
+            .insert("system", make_trie(vec![("Uto", 2)]))
+            .unwrap();
+        assert_eq!(*replaced.find(&"Tamana").unwrap().unwrap(), 24);
+        assert_eq!(dictionary_set.names(), ["system", "user", "emoji"]);
+        assert!(dictionary_set
+            .get("system")
+            .unwrap()
+            .find(&"Tamana")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn remove() {
+        let mut dictionary_set = make_dictionary_set();
+
+        assert!(dictionary_set.remove("system").is_some());
+        assert!(dictionary_set.remove("system").is_none());
+        assert_eq!(dictionary_set.names(), ["user"]);
+    }
+
+    #[test]
+    fn reload() {
+        let mut dictionary_set = make_dictionary_set();
+
+        let serialized = serialize(&make_trie(vec![("Uto", 1)]));
+        let storage =
+            MemoryStorage::new_with_reader(&mut Cursor::new(serialized), &mut value_deserializer())
+                .unwrap();
+        dictionary_set.reload("user", Box::new(storage)).unwrap();
+        assert_eq!(
+            *dictionary_set
+                .get("user")
+                .unwrap()
+                .find(&"Uto")
+                .unwrap()
+                .unwrap(),
+            1
+        );
+
+        let error = dictionary_set
+            .reload("emoji", Box::new(MemoryStorage::<i32>::new()))
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<DictionarySetError>().unwrap(),
+            DictionarySetError::UnknownDictionary
+        ));
+    }
+
+    #[test]
+    fn reload_with_reader() {
+        let mut dictionary_set = make_dictionary_set();
+
+        {
+            let serialized = serialize(&make_trie(vec![("Uto", 1)]));
+            dictionary_set
+                .reload_with_reader(
+                    "user",
+                    &mut Cursor::new(serialized),
+                    &mut value_deserializer(),
+                )
+                .unwrap();
+            assert_eq!(
+                *dictionary_set
+                    .get("user")
+                    .unwrap()
+                    .find(&"Uto")
+                    .unwrap()
+                    .unwrap(),
+                1
+            );
+        }
+        {
+            let result = dictionary_set.reload_with_reader(
+                "user",
+                &mut Cursor::new(vec![0xFFu8]),
+                &mut value_deserializer(),
+            );
+            assert!(result.is_err());
+            assert!(dictionary_set
+                .get("user")
+                .unwrap()
+                .find(&"Uto")
+                .unwrap()
+                .is_some());
+        }
+        {
+            let serialized = serialize(&make_trie(vec![("Uto", 1)]));
+            let result = dictionary_set.reload_with_reader(
+                "emoji",
+                &mut Cursor::new(serialized),
+                &mut value_deserializer(),
+            );
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn find() {
+        let dictionary_set = make_dictionary_set();
+
+        {
+            let found = dictionary_set.find(&"Kumamoto").unwrap();
+            assert_eq!(found, [("system", Rc::new(42)), ("user", Rc::new(4242))]);
+        }
+        {
+            let found = dictionary_set.find(&"Tamana").unwrap();
+            assert_eq!(found, [("system", Rc::new(24))]);
+        }
+        {
+            let found = dictionary_set.find(&"Uto").unwrap();
+            assert!(found.is_empty());
+        }
+    }
+}
@@ -4,15 +4,62 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use std::any::type_name_of_val;
-use std::fmt::{self, Debug, Formatter};
-use std::marker::PhantomData;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BinaryHeap};
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::{type_name_of_val, Any};
+use core::cmp::{Ordering, Reverse};
+use core::fmt::{self, Debug, Formatter};
+use core::marker::PhantomData;
 
 use anyhow::Result;
 
+use crate::build_checkpoint::BuildCheckpoint;
 use crate::double_array_builder;
-use crate::double_array_iterator::DoubleArrayIterator;
-use crate::storage::Storage;
+use crate::double_array_iterator::{DoubleArrayIterator, KeyRange};
+#[cfg(feature = "metrics")]
+use crate::lookup_metrics::{LookupMetrics, MetricsCounter};
+use crate::memory_storage::MemoryStorage;
+use crate::serializer::KEY_TERMINATOR;
+use crate::storage::{StorageMut, StorageRead, ValueRef};
+
+// A scored item ordered so that the greater is the better: the higher score
+// first, then the lexicographically less key.
+struct Scored<T> {
+    score: i64,
+    key: Vec<u8>,
+    item: T,
+}
+
+impl<T> PartialEq for Scored<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Scored<T> {}
+
+impl<T> PartialOrd for Scored<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Scored<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .cmp(&other.score)
+            .then_with(|| other.key.cmp(&self.key))
+    }
+}
+
+// An item of the frontier of a best-first search.
+enum Frontier<Value> {
+    Node(usize),
+    Completion(Rc<Value>),
+}
 
 #[derive(Clone, Copy, Debug, thiserror::Error)]
 pub(super) enum DoubleArrayError {
@@ -22,9 +69,13 @@ pub(super) enum DoubleArrayError {
 
 pub(super) type DoubleArrayElement<'a> = (&'a [u8], i32);
 
+type Checkpointing<'a> = (usize, &'a mut dyn FnMut(&BuildCheckpoint) -> Result<()>);
+
 pub(super) struct BuildingObserverSet<'a> {
     adding: &'a mut dyn FnMut(&DoubleArrayElement<'_>),
     done: &'a mut dyn FnMut(),
+    checkpointing: Option<Checkpointing<'a>>,
+    cancelling: Option<&'a mut dyn FnMut() -> Result<()>>,
 }
 
 impl<'a> BuildingObserverSet<'a> {
@@ -32,7 +83,26 @@ impl<'a> BuildingObserverSet<'a> {
         adding: &'a mut dyn FnMut(&DoubleArrayElement<'_>),
         done: &'a mut dyn FnMut(),
     ) -> Self {
-        Self { adding, done }
+        Self {
+            adding,
+            done,
+            checkpointing: None,
+            cancelling: None,
+        }
+    }
+
+    pub(super) fn checkpointing(
+        mut self,
+        checkpoint_interval: usize,
+        checkpointing: &'a mut dyn FnMut(&BuildCheckpoint) -> Result<()>,
+    ) -> Self {
+        self.checkpointing = Some((checkpoint_interval, checkpointing));
+        self
+    }
+
+    pub(super) fn cancelling(mut self, cancelling: &'a mut dyn FnMut() -> Result<()>) -> Self {
+        self.cancelling = Some(cancelling);
+        self
     }
 
     pub(super) fn adding(&mut self, element: &DoubleArrayElement<'_>) {
@@ -42,6 +112,26 @@ impl<'a> BuildingObserverSet<'a> {
     pub(super) fn done(&mut self) {
         (self.done)();
     }
+
+    pub(super) fn checkpoint_due(&self, added_element_count: usize) -> bool {
+        self.checkpointing
+            .as_ref()
+            .is_some_and(|&(interval, _)| interval > 0 && added_element_count % interval == 0)
+    }
+
+    pub(super) fn checkpoint(&mut self, checkpoint: &BuildCheckpoint) -> Result<()> {
+        match &mut self.checkpointing {
+            Some((_, checkpointing)) => checkpointing(checkpoint),
+            None => Ok(()),
+        }
+    }
+
+    pub(super) fn check_cancelled(&mut self) -> Result<()> {
+        match &mut self.cancelling {
+            Some(cancelling) => cancelling(),
+            None => Ok(()),
+        }
+    }
 }
 
 impl Debug for BuildingObserverSet<'_> {
@@ -49,20 +139,65 @@ impl Debug for BuildingObserverSet<'_> {
         f.debug_struct("BuldingObserverSet")
             .field("adding", &type_name_of_val(&self.adding))
             .field("done", &type_name_of_val(&self.done))
+            .field("checkpointing", &type_name_of_val(&self.checkpointing))
+            .field("cancelling", &type_name_of_val(&self.cancelling))
             .finish()
     }
 }
 
 pub(super) const DEFAULT_DENSITY_FACTOR: usize = 1000;
 
-pub(super) const KEY_TERMINATOR: u8 = 0;
-
 pub(super) const VACANT_CHECK_VALUE: u8 = 0xFF;
 
+pub(super) const MAX_INLINE_VALUE: u32 = 0x7FFFFF;
+
+// The base of a terminal node is the value index, or `-1 - value` when the
+// value is inlined.
+pub(super) const fn inline_value_base(value: u32) -> Option<i32> {
+    if value <= MAX_INLINE_VALUE {
+        Some(-1 - value as i32)
+    } else {
+        None
+    }
+}
+
+// Returns true when a terminal node of the storage has an inlined value.
+pub(super) fn has_inlined_values<Value: 'static>(storage: &dyn StorageRead<Value>) -> Result<bool> {
+    for i in 0..storage.base_check_size()? {
+        if storage.check_at(i)? == KEY_TERMINATOR && storage.base_at(i)? < 0 {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+pub(super) fn value_of_base<Value: 'static>(
+    storage: &dyn StorageRead<Value>,
+    base: i32,
+) -> Result<Option<Rc<Value>>> {
+    if base >= 0 {
+        return storage.value_at(base as usize);
+    }
+    let value: Rc<dyn Any> = Rc::new((-1 - base) as u32);
+    Ok(value.downcast::<Value>().ok())
+}
+
+pub(super) fn value_ref_of_base<Value: 'static>(
+    storage: &dyn StorageRead<Value>,
+    base: i32,
+) -> Result<Option<ValueRef<'_, Value>>> {
+    if base >= 0 {
+        return storage.value_ref_at(base as usize);
+    }
+    Ok(value_of_base(storage, base)?.map(ValueRef::Shared))
+}
+
 #[derive(Debug)]
 pub(super) struct DoubleArrayBuilder<'a, Value: Debug> {
     elements: Vec<DoubleArrayElement<'a>>,
     density_factor: usize,
+    elements_sorted: bool,
+    checkpoint: Option<BuildCheckpoint>,
     phantom: PhantomData<Value>,
 }
 
@@ -77,7 +212,16 @@ impl<'a, Value: Clone + Debug + 'static> DoubleArrayBuilder<'a, Value> {
         self
     }
 
-    #[cfg(test)]
+    pub(super) const fn elements_sorted(mut self, elements_sorted: bool) -> Self {
+        self.elements_sorted = elements_sorted;
+        self
+    }
+
+    pub(super) fn resume_from(mut self, checkpoint: Option<BuildCheckpoint>) -> Self {
+        self.checkpoint = checkpoint;
+        self
+    }
+
     pub(super) fn build(self) -> Result<DoubleArray<Value>> {
         self.build_with_observer_set(&mut BuildingObserverSet::new(&mut |_| {}, &mut || {}))
     }
@@ -91,6 +235,8 @@ impl<'a, Value: Clone + Debug + 'static> DoubleArrayBuilder<'a, Value> {
                 self.elements,
                 building_observer_set,
                 self.density_factor,
+                self.elements_sorted,
+                self.checkpoint,
             )?,
             0,
         ))
@@ -99,8 +245,10 @@ impl<'a, Value: Clone + Debug + 'static> DoubleArrayBuilder<'a, Value> {
 
 #[derive(Debug)]
 pub(super) struct DoubleArray<Value: Debug> {
-    storage: Box<dyn Storage<Value>>,
+    storage: Box<dyn StorageRead<Value>>,
     root_base_check_index: usize,
+    #[cfg(feature = "metrics")]
+    metrics_counter: MetricsCounter,
 }
 
 impl<Value: Clone + Debug + 'static> DoubleArray<Value> {
@@ -108,37 +256,93 @@ impl<Value: Clone + Debug + 'static> DoubleArray<Value> {
         DoubleArrayBuilder {
             elements: vec![],
             density_factor: DEFAULT_DENSITY_FACTOR,
+            elements_sorted: false,
+            checkpoint: None,
             phantom: PhantomData,
         }
     }
 
     pub(super) const fn new(
-        storage: Box<dyn Storage<Value>>,
+        storage: Box<dyn StorageRead<Value>>,
         root_base_check_index: usize,
     ) -> Self {
         Self {
             storage,
             root_base_check_index,
+            #[cfg(feature = "metrics")]
+            metrics_counter: MetricsCounter::new(),
         }
     }
 
     pub(super) fn find(&self, key: &[u8]) -> Result<Option<i32>> {
-        let mut terminated_key: Vec<u8>;
-        let index = self.traverse({
-            terminated_key = Vec::from(key);
-            terminated_key.push(KEY_TERMINATOR);
-            &terminated_key
-        })?;
-        match index {
-            Some(index) => Ok(Some(self.storage.base_at(index)?)),
+        match self.terminal_index(key)? {
+            Some(index) => {
+                #[cfg(feature = "metrics")]
+                self.metrics_counter.add_storage_reads(1);
+                Ok(Some(self.storage.base_at(index)?))
+            }
             None => Ok(None),
         }
     }
 
+    pub(super) fn find_many(&self, keys: &[Vec<u8>]) -> Result<Vec<Option<i32>>> {
+        let mut order = (0..keys.len()).collect::<Vec<_>>();
+        order.sort_by(|&i, &j| keys[i].cmp(&keys[j]));
+
+        let mut found = vec![None; keys.len()];
+        // The base-check indices of the nodes traversed for the previous key,
+        // from the root. The traversal of a key resumes at the end of the
+        // prefix shared with the previous key.
+        let mut path = vec![self.root_base_check_index];
+        let mut previous_key: &[u8] = &[];
+        for index in order {
+            let key = keys[index].as_slice();
+            // Nothing follows a key terminator.
+            if key.contains(&KEY_TERMINATOR) {
+                continue;
+            }
+
+            let common_prefix_length = key
+                .iter()
+                .zip(previous_key)
+                .take_while(|(c1, c2)| c1 == c2)
+                .count();
+            path.truncate(common_prefix_length.min(path.len() - 1) + 1);
+            for &c in &key[path.len() - 1..] {
+                let Some(next_base_check_index) = self.child_index(path[path.len() - 1], c)? else {
+                    break;
+                };
+                path.push(next_base_check_index);
+            }
+            previous_key = key;
+
+            if path.len() != key.len() + 1 {
+                continue;
+            }
+            if let Some(terminator_index) =
+                self.child_index(path[path.len() - 1], KEY_TERMINATOR)?
+            {
+                #[cfg(feature = "metrics")]
+                self.metrics_counter.add_storage_reads(1);
+                found[index] = Some(self.storage.base_at(terminator_index)?);
+            }
+        }
+
+        Ok(found)
+    }
+
     pub(super) fn iter(&self) -> DoubleArrayIterator<'_, Value> {
         DoubleArrayIterator::new(self.storage.as_ref(), self.root_base_check_index)
     }
 
+    pub(super) fn range(&self, key_range: KeyRange) -> DoubleArrayIterator<'_, Value> {
+        DoubleArrayIterator::new_with_key_range(
+            self.storage.as_ref(),
+            self.root_base_check_index,
+            key_range,
+        )
+    }
+
     pub(super) fn subtrie(&self, key_prefix: &[u8]) -> Result<Option<Self>> {
         let index = self.traverse(key_prefix)?;
         let Some(index) = index else {
@@ -147,33 +351,367 @@ impl<Value: Clone + Debug + 'static> DoubleArray<Value> {
         Ok(Some(Self::new(self.storage().clone_box(), index)))
     }
 
+    pub(super) fn count_prefix(&self, key_prefix: &[u8]) -> Result<usize> {
+        let Some(index) = self.traverse(key_prefix)? else {
+            return Ok(0);
+        };
+
+        let mut count = 0;
+        let mut base_check_index_stack = vec![index];
+        while let Some(base_check_index) = base_check_index_stack.pop() {
+            if self.storage.check_at(base_check_index)? == KEY_TERMINATOR {
+                count += 1;
+                continue;
+            }
+            base_check_index_stack.extend(
+                self.children(base_check_index)?
+                    .into_iter()
+                    .map(|(_, child_index)| child_index),
+            );
+        }
+        Ok(count)
+    }
+
+    pub(super) fn is_subset_of(&self, other: &Self, same_size: bool) -> Result<bool>
+    where
+        Value: PartialEq,
+    {
+        // Both double arrays are walked in parallel, so no key is materialized.
+        let mut index_pair_stack = vec![(self.root_base_check_index, other.root_base_check_index)];
+        while let Some((index, other_index)) = index_pair_stack.pop() {
+            let terminal = self.storage.check_at(index)? == KEY_TERMINATOR;
+            let other_terminal = other.storage.check_at(other_index)? == KEY_TERMINATOR;
+            if terminal != other_terminal {
+                return Ok(false);
+            }
+            if terminal {
+                let value = self.value_of_base(self.storage.base_at(index)?)?;
+                let other_value = other.value_of_base(other.storage.base_at(other_index)?)?;
+                if value != other_value {
+                    return Ok(false);
+                }
+                continue;
+            }
+
+            let children = self.children(index)?;
+            let other_children = other.children(other_index)?;
+            if same_size && children.len() != other_children.len() {
+                return Ok(false);
+            }
+            for (char_code, child_index) in children {
+                let Ok(position) = other_children.binary_search_by_key(&char_code, |&(c, _)| c)
+                else {
+                    return Ok(false);
+                };
+                index_pair_stack.push((child_index, other_children[position].1));
+            }
+        }
+        Ok(true)
+    }
+
+    // Returns the k best completions of the key prefix in the descending order
+    // of the scores. The whole subtree is walked with a bounded heap.
+    pub(super) fn top_completions(
+        &self,
+        key_prefix: &[u8],
+        k: usize,
+        score: &dyn Fn(&Value) -> i64,
+    ) -> Result<Vec<(Vec<u8>, Rc<Value>)>> {
+        let Some(index) = self.traverse(key_prefix)? else {
+            return Ok(Vec::new());
+        };
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut best = BinaryHeap::with_capacity(k + 1);
+        let mut base_check_index_key_stack = vec![(index, key_prefix.to_vec())];
+        while let Some((base_check_index, key)) = base_check_index_key_stack.pop() {
+            if self.storage.check_at(base_check_index)? == KEY_TERMINATOR {
+                if let Some(value) = self.value_of_base(self.storage.base_at(base_check_index)?)? {
+                    best.push(Reverse(Scored {
+                        score: score(&value),
+                        key,
+                        item: value,
+                    }));
+                    if best.len() > k {
+                        let _worst = best.pop();
+                    }
+                }
+                continue;
+            }
+            for (char_code, child_index) in self.children(base_check_index)? {
+                base_check_index_key_stack.push((child_index, Self::child_key(&key, char_code)));
+            }
+        }
+        Ok(best
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(scored)| (scored.key, scored.item))
+            .collect())
+    }
+
+    // Returns the k best completions of the key prefix in the descending order
+    // of the scores. The subtrees are visited in the descending order of their
+    // max scores, so the walk stops after the k best completions are found.
+    pub(super) fn top_completions_with_max_scores(
+        &self,
+        key_prefix: &[u8],
+        k: usize,
+        score: &dyn Fn(&Value) -> i64,
+        max_scores: &[Option<i64>],
+    ) -> Result<Vec<(Vec<u8>, Rc<Value>)>> {
+        let Some(index) = self.traverse(key_prefix)? else {
+            return Ok(Vec::new());
+        };
+        let max_score_at = |base_check_index: usize| max_scores.get(base_check_index).copied();
+
+        let mut frontier = BinaryHeap::new();
+        if let Some(max_score) = max_score_at(index).flatten() {
+            frontier.push(Scored {
+                score: max_score,
+                key: key_prefix.to_vec(),
+                item: Frontier::Node(index),
+            });
+        }
+        let mut completions = Vec::new();
+        while completions.len() < k {
+            let Some(scored) = frontier.pop() else {
+                break;
+            };
+            let base_check_index = match scored.item {
+                Frontier::Completion(value) => {
+                    completions.push((scored.key, value));
+                    continue;
+                }
+                Frontier::Node(base_check_index) => base_check_index,
+            };
+            if self.storage.check_at(base_check_index)? == KEY_TERMINATOR {
+                if let Some(value) = self.value_of_base(self.storage.base_at(base_check_index)?)? {
+                    frontier.push(Scored {
+                        score: score(&value),
+                        key: scored.key,
+                        item: Frontier::Completion(value),
+                    });
+                }
+                continue;
+            }
+            for (char_code, child_index) in self.children(base_check_index)? {
+                let Some(max_score) = max_score_at(child_index).flatten() else {
+                    continue;
+                };
+                frontier.push(Scored {
+                    score: max_score,
+                    key: Self::child_key(&scored.key, char_code),
+                    item: Frontier::Node(child_index),
+                });
+            }
+        }
+        Ok(completions)
+    }
+
+    // Returns the max scores of the values under the nodes, indexed by the
+    // base-check indices. None means that no value is under the node.
+    pub(super) fn max_scores(&self, score: &dyn Fn(&Value) -> i64) -> Result<Vec<Option<i64>>> {
+        let mut max_scores = vec![None; self.storage.base_check_size()?];
+        // The children of a node are pushed above it, so that the node is
+        // popped again after all of them.
+        let mut base_check_index_stack = vec![(self.root_base_check_index, false)];
+        while let Some((base_check_index, children_visited)) = base_check_index_stack.pop() {
+            if self.storage.check_at(base_check_index)? == KEY_TERMINATOR {
+                max_scores[base_check_index] = self
+                    .value_of_base(self.storage.base_at(base_check_index)?)?
+                    .map(|value| score(&value));
+                continue;
+            }
+            let children = self.children(base_check_index)?;
+            if children_visited {
+                max_scores[base_check_index] = children
+                    .into_iter()
+                    .filter_map(|(_, child_index)| max_scores[child_index])
+                    .max();
+            } else {
+                base_check_index_stack.push((base_check_index, true));
+                base_check_index_stack.extend(
+                    children
+                        .into_iter()
+                        .map(|(_, child_index)| (child_index, false)),
+                );
+            }
+        }
+        Ok(max_scores)
+    }
+
+    fn child_key(key: &[u8], char_code: u8) -> Vec<u8> {
+        let mut child_key = key.to_vec();
+        if char_code != KEY_TERMINATOR {
+            child_key.push(char_code);
+        }
+        child_key
+    }
+
+    fn children(&self, base_check_index: usize) -> Result<Vec<(u8, usize)>> {
+        let base_check_size = self.storage.base_check_size()?;
+        let base = self.storage.base_at(base_check_index)?;
+        let mut children = Vec::new();
+        for char_code in 0..=0xFEu8 {
+            let next_index = base + char_code as i32;
+            if next_index < 0 || next_index as usize >= base_check_size {
+                continue;
+            }
+            if self.storage.check_at(next_index as usize)? == char_code {
+                children.push((char_code, next_index as usize));
+            }
+        }
+        Ok(children)
+    }
+
+    // Returns the base-check index of the node terminating the key.
+    pub(super) fn terminal_index(&self, key: &[u8]) -> Result<Option<usize>> {
+        let mut terminated_key = Vec::from(key);
+        terminated_key.push(KEY_TERMINATOR);
+        self.traverse(&terminated_key)
+    }
+
+    // Returns the base-check indices of the terminal nodes, and the ones of the
+    // other nodes keyed by their bases. The bases of the non-terminal nodes are
+    // unique, so the parent of a node is found by the base.
+    pub(super) fn terminal_and_parent_indices(&self) -> Result<(Vec<usize>, BTreeMap<i32, usize>)> {
+        let mut terminal_indices = Vec::new();
+        let mut parent_indices = BTreeMap::new();
+        for i in 0..self.storage.base_check_size()? {
+            match self.storage.check_at(i)? {
+                KEY_TERMINATOR => terminal_indices.push(i),
+                VACANT_CHECK_VALUE if i != self.root_base_check_index => {}
+                _ => {
+                    let _previous = parent_indices.insert(self.storage.base_at(i)?, i);
+                }
+            }
+        }
+        Ok((terminal_indices, parent_indices))
+    }
+
+    // Returns the serialized key of a terminal node by walking up to the root.
+    // Or None when the node is not under the root.
+    pub(super) fn key_of_terminal(
+        &self,
+        parent_indices: &BTreeMap<i32, usize>,
+        terminal_index: usize,
+    ) -> Result<Option<Vec<u8>>> {
+        let mut key = Vec::new();
+        let mut base_check_index = terminal_index;
+        while base_check_index != self.root_base_check_index {
+            // A walk longer than the node count must pass through a node twice.
+            if key.len() > parent_indices.len() {
+                return Ok(None);
+            }
+            let check = self.storage.check_at(base_check_index)?;
+            if check == VACANT_CHECK_VALUE {
+                return Ok(None);
+            }
+            let Some(&parent_index) = parent_indices.get(&(base_check_index as i32 - check as i32))
+            else {
+                return Ok(None);
+            };
+            if check != KEY_TERMINATOR {
+                key.push(check);
+            }
+            base_check_index = parent_index;
+        }
+        key.reverse();
+        Ok(Some(key))
+    }
+
     fn traverse(&self, key: &[u8]) -> Result<Option<usize>> {
         let mut base_check_index = self.root_base_check_index;
+        let mut terminated = false;
         for c in key {
-            let next_base_check_index =
-                (self.storage.base_at(base_check_index)? + *c as i32) as usize;
-            if next_base_check_index >= self.storage.base_check_size()?
-                || self.storage.check_at(next_base_check_index)? != *c
-            {
+            // Nothing follows a key terminator.
+            if terminated {
                 return Ok(None);
             }
+            terminated = *c == KEY_TERMINATOR;
+            let Some(next_base_check_index) = self.child_index(base_check_index, *c)? else {
+                return Ok(None);
+            };
             base_check_index = next_base_check_index;
         }
 
         Ok(Some(base_check_index))
     }
 
-    pub(super) fn storage(&self) -> &dyn Storage<Value> {
+    pub(super) const fn root_base_check_index(&self) -> usize {
+        self.root_base_check_index
+    }
+
+    pub(super) fn child_index(&self, base_check_index: usize, c: u8) -> Result<Option<usize>> {
+        let next_base_check_index = (self.storage.base_at(base_check_index)? + c as i32) as usize;
+        if next_base_check_index >= self.storage.base_check_size()? {
+            #[cfg(feature = "metrics")]
+            self.metrics_counter.add_storage_reads(1);
+            return Ok(None);
+        }
+        #[cfg(feature = "metrics")]
+        self.metrics_counter.add_storage_reads(2);
+        if self.storage.check_at(next_base_check_index)? != c {
+            return Ok(None);
+        }
+        #[cfg(feature = "metrics")]
+        self.metrics_counter.add_traversal_step();
+        Ok(Some(next_base_check_index))
+    }
+
+    pub(super) fn value_base_at(&self, base_check_index: usize) -> Result<Option<i32>> {
+        let Some(terminator_index) = self.child_index(base_check_index, KEY_TERMINATOR)? else {
+            return Ok(None);
+        };
+        Ok(Some(self.storage.base_at(terminator_index)?))
+    }
+
+    pub(super) fn value_of_base(&self, base: i32) -> Result<Option<Rc<Value>>> {
+        #[cfg(feature = "metrics")]
+        if base >= 0 {
+            self.metrics_counter.add_storage_reads(1);
+        }
+        value_of_base(self.storage.as_ref(), base)
+    }
+
+    pub(super) fn value_ref_of_base(&self, base: i32) -> Result<Option<ValueRef<'_, Value>>> {
+        #[cfg(feature = "metrics")]
+        if base >= 0 {
+            self.metrics_counter.add_storage_reads(1);
+        }
+        value_ref_of_base(self.storage.as_ref(), base)
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(super) fn metrics(&self) -> LookupMetrics {
+        self.metrics_counter
+            .snapshot(self.storage.value_cache_hit_count())
+    }
+
+    pub(super) fn storage(&self) -> &dyn StorageRead<Value> {
         self.storage.as_ref()
     }
 
-    pub(super) fn storage_mut(&mut self) -> &mut dyn Storage<Value> {
-        &mut *self.storage
+    pub(super) fn storage_mut(&mut self) -> Option<&mut dyn StorageMut<Value>> {
+        self.storage.as_storage_mut()
+    }
+
+    // Returns the storage of a double array made by the builder, which is
+    // always a memory storage.
+    pub(super) fn memory_storage_mut(&mut self) -> &mut MemoryStorage<Value> {
+        let Some(storage) = self.storage.downcast_mut::<MemoryStorage<Value>>() else {
+            unreachable!("The storage of a built double array must be a memory storage.");
+        };
+        storage
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use core::ops::Bound;
+
     use super::*;
 
     #[rustfmt::skip]
@@ -306,7 +844,7 @@ mod tests {
         0x00001800, // [11]   24,    10,         0
     ];
 
-    fn base_check_array_of<T: 'static>(storage: &dyn Storage<T>) -> Result<Vec<u32>> {
+    fn base_check_array_of<T: 'static>(storage: &dyn StorageRead<T>) -> Result<Vec<u32>> {
         let size = storage.base_check_size()?;
         let mut array = Vec::<u32>::with_capacity(size);
         for i in 0..size {
@@ -463,6 +1001,75 @@ mod tests {
             assert_eq!(found, 24);
         }
 
+        #[test]
+        fn terminal_index() {
+            let double_array = DoubleArray::<i32>::builder()
+                .elements(EXPECTED_VALUES3.to_vec())
+                .build()
+                .unwrap();
+
+            let index = double_array.terminal_index(b"SETA").unwrap().unwrap();
+            assert_eq!(double_array.storage().base_at(index).unwrap(), 42);
+            assert!(double_array.terminal_index(b"SET").unwrap().is_none());
+        }
+
+        #[test]
+        fn terminal_and_parent_indices() {
+            let double_array = DoubleArray::<i32>::builder()
+                .elements(EXPECTED_VALUES3.to_vec())
+                .build()
+                .unwrap();
+
+            let (terminal_indices, parent_indices) =
+                double_array.terminal_and_parent_indices().unwrap();
+            let mut bases = terminal_indices
+                .iter()
+                .map(|&index| double_array.storage().base_at(index).unwrap())
+                .collect::<Vec<_>>();
+            bases.sort();
+            assert_eq!(bases, [24, 42, 2424]);
+            assert_eq!(parent_indices.get(&-82), Some(&0));
+            assert_eq!(parent_indices.len(), 13);
+        }
+
+        #[test]
+        fn key_of_terminal() {
+            let double_array = DoubleArray::<i32>::builder()
+                .elements(EXPECTED_VALUES3.to_vec())
+                .build()
+                .unwrap();
+            let (_, parent_indices) = double_array.terminal_and_parent_indices().unwrap();
+
+            {
+                let index = double_array.terminal_index(b"UTIGOSI").unwrap().unwrap();
+                assert_eq!(
+                    double_array
+                        .key_of_terminal(&parent_indices, index)
+                        .unwrap()
+                        .unwrap(),
+                    b"UTIGOSI"
+                );
+            }
+            {
+                let subtrie = double_array.subtrie(b"U").unwrap().unwrap();
+                let (_, parent_indices) = subtrie.terminal_and_parent_indices().unwrap();
+
+                let index = double_array.terminal_index(b"UTO").unwrap().unwrap();
+                assert_eq!(
+                    subtrie
+                        .key_of_terminal(&parent_indices, index)
+                        .unwrap()
+                        .unwrap(),
+                    b"TO"
+                );
+                let index = double_array.terminal_index(b"SETA").unwrap().unwrap();
+                assert!(subtrie
+                    .key_of_terminal(&parent_indices, index)
+                    .unwrap()
+                    .is_none());
+            }
+        }
+
         #[test]
         fn find() {
             {
@@ -517,6 +1124,54 @@ mod tests {
             }
         }
 
+        #[test]
+        fn find_many() {
+            {
+                let double_array = DoubleArray::<i32>::builder().build().unwrap();
+
+                let found = double_array.find_many(&[b"SETA".to_vec()]).unwrap();
+                assert_eq!(found, [None]);
+            }
+            {
+                let double_array = DoubleArray::<i32>::builder()
+                    .elements(EXPECTED_VALUES3.to_vec())
+                    .build()
+                    .unwrap();
+
+                let found = double_array
+                    .find_many(&[
+                        b"UTO".to_vec(),
+                        b"SETA".to_vec(),
+                        b"UTI".to_vec(),
+                        b"UTIGOSI".to_vec(),
+                        b"SETA".to_vec(),
+                        b"UTOX".to_vec(),
+                        b"U\0".to_vec(),
+                        b"".to_vec(),
+                    ])
+                    .unwrap();
+                assert_eq!(
+                    found,
+                    [
+                        Some(2424),
+                        Some(42),
+                        None,
+                        Some(24),
+                        Some(42),
+                        None,
+                        None,
+                        None
+                    ]
+                );
+            }
+            {
+                let double_array = DoubleArray::<i32>::builder().build().unwrap();
+
+                let found = double_array.find_many(&[]).unwrap();
+                assert!(found.is_empty());
+            }
+        }
+
         #[test]
         fn iter() {
             {
@@ -542,6 +1197,39 @@ mod tests {
             }
         }
 
+        #[test]
+        fn range() {
+            let double_array = DoubleArray::<i32>::builder()
+                .elements(EXPECTED_VALUES3.to_vec())
+                .build()
+                .unwrap();
+
+            {
+                let values = double_array
+                    .range((Bound::Unbounded, Bound::Unbounded))
+                    .collect::<Vec<_>>();
+                assert_eq!(values, [42, 24, 2424]);
+            }
+            {
+                let values = double_array
+                    .range((
+                        Bound::Excluded(b"SETA".to_vec()),
+                        Bound::Included(b"UTO".to_vec()),
+                    ))
+                    .collect::<Vec<_>>();
+                assert_eq!(values, [24, 2424]);
+            }
+            {
+                let values = double_array
+                    .range((
+                        Bound::Included(b"U".to_vec()),
+                        Bound::Excluded(b"UTO".to_vec()),
+                    ))
+                    .collect::<Vec<_>>();
+                assert_eq!(values, [24]);
+            }
+        }
+
         #[test]
         fn subtrie() {
             {
@@ -614,6 +1302,57 @@ mod tests {
             }
         }
 
+        #[test]
+        fn count_prefix() {
+            let double_array = DoubleArray::<i32>::builder()
+                .elements(EXPECTED_VALUES3.to_vec())
+                .build()
+                .unwrap();
+
+            assert_eq!(double_array.count_prefix(b"").unwrap(), 3);
+            assert_eq!(double_array.count_prefix(b"U").unwrap(), 2);
+            assert_eq!(double_array.count_prefix(b"UTO").unwrap(), 1);
+            assert_eq!(double_array.count_prefix(b"UTOX").unwrap(), 0);
+            assert_eq!(double_array.count_prefix(b"X").unwrap(), 0);
+            {
+                let subtrie = double_array.subtrie(b"U").unwrap().unwrap();
+                assert_eq!(subtrie.count_prefix(b"T").unwrap(), 2);
+            }
+        }
+
+        #[test]
+        fn is_subset_of() {
+            let double_array = DoubleArray::<i32>::builder()
+                .elements(EXPECTED_VALUES3.to_vec())
+                .build()
+                .unwrap();
+            let double_array_density_1 = DoubleArray::<i32>::builder()
+                .elements(EXPECTED_VALUES3.to_vec())
+                .density_factor(1)
+                .build()
+                .unwrap();
+            let double_array_less = DoubleArray::<i32>::builder()
+                .elements(EXPECTED_VALUES3[..2].to_vec())
+                .build()
+                .unwrap();
+
+            assert!(double_array
+                .is_subset_of(&double_array_density_1, true)
+                .unwrap());
+            assert!(double_array_less
+                .is_subset_of(&double_array, false)
+                .unwrap());
+            assert!(!double_array_less.is_subset_of(&double_array, true).unwrap());
+            assert!(!double_array
+                .is_subset_of(&double_array_less, false)
+                .unwrap());
+            {
+                let subtrie = double_array.subtrie(b"U").unwrap().unwrap();
+                let subtrie_less = double_array_less.subtrie(b"U").unwrap().unwrap();
+                assert!(subtrie.is_subset_of(&subtrie_less, true).unwrap());
+            }
+        }
+
         #[test]
         fn storage() {
             let double_array = DoubleArray::<i32>::builder()
@@ -633,7 +1372,20 @@ mod tests {
                 .build()
                 .unwrap();
 
-            let base_check_array = base_check_array_of(double_array.storage_mut()).unwrap();
+            let storage = double_array.storage_mut().unwrap();
+            storage.add_value_at(0, 42).unwrap();
+
+            assert_eq!(*double_array.storage().value_at(0).unwrap().unwrap(), 42);
+        }
+
+        #[test]
+        fn memory_storage_mut() {
+            let mut double_array = DoubleArray::<i32>::builder()
+                .elements(EXPECTED_VALUES3.to_vec())
+                .build()
+                .unwrap();
+
+            let base_check_array = base_check_array_of(double_array.memory_storage_mut()).unwrap();
 
             assert_eq!(base_check_array, EXPECTED_BASE_CHECK_ARRAY3);
         }
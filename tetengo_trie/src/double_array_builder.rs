@@ -4,40 +4,121 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use std::collections::HashSet;
-use std::fmt::Debug;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Debug;
 
 use anyhow::Result;
+use hashbrown::HashSet;
 
+use crate::build_checkpoint::{BuildCheckpoint, BuildCheckpointError};
 use crate::double_array::{
-    BuildingObserverSet, DoubleArrayElement, DoubleArrayError, KEY_TERMINATOR, VACANT_CHECK_VALUE,
+    BuildingObserverSet, DoubleArrayElement, DoubleArrayError, VACANT_CHECK_VALUE,
 };
 use crate::memory_storage::MemoryStorage;
-use crate::storage::Storage;
+use crate::serializer::KEY_TERMINATOR;
+use crate::storage::{StorageMut, StorageRead};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum VacantSlotScan {
+    Storage,
+    Bitset,
+}
+
+const DEFAULT_VACANT_SLOT_SCAN: VacantSlotScan = if cfg!(feature = "bitset-scan") {
+    VacantSlotScan::Bitset
+} else {
+    VacantSlotScan::Storage
+};
 
 pub(super) fn build<T: Clone + Debug + 'static>(
+    elements: Vec<DoubleArrayElement<'_>>,
+    observer: &mut BuildingObserverSet<'_>,
+    density_factor: usize,
+    elements_sorted: bool,
+    checkpoint: Option<BuildCheckpoint>,
+) -> Result<Box<MemoryStorage<T>>> {
+    build_with_scan(
+        elements,
+        observer,
+        density_factor,
+        elements_sorted,
+        checkpoint,
+        DEFAULT_VACANT_SLOT_SCAN,
+    )
+}
+
+#[derive(Debug)]
+struct BuildProgress {
+    element_count: usize,
+    resumed_element_count: usize,
+    added_element_count: usize,
+}
+
+fn build_with_scan<T: Clone + Debug + 'static>(
     mut elements: Vec<DoubleArrayElement<'_>>,
     observer: &mut BuildingObserverSet<'_>,
     density_factor: usize,
-) -> Result<Box<dyn Storage<T>>> {
+    elements_sorted: bool,
+    checkpoint: Option<BuildCheckpoint>,
+    scan: VacantSlotScan,
+) -> Result<Box<MemoryStorage<T>>> {
     if density_factor == 0 {
         return Err(DoubleArrayError::InvalidDensityFactor.into());
     }
 
-    elements.sort_by_key(|(k, _)| *k);
+    if !elements_sorted {
+        elements.sort_by_key(|(k, _)| *k);
+    }
 
-    let mut storage = Box::new(MemoryStorage::<T>::new());
+    let (mut storage, mut base_uniquer, mut occupied_slots, resumed_element_count) =
+        match checkpoint {
+            Some(checkpoint) => {
+                if checkpoint.element_count() != elements.len()
+                    || checkpoint.density_factor() != density_factor
+                {
+                    return Err(BuildCheckpointError::MismatchedCheckpoint.into());
+                }
+                let base_uniquer = checkpoint.bases().iter().copied().collect::<HashSet<_>>();
+                let occupied_slots =
+                    OccupiedSlots::new_with_words(checkpoint.occupied_slot_words().to_vec());
+                let resumed_element_count = checkpoint.added_element_count();
+                let storage = Box::new(MemoryStorage::<T>::new_with_base_check_array(
+                    checkpoint.into_base_check_array(),
+                ));
+                (storage, base_uniquer, occupied_slots, resumed_element_count)
+            }
+            None => {
+                let mut occupied_slots = OccupiedSlots::new();
+                occupied_slots.occupy(0);
+                (
+                    Box::new(MemoryStorage::<T>::new()),
+                    HashSet::new(),
+                    occupied_slots,
+                    0,
+                )
+            }
+        };
 
     if !elements.is_empty() {
-        let mut base_uniquer = HashSet::new();
+        let mut progress = BuildProgress {
+            element_count: elements.len(),
+            resumed_element_count,
+            added_element_count: resumed_element_count,
+        };
         build_iter(
             &elements[..],
             0,
+            0,
             storage.as_mut(),
             0,
             &mut base_uniquer,
+            &mut occupied_slots,
+            &mut progress,
             observer,
             density_factor,
+            scan,
         )?;
     }
 
@@ -45,71 +126,138 @@ pub(super) fn build<T: Clone + Debug + 'static>(
     Ok(storage)
 }
 
-fn build_iter<T: 'static>(
+fn build_iter<T: Clone + Debug + 'static>(
     elements: &[DoubleArrayElement<'_>],
+    elements_offset: usize,
     key_offset: usize,
-    storage: &mut dyn Storage<T>,
+    storage: &mut MemoryStorage<T>,
     base_check_index: usize,
     base_uniquer: &mut HashSet<i32>,
+    occupied_slots: &mut OccupiedSlots,
+    progress: &mut BuildProgress,
     observer: &mut BuildingObserverSet<'_>,
     density_factor: usize,
+    scan: VacantSlotScan,
 ) -> Result<()> {
     let children_firsts = children_firsts(elements, key_offset);
 
-    let base = calc_base(
-        children_firsts.as_slice(),
-        elements,
-        key_offset,
-        storage,
-        base_check_index,
-        density_factor,
-        base_uniquer,
-    )?;
-    storage.set_base_at(base_check_index, base)?;
+    // A node containing a resumed element is already placed.
+    let base = if elements_offset < progress.resumed_element_count {
+        storage.base_at(base_check_index)?
+    } else {
+        let base = match scan {
+            VacantSlotScan::Storage => calc_base(
+                children_firsts.as_slice(),
+                elements,
+                key_offset,
+                storage,
+                base_check_index,
+                density_factor,
+                occupied_slots.first_vacant(),
+                base_uniquer,
+            )?,
+            VacantSlotScan::Bitset => calc_base_with_bitset(
+                children_firsts.as_slice(),
+                elements,
+                key_offset,
+                occupied_slots,
+                base_check_index,
+                density_factor,
+                base_uniquer,
+            ),
+        };
+        storage.set_base_at(base_check_index, base)?;
 
-    for children_first in children_firsts.iter().take(children_firsts.len() - 1) {
-        let (element_key, _) = elements[*children_first];
-        let char_code = char_code_at(element_key, key_offset);
-        let next_base_check_index = (base + char_code as i32) as usize;
-        storage.set_check_at(next_base_check_index, char_code)?;
-    }
+        for children_first in children_firsts.iter().take(children_firsts.len() - 1) {
+            let (element_key, _) = elements[*children_first];
+            let char_code = char_code_at(element_key, key_offset);
+            let next_base_check_index = (base + char_code as i32) as usize;
+            storage.set_check_at(next_base_check_index, char_code)?;
+            occupied_slots.occupy(next_base_check_index);
+        }
+        base
+    };
     for i in 0..children_firsts.len() - 1 {
         let children_first = children_firsts[i];
         let children_last = children_firsts[i + 1];
+        if elements_offset + children_last <= progress.resumed_element_count {
+            continue;
+        }
         let (element_key, value) = elements[children_first];
         let char_code = char_code_at(element_key, key_offset);
         let next_base_check_index = (base + char_code as i32) as usize;
         if char_code == KEY_TERMINATOR {
+            observer.check_cancelled()?;
             observer.adding(&elements[children_first]);
             storage.set_base_at(next_base_check_index, value)?;
+            progress.added_element_count += 1;
+            if observer.checkpoint_due(progress.added_element_count) {
+                observer.checkpoint(&make_checkpoint(
+                    storage,
+                    base_uniquer,
+                    occupied_slots,
+                    progress,
+                    density_factor,
+                )?)?;
+            }
             continue;
         }
         build_iter(
             &elements[children_first..children_last],
+            elements_offset + children_first,
             key_offset + 1,
             storage,
             next_base_check_index,
             base_uniquer,
+            occupied_slots,
+            progress,
             observer,
             density_factor,
+            scan,
         )?;
     }
     Ok(())
 }
 
-fn calc_base<T: 'static>(
+fn make_checkpoint<T: Clone + Debug + 'static>(
+    storage: &MemoryStorage<T>,
+    base_uniquer: &HashSet<i32>,
+    occupied_slots: &OccupiedSlots,
+    progress: &BuildProgress,
+    density_factor: usize,
+) -> Result<BuildCheckpoint> {
+    let base_check_size = storage.base_check_size()?;
+    let mut base_check_array = Vec::with_capacity(base_check_size);
+    for i in 0..base_check_size {
+        base_check_array.push(((storage.base_at(i)? as u32) << 8) | storage.check_at(i)? as u32);
+    }
+    let mut bases = base_uniquer.iter().copied().collect::<Vec<_>>();
+    bases.sort_unstable();
+    Ok(BuildCheckpoint::new(
+        progress.element_count,
+        density_factor,
+        progress.added_element_count,
+        base_check_array,
+        bases,
+        occupied_slots.words.clone(),
+    ))
+}
+
+fn calc_base<T: Clone + Debug + 'static>(
     firsts: &[usize],
     elements: &[DoubleArrayElement<'_>],
     key_offset: usize,
-    storage: &dyn Storage<T>,
+    storage: &MemoryStorage<T>,
     base_check_index: usize,
     density_factor: usize,
+    first_vacant: usize,
     base_uniquer: &mut HashSet<i32>,
 ) -> Result<i32> {
     let (element_key, _) = elements[0];
-    let base_first = (base_check_index - (base_check_index / density_factor)) as i32
-        - char_code_at(element_key, key_offset) as i32
-        + 1;
+    let first_char_code = char_code_at(element_key, key_offset) as i32;
+    let base_first =
+        ((base_check_index - (base_check_index / density_factor)) as i32 - first_char_code + 1)
+            .max(first_vacant as i32 - first_char_code);
     for base in base_first.. {
         let first_last = firsts[firsts.len() - 1];
         let occupied = elements
@@ -137,6 +285,116 @@ fn calc_base<T: 'static>(
     unreachable!()
 }
 
+fn calc_base_with_bitset(
+    firsts: &[usize],
+    elements: &[DoubleArrayElement<'_>],
+    key_offset: usize,
+    occupied_slots: &OccupiedSlots,
+    base_check_index: usize,
+    density_factor: usize,
+    base_uniquer: &mut HashSet<i32>,
+) -> i32 {
+    let char_codes = firsts
+        .iter()
+        .take(firsts.len() - 1)
+        .map(|&first| {
+            let (element_key, _) = elements[first];
+            char_code_at(element_key, key_offset) as i32
+        })
+        .collect::<Vec<_>>();
+    let base_first =
+        (base_check_index - (base_check_index / density_factor)) as i32 - char_codes[0] + 1;
+    let mut base = base_first;
+    loop {
+        let first_vacant = occupied_slots.next_vacant((base + char_codes[0]) as usize);
+        base = first_vacant as i32 - char_codes[0];
+        let occupied = char_codes
+            .iter()
+            .skip(1)
+            .any(|&char_code| occupied_slots.is_occupied((base + char_code) as usize));
+        if !occupied && !base_uniquer.contains(&base) {
+            let _ = base_uniquer.insert(base);
+            return base;
+        }
+        base += 1;
+    }
+}
+
+/**
+ * A bitset of the occupied check slots.
+ *
+ * The words before `first_vacant_word` are all occupied, so that the scans
+ * skip the densely filled head of the double array.
+ */
+#[derive(Debug)]
+struct OccupiedSlots {
+    words: Vec<u64>,
+    first_vacant_word: usize,
+}
+
+impl OccupiedSlots {
+    const fn new() -> Self {
+        Self {
+            words: Vec::new(),
+            first_vacant_word: 0,
+        }
+    }
+
+    fn new_with_words(words: Vec<u64>) -> Self {
+        let first_vacant_word = words
+            .iter()
+            .position(|&word| word != u64::MAX)
+            .unwrap_or(words.len());
+        Self {
+            words,
+            first_vacant_word,
+        }
+    }
+
+    fn first_vacant(&self) -> usize {
+        self.next_vacant(self.first_vacant_word * u64::BITS as usize)
+    }
+
+    fn is_occupied(&self, index: usize) -> bool {
+        self.words
+            .get(index / u64::BITS as usize)
+            .is_some_and(|word| word & (1 << (index % u64::BITS as usize)) != 0)
+    }
+
+    fn occupy(&mut self, index: usize) {
+        let word_index = index / u64::BITS as usize;
+        if word_index >= self.words.len() {
+            self.words.resize(word_index + 1, 0);
+        }
+        self.words[word_index] |= 1 << (index % u64::BITS as usize);
+        while self.words.get(self.first_vacant_word) == Some(&u64::MAX) {
+            self.first_vacant_word += 1;
+        }
+    }
+
+    fn next_vacant(&self, from: usize) -> usize {
+        if from / (u64::BITS as usize) < self.first_vacant_word {
+            return self.first_vacant();
+        }
+        let mut word_index = from / u64::BITS as usize;
+        let Some(&word) = self.words.get(word_index) else {
+            return from;
+        };
+        let masked = word | ((1 << (from % u64::BITS as usize)) - 1);
+        if masked != u64::MAX {
+            return word_index * u64::BITS as usize + masked.trailing_ones() as usize;
+        }
+        word_index += 1;
+        while let Some(&word) = self.words.get(word_index) {
+            if word != u64::MAX {
+                return word_index * u64::BITS as usize + word.trailing_ones() as usize;
+            }
+            word_index += 1;
+        }
+        word_index * u64::BITS as usize
+    }
+}
+
 fn children_firsts(elements: &[DoubleArrayElement<'_>], key_offset: usize) -> Vec<usize> {
     let mut firsts = vec![0];
     let mut child_first = 0;
@@ -167,3 +425,144 @@ const fn char_code_at(bytes: &[u8], index: usize) -> u8 {
         KEY_TERMINATOR
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use crate::double_array::DEFAULT_DENSITY_FACTOR;
+
+    use super::*;
+
+    fn make_keys() -> Vec<Vec<u8>> {
+        let mut keys = Vec::new();
+        for i in 0u32..2000 {
+            let key = format!("{}\u{0}", i.wrapping_mul(2654435761) % 100000);
+            keys.push(key.into_bytes());
+        }
+        keys.sort();
+        keys.dedup();
+        keys
+    }
+
+    #[test]
+    fn build_with_scan() {
+        let keys = make_keys();
+        let elements = || {
+            keys.iter()
+                .enumerate()
+                .map(|(i, key)| (key.as_slice(), i as i32))
+                .collect::<Vec<_>>()
+        };
+
+        let by_storage = super::build_with_scan::<i32>(
+            elements(),
+            &mut BuildingObserverSet::new(&mut |_| {}, &mut || {}),
+            DEFAULT_DENSITY_FACTOR,
+            true,
+            None,
+            VacantSlotScan::Storage,
+        )
+        .unwrap();
+        let by_bitset = super::build_with_scan::<i32>(
+            elements(),
+            &mut BuildingObserverSet::new(&mut |_| {}, &mut || {}),
+            DEFAULT_DENSITY_FACTOR,
+            true,
+            None,
+            VacantSlotScan::Bitset,
+        )
+        .unwrap();
+
+        let size = by_storage.base_check_size().unwrap();
+        assert_eq!(by_bitset.base_check_size().unwrap(), size);
+        for i in 0..size {
+            assert_eq!(
+                by_bitset.base_at(i).unwrap(),
+                by_storage.base_at(i).unwrap()
+            );
+            assert_eq!(
+                by_bitset.check_at(i).unwrap(),
+                by_storage.check_at(i).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn build_with_checkpoint() {
+        let keys = make_keys();
+        let elements = || {
+            keys.iter()
+                .enumerate()
+                .map(|(i, key)| (key.as_slice(), i as i32))
+                .collect::<Vec<_>>()
+        };
+
+        for scan in [VacantSlotScan::Storage, VacantSlotScan::Bitset] {
+            let mut checkpoints = Vec::new();
+            let mut checkpointing = |checkpoint: &BuildCheckpoint| {
+                checkpoints.push(checkpoint.clone());
+                Ok(())
+            };
+            let built = super::build_with_scan::<i32>(
+                elements(),
+                &mut BuildingObserverSet::new(&mut |_| {}, &mut || {})
+                    .checkpointing(500, &mut checkpointing),
+                DEFAULT_DENSITY_FACTOR,
+                true,
+                None,
+                scan,
+            )
+            .unwrap();
+            assert_eq!(checkpoints.len(), keys.len() / 500);
+
+            for checkpoint in checkpoints {
+                let mut adding_count = 0;
+                let resumed = super::build_with_scan::<i32>(
+                    elements(),
+                    &mut BuildingObserverSet::new(&mut |_| adding_count += 1, &mut || {}),
+                    DEFAULT_DENSITY_FACTOR,
+                    true,
+                    Some(checkpoint.clone()),
+                    scan,
+                )
+                .unwrap();
+                assert_eq!(adding_count, keys.len() - checkpoint.added_element_count());
+
+                let size = built.base_check_size().unwrap();
+                assert_eq!(resumed.base_check_size().unwrap(), size);
+                for i in 0..size {
+                    assert_eq!(resumed.base_at(i).unwrap(), built.base_at(i).unwrap());
+                    assert_eq!(resumed.check_at(i).unwrap(), built.check_at(i).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn occupied_slots() {
+        let mut occupied_slots = OccupiedSlots::new();
+        assert!(!occupied_slots.is_occupied(0));
+        assert_eq!(occupied_slots.next_vacant(3), 3);
+
+        for i in 0..130 {
+            if i != 64 {
+                occupied_slots.occupy(i);
+            }
+        }
+        assert!(occupied_slots.is_occupied(63));
+        assert!(!occupied_slots.is_occupied(64));
+        assert!(occupied_slots.is_occupied(129));
+        assert!(!occupied_slots.is_occupied(130));
+        assert_eq!(occupied_slots.next_vacant(0), 64);
+        assert_eq!(occupied_slots.next_vacant(64), 64);
+        assert_eq!(occupied_slots.next_vacant(65), 130);
+        assert_eq!(occupied_slots.next_vacant(200), 200);
+        assert_eq!(occupied_slots.first_vacant(), 64);
+
+        occupied_slots.occupy(64);
+        assert_eq!(occupied_slots.first_vacant_word, 2);
+        assert_eq!(occupied_slots.first_vacant(), 130);
+        assert_eq!(occupied_slots.next_vacant(5), 130);
+    }
+}
@@ -4,30 +4,117 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use std::fmt::Debug;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::type_name_of_val;
+use core::fmt::{self, Debug, Formatter};
+use core::ops::{Bound, RangeBounds};
 
-use crate::double_array;
-use crate::storage::Storage;
+use crate::serializer::KEY_TERMINATOR;
+use crate::storage::StorageRead;
 
-#[derive(Clone, Debug)]
+pub(super) type KeyRange = (Bound<Vec<u8>>, Bound<Vec<u8>>);
+
+type PrefixFilter<'a> = &'a dyn Fn(&[u8]) -> bool;
+
+#[derive(Clone)]
 pub(super) struct DoubleArrayIterator<'a, T: 'static> {
-    storage: &'a dyn Storage<T>,
+    storage: &'a dyn StorageRead<T>,
     base_check_index_key_stack: Vec<(usize, Vec<u8>)>,
+    key_range: KeyRange,
+    max_depth: Option<usize>,
+    prefix_filter: Option<PrefixFilter<'a>>,
+    reversed: bool,
+}
+
+impl<T> Debug for DoubleArrayIterator<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DoubleArrayIterator")
+            .field("storage", &self.storage)
+            .field(
+                "base_check_index_key_stack",
+                &self.base_check_index_key_stack,
+            )
+            .field("key_range", &self.key_range)
+            .field("max_depth", &self.max_depth)
+            .field(
+                "prefix_filter",
+                &self.prefix_filter.map(|filter| type_name_of_val(filter)),
+            )
+            .field("reversed", &self.reversed)
+            .finish()
+    }
 }
 
 impl<'a, T> DoubleArrayIterator<'a, T> {
-    pub(super) fn new(storage: &'a dyn Storage<T>, root_base_check_index: usize) -> Self {
+    pub(super) fn new(storage: &'a dyn StorageRead<T>, root_base_check_index: usize) -> Self {
+        Self::new_with_key_range(
+            storage,
+            root_base_check_index,
+            (Bound::Unbounded, Bound::Unbounded),
+        )
+    }
+
+    pub(super) fn new_with_key_range(
+        storage: &'a dyn StorageRead<T>,
+        root_base_check_index: usize,
+        key_range: KeyRange,
+    ) -> Self {
         Self {
             storage,
             base_check_index_key_stack: vec![(root_base_check_index, Vec::new())],
+            key_range,
+            max_depth: None,
+            prefix_filter: None,
+            reversed: false,
         }
     }
-}
 
-impl<T> Iterator for DoubleArrayIterator<'_, T> {
-    type Item = i32;
+    pub(super) const fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
+    pub(super) const fn prefix_filter(mut self, prefix_filter: PrefixFilter<'a>) -> Self {
+        self.prefix_filter = Some(prefix_filter);
+        self
+    }
+
+    pub(super) const fn reversed(mut self) -> Self {
+        self.reversed = true;
+        self
+    }
+
+    fn may_contain_keys_with_prefix(&self, key_prefix: &[u8]) -> bool {
+        let below_start = match &self.key_range.0 {
+            Bound::Included(start) | Bound::Excluded(start) => {
+                key_prefix < &start[..key_prefix.len().min(start.len())]
+            }
+            Bound::Unbounded => false,
+        };
+        let above_end = match &self.key_range.1 {
+            Bound::Included(end) | Bound::Excluded(end) => {
+                key_prefix > &end[..key_prefix.len().min(end.len())]
+            }
+            Bound::Unbounded => false,
+        };
+        !below_start && !above_end
+    }
+
+    fn may_descend_to(&self, key_prefix: &[u8]) -> bool {
+        if self
+            .max_depth
+            .is_some_and(|max_depth| key_prefix.len() > max_depth)
+        {
+            return false;
+        }
+        self.prefix_filter
+            .is_none_or(|prefix_filter| prefix_filter(key_prefix))
+    }
+}
+
+impl<T> DoubleArrayIterator<'_, T> {
+    pub(super) fn next_with_key(&mut self) -> Option<(Vec<u8>, i32)> {
         let (base_check_index, key) = self.base_check_index_key_stack.pop()?;
 
         let base = match self.storage.base_at(base_check_index) {
@@ -45,14 +132,26 @@ impl<T> Iterator for DoubleArrayIterator<'_, T> {
             }
         };
 
-        if check == double_array::KEY_TERMINATOR {
-            return Some(base);
+        if check == KEY_TERMINATOR {
+            if !self.key_range.contains(&key) {
+                return self.next_with_key();
+            }
+            return Some((key, base));
         }
 
-        for char_code in (0..=0xFE).rev() {
-            let char_code_as_uint8 = char_code as u8;
+        let base_check_size = match self.storage.base_check_size() {
+            Ok(size) => size,
+            Err(e) => {
+                debug_assert!(false, "{}", e);
+                return None;
+            }
+        };
+        // The children are pushed in the reverse order of the iteration, so that
+        // the stack pops them in the order.
+        for i in 0..=0xFEu8 {
+            let char_code_as_uint8 = if self.reversed { i } else { 0xFE - i };
             let next_index = base + char_code_as_uint8 as i32;
-            if next_index < 0 {
+            if next_index < 0 || next_index as usize >= base_check_size {
                 continue;
             }
             let check_at_next_index = match self.storage.check_at(next_index as usize) {
@@ -63,7 +162,7 @@ impl<T> Iterator for DoubleArrayIterator<'_, T> {
                 }
             };
             if check_at_next_index == char_code_as_uint8 {
-                let mut next_key_tail = if char_code_as_uint8 != double_array::KEY_TERMINATOR {
+                let mut next_key_tail = if char_code_as_uint8 != KEY_TERMINATOR {
                     vec![char_code_as_uint8]
                 } else {
                     Vec::new()
@@ -73,17 +172,34 @@ impl<T> Iterator for DoubleArrayIterator<'_, T> {
                     next_key.append(&mut next_key_tail);
                     next_key
                 };
+                if !self.may_contain_keys_with_prefix(&next_key) {
+                    continue;
+                }
+                if char_code_as_uint8 != KEY_TERMINATOR && !self.may_descend_to(&next_key) {
+                    continue;
+                }
                 self.base_check_index_key_stack
                     .push((next_index as usize, next_key));
             }
         }
 
-        self.next()
+        self.next_with_key()
+    }
+}
+
+impl<T> Iterator for DoubleArrayIterator<'_, T> {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_key().map(|(_, value)| value)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
     use crate::double_array::{DoubleArray, DoubleArrayElement};
 
     #[rustfmt::skip]
@@ -195,4 +311,59 @@ mod tests {
             assert_eq!(values, vec![84, 48]);
         }
     }
+
+    #[test]
+    fn next_with_key() {
+        {
+            let double_array = DoubleArray::<i32>::builder().build().unwrap();
+            let mut iterator = double_array.iter();
+
+            assert!(iterator.next_with_key().is_none());
+        }
+        {
+            let double_array = DoubleArray::<i32>::builder()
+                .elements(EXPECTED_VALUES3.to_vec())
+                .build()
+                .unwrap();
+            let mut iterator = double_array.iter();
+
+            {
+                let (key, value) = iterator.next_with_key().unwrap();
+                assert_eq!(key, b"SETA");
+                assert_eq!(value, 42);
+            }
+            {
+                let (key, value) = iterator.next_with_key().unwrap();
+                assert_eq!(key, b"UTIGOSI");
+                assert_eq!(value, 24);
+            }
+            {
+                let (key, value) = iterator.next_with_key().unwrap();
+                assert_eq!(key, b"UTO");
+                assert_eq!(value, 2424);
+            }
+            assert!(iterator.next_with_key().is_none());
+        }
+    }
+
+    #[test]
+    fn reversed() {
+        {
+            let double_array = DoubleArray::<i32>::builder().build().unwrap();
+            let mut iterator = double_array.iter().reversed();
+
+            assert!(iterator.next_with_key().is_none());
+        }
+        {
+            let double_array = DoubleArray::<i32>::builder()
+                .elements(EXPECTED_VALUES3.to_vec())
+                .build()
+                .unwrap();
+            let mut iterator = double_array.iter().reversed();
+            assert_eq!(iterator.next_with_key().unwrap(), (b"UTO".to_vec(), 2424));
+            assert_eq!(iterator.next_with_key().unwrap(), (b"UTIGOSI".to_vec(), 24));
+            assert_eq!(iterator.next_with_key().unwrap(), (b"SETA".to_vec(), 42));
+            assert!(iterator.next_with_key().is_none());
+        }
+    }
 }
@@ -0,0 +1,198 @@
+/*!
+ * An external sort of serialized keys.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+
+use anyhow::Result;
+
+// The records sorted by their keys. The keys are stored in a single buffer.
+pub(super) type SortedRecords = (Vec<u8>, Vec<(Range<usize>, i32)>);
+
+// Sorts the records of a serialized key and a value base, spilling the sorted
+// chunks to temporary files. The chunks are merged into memory at the end, since
+// the double array builder needs all the sorted records at once. The records
+// with the same key keep the order in which they are pushed, as a stable sort
+// does.
+//
+// Each record in a file is a key length, a key and a value base, where the
+// integers are in big endian.
+#[derive(Debug)]
+pub(super) struct ExternalSorter {
+    chunk_size: usize,
+    key_buffer: Vec<u8>,
+    records: Vec<(Range<usize>, i32)>,
+    runs: Vec<File>,
+}
+
+impl ExternalSorter {
+    pub(super) const fn new(chunk_size: usize) -> Self {
+        Self {
+            chunk_size,
+            key_buffer: Vec::new(),
+            records: Vec::new(),
+            runs: Vec::new(),
+        }
+    }
+
+    pub(super) fn push(&mut self, key: &[u8], value_base: i32) -> Result<()> {
+        let start = self.key_buffer.len();
+        self.key_buffer.extend_from_slice(key);
+        self.records
+            .push((start..self.key_buffer.len(), value_base));
+        if self.key_buffer.len() >= self.chunk_size {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn finish(mut self) -> Result<SortedRecords> {
+        if self.runs.is_empty() {
+            self.sort_chunk();
+            return Ok((self.key_buffer, self.records));
+        }
+        if !self.records.is_empty() {
+            self.spill()?;
+        }
+        Self::merge(self.runs)
+    }
+
+    #[cfg(test)]
+    fn run_count(&self) -> usize {
+        self.runs.len()
+    }
+
+    fn sort_chunk(&mut self) {
+        let key_buffer = &self.key_buffer;
+        self.records.sort_by(|(one, _), (another, _)| {
+            key_buffer[one.clone()].cmp(&key_buffer[another.clone()])
+        });
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        self.sort_chunk();
+        let mut writer = BufWriter::new(tempfile::tempfile()?);
+        for (range, value_base) in &self.records {
+            writer.write_all(&(range.len() as u32).to_be_bytes())?;
+            writer.write_all(&self.key_buffer[range.clone()])?;
+            writer.write_all(&value_base.to_be_bytes())?;
+        }
+        let mut file = writer.into_inner()?;
+        let _position = file.seek(SeekFrom::Start(0))?;
+        self.runs.push(file);
+        self.key_buffer.clear();
+        self.records.clear();
+        Ok(())
+    }
+
+    fn merge(runs: Vec<File>) -> Result<SortedRecords> {
+        let mut readers = runs.into_iter().map(BufReader::new).collect::<Vec<_>>();
+        let mut heads = BinaryHeap::with_capacity(readers.len());
+        for (i, reader) in readers.iter_mut().enumerate() {
+            if let Some((key, value_base)) = Self::read_record(reader)? {
+                heads.push(Reverse((key, i, value_base)));
+            }
+        }
+
+        let mut key_buffer = Vec::new();
+        let mut records = Vec::new();
+        while let Some(Reverse((key, i, value_base))) = heads.pop() {
+            let start = key_buffer.len();
+            key_buffer.extend_from_slice(&key);
+            records.push((start..key_buffer.len(), value_base));
+            if let Some((key, value_base)) = Self::read_record(&mut readers[i])? {
+                heads.push(Reverse((key, i, value_base)));
+            }
+        }
+        Ok((key_buffer, records))
+    }
+
+    fn read_record(reader: &mut BufReader<File>) -> Result<Option<(Vec<u8>, i32)>> {
+        if reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        let mut integer = [0u8; 4];
+        reader.read_exact(&mut integer)?;
+        let mut key = vec![0u8; u32::from_be_bytes(integer) as usize];
+        reader.read_exact(&mut key)?;
+        reader.read_exact(&mut integer)?;
+        Ok(Some((key, i32::from_be_bytes(integer))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted_pairs((key_buffer, records): SortedRecords) -> Vec<(Vec<u8>, i32)> {
+        records
+            .into_iter()
+            .map(|(range, value_base)| (key_buffer[range].to_vec(), value_base))
+            .collect()
+    }
+
+    #[test]
+    fn new() {
+        let _sorter = ExternalSorter::new(16);
+    }
+
+    #[test]
+    fn push() {
+        let mut sorter = ExternalSorter::new(8);
+
+        sorter.push(b"Tamana", 0).unwrap();
+        assert_eq!(sorter.run_count(), 0);
+        sorter.push(b"Uto", 1).unwrap();
+        assert_eq!(sorter.run_count(), 1);
+        sorter.push(b"Kumamoto", 2).unwrap();
+        assert_eq!(sorter.run_count(), 2);
+    }
+
+    #[test]
+    fn finish() {
+        let keys: [&[u8]; 7] = [
+            b"Uto",
+            b"Tamana",
+            b"Kumamoto",
+            b"Tamarai",
+            b"Tamana",
+            b"Arao",
+            b"Kikuchi",
+        ];
+        let mut expected = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| (key.to_vec(), i as i32))
+            .collect::<Vec<_>>();
+        expected.sort_by(|(one, _), (another, _)| one.cmp(another));
+
+        for chunk_size in [1, 4, 10, 1000] {
+            let mut sorter = ExternalSorter::new(chunk_size);
+            for (i, key) in keys.iter().enumerate() {
+                sorter.push(key, i as i32).unwrap();
+            }
+
+            assert_eq!(sorted_pairs(sorter.finish().unwrap()), expected);
+        }
+        {
+            let sorter = ExternalSorter::new(4);
+
+            assert!(sorted_pairs(sorter.finish().unwrap()).is_empty());
+        }
+    }
+
+    #[test]
+    fn run_count() {
+        let mut sorter = ExternalSorter::new(4);
+        assert_eq!(sorter.run_count(), 0);
+
+        sorter.push(b"Kumamoto", 0).unwrap();
+        assert_eq!(sorter.run_count(), 1);
+    }
+}
@@ -4,8 +4,10 @@
 
 use std::fs::File;
 use std::ops::Range;
+use std::time::SystemTime;
 
 use anyhow::Result;
+use fs4::fs_std::FileExt;
 use memmap2::Mmap;
 
 /**
@@ -18,15 +20,129 @@ pub enum FileMappingError {
      */
     #[error("the range is out of the mmap")]
     RangeOutOfMmap,
+
+    /**
+     * The file is locked by another process.
+     */
+    #[error("the file is locked by another process")]
+    Locked,
+
+    /**
+     * The file is modified or truncated after it is mapped.
+     */
+    #[error("the file is modified or truncated after it is mapped")]
+    FileChanged,
+}
+
+/**
+ * A file mapping builder.
+ */
+#[derive(Debug)]
+pub struct FileMappingBuilder {
+    file: File,
+    lock: Option<bool>,
+    verifies_on_access: bool,
+}
+
+impl FileMappingBuilder {
+    /**
+     * Takes a shared lock of the file before mapping it.
+     *
+     * Waits while another process holds an exclusive lock.
+     */
+    pub const fn shared_lock(mut self) -> Self {
+        self.lock = Some(true);
+        self
+    }
+
+    /**
+     * Takes a shared lock of the file before mapping it, without waiting.
+     *
+     * The build fails while another process holds an exclusive lock.
+     */
+    pub const fn try_shared_lock(mut self) -> Self {
+        self.lock = Some(false);
+        self
+    }
+
+    /**
+     * Verifies the file on each region access.
+     *
+     * The size and the modification time of the file are compared with the
+     * ones when it is mapped. This costs a system call for each access.
+     */
+    pub const fn verify_on_access(mut self) -> Self {
+        self.verifies_on_access = true;
+        self
+    }
+
+    /**
+     * Builds a file mapping.
+     *
+     * # Returns
+     * A file mapping.
+     *
+     * # Errors
+     * * When the file is locked by another process.
+     * * When it fails to lock the file.
+     * * When it fails to memory-map the file.
+     */
+    pub fn build(self) -> Result<FileMapping> {
+        match self.lock {
+            Some(true) => FileExt::lock_shared(&self.file)?,
+            Some(false) => {
+                if !FileExt::try_lock_shared(&self.file)? {
+                    return Err(FileMappingError::Locked.into());
+                }
+            }
+            None => {}
+        }
+        let generation = Generation::of(&self.file)?;
+        let mmap = unsafe { Mmap::map(&self.file)? };
+        Ok(FileMapping {
+            file: self.file,
+            mmap,
+            generation,
+            verifies_on_access: self.verifies_on_access,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Generation {
+    size: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Generation {
+    fn of(file: &File) -> Result<Self> {
+        let metadata = file.metadata()?;
+        Ok(Self {
+            size: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
 }
 
 /**
  * A file mapping.
+ *
+ * Several processes may map the same file. The locks are advisory: a process
+ * regenerating the file should hold an exclusive lock of it while writing, so
+ * that the readers taking a shared lock do not map a half-written file. A lock
+ * is held until the file mapping is dropped.
+ *
+ * Reading a mapped region of a truncated file may crash the process. Enable
+ * the verification on each access to detect a modification or a truncation
+ * after the file is mapped, or replace the file by renaming a new one instead
+ * of rewriting it in place.
  */
 #[derive(Debug)]
 pub struct FileMapping {
     file: File,
     mmap: Mmap,
+    generation: Generation,
+    verifies_on_access: bool,
 }
 
 impl FileMapping {
@@ -40,8 +156,24 @@ impl FileMapping {
      * * When it fails to memory-map the file.
      */
     pub fn new(file: File) -> Result<Self> {
-        let mmap = unsafe { Mmap::map(&file)? };
-        Ok(Self { file, mmap })
+        Self::builder(file).build()
+    }
+
+    /**
+     * Creates a file mapping builder.
+     *
+     * # Arguments
+     * * `file` - A file.
+     *
+     * # Returns
+     * A file mapping builder.
+     */
+    pub const fn builder(file: File) -> FileMappingBuilder {
+        FileMappingBuilder {
+            file,
+            lock: None,
+            verifies_on_access: false,
+        }
     }
 
     /**
@@ -64,6 +196,20 @@ impl FileMapping {
         self.mmap.len()
     }
 
+    /**
+     * Verifies that the file is not modified nor truncated after it is mapped.
+     *
+     * # Errors
+     * * When the file is modified or truncated.
+     * * When it fails to read the metadata of the file.
+     */
+    pub fn verify(&self) -> Result<()> {
+        if Generation::of(&self.file)? != self.generation {
+            return Err(FileMappingError::FileChanged.into());
+        }
+        Ok(())
+    }
+
     /**
      * Returns the region.
      *
@@ -75,11 +221,16 @@ impl FileMapping {
      *
      * # Errors
      * * When the range is out of the mmap.
+     * * When the verification on each access is enabled and the file is
+     *   modified or truncated.
      */
     pub fn region(&self, range: Range<usize>) -> Result<&[u8]> {
+        if self.verifies_on_access {
+            self.verify()?;
+        }
         self.mmap
             .get(range)
-            .ok_or(FileMappingError::RangeOutOfMmap.into())
+            .ok_or_else(|| FileMappingError::RangeOutOfMmap.into())
     }
 }
 
@@ -87,7 +238,7 @@ impl FileMapping {
 mod tests {
     use std::io::{Seek, SeekFrom, Write};
 
-    use tempfile::tempfile;
+    use tempfile::{tempfile, NamedTempFile};
 
     use super::*;
 
@@ -119,6 +270,40 @@ mod tests {
         assert!(file_mapping.is_ok());
     }
 
+    #[test]
+    fn builder() {
+        {
+            let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
+            let file_mapping = FileMapping::builder(file).shared_lock().build().unwrap();
+
+            let other_file = file_mapping.file().try_clone().unwrap();
+            assert!(FileMapping::builder(other_file)
+                .try_shared_lock()
+                .build()
+                .is_ok());
+        }
+        {
+            let mut writer = NamedTempFile::new().unwrap();
+            writer.write_all(SERIALIZED_FIXED_VALUE_SIZE).unwrap();
+            FileExt::lock_exclusive(writer.as_file()).unwrap();
+
+            let error = FileMapping::builder(writer.reopen().unwrap())
+                .try_shared_lock()
+                .build()
+                .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<FileMappingError>().unwrap(),
+                FileMappingError::Locked
+            ));
+
+            FileExt::unlock(writer.as_file()).unwrap();
+            assert!(FileMapping::builder(writer.reopen().unwrap())
+                .try_shared_lock()
+                .build()
+                .is_ok());
+        }
+    }
+
     #[test]
     fn file() {
         let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
@@ -138,6 +323,21 @@ mod tests {
         assert_eq!(file_mapping.size(), SERIALIZED_FIXED_VALUE_SIZE.len());
     }
 
+    #[test]
+    fn verify() {
+        let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
+        let file_mapping = FileMapping::new(file).unwrap();
+
+        assert!(file_mapping.verify().is_ok());
+
+        file_mapping.file().set_len(8).unwrap();
+        let error = file_mapping.verify().unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FileMappingError>().unwrap(),
+            FileMappingError::FileChanged
+        ));
+    }
+
     #[test]
     fn region() {
         let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
@@ -155,5 +355,20 @@ mod tests {
             let region = file_mapping.region(0..file_mapping.size() + 1);
             assert!(region.is_err());
         }
+        {
+            let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
+            let file_mapping = FileMapping::builder(file)
+                .verify_on_access()
+                .build()
+                .unwrap();
+            assert!(file_mapping.region(0..4).is_ok());
+
+            file_mapping.file().set_len(0).unwrap();
+            let error = file_mapping.region(0..4).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<FileMappingError>().unwrap(),
+                FileMappingError::FileChanged
+            ));
+        }
     }
 }
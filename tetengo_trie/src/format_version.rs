@@ -0,0 +1,102 @@
+/*!
+ * A format version.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+#[cfg(feature = "std")]
+use crate::memory_storage::{VALUE_COMPRESSION_FLAG, VALUE_INTERNING_FLAG};
+
+/**
+ * A format version of serialized storages.
+ *
+ * The serialized storage has no dedicated version field. The version is
+ * identified by the flags in the upper 8 bits of the fixed value size field.
+ */
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FormatVersion(u32);
+
+impl FormatVersion {
+    /// The version 1, which has no format flags.
+    pub const V1: Self = Self(1);
+
+    /// The version 2, which may have the value interning flag.
+    pub const V2: Self = Self(2);
+
+    /// The version 3, which may have the value compression flag.
+    pub const V3: Self = Self(3);
+
+    /// The latest version.
+    pub const LATEST: Self = Self::V3;
+
+    /**
+     * Returns the version number.
+     *
+     * # Returns
+     * The version number.
+     */
+    pub const fn number(&self) -> u32 {
+        self.0
+    }
+
+    #[cfg(feature = "std")]
+    pub(super) const fn of_fixed_value_size_field(fixed_value_size_field: u32) -> Option<Self> {
+        match fixed_value_size_field & FORMAT_FLAG_MASK {
+            0 => Some(Self::V1),
+            VALUE_INTERNING_FLAG => Some(Self::V2),
+            flags if flags & !(VALUE_INTERNING_FLAG | VALUE_COMPRESSION_FLAG) == 0 => {
+                Some(Self::V3)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for FormatVersion {
+    fn default() -> Self {
+        Self::LATEST
+    }
+}
+
+/// The mask of the format flags in the fixed value size field.
+pub(super) const FORMAT_FLAG_MASK: u32 = 0xFF000000;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn number() {
+        assert_eq!(FormatVersion::V1.number(), 1);
+        assert_eq!(FormatVersion::V2.number(), 2);
+        assert_eq!(FormatVersion::V3.number(), 3);
+        assert_eq!(FormatVersion::LATEST, FormatVersion::V3);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn of_fixed_value_size_field() {
+        assert_eq!(
+            FormatVersion::of_fixed_value_size_field(0x00000004),
+            Some(FormatVersion::V1)
+        );
+        assert_eq!(
+            FormatVersion::of_fixed_value_size_field(0x80000004),
+            Some(FormatVersion::V2)
+        );
+        assert_eq!(
+            FormatVersion::of_fixed_value_size_field(0x40000004),
+            Some(FormatVersion::V3)
+        );
+        assert_eq!(
+            FormatVersion::of_fixed_value_size_field(0xC0000004),
+            Some(FormatVersion::V3)
+        );
+        assert_eq!(FormatVersion::of_fixed_value_size_field(0x20000000), None);
+    }
+
+    #[test]
+    fn default() {
+        assert_eq!(FormatVersion::default(), FormatVersion::LATEST);
+    }
+}
@@ -4,8 +4,9 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use std::marker::PhantomData;
-use std::ops;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::ops;
 
 use anyhow::Result;
 
@@ -75,7 +76,36 @@ impl<Object: Integer<Object>> Serializer for IntegerSerializer<Object> {
     }
 
     fn serialize(&self, object: &Self::Object<'_>) -> Vec<u8> {
-        to_bytes(object, self.fe_escape)
+        let mut serialized = Vec::with_capacity(size_of::<Object>());
+        self.serialize_into(object, &mut serialized);
+        serialized
+    }
+
+    fn serialize_into(&self, object: &Self::Object<'_>, buffer: &mut Vec<u8>) {
+        to_bytes_into(object, self.fe_escape, buffer);
+    }
+
+    fn serialized_len(&self, object: &Self::Object<'_>) -> usize {
+        if !self.fe_escape {
+            return size_of::<Object>();
+        }
+        let unescaped = to_unescaped_bytes(object);
+        size_of::<Object>()
+            + unescaped[..size_of::<Object>()]
+                .iter()
+                .filter(|&&byte| byte == 0xFDu8 || byte == 0xFEu8)
+                .count()
+    }
+
+    fn escaped_byte_count(&self, object: &Self::Object<'_>) -> usize {
+        if !self.fe_escape {
+            return 0;
+        }
+        let unescaped = to_unescaped_bytes(object);
+        unescaped[..size_of::<Object>()]
+            .iter()
+            .filter(|&&byte| byte == 0x00u8 || byte == 0xFDu8 || byte == 0xFEu8)
+            .count()
     }
 }
 
@@ -136,41 +166,34 @@ impl<Object: Integer<Object>> Deserializer for IntegerDeserializer<Object> {
     }
 }
 
-fn to_bytes<Object: Integer<Object>>(object: &Object, fe_escape: bool) -> Vec<u8> {
-    if fe_escape {
-        to_bytes_with_escape(object)
-    } else {
-        to_bytes_without_escape(object)
-    }
-}
-
-fn to_bytes_with_escape<Object: Integer<Object>>(object: &Object) -> Vec<u8> {
-    to_bytes_without_escape(object)
-        .into_iter()
-        .flat_map(|b| {
-            if b == 0x00u8 {
-                vec![0xFEu8]
-            } else if b == 0xFDu8 || b == 0xFEu8 {
-                vec![0xFDu8, b]
-            } else {
-                vec![b]
-            }
-        })
-        .collect()
-}
-
-fn to_bytes_without_escape<Object: Integer<Object>>(object: &Object) -> Vec<u8> {
-    let mut bytes = Vec::with_capacity(size_of::<Object>());
+fn to_unescaped_bytes<Object: Integer<Object>>(object: &Object) -> [u8; size_of::<u128>()] {
+    let mut unescaped = [0u8; size_of::<u128>()];
     let mut object = *object;
-    for _ in 0..size_of::<Object>() {
+    for byte in unescaped[..size_of::<Object>()].iter_mut().rev() {
         let byte_object = object & Object::from(0xFFu8);
         let u128_object: i128 = byte_object.into();
-        let u8_object = u128_object as u8;
-        bytes.push(u8_object);
+        *byte = u128_object as u8;
         object >>= 8;
     }
-    bytes.reverse();
-    bytes
+    unescaped
+}
+
+fn to_bytes_into<Object: Integer<Object>>(object: &Object, fe_escape: bool, bytes: &mut Vec<u8>) {
+    let unescaped = to_unescaped_bytes(object);
+    let unescaped = &unescaped[..size_of::<Object>()];
+    if !fe_escape {
+        bytes.extend_from_slice(unescaped);
+        return;
+    }
+    for &b in unescaped.iter() {
+        if b == 0x00u8 {
+            bytes.push(0xFEu8);
+        } else if b == 0xFDu8 || b == 0xFEu8 {
+            bytes.extend_from_slice(&[0xFDu8, b]);
+        } else {
+            bytes.push(b);
+        }
+    }
 }
 
 fn from_bytes<Object: Integer<Object>>(serialized: &[u8], fe_escape: bool) -> Result<Object> {
@@ -286,7 +309,9 @@ impl DeserializerOf<i128> for () {
 
 #[cfg(test)]
 mod tests {
-    use crate::double_array::KEY_TERMINATOR;
+    use alloc::vec;
+
+    use crate::serializer::KEY_TERMINATOR;
 
     use super::*;
 
@@ -332,6 +357,41 @@ mod tests {
         }
     }
 
+    #[test]
+    fn serialized_len() {
+        {
+            let serializer = <() as SerializerOf<u32>>::Type::new(false);
+
+            assert_eq!(serializer.serialized_len(&0xFCFDFEFF), 4);
+        }
+        {
+            let serializer = <() as SerializerOf<u32>>::Type::new(true);
+
+            assert_eq!(serializer.serialized_len(&0xFCFDFEFF), 6);
+            assert_eq!(
+                serializer.serialized_len(&0xFCFDFEFF),
+                serializer.serialize(&0xFCFDFEFF).len()
+            );
+            assert_eq!(serializer.serialized_len(&0x001234AB), 4);
+        }
+    }
+
+    #[test]
+    fn escaped_byte_count() {
+        {
+            let serializer = <() as SerializerOf<u32>>::Type::new(false);
+
+            assert_eq!(serializer.escaped_byte_count(&0xFCFDFEFF), 0);
+        }
+        {
+            let serializer = <() as SerializerOf<u32>>::Type::new(true);
+
+            assert_eq!(serializer.escaped_byte_count(&0xFCFDFEFF), 2);
+            assert_eq!(serializer.escaped_byte_count(&0x001234AB), 1);
+            assert_eq!(serializer.escaped_byte_count(&0x01020304), 0);
+        }
+    }
+
     #[test]
     fn deserialize() {
         {
@@ -0,0 +1,122 @@
+/*!
+ * Key diagnostics.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+/**
+ * Key diagnostics.
+ *
+ * Describes the serialized keys of a trie build, so that the caller can
+ * estimate the storage size and detect the keys expanded by escaping.
+ */
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct KeyDiagnostics {
+    key_count: usize,
+    escaped_byte_count: usize,
+    total_serialized_key_len: usize,
+    max_serialized_key_len: usize,
+}
+
+impl KeyDiagnostics {
+    /**
+     * Creates key diagnostics.
+     *
+     * # Arguments
+     * * `key_count`                - The count of the keys.
+     * * `escaped_byte_count`       - The count of the escaped bytes.
+     * * `total_serialized_key_len` - The total length of the serialized keys.
+     * * `max_serialized_key_len`   - The max length of the serialized keys.
+     */
+    pub const fn new(
+        key_count: usize,
+        escaped_byte_count: usize,
+        total_serialized_key_len: usize,
+        max_serialized_key_len: usize,
+    ) -> Self {
+        Self {
+            key_count,
+            escaped_byte_count,
+            total_serialized_key_len,
+            max_serialized_key_len,
+        }
+    }
+
+    /**
+     * Returns the count of the keys.
+     *
+     * # Returns
+     * The count of the keys.
+     */
+    pub const fn key_count(&self) -> usize {
+        self.key_count
+    }
+
+    /**
+     * Returns the count of the escaped bytes.
+     *
+     * # Returns
+     * The count of the bytes of all the keys which are replaced or prefixed by
+     * the key serializer.
+     */
+    pub const fn escaped_byte_count(&self) -> usize {
+        self.escaped_byte_count
+    }
+
+    /**
+     * Returns the total length of the serialized keys.
+     *
+     * # Returns
+     * The total length of the serialized keys in bytes.
+     */
+    pub const fn total_serialized_key_len(&self) -> usize {
+        self.total_serialized_key_len
+    }
+
+    /**
+     * Returns the max length of the serialized keys.
+     *
+     * # Returns
+     * The max length of the serialized keys in bytes. Or 0 when there is no
+     * key.
+     */
+    pub const fn max_serialized_key_len(&self) -> usize {
+        self.max_serialized_key_len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new() {
+        let _diagnostics = KeyDiagnostics::new(3, 1, 24, 10);
+    }
+
+    #[test]
+    fn key_count() {
+        assert_eq!(KeyDiagnostics::new(3, 1, 24, 10).key_count(), 3);
+    }
+
+    #[test]
+    fn escaped_byte_count() {
+        assert_eq!(KeyDiagnostics::new(3, 1, 24, 10).escaped_byte_count(), 1);
+    }
+
+    #[test]
+    fn total_serialized_key_len() {
+        assert_eq!(
+            KeyDiagnostics::new(3, 1, 24, 10).total_serialized_key_len(),
+            24
+        );
+    }
+
+    #[test]
+    fn max_serialized_key_len() {
+        assert_eq!(
+            KeyDiagnostics::new(3, 1, 24, 10).max_serialized_key_len(),
+            10
+        );
+    }
+}
@@ -0,0 +1,772 @@
+/*!
+ * A lexicon source.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::collections::VecDeque;
+use std::fmt::{self, Debug, Formatter};
+use std::io::BufRead;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use anyhow::Result;
+
+/**
+ * A lexicon source error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum LexiconSourceError {
+    /**
+     * A record is broken.
+     */
+    #[error("the record at line {line} is broken")]
+    BrokenRecord {
+        /**
+         * The line number, starting with 1.
+         */
+        line: usize,
+    },
+
+    /**
+     * A record has no key.
+     */
+    #[error("the record at line {line} has no key")]
+    NoKey {
+        /**
+         * The line number, starting with 1.
+         */
+        line: usize,
+    },
+}
+
+/**
+ * A lexicon record.
+ *
+ * A pair of a key and a payload, with the position of the row in the source
+ * where it comes from.
+ */
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct LexiconRecord {
+    key: String,
+    payload: Vec<u8>,
+    offset: usize,
+    length: usize,
+}
+
+impl LexiconRecord {
+    /**
+     * Creates a lexicon record.
+     *
+     * # Arguments
+     * * `key`     - A key.
+     * * `payload` - A payload.
+     * * `offset`  - The offset of the row in the source.
+     * * `length`  - The length of the row in the source, including the line break.
+     */
+    pub const fn new(key: String, payload: Vec<u8>, offset: usize, length: usize) -> Self {
+        Self {
+            key,
+            payload,
+            offset,
+            length,
+        }
+    }
+
+    /**
+     * Returns the key.
+     *
+     * # Returns
+     * The key.
+     */
+    pub fn key(&self) -> &str {
+        self.key.as_str()
+    }
+
+    /**
+     * Returns the payload.
+     *
+     * # Returns
+     * The payload.
+     */
+    pub fn payload(&self) -> &[u8] {
+        self.payload.as_slice()
+    }
+
+    /**
+     * Returns the offset of the row in the source.
+     *
+     * # Returns
+     * The offset in bytes.
+     */
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /**
+     * Returns the length of the row in the source.
+     *
+     * # Returns
+     * The length in bytes, including the line break.
+     */
+    pub const fn length(&self) -> usize {
+        self.length
+    }
+}
+
+/**
+ * A lexicon source.
+ *
+ * Reads the records of a lexicon file such as UniDic lex.csv or Sudachi CSV.
+ * A dictionary builder consumes the records without knowing the document
+ * format.
+ */
+pub trait LexiconSource: Debug {
+    /**
+     * Returns the next record.
+     *
+     * A row of the source may yield several records with different keys. They
+     * share the payload and the position of the row.
+     *
+     * # Returns
+     * The next record. Or None when the source is exhausted.
+     *
+     * # Errors
+     * * When it fails to read the source.
+     * * When a record is broken.
+     */
+    fn next_record(&mut self) -> Result<Option<LexiconRecord>>;
+}
+
+/**
+ * A key selector.
+ *
+ * Selects the keys from the columns of a row. Returns None when the row is
+ * broken.
+ */
+pub type KeySelector = Box<dyn Fn(&[&str]) -> Option<Vec<String>>>;
+
+/**
+ * A CSV lexicon source.
+ *
+ * The payload of the records is the row without the line break. A column
+ * enclosed in double quotes may contain commas, but no double quote.
+ *
+ * # Type Parameters
+ * * `R` - A reader type.
+ */
+#[derive(Debug)]
+pub struct CsvLexiconSource<R> {
+    delimited: DelimitedSource<R>,
+}
+
+impl<R: BufRead + Debug> CsvLexiconSource<R> {
+    /**
+     * Creates a CSV lexicon source.
+     *
+     * # Arguments
+     * * `reader`      - A reader.
+     * * `key_columns` - The indices of the key columns.
+     */
+    pub fn new(reader: R, key_columns: &[usize]) -> Self {
+        Self::new_with_key_selector(reader, columns_selector(key_columns))
+    }
+
+    /**
+     * Creates a CSV lexicon source with a key selector.
+     *
+     * # Arguments
+     * * `reader`       - A reader.
+     * * `key_selector` - A key selector.
+     */
+    pub fn new_with_key_selector(reader: R, key_selector: KeySelector) -> Self {
+        Self {
+            delimited: DelimitedSource::new(reader, key_selector),
+        }
+    }
+}
+
+impl<R: BufRead + Debug> LexiconSource for CsvLexiconSource<R> {
+    fn next_record(&mut self) -> Result<Option<LexiconRecord>> {
+        self.delimited.next_record(|row| split_csv(row, ','))
+    }
+}
+
+/**
+ * A TSV lexicon source.
+ *
+ * The payload of the records is the row without the line break. The columns
+ * are separated by tabs as they are.
+ *
+ * # Type Parameters
+ * * `R` - A reader type.
+ */
+#[derive(Debug)]
+pub struct TsvLexiconSource<R> {
+    delimited: DelimitedSource<R>,
+}
+
+impl<R: BufRead + Debug> TsvLexiconSource<R> {
+    /**
+     * Creates a TSV lexicon source.
+     *
+     * # Arguments
+     * * `reader`      - A reader.
+     * * `key_columns` - The indices of the key columns.
+     */
+    pub fn new(reader: R, key_columns: &[usize]) -> Self {
+        Self::new_with_key_selector(reader, columns_selector(key_columns))
+    }
+
+    /**
+     * Creates a TSV lexicon source with a key selector.
+     *
+     * # Arguments
+     * * `reader`       - A reader.
+     * * `key_selector` - A key selector.
+     */
+    pub fn new_with_key_selector(reader: R, key_selector: KeySelector) -> Self {
+        Self {
+            delimited: DelimitedSource::new(reader, key_selector),
+        }
+    }
+}
+
+impl<R: BufRead + Debug> LexiconSource for TsvLexiconSource<R> {
+    fn next_record(&mut self) -> Result<Option<LexiconRecord>> {
+        self.delimited
+            .next_record(|row| row.split('\t').collect::<Vec<_>>())
+    }
+}
+
+/**
+ * A JSON Lines lexicon source.
+ *
+ * Each row is a JSON object. The keys are the string members with the given
+ * names at the top level of the object. The other members are not examined
+ * except for the syntax. The payload of the records is the row without the
+ * line break.
+ *
+ * # Type Parameters
+ * * `R` - A reader type.
+ */
+#[derive(Debug)]
+pub struct JsonlLexiconSource<R> {
+    rows: RowReader<R>,
+    key_fields: Vec<String>,
+}
+
+impl<R: BufRead + Debug> JsonlLexiconSource<R> {
+    /**
+     * Creates a JSON Lines lexicon source.
+     *
+     * # Arguments
+     * * `reader`     - A reader.
+     * * `key_fields` - The names of the key members.
+     */
+    pub fn new(reader: R, key_fields: &[&str]) -> Self {
+        Self {
+            rows: RowReader::new(reader),
+            key_fields: key_fields.iter().map(|field| field.to_string()).collect(),
+        }
+    }
+}
+
+impl<R: BufRead + Debug> LexiconSource for JsonlLexiconSource<R> {
+    fn next_record(&mut self) -> Result<Option<LexiconRecord>> {
+        let key_fields = &self.key_fields;
+        self.rows.next_record(|row, line| {
+            let Some(members) = string_members_of(row) else {
+                return Err(LexiconSourceError::BrokenRecord { line }.into());
+            };
+            let keys = key_fields
+                .iter()
+                .filter_map(|field| {
+                    members
+                        .iter()
+                        .find(|(name, _)| name == field)
+                        .map(|(_, value)| value.clone())
+                })
+                .collect::<Vec<_>>();
+            if keys.is_empty() {
+                return Err(LexiconSourceError::NoKey { line }.into());
+            }
+            Ok(keys)
+        })
+    }
+}
+
+struct DelimitedSource<R> {
+    rows: RowReader<R>,
+    key_selector: KeySelector,
+}
+
+impl<R: BufRead + Debug> DelimitedSource<R> {
+    fn new(reader: R, key_selector: KeySelector) -> Self {
+        Self {
+            rows: RowReader::new(reader),
+            key_selector,
+        }
+    }
+
+    fn next_record(&mut self, split: impl Fn(&str) -> Vec<&str>) -> Result<Option<LexiconRecord>> {
+        let key_selector = &self.key_selector;
+        self.rows.next_record(|row, line| {
+            let columns = split(row);
+            let Some(keys) = key_selector(&columns) else {
+                return Err(LexiconSourceError::BrokenRecord { line }.into());
+            };
+            Ok(keys)
+        })
+    }
+}
+
+impl<R: Debug> Debug for DelimitedSource<R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DelimitedSource")
+            .field("rows", &self.rows)
+            .field("key_selector", &"KeySelector")
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+struct RowReader<R> {
+    reader: R,
+    offset: usize,
+    line: usize,
+    pending: VecDeque<LexiconRecord>,
+}
+
+impl<R: BufRead> RowReader<R> {
+    const fn new(reader: R) -> Self {
+        Self {
+            reader,
+            offset: 0,
+            line: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn next_record(
+        &mut self,
+        mut keys_of: impl FnMut(&str, usize) -> Result<Vec<String>>,
+    ) -> Result<Option<LexiconRecord>> {
+        loop {
+            if let Some(record) = self.pending.pop_front() {
+                return Ok(Some(record));
+            }
+
+            let mut bytes = Vec::new();
+            let length = self.reader.read_until(b'\n', &mut bytes)?;
+            if length == 0 {
+                return Ok(None);
+            }
+            let offset = self.offset;
+            self.offset += length;
+            self.line += 1;
+
+            let row = bytes.strip_suffix(b"\n").unwrap_or(&bytes);
+            let row = row.strip_suffix(b"\r").unwrap_or(row);
+            if row.is_empty() {
+                continue;
+            }
+            let Ok(row_str) = std::str::from_utf8(row) else {
+                return Err(LexiconSourceError::BrokenRecord { line: self.line }.into());
+            };
+
+            let mut keys = keys_of(row_str, self.line)?;
+            keys.retain(|key| !key.is_empty());
+            if keys.is_empty() {
+                return Err(LexiconSourceError::NoKey { line: self.line }.into());
+            }
+            for (i, key) in keys.iter().enumerate() {
+                if keys[..i].contains(key) {
+                    continue;
+                }
+                self.pending.push_back(LexiconRecord::new(
+                    key.clone(),
+                    row.to_vec(),
+                    offset,
+                    length,
+                ));
+            }
+        }
+    }
+}
+
+fn columns_selector(key_columns: &[usize]) -> KeySelector {
+    let key_columns = key_columns.to_vec();
+    Box::new(move |columns| {
+        key_columns
+            .iter()
+            .map(|&index| columns.get(index).map(|column| column.to_string()))
+            .collect()
+    })
+}
+
+fn split_csv(string: &str, delimiter: char) -> Vec<&str> {
+    let mut elements = Vec::new();
+
+    let mut first = 0usize;
+    loop {
+        if string[first..].starts_with('"') {
+            if let Some(length) = string[first + 1..].find('"') {
+                let last = first + 1 + length;
+                elements.push(&string[first + 1..last]);
+                if last + 1 >= string.len() {
+                    break;
+                }
+                first = last + 2;
+            } else {
+                elements.push(&string[first + 1..]);
+                break;
+            }
+        } else if let Some(length) = string[first..].find(delimiter) {
+            let last = first + length;
+            elements.push(&string[first..last]);
+            first = last + 1;
+        } else {
+            elements.push(&string[first..]);
+            break;
+        }
+    }
+
+    elements
+}
+
+fn string_members_of(object: &str) -> Option<Vec<(String, String)>> {
+    let mut chars = object.chars().peekable();
+    let mut members = Vec::new();
+
+    skip_whitespaces(&mut chars);
+    if chars.next()? != '{' {
+        return None;
+    }
+    skip_whitespaces(&mut chars);
+    if chars.peek() == Some(&'}') {
+        let _closing = chars.next();
+    } else {
+        loop {
+            skip_whitespaces(&mut chars);
+            if chars.next()? != '"' {
+                return None;
+            }
+            let name = parse_string(&mut chars)?;
+            skip_whitespaces(&mut chars);
+            if chars.next()? != ':' {
+                return None;
+            }
+            skip_whitespaces(&mut chars);
+            if chars.peek() == Some(&'"') {
+                let _opening = chars.next();
+                let value = parse_string(&mut chars)?;
+                members.push((name, value));
+            } else {
+                skip_value(&mut chars)?;
+            }
+            skip_whitespaces(&mut chars);
+            match chars.next()? {
+                ',' => continue,
+                '}' => break,
+                _ => return None,
+            }
+        }
+    }
+    skip_whitespaces(&mut chars);
+    if chars.next().is_some() {
+        return None;
+    }
+
+    Some(members)
+}
+
+fn skip_whitespaces(chars: &mut Peekable<Chars<'_>>) {
+    while chars
+        .next_if(|c| matches!(c, ' ' | '\t' | '\r' | '\n'))
+        .is_some()
+    {}
+}
+
+fn parse_string(chars: &mut Peekable<Chars<'_>>) -> Option<String> {
+    let mut string = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(string),
+            '\\' => match chars.next()? {
+                '"' => string.push('"'),
+                '\\' => string.push('\\'),
+                '/' => string.push('/'),
+                'b' => string.push('\u{08}'),
+                'f' => string.push('\u{0C}'),
+                'n' => string.push('\n'),
+                'r' => string.push('\r'),
+                't' => string.push('\t'),
+                'u' => {
+                    let unit = parse_hex4(chars)?;
+                    if (0xD800..0xDC00).contains(&unit) {
+                        if chars.next()? != '\\' || chars.next()? != 'u' {
+                            return None;
+                        }
+                        let low_unit = parse_hex4(chars)?;
+                        string.push(char::decode_utf16([unit, low_unit]).next()?.ok()?);
+                    } else {
+                        string.push(char::decode_utf16([unit]).next()?.ok()?);
+                    }
+                }
+                _ => return None,
+            },
+            c if c.is_control() => return None,
+            c => string.push(c),
+        }
+    }
+}
+
+fn parse_hex4(chars: &mut Peekable<Chars<'_>>) -> Option<u16> {
+    let hex = chars.by_ref().take(4).collect::<String>();
+    if hex.len() != 4 {
+        return None;
+    }
+    u16::from_str_radix(&hex, 16).ok()
+}
+
+fn skip_value(chars: &mut Peekable<Chars<'_>>) -> Option<()> {
+    let mut depth = 0usize;
+    let mut skipped = false;
+    while let Some(&c) = chars.peek() {
+        match c {
+            '"' => {
+                let _opening = chars.next();
+                let _string = parse_string(chars)?;
+            }
+            '{' | '[' => {
+                let _opening = chars.next();
+                depth += 1;
+            }
+            '}' | ']' if depth > 0 => {
+                let _closing = chars.next();
+                depth -= 1;
+            }
+            ',' | '}' | ']' | ' ' | '\t' | '\r' | '\n' if depth == 0 => break,
+            _ => {
+                let _c = chars.next();
+            }
+        }
+        skipped = true;
+    }
+    (skipped && depth == 0).then_some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn records_of(source: &mut dyn LexiconSource) -> Vec<LexiconRecord> {
+        let mut records = Vec::new();
+        while let Some(record) = source.next_record().unwrap() {
+            records.push(record);
+        }
+        records
+    }
+
+    fn keys_of(records: &[LexiconRecord]) -> Vec<&str> {
+        records.iter().map(LexiconRecord::key).collect()
+    }
+
+    mod lexicon_record {
+        use super::*;
+
+        #[test]
+        fn new() {
+            let _record = LexiconRecord::new(String::from("Kumamoto"), b"payload".to_vec(), 42, 24);
+        }
+
+        #[test]
+        fn key() {
+            let record = LexiconRecord::new(String::from("Kumamoto"), b"payload".to_vec(), 42, 24);
+
+            assert_eq!(record.key(), "Kumamoto");
+        }
+
+        #[test]
+        fn payload() {
+            let record = LexiconRecord::new(String::from("Kumamoto"), b"payload".to_vec(), 42, 24);
+
+            assert_eq!(record.payload(), b"payload");
+        }
+
+        #[test]
+        fn offset() {
+            let record = LexiconRecord::new(String::from("Kumamoto"), b"payload".to_vec(), 42, 24);
+
+            assert_eq!(record.offset(), 42);
+        }
+
+        #[test]
+        fn length() {
+            let record = LexiconRecord::new(String::from("Kumamoto"), b"payload".to_vec(), 42, 24);
+
+            assert_eq!(record.length(), 24);
+        }
+    }
+
+    mod csv_lexicon_source {
+        use super::*;
+
+        const CSV: &str = "\
+みず,10,11,100,水\n\
+\n\
+\",\",20,21,300,\",\"\r\n\
+き,10,11,100,木";
+
+        #[test]
+        fn new() {
+            let _source = CsvLexiconSource::new(Cursor::new(CSV), &[0, 4]);
+        }
+
+        #[test]
+        fn new_with_key_selector() {
+            let mut source = CsvLexiconSource::new_with_key_selector(
+                Cursor::new(CSV),
+                Box::new(|columns| {
+                    if columns.len() != 5 {
+                        return None;
+                    }
+                    Some(vec![columns[4].to_string()])
+                }),
+            );
+
+            assert_eq!(keys_of(&records_of(&mut source)), ["水", ",", "木"]);
+        }
+
+        #[test]
+        fn next_record() {
+            {
+                let mut source = CsvLexiconSource::new(Cursor::new(CSV), &[0, 4]);
+
+                let records = records_of(&mut source);
+                assert_eq!(keys_of(&records), ["みず", "水", ",", "き", "木"]);
+                assert_eq!(records[0].payload(), "みず,10,11,100,水".as_bytes());
+                assert_eq!(records[0].offset(), 0);
+                assert_eq!(records[0].length(), 21);
+                assert_eq!(records[1].offset(), 0);
+                assert_eq!(records[2].payload(), b"\",\",20,21,300,\",\"");
+                assert_eq!(records[2].offset(), 22);
+                assert_eq!(records[2].length(), 19);
+                assert_eq!(records[3].offset(), 41);
+                assert_eq!(records[3].length(), 17);
+            }
+            {
+                let mut source = CsvLexiconSource::new(Cursor::new("みず,10\n"), &[0, 4]);
+
+                let error = source.next_record().unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<LexiconSourceError>().unwrap(),
+                    LexiconSourceError::BrokenRecord { line: 1 }
+                ));
+            }
+            {
+                let mut source = CsvLexiconSource::new(Cursor::new(b"\xFF,10\n"), &[0]);
+
+                assert!(source.next_record().is_err());
+            }
+        }
+    }
+
+    mod tsv_lexicon_source {
+        use super::*;
+
+        const TSV: &str = "みず\t水\n,\t,\n";
+
+        #[test]
+        fn new() {
+            let _source = TsvLexiconSource::new(Cursor::new(TSV), &[0, 1]);
+        }
+
+        #[test]
+        fn new_with_key_selector() {
+            let mut source = TsvLexiconSource::new_with_key_selector(
+                Cursor::new(TSV),
+                Box::new(|columns| Some(vec![columns[1].to_uppercase()])),
+            );
+
+            assert_eq!(keys_of(&records_of(&mut source)), ["水", ","]);
+        }
+
+        #[test]
+        fn next_record() {
+            {
+                let mut source = TsvLexiconSource::new(Cursor::new(TSV), &[0, 1]);
+
+                let records = records_of(&mut source);
+                assert_eq!(keys_of(&records), ["みず", "水", ","]);
+                assert_eq!(records[2].payload(), b",\t,");
+                assert_eq!(records[2].offset(), 11);
+                assert_eq!(records[2].length(), 4);
+            }
+            {
+                let mut source = TsvLexiconSource::new(Cursor::new("\t\n"), &[0]);
+
+                let error = source.next_record().unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<LexiconSourceError>().unwrap(),
+                    LexiconSourceError::NoKey { line: 1 }
+                ));
+            }
+        }
+    }
+
+    mod jsonl_lexicon_source {
+        use super::*;
+
+        const JSONL: &str = concat!(
+            r#"{"surface": "みず", "reading": "ミズ", "cost": 100, "pos": ["名詞", {"a": "}"}]}"#,
+            "\n",
+            r#"{"surface":"\"水😀\"","reading":null}"#,
+            "\n",
+        );
+
+        #[test]
+        fn new() {
+            let _source = JsonlLexiconSource::new(Cursor::new(JSONL), &["surface", "reading"]);
+        }
+
+        #[test]
+        fn next_record() {
+            {
+                let mut source =
+                    JsonlLexiconSource::new(Cursor::new(JSONL), &["surface", "reading"]);
+
+                let records = records_of(&mut source);
+                assert_eq!(keys_of(&records), ["みず", "ミズ", "\"水😀\""]);
+                assert_eq!(
+                    records[1].payload(),
+                    JSONL.lines().next().unwrap().as_bytes()
+                );
+                assert_eq!(records[2].offset(), JSONL.lines().next().unwrap().len() + 1);
+            }
+            {
+                let mut source =
+                    JsonlLexiconSource::new(Cursor::new(r#"{"cost": 1}"#), &["surface"]);
+
+                let error = source.next_record().unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<LexiconSourceError>().unwrap(),
+                    LexiconSourceError::NoKey { line: 1 }
+                ));
+            }
+            {
+                let mut source =
+                    JsonlLexiconSource::new(Cursor::new(r#"{"surface": "みず""#), &["surface"]);
+
+                let error = source.next_record().unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<LexiconSourceError>().unwrap(),
+                    LexiconSourceError::BrokenRecord { line: 1 }
+                ));
+            }
+        }
+    }
+}
@@ -3,33 +3,101 @@
 #![doc = "```rust"]
 #![doc = include_str!("../tests/usage.rs")]
 #![doc = "```"]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+pub mod build_checkpoint;
+pub mod byte_writer;
+pub mod bytes_serializer;
+pub mod completion_hints;
+pub mod darts;
+pub mod dictionary_set;
+#[cfg(feature = "std")]
 pub mod file_mapping;
+pub mod format_version;
 pub mod integer_serializer;
+pub mod key_diagnostics;
+#[cfg(feature = "std")]
+pub mod lexicon_source;
+#[cfg(feature = "std")]
+pub mod load_report;
+#[cfg(feature = "metrics")]
+pub mod lookup_metrics;
 pub mod memory_storage;
+#[cfg(feature = "std")]
+pub mod mmap_mut_storage;
+#[cfg(feature = "std")]
 pub mod mmap_storage;
+#[cfg(feature = "std")]
+pub mod persistent_memory_storage;
 pub mod serializer;
 pub mod shared_storage;
 pub mod storage;
 pub mod string_serializer;
+#[cfg(feature = "std")]
+pub mod swappable_trie;
 pub mod trie;
+pub mod trie_cursor;
 pub mod trie_iterator;
+pub mod trie_range_iterator;
+pub mod trie_snapshot;
+pub mod trie_suffix_iterator;
+pub mod value_compression;
 pub mod value_serializer;
 
 mod double_array;
 mod double_array_builder;
 mod double_array_iterator;
+#[cfg(feature = "std")]
+mod external_sort;
 
+pub use build_checkpoint::{BuildCheckpoint, BuildCheckpointError};
+pub use byte_writer::ByteWriter;
+pub use bytes_serializer::{BytesDeserializationError, BytesDeserializer, BytesSerializer};
+pub use completion_hints::CompletionHints;
+pub use darts::{DartsDoubleArray, DartsError, DartsUnit};
+pub use dictionary_set::{DictionarySet, DictionarySetError};
+#[cfg(feature = "std")]
 pub use file_mapping::{FileMapping, FileMappingError};
+pub use format_version::FormatVersion;
 pub use integer_serializer::{IntegerDeserializer, IntegerSerializer};
-pub use memory_storage::MemoryStorage;
+pub use key_diagnostics::KeyDiagnostics;
+#[cfg(feature = "std")]
+pub use lexicon_source::{
+    CsvLexiconSource, JsonlLexiconSource, KeySelector, LexiconRecord, LexiconSource,
+    LexiconSourceError, TsvLexiconSource,
+};
+#[cfg(feature = "std")]
+pub use load_report::LoadReport;
+#[cfg(feature = "metrics")]
+pub use lookup_metrics::{LookupMetrics, LookupOperation, MetricsSink};
+pub use memory_storage::{MemoryStorage, MemoryStorageError};
+#[cfg(feature = "std")]
+pub use mmap_mut_storage::{MmapMutStorage, MmapMutStorageError};
+#[cfg(feature = "std")]
 pub use mmap_storage::{MmapStorage, MmapStorageError};
+#[cfg(feature = "std")]
+pub use persistent_memory_storage::{PersistentMemoryStorage, PersistentMemoryStorageError};
 pub use serializer::{
-    DeserializationError, Deserializer, DeserializerOf, Serializer, SerializerOf,
+    DeserializationError, Deserializer, DeserializerOf, Serializer, SerializerOf, KEY_TERMINATOR,
 };
 pub use shared_storage::SharedStorage;
-pub use storage::{Storage, StorageError};
-pub use string_serializer::{StrSerializer, StringDeserializer, StringSerializer};
-pub use trie::{BuldingObserverSet, Trie};
+pub use storage::{
+    Storage, StorageError, StorageMut, StorageRead, StorageSerializationError,
+    StorageValueIterator, ValueRef,
+};
+pub use string_serializer::{
+    CodePointDeserializer, CodePointSerializer, StrSerializer, StringDeserializer,
+    StringSerializer, Utf16StrSerializer, Utf16StringDeserializer, Utf16StringSerializer,
+};
+#[cfg(feature = "std")]
+pub use swappable_trie::SwappableTrie;
+pub use trie::{BuldingObserverSet, KeyExpander, Trie, TrieBuilderError, TrieError, TrieTsvError};
+pub use trie_cursor::{CursorState, TrieCursor};
 pub use trie_iterator::TrieIterator;
-pub use value_serializer::{ValueDeserializer, ValueSerializer};
+pub use trie_range_iterator::TrieRangeIterator;
+pub use trie_snapshot::TrieSnapshot;
+pub use trie_suffix_iterator::TrieSuffixIterator;
+pub use value_compression::{ValueCompression, ValueCompressionError};
+pub use value_serializer::{ValueDeserializer, ValueSerializer, ValueSerializerError};
@@ -0,0 +1,79 @@
+/*!
+ * A load report.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use anyhow::Error;
+
+/**
+ * A load report.
+ *
+ * Lists the values which are not loaded in a lenient load.
+ */
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    corrupt_values: Vec<(usize, Error)>,
+}
+
+impl LoadReport {
+    /**
+     * Creates a load report.
+     *
+     * # Arguments
+     * * `corrupt_values` - Pairs of the index of a corrupt value and its error.
+     */
+    pub const fn new(corrupt_values: Vec<(usize, Error)>) -> Self {
+        Self { corrupt_values }
+    }
+
+    /**
+     * Returns the corrupt values.
+     *
+     * The corrupt values are substituted with None. They are in ascending
+     * order of the value indices.
+     *
+     * # Returns
+     * Pairs of the index of a corrupt value and its error.
+     */
+    pub fn corrupt_values(&self) -> &[(usize, Error)] {
+        self.corrupt_values.as_slice()
+    }
+
+    /**
+     * Returns `true` if no value is corrupt.
+     *
+     * # Returns
+     * `true` if no value is corrupt.
+     */
+    pub fn is_clean(&self) -> bool {
+        self.corrupt_values.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn new() {
+        let _report = LoadReport::new(vec![(42, anyhow!("broken"))]);
+    }
+
+    #[test]
+    fn corrupt_values() {
+        let report = LoadReport::new(vec![(42, anyhow!("broken"))]);
+
+        assert_eq!(report.corrupt_values().len(), 1);
+        assert_eq!(report.corrupt_values()[0].0, 42);
+        assert_eq!(report.corrupt_values()[0].1.to_string(), "broken");
+    }
+
+    #[test]
+    fn is_clean() {
+        assert!(LoadReport::default().is_clean());
+        assert!(!LoadReport::new(vec![(42, anyhow!("broken"))]).is_clean());
+    }
+}
@@ -0,0 +1,205 @@
+/*!
+ * Lookup metrics.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use core::cell::Cell;
+use core::fmt::Debug;
+
+/**
+ * A lookup operation.
+ */
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LookupOperation {
+    /**
+     * `Trie::find`.
+     */
+    Find,
+
+    /**
+     * `Trie::find_many`.
+     */
+    FindMany,
+
+    /**
+     * `Trie::contains`.
+     */
+    Contains,
+}
+
+/**
+ * Lookup metrics.
+ *
+ * The counters of a single lookup.
+ */
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct LookupMetrics {
+    traversal_steps: usize,
+    storage_reads: usize,
+    value_cache_hits: usize,
+}
+
+impl LookupMetrics {
+    /**
+     * Creates lookup metrics.
+     *
+     * # Arguments
+     * * `traversal_steps`  - A traversal step count.
+     * * `storage_reads`    - A storage read count.
+     * * `value_cache_hits` - A value cache hit count.
+     */
+    pub const fn new(
+        traversal_steps: usize,
+        storage_reads: usize,
+        value_cache_hits: usize,
+    ) -> Self {
+        Self {
+            traversal_steps,
+            storage_reads,
+            value_cache_hits,
+        }
+    }
+
+    /**
+     * Returns the traversal step count.
+     *
+     * A step is a move from a node of the double array to its child.
+     *
+     * # Returns
+     * The traversal step count.
+     */
+    pub const fn traversal_steps(&self) -> usize {
+        self.traversal_steps
+    }
+
+    /**
+     * Returns the storage read count.
+     *
+     * Counts the reads of the bases, the checks and the values.
+     *
+     * # Returns
+     * The storage read count.
+     */
+    pub const fn storage_reads(&self) -> usize {
+        self.storage_reads
+    }
+
+    /**
+     * Returns the value cache hit count.
+     *
+     * # Returns
+     * The value cache hit count. Always 0 for a storage without a value cache.
+     */
+    pub const fn value_cache_hits(&self) -> usize {
+        self.value_cache_hits
+    }
+
+    pub(crate) const fn since(&self, earlier: &Self) -> Self {
+        Self {
+            traversal_steps: self.traversal_steps.wrapping_sub(earlier.traversal_steps),
+            storage_reads: self.storage_reads.wrapping_sub(earlier.storage_reads),
+            value_cache_hits: self.value_cache_hits.wrapping_sub(earlier.value_cache_hits),
+        }
+    }
+}
+
+/**
+ * A metrics sink.
+ *
+ * Receives the metrics of each lookup of a trie.
+ */
+pub trait MetricsSink: Debug {
+    /**
+     * Records the metrics of a lookup.
+     *
+     * # Arguments
+     * * `operation` - A lookup operation.
+     * * `metrics`   - The metrics of the lookup.
+     */
+    fn record(&self, operation: LookupOperation, metrics: &LookupMetrics);
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct MetricsCounter {
+    traversal_steps: Cell<usize>,
+    storage_reads: Cell<usize>,
+}
+
+impl MetricsCounter {
+    pub(crate) const fn new() -> Self {
+        Self {
+            traversal_steps: Cell::new(0),
+            storage_reads: Cell::new(0),
+        }
+    }
+
+    pub(crate) fn add_traversal_step(&self) {
+        self.traversal_steps
+            .set(self.traversal_steps.get().wrapping_add(1));
+    }
+
+    pub(crate) fn add_storage_reads(&self, count: usize) {
+        self.storage_reads
+            .set(self.storage_reads.get().wrapping_add(count));
+    }
+
+    pub(crate) fn snapshot(&self, value_cache_hits: usize) -> LookupMetrics {
+        LookupMetrics::new(
+            self.traversal_steps.get(),
+            self.storage_reads.get(),
+            value_cache_hits,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod lookup_metrics {
+        use super::*;
+
+        #[test]
+        fn new() {
+            let _metrics = LookupMetrics::new(3, 1, 4);
+        }
+
+        #[test]
+        fn traversal_steps() {
+            assert_eq!(LookupMetrics::new(3, 1, 4).traversal_steps(), 3);
+        }
+
+        #[test]
+        fn storage_reads() {
+            assert_eq!(LookupMetrics::new(3, 1, 4).storage_reads(), 1);
+        }
+
+        #[test]
+        fn value_cache_hits() {
+            assert_eq!(LookupMetrics::new(3, 1, 4).value_cache_hits(), 4);
+        }
+
+        #[test]
+        fn since() {
+            let earlier = LookupMetrics::new(3, 1, 4);
+            let later = LookupMetrics::new(5, 9, 6);
+
+            assert_eq!(later.since(&earlier), LookupMetrics::new(2, 8, 2));
+        }
+    }
+
+    mod metrics_counter {
+        use super::*;
+
+        #[test]
+        fn snapshot() {
+            let counter = MetricsCounter::new();
+            counter.add_traversal_step();
+            counter.add_traversal_step();
+            counter.add_storage_reads(5);
+
+            assert_eq!(counter.snapshot(7), LookupMetrics::new(2, 5, 7));
+        }
+    }
+}
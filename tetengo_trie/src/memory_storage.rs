@@ -4,26 +4,132 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use std::any::Any;
-use std::cell::RefCell;
-use std::fmt::Debug;
-use std::io::{Read, Write};
-use std::rc::Rc;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::RefCell;
+use core::fmt::Debug;
+#[cfg(feature = "std")]
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::io::Read;
+#[cfg(feature = "std")]
 use std::sync::LazyLock;
 
 use anyhow::Result;
-
-use crate::double_array::VACANT_CHECK_VALUE;
-use crate::integer_serializer::{IntegerDeserializer, IntegerSerializer};
-use crate::serializer::{Deserializer, Serializer};
-use crate::storage::Storage;
-use crate::value_serializer::{ValueDeserializer, ValueSerializer};
+use hashbrown::HashMap;
+
+use crate::byte_writer::ByteWriter;
+use crate::double_array::{has_inlined_values, VACANT_CHECK_VALUE};
+use crate::format_version::{FormatVersion, FORMAT_FLAG_MASK};
+#[cfg(feature = "std")]
+use crate::integer_serializer::IntegerDeserializer;
+use crate::integer_serializer::IntegerSerializer;
+#[cfg(feature = "std")]
+use crate::load_report::LoadReport;
+#[cfg(feature = "std")]
+use crate::serializer::Deserializer;
+use crate::serializer::Serializer;
+use crate::storage::{StorageError, StorageMut, StorageRead, StorageSerializationError, ValueRef};
+use crate::value_compression::ValueCompression;
+#[cfg(feature = "std")]
+use crate::value_compression::ValueCompressionError;
+#[cfg(feature = "std")]
+use crate::value_serializer::ValueDeserializer;
+use crate::value_serializer::{ValueSerializer, ValueSerializerError};
 
 type ValueArrayElement<Value> = Option<Rc<Value>>;
 
+/// The flag in the fixed value size field indicating that the values are interned.
+pub(super) const VALUE_INTERNING_FLAG: u32 = 0x80000000;
+
+/// The flag in the fixed value size field indicating that the values are compressed.
+pub(super) const VALUE_COMPRESSION_FLAG: u32 = 0x40000000;
+
+/// The value count in a compressed block.
+pub(super) const COMPRESSED_BLOCK_VALUE_COUNT: usize = 64;
+
+/// The value pool index indicating that there is no value.
+pub(super) const NO_VALUE_POOL_INDEX: u32 = 0xFFFFFFFF;
+
+/**
+ * A memory storage error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum MemoryStorageError {
+    /**
+     * A value pool index is out of range.
+     */
+    #[error("a value pool index is out of range")]
+    ValuePoolIndexOutOfRange,
+
+    /**
+     * The format version is not supported.
+     */
+    #[error("the format version is not supported")]
+    UnsupportedFormatVersion,
+
+    /**
+     * A base-check index or a value index is out of range.
+     */
+    #[error("an index is out of range")]
+    IndexOutOfRange,
+
+    /**
+     * A value shares the corrupt data reported for another value.
+     */
+    #[error("the value shares the corrupt data reported for another value")]
+    SharedCorruptValue,
+
+    /**
+     * The value count does not match the one of the base-check array.
+     */
+    #[error("the value count does not match the one of the base-check array")]
+    ValueCountMismatch,
+}
+
+impl StorageError for MemoryStorageError {}
+
 /**
  * A memory storage.
  *
+ * # Serialized Format
+ * All the integers are 32-bit unsigned big endian.
+ *
+ * 1. The base-check array size _n_.
+ * 2. _n_ base-check values.
+ * 3. The value array size _m_.
+ * 4. The fixed value size _s_ (0 when the value size is variable) with the
+ *    format flags in the upper 8 bits.
+ * 5. The value array.
+ *    - When _s_ is 0, each value is its size followed by its bytes. A missing
+ *      value is the size 0.
+ *    - Otherwise, each value is _s_ bytes. A missing value is _s_ bytes of 0xFF.
+ *
+ * When the value interning flag (0x80000000) is set, the value array (5.) is
+ * replaced with _m_ value pool indexes (0xFFFFFFFF for a missing value), the
+ * value pool size _p_ and _p_ values in the same encoding as the value array.
+ *
+ * When the value compression flag (0x40000000) is set, the values in the
+ * value array or the value pool are stored in compressed blocks instead:
+ *
+ * 1. The codec ID (1 for LZ4).
+ * 2. The value count in a block _k_.
+ * 3. The block count _b_.
+ * 4. _b_ + 1 block offsets from the first block. The last one is the total
+ *    size of the blocks.
+ * 5. _b_ blocks. Each block is its decompressed size followed by the
+ *    compressed bytes of _k_ values (fewer in the last block) in the same
+ *    encoding as the value array.
+ *
+ * The format with no flags is `FormatVersion::V1`, the one with only the value
+ * interning flag is `FormatVersion::V2`, and the one with the value
+ * compression flag is `FormatVersion::V3`.
+ *
+ * The values serialized by `serialize_values_only` are 3. to 5. only.
+ *
  * # Type Parameters
  * * `Value` - A value type.
  */
@@ -31,6 +137,7 @@ type ValueArrayElement<Value> = Option<Rc<Value>>;
 pub struct MemoryStorage<Value: Clone> {
     base_check_array: RefCell<Vec<u32>>,
     value_array: Vec<ValueArrayElement<Value>>,
+    format_version: FormatVersion,
 }
 
 impl<Value: Clone + 'static> MemoryStorage<Value> {
@@ -41,6 +148,7 @@ impl<Value: Clone + 'static> MemoryStorage<Value> {
         Self {
             base_check_array: RefCell::new(vec![VACANT_CHECK_VALUE as u32]),
             value_array: Vec::new(),
+            format_version: FormatVersion::LATEST,
         }
     }
 
@@ -53,19 +161,159 @@ impl<Value: Clone + 'static> MemoryStorage<Value> {
      *
      * # Errors
      * * When it fails to read the memory.
+     * * When the format version is not supported.
      */
+    #[cfg(feature = "std")]
     pub fn new_with_reader(
         reader: &mut dyn Read,
         value_deserializer: &mut ValueDeserializer<Value>,
     ) -> Result<Self> {
-        let (base_check_array, value_array) = Self::deserialize(reader, value_deserializer)?;
+        let (base_check_array, value_array, format_version) =
+            Self::deserialize(reader, value_deserializer, &mut LoadDiagnostics::strict())?;
+        Ok(Self {
+            base_check_array: RefCell::new(base_check_array),
+            value_array,
+            format_version,
+        })
+    }
+
+    /**
+     * Creates a memory storage leniently.
+     *
+     * The values which fail to be deserialized are substituted with None and
+     * listed in the load report, and the load continues. So are the values in
+     * a broken compressed block and the values with an out-of-range value pool
+     * index. A value sharing a corrupt pooled value or block with a preceding
+     * one is reported with `MemoryStorageError::SharedCorruptValue`.
+     *
+     * # Arguments
+     * * `reader`             - A reader.
+     * * `value_deserializer` - A deserializer for value objects.
+     *
+     * # Returns
+     * The memory storage and the load report.
+     *
+     * # Errors
+     * * When it fails to read the memory.
+     * * When the format version is not supported.
+     */
+    #[cfg(feature = "std")]
+    pub fn new_with_reader_lenient(
+        reader: &mut dyn Read,
+        value_deserializer: &mut ValueDeserializer<Value>,
+    ) -> Result<(Self, LoadReport)> {
+        let mut diagnostics = LoadDiagnostics::lenient();
+        let (base_check_array, value_array, format_version) =
+            Self::deserialize(reader, value_deserializer, &mut diagnostics)?;
+        Ok((
+            Self {
+                base_check_array: RefCell::new(base_check_array),
+                value_array,
+                format_version,
+            },
+            diagnostics.into_report(),
+        ))
+    }
+
+    /**
+     * Creates a memory storage attaching values to a base-check array.
+     *
+     * The base-check array is read from a serialized storage, and its values
+     * are skipped. The values are read from the ones serialized by
+     * `serialize_values_only` instead. So some value sets can share one
+     * base-check array.
+     *
+     * The values inlined into the base-check array (see
+     * `TrieBuilder::inline_values`) are not replaced by the attached values.
+     * Build the trie without inlining values to share its base-check array.
+     *
+     * # Arguments
+     * * `base_check_reader`  - A reader of a serialized storage.
+     * * `value_reader`       - A reader of serialized values.
+     * * `value_deserializer` - A deserializer for value objects.
+     *
+     * # Errors
+     * * When it fails to read the memory.
+     * * When the format version is not supported.
+     * * When the value count does not match the one of the serialized
+     *   storage.
+     */
+    #[cfg(feature = "std")]
+    pub fn new_with_value_reader(
+        base_check_reader: &mut dyn Read,
+        value_reader: &mut dyn Read,
+        value_deserializer: &mut ValueDeserializer<Value>,
+    ) -> Result<Self> {
+        let base_check_array = Self::deserialize_base_check_array(base_check_reader)?;
+        let value_count = Self::read_u32(base_check_reader)? as usize;
+        let (value_array, format_version) = Self::deserialize_value_array(
+            value_reader,
+            value_deserializer,
+            &mut LoadDiagnostics::strict(),
+        )?;
+        if value_array.len() != value_count {
+            return Err(MemoryStorageError::ValueCountMismatch.into());
+        }
         Ok(Self {
             base_check_array: RefCell::new(base_check_array),
             value_array,
+            format_version,
         })
     }
 
-    fn serialize_base_check_array(writer: &mut dyn Write, base_check_array: &[u32]) -> Result<()> {
+    /**
+     * Creates a memory storage from a base-check array and values.
+     *
+     * Each element of the base-check array is `(base << 8) | check`, where the
+     * base is a signed 24-bit integer and the check is a byte. The element 0
+     * is the root. The child of a node for a byte `c` is at `base + c`, and
+     * its check is `c`. A vacant element has the check `0xFF`. The base of the
+     * child for `KEY_TERMINATOR` is the index of the value in `values`.
+     *
+     * The arrays are not validated.
+     *
+     * # Arguments
+     * * `base_check_array` - A base-check array.
+     * * `values`           - Values.
+     */
+    pub fn from_raw_parts(base_check_array: Vec<u32>, values: Vec<Option<Value>>) -> Self {
+        Self {
+            base_check_array: RefCell::new(base_check_array),
+            value_array: values.into_iter().map(|value| value.map(Rc::new)).collect(),
+            format_version: FormatVersion::LATEST,
+        }
+    }
+
+    pub(crate) fn new_with_base_check_array(base_check_array: Vec<u32>) -> Self {
+        Self {
+            base_check_array: RefCell::new(base_check_array),
+            value_array: Vec::new(),
+            format_version: FormatVersion::LATEST,
+        }
+    }
+
+    /**
+     * Returns the format version.
+     *
+     * # Returns
+     * The format version of the serialized storage which this storage is read
+     * from. Or `FormatVersion::LATEST` when this storage is not read from a
+     * serialized one.
+     */
+    pub const fn format_version(&self) -> FormatVersion {
+        self.format_version
+    }
+
+    pub(crate) fn reserve_value_slots(&mut self, value_count: usize) {
+        if value_count > self.value_array.len() {
+            self.value_array.resize_with(value_count, || None);
+        }
+    }
+
+    fn serialize_base_check_array(
+        writer: &mut dyn ByteWriter,
+        base_check_array: &[u32],
+    ) -> Result<()> {
         debug_assert!(base_check_array.len() < u32::MAX as usize);
         Self::write_u32(writer, base_check_array.len() as u32)?;
         for v in base_check_array {
@@ -75,110 +323,396 @@ impl<Value: Clone + 'static> MemoryStorage<Value> {
     }
 
     fn serialize_value_array(
-        writer: &mut dyn Write,
+        writer: &mut dyn ByteWriter,
         value_serializer: &mut ValueSerializer<'_, Value>,
         value_array: &[ValueArrayElement<Value>],
     ) -> Result<()> {
         debug_assert!(value_array.len() < u32::MAX as usize);
         Self::write_u32(writer, value_array.len() as u32)?;
 
-        debug_assert!(value_serializer.fixed_value_size() < u32::MAX as usize);
+        if value_serializer.fixed_value_size() & FORMAT_FLAG_MASK as usize != 0 {
+            return Err(ValueSerializerError::TooLargeFixedValueSize.into());
+        }
         let fixed_value_size = value_serializer.fixed_value_size() as u32;
-        Self::write_u32(writer, fixed_value_size)?;
+        let compression = value_serializer.compression();
+        let compression_flag = if compression.is_some() {
+            VALUE_COMPRESSION_FLAG
+        } else {
+            0
+        };
 
-        if fixed_value_size == 0 {
+        if value_serializer.value_interning() {
+            Self::write_u32(
+                writer,
+                fixed_value_size | VALUE_INTERNING_FLAG | compression_flag,
+            )?;
+
+            let mut value_pool = Vec::<Vec<u8>>::new();
+            let mut value_pool_indexes = HashMap::<Vec<u8>, u32>::new();
+            let mut buffer = Vec::new();
             for v in value_array {
-                if let Some(v) = v {
-                    let serialized = value_serializer.serialize(v);
-                    debug_assert!(serialized.len() < u32::MAX as usize);
-                    Self::write_u32(writer, serialized.len() as u32)?;
-                    writer.write_all(&serialized)?;
+                let value_pool_index = if let Some(v) = v {
+                    buffer.clear();
+                    value_serializer.serialize_into(v, &mut buffer)?;
+                    if let Some(&value_pool_index) = value_pool_indexes.get(buffer.as_slice()) {
+                        value_pool_index
+                    } else {
+                        debug_assert!(value_pool.len() < NO_VALUE_POOL_INDEX as usize);
+                        let value_pool_index = value_pool.len() as u32;
+                        let _prev_value =
+                            value_pool_indexes.insert(buffer.clone(), value_pool_index);
+                        value_pool.push(buffer.clone());
+                        value_pool_index
+                    }
                 } else {
-                    Self::write_u32(writer, 0)?;
+                    NO_VALUE_POOL_INDEX
+                };
+                Self::write_u32(writer, value_pool_index)?;
+            }
+
+            Self::write_u32(writer, value_pool.len() as u32)?;
+            if let Some(compression) = compression {
+                let value_pool = value_pool.into_iter().map(Some).collect::<Vec<_>>();
+                Self::write_compressed_values(writer, fixed_value_size, compression, &value_pool)?;
+            } else {
+                for serialized in &value_pool {
+                    Self::write_serialized_value(writer, fixed_value_size, Some(serialized))?;
                 }
             }
         } else {
-            for v in value_array {
-                if let Some(v) = v {
-                    let serialized = value_serializer.serialize(v);
-                    debug_assert!(serialized.len() == fixed_value_size as usize);
-                    writer.write_all(&serialized)?;
-                } else {
-                    let uninitialized = vec![Self::UNINITIALIZED_BYTE; fixed_value_size as usize];
-                    writer.write_all(&uninitialized)?;
+            Self::write_u32(writer, fixed_value_size | compression_flag)?;
+
+            if let Some(compression) = compression {
+                let serialized_values = value_array
+                    .iter()
+                    .map(|v| {
+                        v.as_ref()
+                            .map(|v| value_serializer.serialize(v))
+                            .transpose()
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Self::write_compressed_values(
+                    writer,
+                    fixed_value_size,
+                    compression,
+                    &serialized_values,
+                )?;
+            } else {
+                let mut buffer = Vec::new();
+                for v in value_array {
+                    let serialized = if let Some(v) = v {
+                        buffer.clear();
+                        value_serializer.serialize_into(v, &mut buffer)?;
+                        Some(buffer.as_slice())
+                    } else {
+                        None
+                    };
+                    Self::write_serialized_value(writer, fixed_value_size, serialized)?;
                 }
             }
         }
         Ok(())
     }
 
-    fn write_u32(writer: &mut dyn Write, value: u32) -> Result<()> {
-        static INTEGER_SERIALIZER: LazyLock<IntegerSerializer<u32>> =
-            LazyLock::new(|| IntegerSerializer::new(false));
+    fn write_compressed_values(
+        writer: &mut dyn ByteWriter,
+        fixed_value_size: u32,
+        compression: ValueCompression,
+        serialized_values: &[Option<Vec<u8>>],
+    ) -> Result<()> {
+        let blocks = serialized_values
+            .chunks(COMPRESSED_BLOCK_VALUE_COUNT)
+            .map(|chunk| {
+                let mut block = Vec::new();
+                for serialized in chunk {
+                    Self::write_serialized_value(
+                        &mut block,
+                        fixed_value_size,
+                        serialized.as_deref(),
+                    )?;
+                }
+                debug_assert!(block.len() < u32::MAX as usize);
+                let mut compressed_block = Vec::new();
+                Self::write_u32(&mut compressed_block, block.len() as u32)?;
+                compressed_block.extend(compression.compress(&block));
+                Ok(compressed_block)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Self::write_u32(writer, compression.id())?;
+        Self::write_u32(writer, COMPRESSED_BLOCK_VALUE_COUNT as u32)?;
+        Self::write_u32(writer, blocks.len() as u32)?;
+        let mut block_offset = 0usize;
+        Self::write_u32(writer, 0)?;
+        for block in &blocks {
+            block_offset += block.len();
+            debug_assert!(block_offset < u32::MAX as usize);
+            Self::write_u32(writer, block_offset as u32)?;
+        }
+        for block in &blocks {
+            writer.write_bytes(block)?;
+        }
+        Ok(())
+    }
 
-        let serialized = INTEGER_SERIALIZER.serialize(&value);
-        writer.write_all(&serialized)?;
+    fn write_serialized_value(
+        writer: &mut dyn ByteWriter,
+        fixed_value_size: u32,
+        serialized: Option<&[u8]>,
+    ) -> Result<()> {
+        if fixed_value_size == 0 {
+            if let Some(serialized) = serialized {
+                debug_assert!(serialized.len() < u32::MAX as usize);
+                Self::write_u32(writer, serialized.len() as u32)?;
+                writer.write_bytes(serialized)?;
+            } else {
+                Self::write_u32(writer, 0)?;
+            }
+        } else if let Some(serialized) = serialized {
+            if serialized.len() != fixed_value_size as usize {
+                return Err(ValueSerializerError::MismatchedValueSize.into());
+            }
+            writer.write_bytes(serialized)?;
+        } else {
+            let uninitialized = vec![Self::UNINITIALIZED_BYTE; fixed_value_size as usize];
+            writer.write_bytes(&uninitialized)?;
+        }
+        Ok(())
+    }
+
+    fn write_u32(writer: &mut dyn ByteWriter, value: u32) -> Result<()> {
+        let serialized = IntegerSerializer::<u32>::new(false).serialize(&value);
+        writer.write_bytes(&serialized)?;
         Ok(())
     }
 
+    #[cfg(feature = "std")]
     fn deserialize(
         reader: &mut dyn Read,
         value_deserializer: &mut ValueDeserializer<Value>,
-    ) -> Result<(Vec<u32>, Vec<ValueArrayElement<Value>>)> {
+        diagnostics: &mut LoadDiagnostics,
+    ) -> Result<(Vec<u32>, Vec<ValueArrayElement<Value>>, FormatVersion)> {
         let base_check_array = Self::deserialize_base_check_array(reader)?;
-        let value_array = Self::deserialize_value_array(reader, value_deserializer)?;
-        Ok((base_check_array, value_array))
+        let (value_array, format_version) =
+            Self::deserialize_value_array(reader, value_deserializer, diagnostics)?;
+        Ok((base_check_array, value_array, format_version))
     }
 
+    #[cfg(feature = "std")]
     fn deserialize_base_check_array(reader: &mut dyn Read) -> Result<Vec<u32>> {
         let size = Self::read_u32(reader)? as usize;
-        let mut base_check_array = Vec::with_capacity(size);
+        let mut base_check_array = Vec::with_capacity(size.min(Self::PREALLOCATION_LIMIT));
         for _ in 0..size {
             base_check_array.push(Self::read_u32(reader)?);
         }
         Ok(base_check_array)
     }
 
+    #[cfg(feature = "std")]
     fn deserialize_value_array(
         reader: &mut dyn Read,
         value_deserializer: &mut ValueDeserializer<Value>,
-    ) -> Result<Vec<ValueArrayElement<Value>>> {
+        diagnostics: &mut LoadDiagnostics,
+    ) -> Result<(Vec<ValueArrayElement<Value>>, FormatVersion)> {
         let size = Self::read_u32(reader)? as usize;
 
-        let fixed_value_size = Self::read_u32(reader)? as usize;
-        let mut value_array = Vec::with_capacity(size);
-        if fixed_value_size == 0 {
+        let fixed_value_size_and_flag = Self::read_u32(reader)?;
+        let Some(format_version) =
+            FormatVersion::of_fixed_value_size_field(fixed_value_size_and_flag)
+        else {
+            return Err(MemoryStorageError::UnsupportedFormatVersion.into());
+        };
+        let fixed_value_size = (fixed_value_size_and_flag & !FORMAT_FLAG_MASK) as usize;
+        let compressed = fixed_value_size_and_flag & VALUE_COMPRESSION_FLAG != 0;
+        if fixed_value_size_and_flag & VALUE_INTERNING_FLAG != 0 {
+            let mut value_pool_indexes = Vec::with_capacity(size.min(Self::PREALLOCATION_LIMIT));
             for _ in 0..size {
-                let element_size = Self::read_u32(reader)? as usize;
-                if element_size > 0 {
-                    let mut to_deserialize = vec![0; element_size];
-                    reader.read_exact(&mut to_deserialize)?;
-                    value_array.push(Some(Rc::new(
-                        value_deserializer.deserialize(&to_deserialize)?,
-                    )));
-                } else {
-                    value_array.push(None);
-                }
+                value_pool_indexes.push(Self::read_u32(reader)?);
             }
+
+            let value_pool_size = Self::read_u32(reader)? as usize;
+            // The errors of the pooled values are reported for the values referring to them.
+            let mut value_pool_diagnostics = diagnostics.for_value_pool();
+            let value_pool = Self::read_values(
+                reader,
+                value_deserializer,
+                fixed_value_size,
+                value_pool_size,
+                compressed,
+                &mut value_pool_diagnostics,
+            )?;
+            let mut value_pool_errors = value_pool_diagnostics
+                .into_corrupt_values()
+                .into_iter()
+                .map(|(value_pool_index, error)| (value_pool_index, Some(error)))
+                .collect::<HashMap<_, _>>();
+
+            let value_array = value_pool_indexes
+                .into_iter()
+                .enumerate()
+                .map(|(i, value_pool_index)| {
+                    if value_pool_index == NO_VALUE_POOL_INDEX {
+                        return Ok(None);
+                    }
+                    let Some(value) = value_pool.get(value_pool_index as usize) else {
+                        return diagnostics
+                            .recover(i, Err(MemoryStorageError::ValuePoolIndexOutOfRange.into()));
+                    };
+                    if let Some(error) = value_pool_errors.get_mut(&(value_pool_index as usize)) {
+                        let error = error
+                            .take()
+                            .unwrap_or_else(|| MemoryStorageError::SharedCorruptValue.into());
+                        return diagnostics.recover(i, Err(error));
+                    }
+                    Ok(value.clone())
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok((value_array, format_version))
         } else {
-            for _ in 0..size {
-                let mut to_deserialize = vec![0; fixed_value_size];
-                reader.read_exact(&mut to_deserialize)?;
-                if to_deserialize
-                    .iter()
-                    .all(|&e| e == Self::UNINITIALIZED_BYTE)
-                {
-                    value_array.push(None);
-                } else {
-                    value_array.push(Some(Rc::new(
-                        value_deserializer.deserialize(&to_deserialize)?,
-                    )));
-                }
+            let value_array = Self::read_values(
+                reader,
+                value_deserializer,
+                fixed_value_size,
+                size,
+                compressed,
+                diagnostics,
+            )?;
+            Ok((value_array, format_version))
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn read_values(
+        reader: &mut dyn Read,
+        value_deserializer: &mut ValueDeserializer<Value>,
+        fixed_value_size: usize,
+        count: usize,
+        compressed: bool,
+        diagnostics: &mut LoadDiagnostics,
+    ) -> Result<Vec<ValueArrayElement<Value>>> {
+        let mut values = Vec::with_capacity(count.min(Self::PREALLOCATION_LIMIT));
+        if !compressed {
+            for i in 0..count {
+                values.push(Self::read_value(
+                    reader,
+                    value_deserializer,
+                    fixed_value_size,
+                    diagnostics,
+                    i,
+                )?);
             }
+            return Ok(values);
         }
-        Ok(value_array)
+
+        let compression = ValueCompression::of_id(Self::read_u32(reader)?)?;
+        let block_value_count = Self::read_u32(reader)? as usize;
+        let block_count = Self::read_u32(reader)? as usize;
+        if block_value_count == 0 || block_count != count.div_ceil(block_value_count) {
+            return Err(ValueCompressionError::BrokenBlock.into());
+        }
+        let mut block_offsets =
+            Vec::with_capacity((block_count + 1).min(Self::PREALLOCATION_LIMIT));
+        for _ in 0..=block_count {
+            block_offsets.push(Self::read_u32(reader)? as usize);
+        }
+        for (i, block_range) in block_offsets.windows(2).enumerate() {
+            let Some(block_size) = block_range[1].checked_sub(block_range[0]) else {
+                return Err(ValueCompressionError::BrokenBlock.into());
+            };
+            let block = Self::read_bytes(reader, block_size)?;
+            let value_indices =
+                i * block_value_count..(i * block_value_count + block_value_count).min(count);
+            match Self::read_block(
+                &block,
+                compression,
+                value_deserializer,
+                fixed_value_size,
+                diagnostics,
+                value_indices.clone(),
+            ) {
+                Ok(block_values) => values.extend(block_values),
+                Err(error) => values.extend(diagnostics.recover_all(value_indices, error)?),
+            }
+        }
+        Ok(values)
     }
 
+    #[cfg(feature = "std")]
+    fn read_block(
+        block: &[u8],
+        compression: ValueCompression,
+        value_deserializer: &mut ValueDeserializer<Value>,
+        fixed_value_size: usize,
+        diagnostics: &mut LoadDiagnostics,
+        value_indices: Range<usize>,
+    ) -> Result<Vec<ValueArrayElement<Value>>> {
+        let Some((decompressed_size, compressed_block)) = block.split_at_checked(size_of::<u32>())
+        else {
+            return Err(ValueCompressionError::BrokenBlock.into());
+        };
+        let decompressed_size = Self::read_u32(&mut &decompressed_size[..])? as usize;
+        let decompressed = compression.decompress(compressed_block, decompressed_size)?;
+
+        let mut block_reader = decompressed.as_slice();
+        let mut values = Vec::with_capacity(value_indices.len());
+        for value_index in value_indices {
+            values.push(Self::read_value(
+                &mut block_reader,
+                value_deserializer,
+                fixed_value_size,
+                diagnostics,
+                value_index,
+            )?);
+        }
+        if !block_reader.is_empty() {
+            return Err(ValueCompressionError::BrokenBlock.into());
+        }
+        Ok(values)
+    }
+
+    #[cfg(feature = "std")]
+    fn read_value(
+        reader: &mut dyn Read,
+        value_deserializer: &mut ValueDeserializer<Value>,
+        fixed_value_size: usize,
+        diagnostics: &mut LoadDiagnostics,
+        value_index: usize,
+    ) -> Result<ValueArrayElement<Value>> {
+        let to_deserialize = if fixed_value_size == 0 {
+            let element_size = Self::read_u32(reader)? as usize;
+            if element_size == 0 {
+                return Ok(None);
+            }
+            Self::read_bytes(reader, element_size)?
+        } else {
+            let to_deserialize = Self::read_bytes(reader, fixed_value_size)?;
+            if to_deserialize
+                .iter()
+                .all(|&e| e == Self::UNINITIALIZED_BYTE)
+            {
+                return Ok(None);
+            }
+            to_deserialize
+        };
+        diagnostics.recover(
+            value_index,
+            value_deserializer
+                .deserialize(&to_deserialize)
+                .map(|value| Some(Rc::new(value))),
+        )
+    }
+
+    #[cfg(feature = "std")]
+    fn read_bytes(reader: &mut dyn Read, size: usize) -> Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(size.min(Self::PREALLOCATION_LIMIT));
+        let read_size = reader.take(size as u64).read_to_end(&mut bytes)?;
+        if read_size < size {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(bytes)
+    }
+
+    #[cfg(feature = "std")]
     fn read_u32(reader: &mut dyn Read) -> Result<u32> {
         static U32_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
             LazyLock::new(|| IntegerDeserializer::new(false));
@@ -190,44 +724,39 @@ impl<Value: Clone + 'static> MemoryStorage<Value> {
 
     const UNINITIALIZED_BYTE: u8 = 0xFF;
 
-    fn ensure_base_check_size(&self, size: usize) {
+    // The sizes in a serialized storage are not trusted until the elements are
+    // actually read.
+    #[cfg(feature = "std")]
+    const PREALLOCATION_LIMIT: usize = 0x10000;
+
+    fn ensure_base_check_index(&self, base_check_index: usize) -> Result<()> {
+        let Some(size) = base_check_index.checked_add(1) else {
+            return Err(MemoryStorageError::IndexOutOfRange.into());
+        };
         if size > self.base_check_array.borrow().len() {
             self.base_check_array
                 .borrow_mut()
                 .resize(size, VACANT_CHECK_VALUE as u32);
         }
+        Ok(())
     }
 }
 
-impl<Value: Clone + Debug + 'static> Storage<Value> for MemoryStorage<Value> {
+impl<Value: Clone + Debug + 'static> StorageRead<Value> for MemoryStorage<Value> {
     fn base_check_size(&self) -> Result<usize> {
         Ok(self.base_check_array.borrow().len())
     }
 
     fn base_at(&self, base_check_index: usize) -> Result<i32> {
-        self.ensure_base_check_size(base_check_index + 1);
+        self.ensure_base_check_index(base_check_index)?;
         Ok(self.base_check_array.borrow()[base_check_index] as i32 >> 8i32)
     }
 
-    fn set_base_at(&mut self, base_check_index: usize, base: i32) -> Result<()> {
-        self.ensure_base_check_size(base_check_index + 1);
-        self.base_check_array.borrow_mut()[base_check_index] &= 0x000000FF;
-        self.base_check_array.borrow_mut()[base_check_index] |= (base as u32) << 8;
-        Ok(())
-    }
-
     fn check_at(&self, base_check_index: usize) -> Result<u8> {
-        self.ensure_base_check_size(base_check_index + 1);
+        self.ensure_base_check_index(base_check_index)?;
         Ok((self.base_check_array.borrow()[base_check_index] & 0xFF) as u8)
     }
 
-    fn set_check_at(&mut self, base_check_index: usize, check: u8) -> Result<()> {
-        self.ensure_base_check_size(base_check_index + 1);
-        self.base_check_array.borrow_mut()[base_check_index] &= 0xFFFFFF00;
-        self.base_check_array.borrow_mut()[base_check_index] |= check as u32;
-        Ok(())
-    }
-
     fn value_count(&self) -> Result<usize> {
         Ok(self.value_array.len())
     }
@@ -239,12 +768,11 @@ impl<Value: Clone + Debug + 'static> Storage<Value> for MemoryStorage<Value> {
         Ok(value.clone())
     }
 
-    fn add_value_at(&mut self, value_index: usize, value: Value) -> Result<()> {
-        if value_index >= self.value_array.len() {
-            self.value_array.resize_with(value_index + 1, || None);
-        }
-        self.value_array[value_index] = Some(Rc::new(value));
-        Ok(())
+    fn value_ref_at(&self, value_index: usize) -> Result<Option<ValueRef<'_, Value>>> {
+        let Some(value) = self.value_array.get(value_index) else {
+            return Ok(None);
+        };
+        Ok(value.as_deref().map(ValueRef::Borrowed))
     }
 
     fn filling_rate(&self) -> Result<f64> {
@@ -259,7 +787,7 @@ impl<Value: Clone + Debug + 'static> Storage<Value> for MemoryStorage<Value> {
 
     fn serialize(
         &self,
-        writer: &mut dyn Write,
+        writer: &mut dyn ByteWriter,
         value_serializer: &mut ValueSerializer<'_, Value>,
     ) -> Result<()> {
         Self::serialize_base_check_array(writer, &self.base_check_array.borrow())?;
@@ -267,10 +795,22 @@ impl<Value: Clone + Debug + 'static> Storage<Value> for MemoryStorage<Value> {
 
         Ok(())
     }
-    fn clone_box(&self) -> Box<dyn Storage<Value>> {
+
+    fn serialize_values_only(
+        &self,
+        writer: &mut dyn ByteWriter,
+        value_serializer: &mut ValueSerializer<'_, Value>,
+    ) -> Result<()> {
+        if has_inlined_values(self)? {
+            return Err(StorageSerializationError::ValuesInlined.into());
+        }
+        Self::serialize_value_array(writer, value_serializer, &self.value_array)
+    }
+    fn clone_box(&self) -> Box<dyn StorageRead<Value>> {
         Box::new(Self {
             base_check_array: RefCell::new(self.base_check_array.borrow().clone()),
             value_array: self.value_array.clone(),
+            format_version: self.format_version,
         })
     }
 
@@ -281,14 +821,124 @@ impl<Value: Clone + Debug + 'static> Storage<Value> for MemoryStorage<Value> {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn as_storage_mut(&mut self) -> Option<&mut dyn StorageMut<Value>> {
+        Some(self)
+    }
+}
+
+impl<Value: Clone + Debug + 'static> StorageMut<Value> for MemoryStorage<Value> {
+    fn set_base_at(&mut self, base_check_index: usize, base: i32) -> Result<()> {
+        self.ensure_base_check_index(base_check_index)?;
+        self.base_check_array.borrow_mut()[base_check_index] &= 0x000000FF;
+        self.base_check_array.borrow_mut()[base_check_index] |= (base as u32) << 8;
+        Ok(())
+    }
+
+    fn set_check_at(&mut self, base_check_index: usize, check: u8) -> Result<()> {
+        self.ensure_base_check_index(base_check_index)?;
+        self.base_check_array.borrow_mut()[base_check_index] &= 0xFFFFFF00;
+        self.base_check_array.borrow_mut()[base_check_index] |= check as u32;
+        Ok(())
+    }
+
+    fn add_value_at(&mut self, value_index: usize, value: Value) -> Result<()> {
+        if value_index >= self.value_array.len() {
+            let Some(size) = value_index.checked_add(1) else {
+                return Err(MemoryStorageError::IndexOutOfRange.into());
+            };
+            self.value_array.resize_with(size, || None);
+        }
+        self.value_array[value_index] = Some(Rc::new(value));
+        Ok(())
+    }
+}
+
+// Collects the errors of the corrupt values in a lenient load. A strict load
+// fails with the errors instead.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct LoadDiagnostics {
+    corrupt_values: Option<Vec<(usize, anyhow::Error)>>,
+}
+
+#[cfg(feature = "std")]
+impl LoadDiagnostics {
+    const fn strict() -> Self {
+        Self {
+            corrupt_values: None,
+        }
+    }
+
+    const fn lenient() -> Self {
+        Self {
+            corrupt_values: Some(Vec::new()),
+        }
+    }
+
+    const fn for_value_pool(&self) -> Self {
+        if self.corrupt_values.is_some() {
+            Self::lenient()
+        } else {
+            Self::strict()
+        }
+    }
+
+    fn recover<T>(&mut self, index: usize, result: Result<Option<T>>) -> Result<Option<T>> {
+        match (result, &mut self.corrupt_values) {
+            (Err(error), Some(corrupt_values)) => {
+                corrupt_values.push((index, error));
+                Ok(None)
+            }
+            (result, _) => result,
+        }
+    }
+
+    fn recover_all<T>(
+        &mut self,
+        indices: Range<usize>,
+        error: anyhow::Error,
+    ) -> Result<Vec<Option<T>>> {
+        let Some(corrupt_values) = &mut self.corrupt_values else {
+            return Err(error);
+        };
+        let mut error = Some(error);
+        for index in indices.clone() {
+            let error = error
+                .take()
+                .unwrap_or_else(|| MemoryStorageError::SharedCorruptValue.into());
+            corrupt_values.push((index, error));
+        }
+        Ok(indices.map(|_| None).collect())
+    }
+
+    fn into_corrupt_values(self) -> Vec<(usize, anyhow::Error)> {
+        let mut corrupt_values = self.corrupt_values.unwrap_or_default();
+        // A value in a broken block may be reported twice.
+        corrupt_values.sort_by_key(|(index, _)| *index);
+        corrupt_values.dedup_by_key(|(index, _)| *index);
+        corrupt_values
+    }
+
+    fn into_report(self) -> LoadReport {
+        LoadReport::new(self.into_corrupt_values())
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+    #[cfg(feature = "std")]
+    use core::ops::ControlFlow;
+    #[cfg(feature = "std")]
     use std::io::Cursor;
 
-    use crate::serializer::{Deserializer, Serializer};
+    #[cfg(feature = "std")]
+    use crate::serializer::{Deserializer, Serializer, KEY_TERMINATOR};
+    #[cfg(feature = "std")]
     use crate::string_serializer::{StrSerializer, StringDeserializer};
+    #[cfg(feature = "std")]
     use crate::value_serializer::ValueSerializer;
 
     use super::*;
@@ -298,6 +948,7 @@ mod tests {
         let _storage = MemoryStorage::<i32>::new();
     }
 
+    #[cfg(feature = "std")]
     #[rustfmt::skip]
     const SERIALIZED: &[u8] = &[
         0x00u8, 0x00u8, 0x00u8, 0x02u8,
@@ -315,10 +966,12 @@ mod tests {
         0x68u8, 0x6Fu8, 0x67u8, 0x65u8,
     ];
 
+    #[cfg(feature = "std")]
     fn create_input_stream() -> Box<dyn Read> {
         Box::new(Cursor::new(SERIALIZED))
     }
 
+    #[cfg(feature = "std")]
     #[rustfmt::skip]
     const SERIALIZED_FIXED_VALUE_SIZE: &[u8] = &[
         0x00u8, 0x00u8, 0x00u8, 0x02u8,
@@ -333,13 +986,39 @@ mod tests {
         0x00u8, 0x00u8, 0x00u8, 0x03u8,
     ];
 
+    #[cfg(feature = "std")]
     fn create_input_stream_fixed_value_size() -> Box<dyn Read> {
         Box::new(Cursor::new(SERIALIZED_FIXED_VALUE_SIZE))
     }
 
+    #[cfg(feature = "std")]
+    #[rustfmt::skip]
+    const SERIALIZED_VALUE_INTERNED: &[u8] = &[
+        0x00u8, 0x00u8, 0x00u8, 0x02u8,
+        0x00u8, 0x00u8, 0x2Au8, 0xFFu8,
+        0x00u8, 0x00u8, 0xFEu8, 0x18u8,
+        0x00u8, 0x00u8, 0x00u8, 0x05u8,
+        0x80u8, 0x00u8, 0x00u8, 0x00u8,
+        0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8,
+        0x00u8, 0x00u8, 0x00u8, 0x00u8,
+        0x00u8, 0x00u8, 0x00u8, 0x01u8,
+        0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8,
+        0x00u8, 0x00u8, 0x00u8, 0x01u8,
+        0x00u8, 0x00u8, 0x00u8, 0x02u8,
+        0x00u8, 0x00u8, 0x00u8, 0x04u8,
+        0x70u8, 0x69u8, 0x79u8, 0x6Fu8,
+        0x00u8, 0x00u8, 0x00u8, 0x04u8,
+        0x68u8, 0x6Fu8, 0x67u8, 0x65u8,
+    ];
+
+    #[cfg(feature = "std")]
+    fn create_input_stream_value_interned() -> Box<dyn Read> {
+        Box::new(Cursor::new(SERIALIZED_VALUE_INTERNED))
+    }
+
     const BASE_CHECK_ARRAY: &[u32] = &[0x00002AFFu32, 0x0000FE18u32];
 
-    fn base_check_array_of<Value: 'static>(storage: &dyn Storage<Value>) -> Vec<u32> {
+    fn base_check_array_of<Value: 'static>(storage: &dyn StorageRead<Value>) -> Vec<u32> {
         let size = storage.base_check_size().unwrap();
         let mut array = Vec::<u32>::with_capacity(size);
         for i in 0..size {
@@ -351,6 +1030,7 @@ mod tests {
         array
     }
 
+    #[cfg(feature = "std")]
     #[rustfmt::skip]
     const SERIALIZED_BROKEN: &[u8] = &[
         0x00u8, 0x00u8, 0x00u8, 0x02u8,
@@ -358,10 +1038,32 @@ mod tests {
         0x89u8,
     ];
 
+    #[cfg(feature = "std")]
     fn create_input_stream_broken() -> Box<dyn Read> {
         Box::new(Cursor::new(SERIALIZED_BROKEN))
     }
 
+    #[test]
+    fn from_raw_parts() {
+        let storage = MemoryStorage::from_raw_parts(
+            BASE_CHECK_ARRAY.to_vec(),
+            vec![
+                None,
+                Some(String::from("piyo")),
+                Some(String::from("fuga")),
+                None,
+                Some(String::from("hoge")),
+            ],
+        );
+
+        assert_eq!(base_check_array_of(&storage), BASE_CHECK_ARRAY);
+        assert_eq!(storage.value_count().unwrap(), 5);
+        assert!(storage.value_at(0).unwrap().is_none());
+        assert_eq!(storage.value_at(1).unwrap().unwrap().as_ref(), "piyo");
+        assert_eq!(storage.value_at(4).unwrap().unwrap().as_ref(), "hoge");
+    }
+
+    #[cfg(feature = "std")]
     #[test]
     fn new_with_reader() {
         {
@@ -392,6 +1094,38 @@ mod tests {
             assert_eq!(*storage.value_at(2).unwrap().unwrap(), 14);
             assert_eq!(*storage.value_at(1).unwrap().unwrap(), 159);
         }
+        {
+            let mut reader = create_input_stream_value_interned();
+            let mut deserializer = ValueDeserializer::new(Box::new(|serialized| {
+                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                    LazyLock::new(|| StringDeserializer::new(false));
+                STRING_DESERIALIZER.deserialize(serialized)
+            }));
+            let storage = MemoryStorage::new_with_reader(&mut reader, &mut deserializer).unwrap();
+
+            assert_eq!(base_check_array_of(&storage), BASE_CHECK_ARRAY);
+            assert_eq!(storage.value_count().unwrap(), 5);
+            assert!(storage.value_at(0).unwrap().is_none());
+            assert_eq!(storage.value_at(1).unwrap().unwrap().as_ref(), "piyo");
+            assert_eq!(storage.value_at(2).unwrap().unwrap().as_ref(), "hoge");
+            assert!(storage.value_at(3).unwrap().is_none());
+            assert!(Rc::ptr_eq(
+                &storage.value_at(2).unwrap().unwrap(),
+                &storage.value_at(4).unwrap().unwrap()
+            ));
+        }
+        {
+            let mut serialized = SERIALIZED_VALUE_INTERNED.to_vec();
+            serialized[39] = 0x02;
+            let mut reader = Cursor::new(serialized);
+            let mut deserializer = ValueDeserializer::new(Box::new(|serialized| {
+                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                    LazyLock::new(|| StringDeserializer::new(false));
+                STRING_DESERIALIZER.deserialize(serialized)
+            }));
+            let result = MemoryStorage::new_with_reader(&mut reader, &mut deserializer);
+            assert!(result.is_err());
+        }
         {
             let mut reader = create_input_stream_broken();
             let mut deserializer = ValueDeserializer::new(Box::new(|serialized| {
@@ -404,6 +1138,216 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn new_with_reader_lenient() {
+        fn failing_deserializer(broken: &'static str) -> ValueDeserializer<String> {
+            ValueDeserializer::new(Box::new(move |serialized| {
+                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                    LazyLock::new(|| StringDeserializer::new(false));
+                let value = STRING_DESERIALIZER.deserialize(serialized)?;
+                if value == broken {
+                    return Err(anyhow::anyhow!("broken value"));
+                }
+                Ok(value)
+            }))
+        }
+
+        {
+            let mut reader = create_input_stream();
+            let mut deserializer = failing_deserializer("");
+            let (storage, report) =
+                MemoryStorage::new_with_reader_lenient(&mut reader, &mut deserializer).unwrap();
+
+            assert!(report.is_clean());
+            assert_eq!(base_check_array_of(&storage), BASE_CHECK_ARRAY);
+            assert_eq!(storage.value_at(4).unwrap().unwrap().as_ref(), "hoge");
+        }
+        {
+            let mut reader = create_input_stream();
+            let mut deserializer = failing_deserializer("fuga");
+            let (storage, report) =
+                MemoryStorage::new_with_reader_lenient(&mut reader, &mut deserializer).unwrap();
+
+            assert_eq!(report.corrupt_values().len(), 1);
+            assert_eq!(report.corrupt_values()[0].0, 2);
+            assert_eq!(report.corrupt_values()[0].1.to_string(), "broken value");
+            assert_eq!(storage.value_at(4).unwrap().unwrap().as_ref(), "hoge");
+            assert!(storage.value_at(2).unwrap().is_none());
+            assert_eq!(storage.value_at(1).unwrap().unwrap().as_ref(), "piyo");
+        }
+        {
+            let mut reader = create_input_stream_value_interned();
+            let mut deserializer = failing_deserializer("hoge");
+            let (storage, report) =
+                MemoryStorage::new_with_reader_lenient(&mut reader, &mut deserializer).unwrap();
+
+            assert_eq!(report.corrupt_values().len(), 2);
+            assert_eq!(report.corrupt_values()[0].0, 2);
+            assert_eq!(report.corrupt_values()[0].1.to_string(), "broken value");
+            assert_eq!(report.corrupt_values()[1].0, 4);
+            assert!(matches!(
+                report.corrupt_values()[1]
+                    .1
+                    .downcast_ref::<MemoryStorageError>(),
+                Some(MemoryStorageError::SharedCorruptValue)
+            ));
+            assert_eq!(storage.value_at(1).unwrap().unwrap().as_ref(), "piyo");
+            assert!(storage.value_at(2).unwrap().is_none());
+            assert!(storage.value_at(4).unwrap().is_none());
+        }
+        {
+            let mut serialized = SERIALIZED_VALUE_INTERNED.to_vec();
+            serialized[39] = 0x02;
+            let mut reader = Cursor::new(serialized);
+            let mut deserializer = failing_deserializer("");
+            let (storage, report) =
+                MemoryStorage::new_with_reader_lenient(&mut reader, &mut deserializer).unwrap();
+
+            assert_eq!(report.corrupt_values().len(), 1);
+            assert_eq!(report.corrupt_values()[0].0, 4);
+            assert!(matches!(
+                report.corrupt_values()[0]
+                    .1
+                    .downcast_ref::<MemoryStorageError>(),
+                Some(MemoryStorageError::ValuePoolIndexOutOfRange)
+            ));
+            assert_eq!(storage.value_at(2).unwrap().unwrap().as_ref(), "hoge");
+            assert!(storage.value_at(4).unwrap().is_none());
+        }
+        {
+            let mut reader = create_input_stream_broken();
+            let mut deserializer = failing_deserializer("");
+            let result = MemoryStorage::new_with_reader_lenient(&mut reader, &mut deserializer);
+            assert!(result.is_err());
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn new_with_value_reader() {
+        let mut deserializer = ValueDeserializer::new(Box::new(|serialized| {
+            static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                LazyLock::new(|| StringDeserializer::new(false));
+            STRING_DESERIALIZER.deserialize(serialized)
+        }));
+        {
+            let values = MemoryStorage::from_raw_parts(
+                Vec::new(),
+                vec![
+                    None,
+                    Some(String::from("PIYO")),
+                    Some(String::from("FUGA")),
+                    None,
+                    Some(String::from("HOGE")),
+                ],
+            );
+            let mut writer = Cursor::new(Vec::<u8>::new());
+            let mut serializer = ValueSerializer::<String>::new(
+                Box::new(|value: &String| {
+                    static STR_SERIALIZER: LazyLock<StrSerializer> =
+                        LazyLock::new(|| StrSerializer::new(false));
+                    STR_SERIALIZER.serialize(&value.as_str())
+                }),
+                0,
+            );
+            values
+                .serialize_values_only(&mut writer, &mut serializer)
+                .unwrap();
+
+            let mut base_check_reader = create_input_stream();
+            let mut value_reader = Cursor::new(writer.into_inner());
+            let storage = MemoryStorage::new_with_value_reader(
+                &mut base_check_reader,
+                &mut value_reader,
+                &mut deserializer,
+            )
+            .unwrap();
+
+            assert_eq!(base_check_array_of(&storage), BASE_CHECK_ARRAY);
+            assert_eq!(storage.value_count().unwrap(), 5);
+            assert_eq!(storage.value_at(4).unwrap().unwrap().as_ref(), "HOGE");
+            assert_eq!(storage.value_at(2).unwrap().unwrap().as_ref(), "FUGA");
+            assert!(storage.value_at(3).unwrap().is_none());
+        }
+        {
+            let mut base_check_reader = create_input_stream();
+            let mut value_reader = Cursor::new(&SERIALIZED[12..]);
+            let storage = MemoryStorage::new_with_value_reader(
+                &mut base_check_reader,
+                &mut value_reader,
+                &mut deserializer,
+            )
+            .unwrap();
+
+            assert_eq!(storage.value_at(4).unwrap().unwrap().as_ref(), "hoge");
+        }
+        {
+            let mut serialized_values = SERIALIZED[12..].to_vec();
+            serialized_values[3] = 0x04;
+            serialized_values.truncate(serialized_values.len() - 8);
+            let mut base_check_reader = create_input_stream();
+            let mut value_reader = Cursor::new(serialized_values);
+            let error = MemoryStorage::new_with_value_reader(
+                &mut base_check_reader,
+                &mut value_reader,
+                &mut deserializer,
+            )
+            .unwrap_err();
+
+            assert!(matches!(
+                error.downcast_ref::<MemoryStorageError>(),
+                Some(MemoryStorageError::ValueCountMismatch)
+            ));
+        }
+    }
+
+    #[test]
+    fn format_version() {
+        {
+            let storage = MemoryStorage::<String>::new();
+
+            assert_eq!(storage.format_version(), FormatVersion::LATEST);
+        }
+        #[cfg(feature = "std")]
+        {
+            let mut reader = create_input_stream();
+            let mut deserializer = ValueDeserializer::new(Box::new(|serialized| {
+                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                    LazyLock::new(|| StringDeserializer::new(false));
+                STRING_DESERIALIZER.deserialize(serialized)
+            }));
+            let storage = MemoryStorage::new_with_reader(&mut reader, &mut deserializer).unwrap();
+
+            assert_eq!(storage.format_version(), FormatVersion::V1);
+        }
+        #[cfg(feature = "std")]
+        {
+            let mut reader = create_input_stream_value_interned();
+            let mut deserializer = ValueDeserializer::new(Box::new(|serialized| {
+                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                    LazyLock::new(|| StringDeserializer::new(false));
+                STRING_DESERIALIZER.deserialize(serialized)
+            }));
+            let storage = MemoryStorage::new_with_reader(&mut reader, &mut deserializer).unwrap();
+
+            assert_eq!(storage.format_version(), FormatVersion::V2);
+        }
+        #[cfg(feature = "std")]
+        {
+            let mut serialized = SERIALIZED.to_vec();
+            serialized[16] = 0x20;
+            let mut reader = Cursor::new(serialized);
+            let mut deserializer = ValueDeserializer::new(Box::new(|serialized| {
+                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                    LazyLock::new(|| StringDeserializer::new(false));
+                STRING_DESERIALIZER.deserialize(serialized)
+            }));
+            let result = MemoryStorage::new_with_reader(&mut reader, &mut deserializer);
+            assert!(result.is_err());
+        }
+    }
+
     #[test]
     fn base_check_size() {
         {
@@ -422,6 +1366,7 @@ mod tests {
         let storage = MemoryStorage::<u32>::new();
 
         assert_eq!(storage.base_at(42).unwrap(), 0);
+        assert!(storage.base_at(usize::MAX).is_err());
     }
 
     #[test]
@@ -471,6 +1416,33 @@ mod tests {
         assert!(storage.value_at(42).unwrap().is_none());
     }
 
+    #[test]
+    fn value_ref_at() {
+        let mut storage = MemoryStorage::<u32>::new();
+        storage.add_value_at(1, 159).unwrap();
+
+        assert!(storage.value_ref_at(0).unwrap().is_none());
+        let value = storage.value_ref_at(1).unwrap().unwrap();
+        assert!(matches!(value, ValueRef::Borrowed(_)));
+        assert_eq!(*value, 159);
+        assert!(storage.value_ref_at(42).unwrap().is_none());
+    }
+
+    #[test]
+    fn values_at() {
+        let mut storage = MemoryStorage::<u32>::new();
+        storage.add_value_at(1, 159).unwrap();
+        storage.add_value_at(3, 14).unwrap();
+
+        let values = storage
+            .values_at(0..5)
+            .unwrap()
+            .into_iter()
+            .map(|value| value.map(|value| *value))
+            .collect::<Vec<_>>();
+        assert_eq!(values, [None, Some(159), None, Some(14), None]);
+    }
+
     #[test]
     fn add_value_at() {
         let mut storage = MemoryStorage::<String>::new();
@@ -490,6 +1462,10 @@ mod tests {
 
         assert_eq!(storage.value_at(0).unwrap().unwrap().as_ref(), "piyo");
         assert_eq!(storage.value_at(42).unwrap().unwrap().as_ref(), "fuga");
+
+        assert!(storage
+            .add_value_at(usize::MAX, String::from("hoge"))
+            .is_err());
     }
 
     #[test]
@@ -511,6 +1487,7 @@ mod tests {
         assert!((storage.filling_rate().unwrap() - 3.0 / 9.0).abs() < 0.1);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn serialize() {
         {
@@ -594,6 +1571,193 @@ mod tests {
             let serialized = writer.get_ref();
             assert_eq!(serialized.as_slice(), EXPECTED);
         }
+        {
+            let mut storage = MemoryStorage::<String>::new();
+
+            storage.set_base_at(0, 42).unwrap();
+            storage.set_base_at(1, 0xFE).unwrap();
+            storage.set_check_at(1, 24).unwrap();
+
+            storage.add_value_at(4, String::from("hoge")).unwrap();
+            storage.add_value_at(2, String::from("hoge")).unwrap();
+            storage.add_value_at(1, String::from("piyo")).unwrap();
+
+            let mut writer = Cursor::new(Vec::<u8>::new());
+            let mut serializer = ValueSerializer::<String>::new_with_value_interning(
+                Box::new(|value: &String| {
+                    static STR_SERIALIZER: LazyLock<StrSerializer> =
+                        LazyLock::new(|| StrSerializer::new(false));
+                    STR_SERIALIZER.serialize(&value.as_str())
+                }),
+                0,
+            );
+            let result = storage.serialize(&mut writer, &mut serializer);
+            assert!(result.is_ok());
+
+            let serialized = writer.get_ref();
+            assert_eq!(serialized.as_slice(), SERIALIZED_VALUE_INTERNED);
+        }
+    }
+
+    #[cfg(feature = "lz4")]
+    #[cfg(feature = "std")]
+    #[test]
+    fn serialize_compressed() {
+        use crate::value_compression::ValueCompression;
+
+        let mut storage = MemoryStorage::<String>::new();
+        storage.set_base_at(0, 42).unwrap();
+        for i in (0..150usize).filter(|i| i % 7 != 0) {
+            storage
+                .add_value_at(i, format!("value-{}", i % 10))
+                .unwrap();
+        }
+
+        for (value_interning, fixed_value_size) in [(false, 0), (true, 0), (false, 7), (true, 7)] {
+            let serialize = Box::new(|value: &String| {
+                static STR_SERIALIZER: LazyLock<StrSerializer> =
+                    LazyLock::new(|| StrSerializer::new(false));
+                STR_SERIALIZER.serialize(&value.as_str())
+            });
+            let mut serializer = if value_interning {
+                ValueSerializer::new_with_value_interning(serialize, fixed_value_size)
+            } else {
+                ValueSerializer::new(serialize, fixed_value_size)
+            }
+            .compressed_with(ValueCompression::Lz4);
+            let mut writer = Cursor::new(Vec::<u8>::new());
+            storage.serialize(&mut writer, &mut serializer).unwrap();
+
+            let mut reader = Cursor::new(writer.into_inner());
+            let mut deserializer = ValueDeserializer::new(Box::new(|serialized| {
+                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                    LazyLock::new(|| StringDeserializer::new(false));
+                STRING_DESERIALIZER.deserialize(serialized)
+            }));
+            let deserialized =
+                MemoryStorage::<String>::new_with_reader(&mut reader, &mut deserializer).unwrap();
+
+            assert_eq!(deserialized.format_version(), FormatVersion::V3);
+            assert_eq!(deserialized.base_at(0).unwrap(), 42);
+            assert_eq!(deserialized.value_count().unwrap(), 150);
+            for i in 0..150usize {
+                let value = deserialized.value_at(i).unwrap();
+                if i % 7 == 0 {
+                    assert!(value.is_none());
+                } else {
+                    assert_eq!(*value.unwrap(), format!("value-{}", i % 10));
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn serialize_values_only() {
+        let mut storage = MemoryStorage::<String>::new();
+
+        storage.set_base_at(0, 42).unwrap();
+        storage.set_base_at(1, 0xFE).unwrap();
+        storage.set_check_at(1, 24).unwrap();
+
+        storage.add_value_at(4, String::from("hoge")).unwrap();
+        storage.add_value_at(2, String::from("fuga")).unwrap();
+        storage.add_value_at(1, String::from("piyo")).unwrap();
+
+        let mut writer = Cursor::new(Vec::<u8>::new());
+        let mut serializer = ValueSerializer::<String>::new(
+            Box::new(|value: &String| {
+                static STR_SERIALIZER: LazyLock<StrSerializer> =
+                    LazyLock::new(|| StrSerializer::new(false));
+                STR_SERIALIZER.serialize(&value.as_str())
+            }),
+            0,
+        );
+        storage
+            .serialize_values_only(&mut writer, &mut serializer)
+            .unwrap();
+
+        assert_eq!(writer.get_ref().as_slice(), &SERIALIZED[12..]);
+
+        storage.set_base_at(2, -43).unwrap();
+        storage.set_check_at(2, KEY_TERMINATOR).unwrap();
+        let error = storage
+            .serialize_values_only(&mut Cursor::new(Vec::<u8>::new()), &mut serializer)
+            .unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<StorageSerializationError>(),
+            Some(StorageSerializationError::ValuesInlined)
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn serialize_with_progress() {
+        let make_storage = || {
+            let mut storage = MemoryStorage::<u32>::new();
+            storage.set_base_at(0x8000, 42).unwrap();
+            storage.add_value_at(0, 3).unwrap();
+            storage
+        };
+        let make_serializer = || {
+            ValueSerializer::<u32>::new(
+                Box::new(|value| {
+                    static INTEGER_SERIALIZER: LazyLock<IntegerSerializer<u32>> =
+                        LazyLock::new(|| IntegerSerializer::new(false));
+                    INTEGER_SERIALIZER.serialize(value)
+                }),
+                size_of::<u32>(),
+            )
+        };
+        {
+            let storage = make_storage();
+
+            let mut writer = Cursor::new(Vec::<u8>::new());
+            let mut reports = Vec::new();
+            storage
+                .serialize_with_progress(
+                    &mut writer,
+                    &mut make_serializer(),
+                    &mut |bytes_written, total_estimate| {
+                        reports.push((bytes_written, total_estimate));
+                        ControlFlow::Continue(())
+                    },
+                )
+                .unwrap();
+
+            let mut expected_writer = Cursor::new(Vec::<u8>::new());
+            storage
+                .serialize(&mut expected_writer, &mut make_serializer())
+                .unwrap();
+            let serialized_size = expected_writer.get_ref().len();
+            assert_eq!(writer.get_ref(), expected_writer.get_ref());
+            assert!(reports.len() >= 2);
+            assert!(reports
+                .windows(2)
+                .all(|pair| pair[0].0 < pair[1].0 && pair[0].0 <= pair[0].1));
+            assert_eq!(reports.last(), Some(&(serialized_size, serialized_size)));
+        }
+        {
+            let storage = make_storage();
+
+            let mut writer = Cursor::new(Vec::<u8>::new());
+            let mut report_count = 0;
+            let result = storage.serialize_with_progress(
+                &mut writer,
+                &mut make_serializer(),
+                &mut |_, _| {
+                    report_count += 1;
+                    ControlFlow::Break(())
+                },
+            );
+            assert!(matches!(
+                result
+                    .unwrap_err()
+                    .downcast_ref::<StorageSerializationError>(),
+                Some(StorageSerializationError::Cancelled)
+            ));
+            assert_eq!(report_count, 1);
+        }
     }
 
     #[test]
@@ -629,4 +1793,17 @@ mod tests {
 
         let _ = storage.as_any_mut();
     }
+
+    #[test]
+    fn as_storage_mut() {
+        let mut storage = MemoryStorage::<u32>::new();
+
+        storage
+            .as_storage_mut()
+            .unwrap()
+            .set_base_at(0, 42)
+            .unwrap();
+
+        assert_eq!(storage.base_at(0).unwrap(), 42);
+    }
 }
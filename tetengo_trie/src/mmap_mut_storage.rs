@@ -0,0 +1,777 @@
+/*!
+ * A mutable mmap storage.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::fs::File;
+use std::rc::Rc;
+
+use anyhow::Result;
+use memmap2::MmapMut;
+
+use crate::byte_writer::ByteWriter;
+use crate::double_array::has_inlined_values;
+use crate::format_version::{FormatVersion, FORMAT_FLAG_MASK};
+use crate::memory_storage::MemoryStorage;
+use crate::storage::{StorageError, StorageMut, StorageRead, StorageSerializationError};
+use crate::value_serializer::{ValueDeserializer, ValueSerializer};
+
+/**
+ * A mutable mmap storage error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum MmapMutStorageError {
+    /**
+     * The content offset is greater than the file size.
+     */
+    #[error("the content offset is greater than the file size")]
+    InvalidContentOffset,
+
+    /**
+     * The content is out of the file size.
+     */
+    #[error("the content is out of the file size")]
+    ContentOutOfFileSize,
+
+    /**
+     * The format version is not supported.
+     */
+    #[error("the format version is not supported")]
+    UnsupportedFormatVersion,
+
+    /**
+     * The value size is not fixed.
+     */
+    #[error("the value size is not fixed")]
+    ValueSizeNotFixed,
+
+    /**
+     * The values are interned or compressed.
+     */
+    #[error("the values are interned or compressed")]
+    ValuesNotPlain,
+
+    /**
+     * The size of a serialized value does not match the fixed value size.
+     */
+    #[error("the value size does not match the fixed value size")]
+    ValueSizeMismatch,
+
+    /**
+     * A base-check index or a value index is out of range.
+     */
+    #[error("an index is out of range")]
+    IndexOutOfRange,
+}
+
+impl StorageError for MmapMutStorageError {}
+
+/**
+ * A mutable mmap storage.
+ *
+ * Maps a file of the serialized memory storage format writable, so that the
+ * base-check array and the values can be updated in place. This suits the
+ * dictionaries whose values are updated at runtime, such as counters and
+ * frequencies, without serializing the whole storage again.
+ *
+ * Only the values of a fixed size, neither interned nor compressed, are
+ * supported. The file is not resized: the base-check indexes and the value
+ * indexes must be in the ranges of the file.
+ *
+ * The modifications are visible to the other mappings of the file at once,
+ * but they are durable only after `flush()`.
+ *
+ * The clones made with `clone_box()` are memory storages not mapped. The
+ * values failed to be deserialized are left absent in them.
+ *
+ * # Type Parameters
+ * * `Value` - A value type.
+ */
+#[derive(Debug)]
+pub struct MmapMutStorage<Value: Clone + 'static> {
+    mmap: MmapMut,
+    content_offset: usize,
+    base_check_count: usize,
+    value_count: usize,
+    fixed_value_size: usize,
+    value_serializer: ValueSerializer<'static, Value>,
+    value_deserializer: RefCell<ValueDeserializer<Value>>,
+}
+
+impl<Value: Clone + Debug + 'static> MmapMutStorage<Value> {
+    /**
+     * Creates a mutable mmap storage.
+     *
+     * # Arguments
+     * * `file`               - A file opened for reading and writing.
+     * * `content_offset`     - A content offset in the file.
+     * * `value_serializer`   - A serializer for value objects to write.
+     * * `value_deserializer` - A deserializer for value objects to read.
+     *
+     * # Errors
+     * * When it fails to memory-map the file.
+     * * When the content is not of a supported format.
+     * * When the fixed value size of the serializer does not match the
+     *   content.
+     */
+    pub fn new(
+        file: &File,
+        content_offset: usize,
+        value_serializer: ValueSerializer<'static, Value>,
+        value_deserializer: ValueDeserializer<Value>,
+    ) -> Result<Self> {
+        let mmap = unsafe { MmapMut::map_mut(file)? };
+        if content_offset > mmap.len() {
+            return Err(MmapMutStorageError::InvalidContentOffset.into());
+        }
+        let content = &mmap[content_offset..];
+        let content_len = content.len();
+
+        let base_check_count = Self::u32_at(content, 0)? as usize;
+        let value_count = Self::u32_at(content, size_of::<u32>() * (1 + base_check_count))?;
+        let fixed_value_size_and_flags =
+            Self::u32_at(content, size_of::<u32>() * (1 + base_check_count + 1))?;
+        if FormatVersion::of_fixed_value_size_field(fixed_value_size_and_flags).is_none() {
+            return Err(MmapMutStorageError::UnsupportedFormatVersion.into());
+        }
+        if fixed_value_size_and_flags & FORMAT_FLAG_MASK != 0 {
+            return Err(MmapMutStorageError::ValuesNotPlain.into());
+        }
+        let fixed_value_size = fixed_value_size_and_flags as usize;
+        if fixed_value_size == 0 {
+            return Err(MmapMutStorageError::ValueSizeNotFixed.into());
+        }
+        if value_serializer.fixed_value_size() != fixed_value_size {
+            return Err(MmapMutStorageError::ValueSizeMismatch.into());
+        }
+
+        let self_ = Self {
+            mmap,
+            content_offset,
+            base_check_count,
+            value_count: value_count as usize,
+            fixed_value_size,
+            value_serializer,
+            value_deserializer: RefCell::new(value_deserializer),
+        };
+        if self_.content_size() > content_len {
+            return Err(MmapMutStorageError::ContentOutOfFileSize.into());
+        }
+        Ok(self_)
+    }
+
+    fn u32_at(content: &[u8], offset: usize) -> Result<u32> {
+        let Some(bytes) = content.get(offset..offset + size_of::<u32>()) else {
+            return Err(MmapMutStorageError::ContentOutOfFileSize.into());
+        };
+        Ok(u32::from_be_bytes(bytes.try_into()?))
+    }
+}
+
+impl<Value: Clone + 'static> MmapMutStorage<Value> {
+    /**
+     * Flushes the modifications to the file.
+     *
+     * # Errors
+     * * When it fails to flush the modifications.
+     */
+    pub fn flush(&self) -> Result<()> {
+        self.mmap.flush()?;
+        Ok(())
+    }
+
+    const UNINITIALIZED_BYTE: u8 = 0xFF;
+
+    fn content(&self) -> &[u8] {
+        &self.mmap[self.content_offset..self.content_offset + self.content_size()]
+    }
+
+    fn content_size(&self) -> usize {
+        self.value_array_offset() + self.fixed_value_size * self.value_count
+    }
+
+    fn value_array_offset(&self) -> usize {
+        size_of::<u32>() * (1 + self.base_check_count + 2)
+    }
+
+    fn base_check_offset(&self, base_check_index: usize) -> Result<usize> {
+        if base_check_index >= self.base_check_count {
+            return Err(MmapMutStorageError::IndexOutOfRange.into());
+        }
+        Ok(self.content_offset + size_of::<u32>() * (1 + base_check_index))
+    }
+
+    fn read_base_check(&self, base_check_index: usize) -> Result<u32> {
+        let offset = self.base_check_offset(base_check_index)?;
+        Ok(u32::from_be_bytes(
+            self.mmap[offset..offset + size_of::<u32>()].try_into()?,
+        ))
+    }
+
+    fn write_base_check(&mut self, base_check_index: usize, base_check: u32) -> Result<()> {
+        let offset = self.base_check_offset(base_check_index)?;
+        self.mmap[offset..offset + size_of::<u32>()].copy_from_slice(&base_check.to_be_bytes());
+        Ok(())
+    }
+
+    fn value_offset(&self, value_index: usize) -> Result<usize> {
+        if value_index >= self.value_count {
+            return Err(MmapMutStorageError::IndexOutOfRange.into());
+        }
+        Ok(self.content_offset + self.value_array_offset() + self.fixed_value_size * value_index)
+    }
+
+    fn read_value(&self, value_index: usize) -> Result<Option<Rc<Value>>> {
+        let offset = self.value_offset(value_index)?;
+        let serialized = &self.mmap[offset..offset + self.fixed_value_size];
+        if serialized.iter().all(|&b| b == Self::UNINITIALIZED_BYTE) {
+            return Ok(None);
+        }
+        Ok(Some(Rc::new(
+            self.value_deserializer
+                .borrow_mut()
+                .deserialize(serialized)?,
+        )))
+    }
+}
+
+impl<Value: Clone + Debug + 'static> StorageRead<Value> for MmapMutStorage<Value> {
+    fn base_check_size(&self) -> Result<usize> {
+        Ok(self.base_check_count)
+    }
+
+    fn base_at(&self, base_check_index: usize) -> Result<i32> {
+        let base_check = self.read_base_check(base_check_index)?;
+        Ok((base_check as i32) >> 8)
+    }
+
+    fn check_at(&self, base_check_index: usize) -> Result<u8> {
+        let base_check = self.read_base_check(base_check_index)?;
+        Ok((base_check & 0xFF) as u8)
+    }
+
+    fn value_count(&self) -> Result<usize> {
+        Ok(self.value_count)
+    }
+
+    fn value_at(&self, value_index: usize) -> Result<Option<Rc<Value>>> {
+        self.read_value(value_index)
+    }
+
+    fn filling_rate(&self) -> Result<f64> {
+        let mut empty_count = 0usize;
+        for i in 0..self.base_check_count {
+            if self.read_base_check(i)? == 0x000000FF {
+                empty_count += 1;
+            }
+        }
+        Ok(1.0 - (empty_count as f64) / (self.base_check_count as f64))
+    }
+
+    fn serialize(
+        &self,
+        writer: &mut dyn ByteWriter,
+        _: &mut ValueSerializer<'_, Value>,
+    ) -> Result<()> {
+        // The content is already in the serialized format.
+        writer.write_bytes(self.content())
+    }
+
+    fn serialize_values_only(
+        &self,
+        writer: &mut dyn ByteWriter,
+        _: &mut ValueSerializer<'_, Value>,
+    ) -> Result<()> {
+        if has_inlined_values(self)? {
+            return Err(StorageSerializationError::ValuesInlined.into());
+        }
+        writer.write_bytes(&self.content()[size_of::<u32>() * (1 + self.base_check_count)..])
+    }
+
+    fn clone_box(&self) -> Box<dyn StorageRead<Value>> {
+        let mut clone = MemoryStorage::new();
+        for i in 0..self.base_check_count {
+            if let Ok(base_check) = self.read_base_check(i) {
+                let _result = clone.set_base_at(i, (base_check as i32) >> 8);
+                let _result = clone.set_check_at(i, (base_check & 0xFF) as u8);
+            }
+        }
+        for i in 0..self.value_count {
+            if let Ok(Some(value)) = self.read_value(i) {
+                let _result = clone.add_value_at(i, value.as_ref().clone());
+            }
+        }
+        Box::new(clone)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn as_storage_mut(&mut self) -> Option<&mut dyn StorageMut<Value>> {
+        Some(self)
+    }
+}
+
+impl<Value: Clone + Debug + 'static> StorageMut<Value> for MmapMutStorage<Value> {
+    fn set_base_at(&mut self, base_check_index: usize, base: i32) -> Result<()> {
+        let base_check = self.read_base_check(base_check_index)?;
+        self.write_base_check(
+            base_check_index,
+            (base_check & 0x000000FF) | ((base as u32) << 8),
+        )
+    }
+
+    fn set_check_at(&mut self, base_check_index: usize, check: u8) -> Result<()> {
+        let base_check = self.read_base_check(base_check_index)?;
+        self.write_base_check(base_check_index, (base_check & 0xFFFFFF00) | check as u32)
+    }
+
+    fn add_value_at(&mut self, value_index: usize, value: Value) -> Result<()> {
+        let offset = self.value_offset(value_index)?;
+        let serialized = self.value_serializer.serialize(&value)?;
+        if serialized.len() != self.fixed_value_size {
+            return Err(MmapMutStorageError::ValueSizeMismatch.into());
+        }
+        self.mmap[offset..offset + self.fixed_value_size].copy_from_slice(&serialized);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+    use std::sync::LazyLock;
+
+    use tempfile::tempfile;
+
+    use crate::integer_serializer::{IntegerDeserializer, IntegerSerializer};
+    use crate::serializer::{Deserializer, Serializer, KEY_TERMINATOR};
+    use crate::string_serializer::{StrSerializer, StringDeserializer, StringSerializer};
+    use crate::trie::Trie;
+
+    use super::*;
+
+    #[rustfmt::skip]
+    const SERIALIZED: &[u8] = &[
+        0x00u8, 0x00u8, 0x00u8, 0x02u8,
+        0x00u8, 0x00u8, 0x2Au8, 0xFFu8,
+        0x00u8, 0x00u8, 0xFEu8, 0x18u8,
+        0x00u8, 0x00u8, 0x00u8, 0x05u8,
+        0x00u8, 0x00u8, 0x00u8, 0x04u8,
+        0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8,
+        0x00u8, 0x00u8, 0x00u8, 0x9Fu8,
+        0x00u8, 0x00u8, 0x00u8, 0x0Eu8,
+        0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8,
+        0x00u8, 0x00u8, 0x00u8, 0x03u8,
+    ];
+
+    #[rustfmt::skip]
+    const SERIALIZED_WITH_HEADER: &[u8] = &[
+        // header
+        0x01u8, 0x23u8, 0x45u8, 0x67u8, 0x89u8,
+
+        // content
+        0x00u8, 0x00u8, 0x00u8, 0x02u8,
+        0x00u8, 0x00u8, 0x2Au8, 0xFFu8,
+        0x00u8, 0x00u8, 0xFEu8, 0x18u8,
+        0x00u8, 0x00u8, 0x00u8, 0x05u8,
+        0x00u8, 0x00u8, 0x00u8, 0x04u8,
+        0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8,
+        0x00u8, 0x00u8, 0x00u8, 0x9Fu8,
+        0x00u8, 0x00u8, 0x00u8, 0x0Eu8,
+        0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8,
+        0x00u8, 0x00u8, 0x00u8, 0x03u8,
+    ];
+
+    #[rustfmt::skip]
+    const SERIALIZED_VALUE_SIZE_NOT_FIXED: &[u8] = &[
+        0x00u8, 0x00u8, 0x00u8, 0x01u8,
+        0x00u8, 0x00u8, 0x00u8, 0xFFu8,
+        0x00u8, 0x00u8, 0x00u8, 0x01u8,
+        0x00u8, 0x00u8, 0x00u8, 0x00u8,
+        0x00u8, 0x00u8, 0x00u8, 0x04u8,
+        0x70u8, 0x69u8, 0x79u8, 0x6Fu8,
+    ];
+
+    #[rustfmt::skip]
+    const SERIALIZED_VALUE_INTERNED: &[u8] = &[
+        0x00u8, 0x00u8, 0x00u8, 0x01u8,
+        0x00u8, 0x00u8, 0x00u8, 0xFFu8,
+        0x00u8, 0x00u8, 0x00u8, 0x01u8,
+        0x80u8, 0x00u8, 0x00u8, 0x04u8,
+        0x00u8, 0x00u8, 0x00u8, 0x00u8,
+        0x00u8, 0x00u8, 0x00u8, 0x01u8,
+        0x00u8, 0x00u8, 0x00u8, 0x9Fu8,
+    ];
+
+    #[rustfmt::skip]
+    const SERIALIZED_TRUNCATED: &[u8] = &[
+        0x00u8, 0x00u8, 0x00u8, 0x02u8,
+        0x00u8, 0x00u8, 0x2Au8, 0xFFu8,
+        0x00u8, 0x00u8, 0xFEu8, 0x18u8,
+        0x00u8, 0x00u8, 0x00u8, 0x05u8,
+        0x00u8, 0x00u8, 0x00u8, 0x04u8,
+        0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8,
+    ];
+
+    fn make_temporary_file(initial_content: &[u8]) -> File {
+        let mut file = tempfile().unwrap();
+        file.write_all(initial_content).unwrap();
+        let _ = file.seek(SeekFrom::Start(0)).unwrap();
+        file
+    }
+
+    fn read_file(file: &mut File) -> Vec<u8> {
+        let _ = file.seek(SeekFrom::Start(0)).unwrap();
+        let mut content = Vec::new();
+        let _size = file.read_to_end(&mut content).unwrap();
+        content
+    }
+
+    fn value_serializer() -> ValueSerializer<'static, u32> {
+        ValueSerializer::<u32>::new(
+            Box::new(|value| {
+                static INTEGER_SERIALIZER: LazyLock<IntegerSerializer<u32>> =
+                    LazyLock::new(|| IntegerSerializer::new(false));
+                INTEGER_SERIALIZER.serialize(value)
+            }),
+            size_of::<u32>(),
+        )
+    }
+
+    fn value_deserializer() -> ValueDeserializer<u32> {
+        ValueDeserializer::<u32>::new(Box::new(|serialized| {
+            static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
+                LazyLock::new(|| IntegerDeserializer::new(false));
+            INTEGER_DESERIALIZER.deserialize(serialized)
+        }))
+    }
+
+    fn open(file: &File) -> Result<MmapMutStorage<u32>> {
+        MmapMutStorage::new(file, 0, value_serializer(), value_deserializer())
+    }
+
+    #[test]
+    fn new() {
+        {
+            let file = make_temporary_file(SERIALIZED);
+            assert!(open(&file).is_ok());
+        }
+        {
+            let file = make_temporary_file(SERIALIZED_WITH_HEADER);
+            let storage =
+                MmapMutStorage::new(&file, 5, value_serializer(), value_deserializer()).unwrap();
+            assert_eq!(storage.base_at(0).unwrap(), 0x2A);
+        }
+        {
+            let file = make_temporary_file(SERIALIZED);
+            let storage = MmapMutStorage::new(
+                &file,
+                SERIALIZED.len() + 1,
+                value_serializer(),
+                value_deserializer(),
+            );
+            assert!(storage.is_err());
+        }
+        {
+            let file = make_temporary_file(SERIALIZED_VALUE_SIZE_NOT_FIXED);
+            assert!(open(&file).is_err());
+        }
+        {
+            let file = make_temporary_file(SERIALIZED_VALUE_INTERNED);
+            assert!(open(&file).is_err());
+        }
+        {
+            let file = make_temporary_file(SERIALIZED_TRUNCATED);
+            assert!(open(&file).is_err());
+        }
+        {
+            let file = make_temporary_file(SERIALIZED);
+            let serializer = ValueSerializer::<u32>::new(
+                Box::new(|value| {
+                    static INTEGER_SERIALIZER: LazyLock<IntegerSerializer<u32>> =
+                        LazyLock::new(|| IntegerSerializer::new(false));
+                    INTEGER_SERIALIZER.serialize(value)
+                }),
+                0,
+            );
+            let storage = MmapMutStorage::new(&file, 0, serializer, value_deserializer());
+            assert!(storage.is_err());
+        }
+    }
+
+    #[test]
+    fn flush() {
+        let mut file = make_temporary_file(SERIALIZED);
+        let mut storage = open(&file).unwrap();
+
+        storage.add_value_at(0, 42).unwrap();
+        storage.flush().unwrap();
+
+        let content = read_file(&mut file);
+        assert_eq!(&content[20..24], &[0x00, 0x00, 0x00, 0x2A]);
+    }
+
+    #[test]
+    fn base_check_size() {
+        let file = make_temporary_file(SERIALIZED);
+        let storage = open(&file).unwrap();
+
+        assert_eq!(storage.base_check_size().unwrap(), 2);
+    }
+
+    #[test]
+    fn base_at() {
+        let file = make_temporary_file(SERIALIZED);
+        let storage = open(&file).unwrap();
+
+        assert_eq!(storage.base_at(0).unwrap(), 0x2A);
+        assert_eq!(storage.base_at(1).unwrap(), 0xFE);
+        assert!(storage.base_at(2).is_err());
+    }
+
+    #[test]
+    fn set_base_at() {
+        let file = make_temporary_file(SERIALIZED);
+        let mut storage = open(&file).unwrap();
+
+        storage.set_base_at(1, 4242).unwrap();
+
+        assert_eq!(storage.base_at(1).unwrap(), 4242);
+        assert_eq!(storage.check_at(1).unwrap(), 0x18);
+        assert!(storage.set_base_at(2, 4242).is_err());
+    }
+
+    #[test]
+    fn check_at() {
+        let file = make_temporary_file(SERIALIZED);
+        let storage = open(&file).unwrap();
+
+        assert_eq!(storage.check_at(0).unwrap(), 0xFF);
+        assert_eq!(storage.check_at(1).unwrap(), 0x18);
+        assert!(storage.check_at(2).is_err());
+    }
+
+    #[test]
+    fn set_check_at() {
+        let file = make_temporary_file(SERIALIZED);
+        let mut storage = open(&file).unwrap();
+
+        storage.set_check_at(0, 124).unwrap();
+
+        assert_eq!(storage.check_at(0).unwrap(), 124);
+        assert_eq!(storage.base_at(0).unwrap(), 0x2A);
+        assert!(storage.set_check_at(2, 124).is_err());
+    }
+
+    #[test]
+    fn value_count() {
+        let file = make_temporary_file(SERIALIZED);
+        let storage = open(&file).unwrap();
+
+        assert_eq!(storage.value_count().unwrap(), 5);
+    }
+
+    #[test]
+    fn value_at() {
+        let file = make_temporary_file(SERIALIZED);
+        let storage = open(&file).unwrap();
+
+        assert!(storage.value_at(0).unwrap().is_none());
+        assert_eq!(*storage.value_at(1).unwrap().unwrap(), 159);
+        assert_eq!(*storage.value_at(2).unwrap().unwrap(), 14);
+        assert!(storage.value_at(3).unwrap().is_none());
+        assert_eq!(*storage.value_at(4).unwrap().unwrap(), 3);
+        assert!(storage.value_at(5).is_err());
+    }
+
+    #[test]
+    fn add_value_at() {
+        {
+            let file = make_temporary_file(SERIALIZED);
+            let mut storage = open(&file).unwrap();
+
+            storage.add_value_at(3, 42).unwrap();
+            storage.add_value_at(1, 24).unwrap();
+
+            assert_eq!(*storage.value_at(1).unwrap().unwrap(), 24);
+            assert_eq!(*storage.value_at(3).unwrap().unwrap(), 42);
+            assert!(storage.add_value_at(5, 42).is_err());
+        }
+        {
+            let file = make_temporary_file(SERIALIZED);
+            let serializer = ValueSerializer::<u32>::new(Box::new(|_| vec![0x00, 0x01]), 4);
+            let mut storage =
+                MmapMutStorage::new(&file, 0, serializer, value_deserializer()).unwrap();
+
+            assert!(storage.add_value_at(0, 42).is_err());
+        }
+    }
+
+    #[test]
+    fn filling_rate() {
+        let file = make_temporary_file(SERIALIZED);
+        let storage = open(&file).unwrap();
+
+        assert_eq!(storage.filling_rate().unwrap(), 1.0);
+    }
+
+    #[test]
+    fn serialize() {
+        let file = make_temporary_file(SERIALIZED_WITH_HEADER);
+        let mut storage =
+            MmapMutStorage::new(&file, 5, value_serializer(), value_deserializer()).unwrap();
+        storage.add_value_at(0, 42).unwrap();
+
+        let mut writer = Cursor::new(Vec::<u8>::new());
+        storage
+            .serialize(&mut writer, &mut value_serializer())
+            .unwrap();
+
+        let mut expected = SERIALIZED.to_vec();
+        expected[20..24].copy_from_slice(&[0x00, 0x00, 0x00, 0x2A]);
+        assert_eq!(writer.get_ref().as_slice(), expected.as_slice());
+    }
+
+    #[test]
+    fn serialize_values_only() {
+        {
+            let file = make_temporary_file(SERIALIZED);
+            let storage = open(&file).unwrap();
+
+            let mut writer = Cursor::new(Vec::<u8>::new());
+            storage
+                .serialize_values_only(&mut writer, &mut value_serializer())
+                .unwrap();
+
+            assert_eq!(writer.get_ref().as_slice(), &SERIALIZED[12..]);
+        }
+        {
+            let file = make_temporary_file(SERIALIZED);
+            let mut storage = open(&file).unwrap();
+            storage.set_base_at(1, -43).unwrap();
+            storage.set_check_at(1, KEY_TERMINATOR).unwrap();
+
+            let mut writer = Cursor::new(Vec::<u8>::new());
+            let error = storage
+                .serialize_values_only(&mut writer, &mut value_serializer())
+                .unwrap_err();
+
+            assert!(matches!(
+                error.downcast_ref::<StorageSerializationError>(),
+                Some(StorageSerializationError::ValuesInlined)
+            ));
+        }
+    }
+
+    #[test]
+    fn clone_box() {
+        let file = make_temporary_file(SERIALIZED);
+        let mut storage = open(&file).unwrap();
+
+        let clone = storage.clone_box();
+        storage.add_value_at(0, 42).unwrap();
+
+        assert!(clone.is::<MemoryStorage<u32>>());
+        assert_eq!(clone.base_check_size().unwrap(), 2);
+        assert_eq!(clone.base_at(1).unwrap(), 0xFE);
+        assert_eq!(clone.check_at(1).unwrap(), 0x18);
+        assert!(clone.value_at(0).unwrap().is_none());
+        assert_eq!(*clone.value_at(1).unwrap().unwrap(), 159);
+    }
+
+    #[test]
+    fn as_any() {
+        let file = make_temporary_file(SERIALIZED);
+        let storage = open(&file).unwrap();
+
+        let _ = storage.as_any();
+    }
+
+    #[test]
+    fn as_any_mut() {
+        let file = make_temporary_file(SERIALIZED);
+        let mut storage = open(&file).unwrap();
+
+        let _ = storage.as_any_mut();
+    }
+
+    #[test]
+    fn as_storage_mut() {
+        let file = make_temporary_file(SERIALIZED);
+        let mut storage = open(&file).unwrap();
+
+        storage
+            .as_storage_mut()
+            .unwrap()
+            .set_base_at(0, 42)
+            .unwrap();
+
+        assert_eq!(storage.base_at(0).unwrap(), 42);
+    }
+
+    #[test]
+    fn replace_value_of_trie() {
+        let trie = Trie::<&str, String>::builder()
+            .elements(vec![
+                ("Kumamoto", String::from("0000")),
+                ("Tamana", String::from("0001")),
+            ])
+            .build()
+            .unwrap();
+        let mut serialized = Cursor::new(Vec::<u8>::new());
+        let mut serializer = ValueSerializer::<String>::new(
+            Box::new(|value: &String| {
+                static STR_SERIALIZER: LazyLock<StrSerializer> =
+                    LazyLock::new(|| StrSerializer::new(false));
+                STR_SERIALIZER.serialize(&value.as_str())
+            }),
+            4,
+        );
+        trie.storage()
+            .serialize(&mut serialized, &mut serializer)
+            .unwrap();
+        let mut file = make_temporary_file(serialized.get_ref());
+
+        {
+            let serializer = ValueSerializer::<String>::new(
+                Box::new(|value: &String| {
+                    static STRING_SERIALIZER: LazyLock<StringSerializer> =
+                        LazyLock::new(|| StringSerializer::new(false));
+                    STRING_SERIALIZER.serialize(value)
+                }),
+                4,
+            );
+            let deserializer = ValueDeserializer::<String>::new(Box::new(|serialized| {
+                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                    LazyLock::new(|| StringDeserializer::new(false));
+                STRING_DESERIALIZER.deserialize(serialized)
+            }));
+            let storage = MmapMutStorage::new(&file, 0, serializer, deserializer).unwrap();
+            let mut trie = Trie::<&str, String>::builder_with_storage(Box::new(storage)).build();
+
+            let old_value = trie.replace_value(&"Tamana", String::from("0042")).unwrap();
+
+            assert_eq!(old_value.unwrap().as_str(), "0001");
+            assert_eq!(trie.find(&"Tamana").unwrap().unwrap().as_str(), "0042");
+            trie.storage()
+                .downcast_ref::<MmapMutStorage<String>>()
+                .unwrap()
+                .flush()
+                .unwrap();
+        }
+
+        let content = read_file(&mut file);
+        assert!(content.windows(4).any(|window| window == b"0042"));
+        assert!(!content.windows(4).any(|window| window == b"0001"));
+    }
+}
@@ -7,7 +7,7 @@
 use std::any::Any;
 use std::cell::RefCell;
 use std::fmt::Debug;
-use std::io::Write;
+use std::ops::Range;
 use std::rc::Rc;
 use std::sync::LazyLock;
 
@@ -15,16 +15,28 @@ use anyhow::Result;
 use hashlink::LinkedHashMap;
 use tempfile as _;
 
+use crate::byte_writer::ByteWriter;
 use crate::file_mapping::FileMapping;
+use crate::format_version::{FormatVersion, FORMAT_FLAG_MASK};
 use crate::integer_serializer::IntegerDeserializer;
+use crate::memory_storage::{NO_VALUE_POOL_INDEX, VALUE_COMPRESSION_FLAG, VALUE_INTERNING_FLAG};
 use crate::serializer::Deserializer;
-use crate::storage::{Storage, StorageError};
+use crate::storage::{StorageError, StorageRead};
+use crate::value_compression::{ValueCompression, ValueCompressionError};
 use crate::value_serializer::{ValueDeserializer, ValueSerializer};
 
+#[derive(Clone, Debug)]
+enum CachedValue<Value> {
+    Value(Option<Rc<Value>>),
+    Broken(Rc<str>),
+}
+
 #[derive(Clone, Debug)]
 struct ValueCache<Value> {
     cache_capacity: usize,
-    map: LinkedHashMap<usize, Option<Rc<Value>>>,
+    map: LinkedHashMap<usize, CachedValue<Value>>,
+    #[cfg(feature = "metrics")]
+    hit_count: usize,
 }
 
 impl<Value> ValueCache<Value> {
@@ -32,6 +44,8 @@ impl<Value> ValueCache<Value> {
         Self {
             cache_capacity,
             map: LinkedHashMap::new(),
+            #[cfg(feature = "metrics")]
+            hit_count: 0,
         }
     }
 
@@ -39,14 +53,22 @@ impl<Value> ValueCache<Value> {
         self.map.contains_key(&index)
     }
 
-    fn at(&mut self, index: usize) -> Option<&Option<Rc<Value>>> {
+    fn at(&mut self, index: usize) -> Option<&CachedValue<Value>> {
         let _ = self.map.to_back(&index);
-        self.map.get(&index)
+        let value = self.map.get(&index);
+        #[cfg(feature = "metrics")]
+        if value.is_some() {
+            self.hit_count += 1;
+        }
+        value
     }
 
-    fn insert(&mut self, index: usize, value: Option<Rc<Value>>) {
+    fn insert(&mut self, index: usize, value: CachedValue<Value>) {
         debug_assert!(!self.has(index));
 
+        if self.cache_capacity == 0 {
+            return;
+        }
         while self.map.len() >= self.cache_capacity {
             let _popped = self.map.pop_front();
         }
@@ -55,6 +77,42 @@ impl<Value> ValueCache<Value> {
     }
 }
 
+#[derive(Clone, Copy, Debug)]
+struct CompressedValues {
+    compression: ValueCompression,
+    block_value_count: usize,
+    block_count: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ValueLayout {
+    fixed_value_size: usize,
+    value_count: usize,
+    value_array_offset: usize,
+    value_interning: bool,
+    compressed_values: Option<CompressedValues>,
+}
+
+type DecompressedBlock = (usize, Vec<u8>);
+
+// The bytes which the content is read from.
+#[derive(Clone, Debug)]
+enum Source {
+    FileMapping(Rc<FileMapping>),
+    Bytes(&'static [u8]),
+}
+
+impl Source {
+    fn region(&self, range: Range<usize>) -> Result<&[u8]> {
+        match self {
+            Source::FileMapping(file_mapping) => file_mapping.region(range),
+            Source::Bytes(bytes) => bytes
+                .get(range)
+                .ok_or_else(|| MmapStorageError::MmapRegionOutOfFileSize.into()),
+        }
+    }
+}
+
 /**
  * An mmap storage error.
  */
@@ -77,6 +135,30 @@ pub enum MmapStorageError {
      */
     #[error("the mmap region is out of the file size")]
     MmapRegionOutOfFileSize,
+
+    /**
+     * The format version is not supported.
+     */
+    #[error("the format version is not supported")]
+    UnsupportedFormatVersion,
+
+    /**
+     * A base-check index or a value index is out of range.
+     */
+    #[error("an index is out of range")]
+    IndexOutOfRange,
+
+    /**
+     * The operation is not supported.
+     */
+    #[error("the operation is not supported")]
+    UnsupportedOperation,
+
+    /**
+     * A value failed to be deserialized before.
+     */
+    #[error("the value failed to be deserialized before")]
+    BrokenValue,
 }
 
 impl StorageError for MmapStorageError {}
@@ -89,11 +171,12 @@ impl StorageError for MmapStorageError {}
 */
 #[derive(Debug)]
 pub struct MmapStorageBuilder<Value: Clone + Debug> {
-    file_mapping: Rc<FileMapping>,
+    source: Source,
     content_offset: usize,
     file_size: usize,
     value_deserializer: ValueDeserializer<Value>,
     value_cache_capacity: usize,
+    broken_value_as_none: bool,
 }
 
 impl<Value: Clone + Debug + 'static> MmapStorageBuilder<Value> {
@@ -108,6 +191,23 @@ impl<Value: Clone + Debug + 'static> MmapStorageBuilder<Value> {
         self
     }
 
+    /**
+     * Sets whether the broken values are treated as none.
+     *
+     * A value whose deserialization fails is cached as broken, so that the
+     * deserialization is not retried. When this is false, the first access
+     * returns the deserialization error, and the following accesses return
+     * `MmapStorageError::BrokenValue` with the message of it. When this is
+     * true, the accesses return none instead. The default is false.
+     *
+     * # Arguments
+     * * `value` - Set true to treat the broken values as none.
+     */
+    pub const fn broken_value_as_none(mut self, value: bool) -> Self {
+        self.broken_value_as_none = value;
+        self
+    }
+
     /**
      * Builds a mmap storage.
      *
@@ -120,11 +220,12 @@ impl<Value: Clone + Debug + 'static> MmapStorageBuilder<Value> {
      */
     pub fn build(self) -> Result<MmapStorage<Value>> {
         let self_ = MmapStorage::<Value> {
-            file_mapping: self.file_mapping,
+            source: self.source,
             content_offset: self.content_offset,
             file_size: self.file_size,
             value_deserializer: Rc::new(RefCell::new(self.value_deserializer)),
             value_cache: RefCell::new(ValueCache::new(self.value_cache_capacity)),
+            broken_value_as_none: self.broken_value_as_none,
         };
 
         if self_.content_offset > self_.file_size {
@@ -132,7 +233,12 @@ impl<Value: Clone + Debug + 'static> MmapStorageBuilder<Value> {
         }
 
         let base_check_count = self_.base_check_size()?;
-        let fixed_value_size = self_.read_u32(size_of::<u32>() * (1 + base_check_count + 1))?;
+        let fixed_value_size_and_flag =
+            self_.read_u32(size_of::<u32>() * (1 + base_check_count + 1))?;
+        if FormatVersion::of_fixed_value_size_field(fixed_value_size_and_flag).is_none() {
+            return Err(MmapStorageError::UnsupportedFormatVersion.into());
+        }
+        let (fixed_value_size, _, _) = self_.fixed_value_size_and_flags()?;
         if fixed_value_size == 0 {
             return Err(MmapStorageError::ValueSizeNotFixed.into());
         }
@@ -144,16 +250,26 @@ impl<Value: Clone + Debug + 'static> MmapStorageBuilder<Value> {
 /**
  * An mmap storage.
  *
+ * When the values are compressed, only the block containing a value is
+ * decompressed to read it.
+ *
+ * To share a file with the processes regenerating it, build the file mapping
+ * with a shared lock and the verification on each access. See `FileMapping`.
+ *
+ * The storage can also read the bytes embedded in the binary. See
+ * `builder_with_bytes`.
+ *
  * # Type Parameters
  * * `Value` - A value type.
  */
 #[derive(Debug)]
 pub struct MmapStorage<Value: Clone + Debug> {
-    file_mapping: Rc<FileMapping>,
+    source: Source,
     content_offset: usize,
     file_size: usize,
     value_deserializer: Rc<RefCell<ValueDeserializer<Value>>>,
     value_cache: RefCell<ValueCache<Value>>,
+    broken_value_as_none: bool,
 }
 
 impl<Value: Clone + Debug + 'static> MmapStorage<Value> {
@@ -179,46 +295,302 @@ impl<Value: Clone + Debug + 'static> MmapStorage<Value> {
         value_deserializer: ValueDeserializer<Value>,
     ) -> MmapStorageBuilder<Value> {
         MmapStorageBuilder::<Value> {
-            file_mapping,
+            source: Source::FileMapping(file_mapping),
             content_offset,
             file_size,
             value_deserializer,
             value_cache_capacity: Self::DEFAULT_VALUE_CACHE_CAPACITY,
+            broken_value_as_none: false,
         }
     }
 
-    fn ensure_value_cached(&self, value_index: usize) -> Result<()> {
-        if self.value_cache.borrow().has(value_index) {
-            return Ok(());
+    /**
+     * Creates an mmap storage builder reading a byte slice.
+     *
+     * The bytes have the same layout as the file of `builder`, so that a
+     * dictionary embedded with `include_bytes!` can be read without any file.
+     *
+     * # Arguments
+     * * `bytes`              - Bytes.
+     * * `content_offset`     - A content offset in the bytes.
+     * * `value_deserializer` - A deserializer for value objects.
+     *
+     * # Returns
+     * An mmap storage builder.
+     */
+    pub const fn builder_with_bytes(
+        bytes: &'static [u8],
+        content_offset: usize,
+        value_deserializer: ValueDeserializer<Value>,
+    ) -> MmapStorageBuilder<Value> {
+        MmapStorageBuilder::<Value> {
+            source: Source::Bytes(bytes),
+            content_offset,
+            file_size: bytes.len(),
+            value_deserializer,
+            value_cache_capacity: Self::DEFAULT_VALUE_CACHE_CAPACITY,
+            broken_value_as_none: false,
         }
+    }
 
-        let base_check_count = self.base_check_size()?;
-        let fixed_value_size =
-            self.read_u32(size_of::<u32>() * (1 + base_check_count + 1))? as usize;
-        let offset = size_of::<u32>() * (1 + base_check_count + 2) + fixed_value_size * value_index;
-        let serialized = self.read_bytes(offset, fixed_value_size)?;
-        if serialized == vec![Self::UNINITIALIZED_BYTE; fixed_value_size] {
-            self.value_cache.borrow_mut().insert(value_index, None);
+    fn read_value(&self, value_index: usize) -> Result<Option<Rc<Value>>> {
+        if let Some(value) = self.value_cache.borrow_mut().at(value_index) {
+            return self.cached_value(value);
+        }
+        if value_index >= self.value_count()? {
+            return Err(MmapStorageError::IndexOutOfRange.into());
+        }
+
+        let layout = self.value_layout()?;
+        let mut decompressed_block = None;
+        let serialized =
+            self.read_serialized_value(&layout, value_index, &mut decompressed_block)?;
+        self.deserialize_and_cache(value_index, serialized)
+    }
+
+    fn read_values(&self, value_indexes: Range<usize>) -> Result<Vec<Option<Rc<Value>>>> {
+        if value_indexes.end > self.value_count()? {
+            return Err(MmapStorageError::IndexOutOfRange.into());
+        }
+
+        let layout = self.value_layout()?;
+        let contiguous_region = if layout.value_interning || layout.compressed_values.is_some() {
+            None
         } else {
-            let value = self
-                .value_deserializer
-                .borrow_mut()
-                .deserialize(serialized)?;
-            self.value_cache
+            let Some((offset, size)) = layout
+                .fixed_value_size
+                .checked_mul(value_indexes.start)
+                .and_then(|offset| offset.checked_add(layout.value_array_offset))
+                .zip(layout.fixed_value_size.checked_mul(value_indexes.len()))
+            else {
+                return Err(MmapStorageError::MmapRegionOutOfFileSize.into());
+            };
+            Some(self.read_bytes(offset, size)?)
+        };
+
+        let mut decompressed_block = None;
+        let mut values = Vec::with_capacity(value_indexes.len());
+        for (i, value_index) in value_indexes.clone().enumerate() {
+            if let Some(value) = self.value_cache.borrow_mut().at(value_index) {
+                values.push(self.cached_value(value)?);
+                continue;
+            }
+            let serialized = if let Some(region) = contiguous_region {
+                let offset = layout.fixed_value_size * i;
+                Some(&region[offset..offset + layout.fixed_value_size])
+            } else {
+                self.read_serialized_value(&layout, value_index, &mut decompressed_block)?
+            };
+            values.push(self.deserialize_and_cache(value_index, serialized)?);
+        }
+        Ok(values)
+    }
+
+    fn cached_value(&self, value: &CachedValue<Value>) -> Result<Option<Rc<Value>>> {
+        match value {
+            CachedValue::Value(value) => Ok(value.clone()),
+            CachedValue::Broken(_) if self.broken_value_as_none => Ok(None),
+            CachedValue::Broken(message) => {
+                Err(anyhow::Error::from(MmapStorageError::BrokenValue).context(message.to_string()))
+            }
+        }
+    }
+
+    fn read_serialized_value<'a>(
+        &'a self,
+        layout: &ValueLayout,
+        value_index: usize,
+        decompressed_block: &'a mut Option<DecompressedBlock>,
+    ) -> Result<Option<&'a [u8]>> {
+        let (values_offset, index_in_values) = if layout.value_interning {
+            let Some(value_pool_index_offset) = size_of::<u32>()
+                .checked_mul(value_index)
+                .and_then(|offset| offset.checked_add(layout.value_array_offset))
+            else {
+                return Err(MmapStorageError::MmapRegionOutOfFileSize.into());
+            };
+            let value_pool_index = self.read_u32(value_pool_index_offset)?;
+            if value_pool_index == NO_VALUE_POOL_INDEX {
+                return Ok(None);
+            }
+            let value_pool_offset =
+                layout.value_array_offset + size_of::<u32>() * (layout.value_count + 1);
+            (value_pool_offset, value_pool_index as usize)
+        } else {
+            (layout.value_array_offset, value_index)
+        };
+        if let Some(compressed_values) = &layout.compressed_values {
+            let block_index = index_in_values / compressed_values.block_value_count;
+            if !matches!(
+                decompressed_block,
+                Some((decompressed_block_index, _)) if *decompressed_block_index == block_index
+            ) {
+                let block =
+                    self.read_compressed_block(values_offset, compressed_values, block_index)?;
+                *decompressed_block = Some((block_index, block));
+            }
+            let Some((_, block)) = decompressed_block else {
+                unreachable!("The decompressed block must be set.");
+            };
+            let offset =
+                layout.fixed_value_size * (index_in_values % compressed_values.block_value_count);
+            let Some(serialized) = block.get(offset..offset + layout.fixed_value_size) else {
+                return Err(ValueCompressionError::BrokenBlock.into());
+            };
+            Ok(Some(serialized))
+        } else {
+            let Some(offset) = layout
+                .fixed_value_size
+                .checked_mul(index_in_values)
+                .and_then(|offset| offset.checked_add(values_offset))
+            else {
+                return Err(MmapStorageError::MmapRegionOutOfFileSize.into());
+            };
+            Ok(Some(self.read_bytes(offset, layout.fixed_value_size)?))
+        }
+    }
+
+    fn deserialize_and_cache(
+        &self,
+        value_index: usize,
+        serialized: Option<&[u8]>,
+    ) -> Result<Option<Rc<Value>>> {
+        let value = match serialized {
+            Some(serialized) => self.deserialize_value(serialized),
+            None => Ok(None),
+        };
+        match value {
+            Ok(value) => {
+                self.value_cache
+                    .borrow_mut()
+                    .insert(value_index, CachedValue::Value(value.clone()));
+                Ok(value)
+            }
+            Err(e) => {
+                self.value_cache
+                    .borrow_mut()
+                    .insert(value_index, CachedValue::Broken(e.to_string().into()));
+                if self.broken_value_as_none {
+                    Ok(None)
+                } else {
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    fn deserialize_value(&self, serialized: &[u8]) -> Result<Option<Rc<Value>>> {
+        if serialized.iter().all(|&b| b == Self::UNINITIALIZED_BYTE) {
+            return Ok(None);
+        }
+        Ok(Some(Rc::new(
+            self.value_deserializer
                 .borrow_mut()
-                .insert(value_index, Some(Rc::new(value)));
+                .deserialize(serialized)?,
+        )))
+    }
+
+    fn read_compressed_block(
+        &self,
+        values_offset: usize,
+        compressed_values: &CompressedValues,
+        block_index: usize,
+    ) -> Result<Vec<u8>> {
+        if block_index >= compressed_values.block_count {
+            return Err(ValueCompressionError::BrokenBlock.into());
         }
-        Ok(())
+
+        let block_offsets_offset = values_offset + size_of::<u32>() * 3;
+        let Some(blocks_offset) = compressed_values
+            .block_count
+            .checked_add(1)
+            .and_then(|count| count.checked_mul(size_of::<u32>()))
+            .and_then(|size| size.checked_add(block_offsets_offset))
+        else {
+            return Err(MmapStorageError::MmapRegionOutOfFileSize.into());
+        };
+        let block_begin =
+            self.read_u32(block_offsets_offset + size_of::<u32>() * block_index)? as usize;
+        let block_end =
+            self.read_u32(block_offsets_offset + size_of::<u32>() * (block_index + 1))? as usize;
+        let Some(block_size) = block_end.checked_sub(block_begin) else {
+            return Err(ValueCompressionError::BrokenBlock.into());
+        };
+        let block = self.read_bytes(blocks_offset + block_begin, block_size)?;
+        let Some((decompressed_size, compressed_block)) = block.split_at_checked(size_of::<u32>())
+        else {
+            return Err(ValueCompressionError::BrokenBlock.into());
+        };
+        let decompressed_size = u32::from_be_bytes(decompressed_size.try_into()?) as usize;
+        compressed_values
+            .compression
+            .decompress(compressed_block, decompressed_size)
+    }
+
+    fn value_layout(&self) -> Result<ValueLayout> {
+        let base_check_count = self.base_check_size()?;
+        let (fixed_value_size, value_interning, value_compression) =
+            self.fixed_value_size_and_flags()?;
+        let value_array_offset = size_of::<u32>() * (1 + base_check_count + 2);
+        let value_count = self.value_count()?;
+        let compressed_values = if value_compression {
+            let values_offset = if value_interning {
+                value_array_offset + size_of::<u32>() * (value_count + 1)
+            } else {
+                value_array_offset
+            };
+            let compression = ValueCompression::of_id(self.read_u32(values_offset)?)?;
+            let block_value_count = self.read_u32(values_offset + size_of::<u32>())? as usize;
+            let block_count = self.read_u32(values_offset + size_of::<u32>() * 2)? as usize;
+            if block_value_count == 0 {
+                return Err(ValueCompressionError::BrokenBlock.into());
+            }
+            Some(CompressedValues {
+                compression,
+                block_value_count,
+                block_count,
+            })
+        } else {
+            None
+        };
+        Ok(ValueLayout {
+            fixed_value_size,
+            value_count,
+            value_array_offset,
+            value_interning,
+            compressed_values,
+        })
+    }
+
+    fn read_base_check(&self, base_check_index: usize) -> Result<u32> {
+        if base_check_index >= self.base_check_size()? {
+            return Err(MmapStorageError::IndexOutOfRange.into());
+        }
+        self.read_u32(size_of::<u32>() * (1 + base_check_index))
     }
 
     const UNINITIALIZED_BYTE: u8 = 0xFF;
 
+    fn fixed_value_size_and_flags(&self) -> Result<(usize, bool, bool)> {
+        let base_check_count = self.base_check_size()?;
+        let fixed_value_size_and_flag =
+            self.read_u32(size_of::<u32>() * (1 + base_check_count + 1))?;
+        Ok((
+            (fixed_value_size_and_flag & !FORMAT_FLAG_MASK) as usize,
+            fixed_value_size_and_flag & VALUE_INTERNING_FLAG != 0,
+            fixed_value_size_and_flag & VALUE_COMPRESSION_FLAG != 0,
+        ))
+    }
+
     fn read_bytes(&self, offset: usize, size: usize) -> Result<&[u8]> {
-        if offset + size > self.file_size {
+        if offset
+            .checked_add(size)
+            .is_none_or(|region_end| region_end > self.file_size)
+        {
             return Err(MmapStorageError::MmapRegionOutOfFileSize.into());
         }
 
-        self.file_mapping
+        self.source
             .region(self.content_offset + offset..self.content_offset + offset + size)
     }
     fn read_u32(&self, offset: usize) -> Result<u32> {
@@ -228,29 +600,21 @@ impl<Value: Clone + Debug + 'static> MmapStorage<Value> {
     }
 }
 
-impl<Value: Clone + Debug + 'static> Storage<Value> for MmapStorage<Value> {
+impl<Value: Clone + Debug + 'static> StorageRead<Value> for MmapStorage<Value> {
     fn base_check_size(&self) -> Result<usize> {
         self.read_u32(0).map(|v| v as usize)
     }
 
     fn base_at(&self, base_check_index: usize) -> Result<i32> {
-        let base_check = self.read_u32(size_of::<u32>() * (1 + base_check_index))?;
+        let base_check = self.read_base_check(base_check_index)?;
         Ok((base_check as i32) >> 8)
     }
 
-    fn set_base_at(&mut self, _: usize, _: i32) -> Result<()> {
-        unreachable!("Unsupported operation.");
-    }
-
     fn check_at(&self, base_check_index: usize) -> Result<u8> {
-        let base_check = self.read_u32(size_of::<u32>() * (1 + base_check_index))?;
+        let base_check = self.read_base_check(base_check_index)?;
         Ok((base_check & 0xFF) as u8)
     }
 
-    fn set_check_at(&mut self, _: usize, _: u8) -> Result<()> {
-        unreachable!("Unsupported operation.");
-    }
-
     fn value_count(&self) -> Result<usize> {
         let base_check_count = self.base_check_size()?;
         self.read_u32(size_of::<u32>() * (1 + base_check_count))
@@ -258,16 +622,16 @@ impl<Value: Clone + Debug + 'static> Storage<Value> for MmapStorage<Value> {
     }
 
     fn value_at(&self, value_index: usize) -> Result<Option<Rc<Value>>> {
-        self.ensure_value_cached(value_index)?;
-        let mut cache_ref = self.value_cache.borrow_mut();
-        let Some(value) = cache_ref.at(value_index) else {
-            unreachable!("The value must be cached.")
-        };
-        Ok(value.clone())
+        self.read_value(value_index)
     }
 
-    fn add_value_at(&mut self, _: usize, _: Value) -> Result<()> {
-        unreachable!("Unsupported operation.");
+    fn values_at(&self, value_indexes: Range<usize>) -> Result<Vec<Option<Rc<Value>>>> {
+        self.read_values(value_indexes)
+    }
+
+    #[cfg(feature = "metrics")]
+    fn value_cache_hit_count(&self) -> usize {
+        self.value_cache.borrow().hit_count
     }
 
     fn filling_rate(&self) -> Result<f64> {
@@ -282,17 +646,26 @@ impl<Value: Clone + Debug + 'static> Storage<Value> for MmapStorage<Value> {
         Ok(1.0 - (empty_count as f64) / (base_check_count as f64))
     }
 
-    fn serialize(&self, _: &mut dyn Write, _: &mut ValueSerializer<'_, Value>) -> Result<()> {
-        unreachable!("Unsupported operation.");
+    fn serialize(&self, _: &mut dyn ByteWriter, _: &mut ValueSerializer<'_, Value>) -> Result<()> {
+        Err(MmapStorageError::UnsupportedOperation.into())
+    }
+
+    fn serialize_values_only(
+        &self,
+        _: &mut dyn ByteWriter,
+        _: &mut ValueSerializer<'_, Value>,
+    ) -> Result<()> {
+        Err(MmapStorageError::UnsupportedOperation.into())
     }
 
-    fn clone_box(&self) -> Box<dyn Storage<Value>> {
+    fn clone_box(&self) -> Box<dyn StorageRead<Value>> {
         Box::new(Self {
-            file_mapping: self.file_mapping.clone(),
+            source: self.source.clone(),
             file_size: self.file_size,
             content_offset: self.content_offset,
             value_deserializer: self.value_deserializer.clone(),
             value_cache: RefCell::new(self.value_cache.borrow().clone()),
+            broken_value_as_none: self.broken_value_as_none,
         })
     }
 
@@ -314,6 +687,7 @@ mod tests {
 
     use tempfile::tempfile;
 
+    use crate::file_mapping::FileMappingError;
     use crate::serializer::Serializer;
 
     use super::*;
@@ -349,6 +723,23 @@ mod tests {
         0x00u8, 0x00u8, 0x00u8, 0x03u8,
     ];
 
+    #[rustfmt::skip]
+    const SERIALIZED_FIXED_VALUE_SIZE_VALUE_INTERNED: &[u8] = &[
+        0x00u8, 0x00u8, 0x00u8, 0x02u8,
+        0x00u8, 0x00u8, 0x2Au8, 0xFFu8,
+        0x00u8, 0x00u8, 0xFEu8, 0x18u8,
+        0x00u8, 0x00u8, 0x00u8, 0x05u8,
+        0x80u8, 0x00u8, 0x00u8, 0x04u8,
+        0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8,
+        0x00u8, 0x00u8, 0x00u8, 0x00u8,
+        0x00u8, 0x00u8, 0x00u8, 0x01u8,
+        0xFFu8, 0xFFu8, 0xFFu8, 0xFFu8,
+        0x00u8, 0x00u8, 0x00u8, 0x00u8,
+        0x00u8, 0x00u8, 0x00u8, 0x02u8,
+        0x00u8, 0x00u8, 0x00u8, 0x9Fu8,
+        0x00u8, 0x00u8, 0x00u8, 0x0Eu8,
+    ];
+
     #[rustfmt::skip]
     const SERIALIZED_FIXED_VALUE_SIZE_WITH_HEADER: &[u8] = &[
         // header
@@ -383,7 +774,7 @@ mod tests {
         0x00u8,
     ];
 
-    fn base_check_array_of<Value: 'static>(storage: &dyn Storage<Value>) -> Vec<u32> {
+    fn base_check_array_of<Value: 'static>(storage: &dyn StorageRead<Value>) -> Vec<u32> {
         let size = storage.base_check_size().unwrap();
         let mut array = Vec::<u32>::with_capacity(size);
         for i in 0..size {
@@ -403,6 +794,7 @@ mod tests {
     }
 
     mod mmap_storage {
+        use std::cell::Cell;
         use std::io::Cursor;
 
         use crate::double_array::VACANT_CHECK_VALUE;
@@ -498,6 +890,87 @@ mod tests {
                     .build();
                 assert!(storage.is_ok());
             }
+            {
+                let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
+                let file_size = file_size_of(&file);
+                let file_mapping = Rc::new(FileMapping::new(file).unwrap());
+                let deserializer = ValueDeserializer::<u32>::new(Box::new(|serialized| {
+                    static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
+                        LazyLock::new(|| IntegerDeserializer::new(false));
+                    INTEGER_DESERIALIZER.deserialize(serialized)
+                }));
+                let storage = MmapStorage::builder(file_mapping, 0, file_size, deserializer)
+                    .broken_value_as_none(true)
+                    .build();
+                assert!(storage.is_ok());
+            }
+            {
+                let mut serialized = SERIALIZED_FIXED_VALUE_SIZE.to_vec();
+                serialized[16] = 0x20;
+                let file = make_temporary_file(&serialized);
+                let file_size = file_size_of(&file);
+                let file_mapping = Rc::new(FileMapping::new(file).unwrap());
+                let deserializer = ValueDeserializer::<u32>::new(Box::new(|serialized| {
+                    static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
+                        LazyLock::new(|| IntegerDeserializer::new(false));
+                    INTEGER_DESERIALIZER.deserialize(serialized)
+                }));
+                let storage =
+                    MmapStorage::builder(file_mapping, 0, file_size, deserializer).build();
+                assert!(storage.is_err());
+            }
+        }
+
+        #[test]
+        fn builder_with_bytes() {
+            {
+                let deserializer = ValueDeserializer::<u32>::new(Box::new(|serialized| {
+                    static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
+                        LazyLock::new(|| IntegerDeserializer::new(false));
+                    INTEGER_DESERIALIZER.deserialize(serialized)
+                }));
+                let storage = MmapStorage::builder_with_bytes(
+                    SERIALIZED_FIXED_VALUE_SIZE_WITH_HEADER,
+                    5,
+                    deserializer,
+                )
+                .build()
+                .unwrap();
+
+                assert_eq!(
+                    base_check_array_of(&storage),
+                    [0x00002AFFu32, 0x0000FE18u32]
+                );
+                assert_eq!(storage.value_count().unwrap(), 5);
+                assert!(storage.value_at(0).unwrap().is_none());
+                assert_eq!(*storage.value_at(1).unwrap().unwrap(), 159);
+                assert_eq!(*storage.value_at(4).unwrap().unwrap(), 3);
+                assert!(storage.value_at(5).is_err());
+
+                let cloned = storage.clone_box();
+                assert_eq!(*cloned.value_at(2).unwrap().unwrap(), 14);
+            }
+            {
+                let deserializer = ValueDeserializer::<u32>::new(Box::new(|serialized| {
+                    static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
+                        LazyLock::new(|| IntegerDeserializer::new(false));
+                    INTEGER_DESERIALIZER.deserialize(serialized)
+                }));
+                let storage =
+                    MmapStorage::builder_with_bytes(SERIALIZED_BROKEN, 0, deserializer).build();
+                assert!(storage.is_err());
+            }
+            {
+                let deserializer = ValueDeserializer::<u32>::new(Box::new(|serialized| {
+                    static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
+                        LazyLock::new(|| IntegerDeserializer::new(false));
+                    INTEGER_DESERIALIZER.deserialize(serialized)
+                }));
+                let storage =
+                    MmapStorage::builder_with_bytes(SERIALIZED_FIXED_VALUE_SIZE, 41, deserializer)
+                        .build();
+                assert!(storage.is_err());
+            }
         }
 
         #[test]
@@ -551,6 +1024,7 @@ mod tests {
 
                 assert_eq!(storage.base_at(0).unwrap(), 42);
                 assert_eq!(storage.base_at(1).unwrap(), 0xFE);
+                assert!(storage.base_at(2).is_err());
             }
             {
                 let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE_WITH_HEADER);
@@ -570,24 +1044,6 @@ mod tests {
             }
         }
 
-        #[test]
-        #[should_panic]
-        fn set_base_at() {
-            let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
-            let file_size = file_size_of(&file);
-            let file_mapping = Rc::new(FileMapping::new(file).unwrap());
-            let deserializer = ValueDeserializer::<u32>::new(Box::new(|serialized| {
-                static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
-                    LazyLock::new(|| IntegerDeserializer::new(false));
-                INTEGER_DESERIALIZER.deserialize(serialized)
-            }));
-            let mut storage = MmapStorage::builder(file_mapping, 0, file_size, deserializer)
-                .build()
-                .unwrap();
-
-            let _result = storage.set_base_at(42, 4242);
-        }
-
         #[test]
         fn check_at() {
             {
@@ -624,24 +1080,6 @@ mod tests {
             }
         }
 
-        #[test]
-        #[should_panic]
-        fn set_check_at() {
-            let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
-            let file_size = file_size_of(&file);
-            let file_mapping = Rc::new(FileMapping::new(file).unwrap());
-            let deserializer = ValueDeserializer::<u32>::new(Box::new(|serialized| {
-                static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
-                    LazyLock::new(|| IntegerDeserializer::new(false));
-                INTEGER_DESERIALIZER.deserialize(serialized)
-            }));
-            let mut storage = MmapStorage::builder(file_mapping, 0, file_size, deserializer)
-                .build()
-                .unwrap();
-
-            let _result = storage.set_check_at(24, 124);
-        }
-
         #[test]
         fn value_count() {
             {
@@ -696,6 +1134,7 @@ mod tests {
                 assert_eq!(*storage.value_at(2).unwrap().unwrap(), 14);
                 assert!(storage.value_at(3).unwrap().is_none());
                 assert_eq!(*storage.value_at(4).unwrap().unwrap(), 3);
+                assert!(storage.value_at(usize::MAX).is_err());
             }
             {
                 let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE_WITH_HEADER);
@@ -716,11 +1155,270 @@ mod tests {
                 assert!(storage.value_at(3).unwrap().is_none());
                 assert_eq!(*storage.value_at(4).unwrap().unwrap(), 3);
             }
+            {
+                let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE_VALUE_INTERNED);
+                let file_size = file_size_of(&file);
+                let file_mapping = Rc::new(FileMapping::new(file).unwrap());
+                let deserializer = ValueDeserializer::<u32>::new(Box::new(|serialized| {
+                    static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
+                        LazyLock::new(|| IntegerDeserializer::new(false));
+                    INTEGER_DESERIALIZER.deserialize(serialized)
+                }));
+                let storage = MmapStorage::builder(file_mapping, 0, file_size, deserializer)
+                    .build()
+                    .unwrap();
+
+                assert_eq!(storage.value_count().unwrap(), 5);
+                assert!(storage.value_at(0).unwrap().is_none());
+                assert_eq!(*storage.value_at(1).unwrap().unwrap(), 159);
+                assert_eq!(*storage.value_at(2).unwrap().unwrap(), 14);
+                assert!(storage.value_at(3).unwrap().is_none());
+                assert_eq!(*storage.value_at(4).unwrap().unwrap(), 159);
+                assert!(storage.value_at(usize::MAX).is_err());
+            }
+            {
+                let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
+                let file_size = file_size_of(&file);
+                let file_mapping = Rc::new(
+                    FileMapping::builder(file)
+                        .shared_lock()
+                        .verify_on_access()
+                        .build()
+                        .unwrap(),
+                );
+                let deserializer = ValueDeserializer::<u32>::new(Box::new(|serialized| {
+                    static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
+                        LazyLock::new(|| IntegerDeserializer::new(false));
+                    INTEGER_DESERIALIZER.deserialize(serialized)
+                }));
+                let storage =
+                    MmapStorage::builder(file_mapping.clone(), 0, file_size, deserializer)
+                        .value_cache_capacity(0)
+                        .build()
+                        .unwrap();
+
+                assert_eq!(*storage.value_at(1).unwrap().unwrap(), 159);
+
+                file_mapping.file().set_len(16).unwrap();
+                let error = storage.value_at(1).unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<FileMappingError>().unwrap(),
+                    FileMappingError::FileChanged
+                ));
+            }
+        }
+
+        #[test]
+        fn value_at_broken() {
+            let make_deserializer = |call_count: Rc<Cell<usize>>| {
+                ValueDeserializer::<u32>::new(Box::new(move |serialized| {
+                    static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
+                        LazyLock::new(|| IntegerDeserializer::new(false));
+                    call_count.set(call_count.get() + 1);
+                    let value = INTEGER_DESERIALIZER.deserialize(serialized)?;
+                    if value == 14 {
+                        return Err(MmapStorageError::IndexOutOfRange.into());
+                    }
+                    Ok(value)
+                }))
+            };
+            {
+                let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
+                let file_size = file_size_of(&file);
+                let file_mapping = Rc::new(FileMapping::new(file).unwrap());
+                let call_count = Rc::new(Cell::new(0));
+                let storage = MmapStorage::builder(
+                    file_mapping,
+                    0,
+                    file_size,
+                    make_deserializer(call_count.clone()),
+                )
+                .build()
+                .unwrap();
+
+                let error = storage.value_at(2).unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<MmapStorageError>().unwrap(),
+                    MmapStorageError::IndexOutOfRange
+                ));
+                assert_eq!(call_count.get(), 1);
+
+                let error = storage.value_at(2).unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<MmapStorageError>().unwrap(),
+                    MmapStorageError::BrokenValue
+                ));
+                assert_eq!(error.to_string(), "an index is out of range");
+                assert!(storage.values_at(0..5).is_err());
+                assert_eq!(call_count.get(), 2);
+
+                assert_eq!(*storage.value_at(1).unwrap().unwrap(), 159);
+                assert_eq!(call_count.get(), 2);
+            }
+            {
+                let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
+                let file_size = file_size_of(&file);
+                let file_mapping = Rc::new(FileMapping::new(file).unwrap());
+                let call_count = Rc::new(Cell::new(0));
+                let storage = MmapStorage::builder(
+                    file_mapping,
+                    0,
+                    file_size,
+                    make_deserializer(call_count.clone()),
+                )
+                .broken_value_as_none(true)
+                .build()
+                .unwrap();
+
+                assert!(storage.value_at(2).unwrap().is_none());
+                assert!(storage.value_at(2).unwrap().is_none());
+                assert_eq!(call_count.get(), 1);
+
+                let values = storage
+                    .values_at(0..5)
+                    .unwrap()
+                    .into_iter()
+                    .map(|value| value.map(|value| *value))
+                    .collect::<Vec<_>>();
+                assert_eq!(values, [None, Some(159), None, None, Some(3)]);
+                assert_eq!(call_count.get(), 3);
+            }
+            {
+                let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
+                let file_size = file_size_of(&file);
+                let file_mapping = Rc::new(FileMapping::new(file).unwrap());
+                let call_count = Rc::new(Cell::new(0));
+                let storage = MmapStorage::builder(
+                    file_mapping,
+                    0,
+                    file_size,
+                    make_deserializer(call_count.clone()),
+                )
+                .value_cache_capacity(0)
+                .build()
+                .unwrap();
+
+                assert!(storage.value_at(2).is_err());
+                assert!(storage.value_at(2).is_err());
+                assert_eq!(call_count.get(), 2);
+            }
         }
 
+        #[cfg(feature = "lz4")]
         #[test]
-        #[should_panic]
-        fn add_value_at() {
+        fn value_at_compressed() {
+            use crate::memory_storage::MemoryStorage;
+            use crate::storage::StorageMut;
+            use crate::value_compression::ValueCompression;
+            use crate::value_serializer::ValueSerializer;
+
+            let mut memory_storage = MemoryStorage::<u32>::new();
+            for i in (0..150u32).filter(|i| i % 7 != 0) {
+                memory_storage.add_value_at(i as usize, i % 10).unwrap();
+            }
+
+            for value_interning in [false, true] {
+                let make_serializer = || {
+                    let serialize = Box::new(|value: &u32| {
+                        static INTEGER_SERIALIZER: LazyLock<IntegerSerializer<u32>> =
+                            LazyLock::new(|| IntegerSerializer::new(false));
+                        INTEGER_SERIALIZER.serialize(value)
+                    });
+                    if value_interning {
+                        ValueSerializer::new_with_value_interning(serialize, size_of::<u32>())
+                    } else {
+                        ValueSerializer::new(serialize, size_of::<u32>())
+                    }
+                    .compressed_with(ValueCompression::Lz4)
+                };
+                let mut serialized = Vec::new();
+                memory_storage
+                    .serialize(&mut serialized, &mut make_serializer())
+                    .unwrap();
+
+                let file = make_temporary_file(&serialized);
+                let file_size = file_size_of(&file);
+                let file_mapping = Rc::new(FileMapping::new(file).unwrap());
+                let deserializer = ValueDeserializer::<u32>::new(Box::new(|serialized| {
+                    static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
+                        LazyLock::new(|| IntegerDeserializer::new(false));
+                    INTEGER_DESERIALIZER.deserialize(serialized)
+                }));
+                let storage = MmapStorage::builder(file_mapping, 0, file_size, deserializer)
+                    .build()
+                    .unwrap();
+
+                assert_eq!(storage.value_count().unwrap(), 150);
+                for i in (0..150u32).rev() {
+                    let value = storage.value_at(i as usize).unwrap();
+                    if i % 7 == 0 {
+                        assert!(value.is_none());
+                    } else {
+                        assert_eq!(*value.unwrap(), i % 10);
+                    }
+                }
+                assert!(storage.value_at(150).is_err());
+
+                let values = storage.values_at(0..150).unwrap();
+                for (i, value) in values.into_iter().enumerate() {
+                    if i % 7 == 0 {
+                        assert!(value.is_none());
+                    } else {
+                        assert_eq!(*value.unwrap(), i as u32 % 10);
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn values_at() {
+            for (serialized, expected) in [
+                (
+                    SERIALIZED_FIXED_VALUE_SIZE,
+                    [None, Some(159), Some(14), None, Some(3)],
+                ),
+                (
+                    SERIALIZED_FIXED_VALUE_SIZE_VALUE_INTERNED,
+                    [None, Some(159), Some(14), None, Some(159)],
+                ),
+            ] {
+                let file = make_temporary_file(serialized);
+                let file_size = file_size_of(&file);
+                let file_mapping = Rc::new(FileMapping::new(file).unwrap());
+                let deserializer = ValueDeserializer::<u32>::new(Box::new(|serialized| {
+                    static INTEGER_DESERIALIZER: LazyLock<IntegerDeserializer<u32>> =
+                        LazyLock::new(|| IntegerDeserializer::new(false));
+                    INTEGER_DESERIALIZER.deserialize(serialized)
+                }));
+                let storage = MmapStorage::builder(file_mapping, 0, file_size, deserializer)
+                    .build()
+                    .unwrap();
+
+                let _cached = storage.value_at(2).unwrap();
+                let values = storage
+                    .values_at(0..5)
+                    .unwrap()
+                    .into_iter()
+                    .map(|value| value.map(|value| *value))
+                    .collect::<Vec<_>>();
+                assert_eq!(values, expected);
+                assert_eq!(
+                    storage
+                        .values_at(1..3)
+                        .unwrap()
+                        .into_iter()
+                        .map(|value| value.map(|value| *value))
+                        .collect::<Vec<_>>(),
+                    expected[1..3]
+                );
+                assert!(storage.values_at(2..2).unwrap().is_empty());
+                assert!(storage.values_at(3..6).is_err());
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        #[test]
+        fn value_cache_hit_count() {
             let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
             let file_size = file_size_of(&file);
             let file_mapping = Rc::new(FileMapping::new(file).unwrap());
@@ -729,11 +1427,15 @@ mod tests {
                     LazyLock::new(|| IntegerDeserializer::new(false));
                 INTEGER_DESERIALIZER.deserialize(serialized)
             }));
-            let mut storage = MmapStorage::builder(file_mapping, 0, file_size, deserializer)
+            let storage = MmapStorage::builder(file_mapping, 0, file_size, deserializer)
                 .build()
                 .unwrap();
 
-            let _result = storage.add_value_at(24, 124);
+            assert_eq!(storage.value_cache_hit_count(), 0);
+            let _value = storage.value_at(4).unwrap();
+            assert_eq!(storage.value_cache_hit_count(), 0);
+            let _value = storage.value_at(4).unwrap();
+            assert_eq!(storage.value_cache_hit_count(), 1);
         }
 
         #[test]
@@ -755,7 +1457,6 @@ mod tests {
         }
 
         #[test]
-        #[should_panic]
         fn serialize() {
             let file = make_temporary_file(SERIALIZED_FIXED_VALUE_SIZE);
             let file_size = file_size_of(&file);
@@ -779,7 +1480,7 @@ mod tests {
                 size_of::<u32>(),
             );
 
-            let _result = storage.serialize(&mut writer, &mut serializer);
+            assert!(storage.serialize(&mut writer, &mut serializer).is_err());
         }
 
         #[test]
@@ -0,0 +1,500 @@
+/*!
+ * A persistent memory storage.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use std::any::Any;
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use anyhow::Result;
+
+use crate::byte_writer::ByteWriter;
+use crate::memory_storage::MemoryStorage;
+use crate::storage::{StorageError, StorageMut, StorageRead, ValueRef};
+use crate::value_serializer::{ValueDeserializer, ValueSerializer};
+
+/**
+ * A persistent memory storage error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum PersistentMemoryStorageError {
+    /**
+     * The journal is broken.
+     */
+    #[error("the journal is broken")]
+    BrokenJournal,
+}
+
+impl StorageError for PersistentMemoryStorageError {}
+
+const SET_BASE_RECORD: u8 = 1;
+
+const SET_CHECK_RECORD: u8 = 2;
+
+const ADD_VALUE_RECORD: u8 = 3;
+
+/**
+ * A persistent memory storage.
+ *
+ * Holds the content in a memory storage and journals its mutations to a file.
+ * When the storage is created with an existing journal, the mutations in it
+ * are replayed. So the mutations made through `StorageMut`, such as the values
+ * replaced by `Trie::replace_value`, are recovered from the last
+ * synchronization after a crash.
+ *
+ * The trie builders fill a new memory storage, not this storage. To resume a
+ * long build interrupted by a crash, use the checkpoints of `TrieBuilder`
+ * instead.
+ *
+ * The mutations are buffered and written to the journal on `sync()` or on
+ * drop. A record left incomplete by a crash is discarded on the replay.
+ *
+ * The clones made with `clone_box()` are memory storages not journaled.
+ *
+ * # Journal Format
+ * All the integers are 32-bit unsigned big endian. Each record is one of:
+ *
+ * - 0x01, a base-check index and a base value.
+ * - 0x02, a base-check index and a check value in a byte.
+ * - 0x03, a value index, the size of the serialized value and its bytes.
+ *
+ * # Type Parameters
+ * * `Value` - A value type.
+ */
+#[derive(Debug)]
+pub struct PersistentMemoryStorage<Value: Clone + 'static> {
+    entity: MemoryStorage<Value>,
+    journal: File,
+    pending_records: Vec<u8>,
+    value_serializer: ValueSerializer<'static, Value>,
+}
+
+impl<Value: Clone + Debug + 'static> PersistentMemoryStorage<Value> {
+    /**
+     * Creates a persistent memory storage.
+     *
+     * # Arguments
+     * * `journal_path`       - A journal file path. The file is created when it does not exist.
+     * * `value_serializer`   - A serializer for value objects to journal.
+     * * `value_deserializer` - A deserializer for value objects to replay.
+     *
+     * # Errors
+     * * When it fails to open the journal.
+     * * When the journal is broken.
+     */
+    pub fn new(
+        journal_path: &Path,
+        value_serializer: ValueSerializer<'static, Value>,
+        value_deserializer: &mut ValueDeserializer<Value>,
+    ) -> Result<Self> {
+        let mut journal = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(journal_path)?;
+        let mut records = Vec::new();
+        let _size = journal.read_to_end(&mut records)?;
+
+        let mut entity = MemoryStorage::new();
+        let replayed_size = Self::replay(&records, &mut entity, value_deserializer)?;
+        if replayed_size < records.len() {
+            journal.set_len(replayed_size as u64)?;
+        }
+
+        Ok(Self {
+            entity,
+            journal,
+            pending_records: Vec::new(),
+            value_serializer,
+        })
+    }
+
+    fn replay(
+        records: &[u8],
+        entity: &mut MemoryStorage<Value>,
+        value_deserializer: &mut ValueDeserializer<Value>,
+    ) -> Result<usize> {
+        let mut offset = 0;
+        while offset < records.len() {
+            let record = &records[offset..];
+            let Some(index) = Self::u32_at(record, 1) else {
+                break;
+            };
+            let index = index as usize;
+            let record_size = match record[0] {
+                SET_BASE_RECORD => {
+                    let Some(base) = Self::u32_at(record, 5) else {
+                        break;
+                    };
+                    entity.set_base_at(index, base as i32)?;
+                    9
+                }
+                SET_CHECK_RECORD => {
+                    let Some(&check) = record.get(5) else {
+                        break;
+                    };
+                    entity.set_check_at(index, check)?;
+                    6
+                }
+                ADD_VALUE_RECORD => {
+                    let Some(value_size) = Self::u32_at(record, 5) else {
+                        break;
+                    };
+                    let Some(serialized) = record.get(9..9 + value_size as usize) else {
+                        break;
+                    };
+                    entity.add_value_at(index, value_deserializer.deserialize(serialized)?)?;
+                    9 + serialized.len()
+                }
+                _ => return Err(PersistentMemoryStorageError::BrokenJournal.into()),
+            };
+            offset += record_size;
+        }
+        Ok(offset)
+    }
+
+    fn u32_at(record: &[u8], offset: usize) -> Option<u32> {
+        let bytes = record.get(offset..offset + size_of::<u32>())?;
+        Some(u32::from_be_bytes(bytes.try_into().ok()?))
+    }
+}
+
+impl<Value: Clone + 'static> PersistentMemoryStorage<Value> {
+    /**
+     * Writes the pending mutations to the journal.
+     *
+     * # Errors
+     * * When it fails to write the journal.
+     */
+    pub fn sync(&mut self) -> Result<()> {
+        if self.pending_records.is_empty() {
+            return Ok(());
+        }
+        self.journal.write_all(&self.pending_records)?;
+        self.journal.sync_data()?;
+        self.pending_records.clear();
+        Ok(())
+    }
+
+    fn push_record(&mut self, kind: u8, index: usize, body: &[u8]) {
+        self.pending_records.push(kind);
+        self.pending_records
+            .extend_from_slice(&(index as u32).to_be_bytes());
+        self.pending_records.extend_from_slice(body);
+    }
+}
+
+impl<Value: Clone + 'static> Drop for PersistentMemoryStorage<Value> {
+    fn drop(&mut self) {
+        let _result = self.sync();
+    }
+}
+
+impl<Value: Clone + Debug + 'static> StorageRead<Value> for PersistentMemoryStorage<Value> {
+    fn base_check_size(&self) -> Result<usize> {
+        self.entity.base_check_size()
+    }
+
+    fn base_at(&self, base_check_index: usize) -> Result<i32> {
+        self.entity.base_at(base_check_index)
+    }
+
+    fn check_at(&self, base_check_index: usize) -> Result<u8> {
+        self.entity.check_at(base_check_index)
+    }
+
+    fn value_count(&self) -> Result<usize> {
+        self.entity.value_count()
+    }
+
+    fn value_at(&self, value_index: usize) -> Result<Option<Rc<Value>>> {
+        self.entity.value_at(value_index)
+    }
+
+    fn value_ref_at(&self, value_index: usize) -> Result<Option<ValueRef<'_, Value>>> {
+        self.entity.value_ref_at(value_index)
+    }
+
+    fn filling_rate(&self) -> Result<f64> {
+        self.entity.filling_rate()
+    }
+
+    fn serialize(
+        &self,
+        writer: &mut dyn ByteWriter,
+        value_serializer: &mut ValueSerializer<'_, Value>,
+    ) -> Result<()> {
+        self.entity.serialize(writer, value_serializer)
+    }
+
+    fn serialize_values_only(
+        &self,
+        writer: &mut dyn ByteWriter,
+        value_serializer: &mut ValueSerializer<'_, Value>,
+    ) -> Result<()> {
+        self.entity.serialize_values_only(writer, value_serializer)
+    }
+
+    fn clone_box(&self) -> Box<dyn StorageRead<Value>> {
+        self.entity.clone_box()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn as_storage_mut(&mut self) -> Option<&mut dyn StorageMut<Value>> {
+        Some(self)
+    }
+}
+
+impl<Value: Clone + Debug + 'static> StorageMut<Value> for PersistentMemoryStorage<Value> {
+    fn set_base_at(&mut self, base_check_index: usize, base: i32) -> Result<()> {
+        self.entity.set_base_at(base_check_index, base)?;
+        self.push_record(
+            SET_BASE_RECORD,
+            base_check_index,
+            &(base as u32).to_be_bytes(),
+        );
+        Ok(())
+    }
+
+    fn set_check_at(&mut self, base_check_index: usize, check: u8) -> Result<()> {
+        self.entity.set_check_at(base_check_index, check)?;
+        self.push_record(SET_CHECK_RECORD, base_check_index, &[check]);
+        Ok(())
+    }
+
+    fn add_value_at(&mut self, value_index: usize, value: Value) -> Result<()> {
+        let serialized = self.value_serializer.serialize(&value)?;
+        self.entity.add_value_at(value_index, value)?;
+        let mut body = Vec::with_capacity(size_of::<u32>() + serialized.len());
+        body.extend_from_slice(&(serialized.len() as u32).to_be_bytes());
+        body.extend_from_slice(&serialized);
+        self.push_record(ADD_VALUE_RECORD, value_index, &body);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn value_serializer() -> ValueSerializer<'static, u32> {
+        ValueSerializer::new(Box::new(|value: &u32| value.to_be_bytes().to_vec()), 0)
+    }
+
+    fn value_deserializer() -> ValueDeserializer<u32> {
+        ValueDeserializer::new(Box::new(|serialized: &[u8]| {
+            Ok(u32::from_be_bytes(serialized.try_into()?))
+        }))
+    }
+
+    fn open(journal_path: &Path) -> Result<PersistentMemoryStorage<u32>> {
+        PersistentMemoryStorage::new(journal_path, value_serializer(), &mut value_deserializer())
+    }
+
+    fn mutate(storage: &mut PersistentMemoryStorage<u32>) {
+        storage.set_base_at(42, 4242).unwrap();
+        storage.set_check_at(24, 124).unwrap();
+        storage.add_value_at(3, 33).unwrap();
+    }
+
+    fn assert_mutated(storage: &PersistentMemoryStorage<u32>) {
+        assert_eq!(storage.base_at(42).unwrap(), 4242);
+        assert_eq!(storage.check_at(24).unwrap(), 124);
+        assert_eq!(storage.value_count().unwrap(), 4);
+        assert_eq!(*storage.value_at(3).unwrap().unwrap(), 33);
+    }
+
+    #[test]
+    fn new() {
+        let directory = TempDir::new().unwrap();
+        let journal_path = directory.path().join("journal");
+        {
+            let storage = open(&journal_path).unwrap();
+
+            assert!(journal_path.exists());
+            assert_eq!(storage.value_count().unwrap(), 0);
+        }
+        {
+            fs::write(&journal_path, [0x7F, 0, 0, 0, 0, 0]).unwrap();
+
+            assert!(open(&journal_path).is_err());
+        }
+    }
+
+    #[test]
+    fn sync() {
+        let directory = TempDir::new().unwrap();
+        let journal_path = directory.path().join("journal");
+        {
+            let mut storage = open(&journal_path).unwrap();
+            mutate(&mut storage);
+            storage.sync().unwrap();
+
+            let reopened = open(&journal_path).unwrap();
+            assert_mutated(&reopened);
+        }
+        {
+            let mut storage = open(&journal_path).unwrap();
+            storage.set_base_at(42, 5353).unwrap();
+        }
+        {
+            let storage = open(&journal_path).unwrap();
+
+            assert_eq!(storage.base_at(42).unwrap(), 5353);
+        }
+    }
+
+    #[test]
+    fn drop() {
+        let directory = TempDir::new().unwrap();
+        let journal_path = directory.path().join("journal");
+        {
+            let mut storage = open(&journal_path).unwrap();
+            mutate(&mut storage);
+        }
+
+        let storage = open(&journal_path).unwrap();
+        assert_mutated(&storage);
+    }
+
+    #[test]
+    fn replay_incomplete_record() {
+        let directory = TempDir::new().unwrap();
+        let journal_path = directory.path().join("journal");
+        {
+            let mut storage = open(&journal_path).unwrap();
+            mutate(&mut storage);
+        }
+        let complete_size = fs::metadata(&journal_path).unwrap().len();
+        {
+            let mut journal = OpenOptions::new().append(true).open(&journal_path).unwrap();
+            journal.write_all(&[SET_BASE_RECORD, 0, 0]).unwrap();
+        }
+        {
+            let mut storage = open(&journal_path).unwrap();
+            assert_mutated(&storage);
+            assert_eq!(fs::metadata(&journal_path).unwrap().len(), complete_size);
+
+            storage.add_value_at(5, 55).unwrap();
+        }
+
+        let storage = open(&journal_path).unwrap();
+        assert_eq!(storage.base_at(42).unwrap(), 4242);
+        assert_eq!(*storage.value_at(3).unwrap().unwrap(), 33);
+        assert_eq!(*storage.value_at(5).unwrap().unwrap(), 55);
+    }
+
+    #[test]
+    fn base_at() {
+        let directory = TempDir::new().unwrap();
+        let storage = open(&directory.path().join("journal")).unwrap();
+
+        assert_eq!(storage.base_at(42).unwrap(), 0);
+    }
+
+    #[test]
+    fn set_base_at() {
+        let directory = TempDir::new().unwrap();
+        let mut storage = open(&directory.path().join("journal")).unwrap();
+
+        storage.set_base_at(42, -4242).unwrap();
+
+        assert_eq!(storage.base_at(42).unwrap(), -4242);
+    }
+
+    #[test]
+    fn check_at() {
+        let directory = TempDir::new().unwrap();
+        let storage = open(&directory.path().join("journal")).unwrap();
+
+        assert_eq!(storage.check_at(42).unwrap(), 0xFF);
+    }
+
+    #[test]
+    fn set_check_at() {
+        let directory = TempDir::new().unwrap();
+        let mut storage = open(&directory.path().join("journal")).unwrap();
+
+        storage.set_check_at(24, 124).unwrap();
+
+        assert_eq!(storage.check_at(24).unwrap(), 124);
+    }
+
+    #[test]
+    fn value_at() {
+        let directory = TempDir::new().unwrap();
+        let storage = open(&directory.path().join("journal")).unwrap();
+
+        assert!(storage.value_at(42).unwrap().is_none());
+    }
+
+    #[test]
+    fn add_value_at() {
+        let directory = TempDir::new().unwrap();
+        let mut storage = open(&directory.path().join("journal")).unwrap();
+
+        storage.add_value_at(24, 2424).unwrap();
+
+        assert_eq!(storage.value_count().unwrap(), 25);
+        assert_eq!(*storage.value_at(24).unwrap().unwrap(), 2424);
+    }
+
+    #[test]
+    fn clone_box() {
+        let directory = TempDir::new().unwrap();
+        let mut storage = open(&directory.path().join("journal")).unwrap();
+        mutate(&mut storage);
+
+        let clone = storage.clone_box();
+
+        assert!(clone.is::<MemoryStorage<u32>>());
+        assert_eq!(clone.base_at(42).unwrap(), 4242);
+        assert_eq!(*clone.value_at(3).unwrap().unwrap(), 33);
+    }
+
+    #[test]
+    fn as_any() {
+        let directory = TempDir::new().unwrap();
+        let storage = open(&directory.path().join("journal")).unwrap();
+
+        let _ = storage.as_any();
+    }
+
+    #[test]
+    fn as_any_mut() {
+        let directory = TempDir::new().unwrap();
+        let mut storage = open(&directory.path().join("journal")).unwrap();
+
+        let _ = storage.as_any_mut();
+    }
+
+    #[test]
+    fn as_storage_mut() {
+        let directory = TempDir::new().unwrap();
+        let mut storage = open(&directory.path().join("journal")).unwrap();
+
+        storage
+            .as_storage_mut()
+            .unwrap()
+            .set_base_at(0, 42)
+            .unwrap();
+
+        assert_eq!(storage.base_at(0).unwrap(), 42);
+    }
+}
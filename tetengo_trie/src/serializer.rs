@@ -4,12 +4,26 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use std::error;
+use alloc::vec::Vec;
+use core::error;
 
 use anyhow::Result;
 
+/**
+ * The key terminator.
+ *
+ * The double array terminates every key with this byte, so a serialized key
+ * must not contain it.
+ */
+pub const KEY_TERMINATOR: u8 = 0x00;
+
 /**
  * A serializer.
+ *
+ * The serialized keys must not contain `KEY_TERMINATOR`. The serializers for
+ * the objects which may contain 0x00 escape it when `fe_escape` is true. See
+ * `IntegerSerializer` and `BytesSerializer`. A trie builder rejects the keys
+ * containing `KEY_TERMINATOR`.
  */
 pub trait Serializer {
     /**
@@ -35,6 +49,50 @@ pub trait Serializer {
      * * The serialized object.
      */
     fn serialize(&self, object: &Self::Object<'_>) -> Vec<u8>;
+
+    /**
+     * Serializes an object into a buffer.
+     *
+     * The serialized object is appended to the buffer, so that the caller can
+     * reuse the buffer for many objects.
+     *
+     * # Arguments
+     * * `object` - An object.
+     * * `buffer` - A buffer.
+     */
+    fn serialize_into(&self, object: &Self::Object<'_>, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.serialize(object));
+    }
+
+    /**
+     * Returns the serialized length of an object.
+     *
+     * The length includes the bytes added by escaping, so that the caller can
+     * estimate the size of a storage without serializing the keys.
+     *
+     * # Arguments
+     * * `object` - An object.
+     *
+     * # Returns
+     * The length of the serialized object in bytes.
+     */
+    fn serialized_len(&self, object: &Self::Object<'_>) -> usize {
+        self.serialize(object).len()
+    }
+
+    /**
+     * Returns the count of the escaped bytes of an object.
+     *
+     * # Arguments
+     * * `object` - An object.
+     *
+     * # Returns
+     * The count of the bytes of the object which are replaced or prefixed in
+     * the serialization. 0 when `fe_escape` is false.
+     */
+    fn escaped_byte_count(&self, _object: &Self::Object<'_>) -> usize {
+        0
+    }
 }
 
 /**
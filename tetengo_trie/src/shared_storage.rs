@@ -4,16 +4,22 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use std::any::Any;
-use std::fmt::Debug;
-use std::io::{Read, Write};
-use std::rc::Rc;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use core::any::Any;
+use core::fmt::Debug;
+#[cfg(feature = "std")]
+use std::io::Read;
 
 use anyhow::Result;
 
+use crate::byte_writer::ByteWriter;
+use crate::format_version::FormatVersion;
 use crate::memory_storage::MemoryStorage;
-use crate::storage::Storage;
-use crate::value_serializer::{ValueDeserializer, ValueSerializer};
+use crate::storage::{StorageMut, StorageRead, ValueRef};
+#[cfg(feature = "std")]
+use crate::value_serializer::ValueDeserializer;
+use crate::value_serializer::ValueSerializer;
 
 /**
  * A shared storage.
@@ -47,6 +53,7 @@ impl<Value: Clone + 'static> SharedStorage<Value> {
      * # Errors
      * * When it fails to read the memory.
      */
+    #[cfg(feature = "std")]
     pub fn new_with_reader(
         reader: &mut dyn Read,
         value_deserializer: &mut ValueDeserializer<Value>,
@@ -56,9 +63,21 @@ impl<Value: Clone + 'static> SharedStorage<Value> {
             entity: Rc::new(entity),
         })
     }
+
+    /**
+     * Returns the format version.
+     *
+     * # Returns
+     * The format version of the serialized storage which this storage is read
+     * from. Or `FormatVersion::LATEST` when this storage is not read from a
+     * serialized one.
+     */
+    pub fn format_version(&self) -> FormatVersion {
+        self.entity.format_version()
+    }
 }
 
-impl<Value: Clone + Debug + 'static> Storage<Value> for SharedStorage<Value> {
+impl<Value: Clone + Debug + 'static> StorageRead<Value> for SharedStorage<Value> {
     fn base_check_size(&self) -> Result<usize> {
         self.entity.base_check_size()
     }
@@ -67,20 +86,10 @@ impl<Value: Clone + Debug + 'static> Storage<Value> for SharedStorage<Value> {
         self.entity.base_at(base_check_index)
     }
 
-    fn set_base_at(&mut self, base_check_index: usize, base: i32) -> Result<()> {
-        let entity = Rc::get_mut(&mut self.entity).unwrap();
-        entity.set_base_at(base_check_index, base)
-    }
-
     fn check_at(&self, base_check_index: usize) -> Result<u8> {
         self.entity.check_at(base_check_index)
     }
 
-    fn set_check_at(&mut self, base_check_index: usize, check: u8) -> Result<()> {
-        let entity = Rc::get_mut(&mut self.entity).unwrap();
-        entity.set_check_at(base_check_index, check)
-    }
-
     fn value_count(&self) -> Result<usize> {
         self.entity.value_count()
     }
@@ -89,9 +98,8 @@ impl<Value: Clone + Debug + 'static> Storage<Value> for SharedStorage<Value> {
         self.entity.value_at(value_index)
     }
 
-    fn add_value_at(&mut self, value_index: usize, value: Value) -> Result<()> {
-        let entity = Rc::get_mut(&mut self.entity).unwrap();
-        entity.add_value_at(value_index, value)
+    fn value_ref_at(&self, value_index: usize) -> Result<Option<ValueRef<'_, Value>>> {
+        self.entity.value_ref_at(value_index)
     }
 
     fn filling_rate(&self) -> Result<f64> {
@@ -100,13 +108,21 @@ impl<Value: Clone + Debug + 'static> Storage<Value> for SharedStorage<Value> {
 
     fn serialize(
         &self,
-        writer: &mut dyn Write,
+        writer: &mut dyn ByteWriter,
         value_serializer: &mut ValueSerializer<'_, Value>,
     ) -> Result<()> {
         self.entity.serialize(writer, value_serializer)
     }
 
-    fn clone_box(&self) -> Box<dyn Storage<Value>> {
+    fn serialize_values_only(
+        &self,
+        writer: &mut dyn ByteWriter,
+        value_serializer: &mut ValueSerializer<'_, Value>,
+    ) -> Result<()> {
+        self.entity.serialize_values_only(writer, value_serializer)
+    }
+
+    fn clone_box(&self) -> Box<dyn StorageRead<Value>> {
         Box::new(Self {
             entity: self.entity.clone(),
         })
@@ -119,15 +135,44 @@ impl<Value: Clone + Debug + 'static> Storage<Value> for SharedStorage<Value> {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn as_storage_mut(&mut self) -> Option<&mut dyn StorageMut<Value>> {
+        // The entity shared with the other storages cannot be modified.
+        let _entity = Rc::get_mut(&mut self.entity)?;
+        Some(self)
+    }
+}
+
+impl<Value: Clone + Debug + 'static> StorageMut<Value> for SharedStorage<Value> {
+    fn set_base_at(&mut self, base_check_index: usize, base: i32) -> Result<()> {
+        let entity = Rc::get_mut(&mut self.entity).unwrap();
+        entity.set_base_at(base_check_index, base)
+    }
+
+    fn set_check_at(&mut self, base_check_index: usize, check: u8) -> Result<()> {
+        let entity = Rc::get_mut(&mut self.entity).unwrap();
+        entity.set_check_at(base_check_index, check)
+    }
+
+    fn add_value_at(&mut self, value_index: usize, value: Value) -> Result<()> {
+        let entity = Rc::get_mut(&mut self.entity).unwrap();
+        entity.add_value_at(value_index, value)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    #[cfg(feature = "std")]
     use std::io::Cursor;
+    #[cfg(feature = "std")]
     use std::sync::LazyLock;
 
     use crate::double_array::VACANT_CHECK_VALUE;
+    #[cfg(feature = "std")]
     use crate::serializer::{Deserializer, Serializer};
+    #[cfg(feature = "std")]
     use crate::string_serializer::{StrSerializer, StringDeserializer};
 
     use super::*;
@@ -137,6 +182,7 @@ mod tests {
         let _storage = SharedStorage::<u32>::new();
     }
 
+    #[cfg(feature = "std")]
     #[rustfmt::skip]
     const SERIALIZED: &[u8] = &[
         0x00u8, 0x00u8, 0x00u8, 0x02u8,
@@ -154,10 +200,12 @@ mod tests {
         0x68u8, 0x6Fu8, 0x67u8, 0x65u8,
     ];
 
+    #[cfg(feature = "std")]
     fn create_input_stream() -> Box<dyn Read> {
         Box::new(Cursor::new(SERIALIZED))
     }
 
+    #[cfg(feature = "std")]
     #[rustfmt::skip]
     const SERIALIZED_BROKEN: &[u8] = &[
         0x00u8, 0x00u8, 0x00u8, 0x02u8,
@@ -165,13 +213,15 @@ mod tests {
         0x89u8,
     ];
 
+    #[cfg(feature = "std")]
     fn create_input_stream_broken() -> Box<dyn Read> {
         Box::new(Cursor::new(SERIALIZED_BROKEN))
     }
 
+    #[cfg(feature = "std")]
     const BASE_CHECK_ARRAY: &[u32] = &[0x00002AFFu32, 0x0000FE18u32];
 
-    fn base_check_array_of<Value: 'static>(storage: &dyn Storage<Value>) -> Vec<u32> {
+    fn base_check_array_of<Value: 'static>(storage: &dyn StorageRead<Value>) -> Vec<u32> {
         let size = storage.base_check_size().unwrap();
         let mut array = Vec::<u32>::with_capacity(size);
         for i in 0..size {
@@ -183,6 +233,7 @@ mod tests {
         array
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn new_with_reader() {
         {
@@ -211,6 +262,13 @@ mod tests {
         }
     }
 
+    #[test]
+    fn format_version() {
+        let storage = SharedStorage::<String>::new();
+
+        assert_eq!(storage.format_version(), FormatVersion::LATEST);
+    }
+
     #[test]
     fn base_check_size() {
         {
@@ -318,6 +376,7 @@ mod tests {
         assert!((storage.filling_rate().unwrap() - 3.0 / 9.0).abs() < 0.1);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn serialize() {
         let mut storage = SharedStorage::<String>::new();
@@ -410,4 +469,22 @@ mod tests {
 
         let _ = storage.as_any_mut();
     }
+
+    #[test]
+    fn as_storage_mut() {
+        let mut storage = SharedStorage::<u32>::new();
+
+        storage
+            .as_storage_mut()
+            .unwrap()
+            .set_base_at(0, 42)
+            .unwrap();
+        assert_eq!(storage.base_at(0).unwrap(), 42);
+
+        let shared_storage = storage.clone_box();
+        assert!(storage.as_storage_mut().is_none());
+
+        drop(shared_storage);
+        assert!(storage.as_storage_mut().is_some());
+    }
 }
@@ -4,14 +4,17 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use std::any::Any;
-use std::error;
-use std::fmt::Debug;
-use std::io::Write;
-use std::rc::Rc;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec::{self, Vec};
+use core::any::Any;
+use core::error;
+use core::fmt::{self, Debug, Formatter};
+use core::ops::{ControlFlow, Deref, Range};
 
 use anyhow::Result;
 
+use crate::byte_writer::ByteWriter;
 use crate::value_serializer::ValueSerializer;
 
 /**
@@ -20,12 +23,38 @@ use crate::value_serializer::ValueSerializer;
 pub trait StorageError: error::Error {}
 
 /**
- * A storage.
+ * A storage serialization error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum StorageSerializationError {
+    /**
+     * The serialization is cancelled.
+     */
+    #[error("the serialization is cancelled")]
+    Cancelled,
+
+    /**
+     * The values are inlined into the base-check array.
+     */
+    #[error("the values are inlined into the base-check array")]
+    ValuesInlined,
+}
+
+impl StorageError for StorageSerializationError {}
+
+/// The byte count written between the progress reports.
+const PROGRESS_CHUNK_SIZE: usize = 0x10000;
+
+/// The value count read at once by a storage value iterator.
+const VALUE_CHUNK_SIZE: usize = 0x100;
+
+/**
+ * A read-only storage.
  *
  * # Type Parameters
  * * `Value` - A value type.
  */
-pub trait Storage<Value: 'static>: Debug + 'static {
+pub trait StorageRead<Value: 'static>: Debug + 'static {
     /**
      * Returns the base-check size.
      *
@@ -51,18 +80,6 @@ pub trait Storage<Value: 'static>: Debug + 'static {
      */
     fn base_at(&self, base_check_index: usize) -> Result<i32>;
 
-    /**
-     * Sets a base value.
-     *
-     * # Arguments
-     * * `base_check_index` - A base-check index.
-     * * `base`             - A base value.
-     *
-     * # Errors
-     * * When it fails to write the base value.
-     */
-    fn set_base_at(&mut self, base_check_index: usize, base: i32) -> Result<()>;
-
     /**
      * Return the check value.
      *
@@ -77,18 +94,6 @@ pub trait Storage<Value: 'static>: Debug + 'static {
      */
     fn check_at(&self, base_check_index: usize) -> Result<u8>;
 
-    /**
-     * Sets a check value.
-     *
-     * # Arguments
-     * * `base_check_index` - A base-check index.
-     * * `check`            - A check value.
-     *
-     * # Errors
-     * * When it fails to write the check value.
-     */
-    fn set_check_at(&mut self, base_check_index: usize, check: u8) -> Result<()>;
-
     /**
      * Returns the value count.
      *
@@ -115,16 +120,56 @@ pub trait Storage<Value: 'static>: Debug + 'static {
     fn value_at(&self, value_index: usize) -> Result<Option<Rc<Value>>>;
 
     /**
-     * Adds a value object.
+     * Returns the value objects in a range.
+     *
+     * # Arguments
+     * * `value_indexes` - A range of value indexes.
+     *
+     * # Returns
+     * The value objects. Each is None when there is no corresponding value
+     * object.
+     *
+     * # Errors
+     * * When it fails to read the value objects.
+     */
+    fn values_at(&self, value_indexes: Range<usize>) -> Result<Vec<Option<Rc<Value>>>> {
+        value_indexes
+            .map(|value_index| self.value_at(value_index))
+            .collect()
+    }
+
+    /**
+     * Returns a reference to the value object.
+     *
+     * A storage which owns its value objects in place returns a reference
+     * borrowed from itself. The default implementation returns the value
+     * object shared by `value_at`.
      *
      * # Arguments
      * * `value_index` - A value index.
-     * * `value`       - A value object.
+     *
+     * # Returns
+     * A reference to the value object. Or None when there is no corresponding
+     * value object.
      *
      * # Errors
-     * * When it fails to write the value object.
+     * * When it fails to read the value object.
      */
-    fn add_value_at(&mut self, value_index: usize, value: Value) -> Result<()>;
+    fn value_ref_at(&self, value_index: usize) -> Result<Option<ValueRef<'_, Value>>> {
+        Ok(self.value_at(value_index)?.map(ValueRef::Shared))
+    }
+
+    /**
+     * Returns the value cache hit count.
+     *
+     * # Returns
+     * The count of the value reads served from the value cache since this
+     * storage was created. Always 0 for a storage without a value cache.
+     */
+    #[cfg(feature = "metrics")]
+    fn value_cache_hit_count(&self) -> usize {
+        0
+    }
 
     /**
      * Returns the filling rate.
@@ -149,17 +194,85 @@ pub trait Storage<Value: 'static>: Debug + 'static {
      */
     fn serialize(
         &self,
-        writer: &mut dyn Write,
+        writer: &mut dyn ByteWriter,
         value_serializer: &mut ValueSerializer<'_, Value>,
     ) -> Result<()>;
 
+    /**
+     * Serializes the values of this storage only.
+     *
+     * The base-check array is not written. The serialized values can be
+     * attached to a serialized storage with the same key structure by
+     * `MemoryStorage::new_with_value_reader`, so that some value sets share
+     * one base-check array.
+     *
+     * The values inlined into the base-check array (see
+     * `TrieBuilder::inline_values`) cannot be detached from it. So the
+     * storage with inlined values cannot be serialized in this way.
+     *
+     * # Arguments
+     * * `writer`           - A writer.
+     * * `value_serializer` - A serializer for value objects.
+     *
+     * # Errors
+     * * When the base-check array has inlined values.
+     * * When it fails to serialize the values.
+     */
+    fn serialize_values_only(
+        &self,
+        writer: &mut dyn ByteWriter,
+        value_serializer: &mut ValueSerializer<'_, Value>,
+    ) -> Result<()>;
+
+    /**
+     * Serializes this storage with a progress callback.
+     *
+     * The callback is called with the byte count written so far and the
+     * estimated total byte count, each time about 64 KiB are written and once
+     * at the end. The serialization stops when the callback returns
+     * `ControlFlow::Break`. The bytes already written are left in the writer.
+     *
+     * # Arguments
+     * * `writer`           - A writer.
+     * * `value_serializer` - A serializer for value objects.
+     * * `progress`         - A progress callback.
+     *
+     * # Errors
+     * * When it fails to serialize the content.
+     * * When the callback cancels the serialization.
+     */
+    fn serialize_with_progress(
+        &self,
+        writer: &mut dyn ByteWriter,
+        value_serializer: &mut ValueSerializer<'_, Value>,
+        progress: &mut dyn FnMut(usize, usize) -> ControlFlow<()>,
+    ) -> Result<()> {
+        let value_size = match value_serializer.fixed_value_size() {
+            0 => 2 * size_of::<u32>(),
+            fixed_value_size => fixed_value_size,
+        };
+        let total_estimate =
+            (self.base_check_size()? + 4) * size_of::<u32>() + self.value_count()? * value_size;
+        let mut progress_writer = ProgressWriter {
+            writer,
+            progress,
+            total_estimate,
+            bytes_written: 0,
+            bytes_reported: 0,
+        };
+        self.serialize(&mut progress_writer, value_serializer)?;
+        let bytes_written = progress_writer.bytes_written;
+        let _flow = (progress_writer.progress)(bytes_written, bytes_written);
+        Ok(())
+    }
+
     /**
      * Clones this storage as `Box`.
      *
      * # Returns
      * A Box of a clone of this storage.
      */
-    fn clone_box(&self) -> Box<dyn Storage<Value>>;
+    fn clone_box(&self) -> Box<dyn StorageRead<Value>>;
 
     /**
      * Returns this object as 'Any'.
@@ -176,16 +289,109 @@ pub trait Storage<Value: 'static>: Debug + 'static {
      * This mutable object as 'Any'.
      */
     fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /**
+     * Returns this storage as a mutable storage.
+     *
+     * # Returns
+     * This storage as a mutable storage. Or None when this storage cannot be
+     * modified.
+     */
+    fn as_storage_mut(&mut self) -> Option<&mut dyn StorageMut<Value>> {
+        None
+    }
 }
 
-impl<Value: 'static> dyn Storage<Value> {
+/**
+ * A mutable storage.
+ *
+ * # Type Parameters
+ * * `Value` - A value type.
+ */
+pub trait StorageMut<Value: 'static>: StorageRead<Value> {
+    /**
+     * Sets a base value.
+     *
+     * # Arguments
+     * * `base_check_index` - A base-check index.
+     * * `base`             - A base value.
+     *
+     * # Errors
+     * * When it fails to write the base value.
+     */
+    fn set_base_at(&mut self, base_check_index: usize, base: i32) -> Result<()>;
+
+    /**
+     * Sets a check value.
+     *
+     * # Arguments
+     * * `base_check_index` - A base-check index.
+     * * `check`            - A check value.
+     *
+     * # Errors
+     * * When it fails to write the check value.
+     */
+    fn set_check_at(&mut self, base_check_index: usize, check: u8) -> Result<()>;
+
+    /**
+     * Adds a value object.
+     *
+     * # Arguments
+     * * `value_index` - A value index.
+     * * `value`       - A value object.
+     *
+     * # Errors
+     * * When it fails to write the value object.
+     */
+    fn add_value_at(&mut self, value_index: usize, value: Value) -> Result<()>;
+}
+
+/**
+ * A storage.
+ *
+ * Kept for backward compatibility. Every mutable storage is a storage.
+ *
+ * # Type Parameters
+ * * `Value` - A value type.
+ */
+pub trait Storage<Value: 'static>: StorageMut<Value> {}
+
+impl<Value: 'static, T: StorageMut<Value> + ?Sized> Storage<Value> for T {}
+
+struct ProgressWriter<'a> {
+    writer: &'a mut dyn ByteWriter,
+    progress: &'a mut dyn FnMut(usize, usize) -> ControlFlow<()>,
+    total_estimate: usize,
+    bytes_written: usize,
+    bytes_reported: usize,
+}
+
+impl ByteWriter for ProgressWriter<'_> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_bytes(bytes)?;
+        self.bytes_written += bytes.len();
+        if self.bytes_written - self.bytes_reported < PROGRESS_CHUNK_SIZE {
+            return Ok(());
+        }
+        self.bytes_reported = self.bytes_written;
+        match (self.progress)(
+            self.bytes_written,
+            self.total_estimate.max(self.bytes_written),
+        ) {
+            ControlFlow::Continue(()) => Ok(()),
+            ControlFlow::Break(()) => Err(StorageSerializationError::Cancelled.into()),
+        }
+    }
+}
+
+impl<Value: 'static> dyn StorageRead<Value> {
     /**
      * Returns `true` if the concrete type of this input is `T`.
      *
      * # Returns
      * `true` if the concrete type of this input is `T`.
      */
-    pub fn is<T: Storage<Value>>(&self) -> bool {
+    pub fn is<T: StorageRead<Value>>(&self) -> bool {
         self.as_any().is::<T>()
     }
 
@@ -195,7 +401,7 @@ impl<Value: 'static> dyn Storage<Value> {
      * # Returns
      * The object of the concrete type.
      */
-    pub fn downcast_ref<T: Storage<Value>>(&self) -> Option<&T> {
+    pub fn downcast_ref<T: StorageRead<Value>>(&self) -> Option<&T> {
         self.as_any().downcast_ref::<T>()
     }
 
@@ -205,19 +411,146 @@ impl<Value: 'static> dyn Storage<Value> {
      * # Returns
      * The mutable object of the concrete type.
      */
-    pub fn downcast_mut<T: Storage<Value>>(&mut self) -> Option<&mut T> {
+    pub fn downcast_mut<T: StorageRead<Value>>(&mut self) -> Option<&mut T> {
         self.as_any_mut().downcast_mut::<T>()
     }
+
+    /**
+     * Returns an iterator over the values.
+     *
+     * The values are read in the order of their indexes without traversing
+     * the double array. The slots without values are skipped.
+     *
+     * The values inlined into the double array (see
+     * `TrieBuilder::inline_values`) are not in the slots, so they are not
+     * iterated. Iterate the trie to read all the values of such a trie.
+     *
+     * # Returns
+     * An iterator over the pairs of the value indexes and the values.
+     *
+     * # Errors
+     * * When it fails to read the value count.
+     */
+    pub fn iter_values(&self) -> Result<StorageValueIterator<'_, Value>> {
+        Ok(StorageValueIterator {
+            storage: self,
+            value_indexes: 0..self.value_count()?,
+            chunk: Vec::new().into_iter(),
+            chunk_offset: 0,
+        })
+    }
+}
+
+/**
+ * An iterator over the values of a storage.
+ *
+ * Each item is a pair of a value index and a value, or an error when it fails
+ * to read the values.
+ *
+ * # Type Parameters
+ * * `Value` - A value type.
+ */
+pub struct StorageValueIterator<'a, Value: 'static> {
+    storage: &'a dyn StorageRead<Value>,
+    value_indexes: Range<usize>,
+    chunk: vec::IntoIter<Option<Rc<Value>>>,
+    chunk_offset: usize,
+}
+
+impl<Value: 'static> Debug for StorageValueIterator<'_, Value> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StorageValueIterator")
+            .field("storage", &self.storage)
+            .field("value_indexes", &self.value_indexes)
+            .field("chunk_offset", &self.chunk_offset)
+            .finish()
+    }
+}
+
+impl<Value: 'static> Iterator for StorageValueIterator<'_, Value> {
+    type Item = Result<(usize, Rc<Value>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let chunk_index = self.chunk_offset;
+            match self.chunk.next() {
+                Some(value) => {
+                    self.chunk_offset += 1;
+                    if let Some(value) = value {
+                        return Some(Ok((chunk_index, value)));
+                    }
+                }
+                None => {
+                    if self.value_indexes.is_empty() {
+                        return None;
+                    }
+                    // The values are read in chunks, so that a storage can
+                    // read the values in a block at once.
+                    let chunk_start = self.value_indexes.start;
+                    let chunk_end = self
+                        .value_indexes
+                        .end
+                        .min(chunk_start.saturating_add(VALUE_CHUNK_SIZE));
+                    self.value_indexes.start = chunk_end;
+                    match self.storage.values_at(chunk_start..chunk_end) {
+                        Ok(values) => {
+                            self.chunk = values.into_iter();
+                            self.chunk_offset = chunk_start;
+                        }
+                        Err(e) => {
+                            self.value_indexes = 0..0;
+                            return Some(Err(e));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/**
+ * A reference to a value object.
+ *
+ * # Type Parameters
+ * * `Value` - A value type.
+ */
+#[derive(Debug)]
+pub enum ValueRef<'a, Value> {
+    /**
+     * A value object borrowed from a storage.
+     */
+    Borrowed(&'a Value),
+
+    /**
+     * A value object shared with a storage or made on the fly.
+     */
+    Shared(Rc<Value>),
+}
+
+impl<Value> Deref for ValueRef<'_, Value> {
+    type Target = Value;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            ValueRef::Borrowed(value) => value,
+            ValueRef::Shared(value) => value.as_ref(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::{String, ToString};
+
+    use crate::memory_storage::MemoryStorage;
+    use crate::trie::Trie;
+
     use super::*;
 
     #[derive(Debug)]
     struct ConcreteStorage1;
 
-    impl Storage<i32> for ConcreteStorage1 {
+    impl StorageRead<i32> for ConcreteStorage1 {
         fn base_check_size(&self) -> Result<usize> {
             unimplemented!()
         }
@@ -226,18 +559,10 @@ mod tests {
             unimplemented!()
         }
 
-        fn set_base_at(&mut self, _: usize, _: i32) -> Result<()> {
-            unimplemented!()
-        }
-
         fn check_at(&self, _: usize) -> Result<u8> {
             unimplemented!()
         }
 
-        fn set_check_at(&mut self, _: usize, _: u8) -> Result<()> {
-            unimplemented!()
-        }
-
         fn value_count(&self) -> Result<usize> {
             unimplemented!()
         }
@@ -246,19 +571,27 @@ mod tests {
             unimplemented!()
         }
 
-        fn add_value_at(&mut self, _: usize, _: i32) -> Result<()> {
+        fn filling_rate(&self) -> Result<f64> {
             unimplemented!()
         }
 
-        fn filling_rate(&self) -> Result<f64> {
+        fn serialize(
+            &self,
+            _: &mut dyn ByteWriter,
+            _: &mut ValueSerializer<'_, i32>,
+        ) -> Result<()> {
             unimplemented!()
         }
 
-        fn serialize(&self, _: &mut dyn Write, _: &mut ValueSerializer<'_, i32>) -> Result<()> {
+        fn serialize_values_only(
+            &self,
+            _: &mut dyn ByteWriter,
+            _: &mut ValueSerializer<'_, i32>,
+        ) -> Result<()> {
             unimplemented!()
         }
 
-        fn clone_box(&self) -> Box<dyn Storage<i32>> {
+        fn clone_box(&self) -> Box<dyn StorageRead<i32>> {
             unimplemented!()
         }
 
@@ -271,27 +604,33 @@ mod tests {
         }
     }
 
-    #[derive(Debug)]
-    struct ConcreteInput2;
+    impl StorageMut<i32> for ConcreteStorage1 {
+        fn set_base_at(&mut self, _: usize, _: i32) -> Result<()> {
+            unimplemented!()
+        }
 
-    impl Storage<i32> for ConcreteInput2 {
-        fn base_check_size(&self) -> Result<usize> {
+        fn set_check_at(&mut self, _: usize, _: u8) -> Result<()> {
             unimplemented!()
         }
 
-        fn base_at(&self, _: usize) -> Result<i32> {
+        fn add_value_at(&mut self, _: usize, _: i32) -> Result<()> {
             unimplemented!()
         }
+    }
 
-        fn set_base_at(&mut self, _: usize, _: i32) -> Result<()> {
+    #[derive(Debug)]
+    struct ConcreteInput2;
+
+    impl StorageRead<i32> for ConcreteInput2 {
+        fn base_check_size(&self) -> Result<usize> {
             unimplemented!()
         }
 
-        fn check_at(&self, _: usize) -> Result<u8> {
+        fn base_at(&self, _: usize) -> Result<i32> {
             unimplemented!()
         }
 
-        fn set_check_at(&mut self, _: usize, _: u8) -> Result<()> {
+        fn check_at(&self, _: usize) -> Result<u8> {
             unimplemented!()
         }
 
@@ -303,19 +642,27 @@ mod tests {
             unimplemented!()
         }
 
-        fn add_value_at(&mut self, _: usize, _: i32) -> Result<()> {
+        fn filling_rate(&self) -> Result<f64> {
             unimplemented!()
         }
 
-        fn filling_rate(&self) -> Result<f64> {
+        fn serialize(
+            &self,
+            _: &mut dyn ByteWriter,
+            _: &mut ValueSerializer<'_, i32>,
+        ) -> Result<()> {
             unimplemented!()
         }
 
-        fn serialize(&self, _: &mut dyn Write, _: &mut ValueSerializer<'_, i32>) -> Result<()> {
+        fn serialize_values_only(
+            &self,
+            _: &mut dyn ByteWriter,
+            _: &mut ValueSerializer<'_, i32>,
+        ) -> Result<()> {
             unimplemented!()
         }
 
-        fn clone_box(&self) -> Box<dyn Storage<i32>> {
+        fn clone_box(&self) -> Box<dyn StorageRead<i32>> {
             unimplemented!()
         }
 
@@ -328,10 +675,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn storage() {
+        fn as_storage<T: Storage<i32>>(storage: &T) -> &T {
+            storage
+        }
+
+        let input = ConcreteStorage1;
+        let _storage = as_storage(&input);
+    }
+
     #[test]
     fn is() {
         let input = ConcreteStorage1;
-        let input_ref: &dyn Storage<i32> = &input;
+        let input_ref: &dyn StorageRead<i32> = &input;
 
         assert!(input_ref.is::<ConcreteStorage1>());
         assert!(!input_ref.is::<ConcreteInput2>());
@@ -340,7 +697,7 @@ mod tests {
     #[test]
     fn downcast_ref() {
         let input = ConcreteStorage1;
-        let input_ref: &dyn Storage<i32> = &input;
+        let input_ref: &dyn StorageRead<i32> = &input;
 
         assert!(input_ref.downcast_ref::<ConcreteStorage1>().is_some());
         assert!(input_ref.downcast_ref::<ConcreteInput2>().is_none());
@@ -349,9 +706,58 @@ mod tests {
     #[test]
     fn downcast_mut() {
         let mut input = ConcreteStorage1;
-        let input_ref: &mut dyn Storage<i32> = &mut input;
+        let input_ref: &mut dyn StorageRead<i32> = &mut input;
 
         assert!(input_ref.downcast_mut::<ConcreteStorage1>().is_some());
         assert!(input_ref.downcast_mut::<ConcreteInput2>().is_none());
     }
+
+    #[test]
+    fn iter_values() {
+        {
+            let storage = MemoryStorage::<String>::new();
+            let storage_ref: &dyn StorageRead<String> = &storage;
+
+            assert!(storage_ref.iter_values().unwrap().next().is_none());
+        }
+        {
+            let mut storage = MemoryStorage::<String>::new();
+            storage.add_value_at(24, "hoge".to_string()).unwrap();
+            storage.add_value_at(0x100, "fuga".to_string()).unwrap();
+            storage.add_value_at(0, "piyo".to_string()).unwrap();
+            let storage_ref: &dyn StorageRead<String> = &storage;
+
+            let values = storage_ref
+                .iter_values()
+                .unwrap()
+                .map(|item| item.map(|(index, value)| (index, (*value).clone())))
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(
+                values,
+                [
+                    (0, "piyo".to_string()),
+                    (24, "hoge".to_string()),
+                    (0x100, "fuga".to_string())
+                ]
+            );
+        }
+        {
+            let trie = Trie::<&str, u32>::builder()
+                .elements([("Kumamoto", 42u32), ("Tamana", 0x0100_0000u32)].to_vec())
+                .inline_values()
+                .build()
+                .unwrap();
+
+            let values = trie
+                .storage()
+                .iter_values()
+                .unwrap()
+                .map(|item| item.map(|(_, value)| *value))
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(values, [0x0100_0000u32]);
+            assert_eq!(trie.iter().count(), 2);
+        }
+    }
 }
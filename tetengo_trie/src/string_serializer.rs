@@ -4,12 +4,18 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
+use alloc::string::String;
+use alloc::vec::Vec;
 use anyhow::Result;
+use core::str;
 
 use crate::serializer::{Deserializer, DeserializerOf, Serializer, SerializerOf};
 
 /**
  * A string (&str) serializer.
+ *
+ * The strings are serialized as they are in UTF-8. A trie rejects the keys
+ * containing U+0000.
  */
 #[derive(Clone, Copy, Debug, Default)]
 pub struct StrSerializer;
@@ -24,10 +30,21 @@ impl Serializer for StrSerializer {
     fn serialize(&self, object: &Self::Object<'_>) -> Vec<u8> {
         object.as_bytes().to_vec()
     }
+
+    fn serialize_into(&self, object: &Self::Object<'_>, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(object.as_bytes());
+    }
+
+    fn serialized_len(&self, object: &Self::Object<'_>) -> usize {
+        object.len()
+    }
 }
 
 /**
  * A string (String) serializer.
+ *
+ * The strings are serialized as they are in UTF-8. A trie rejects the keys
+ * containing U+0000.
  */
 #[derive(Clone, Copy, Debug, Default)]
 pub struct StringSerializer;
@@ -42,6 +59,14 @@ impl Serializer for StringSerializer {
     fn serialize(&self, object: &Self::Object<'_>) -> Vec<u8> {
         object.as_bytes().to_vec()
     }
+
+    fn serialize_into(&self, object: &Self::Object<'_>, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(object.as_bytes());
+    }
+
+    fn serialized_len(&self, object: &Self::Object<'_>) -> usize {
+        object.len()
+    }
 }
 
 /**
@@ -62,6 +87,149 @@ impl Deserializer for StringDeserializer {
     }
 }
 
+/**
+ * A UTF-16 string (&[u16]) serializer.
+ *
+ * The strings are serialized in UTF-8, in the same way as `StrSerializer`, so
+ * that a trie built with UTF-8 keys can be looked up with UTF-16 keys. A lone
+ * surrogate is serialized as U+FFFD. A trie rejects the keys containing
+ * U+0000.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Utf16StrSerializer;
+
+impl Serializer for Utf16StrSerializer {
+    type Object<'a> = &'a [u16];
+
+    fn new(_: bool) -> Self {
+        Utf16StrSerializer {}
+    }
+
+    fn serialize(&self, object: &Self::Object<'_>) -> Vec<u8> {
+        let mut serialized = Vec::with_capacity(self.serialized_len(object));
+        self.serialize_into(object, &mut serialized);
+        serialized
+    }
+
+    fn serialize_into(&self, object: &Self::Object<'_>, buffer: &mut Vec<u8>) {
+        serialize_chars_into(decode_utf16(object), buffer);
+    }
+
+    fn serialized_len(&self, object: &Self::Object<'_>) -> usize {
+        decode_utf16(object).map(char::len_utf8).sum()
+    }
+}
+
+/**
+ * A UTF-16 string (`Vec<u16>`) serializer.
+ *
+ * The strings are serialized in UTF-8, in the same way as `StringSerializer`,
+ * so that a trie built with UTF-8 keys can be looked up with UTF-16 keys. A
+ * lone surrogate is serialized as U+FFFD. A trie rejects the keys containing
+ * U+0000.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Utf16StringSerializer;
+
+impl Serializer for Utf16StringSerializer {
+    type Object<'a> = Vec<u16>;
+
+    fn new(_: bool) -> Self {
+        Utf16StringSerializer {}
+    }
+
+    fn serialize(&self, object: &Self::Object<'_>) -> Vec<u8> {
+        Utf16StrSerializer.serialize(&object.as_slice())
+    }
+
+    fn serialize_into(&self, object: &Self::Object<'_>, buffer: &mut Vec<u8>) {
+        Utf16StrSerializer.serialize_into(&object.as_slice(), buffer);
+    }
+
+    fn serialized_len(&self, object: &Self::Object<'_>) -> usize {
+        Utf16StrSerializer.serialized_len(&object.as_slice())
+    }
+}
+
+/**
+ * A UTF-16 string (`Vec<u16>`) deserializer.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Utf16StringDeserializer;
+
+impl Deserializer for Utf16StringDeserializer {
+    type Object = Vec<u16>;
+
+    fn new(_: bool) -> Self {
+        Utf16StringDeserializer {}
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Self::Object> {
+        Ok(str::from_utf8(bytes)?.encode_utf16().collect())
+    }
+}
+
+/**
+ * A code point sequence (`Vec<char>`) serializer.
+ *
+ * The code points are serialized in UTF-8, in the same way as
+ * `StringSerializer`. A trie rejects the keys containing U+0000.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CodePointSerializer;
+
+impl Serializer for CodePointSerializer {
+    type Object<'a> = Vec<char>;
+
+    fn new(_: bool) -> Self {
+        CodePointSerializer {}
+    }
+
+    fn serialize(&self, object: &Self::Object<'_>) -> Vec<u8> {
+        let mut serialized = Vec::with_capacity(self.serialized_len(object));
+        self.serialize_into(object, &mut serialized);
+        serialized
+    }
+
+    fn serialize_into(&self, object: &Self::Object<'_>, buffer: &mut Vec<u8>) {
+        serialize_chars_into(object.iter().copied(), buffer);
+    }
+
+    fn serialized_len(&self, object: &Self::Object<'_>) -> usize {
+        object.iter().copied().map(char::len_utf8).sum()
+    }
+}
+
+/**
+ * A code point sequence (`Vec<char>`) deserializer.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CodePointDeserializer;
+
+impl Deserializer for CodePointDeserializer {
+    type Object = Vec<char>;
+
+    fn new(_: bool) -> Self {
+        CodePointDeserializer {}
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Self::Object> {
+        Ok(str::from_utf8(bytes)?.chars().collect())
+    }
+}
+
+fn decode_utf16(units: &[u16]) -> impl Iterator<Item = char> + '_ {
+    char::decode_utf16(units.iter().copied())
+        .map(|decoded| decoded.unwrap_or(char::REPLACEMENT_CHARACTER))
+}
+
+fn serialize_chars_into(chars: impl Iterator<Item = char>, buffer: &mut Vec<u8>) {
+    let mut encoded = [0u8; 4];
+    for char_ in chars {
+        buffer.extend_from_slice(char_.encode_utf8(&mut encoded).as_bytes());
+    }
+}
+
 impl SerializerOf<&str> for () {
     type Type = StrSerializer;
 }
@@ -74,9 +242,30 @@ impl DeserializerOf<String> for () {
     type Type = StringDeserializer;
 }
 
+impl SerializerOf<&[u16]> for () {
+    type Type = Utf16StrSerializer;
+}
+
+impl SerializerOf<Vec<u16>> for () {
+    type Type = Utf16StringSerializer;
+}
+
+impl DeserializerOf<Vec<u16>> for () {
+    type Type = Utf16StringDeserializer;
+}
+
+impl SerializerOf<Vec<char>> for () {
+    type Type = CodePointSerializer;
+}
+
+impl DeserializerOf<Vec<char>> for () {
+    type Type = CodePointDeserializer;
+}
+
 #[cfg(test)]
 mod tests {
-    use std::string::FromUtf8Error;
+    use alloc::string::FromUtf8Error;
+    use core::str::Utf8Error;
 
     use super::*;
 
@@ -89,7 +278,7 @@ mod tests {
             let expected_serialized = "Sakuramachi";
             let serialized = serializer.serialize(&object);
             assert_eq!(
-                std::str::from_utf8(serialized.as_slice()).unwrap_or_default(),
+                str::from_utf8(serialized.as_slice()).unwrap_or_default(),
                 expected_serialized
             );
             assert!(!serialized.iter().any(|&b| b == 0x00u8));
@@ -101,11 +290,77 @@ mod tests {
             let expected_serialized = String::from("Sakuramachi");
             let serialized = serializer.serialize(&object);
             assert_eq!(
-                std::str::from_utf8(serialized.as_slice()).unwrap_or_default(),
+                str::from_utf8(serialized.as_slice()).unwrap_or_default(),
                 expected_serialized
             );
             assert!(!serialized.iter().any(|&b| b == 0x00u8));
         }
+        {
+            let serializer = <() as SerializerOf<&[u16]>>::Type::new(false);
+
+            let object = "桜町🌸".encode_utf16().collect::<Vec<_>>();
+            let serialized = serializer.serialize(&object.as_slice());
+            assert_eq!(
+                serialized,
+                StringSerializer.serialize(&String::from("桜町🌸"))
+            );
+
+            let lone_surrogate = [0x0041u16, 0xD800u16];
+            assert_eq!(
+                serializer.serialize(&lone_surrogate.as_slice()),
+                "A\u{FFFD}".as_bytes()
+            );
+        }
+        {
+            let serializer = <() as SerializerOf<Vec<u16>>>::Type::new(false);
+
+            let object = "桜町🌸".encode_utf16().collect::<Vec<_>>();
+            let mut serialized = b"Kumamoto".to_vec();
+            serializer.serialize_into(&object, &mut serialized);
+            assert_eq!(serialized, "Kumamoto桜町🌸".as_bytes());
+        }
+        {
+            let serializer = <() as SerializerOf<Vec<char>>>::Type::new(false);
+
+            let object = "桜町🌸".chars().collect::<Vec<_>>();
+            let serialized = serializer.serialize(&object);
+            assert_eq!(
+                serialized,
+                StringSerializer.serialize(&String::from("桜町🌸"))
+            );
+        }
+    }
+
+    #[test]
+    fn serialized_len() {
+        {
+            let serializer = <() as SerializerOf<&str>>::Type::new(false);
+
+            assert_eq!(serializer.serialized_len(&"Sakuramachi"), 11);
+        }
+        {
+            let serializer = <() as SerializerOf<String>>::Type::new(false);
+
+            assert_eq!(serializer.serialized_len(&String::from("桜町")), 6);
+        }
+        {
+            let serializer = <() as SerializerOf<&[u16]>>::Type::new(false);
+
+            let object = "桜町🌸".encode_utf16().collect::<Vec<_>>();
+            assert_eq!(serializer.serialized_len(&object.as_slice()), 10);
+        }
+        {
+            let serializer = <() as SerializerOf<Vec<u16>>>::Type::new(false);
+
+            let object = "桜町🌸".encode_utf16().collect::<Vec<_>>();
+            assert_eq!(serializer.serialized_len(&object), 10);
+        }
+        {
+            let serializer = <() as SerializerOf<Vec<char>>>::Type::new(false);
+
+            let object = "桜町🌸".chars().collect::<Vec<_>>();
+            assert_eq!(serializer.serialized_len(&object), 10);
+        }
     }
 
     #[test]
@@ -128,5 +383,27 @@ mod tests {
                 false
             });
         }
+        {
+            let deserializer = <() as DeserializerOf<Vec<u16>>>::Type::new(false);
+
+            let object = deserializer.deserialize("桜町🌸".as_bytes()).unwrap();
+            assert_eq!(object, "桜町🌸".encode_utf16().collect::<Vec<_>>());
+
+            let serialized = &[0xFFu8, 0xFFu8, 0xFFu8];
+            assert!(if let Err(e) = deserializer.deserialize(serialized) {
+                e.downcast_ref::<Utf8Error>().is_some()
+            } else {
+                false
+            });
+        }
+        {
+            let deserializer = <() as DeserializerOf<Vec<char>>>::Type::new(false);
+
+            let object = deserializer.deserialize("桜町🌸".as_bytes()).unwrap();
+            assert_eq!(object, "桜町🌸".chars().collect::<Vec<_>>());
+
+            let serialized = &[0xFFu8, 0xFFu8, 0xFFu8];
+            assert!(deserializer.deserialize(serialized).is_err());
+        }
     }
 }
@@ -0,0 +1,202 @@
+/*!
+ * A swappable trie.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use core::fmt::Debug;
+use std::sync::{PoisonError, RwLock};
+
+use crate::serializer::{Serializer, SerializerOf};
+use crate::trie_snapshot::TrieSnapshot;
+
+/**
+ * A swappable trie.
+ *
+ * Holds the snapshot of the current trie and replaces it as a whole, such as
+ * after a dictionary is rebuilt in the background. The readers load a
+ * snapshot and keep using it while the trie is replaced, so they see either
+ * the old trie or the new one, never a mixture of them. The old trie is
+ * dropped when the last snapshot of it is dropped.
+ *
+ * The lock is held only while a snapshot is cloned or replaced, so the
+ * callers do not manage it. A swappable trie can be shared among threads
+ * when its snapshots can.
+ *
+ * # Type Parameters
+ * * `Key`           - A key type.
+ * * `Value`         - A value type.
+ * * `KeySerializer` - A key serializer type.
+ */
+#[derive(Debug)]
+pub struct SwappableTrie<
+    Key,
+    Value: Debug,
+    KeySerializer: Serializer = <() as SerializerOf<Key>>::Type,
+> {
+    current: RwLock<TrieSnapshot<Key, Value, KeySerializer>>,
+}
+
+impl<Key, Value: Debug, KeySerializer: Serializer + Clone>
+    SwappableTrie<Key, Value, KeySerializer>
+{
+    /**
+     * Creates a swappable trie.
+     *
+     * # Arguments
+     * * `snapshot` - A snapshot of an initial trie.
+     */
+    pub const fn new(snapshot: TrieSnapshot<Key, Value, KeySerializer>) -> Self {
+        Self {
+            current: RwLock::new(snapshot),
+        }
+    }
+
+    /**
+     * Loads a snapshot of the current trie.
+     *
+     * # Returns
+     * A snapshot of the current trie.
+     */
+    pub fn load(&self) -> TrieSnapshot<Key, Value, KeySerializer> {
+        // A snapshot is replaced as a whole, so it is not broken by a panic.
+        self.current
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /**
+     * Stores a snapshot as the current one.
+     *
+     * # Arguments
+     * * `snapshot` - A snapshot.
+     */
+    pub fn store(&self, snapshot: TrieSnapshot<Key, Value, KeySerializer>) {
+        let _previous = self.swap(snapshot);
+    }
+
+    /**
+     * Stores a snapshot as the current one and returns the previous one.
+     *
+     * # Arguments
+     * * `snapshot` - A snapshot.
+     *
+     * # Returns
+     * The previous snapshot.
+     */
+    pub fn swap(
+        &self,
+        snapshot: TrieSnapshot<Key, Value, KeySerializer>,
+    ) -> TrieSnapshot<Key, Value, KeySerializer> {
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        core::mem::replace(&mut *current, snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::trie::Trie;
+
+    use super::*;
+
+    fn make_snapshot(elements: Vec<(&'static str, i32)>) -> TrieSnapshot<&'static str, i32> {
+        let trie = Trie::<&str, i32>::builder()
+            .elements(elements)
+            .build()
+            .unwrap();
+        TrieSnapshot::new(&trie).unwrap()
+    }
+
+    #[test]
+    fn new() {
+        let _swappable_trie = SwappableTrie::new(make_snapshot(vec![("Kumamoto", 42)]));
+    }
+
+    #[test]
+    fn load() {
+        {
+            let swappable_trie = SwappableTrie::new(make_snapshot(vec![("Kumamoto", 42)]));
+
+            let snapshot = swappable_trie.load();
+            assert_eq!(*snapshot.trie().find(&"Kumamoto").unwrap().unwrap(), 42);
+            assert!(TrieSnapshot::ptr_eq(&snapshot, &swappable_trie.load()));
+        }
+        {
+            let swappable_trie = SwappableTrie::new(make_snapshot(vec![("Kumamoto", 42)]));
+
+            thread::scope(|scope| {
+                let readers = (0..4)
+                    .map(|_| {
+                        scope.spawn(|| {
+                            (0..1000)
+                                .map(|_| {
+                                    let trie = swappable_trie.load().trie();
+                                    match (
+                                        trie.find(&"Kumamoto").unwrap(),
+                                        trie.find(&"Tamana").unwrap(),
+                                    ) {
+                                        (Some(value), None) => {
+                                            assert_eq!(*value, 42);
+                                            false
+                                        }
+                                        (None, Some(value)) => {
+                                            assert_eq!(*value, 24);
+                                            true
+                                        }
+                                        _ => unreachable!("a trie must be either old or new."),
+                                    }
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let writer = scope.spawn(|| {
+                    swappable_trie.store(make_snapshot(vec![("Tamana", 24)]));
+                });
+
+                writer.join().unwrap();
+                for reader in readers {
+                    let found_new = reader.join().unwrap();
+                    // Once a reader sees the new trie, it never sees the old one.
+                    assert!(found_new.windows(2).all(|pair| pair[0] <= pair[1]));
+                }
+            });
+
+            assert_eq!(
+                *swappable_trie
+                    .load()
+                    .trie()
+                    .find(&"Tamana")
+                    .unwrap()
+                    .unwrap(),
+                24
+            );
+        }
+    }
+
+    #[test]
+    fn store() {
+        let swappable_trie = SwappableTrie::new(make_snapshot(vec![("Kumamoto", 42)]));
+        let old_snapshot = swappable_trie.load();
+
+        swappable_trie.store(make_snapshot(vec![("Tamana", 24)]));
+
+        let new_trie = swappable_trie.load().trie();
+        assert!(new_trie.find(&"Kumamoto").unwrap().is_none());
+        assert_eq!(*new_trie.find(&"Tamana").unwrap().unwrap(), 24);
+        assert_eq!(*old_snapshot.trie().find(&"Kumamoto").unwrap().unwrap(), 42);
+    }
+
+    #[test]
+    fn swap() {
+        let swappable_trie = SwappableTrie::new(make_snapshot(vec![("Kumamoto", 42)]));
+
+        let previous = swappable_trie.swap(make_snapshot(vec![("Tamana", 24)]));
+
+        assert_eq!(*previous.trie().find(&"Kumamoto").unwrap().unwrap(), 42);
+        assert!(!TrieSnapshot::ptr_eq(&previous, &swappable_trie.load()));
+    }
+}
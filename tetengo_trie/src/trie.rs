@@ -4,18 +4,143 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use std::any::type_name_of_val;
-use std::cell::RefCell;
-use std::fmt::{self, Debug, Formatter};
-use std::marker::PhantomData;
-use std::rc::Rc;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BinaryHeap};
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::any::{type_name_of_val, Any};
+use core::cell::RefCell;
+use core::cmp::Ordering;
+use core::fmt::{self, Debug, Formatter, Write as _};
+use core::marker::PhantomData;
+use core::ops::RangeBounds;
+#[cfg(feature = "std")]
+use std::collections::HashSet;
+#[cfg(feature = "std")]
+use std::io::{BufRead, BufReader, Read};
 
 use anyhow::Result;
 
+use crate::build_checkpoint::BuildCheckpoint;
+use crate::byte_writer::ByteWriter;
+use crate::completion_hints::CompletionHints;
 use crate::double_array::{self, DoubleArray, DEFAULT_DENSITY_FACTOR};
-use crate::serializer::{Serializer, SerializerOf};
-use crate::storage::Storage;
+#[cfg(feature = "std")]
+use crate::external_sort::ExternalSorter;
+use crate::key_diagnostics::KeyDiagnostics;
+#[cfg(feature = "metrics")]
+use crate::lookup_metrics::{LookupMetrics, LookupOperation, MetricsSink};
+use crate::serializer::{Deserializer, Serializer, SerializerOf, KEY_TERMINATOR};
+use crate::storage::{StorageMut, StorageRead, ValueRef};
+use crate::trie_cursor::TrieCursor;
 use crate::trie_iterator::TrieIterator;
+use crate::trie_range_iterator::TrieRangeIterator;
+use crate::trie_suffix_iterator::TrieSuffixIterator;
+
+/**
+ * A trie TSV error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum TrieTsvError {
+    /**
+     * A line has no tab separating the key and the value.
+     */
+    #[error("a line has no tab separating the key and the value")]
+    MissingSeparator,
+
+    /**
+     * A line has an invalid escape sequence.
+     */
+    #[error("a line has an invalid escape sequence")]
+    InvalidEscapeSequence,
+
+    /**
+     * A key appears more than once.
+     */
+    #[error("the key at {index} appears more than once")]
+    DuplicateKey {
+        /// The index of the second record with the key.
+        index: usize,
+    },
+}
+
+/**
+ * A trie builder error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum TrieBuilderError {
+    /**
+     * The keys are not sorted though they are assumed to be sorted.
+     */
+    #[error("the key at {index} is less than the previous one")]
+    UnsortedKeys {
+        /// The index of the first element whose key is out of order.
+        index: usize,
+    },
+
+    /**
+     * A serialized key contains the key terminator.
+     */
+    #[error("the key at {index} contains the key terminator")]
+    KeyContainsTerminator {
+        /// The index of the element whose key contains the key terminator.
+        index: usize,
+    },
+
+    /**
+     * A serialized key is longer than the max key length.
+     */
+    #[error("the key at {index} is longer than the max key length")]
+    TooLongKey {
+        /// The index of the element whose key is too long.
+        index: usize,
+    },
+
+    /**
+     * The build is cancelled.
+     */
+    #[error("the build is cancelled")]
+    Cancelled,
+}
+
+/**
+ * A trie error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum TrieError {
+    /**
+     * A serialized key is longer than the max key length.
+     */
+    #[error("the key is longer than the max key length")]
+    TooLongKey,
+
+    /**
+     * The storage cannot be modified.
+     */
+    #[error("the storage cannot be modified")]
+    ImmutableStorage,
+
+    /**
+     * An inlined value is replaced with a value which cannot be inlined.
+     */
+    #[error("an inlined value is replaced with a value which cannot be inlined")]
+    NotInlinableValue,
+}
+
+/**
+ * A key expander.
+ *
+ * Returns the keys of a record.
+ *
+ * # Type Parameters
+ * * `Record` - A record type.
+ * * `Key`    - A key type.
+ */
+pub type KeyExpander<'a, Record, Key> = &'a dyn Fn(&Record) -> Vec<Key>;
+
+type Checkpointing<'a> = (usize, &'a mut dyn FnMut(&BuildCheckpoint) -> Result<()>);
 
 /**
  * A building observer set.
@@ -23,6 +148,9 @@ use crate::trie_iterator::TrieIterator;
 pub struct BuldingObserverSet<'a> {
     adding: &'a mut dyn FnMut(&[u8]),
     done: &'a mut dyn FnMut(),
+    checkpointing: Option<Checkpointing<'a>>,
+    diagnosing: Option<&'a mut dyn FnMut(&KeyDiagnostics)>,
+    cancelling: Option<&'a dyn Fn() -> bool>,
 }
 
 impl<'a> BuldingObserverSet<'a> {
@@ -34,7 +162,89 @@ impl<'a> BuldingObserverSet<'a> {
      * * `done` - A done observer.
      */
     pub fn new(adding: &'a mut dyn FnMut(&[u8]), done: &'a mut dyn FnMut()) -> Self {
-        Self { adding, done }
+        Self {
+            adding,
+            done,
+            checkpointing: None,
+            diagnosing: None,
+            cancelling: None,
+        }
+    }
+
+    /**
+     * Sets a key diagnosing observer.
+     *
+     * The observer is called once with the diagnostics of the serialized keys
+     * before the double array is built.
+     *
+     * # Arguments
+     * * `diagnosing` - A key diagnosing observer.
+     */
+    pub fn diagnosing(mut self, diagnosing: &'a mut dyn FnMut(&KeyDiagnostics)) -> Self {
+        self.diagnosing = Some(diagnosing);
+        self
+    }
+
+    /**
+     * Sets a checkpointing observer.
+     *
+     * The observer is called with a checkpoint every time the specified count
+     * of elements are added. Save the checkpoint, for example, to a temporary
+     * file, and pass it to `TrieBuilder::resume_from` to resume the build
+     * after a crash. An error returned by the observer aborts the build.
+     *
+     * Taking a checkpoint copies the whole partial double array, so the
+     * interval should not be too small.
+     *
+     * # Arguments
+     * * `checkpoint_interval` - A checkpoint interval in elements. 0 disables
+     *   the checkpoints.
+     * * `checkpointing`       - A checkpointing observer.
+     */
+    pub fn checkpointing(
+        mut self,
+        checkpoint_interval: usize,
+        checkpointing: &'a mut dyn FnMut(&BuildCheckpoint) -> Result<()>,
+    ) -> Self {
+        self.checkpointing = Some((checkpoint_interval, checkpointing));
+        self
+    }
+
+    /**
+     * Sets a cancelling observer.
+     *
+     * The observer is called before each key is serialized and before each
+     * element is added. When it returns true, the build is aborted with
+     * `TrieBuilderError::Cancelled`. No partial trie is left.
+     *
+     * # Arguments
+     * * `cancelling` - A cancelling observer returning true to cancel.
+     */
+    pub fn cancelling(mut self, cancelling: &'a dyn Fn() -> bool) -> Self {
+        self.cancelling = Some(cancelling);
+        self
+    }
+
+    /**
+     * Returns `true` if the build is cancelled.
+     *
+     * # Returns
+     * `true` if the cancelling observer returns true. Or `false` when no
+     * cancelling observer is set.
+     */
+    pub fn cancelled(&self) -> bool {
+        self.cancelling.is_some_and(|cancelling| cancelling())
+    }
+
+    /**
+     * Returns the checkpoint interval.
+     *
+     * # Returns
+     * The checkpoint interval. Or `None` when no checkpointing observer is
+     * set.
+     */
+    pub fn checkpoint_interval(&self) -> Option<usize> {
+        self.checkpointing.as_ref().map(|&(interval, _)| interval)
     }
 
     /**
@@ -47,12 +257,40 @@ impl<'a> BuldingObserverSet<'a> {
         (self.adding)(serialized_key);
     }
 
+    /**
+     * Calls `diagnosing`.
+     *
+     * # Arguments
+     * * `diagnostics` - Key diagnostics.
+     */
+    pub fn diagnosed(&mut self, diagnostics: &KeyDiagnostics) {
+        if let Some(diagnosing) = &mut self.diagnosing {
+            diagnosing(diagnostics);
+        }
+    }
+
     /**
      * Calls `done`.
      */
     pub fn done(&mut self) {
         (self.done)();
     }
+
+    /**
+     * Calls `checkpointing`.
+     *
+     * # Arguments
+     * * `checkpoint` - A checkpoint.
+     *
+     * # Errors
+     * * When the checkpointing observer fails.
+     */
+    pub fn checkpoint(&mut self, checkpoint: &BuildCheckpoint) -> Result<()> {
+        match &mut self.checkpointing {
+            Some((_, checkpointing)) => checkpointing(checkpoint),
+            None => Ok(()),
+        }
+    }
 }
 
 impl Debug for BuldingObserverSet<'_> {
@@ -60,6 +298,9 @@ impl Debug for BuldingObserverSet<'_> {
         f.debug_struct("BuldingObserverSet")
             .field("adding", &type_name_of_val(&self.adding))
             .field("done", &type_name_of_val(&self.done))
+            .field("checkpointing", &type_name_of_val(&self.checkpointing))
+            .field("diagnosing", &type_name_of_val(&self.diagnosing))
+            .field("cancelling", &type_name_of_val(&self.cancelling))
             .finish()
     }
 }
@@ -70,27 +311,78 @@ const DEFAULT_DOUBLE_ARRAY_DENSITY_FACTOR: usize = DEFAULT_DENSITY_FACTOR;
 /**
  * A trie builder.
  *
+ * The keys of the elements may borrow data for the lifetime `'k`. The built
+ * trie does not borrow them, so borrowed keys such as `&str` avoid copying the
+ * keys only for building.
+ *
  * # Type Parameters
+ * * `'k`            - A lifetime of the keys of the elements.
  * * `Key`           - A key type.
  * * `Value`         - A value type.
  * * `KeySerializer` - A key serializer type.
  */
 #[derive(Debug)]
-pub struct TrieBuilder<Key, Value, KeySerializer: Serializer> {
+pub struct TrieBuilder<'k, Key, Value, KeySerializer: Serializer> {
     phantom: PhantomData<Key>,
-    elements: Vec<(KeySerializer::Object<'static>, Value)>,
+    keys: Vec<(KeySerializer::Object<'k>, usize)>,
+    values: Vec<Value>,
     key_serializer: KeySerializer,
     double_array_density_factor: usize,
+    keys_sorted: bool,
+    values_inlined: bool,
+    checkpoint: Option<BuildCheckpoint>,
+    max_key_len: Option<usize>,
+    #[cfg(feature = "std")]
+    external_sort_chunk_size: Option<usize>,
 }
 
-impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer>
-    TrieBuilder<Key, Value, KeySerializer>
+impl<'k, Key, Value: Clone + Debug + 'static, KeySerializer: Serializer>
+    TrieBuilder<'k, Key, Value, KeySerializer>
 {
     /**
      * Sets elements.
      */
-    pub fn elements(mut self, elements: Vec<(KeySerializer::Object<'static>, Value)>) -> Self {
-        self.elements = elements;
+    pub fn elements(mut self, elements: Vec<(KeySerializer::Object<'k>, Value)>) -> Self {
+        let (keys, values): (Vec<_>, Vec<_>) = elements
+            .into_iter()
+            .enumerate()
+            .map(|(i, (key, value))| ((key, i), value))
+            .unzip();
+        self.keys = keys;
+        self.values = values;
+        self
+    }
+
+    /**
+     * Sets records expanded to elements.
+     *
+     * Each record is expanded to its keys by the key expander, such as the
+     * surface form and the reading of a word. All the keys of a record share a
+     * single value slot, so the value is stored only once. The size of the
+     * built trie is the count of the records.
+     *
+     * The indices in the build errors are of the expanded keys.
+     *
+     * # Arguments
+     * * `records`      - Pairs of a record and its value.
+     * * `key_expander` - A key expander.
+     */
+    pub fn expanded_records<Record>(
+        mut self,
+        records: Vec<(Record, Value)>,
+        key_expander: KeyExpander<'_, Record, KeySerializer::Object<'k>>,
+    ) -> Self {
+        self.keys = Vec::with_capacity(records.len());
+        self.values = Vec::with_capacity(records.len());
+        for (record, value) in records {
+            let value_index = self.values.len();
+            self.keys.extend(
+                key_expander(&record)
+                    .into_iter()
+                    .map(|key| (key, value_index)),
+            );
+            self.values.push(value);
+        }
         self
     }
 
@@ -110,6 +402,75 @@ impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer>
         self
     }
 
+    /**
+     * Assumes that the elements are already sorted by their serialized keys.
+     *
+     * The builder skips sorting the elements and only checks their order.
+     */
+    pub fn assume_sorted(mut self) -> Self {
+        self.keys_sorted = true;
+        self
+    }
+
+    /**
+     * Inlines small values into the double array.
+     *
+     * When the value type is `u32`, the values up to 0x7FFFFF are stored in
+     * the terminal nodes of the double array instead of the value array. It
+     * saves the memory for the values and a storage access on each successful
+     * lookup. The other values are stored in the value array as usual.
+     *
+     * A trie with inlined values can be read by this version or later only.
+     */
+    pub fn inline_values(mut self) -> Self {
+        self.values_inlined = true;
+        self
+    }
+
+    /**
+     * Resumes the build from a checkpoint.
+     *
+     * The elements, the key serializer and the double array density factor
+     * must be the same as the ones of the interrupted build. The elements
+     * already added in the checkpoint are skipped, and the adding observer is
+     * not called for them.
+     */
+    pub fn resume_from(mut self, checkpoint: BuildCheckpoint) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    /**
+     * Sets a max key length.
+     *
+     * The build fails when a serialized key is longer than `max_key_len`
+     * bytes. The built trie rejects the longer keys on lookups.
+     */
+    pub fn max_key_len(mut self, max_key_len: usize) -> Self {
+        self.max_key_len = Some(max_key_len);
+        self
+    }
+
+    /**
+     * Sorts the elements externally.
+     *
+     * The serialized keys are sorted in chunks of about `chunk_size` bytes,
+     * and the sorted chunks are spilled to temporary files. They are merged
+     * into the sorted keys at the end. So the unsorted serialized keys are
+     * not held in memory at once. The double array is built from the sorted
+     * keys, which are still held in memory. This lowers the memory of the
+     * sort only, and the memory of the whole build is not bounded by
+     * `chunk_size`.
+     *
+     * The elements with the same key keep their order, as in the sort in
+     * memory. This is ignored when the elements are assumed to be sorted.
+     */
+    #[cfg(feature = "std")]
+    pub fn external_sort(mut self, chunk_size: usize) -> Self {
+        self.external_sort_chunk_size = Some(chunk_size);
+        self
+    }
+
     /**
      * Builds a trie.
      *
@@ -118,6 +479,11 @@ impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer>
      *
      * # Errors
      * * When it fails to access the storage.
+     * * When a serialized key contains `KEY_TERMINATOR`.
+     * * When a serialized key is longer than the max key length.
+     * * When the elements are assumed to be sorted but they are not.
+     * * When the checkpoint to resume from does not match the elements.
+     * * When it fails to access the temporary files of the external sort.
      */
     pub fn build(self) -> Result<Trie<Key, Value, KeySerializer>> {
         self.build_with_observer_set(&mut BuldingObserverSet::new(&mut |_| {}, &mut || {}))
@@ -131,22 +497,103 @@ impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer>
      *
      * # Errors
      * * When it fails to access the storage.
+     * * When a serialized key contains `KEY_TERMINATOR`.
+     * * When a serialized key is longer than the max key length.
+     * * When the elements are assumed to be sorted but they are not.
+     * * When the checkpoint to resume from does not match the elements.
+     * * When the checkpointing observer fails.
+     * * When it fails to access the temporary files of the external sort.
+     * * When the build is cancelled.
      */
     pub fn build_with_observer_set(
         self,
         building_observer_set: &mut BuldingObserverSet<'_>,
     ) -> Result<Trie<Key, Value, KeySerializer>> {
-        let mut double_array_content_keys = Vec::<Vec<u8>>::with_capacity(self.elements.len());
-        for element in &self.elements {
-            let (key, _) = &element;
-            let serialized_key = self.key_serializer.serialize(key);
-            double_array_content_keys.push(serialized_key);
+        let value_bases = self
+            .values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                self.values_inlined
+                    .then(|| <dyn Any>::downcast_ref::<u32>(value))
+                    .flatten()
+                    .and_then(|value| double_array::inline_value_base(*value))
+                    .unwrap_or(i as i32)
+            })
+            .collect::<Vec<_>>();
+
+        #[cfg(feature = "std")]
+        let mut external_sorter = self
+            .external_sort_chunk_size
+            .filter(|_| !self.keys_sorted)
+            .map(ExternalSorter::new);
+        // The serialized keys are stored in a single buffer to avoid an allocation per key.
+        let mut serialized_key_buffer = Vec::<u8>::new();
+        let mut serialized_key_ranges = Vec::with_capacity(self.keys.len());
+        let diagnosing = building_observer_set.diagnosing.is_some();
+        let mut escaped_byte_count = 0;
+        let mut total_serialized_key_len = 0;
+        let mut max_serialized_key_len = 0;
+        for (i, (key, value_index)) in self.keys.iter().enumerate() {
+            if building_observer_set.cancelled() {
+                return Err(TrieBuilderError::Cancelled.into());
+            }
+            let start = serialized_key_buffer.len();
+            self.key_serializer
+                .serialize_into(key, &mut serialized_key_buffer);
+            let serialized_key = &serialized_key_buffer[start..];
+            if diagnosing {
+                escaped_byte_count += self.key_serializer.escaped_byte_count(key);
+                total_serialized_key_len += serialized_key.len();
+                max_serialized_key_len = max_serialized_key_len.max(serialized_key.len());
+            }
+            if serialized_key.contains(&KEY_TERMINATOR) {
+                return Err(TrieBuilderError::KeyContainsTerminator { index: i }.into());
+            }
+            if self
+                .max_key_len
+                .is_some_and(|max_key_len| serialized_key.len() > max_key_len)
+            {
+                return Err(TrieBuilderError::TooLongKey { index: i }.into());
+            }
+            #[cfg(feature = "std")]
+            if let Some(external_sorter) = &mut external_sorter {
+                external_sorter.push(serialized_key, value_bases[*value_index])?;
+                serialized_key_buffer.truncate(start);
+                continue;
+            }
+            serialized_key_ranges.push((
+                start..serialized_key_buffer.len(),
+                value_bases[*value_index],
+            ));
+        }
+        building_observer_set.diagnosed(&KeyDiagnostics::new(
+            self.keys.len(),
+            escaped_byte_count,
+            total_serialized_key_len,
+            max_serialized_key_len,
+        ));
+        #[allow(unused_mut)]
+        let mut keys_sorted = self.keys_sorted;
+        #[cfg(feature = "std")]
+        if let Some(external_sorter) = external_sorter {
+            (serialized_key_buffer, serialized_key_ranges) = external_sorter.finish()?;
+            keys_sorted = true;
         }
-        let mut double_array_contents = Vec::<(&[u8], i32)>::with_capacity(self.elements.len());
-        for (i, _) in self.elements.iter().enumerate() {
-            double_array_contents.push((&double_array_content_keys[i], i as i32));
+        let double_array_contents = serialized_key_ranges
+            .into_iter()
+            .map(|(range, value_base)| (&serialized_key_buffer[range], value_base))
+            .collect::<Vec<_>>();
+        if self.keys_sorted {
+            if let Some(i) = double_array_contents
+                .windows(2)
+                .position(|contents| contents[0].0 > contents[1].0)
+            {
+                return Err(TrieBuilderError::UnsortedKeys { index: i + 1 }.into());
+            }
         }
 
+        let checkpoint_interval = building_observer_set.checkpoint_interval();
         let building_observer_set_ref_cell = RefCell::new(building_observer_set);
         let adding = &mut |&(key, _): &(&[u8], i32)| {
             building_observer_set_ref_cell.borrow_mut().adding(key);
@@ -154,22 +601,50 @@ impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer>
         let done = &mut || {
             building_observer_set_ref_cell.borrow_mut().done();
         };
-        let observer_set = &mut double_array::BuildingObserverSet::new(adding, done);
+        let checkpointing = &mut |checkpoint: &BuildCheckpoint| {
+            building_observer_set_ref_cell
+                .borrow_mut()
+                .checkpoint(checkpoint)
+        };
+        let cancelling = &mut || {
+            if building_observer_set_ref_cell.borrow().cancelled() {
+                Err(TrieBuilderError::Cancelled.into())
+            } else {
+                Ok(())
+            }
+        };
+        let mut observer_set =
+            double_array::BuildingObserverSet::new(adding, done).cancelling(cancelling);
+        if let Some(checkpoint_interval) = checkpoint_interval {
+            observer_set = observer_set.checkpointing(checkpoint_interval, checkpointing);
+        }
 
         let mut double_array = DoubleArray::<Value>::builder()
             .elements(double_array_contents)
             .density_factor(self.double_array_density_factor)
-            .build_with_observer_set(observer_set)?;
-
-        for (i, element) in self.elements.into_iter().enumerate() {
-            let (_, value) = element;
-            double_array.storage_mut().add_value_at(i, value)?;
+            .elements_sorted(keys_sorted)
+            .resume_from(self.checkpoint)
+            .build_with_observer_set(&mut observer_set)?;
+
+        let value_count = self.values.len();
+        for (i, value) in self.values.into_iter().enumerate() {
+            if value_bases[i] < 0 {
+                continue;
+            }
+            double_array.memory_storage_mut().add_value_at(i, value)?;
         }
+        // The size of the trie is the value count.
+        double_array
+            .memory_storage_mut()
+            .reserve_value_slots(value_count);
 
         Ok(Trie {
             phantom: PhantomData,
             double_array,
             key_serializer: self.key_serializer,
+            max_key_len: self.max_key_len,
+            #[cfg(feature = "metrics")]
+            metrics_sink: None,
         })
     }
 }
@@ -184,8 +659,9 @@ impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer>
  */
 pub struct TrieStorageBuilder<Key, Value: Clone, KeySerializer: Serializer> {
     phantom_key: PhantomData<Key>,
-    storage: Box<dyn Storage<Value>>,
+    storage: Box<dyn StorageRead<Value>>,
     key_serializer: KeySerializer,
+    max_key_len: Option<usize>,
 }
 
 impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer>
@@ -199,6 +675,17 @@ impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer>
         self
     }
 
+    /**
+     * Sets a max key length.
+     *
+     * The built trie rejects the keys whose serialized forms are longer than
+     * `max_key_len` bytes on lookups.
+     */
+    pub fn max_key_len(mut self, max_key_len: usize) -> Self {
+        self.max_key_len = Some(max_key_len);
+        self
+    }
+
     /**
      * Builds a trie.
      *
@@ -210,6 +697,9 @@ impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer>
             phantom: PhantomData,
             double_array: DoubleArray::new(self.storage, 0),
             key_serializer: self.key_serializer,
+            max_key_len: self.max_key_len,
+            #[cfg(feature = "metrics")]
+            metrics_sink: None,
         }
     }
 }
@@ -219,15 +709,51 @@ impl<Key, Value: Clone, KeySerializer: Serializer> Debug
 {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("_TrieStorageBuilder")
-            .field("storage", &"Box<dyn Storage<Value>>")
+            .field("storage", &"Box<dyn StorageRead<Value>>")
             .field("key_serializer", &"KeySerializer")
+            .field("max_key_len", &self.max_key_len)
             .finish()
     }
 }
 
+struct TopKCandidate<Criterion, Value> {
+    criterion: Criterion,
+    terminal_index: usize,
+    value: Rc<Value>,
+}
+
+impl<Criterion: Ord, Value> PartialEq for TopKCandidate<Criterion, Value> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<Criterion: Ord, Value> Eq for TopKCandidate<Criterion, Value> {}
+
+impl<Criterion: Ord, Value> PartialOrd for TopKCandidate<Criterion, Value> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Criterion: Ord, Value> Ord for TopKCandidate<Criterion, Value> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.criterion
+            .cmp(&other.criterion)
+            .then_with(|| other.terminal_index.cmp(&self.terminal_index))
+    }
+}
+
 /**
  * A trie.
  *
+ * A lookup such as `find` accesses the storage a few times for each byte of
+ * the serialized key, so it takes the time proportional to the key length
+ * regardless of the element count. Building takes the time proportional to the
+ * total length of the serialized keys multiplied by the count of the distinct
+ * bytes following each key prefix, and it slows down as the double array gets
+ * dense. Set a max key length to bound the time for untrusted keys.
+ *
  * # Type Parameters
  * * `Key`           - A key type.
  * * `Value`         - A value type.
@@ -238,6 +764,9 @@ pub struct Trie<Key, Value: Debug, KeySerializer: Serializer = <() as Serializer
     phantom: PhantomData<Key>,
     double_array: DoubleArray<Value>,
     key_serializer: KeySerializer,
+    max_key_len: Option<usize>,
+    #[cfg(feature = "metrics")]
+    metrics_sink: Option<Rc<dyn MetricsSink>>,
 }
 
 impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer + Clone>
@@ -249,12 +778,19 @@ impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer + Clone>
      * # Returns
      * A trie builder.
      */
-    pub fn builder() -> TrieBuilder<Key, Value, KeySerializer> {
+    pub fn builder<'k>() -> TrieBuilder<'k, Key, Value, KeySerializer> {
         TrieBuilder {
             phantom: PhantomData,
-            elements: Vec::new(),
+            keys: Vec::new(),
+            values: Vec::new(),
             key_serializer: KeySerializer::new(true),
             double_array_density_factor: DEFAULT_DOUBLE_ARRAY_DENSITY_FACTOR,
+            keys_sorted: false,
+            values_inlined: false,
+            checkpoint: None,
+            max_key_len: None,
+            #[cfg(feature = "std")]
+            external_sort_chunk_size: None,
         }
     }
 
@@ -265,12 +801,13 @@ impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer + Clone>
      * A trie builder with a storage.
      */
     pub fn builder_with_storage(
-        storage: Box<dyn Storage<Value>>,
+        storage: Box<dyn StorageRead<Value>>,
     ) -> TrieStorageBuilder<Key, Value, KeySerializer> {
         TrieStorageBuilder {
             phantom_key: PhantomData,
             storage,
             key_serializer: KeySerializer::new(true),
+            max_key_len: None,
         }
     }
 
@@ -310,10 +847,20 @@ impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer + Clone>
      * `true` if the trie contains the given key.
      *
      * # Errors
+     * * When the serialized key is longer than the max key length.
      * * When it fails to access the storage.
      */
     pub fn contains(&self, key: &KeySerializer::Object<'_>) -> Result<bool> {
-        let serialized_key = self.key_serializer.serialize(key);
+        #[cfg(feature = "metrics")]
+        let start = self.double_array.metrics();
+        let contained = self.contains_impl(key);
+        #[cfg(feature = "metrics")]
+        self.record_metrics(LookupOperation::Contains, &start);
+        contained
+    }
+
+    fn contains_impl(&self, key: &KeySerializer::Object<'_>) -> Result<bool> {
+        let serialized_key = self.serialize_key(key)?;
         Ok(self.double_array.find(&serialized_key)?.is_some())
     }
 
@@ -327,137 +874,1869 @@ impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer + Clone>
      * The value object. Or None when the trie does not have the given key.
      *
      * # Errors
+     * * When the serialized key is longer than the max key length.
      * * When it fails to access the storage.
      */
     pub fn find(&self, key: &KeySerializer::Object<'_>) -> Result<Option<Rc<Value>>> {
-        let serialized_key = self.key_serializer.serialize(key);
-        let index = self.double_array.find(&serialized_key)?;
-        let Some(index) = index else {
+        #[cfg(feature = "metrics")]
+        let start = self.double_array.metrics();
+        let found = self.find_impl(key);
+        #[cfg(feature = "metrics")]
+        self.record_metrics(LookupOperation::Find, &start);
+        found
+    }
+
+    fn find_impl(&self, key: &KeySerializer::Object<'_>) -> Result<Option<Rc<Value>>> {
+        let serialized_key = self.serialize_key(key)?;
+        let value_base = self.double_array.find(&serialized_key)?;
+        let Some(value_base) = value_base else {
             return Ok(None);
         };
 
-        self.double_array.storage().value_at(index as usize)
+        self.double_array.value_of_base(value_base)
     }
 
     /**
-     * Returns an iterator.
+     * Finds a reference to the value object corresponding to the given key.
+     *
+     * Unlike `find`, the value object is borrowed from the storage when the
+     * storage owns it in place, which saves the reference counting.
+     *
+     * # Arguments
+     * * `key` - A key.
      *
      * # Returns
-     * A double array iterator.
+     * A reference to the value object. Or None when the trie does not have the
+     * given key.
+     *
+     * # Errors
+     * * When the serialized key is longer than the max key length.
+     * * When it fails to access the storage.
      */
-    pub fn iter(&self) -> TrieIterator<'_, Value> {
-        TrieIterator::new(self.double_array.iter(), self.double_array.storage())
+    pub fn find_ref(&self, key: &KeySerializer::Object<'_>) -> Result<Option<ValueRef<'_, Value>>> {
+        #[cfg(feature = "metrics")]
+        let start = self.double_array.metrics();
+        let found = self.find_ref_impl(key);
+        #[cfg(feature = "metrics")]
+        self.record_metrics(LookupOperation::Find, &start);
+        found
+    }
+
+    fn find_ref_impl(
+        &self,
+        key: &KeySerializer::Object<'_>,
+    ) -> Result<Option<ValueRef<'_, Value>>> {
+        let serialized_key = self.serialize_key(key)?;
+        let value_base = self.double_array.find(&serialized_key)?;
+        let Some(value_base) = value_base else {
+            return Ok(None);
+        };
+
+        self.double_array.value_ref_of_base(value_base)
     }
 
     /**
-     * Returns a subtrie.
+     * Finds the value objects corresponding to the given keys.
+     *
+     * The serialized keys are sorted, and the traversal of each key resumes at
+     * the end of the prefix shared with the preceding one. So this is faster
+     * than calling `find` for each key, especially when the keys share long
+     * prefixes.
+     *
+     * # Arguments
+     * * `keys` - Keys.
+     *
+     * # Returns
+     * The value objects in the order of the keys. Each is None when the trie
+     * does not have the key.
+     *
+     * # Errors
+     * * When a serialized key is longer than the max key length.
+     * * When it fails to access the storage.
+     */
+    pub fn find_many(&self, keys: &[KeySerializer::Object<'_>]) -> Result<Vec<Option<Rc<Value>>>> {
+        #[cfg(feature = "metrics")]
+        let start = self.double_array.metrics();
+        let found = self.find_many_impl(keys);
+        #[cfg(feature = "metrics")]
+        self.record_metrics(LookupOperation::FindMany, &start);
+        found
+    }
+
+    fn find_many_impl(&self, keys: &[KeySerializer::Object<'_>]) -> Result<Vec<Option<Rc<Value>>>> {
+        let serialized_keys = keys
+            .iter()
+            .map(|key| self.serialize_key(key))
+            .collect::<Result<Vec<_>>>()?;
+        self.double_array
+            .find_many(&serialized_keys)?
+            .into_iter()
+            .map(|value_base| match value_base {
+                Some(value_base) => self.double_array.value_of_base(value_base),
+                None => Ok(None),
+            })
+            .collect()
+    }
+
+    /**
+     * Counts the keys with the given prefix.
+     *
+     * Walks the double array below the prefix without reading the value
+     * objects.
      *
      * # Arguments
      * * `key_prefix` - A key prefix.
      *
      * # Returns
-     * A subtrie. Or None when the trie does not have the given key prefix.
+     * The count of the keys with the prefix.
      *
      * # Errors
      * * When it fails to access the storage.
      */
-    pub fn subtrie(&self, key_prefix: &KeySerializer::Object<'_>) -> Result<Option<Self>> {
+    pub fn count_prefix(&self, key_prefix: &KeySerializer::Object<'_>) -> Result<usize> {
         let serialized_key_prefix = self.key_serializer.serialize(key_prefix);
-        let subdouble_array = self.double_array.subtrie(&serialized_key_prefix)?;
-        let Some(subdouble_array) = subdouble_array else {
-            return Ok(None);
-        };
-        Ok(Some(Self {
-            phantom: PhantomData,
-            double_array: subdouble_array,
-            key_serializer: self.key_serializer.clone(),
-        }))
+        self.double_array.count_prefix(&serialized_key_prefix)
     }
 
     /**
-     * Returns the storage.
+     * Returns `true` when the trie has the same keys and values as another.
+     *
+     * The double arrays are walked in parallel without listing the keys. The
+     * layouts of the double arrays and the storages may differ.
+     *
+     * # Arguments
+     * * `other` - Another trie.
      *
      * # Returns
-     * The storage.
+     * `true` if the tries have the same keys and values.
+     *
+     * # Errors
+     * * When it fails to access the storages.
      */
-    pub fn storage(&self) -> &dyn Storage<Value> {
-        self.double_array.storage()
+    pub fn content_eq(&self, other: &Self) -> Result<bool>
+    where
+        Value: PartialEq,
+    {
+        self.double_array.is_subset_of(&other.double_array, true)
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use std::io::Cursor;
-    use std::sync::LazyLock;
 
-    use crate::memory_storage::MemoryStorage;
-    use crate::serializer::Deserializer;
-    use crate::string_serializer::{StrSerializer, StringDeserializer};
-    use crate::value_serializer::{ValueDeserializer, ValueSerializer};
+    /**
+     * Returns `true` when another trie has all the keys of the trie with the
+     * same values.
+     *
+     * The double arrays are walked in parallel without listing the keys.
+     *
+     * # Arguments
+     * * `other` - Another trie.
+     *
+     * # Returns
+     * `true` if the trie is a subset of the other.
+     *
+     * # Errors
+     * * When it fails to access the storages.
+     */
+    pub fn is_subset_of(&self, other: &Self) -> Result<bool>
+    where
+        Value: PartialEq,
+    {
+        self.double_array.is_subset_of(&other.double_array, false)
+    }
+
+    /**
+     * Removes the keys with the given prefix.
+     *
+     * The trie is rebuilt in a memory storage from the remaining keys, so the
+     * value slots of the removed keys are freed. It takes the time of building
+     * a trie with the remaining keys. Nothing is rebuilt when no key has the
+     * prefix.
+     *
+     * # Arguments
+     * * `key_prefix` - A key prefix.
+     *
+     * # Returns
+     * The count of the removed keys.
+     *
+     * # Errors
+     * * When it fails to access the storage.
+     */
+    pub fn remove_subtree(&mut self, key_prefix: &KeySerializer::Object<'_>) -> Result<usize> {
+        let serialized_key_prefix = self.key_serializer.serialize(key_prefix);
+        if self.double_array.count_prefix(&serialized_key_prefix)? == 0 {
+            return Ok(0);
+        }
+
+        let mut remaining_keys = Vec::<Vec<u8>>::new();
+        let mut remaining_value_bases = Vec::<i32>::new();
+        let mut removed_count = 0;
+        let mut iterator = self.double_array.iter();
+        while let Some((serialized_key, value_base)) = iterator.next_with_key() {
+            if serialized_key.starts_with(&serialized_key_prefix) {
+                removed_count += 1;
+                continue;
+            }
+            remaining_keys.push(serialized_key);
+            remaining_value_bases.push(value_base);
+        }
+
+        // The keys sharing a value slot keep sharing it.
+        let mut value_indices = BTreeMap::<i32, usize>::new();
+        let mut inlined_value_count = 0;
+        let mut values = Vec::<(usize, Value)>::new();
+        let mut double_array_contents = Vec::<(&[u8], i32)>::with_capacity(remaining_keys.len());
+        for (serialized_key, value_base) in remaining_keys.iter().zip(remaining_value_bases.iter())
+        {
+            if *value_base < 0 {
+                double_array_contents.push((serialized_key, *value_base));
+                inlined_value_count += 1;
+                continue;
+            }
+            let value_index = if let Some(&value_index) = value_indices.get(value_base) {
+                value_index
+            } else {
+                let value_index = value_indices.len();
+                let _prev_value = value_indices.insert(*value_base, value_index);
+                if let Some(value) = self.double_array.storage().value_at(*value_base as usize)? {
+                    values.push((value_index, value.as_ref().clone()));
+                }
+                value_index
+            };
+            double_array_contents.push((serialized_key, value_index as i32));
+        }
+
+        let mut double_array = DoubleArray::<Value>::builder()
+            .elements(double_array_contents)
+            .build()?;
+        for (i, value) in values {
+            double_array.memory_storage_mut().add_value_at(i, value)?;
+        }
+        // The size of the trie is the value count.
+        double_array
+            .memory_storage_mut()
+            .reserve_value_slots(value_indices.len() + inlined_value_count);
+        self.double_array = double_array;
+
+        Ok(removed_count)
+    }
+
+    /**
+     * Replaces the value of a key.
+     *
+     * The value slot of the key is overwritten in place, so the structure of
+     * the double array is not changed. The keys sharing the value slot, such
+     * as the ones expanded from a record, get the new value too.
+     *
+     * An inlined value (see `TrieBuilder::inline_values`) is replaced only
+     * with a value which can be inlined.
+     *
+     * # Arguments
+     * * `key`   - A key.
+     * * `value` - A new value.
+     *
+     * # Returns
+     * The old value. Or None when the trie does not have the key.
+     *
+     * # Errors
+     * * When a serialized key is longer than the max key length.
+     * * When the storage cannot be modified.
+     * * When an inlined value is replaced with a value which cannot be
+     *   inlined.
+     * * When it fails to access the storage.
+     */
+    pub fn replace_value(
+        &mut self,
+        key: &KeySerializer::Object<'_>,
+        value: Value,
+    ) -> Result<Option<Rc<Value>>> {
+        let serialized_key = self.serialize_key(key)?;
+        let Some(terminal_index) = self.double_array.terminal_index(&serialized_key)? else {
+            return Ok(None);
+        };
+        let value_base = self.double_array.storage().base_at(terminal_index)?;
+        let old_value = self.double_array.value_of_base(value_base)?;
+
+        let Some(storage) = self.double_array.storage_mut() else {
+            return Err(TrieError::ImmutableStorage.into());
+        };
+        if value_base >= 0 {
+            storage.add_value_at(value_base as usize, value)?;
+        } else {
+            let Some(inlined_value_base) = <dyn Any>::downcast_ref::<u32>(&value)
+                .and_then(|value| double_array::inline_value_base(*value))
+            else {
+                return Err(TrieError::NotInlinableValue.into());
+            };
+            storage.set_base_at(terminal_index, inlined_value_base)?;
+        }
+        Ok(old_value)
+    }
+
+    /**
+     * Returns a cursor.
+     *
+     * The cursor matches the keys one serialized key byte at a time.
+     *
+     * # Returns
+     * A trie cursor at the root.
+     */
+    pub fn cursor(&self) -> TrieCursor<'_, Value> {
+        TrieCursor::new(&self.double_array)
+    }
+
+    /**
+     * Returns an iterator.
+     *
+     * The iterator yields the values in the ascending lexicographic order of
+     * the serialized keys. A key precedes the longer keys beginning with it.
+     *
+     * # Returns
+     * A double array iterator.
+     */
+    pub fn iter(&self) -> TrieIterator<'_, Value> {
+        TrieIterator::new(self.double_array.iter(), self.double_array.storage())
+    }
+
+    /**
+     * Returns a reverse iterator.
+     *
+     * The iterator yields the values in the descending lexicographic order of
+     * the serialized keys. A key follows the longer keys beginning with it.
+     *
+     * # Returns
+     * A double array iterator.
+     */
+    pub fn iter_rev(&self) -> TrieIterator<'_, Value> {
+        TrieIterator::new(
+            self.double_array.iter().reversed(),
+            self.double_array.storage(),
+        )
+    }
+
+    /**
+     * Returns an iterator for a key range.
+     *
+     * The keys are compared in their serialized forms lexicographically.
+     *
+     * # Arguments
+     * * `key_range` - A key range.
+     *
+     * # Returns
+     * A trie range iterator, which yields the serialized keys and the values.
+     */
+    pub fn range<'k>(
+        &self,
+        key_range: impl RangeBounds<KeySerializer::Object<'k>> + 'k,
+    ) -> TrieRangeIterator<'_, Value> {
+        let serialized_key_range = (
+            key_range
+                .start_bound()
+                .map(|key| self.key_serializer.serialize(key)),
+            key_range
+                .end_bound()
+                .map(|key| self.key_serializer.serialize(key)),
+        );
+        TrieRangeIterator::new(
+            self.double_array.range(serialized_key_range),
+            self.double_array.storage(),
+        )
+    }
+
+    /**
+     * Returns an iterator bounded by a depth and a prefix filter.
+     *
+     * The iterator skips the keys longer than `max_depth` bytes in their
+     * serialized forms, and the keys with the prefixes rejected by
+     * `prefix_filter`. The subtrees below them are not visited, so that the
+     * iteration on a subtrie yields the completions of its key prefix in a
+     * bounded time.
+     *
+     * The keys are relative to the root of this trie.
+     *
+     * # Arguments
+     * * `max_depth`     - A max depth in bytes.
+     * * `prefix_filter` - A filter called with each serialized key prefix.
+     *
+     * # Returns
+     * A trie range iterator, which yields the serialized keys and the values.
+     */
+    pub fn iter_bounded<'a>(
+        &'a self,
+        max_depth: usize,
+        prefix_filter: &'a dyn Fn(&[u8]) -> bool,
+    ) -> TrieRangeIterator<'a, Value> {
+        TrieRangeIterator::new(
+            self.double_array
+                .iter()
+                .max_depth(max_depth)
+                .prefix_filter(prefix_filter),
+            self.double_array.storage(),
+        )
+    }
+
+    /**
+     * Returns an iterator yielding the deserialized keys.
+     *
+     * The keys are relative to the root of this trie. For a subtrie, they are
+     * the suffixes following the key prefix.
+     *
+     * # Arguments
+     * * `key_deserializer` - A key deserializer.
+     *
+     * # Returns
+     * A trie suffix iterator, which yields the deserialized keys and the
+     * values.
+     */
+    pub fn iter_suffixes<KeyDeserializer: Deserializer>(
+        &self,
+        key_deserializer: KeyDeserializer,
+    ) -> TrieSuffixIterator<'_, Value, KeyDeserializer> {
+        TrieSuffixIterator::new(
+            self.double_array.iter(),
+            self.double_array.storage(),
+            key_deserializer,
+        )
+    }
+
+    /**
+     * Returns the k best entries by a criterion of the values.
+     *
+     * The values are read from the value array at once, and only the keys of
+     * the selected entries are reconstructed by walking up from their nodes.
+     * So the keys are not enumerated.
+     *
+     * # Arguments
+     * * `k`         - The max count of the entries.
+     * * `criterion` - A function returning the criterion of a value.
+     *
+     * # Returns
+     * The pairs of a serialized key and a value, in the descending order of
+     * the criterion. The order of the entries with the same criterion is
+     * unspecified. For a subtrie, the keys are the suffixes following the key
+     * prefix.
+     *
+     * # Type Parameters
+     * * `Criterion` - A criterion type.
+     *
+     * # Errors
+     * * When it fails to access the storage.
+     */
+    pub fn top_k_by<Criterion: Ord>(
+        &self,
+        k: usize,
+        criterion: impl Fn(&Value) -> Criterion,
+    ) -> Result<Vec<(Vec<u8>, Rc<Value>)>> {
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let storage = self.double_array.storage();
+        let (terminal_indices, parent_indices) = self.double_array.terminal_and_parent_indices()?;
+        let values = storage.values_at(0..storage.value_count()?)?;
+        let mut candidates = BinaryHeap::with_capacity(terminal_indices.len());
+        for terminal_index in terminal_indices {
+            let base = storage.base_at(terminal_index)?;
+            let value = if base >= 0 {
+                values.get(base as usize).cloned().flatten()
+            } else {
+                double_array::value_of_base(storage, base)?
+            };
+            let Some(value) = value else {
+                continue;
+            };
+            candidates.push(TopKCandidate {
+                criterion: criterion(&value),
+                terminal_index,
+                value,
+            });
+        }
+
+        let mut entries = Vec::with_capacity(k.min(candidates.len()));
+        while entries.len() < k {
+            let Some(candidate) = candidates.pop() else {
+                break;
+            };
+            // The nodes out of a subtrie are skipped.
+            let Some(key) = self
+                .double_array
+                .key_of_terminal(&parent_indices, candidate.terminal_index)?
+            else {
+                continue;
+            };
+            entries.push((key, candidate.value));
+        }
+        Ok(entries)
+    }
+
+    /**
+     * Returns the k best completions of a key prefix by the scores of the
+     * values.
+     *
+     * The whole subtree below the key prefix is walked, keeping the k best
+     * completions in a bounded heap. See `top_completions_with_hints` not to
+     * walk the whole subtree.
+     *
+     * # Arguments
+     * * `key_prefix` - A key prefix.
+     * * `k`          - The max count of the completions.
+     * * `score`      - A function returning the score of a value.
+     *
+     * # Returns
+     * The pairs of a serialized key and a value, in the descending order of
+     * the scores. The completions with the same score are in the ascending
+     * order of the serialized keys. The keys include the key prefix.
+     *
+     * # Errors
+     * * When it fails to access the storage.
+     */
+    pub fn top_completions(
+        &self,
+        key_prefix: &KeySerializer::Object<'_>,
+        k: usize,
+        score: impl Fn(&Value) -> i64,
+    ) -> Result<Vec<(Vec<u8>, Rc<Value>)>> {
+        let serialized_key_prefix = self.key_serializer.serialize(key_prefix);
+        self.double_array
+            .top_completions(&serialized_key_prefix, k, &score)
+    }
+
+    /**
+     * Returns the k best completions of a key prefix by the scores of the
+     * values, with completion hints.
+     *
+     * The subtrees are visited in the descending order of the max scores in
+     * the hints, and the walk stops after the k best completions are found.
+     * So the whole subtree below the key prefix is not walked.
+     *
+     * # Arguments
+     * * `key_prefix` - A key prefix.
+     * * `k`          - The max count of the completions.
+     * * `score`      - A function returning the score of a value. It must be the one the hints are made with.
+     * * `hints`      - Completion hints made by `completion_hints`.
+     *
+     * # Returns
+     * The pairs of a serialized key and a value, in the descending order of
+     * the scores. The completions with the same score are in the ascending
+     * order of the serialized keys. The keys include the key prefix.
+     *
+     * # Errors
+     * * When it fails to access the storage.
+     */
+    pub fn top_completions_with_hints(
+        &self,
+        key_prefix: &KeySerializer::Object<'_>,
+        k: usize,
+        score: impl Fn(&Value) -> i64,
+        hints: &CompletionHints,
+    ) -> Result<Vec<(Vec<u8>, Rc<Value>)>> {
+        let serialized_key_prefix = self.key_serializer.serialize(key_prefix);
+        self.double_array.top_completions_with_max_scores(
+            &serialized_key_prefix,
+            k,
+            &score,
+            hints.max_scores(),
+        )
+    }
+
+    /**
+     * Makes completion hints.
+     *
+     * All the values are read once to find the max score under each node.
+     *
+     * # Arguments
+     * * `score` - A function returning the score of a value.
+     *
+     * # Returns
+     * Completion hints.
+     *
+     * # Errors
+     * * When it fails to access the storage.
+     */
+    pub fn completion_hints(&self, score: impl Fn(&Value) -> i64) -> Result<CompletionHints> {
+        Ok(CompletionHints::new(self.double_array.max_scores(&score)?))
+    }
+
+    /**
+     * Returns a subtrie.
+     *
+     * # Arguments
+     * * `key_prefix` - A key prefix.
+     *
+     * # Returns
+     * A subtrie. Or None when the trie does not have the given key prefix.
+     *
+     * # Errors
+     * * When it fails to access the storage.
+     */
+    pub fn subtrie(&self, key_prefix: &KeySerializer::Object<'_>) -> Result<Option<Self>> {
+        let serialized_key_prefix = self.key_serializer.serialize(key_prefix);
+        let subdouble_array = self.double_array.subtrie(&serialized_key_prefix)?;
+        let Some(subdouble_array) = subdouble_array else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            phantom: PhantomData,
+            double_array: subdouble_array,
+            key_serializer: self.key_serializer.clone(),
+            max_key_len: self.max_key_len,
+            #[cfg(feature = "metrics")]
+            metrics_sink: self.metrics_sink.clone(),
+        }))
+    }
+
+    /**
+     * Returns the storage.
+     *
+     * # Returns
+     * The storage.
+     */
+    pub fn storage(&self) -> &dyn StorageRead<Value> {
+        self.double_array.storage()
+    }
+
+    /**
+     * Returns the max key length.
+     *
+     * # Returns
+     * The max key length in bytes. Or `None` when the key length is not
+     * limited.
+     */
+    pub const fn max_key_len(&self) -> Option<usize> {
+        self.max_key_len
+    }
+
+    /**
+     * Sets a metrics sink.
+     *
+     * The sink receives the metrics of each `find`, `find_many` and
+     * `contains` call.
+     *
+     * # Arguments
+     * * `metrics_sink` - A metrics sink. Or None to stop recording.
+     */
+    #[cfg(feature = "metrics")]
+    pub fn set_metrics_sink(&mut self, metrics_sink: Option<Rc<dyn MetricsSink>>) {
+        self.metrics_sink = metrics_sink;
+    }
+
+    #[cfg(feature = "metrics")]
+    fn record_metrics(&self, operation: LookupOperation, start: &LookupMetrics) {
+        if let Some(metrics_sink) = &self.metrics_sink {
+            metrics_sink.record(operation, &self.double_array.metrics().since(start));
+        }
+    }
+
+    pub(crate) const fn key_serializer(&self) -> &KeySerializer {
+        &self.key_serializer
+    }
+
+    pub(crate) const fn root_base_check_index(&self) -> usize {
+        self.double_array.root_base_check_index()
+    }
+
+    // Makes a trie from a storage whose root is at the given index, such as the
+    // one of a subtrie.
+    pub(crate) fn new_with_root(
+        storage: Box<dyn StorageRead<Value>>,
+        root_base_check_index: usize,
+        key_serializer: KeySerializer,
+        max_key_len: Option<usize>,
+    ) -> Self {
+        Self {
+            phantom: PhantomData,
+            double_array: DoubleArray::new(storage, root_base_check_index),
+            key_serializer,
+            max_key_len,
+            #[cfg(feature = "metrics")]
+            metrics_sink: None,
+        }
+    }
+
+    fn serialize_key(&self, key: &KeySerializer::Object<'_>) -> Result<Vec<u8>> {
+        let serialized_key = self.key_serializer.serialize(key);
+        if self
+            .max_key_len
+            .is_some_and(|max_key_len| serialized_key.len() > max_key_len)
+        {
+            return Err(TrieError::TooLongKey.into());
+        }
+        Ok(serialized_key)
+    }
+
+    /**
+     * Dumps the trie in TSV.
+     *
+     * Each line consists of a serialized key and a formatted value separated by
+     * a tab. The key is written as UTF-8 text. Backslashes, tabs, carriage
+     * returns and line feeds in the keys and the values are escaped as `\\`,
+     * `\t`, `\r` and `\n`. The other ASCII control characters and the bytes that
+     * are not valid UTF-8 are escaped as `\xHH`.
+     *
+     * The lines are in the order of the serialized keys.
+     *
+     * # Arguments
+     * * `writer`          - A writer.
+     * * `value_formatter` - A value formatter.
+     *
+     * # Errors
+     * * When it fails to access the storage.
+     * * When it fails to write.
+     */
+    pub fn dump_tsv(
+        &self,
+        writer: &mut dyn ByteWriter,
+        value_formatter: &mut dyn FnMut(&Value) -> String,
+    ) -> Result<()> {
+        let mut iterator = self.double_array.iter();
+        while let Some((serialized_key, value_base)) = iterator.next_with_key() {
+            let Some(value) = double_array::value_of_base(self.double_array.storage(), value_base)?
+            else {
+                continue;
+            };
+            let line = format!(
+                "{}\t{}\n",
+                escape_tsv_field(&serialized_key),
+                escape_tsv_field(value_formatter(&value).as_bytes())
+            );
+            writer.write_bytes(line.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Loads a trie from TSV.
+     *
+     * The TSV must be in the format written by `dump_tsv`. Empty lines are
+     * ignored. Since `dump_tsv` writes each key once, a key appearing more
+     * than once is rejected rather than one of its values being chosen.
+     *
+     * # Arguments
+     * * `reader`       - A reader.
+     * * `value_parser` - A value parser.
+     *
+     * # Returns
+     * A trie.
+     *
+     * # Errors
+     * * When it fails to read.
+     * * When the TSV is broken.
+     * * When a key contains `KEY_TERMINATOR`.
+     * * When a key appears more than once.
+     * * When `value_parser` fails.
+     */
+    #[cfg(feature = "std")]
+    pub fn load_tsv(
+        reader: &mut dyn Read,
+        value_parser: &mut dyn FnMut(&str) -> Result<Value>,
+    ) -> Result<Self> {
+        let mut serialized_keys = Vec::new();
+        let mut values = Vec::new();
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+            if line.is_empty() {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('\t') else {
+                return Err(TrieTsvError::MissingSeparator.into());
+            };
+            let serialized_key = unescape_tsv_field(key)?;
+            if serialized_key.contains(&KEY_TERMINATOR) {
+                return Err(TrieBuilderError::KeyContainsTerminator {
+                    index: serialized_keys.len(),
+                }
+                .into());
+            }
+            serialized_keys.push(serialized_key);
+            let value = String::from_utf8(unescape_tsv_field(value)?)?;
+            values.push(value_parser(&value)?);
+        }
+        let mut key_set = HashSet::with_capacity(serialized_keys.len());
+        for (i, serialized_key) in serialized_keys.iter().enumerate() {
+            if !key_set.insert(serialized_key.as_slice()) {
+                return Err(TrieTsvError::DuplicateKey { index: i }.into());
+            }
+        }
+
+        let double_array_contents = serialized_keys
+            .iter()
+            .enumerate()
+            .map(|(i, serialized_key)| (serialized_key.as_slice(), i as i32))
+            .collect::<Vec<_>>();
+        let mut double_array = DoubleArray::<Value>::builder()
+            .elements(double_array_contents)
+            .build()?;
+        for (i, value) in values.into_iter().enumerate() {
+            double_array.memory_storage_mut().add_value_at(i, value)?;
+        }
+
+        Ok(Self {
+            phantom: PhantomData,
+            double_array,
+            key_serializer: KeySerializer::new(true),
+            max_key_len: None,
+            #[cfg(feature = "metrics")]
+            metrics_sink: None,
+        })
+    }
+}
+
+fn escape_tsv_field(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                '\t' => escaped.push_str("\\t"),
+                '\r' => escaped.push_str("\\r"),
+                '\n' => escaped.push_str("\\n"),
+                _ if c.is_ascii_control() => {
+                    let _ = write!(escaped, "\\x{:02X}", c as u8);
+                }
+                _ => escaped.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            let _ = write!(escaped, "\\x{:02X}", byte);
+        }
+    }
+    escaped
+}
+
+#[cfg(feature = "std")]
+fn unescape_tsv_field(field: &str) -> Result<Vec<u8>> {
+    let mut unescaped = Vec::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0u8; 4];
+            unescaped.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => unescaped.push(b'\\'),
+            Some('t') => unescaped.push(b'\t'),
+            Some('r') => unescaped.push(b'\r'),
+            Some('n') => unescaped.push(b'\n'),
+            Some('x') => {
+                let hex = chars.by_ref().take(2).collect::<String>();
+                if hex.len() != 2 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err(TrieTsvError::InvalidEscapeSequence.into());
+                }
+                let byte = u8::from_str_radix(&hex, 16)?;
+                unescaped.push(byte);
+            }
+            _ => return Err(TrieTsvError::InvalidEscapeSequence.into()),
+        }
+    }
+    Ok(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+    use core::cell::Cell;
+    use core::cmp::Reverse;
+    #[cfg(feature = "std")]
+    use std::io::Cursor;
+    #[cfg(feature = "std")]
+    use std::sync::LazyLock;
+
+    #[cfg(feature = "std")]
+    use crate::build_checkpoint::BuildCheckpointError;
+    use crate::bytes_serializer::BytesSerializer;
+    #[cfg(feature = "std")]
+    use crate::format_version::FormatVersion;
+    use crate::memory_storage::MemoryStorage;
+    use crate::serializer::Deserializer;
+    #[cfg(feature = "std")]
+    use crate::shared_storage::SharedStorage;
+    use crate::string_serializer::{StrSerializer, StringDeserializer};
+    #[cfg(feature = "std")]
+    use crate::value_serializer::{ValueDeserializer, ValueSerializer};
 
     use super::*;
 
-    const KUMAMOTO: &str = "熊本";
+    const KUMAMOTO: &str = "熊本";
+
+    const TAMANA: &str = "玉名";
+
+    const TAMARAI: &str = "玉来";
+
+    const TAMA: &str = "玉";
+
+    const UTO: &str = "宇土";
+
+    #[cfg(feature = "std")]
+    #[rustfmt::skip]
+    const SERIALIZED: &[u8] = &[
+        // base check array
+        0x00u8, 0x00u8, 0x00u8, 0x0Bu8,
+        0xFFu8, 0xFFu8, 0x90u8, 0xFFu8,
+        0xFFu8, 0xFFu8, 0x78u8, 0x71u8,
+        0xFFu8, 0xFFu8, 0x9Du8, 0x8Au8,
+        0xFFu8, 0xFFu8, 0x7Eu8, 0x73u8,
+        0xFFu8, 0xFFu8, 0xD9u8, 0x67u8,
+        0x00u8, 0x00u8, 0x06u8, 0x2Cu8,
+        0x00u8, 0x00u8, 0x00u8, 0x00u8,
+        0xFFu8, 0xFFu8, 0xB4u8, 0x89u8,
+        0xFFu8, 0xFFu8, 0xFCu8, 0x54u8,
+        0x00u8, 0x00u8, 0x0Au8, 0x0Du8,
+        0x00u8, 0x00u8, 0x01u8, 0x00u8,
+
+        // value array
+        0x00u8, 0x00u8, 0x00u8, 0x02u8,
+        0x00u8, 0x00u8, 0x00u8, 0x00u8,
+        0x00u8, 0x00u8, 0x00u8, 0x06u8,
+        0xE7u8, 0x86u8, 0x8Au8, 0xE6u8, 0x9Cu8, 0xACu8,
+        0x00u8, 0x00u8, 0x00u8, 0x06u8,
+        0xE7u8, 0x8Eu8, 0x89u8, 0xE5u8, 0x90u8, 0x8Du8,
+    ];
+
+    #[cfg(feature = "std")]
+    #[rustfmt::skip]
+    const GOLDEN_V1: &[u8] = &[
+        // base check array
+        0x00u8, 0x00u8, 0x00u8, 0x17u8,
+        0xFFu8, 0xFFu8, 0x1Cu8, 0xFFu8,
+        0xFFu8, 0xFFu8, 0x54u8, 0xE5u8,
+        0xFFu8, 0xFFu8, 0x7Du8, 0xAEu8,
+        0xFFu8, 0xFFu8, 0x83u8, 0xE7u8,
+        0xFFu8, 0xFFu8, 0x20u8, 0x87u8,
+        0xFFu8, 0xFFu8, 0x6Au8, 0xE5u8,
+        0xFFu8, 0xFFu8, 0x68u8, 0x9Cu8,
+        0x00u8, 0x00u8, 0x08u8, 0x9Fu8,
+        0x00u8, 0x00u8, 0x02u8, 0x00u8,
+        0xFFu8, 0xFFu8, 0x80u8, 0x86u8,
+        0xFFu8, 0xFFu8, 0x25u8, 0x8Au8,
+        0xFFu8, 0xFFu8, 0x70u8, 0xE6u8,
+        0xFFu8, 0xFFu8, 0x61u8, 0x9Cu8,
+        0x00u8, 0x00u8, 0x0Eu8, 0xACu8,
+        0x00u8, 0x00u8, 0x00u8, 0x00u8,
+        0x00u8, 0x00u8, 0x00u8, 0xFFu8,
+        0x00u8, 0x00u8, 0x00u8, 0xFFu8,
+        0xFFu8, 0xFFu8, 0x89u8, 0x8Eu8,
+        0xFFu8, 0xFFu8, 0x2Eu8, 0x89u8,
+        0xFFu8, 0xFFu8, 0x84u8, 0xE5u8,
+        0xFFu8, 0xFFu8, 0x88u8, 0x90u8,
+        0x00u8, 0x00u8, 0x16u8, 0x8Du8,
+        0x00u8, 0x00u8, 0x01u8, 0x00u8,
+
+        // value array
+        0x00u8, 0x00u8, 0x00u8, 0x03u8,
+        0x00u8, 0x00u8, 0x00u8, 0x00u8,
+        0x00u8, 0x00u8, 0x00u8, 0x08u8,
+        0x4Bu8, 0x75u8, 0x6Du8, 0x61u8, 0x6Du8, 0x6Fu8, 0x74u8, 0x6Fu8,
+        0x00u8, 0x00u8, 0x00u8, 0x06u8,
+        0x54u8, 0x61u8, 0x6Du8, 0x61u8, 0x6Eu8, 0x61u8,
+        0x00u8, 0x00u8, 0x00u8, 0x08u8,
+        0x4Bu8, 0x75u8, 0x6Du8, 0x61u8, 0x6Du8, 0x6Fu8, 0x74u8, 0x6Fu8,
+    ];
+
+    #[cfg(feature = "std")]
+    #[rustfmt::skip]
+    const GOLDEN_V2: &[u8] = &[
+        // base check array
+        0x00u8, 0x00u8, 0x00u8, 0x17u8,
+        0xFFu8, 0xFFu8, 0x1Cu8, 0xFFu8,
+        0xFFu8, 0xFFu8, 0x54u8, 0xE5u8,
+        0xFFu8, 0xFFu8, 0x7Du8, 0xAEu8,
+        0xFFu8, 0xFFu8, 0x83u8, 0xE7u8,
+        0xFFu8, 0xFFu8, 0x20u8, 0x87u8,
+        0xFFu8, 0xFFu8, 0x6Au8, 0xE5u8,
+        0xFFu8, 0xFFu8, 0x68u8, 0x9Cu8,
+        0x00u8, 0x00u8, 0x08u8, 0x9Fu8,
+        0x00u8, 0x00u8, 0x02u8, 0x00u8,
+        0xFFu8, 0xFFu8, 0x80u8, 0x86u8,
+        0xFFu8, 0xFFu8, 0x25u8, 0x8Au8,
+        0xFFu8, 0xFFu8, 0x70u8, 0xE6u8,
+        0xFFu8, 0xFFu8, 0x61u8, 0x9Cu8,
+        0x00u8, 0x00u8, 0x0Eu8, 0xACu8,
+        0x00u8, 0x00u8, 0x00u8, 0x00u8,
+        0x00u8, 0x00u8, 0x00u8, 0xFFu8,
+        0x00u8, 0x00u8, 0x00u8, 0xFFu8,
+        0xFFu8, 0xFFu8, 0x89u8, 0x8Eu8,
+        0xFFu8, 0xFFu8, 0x2Eu8, 0x89u8,
+        0xFFu8, 0xFFu8, 0x84u8, 0xE5u8,
+        0xFFu8, 0xFFu8, 0x88u8, 0x90u8,
+        0x00u8, 0x00u8, 0x16u8, 0x8Du8,
+        0x00u8, 0x00u8, 0x01u8, 0x00u8,
+
+        // value array
+        0x00u8, 0x00u8, 0x00u8, 0x03u8,
+        0x80u8, 0x00u8, 0x00u8, 0x00u8,
+        0x00u8, 0x00u8, 0x00u8, 0x00u8,
+        0x00u8, 0x00u8, 0x00u8, 0x01u8,
+        0x00u8, 0x00u8, 0x00u8, 0x00u8,
+
+        // value pool
+        0x00u8, 0x00u8, 0x00u8, 0x02u8,
+        0x00u8, 0x00u8, 0x00u8, 0x08u8,
+        0x4Bu8, 0x75u8, 0x6Du8, 0x61u8, 0x6Du8, 0x6Fu8, 0x74u8, 0x6Fu8,
+        0x00u8, 0x00u8, 0x00u8, 0x06u8,
+        0x54u8, 0x61u8, 0x6Du8, 0x61u8, 0x6Eu8, 0x61u8,
+    ];
+
+    #[cfg(feature = "std")]
+    fn create_input_stream() -> Box<dyn Read> {
+        Box::new(Cursor::new(SERIALIZED))
+    }
+
+    #[test]
+    fn builder() {
+        {
+            let _trie = Trie::<&str, i32>::builder().build().unwrap();
+        }
+
+        {
+            let key_serializer = StrSerializer::new(true);
+            let _trie = Trie::<&str, i32>::builder()
+                .key_serializer(key_serializer)
+                .build()
+                .unwrap();
+        }
+
+        {
+            let _trie = Trie::<&str, i32>::builder()
+                .elements([("Kumamoto", 42), ("Tamana", 24)].to_vec())
+                .build()
+                .unwrap();
+        }
+        {
+            let _trie = Trie::<&str, String>::builder()
+                .elements(
+                    [
+                        (KUMAMOTO, KUMAMOTO.to_string()),
+                        (TAMANA, TAMANA.to_string()),
+                    ]
+                    .to_vec(),
+                )
+                .build()
+                .unwrap();
+        }
+
+        {
+            let content = [
+                ("kumamoto", KUMAMOTO.to_string()),
+                ("tamana", TAMANA.to_string()),
+            ]
+            .to_vec();
+            let _trie = Trie::<&str, String>::builder()
+                .elements(content)
+                .key_serializer(StrSerializer::new(true))
+                .build()
+                .unwrap();
+        }
+        {
+            let trie = {
+                let keys = [String::from("Kumamoto"), String::from("Tamana")];
+                Trie::<&str, i32>::builder()
+                    .elements(vec![(keys[0].as_str(), 42), (keys[1].as_str(), 24)])
+                    .build()
+                    .unwrap()
+            };
+
+            assert_eq!(*trie.find(&"Kumamoto").unwrap().unwrap(), 42);
+            assert_eq!(*trie.find(&"Tamana").unwrap().unwrap(), 24);
+        }
+        {
+            let trie = Trie::<&str, String>::builder()
+                .expanded_records(
+                    [
+                        (("熊本", "くまもと"), KUMAMOTO.to_string()),
+                        (("玉名", "たまな"), TAMANA.to_string()),
+                    ]
+                    .to_vec(),
+                    &|&(surface, reading)| vec![surface, reading],
+                )
+                .build()
+                .unwrap();
+
+            assert_eq!(trie.size().unwrap(), 2);
+            assert_eq!(*trie.find(&"熊本").unwrap().unwrap(), KUMAMOTO);
+            assert_eq!(*trie.find(&"くまもと").unwrap().unwrap(), KUMAMOTO);
+            assert!(Rc::ptr_eq(
+                &trie.find(&"玉名").unwrap().unwrap(),
+                &trie.find(&"たまな").unwrap().unwrap()
+            ));
+        }
+        {
+            let trie = Trie::<&str, u32>::builder()
+                .expanded_records(
+                    [(("Kumamoto", "kumamoto"), 42u32)].to_vec(),
+                    &|&(surface, reading)| vec![surface, reading],
+                )
+                .inline_values()
+                .build()
+                .unwrap();
+
+            assert_eq!(trie.size().unwrap(), 1);
+            assert_eq!(*trie.find(&"Kumamoto").unwrap().unwrap(), 42);
+            assert_eq!(*trie.find(&"kumamoto").unwrap().unwrap(), 42);
+        }
+        {
+            let error = Trie::<&str, i32>::builder()
+                .expanded_records(
+                    [(("Kumamoto", "Tama\0na"), 42)].to_vec(),
+                    &|&(surface, reading)| vec![surface, reading],
+                )
+                .build()
+                .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<TrieBuilderError>().unwrap(),
+                TrieBuilderError::KeyContainsTerminator { index: 1 }
+            ));
+        }
+
+        {
+            let mut added_serialized_keys = Vec::<Vec<u8>>::new();
+            let mut done = false;
+            let _trie = Trie::<&str, i32>::builder()
+                .elements([("Kumamoto", 42), ("Tamana", 24)].to_vec())
+                .key_serializer(StrSerializer::new(true))
+                .build_with_observer_set(&mut BuldingObserverSet::new(
+                    &mut |serialized_keys| {
+                        added_serialized_keys.push(serialized_keys.to_vec());
+                    },
+                    &mut || {
+                        done = true;
+                    },
+                ))
+                .unwrap();
+
+            let key_deserializer = StringDeserializer::new(true);
+            assert_eq!(added_serialized_keys.len(), 2);
+            assert_eq!(
+                key_deserializer
+                    .deserialize(added_serialized_keys[0].as_ref())
+                    .unwrap(),
+                "Kumamoto"
+            );
+            assert_eq!(
+                key_deserializer
+                    .deserialize(added_serialized_keys[1].as_ref())
+                    .unwrap(),
+                "Tamana"
+            );
+            assert!(done);
+        }
+
+        {
+            let mut added_serialized_keys = Vec::<Vec<u8>>::new();
+            let mut done = false;
+            let _trie = Trie::<&str, i32>::builder()
+                .elements([("Kumamoto", 42), ("Tamana", 24)].to_vec())
+                .key_serializer(StrSerializer::new(true))
+                .double_array_density_factor(DEFAULT_DOUBLE_ARRAY_DENSITY_FACTOR)
+                .build_with_observer_set(&mut BuldingObserverSet::new(
+                    &mut |serialized_keys| {
+                        added_serialized_keys.push(serialized_keys.to_vec());
+                    },
+                    &mut || {
+                        done = true;
+                    },
+                ))
+                .unwrap();
+
+            let key_deserializer = StringDeserializer::new(true);
+            assert_eq!(added_serialized_keys.len(), 2);
+            assert_eq!(
+                key_deserializer
+                    .deserialize(added_serialized_keys[0].as_ref())
+                    .unwrap(),
+                "Kumamoto"
+            );
+            assert_eq!(
+                key_deserializer
+                    .deserialize(added_serialized_keys[1].as_ref())
+                    .unwrap(),
+                "Tamana"
+            );
+            assert!(done);
+        }
+
+        {
+            let trie = Trie::<&str, i32>::builder()
+                .elements([("Kumamoto", 42), ("Tamana", 24), ("Uto", 4242)].to_vec())
+                .assume_sorted()
+                .build()
+                .unwrap();
+
+            assert_eq!(*trie.find(&"Kumamoto").unwrap().unwrap(), 42);
+            assert_eq!(*trie.find(&"Tamana").unwrap().unwrap(), 24);
+            assert_eq!(*trie.find(&"Uto").unwrap().unwrap(), 4242);
+        }
+        {
+            let result = Trie::<&str, i32>::builder()
+                .elements([("Kumamoto", 42), ("Uto", 4242), ("Tamana", 24)].to_vec())
+                .assume_sorted()
+                .build();
+
+            let error = result.unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<TrieBuilderError>().unwrap(),
+                TrieBuilderError::UnsortedKeys { index: 2 }
+            ));
+        }
+        {
+            let result = Trie::<&str, i32>::builder()
+                .elements([("Kumamoto", 42), ("Tama\0na", 24)].to_vec())
+                .build();
+
+            let error = result.unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<TrieBuilderError>().unwrap(),
+                TrieBuilderError::KeyContainsTerminator { index: 1 }
+            ));
+        }
+        {
+            let trie = Trie::<&str, i32>::builder()
+                .elements([("Kumamoto", 42), ("Tamana", 24)].to_vec())
+                .max_key_len(8)
+                .build()
+                .unwrap();
+
+            assert_eq!(trie.max_key_len(), Some(8));
+            assert_eq!(*trie.find(&"Kumamoto").unwrap().unwrap(), 42);
+        }
+        {
+            let result = Trie::<&str, i32>::builder()
+                .elements([("Tamana", 24), ("Kumamoto", 42)].to_vec())
+                .max_key_len(7)
+                .build();
+
+            let error = result.unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<TrieBuilderError>().unwrap(),
+                TrieBuilderError::TooLongKey { index: 1 }
+            ));
+        }
+        #[cfg(feature = "std")]
+        {
+            let elements = [
+                ("Kumamoto", 42u32),
+                ("Tamana", 0x7FFFFF),
+                ("Uto", 0x800000),
+                ("Yatsushiro", 0),
+            ];
+            let trie = Trie::<&str, u32>::builder()
+                .elements(elements.to_vec())
+                .inline_values()
+                .build()
+                .unwrap();
+
+            assert_eq!(trie.size().unwrap(), 4);
+            for (key, value) in elements {
+                assert_eq!(*trie.find(&key).unwrap().unwrap(), value);
+            }
+            assert!(trie.storage().value_at(0).unwrap().is_none());
+            assert_eq!(*trie.storage().value_at(2).unwrap().unwrap(), 0x800000);
+            assert_eq!(
+                trie.iter().map(|value| *value).collect::<Vec<_>>(),
+                [42, 0x7FFFFF, 0x800000, 0]
+            );
+
+            let mut serialized = Vec::new();
+            let mut value_serializer = ValueSerializer::new(
+                Box::new(|value: &u32| value.to_be_bytes().to_vec()),
+                size_of::<u32>(),
+            );
+            trie.storage()
+                .serialize(&mut serialized, &mut value_serializer)
+                .unwrap();
+            let mut value_deserializer = ValueDeserializer::new(Box::new(|serialized| {
+                Ok(u32::from_be_bytes(serialized.try_into()?))
+            }));
+            let storage = Box::new(
+                MemoryStorage::new_with_reader(
+                    &mut Cursor::new(serialized),
+                    &mut value_deserializer,
+                )
+                .unwrap(),
+            );
+            let trie = Trie::<&str, u32>::builder_with_storage(storage).build();
+            for (key, value) in elements {
+                assert_eq!(*trie.find(&key).unwrap().unwrap(), value);
+            }
+        }
+        {
+            let trie = Trie::<&str, i32>::builder()
+                .elements([("Kumamoto", 42), ("Tamana", 24)].to_vec())
+                .inline_values()
+                .build()
+                .unwrap();
+
+            assert_eq!(*trie.storage().value_at(0).unwrap().unwrap(), 42);
+            assert_eq!(*trie.find(&"Tamana").unwrap().unwrap(), 24);
+        }
+        {
+            let keys = [
+                vec![0x00u8],
+                vec![0x00u8, 0x00u8],
+                vec![0x00u8, 0xFEu8],
+                vec![0xFDu8, 0xFEu8, 0xFFu8],
+                vec![0xFEu8],
+            ];
+            let trie = Trie::<Vec<u8>, usize>::builder()
+                .elements(keys.iter().cloned().zip(0..).collect())
+                .build()
+                .unwrap();
+
+            for (i, key) in keys.iter().enumerate() {
+                assert_eq!(*trie.find(key).unwrap().unwrap(), i);
+            }
+            assert!(trie.find(&vec![0x00u8, 0x01u8]).unwrap().is_none());
+            assert_eq!(trie.count_prefix(&vec![0x00u8]).unwrap(), 3);
+        }
+        {
+            let trie = Trie::<Vec<u8>, usize, BytesSerializer>::builder()
+                .key_serializer(BytesSerializer::new(false))
+                .elements([(vec![0x01u8], 0)].to_vec())
+                .build()
+                .unwrap();
+
+            assert!(trie.find(&vec![0x01u8, 0x00u8, 0x02u8]).unwrap().is_none());
+        }
+        #[cfg(feature = "std")]
+        {
+            let elements = (0..100)
+                .map(|i| (format!("{}", i * 7919 % 1000), i))
+                .collect::<Vec<_>>();
+            let serialize = |trie: &Trie<String, i32>| {
+                let mut serialized = Vec::new();
+                let mut value_serializer = ValueSerializer::new(
+                    Box::new(|value: &i32| value.to_be_bytes().to_vec()),
+                    size_of::<i32>(),
+                );
+                trie.storage()
+                    .serialize(&mut serialized, &mut value_serializer)
+                    .unwrap();
+                serialized
+            };
+
+            let mut checkpoints = Vec::new();
+            let trie = Trie::<String, i32>::builder()
+                .elements(elements.clone())
+                .build_with_observer_set(
+                    &mut BuldingObserverSet::new(&mut |_| {}, &mut || {}).checkpointing(
+                        30,
+                        &mut |checkpoint| {
+                            let mut serialized = Vec::new();
+                            checkpoint.serialize(&mut serialized)?;
+                            checkpoints.push(serialized);
+                            Ok(())
+                        },
+                    ),
+                )
+                .unwrap();
+            assert_eq!(checkpoints.len(), 3);
+
+            for serialized_checkpoint in checkpoints {
+                let checkpoint =
+                    BuildCheckpoint::new_with_reader(&mut Cursor::new(serialized_checkpoint))
+                        .unwrap();
+                let added_element_count = checkpoint.added_element_count();
+                let mut adding_count = 0;
+                let resumed_trie = Trie::<String, i32>::builder()
+                    .elements(elements.clone())
+                    .resume_from(checkpoint)
+                    .build_with_observer_set(&mut BuldingObserverSet::new(
+                        &mut |_| adding_count += 1,
+                        &mut || {},
+                    ))
+                    .unwrap();
+
+                assert_eq!(adding_count, elements.len() - added_element_count);
+                assert_eq!(serialize(&resumed_trie), serialize(&trie));
+                for (key, value) in &elements {
+                    assert_eq!(*resumed_trie.find(key).unwrap().unwrap(), *value);
+                }
+            }
+
+            let error = Trie::<String, i32>::builder()
+                .elements(elements[..50].to_vec())
+                .resume_from(BuildCheckpoint::new(
+                    100,
+                    DEFAULT_DOUBLE_ARRAY_DENSITY_FACTOR,
+                    30,
+                    Vec::new(),
+                    Vec::new(),
+                    Vec::new(),
+                ))
+                .build()
+                .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<BuildCheckpointError>().unwrap(),
+                BuildCheckpointError::MismatchedCheckpoint
+            ));
+        }
+        {
+            let result = Trie::<&str, i32>::builder()
+                .elements([("Kumamoto", 42), ("Tamana", 24)].to_vec())
+                .build_with_observer_set(
+                    &mut BuldingObserverSet::new(&mut |_| {}, &mut || {})
+                        .checkpointing(1, &mut |_| Err(anyhow::anyhow!("disk full"))),
+                );
+            assert!(result.is_err());
+        }
+        {
+            let mut diagnostics = None;
+            let _trie = Trie::<Vec<u8>, usize>::builder()
+                .elements(
+                    [
+                        (vec![0x00u8], 0),
+                        (vec![0x00u8, 0x00u8], 1),
+                        (vec![0x00u8, 0xFEu8], 2),
+                        (vec![0xFDu8, 0xFEu8, 0xFFu8], 3),
+                    ]
+                    .to_vec(),
+                )
+                .build_with_observer_set(
+                    &mut BuldingObserverSet::new(&mut |_| {}, &mut || {})
+                        .diagnosing(&mut |d| diagnostics = Some(*d)),
+                )
+                .unwrap();
+
+            assert_eq!(diagnostics, Some(KeyDiagnostics::new(4, 7, 11, 5)));
+        }
+        #[cfg(feature = "std")]
+        {
+            let elements = (0..100)
+                .map(|i| (format!("{:03}", (i * 37) % 60), i))
+                .collect::<Vec<_>>();
+            let trie = Trie::<String, i32>::builder()
+                .elements(elements.clone())
+                .build()
+                .unwrap();
+
+            for chunk_size in [1, 16, 1000] {
+                let externally_sorted_trie = Trie::<String, i32>::builder()
+                    .elements(elements.clone())
+                    .external_sort(chunk_size)
+                    .build()
+                    .unwrap();
+
+                assert_eq!(
+                    externally_sorted_trie
+                        .iter()
+                        .map(|value| *value)
+                        .collect::<Vec<_>>(),
+                    trie.iter().map(|value| *value).collect::<Vec<_>>()
+                );
+                for (key, _) in &elements {
+                    assert_eq!(
+                        externally_sorted_trie.find(key).unwrap(),
+                        trie.find(key).unwrap()
+                    );
+                }
+            }
+
+            let error = Trie::<&str, i32>::builder()
+                .elements([("Kumamoto", 42), ("Tamana", 24)].to_vec())
+                .max_key_len(6)
+                .external_sort(4)
+                .build()
+                .unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<TrieBuilderError>().unwrap(),
+                TrieBuilderError::TooLongKey { index: 0 }
+            ));
+        }
+        {
+            let elements = (0..100)
+                .map(|i| (format!("{:03}", i), i))
+                .collect::<Vec<_>>();
+
+            for cancelled_after in [0, 3] {
+                let adding_count = Cell::new(0);
+                let result = Trie::<String, i32>::builder()
+                    .elements(elements.clone())
+                    .build_with_observer_set(
+                        &mut BuldingObserverSet::new(
+                            &mut |_| adding_count.set(adding_count.get() + 1),
+                            &mut || {},
+                        )
+                        .cancelling(&|| adding_count.get() >= cancelled_after),
+                    );
+
+                let error = result.unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<TrieBuilderError>().unwrap(),
+                    TrieBuilderError::Cancelled
+                ));
+                assert_eq!(adding_count.get(), cancelled_after);
+            }
+
+            let trie = Trie::<String, i32>::builder()
+                .elements(elements.clone())
+                .build_with_observer_set(
+                    &mut BuldingObserverSet::new(&mut |_| {}, &mut || {}).cancelling(&|| false),
+                )
+                .unwrap();
+            assert_eq!(trie.size().unwrap(), elements.len());
+        }
+    }
+
+    #[test]
+    fn builder_with_storage() {
+        #[cfg(feature = "std")]
+        {
+            let mut reader = create_input_stream();
+            let mut value_deserializer = ValueDeserializer::new(Box::new(|serialized| {
+                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                    LazyLock::new(|| StringDeserializer::new(false));
+                STRING_DESERIALIZER.deserialize(serialized)
+            }));
+            let storage = Box::new(
+                MemoryStorage::new_with_reader(&mut reader, &mut value_deserializer).unwrap(),
+            );
+            let _trie = Trie::<&str, String>::builder_with_storage(storage).build();
+        }
+
+        #[cfg(feature = "std")]
+        {
+            let mut reader = create_input_stream();
+            let mut value_deserializer = ValueDeserializer::new(Box::new(|serialized| {
+                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                    LazyLock::new(|| StringDeserializer::new(false));
+                STRING_DESERIALIZER.deserialize(serialized)
+            }));
+            let storage = Box::new(
+                MemoryStorage::new_with_reader(&mut reader, &mut value_deserializer).unwrap(),
+            );
+            let _trie = Trie::<&str, String>::builder_with_storage(storage)
+                .key_serializer(StrSerializer::new(true))
+                .build();
+        }
+        #[cfg(feature = "std")]
+        {
+            let mut reader = create_input_stream();
+            let mut value_deserializer = ValueDeserializer::new(Box::new(|serialized| {
+                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                    LazyLock::new(|| StringDeserializer::new(false));
+                STRING_DESERIALIZER.deserialize(serialized)
+            }));
+            let storage = Box::new(
+                MemoryStorage::new_with_reader(&mut reader, &mut value_deserializer).unwrap(),
+            );
+            let trie = Trie::<&str, String>::builder_with_storage(storage)
+                .max_key_len(6)
+                .build();
+
+            assert_eq!(trie.max_key_len(), Some(6));
+        }
+    }
+
+    #[test]
+    fn is_empy() {
+        {
+            let trie = Trie::<&str, String>::builder().build().unwrap();
+
+            assert!(trie.is_empty().unwrap());
+        }
+        {
+            let trie = Trie::<&str, String>::builder()
+                .elements([(KUMAMOTO, KUMAMOTO.to_string())].to_vec())
+                .build()
+                .unwrap();
+
+            assert!(!trie.is_empty().unwrap());
+        }
+        {
+            let trie = Trie::<&str, String>::builder()
+                .elements(
+                    [
+                        (KUMAMOTO, KUMAMOTO.to_string()),
+                        (TAMANA, TAMANA.to_string()),
+                    ]
+                    .to_vec(),
+                )
+                .build()
+                .unwrap();
+
+            assert!(!trie.is_empty().unwrap());
+        }
+    }
+
+    #[test]
+    fn size() {
+        {
+            let trie = Trie::<&str, String>::builder().build().unwrap();
+
+            assert_eq!(trie.size().unwrap(), 0);
+        }
+        {
+            let trie = Trie::<&str, String>::builder()
+                .elements([(KUMAMOTO, KUMAMOTO.to_string())].to_vec())
+                .build()
+                .unwrap();
+
+            assert_eq!(trie.size().unwrap(), 1);
+        }
+        {
+            let trie = Trie::<&str, String>::builder()
+                .elements(
+                    [
+                        (KUMAMOTO, KUMAMOTO.to_string()),
+                        (TAMANA, TAMANA.to_string()),
+                    ]
+                    .to_vec(),
+                )
+                .build()
+                .unwrap();
+
+            assert_eq!(trie.size().unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn contains() {
+        {
+            let trie = Trie::<&str, String>::builder().build().unwrap();
+
+            assert!(!trie.contains(&KUMAMOTO).unwrap());
+        }
+        {
+            let trie = Trie::<&str, String>::builder()
+                .elements(
+                    [
+                        (KUMAMOTO, KUMAMOTO.to_string()),
+                        (TAMANA, TAMANA.to_string()),
+                    ]
+                    .to_vec(),
+                )
+                .build()
+                .unwrap();
+
+            assert!(trie.contains(&KUMAMOTO).unwrap());
+            assert!(trie.contains(&TAMANA).unwrap());
+            assert!(!trie.contains(&UTO).unwrap());
+        }
+    }
+
+    #[test]
+    fn find() {
+        {
+            let trie = Trie::<&str, String>::builder().build().unwrap();
 
-    const TAMANA: &str = "玉名";
+            let found = trie.find(&KUMAMOTO).unwrap();
+            assert!(found.is_none());
+        }
+        {
+            let trie = Trie::<&str, String>::builder()
+                .elements(
+                    [
+                        (KUMAMOTO, KUMAMOTO.to_string()),
+                        (TAMANA, TAMANA.to_string()),
+                    ]
+                    .to_vec(),
+                )
+                .build()
+                .unwrap();
 
-    const TAMARAI: &str = "玉来";
+            {
+                let found = trie.find(&KUMAMOTO).unwrap().unwrap();
+                assert_eq!(*found, KUMAMOTO.to_string());
+            }
+            {
+                let found = trie.find(&TAMANA).unwrap().unwrap();
+                assert_eq!(*found, TAMANA.to_string());
+            }
+            {
+                let found = trie.find(&UTO).unwrap();
+                assert!(found.is_none());
+            }
+        }
+        {
+            let trie = Trie::<&str, i32>::builder()
+                .elements([("Kumamoto", 42), ("Tamana", 24)].to_vec())
+                .max_key_len(8)
+                .build()
+                .unwrap();
 
-    const TAMA: &str = "玉";
+            assert_eq!(*trie.find(&"Tamana").unwrap().unwrap(), 24);
+            let error = trie.find(&"Kumamotoshi").unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<TrieError>().unwrap(),
+                TrieError::TooLongKey
+            ));
+        }
+    }
 
-    const UTO: &str = "宇土";
+    #[test]
+    fn find_ref() {
+        {
+            let trie = Trie::<&str, String>::builder().build().unwrap();
 
-    #[rustfmt::skip]
-    const SERIALIZED: &[u8] = &[
-        // base check array
-        0x00u8, 0x00u8, 0x00u8, 0x0Bu8,
-        0xFFu8, 0xFFu8, 0x90u8, 0xFFu8,
-        0xFFu8, 0xFFu8, 0x78u8, 0x71u8,
-        0xFFu8, 0xFFu8, 0x9Du8, 0x8Au8,
-        0xFFu8, 0xFFu8, 0x7Eu8, 0x73u8,
-        0xFFu8, 0xFFu8, 0xD9u8, 0x67u8,
-        0x00u8, 0x00u8, 0x06u8, 0x2Cu8,
-        0x00u8, 0x00u8, 0x00u8, 0x00u8,
-        0xFFu8, 0xFFu8, 0xB4u8, 0x89u8,
-        0xFFu8, 0xFFu8, 0xFCu8, 0x54u8,
-        0x00u8, 0x00u8, 0x0Au8, 0x0Du8,
-        0x00u8, 0x00u8, 0x01u8, 0x00u8,
+            assert!(trie.find_ref(&KUMAMOTO).unwrap().is_none());
+        }
+        {
+            let trie = Trie::<&str, String>::builder()
+                .elements(
+                    [
+                        (KUMAMOTO, KUMAMOTO.to_string()),
+                        (TAMANA, TAMANA.to_string()),
+                    ]
+                    .to_vec(),
+                )
+                .build()
+                .unwrap();
 
-        // value array
-        0x00u8, 0x00u8, 0x00u8, 0x02u8,
-        0x00u8, 0x00u8, 0x00u8, 0x00u8,
-        0x00u8, 0x00u8, 0x00u8, 0x06u8,
-        0xE7u8, 0x86u8, 0x8Au8, 0xE6u8, 0x9Cu8, 0xACu8,
-        0x00u8, 0x00u8, 0x00u8, 0x06u8,
-        0xE7u8, 0x8Eu8, 0x89u8, 0xE5u8, 0x90u8, 0x8Du8,
-    ];
+            {
+                let found = trie.find_ref(&KUMAMOTO).unwrap().unwrap();
+                assert!(matches!(found, ValueRef::Borrowed(_)));
+                assert_eq!(*found, KUMAMOTO.to_string());
+            }
+            {
+                let found = trie.find_ref(&TAMANA).unwrap().unwrap();
+                assert_eq!(*found, TAMANA.to_string());
+            }
+            assert!(trie.find_ref(&UTO).unwrap().is_none());
+        }
+        {
+            let trie = Trie::<&str, u32>::builder()
+                .elements([("Kumamoto", 42), ("Tamana", 24)].to_vec())
+                .inline_values()
+                .max_key_len(8)
+                .build()
+                .unwrap();
 
-    fn create_input_stream() -> Box<dyn std::io::Read> {
-        Box::new(Cursor::new(SERIALIZED))
+            let found = trie.find_ref(&"Tamana").unwrap().unwrap();
+            assert!(matches!(found, ValueRef::Shared(_)));
+            assert_eq!(*found, 24);
+            let error = trie.find_ref(&"Kumamotoshi").unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<TrieError>().unwrap(),
+                TrieError::TooLongKey
+            ));
+        }
     }
 
     #[test]
-    fn builder() {
+    fn max_key_len() {
         {
-            let _trie = Trie::<&str, i32>::builder().build().unwrap();
+            let trie = Trie::<&str, i32>::builder().build().unwrap();
+
+            assert!(trie.max_key_len().is_none());
+        }
+        {
+            let trie = Trie::<&str, i32>::builder().max_key_len(8).build().unwrap();
+
+            assert_eq!(trie.max_key_len(), Some(8));
+            assert!(trie.contains(&"Kumamotoshi").is_err());
+            assert!(trie.find_many(&["Uto", "Kumamotoshi"]).is_err());
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn set_metrics_sink() {
+        #[derive(Debug, Default)]
+        struct ConcreteMetricsSink {
+            records: RefCell<Vec<(LookupOperation, LookupMetrics)>>,
+        }
+
+        impl MetricsSink for ConcreteMetricsSink {
+            fn record(&self, operation: LookupOperation, metrics: &LookupMetrics) {
+                self.records.borrow_mut().push((operation, *metrics));
+            }
         }
 
+        let mut trie = Trie::<&str, String>::builder()
+            .elements(
+                [
+                    (KUMAMOTO, KUMAMOTO.to_string()),
+                    (TAMANA, TAMANA.to_string()),
+                ]
+                .to_vec(),
+            )
+            .build()
+            .unwrap();
+        let metrics_sink = Rc::new(ConcreteMetricsSink::default());
+        trie.set_metrics_sink(Some(metrics_sink.clone()));
+
+        let _found = trie.find(&KUMAMOTO).unwrap();
+        let _contained = trie.contains(&UTO).unwrap();
+        let _found = trie.find_many(&[KUMAMOTO, TAMANA]).unwrap();
         {
-            let key_serializer = StrSerializer::new(true);
-            let _trie = Trie::<&str, i32>::builder()
-                .key_serializer(key_serializer)
-                .build()
-                .unwrap();
+            let records = metrics_sink.records.borrow();
+            assert_eq!(records.len(), 3);
+
+            // 6 bytes of the key and the terminator, and the reads of their
+            // bases and checks, the value base and the value.
+            assert_eq!(records[0].0, LookupOperation::Find);
+            assert_eq!(records[0].1, LookupMetrics::new(7, 16, 0));
+
+            assert_eq!(records[1].0, LookupOperation::Contains);
+            assert_eq!(records[1].1.traversal_steps(), 0);
+
+            // The traversal of the second key resumes after the shared byte.
+            assert_eq!(records[2].0, LookupOperation::FindMany);
+            assert_eq!(records[2].1.traversal_steps(), 13);
         }
 
+        trie.set_metrics_sink(None);
+        let _found = trie.find(&KUMAMOTO).unwrap();
+        assert_eq!(metrics_sink.records.borrow().len(), 3);
+    }
+
+    #[test]
+    fn find_many() {
+        {
+            let trie = Trie::<&str, String>::builder().build().unwrap();
+
+            let found = trie.find_many(&[KUMAMOTO]).unwrap();
+            assert_eq!(found, [None]);
+        }
         {
-            let _trie = Trie::<&str, i32>::builder()
-                .elements([("Kumamoto", 42), ("Tamana", 24)].to_vec())
+            let trie = Trie::<&str, String>::builder()
+                .elements(
+                    [
+                        (KUMAMOTO, KUMAMOTO.to_string()),
+                        (TAMANA, TAMANA.to_string()),
+                    ]
+                    .to_vec(),
+                )
                 .build()
                 .unwrap();
+
+            let found = trie.find_many(&[TAMANA, UTO, KUMAMOTO, TAMANA]).unwrap();
+            assert_eq!(
+                found,
+                [
+                    Some(Rc::new(TAMANA.to_string())),
+                    None,
+                    Some(Rc::new(KUMAMOTO.to_string())),
+                    Some(Rc::new(TAMANA.to_string())),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn cursor() {
+        let trie = Trie::<&str, i32>::builder()
+            .elements(vec![("Kumamoto", 42), ("Tamana", 24)])
+            .build()
+            .unwrap();
+
+        let mut cursor = trie.cursor();
+        let matched = cursor
+            .longest_match(&mut "Tamanashi".bytes())
+            .unwrap()
+            .unwrap();
+        assert_eq!(matched.0, 6);
+        assert_eq!(*matched.1, 24);
+    }
+
+    #[test]
+    fn iter() {
+        {
+            let trie = Trie::<&str, String>::builder().build().unwrap();
+
+            let _iterator = trie.iter();
         }
         {
-            let _trie = Trie::<&str, String>::builder()
+            let trie = Trie::<&str, String>::builder()
                 .elements(
                     [
                         (KUMAMOTO, KUMAMOTO.to_string()),
@@ -467,251 +2746,459 @@ mod tests {
                 )
                 .build()
                 .unwrap();
-        }
 
+            let _iterator = trie.iter();
+        }
         {
-            let content = [
-                ("kumamoto", KUMAMOTO.to_string()),
-                ("tamana", TAMANA.to_string()),
-            ]
-            .to_vec();
-            let _trie = Trie::<&str, String>::builder()
-                .elements(content)
-                .key_serializer(StrSerializer::new(true))
+            let trie = Trie::<&str, i32>::builder()
+                .elements([("Tamana", 24), ("Kumamoto", 42), ("Tama", 4242)].to_vec())
                 .build()
                 .unwrap();
+
+            let values = trie.iter().map(|value| *value).collect::<Vec<_>>();
+            assert_eq!(values, [42, 4242, 24]);
         }
+    }
 
+    #[test]
+    fn iter_rev() {
         {
-            let mut added_serialized_keys = Vec::<Vec<u8>>::new();
-            let mut done = false;
-            let _trie = Trie::<&str, i32>::builder()
-                .elements([("Kumamoto", 42), ("Tamana", 24)].to_vec())
-                .key_serializer(StrSerializer::new(true))
-                .build_with_observer_set(&mut BuldingObserverSet::new(
-                    &mut |serialized_keys| {
-                        added_serialized_keys.push(serialized_keys.to_vec());
-                    },
-                    &mut || {
-                        done = true;
-                    },
-                ))
+            let trie = Trie::<&str, i32>::builder().build().unwrap();
+
+            assert!(trie.iter_rev().next().is_none());
+        }
+        {
+            let trie = Trie::<&str, i32>::builder()
+                .elements([("Tamana", 24), ("Kumamoto", 42), ("Tama", 4242)].to_vec())
+                .build()
                 .unwrap();
 
-            let key_deserializer = StringDeserializer::new(true);
-            assert_eq!(added_serialized_keys.len(), 2);
-            assert_eq!(
-                key_deserializer
-                    .deserialize(added_serialized_keys[0].as_ref())
-                    .unwrap(),
-                "Kumamoto"
-            );
-            assert_eq!(
-                key_deserializer
-                    .deserialize(added_serialized_keys[1].as_ref())
-                    .unwrap(),
-                "Tamana"
-            );
-            assert!(done);
+            let values = trie.iter_rev().map(|value| *value).collect::<Vec<_>>();
+            assert_eq!(values, [24, 4242, 42]);
         }
+    }
+
+    #[test]
+    fn range() {
+        let trie = Trie::<&str, i32>::builder()
+            .elements(
+                [
+                    ("Kumamoto", 42),
+                    ("Tamana", 24),
+                    ("Tama", 4242),
+                    ("Tamarai", 2424),
+                    ("Uto", 4224),
+                ]
+                .to_vec(),
+            )
+            .build()
+            .unwrap();
+        let keys_of = |iterator: TrieRangeIterator<'_, i32>| {
+            iterator
+                .map(|(key, _)| String::from_utf8(key).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            keys_of(trie.range(..)),
+            ["Kumamoto", "Tama", "Tamana", "Tamarai", "Uto"]
+        );
+        assert_eq!(
+            keys_of(trie.range("Tama".."Uto")),
+            ["Tama", "Tamana", "Tamarai"]
+        );
+        assert_eq!(
+            keys_of(trie.range("Tama"..="Uto")),
+            ["Tama", "Tamana", "Tamarai", "Uto"]
+        );
+        assert_eq!(keys_of(trie.range("Tamb"..)), ["Uto"]);
+        assert_eq!(keys_of(trie.range(.."Tama")), ["Kumamoto"]);
+        assert_eq!(keys_of(trie.range(..="Tama")), ["Kumamoto", "Tama"]);
+        assert_eq!(keys_of(trie.range("Ta".."Tamara")), ["Tama", "Tamana"]);
+        assert!(keys_of(trie.range("Z"..)).is_empty());
+        assert!(keys_of(trie.range("Tamana".."Tamana")).is_empty());
+
+        let (key, value) = trie.range("Tamarai"..).next().unwrap();
+        assert_eq!(key, b"Tamarai");
+        assert_eq!(*value, 2424);
+    }
+
+    #[test]
+    fn iter_bounded() {
+        let trie = Trie::<&str, i32>::builder()
+            .elements(
+                [
+                    ("Kumamoto", 42),
+                    ("Tamana", 24),
+                    ("Tama", 4242),
+                    ("Tamarai", 2424),
+                    ("Uto", 4224),
+                ]
+                .to_vec(),
+            )
+            .build()
+            .unwrap();
+        let keys_of = |iterator: TrieRangeIterator<'_, i32>| {
+            iterator
+                .map(|(key, _)| String::from_utf8(key).unwrap())
+                .collect::<Vec<_>>()
+        };
 
+        assert_eq!(
+            keys_of(trie.iter_bounded(usize::MAX, &|_| true)),
+            ["Kumamoto", "Tama", "Tamana", "Tamarai", "Uto"]
+        );
+        assert_eq!(keys_of(trie.iter_bounded(4, &|_| true)), ["Tama", "Uto"]);
+        assert!(keys_of(trie.iter_bounded(0, &|_| true)).is_empty());
+        assert_eq!(
+            keys_of(trie.iter_bounded(usize::MAX, &|prefix| prefix[0] == b'T')),
+            ["Tama", "Tamana", "Tamarai"]
+        );
         {
-            let mut added_serialized_keys = Vec::<Vec<u8>>::new();
-            let mut done = false;
-            let _trie = Trie::<&str, i32>::builder()
-                .elements([("Kumamoto", 42), ("Tamana", 24)].to_vec())
-                .key_serializer(StrSerializer::new(true))
-                .double_array_density_factor(DEFAULT_DOUBLE_ARRAY_DENSITY_FACTOR)
-                .build_with_observer_set(&mut BuldingObserverSet::new(
-                    &mut |serialized_keys| {
-                        added_serialized_keys.push(serialized_keys.to_vec());
-                    },
-                    &mut || {
-                        done = true;
-                    },
-                ))
-                .unwrap();
+            let subtrie = trie.subtrie(&"Tama").unwrap().unwrap();
+            let filter = |prefix: &[u8]| prefix[0] != b'r';
 
-            let key_deserializer = StringDeserializer::new(true);
-            assert_eq!(added_serialized_keys.len(), 2);
+            assert_eq!(keys_of(subtrie.iter_bounded(2, &|_| true)), ["", "na"]);
+            assert_eq!(keys_of(subtrie.iter_bounded(3, &filter)), ["", "na"]);
             assert_eq!(
-                key_deserializer
-                    .deserialize(added_serialized_keys[0].as_ref())
-                    .unwrap(),
-                "Kumamoto"
+                keys_of(subtrie.iter_bounded(usize::MAX, &filter)),
+                ["", "na"]
             );
             assert_eq!(
-                key_deserializer
-                    .deserialize(added_serialized_keys[1].as_ref())
-                    .unwrap(),
-                "Tamana"
+                keys_of(subtrie.iter_bounded(usize::MAX, &|_| true)),
+                ["", "na", "rai"]
             );
-            assert!(done);
         }
     }
 
     #[test]
-    fn builder_with_storage() {
+    fn iter_suffixes() {
+        let trie = Trie::<&str, i32>::builder()
+            .elements(vec![("Kumamoto", 42), ("Tamana", 24), ("Tamarai", 2424)])
+            .build()
+            .unwrap();
         {
-            let mut reader = create_input_stream();
-            let mut value_deserializer = ValueDeserializer::new(Box::new(|serialized| {
-                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
-                    LazyLock::new(|| StringDeserializer::new(false));
-                STRING_DESERIALIZER.deserialize(serialized)
-            }));
-            let storage = Box::new(
-                MemoryStorage::new_with_reader(&mut reader, &mut value_deserializer).unwrap(),
-            );
-            let _trie = Trie::<&str, String>::builder_with_storage(storage).build();
-        }
+            let suffixes = trie
+                .iter_suffixes(StringDeserializer::new(true))
+                .map(|(suffix, value)| (suffix, *value))
+                .collect::<Vec<_>>();
 
-        {
-            let mut reader = create_input_stream();
-            let mut value_deserializer = ValueDeserializer::new(Box::new(|serialized| {
-                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
-                    LazyLock::new(|| StringDeserializer::new(false));
-                STRING_DESERIALIZER.deserialize(serialized)
-            }));
-            let storage = Box::new(
-                MemoryStorage::new_with_reader(&mut reader, &mut value_deserializer).unwrap(),
+            assert_eq!(
+                suffixes,
+                [
+                    (String::from("Kumamoto"), 42),
+                    (String::from("Tamana"), 24),
+                    (String::from("Tamarai"), 2424),
+                ]
+            );
+        }
+        {
+            let subtrie = trie.subtrie(&"Tama").unwrap().unwrap();
+            let suffixes = subtrie
+                .iter_suffixes(StringDeserializer::new(true))
+                .map(|(suffix, value)| (suffix, *value))
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                suffixes,
+                [(String::from("na"), 24), (String::from("rai"), 2424)]
             );
-            let _trie = Trie::<&str, String>::builder_with_storage(storage)
-                .key_serializer(StrSerializer::new(true))
-                .build();
         }
     }
 
     #[test]
-    fn is_empy() {
+    fn top_k_by() {
         {
-            let trie = Trie::<&str, String>::builder().build().unwrap();
+            let trie = Trie::<&str, i32>::builder()
+                .elements(vec![
+                    ("Kumamoto", 42),
+                    ("Tamana", 24),
+                    ("Tamarai", 2424),
+                    ("Uto", 4242),
+                ])
+                .build()
+                .unwrap();
 
-            assert!(trie.is_empty().unwrap());
+            let top_k = trie
+                .top_k_by(3, |&value| value)
+                .unwrap()
+                .into_iter()
+                .map(|(key, value)| (key, *value))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                top_k,
+                [
+                    (b"Uto".to_vec(), 4242),
+                    (b"Tamarai".to_vec(), 2424),
+                    (b"Kumamoto".to_vec(), 42)
+                ]
+            );
+
+            let bottom_k = trie
+                .top_k_by(10, |&value| Reverse(value))
+                .unwrap()
+                .into_iter()
+                .map(|(key, value)| (key, *value))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                bottom_k,
+                [
+                    (b"Tamana".to_vec(), 24),
+                    (b"Kumamoto".to_vec(), 42),
+                    (b"Tamarai".to_vec(), 2424),
+                    (b"Uto".to_vec(), 4242)
+                ]
+            );
+
+            assert!(trie.top_k_by(0, |&value| value).unwrap().is_empty());
+
+            let subtrie = trie.subtrie(&"Tama").unwrap().unwrap();
+            let top_k = subtrie
+                .top_k_by(3, |&value| value)
+                .unwrap()
+                .into_iter()
+                .map(|(key, value)| (key, *value))
+                .collect::<Vec<_>>();
+            assert_eq!(top_k, [(b"rai".to_vec(), 2424), (b"na".to_vec(), 24)]);
         }
         {
-            let trie = Trie::<&str, String>::builder()
-                .elements([(KUMAMOTO, KUMAMOTO.to_string())].to_vec())
+            let trie = Trie::<&str, u32>::builder()
+                .elements(vec![("Kumamoto", 42), ("Tamana", 24), ("Tamarai", 2424)])
+                .inline_values()
                 .build()
                 .unwrap();
 
-            assert!(!trie.is_empty().unwrap());
+            let top_k = trie
+                .top_k_by(2, |&value| value)
+                .unwrap()
+                .into_iter()
+                .map(|(key, value)| (key, *value))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                top_k,
+                [(b"Tamarai".to_vec(), 2424), (b"Kumamoto".to_vec(), 42)]
+            );
         }
         {
-            let trie = Trie::<&str, String>::builder()
+            let trie = Trie::<&str, i32>::builder().build().unwrap();
+
+            assert!(trie.top_k_by(3, |&value| value).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn count_prefix() {
+        let trie = Trie::<&str, String>::builder()
+            .elements(
+                [
+                    (KUMAMOTO, KUMAMOTO.to_string()),
+                    (TAMANA, TAMANA.to_string()),
+                    (TAMARAI, TAMARAI.to_string()),
+                ]
+                .to_vec(),
+            )
+            .build()
+            .unwrap();
+
+        assert_eq!(trie.count_prefix(&"").unwrap(), 3);
+        assert_eq!(trie.count_prefix(&TAMA).unwrap(), 2);
+        assert_eq!(trie.count_prefix(&TAMANA).unwrap(), 1);
+        assert_eq!(trie.count_prefix(&"玉名市").unwrap(), 0);
+        assert_eq!(trie.count_prefix(&"八代").unwrap(), 0);
+    }
+
+    #[test]
+    fn content_eq() {
+        let trie = Trie::<&str, String>::builder()
+            .elements(
+                [
+                    (KUMAMOTO, KUMAMOTO.to_string()),
+                    (TAMANA, TAMANA.to_string()),
+                ]
+                .to_vec(),
+            )
+            .build()
+            .unwrap();
+        {
+            let other = Trie::<&str, String>::builder()
                 .elements(
                     [
-                        (KUMAMOTO, KUMAMOTO.to_string()),
                         (TAMANA, TAMANA.to_string()),
+                        (KUMAMOTO, KUMAMOTO.to_string()),
                     ]
                     .to_vec(),
                 )
+                .double_array_density_factor(1)
                 .build()
                 .unwrap();
 
-            assert!(!trie.is_empty().unwrap());
-        }
-    }
-
-    #[test]
-    fn size() {
-        {
-            let trie = Trie::<&str, String>::builder().build().unwrap();
-
-            assert_eq!(trie.size().unwrap(), 0);
+            assert!(trie.content_eq(&other).unwrap());
+            assert!(other.content_eq(&trie).unwrap());
         }
         {
-            let trie = Trie::<&str, String>::builder()
-                .elements([(KUMAMOTO, KUMAMOTO.to_string())].to_vec())
+            let other = Trie::<&str, String>::builder()
+                .elements(
+                    [
+                        (KUMAMOTO, KUMAMOTO.to_string()),
+                        (TAMANA, KUMAMOTO.to_string()),
+                    ]
+                    .to_vec(),
+                )
                 .build()
                 .unwrap();
 
-            assert_eq!(trie.size().unwrap(), 1);
+            assert!(!trie.content_eq(&other).unwrap());
         }
         {
-            let trie = Trie::<&str, String>::builder()
+            let other = Trie::<&str, String>::builder()
                 .elements(
                     [
                         (KUMAMOTO, KUMAMOTO.to_string()),
                         (TAMANA, TAMANA.to_string()),
+                        (TAMARAI, TAMARAI.to_string()),
                     ]
                     .to_vec(),
                 )
                 .build()
                 .unwrap();
 
-            assert_eq!(trie.size().unwrap(), 2);
+            assert!(!trie.content_eq(&other).unwrap());
+            assert!(!other.content_eq(&trie).unwrap());
+        }
+        {
+            let trie = Trie::<&str, u32>::builder()
+                .elements([("Kumamoto", 42u32), ("Tamana", 0x800000)].to_vec())
+                .inline_values()
+                .build()
+                .unwrap();
+            let other = Trie::<&str, u32>::builder()
+                .elements([("Kumamoto", 42u32), ("Tamana", 0x800000)].to_vec())
+                .build()
+                .unwrap();
+
+            assert!(trie.content_eq(&other).unwrap());
         }
     }
 
     #[test]
-    fn contains() {
+    fn is_subset_of() {
+        let trie = Trie::<&str, String>::builder()
+            .elements(
+                [
+                    (KUMAMOTO, KUMAMOTO.to_string()),
+                    (TAMANA, TAMANA.to_string()),
+                ]
+                .to_vec(),
+            )
+            .build()
+            .unwrap();
+        let other = Trie::<&str, String>::builder()
+            .elements(
+                [
+                    (KUMAMOTO, KUMAMOTO.to_string()),
+                    (TAMANA, TAMANA.to_string()),
+                    (TAMARAI, TAMARAI.to_string()),
+                ]
+                .to_vec(),
+            )
+            .build()
+            .unwrap();
+
+        assert!(trie.is_subset_of(&trie).unwrap());
+        assert!(trie.is_subset_of(&other).unwrap());
+        assert!(!other.is_subset_of(&trie).unwrap());
         {
-            let trie = Trie::<&str, String>::builder().build().unwrap();
-
-            assert!(!trie.contains(&KUMAMOTO).unwrap());
+            let empty = Trie::<&str, String>::builder().build().unwrap();
+            assert!(empty.is_subset_of(&trie).unwrap());
+            assert!(!trie.is_subset_of(&empty).unwrap());
         }
         {
-            let trie = Trie::<&str, String>::builder()
+            let subtrie = trie.subtrie(&TAMA).unwrap().unwrap();
+            let other_subtrie = other.subtrie(&TAMA).unwrap().unwrap();
+            assert!(subtrie.is_subset_of(&other_subtrie).unwrap());
+            assert!(!other_subtrie.is_subset_of(&subtrie).unwrap());
+        }
+    }
+
+    #[test]
+    fn remove_subtree() {
+        {
+            let mut trie = Trie::<&str, String>::builder()
                 .elements(
                     [
                         (KUMAMOTO, KUMAMOTO.to_string()),
                         (TAMANA, TAMANA.to_string()),
+                        (TAMARAI, TAMARAI.to_string()),
                     ]
                     .to_vec(),
                 )
                 .build()
                 .unwrap();
 
-            assert!(trie.contains(&KUMAMOTO).unwrap());
-            assert!(trie.contains(&TAMANA).unwrap());
-            assert!(!trie.contains(&UTO).unwrap());
-        }
-    }
+            assert_eq!(trie.remove_subtree(&"八代").unwrap(), 0);
+            assert_eq!(trie.size().unwrap(), 3);
 
-    #[test]
-    fn find() {
-        {
-            let trie = Trie::<&str, String>::builder().build().unwrap();
+            assert_eq!(trie.remove_subtree(&TAMA).unwrap(), 2);
+            assert_eq!(trie.size().unwrap(), 1);
+            assert!(trie.find(&TAMANA).unwrap().is_none());
+            assert!(trie.find(&TAMARAI).unwrap().is_none());
+            assert_eq!(*trie.find(&KUMAMOTO).unwrap().unwrap(), KUMAMOTO);
 
-            let found = trie.find(&KUMAMOTO).unwrap();
-            assert!(found.is_none());
+            assert_eq!(trie.remove_subtree(&"").unwrap(), 1);
+            assert!(trie.is_empty().unwrap());
         }
         {
-            let trie = Trie::<&str, String>::builder()
+            let mut trie = Trie::<&str, u32>::builder()
                 .elements(
                     [
-                        (KUMAMOTO, KUMAMOTO.to_string()),
-                        (TAMANA, TAMANA.to_string()),
+                        ("Kumamoto", 42u32),
+                        ("Tamana", 0x800000),
+                        ("Tamarai", 24),
+                        ("Uto", 0x900000),
                     ]
                     .to_vec(),
                 )
+                .inline_values()
                 .build()
                 .unwrap();
 
-            {
-                let found = trie.find(&KUMAMOTO).unwrap().unwrap();
-                assert_eq!(*found, KUMAMOTO.to_string());
-            }
-            {
-                let found = trie.find(&TAMANA).unwrap().unwrap();
-                assert_eq!(*found, TAMANA.to_string());
-            }
-            {
-                let found = trie.find(&UTO).unwrap();
-                assert!(found.is_none());
-            }
+            assert_eq!(trie.remove_subtree(&"Tama").unwrap(), 2);
+            assert_eq!(trie.size().unwrap(), 2);
+            assert_eq!(*trie.find(&"Kumamoto").unwrap().unwrap(), 42);
+            assert_eq!(*trie.find(&"Uto").unwrap().unwrap(), 0x900000);
+            assert_eq!(
+                trie.iter().map(|value| *value).collect::<Vec<_>>(),
+                [42, 0x900000]
+            );
         }
-    }
-
-    #[test]
-    fn iter() {
         {
-            let trie = Trie::<&str, String>::builder().build().unwrap();
+            let mut trie = Trie::<&str, String>::builder()
+                .expanded_records(
+                    [
+                        (("熊本", "くまもと"), KUMAMOTO.to_string()),
+                        (("玉名", "たまな"), TAMANA.to_string()),
+                    ]
+                    .to_vec(),
+                    &|&(surface, reading)| vec![surface, reading],
+                )
+                .build()
+                .unwrap();
 
-            let _iterator = trie.iter();
+            assert_eq!(trie.remove_subtree(&"たま").unwrap(), 1);
+            assert_eq!(trie.size().unwrap(), 2);
+            assert_eq!(trie.remove_subtree(&"玉名").unwrap(), 1);
+            assert_eq!(trie.size().unwrap(), 1);
+            assert!(Rc::ptr_eq(
+                &trie.find(&"熊本").unwrap().unwrap(),
+                &trie.find(&"くまもと").unwrap().unwrap()
+            ));
         }
+    }
+
+    #[test]
+    fn replace_value() {
         {
-            let trie = Trie::<&str, String>::builder()
+            let mut trie = Trie::<&str, String>::builder()
                 .elements(
                     [
                         (KUMAMOTO, KUMAMOTO.to_string()),
@@ -722,8 +3209,228 @@ mod tests {
                 .build()
                 .unwrap();
 
-            let _iterator = trie.iter();
+            let old_value = trie.replace_value(&TAMANA, UTO.to_string()).unwrap();
+            assert_eq!(old_value.unwrap().as_str(), TAMANA);
+            assert_eq!(trie.find(&TAMANA).unwrap().unwrap().as_str(), UTO);
+            assert_eq!(trie.find(&KUMAMOTO).unwrap().unwrap().as_str(), KUMAMOTO);
+            assert_eq!(trie.size().unwrap(), 2);
+
+            assert!(trie
+                .replace_value(&TAMARAI, UTO.to_string())
+                .unwrap()
+                .is_none());
+            assert!(trie.find(&TAMARAI).unwrap().is_none());
+        }
+        {
+            let mut trie = Trie::<&str, u32>::builder()
+                .elements([("Kumamoto", 42u32), ("Tamana", 0x800000)].to_vec())
+                .inline_values()
+                .build()
+                .unwrap();
+
+            assert_eq!(*trie.replace_value(&"Kumamoto", 24).unwrap().unwrap(), 42);
+            assert_eq!(*trie.find(&"Kumamoto").unwrap().unwrap(), 24);
+            assert_eq!(
+                *trie.replace_value(&"Tamana", 0x900000).unwrap().unwrap(),
+                0x800000
+            );
+            assert_eq!(*trie.find(&"Tamana").unwrap().unwrap(), 0x900000);
+
+            let error = trie.replace_value(&"Kumamoto", 0x800000).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<TrieError>().unwrap(),
+                TrieError::NotInlinableValue
+            ));
+            assert_eq!(*trie.find(&"Kumamoto").unwrap().unwrap(), 24);
+        }
+        {
+            let mut trie = Trie::<&str, String>::builder()
+                .expanded_records(
+                    [(("熊本", "くまもと"), KUMAMOTO.to_string())].to_vec(),
+                    &|&(surface, reading)| vec![surface, reading],
+                )
+                .build()
+                .unwrap();
+
+            let _old_value = trie.replace_value(&"くまもと", UTO.to_string()).unwrap();
+            assert_eq!(trie.find(&"熊本").unwrap().unwrap().as_str(), UTO);
+        }
+        #[cfg(feature = "std")]
+        {
+            let mut serialized = Vec::new();
+            Trie::<&str, i32>::builder()
+                .elements([("Kumamoto", 42)].to_vec())
+                .build()
+                .unwrap()
+                .storage()
+                .serialize(
+                    &mut serialized,
+                    &mut ValueSerializer::new(
+                        Box::new(|value: &i32| value.to_be_bytes().to_vec()),
+                        size_of::<i32>(),
+                    ),
+                )
+                .unwrap();
+            let mut value_deserializer = ValueDeserializer::new(Box::new(|serialized| {
+                Ok(i32::from_be_bytes(serialized.try_into()?))
+            }));
+            let storage = Box::new(
+                SharedStorage::new_with_reader(
+                    &mut Cursor::new(serialized),
+                    &mut value_deserializer,
+                )
+                .unwrap(),
+            );
+            let _shared_storage = storage.clone_box();
+            let mut trie = Trie::<&str, i32>::builder_with_storage(storage).build();
+
+            let error = trie.replace_value(&"Kumamoto", 24).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<TrieError>().unwrap(),
+                TrieError::ImmutableStorage
+            ));
+            assert_eq!(*trie.find(&"Kumamoto").unwrap().unwrap(), 42);
+        }
+    }
+
+    fn create_scored_trie() -> Trie<&'static str, i32> {
+        Trie::<&str, i32>::builder()
+            .elements(vec![
+                ("Kumamoto", 42),
+                ("Tamana", 24),
+                ("Tamarai", 2424),
+                ("Tamaraiki", 2424),
+                ("Tamaru", 4242),
+                ("Uto", 4242),
+            ])
+            .build()
+            .unwrap()
+    }
+
+    fn to_entries(completions: Vec<(Vec<u8>, Rc<i32>)>) -> Vec<(Vec<u8>, i32)> {
+        completions
+            .into_iter()
+            .map(|(key, value)| (key, *value))
+            .collect()
+    }
+
+    #[test]
+    fn top_completions() {
+        let trie = create_scored_trie();
+
+        assert_eq!(
+            to_entries(
+                trie.top_completions(&"Tama", 3, |&value| value as i64)
+                    .unwrap()
+            ),
+            [
+                (b"Tamaru".to_vec(), 4242),
+                (b"Tamarai".to_vec(), 2424),
+                (b"Tamaraiki".to_vec(), 2424)
+            ]
+        );
+        assert_eq!(
+            to_entries(
+                trie.top_completions(&"Tama", 10, |&value| -(value as i64))
+                    .unwrap()
+            ),
+            [
+                (b"Tamana".to_vec(), 24),
+                (b"Tamarai".to_vec(), 2424),
+                (b"Tamaraiki".to_vec(), 2424),
+                (b"Tamaru".to_vec(), 4242)
+            ]
+        );
+        assert_eq!(
+            to_entries(trie.top_completions(&"", 2, |&value| value as i64).unwrap()),
+            [(b"Tamaru".to_vec(), 4242), (b"Uto".to_vec(), 4242)]
+        );
+        assert!(trie
+            .top_completions(&"Tama", 0, |&value| value as i64)
+            .unwrap()
+            .is_empty());
+        assert!(trie
+            .top_completions(&"Sakura", 3, |&value| value as i64)
+            .unwrap()
+            .is_empty());
+
+        let subtrie = trie.subtrie(&"Tama").unwrap().unwrap();
+        assert_eq!(
+            to_entries(
+                subtrie
+                    .top_completions(&"r", 2, |&value| value as i64)
+                    .unwrap()
+            ),
+            [(b"ru".to_vec(), 4242), (b"rai".to_vec(), 2424)]
+        );
+    }
+
+    #[test]
+    fn top_completions_with_hints() {
+        let trie = create_scored_trie();
+        let score = |&value: &i32| value as i64;
+        let hints = trie.completion_hints(score).unwrap();
+
+        assert_eq!(
+            to_entries(
+                trie.top_completions_with_hints(&"Tama", 3, score, &hints)
+                    .unwrap()
+            ),
+            [
+                (b"Tamaru".to_vec(), 4242),
+                (b"Tamarai".to_vec(), 2424),
+                (b"Tamaraiki".to_vec(), 2424)
+            ]
+        );
+        assert_eq!(
+            to_entries(
+                trie.top_completions_with_hints(&"", 2, score, &hints)
+                    .unwrap()
+            ),
+            [(b"Tamaru".to_vec(), 4242), (b"Uto".to_vec(), 4242)]
+        );
+        assert!(trie
+            .top_completions_with_hints(&"Tama", 0, score, &hints)
+            .unwrap()
+            .is_empty());
+        assert!(trie
+            .top_completions_with_hints(&"Sakura", 3, score, &hints)
+            .unwrap()
+            .is_empty());
+        for prefix in ["", "K", "Tama", "Tamar", "Tamarai", "U"] {
+            for k in 0..8 {
+                assert_eq!(
+                    to_entries(
+                        trie.top_completions_with_hints(&prefix, k, score, &hints)
+                            .unwrap()
+                    ),
+                    to_entries(trie.top_completions(&prefix, k, score).unwrap())
+                );
+            }
         }
+
+        let subtrie = trie.subtrie(&"Tama").unwrap().unwrap();
+        assert_eq!(
+            to_entries(
+                subtrie
+                    .top_completions_with_hints(&"r", 2, score, &hints)
+                    .unwrap()
+            ),
+            [(b"ru".to_vec(), 4242), (b"rai".to_vec(), 2424)]
+        );
+    }
+
+    #[test]
+    fn completion_hints() {
+        let trie = create_scored_trie();
+
+        let hints = trie.completion_hints(|&value| value as i64).unwrap();
+
+        assert_eq!(
+            hints.max_scores().len(),
+            trie.storage().base_check_size().unwrap()
+        );
+        assert_eq!(hints.max_scores().iter().flatten().max(), Some(&4242));
     }
 
     #[test]
@@ -851,6 +3558,7 @@ mod tests {
 
             let _storage = trie.storage();
         }
+        #[cfg(feature = "std")]
         {
             let mut reader = create_input_stream();
             let mut value_deserializer = ValueDeserializer::new(Box::new(|serialized| {
@@ -880,4 +3588,196 @@ mod tests {
             assert_eq!(storage_serialized.as_slice(), SERIALIZED);
         }
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn golden() {
+        let trie = Trie::<&str, String>::builder()
+            .elements(
+                [
+                    (KUMAMOTO, String::from("Kumamoto")),
+                    (TAMANA, String::from("Tamana")),
+                    (UTO, String::from("Kumamoto")),
+                ]
+                .to_vec(),
+            )
+            .build()
+            .unwrap();
+
+        for (golden, value_interning, format_version) in [
+            (GOLDEN_V1, false, FormatVersion::V1),
+            (GOLDEN_V2, true, FormatVersion::V2),
+        ] {
+            let mut writer = Cursor::new(Vec::<u8>::new());
+            let serialize = Box::new(|value: &String| {
+                static STR_SERIALIZER: LazyLock<StrSerializer> =
+                    LazyLock::new(|| StrSerializer::new(false));
+                STR_SERIALIZER.serialize(&value.as_str())
+            });
+            let mut serializer = if value_interning {
+                ValueSerializer::<String>::new_with_value_interning(serialize, 0)
+            } else {
+                ValueSerializer::<String>::new(serialize, 0)
+            };
+            trie.storage()
+                .serialize(&mut writer, &mut serializer)
+                .unwrap();
+            assert_eq!(writer.get_ref().as_slice(), golden);
+
+            let mut value_deserializer = ValueDeserializer::new(Box::new(|serialized| {
+                static STRING_DESERIALIZER: LazyLock<StringDeserializer> =
+                    LazyLock::new(|| StringDeserializer::new(false));
+                STRING_DESERIALIZER.deserialize(serialized)
+            }));
+            let storage =
+                MemoryStorage::new_with_reader(&mut Cursor::new(golden), &mut value_deserializer)
+                    .unwrap();
+            assert_eq!(storage.format_version(), format_version);
+            let loaded = Trie::<&str, String>::builder_with_storage(Box::new(storage)).build();
+            assert_eq!(*loaded.find(&KUMAMOTO).unwrap().unwrap(), "Kumamoto");
+            assert_eq!(*loaded.find(&TAMANA).unwrap().unwrap(), "Tamana");
+            assert_eq!(*loaded.find(&UTO).unwrap().unwrap(), "Kumamoto");
+        }
+    }
+
+    #[test]
+    fn dump_tsv() {
+        {
+            let trie = Trie::<&str, i32>::builder().build().unwrap();
+
+            let mut writer = Vec::new();
+            trie.dump_tsv(&mut writer, &mut |value| value.to_string())
+                .unwrap();
+
+            assert!(writer.is_empty());
+        }
+        {
+            let trie = Trie::<&str, String>::builder()
+                .elements(
+                    [
+                        (KUMAMOTO, String::from("Kumamoto")),
+                        ("Tab\tKey", String::from("Line\nFeed")),
+                        ("Back\\slash", String::from("Carriage\rReturn")),
+                    ]
+                    .to_vec(),
+                )
+                .build()
+                .unwrap();
+
+            let mut writer = Vec::new();
+            trie.dump_tsv(&mut writer, &mut |value| value.clone())
+                .unwrap();
+
+            assert_eq!(
+                String::from_utf8(writer).unwrap(),
+                "Back\\\\slash\tCarriage\\rReturn\n\
+                 Tab\\tKey\tLine\\nFeed\n\
+                 熊本\tKumamoto\n"
+            );
+        }
+        {
+            let trie = Trie::<&str, i32>::builder()
+                .elements([("Bell\u{7}", 42)].to_vec())
+                .build()
+                .unwrap();
+
+            let mut writer = Vec::new();
+            trie.dump_tsv(&mut writer, &mut |value| value.to_string())
+                .unwrap();
+
+            assert_eq!(String::from_utf8(writer).unwrap(), "Bell\\x07\t42\n");
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn load_tsv() {
+        {
+            let trie =
+                Trie::<&str, i32>::load_tsv(&mut Cursor::new(b""), &mut |value| Ok(value.parse()?))
+                    .unwrap();
+
+            assert!(trie.is_empty().unwrap());
+        }
+        {
+            let mut reader = Cursor::new(
+                "熊本\tKumamoto\n\
+                 \n\
+                 Tab\\tKey\tLine\\nFeed\r\n\
+                 Back\\\\slash\tCarriage\\rReturn\n"
+                    .as_bytes(),
+            );
+            let trie =
+                Trie::<&str, String>::load_tsv(&mut reader, &mut |value| Ok(value.to_string()))
+                    .unwrap();
+
+            assert_eq!(trie.size().unwrap(), 3);
+            assert_eq!(*trie.find(&KUMAMOTO).unwrap().unwrap(), "Kumamoto");
+            assert_eq!(*trie.find(&"Tab\tKey").unwrap().unwrap(), "Line\nFeed");
+            assert_eq!(
+                *trie.find(&"Back\\slash").unwrap().unwrap(),
+                "Carriage\rReturn"
+            );
+        }
+        {
+            let tsv = "Kuma\\xC0\t42\n";
+            let trie =
+                Trie::<&str, i32>::load_tsv(&mut Cursor::new(tsv), &mut |value| Ok(value.parse()?))
+                    .unwrap();
+
+            let mut writer = Vec::new();
+            trie.dump_tsv(&mut writer, &mut |value| value.to_string())
+                .unwrap();
+
+            assert_eq!(String::from_utf8(writer).unwrap(), tsv);
+        }
+        {
+            let trie =
+                Trie::<&str, i32>::load_tsv(&mut Cursor::new(b"Kumamoto 42\n"), &mut |value| {
+                    Ok(value.parse()?)
+                });
+
+            assert!(trie.is_err());
+        }
+        {
+            let trie = Trie::<&str, i32>::load_tsv(
+                &mut Cursor::new(b"Kumamoto\\x4\t42\n"),
+                &mut |value| Ok(value.parse()?),
+            );
+
+            assert!(trie.is_err());
+        }
+        {
+            let trie = Trie::<&str, i32>::load_tsv(
+                &mut Cursor::new(b"Kumamoto\tforty-two\n"),
+                &mut |value| Ok(value.parse()?),
+            );
+
+            assert!(trie.is_err());
+        }
+        {
+            let error = Trie::<&str, i32>::load_tsv(
+                &mut Cursor::new(b"Kumamoto\t42\nTama\\x00na\t24\n"),
+                &mut |value| Ok(value.parse()?),
+            )
+            .unwrap_err();
+
+            assert!(matches!(
+                error.downcast_ref::<TrieBuilderError>().unwrap(),
+                TrieBuilderError::KeyContainsTerminator { index: 1 }
+            ));
+        }
+        {
+            let error = Trie::<&str, i32>::load_tsv(
+                &mut Cursor::new(b"Kumamoto\t42\nTamana\t24\n\nKumamoto\t4242\n"),
+                &mut |value| Ok(value.parse()?),
+            )
+            .unwrap_err();
+
+            assert!(matches!(
+                error.downcast_ref::<TrieTsvError>().unwrap(),
+                TrieTsvError::DuplicateKey { index: 2 }
+            ));
+        }
+    }
 }
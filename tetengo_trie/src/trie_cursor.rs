@@ -0,0 +1,252 @@
+/*!
+ * A trie cursor.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use alloc::rc::Rc;
+use core::fmt::Debug;
+
+use anyhow::Result;
+
+use crate::double_array::{self, DoubleArray};
+
+/**
+ * A cursor state.
+ */
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CursorState<T> {
+    /// The bytes fed so far are a prefix of some keys but not a key.
+    Continue,
+
+    /// The bytes fed so far are a key. Longer keys may follow.
+    Terminal(Rc<T>),
+
+    /// The bytes fed so far are not a prefix of any key.
+    Dead,
+}
+
+/**
+ * A trie cursor.
+ *
+ * Traverses a trie one serialized key byte at a time, so that keys can be
+ * matched against streaming input without buffering it.
+ */
+#[derive(Debug)]
+pub struct TrieCursor<'a, T: Debug> {
+    double_array: &'a DoubleArray<T>,
+    base_check_index: Option<usize>,
+    length: usize,
+}
+
+impl<'a, T: Clone + Debug + 'static> TrieCursor<'a, T> {
+    /**
+     * Creates a cursor.
+     *
+     * # Arguments
+     * * `double_array` - A double array.
+     */
+    pub(super) const fn new(double_array: &'a DoubleArray<T>) -> Self {
+        Self {
+            double_array,
+            base_check_index: Some(double_array.root_base_check_index()),
+            length: 0,
+        }
+    }
+
+    /**
+     * Advances the cursor by a byte.
+     *
+     * Once the cursor is dead, it stays dead.
+     *
+     * # Arguments
+     * * `byte` - A byte of a serialized key.
+     *
+     * # Returns
+     * The state after the byte.
+     *
+     * # Errors
+     * * When it fails to access the storage.
+     */
+    pub fn advance(&mut self, byte: u8) -> Result<CursorState<T>> {
+        let Some(base_check_index) = self.base_check_index else {
+            return Ok(CursorState::Dead);
+        };
+        self.base_check_index = self.double_array.child_index(base_check_index, byte)?;
+        let Some(base_check_index) = self.base_check_index else {
+            return Ok(CursorState::Dead);
+        };
+        self.length += 1;
+
+        let Some(value_base) = self.double_array.value_base_at(base_check_index)? else {
+            return Ok(CursorState::Continue);
+        };
+        match double_array::value_of_base(self.double_array.storage(), value_base)? {
+            Some(value) => Ok(CursorState::Terminal(value)),
+            None => Ok(CursorState::Continue),
+        }
+    }
+
+    /**
+     * Returns `true` if the cursor is dead.
+     *
+     * # Returns
+     * `true` if the cursor is dead.
+     */
+    pub const fn is_dead(&self) -> bool {
+        self.base_check_index.is_none()
+    }
+
+    /**
+     * Returns the count of the bytes accepted so far.
+     *
+     * The byte which made the cursor dead is not counted.
+     *
+     * # Returns
+     * The count of the bytes accepted so far.
+     */
+    pub const fn len(&self) -> usize {
+        self.length
+    }
+
+    /**
+     * Returns `true` if no byte is accepted yet.
+     *
+     * # Returns
+     * `true` if no byte is accepted yet.
+     */
+    pub const fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /**
+     * Matches the longest key.
+     *
+     * Feeds the bytes until the cursor gets dead or the bytes run out. The
+     * byte which made the cursor dead is consumed from `bytes`.
+     *
+     * # Arguments
+     * * `bytes` - Bytes of a serialized key.
+     *
+     * # Returns
+     * The length of the longest key and its value. Or `None` when no key
+     * matches.
+     *
+     * # Errors
+     * * When it fails to access the storage.
+     */
+    pub fn longest_match(
+        &mut self,
+        bytes: &mut impl Iterator<Item = u8>,
+    ) -> Result<Option<(usize, Rc<T>)>> {
+        let mut longest = None;
+        for byte in bytes {
+            match self.advance(byte)? {
+                CursorState::Continue => {}
+                CursorState::Terminal(value) => longest = Some((self.length, value)),
+                CursorState::Dead => break,
+            }
+        }
+        Ok(longest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use crate::trie::Trie;
+
+    use super::*;
+
+    fn make_trie() -> Trie<&'static str, i32> {
+        Trie::<&str, i32>::builder()
+            .elements(vec![("ab", 1), ("abcd", 2), ("b", 3)])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn advance() {
+        let trie = make_trie();
+        let mut cursor = trie.cursor();
+
+        assert_eq!(cursor.advance(b'a').unwrap(), CursorState::Continue);
+        assert_eq!(
+            cursor.advance(b'b').unwrap(),
+            CursorState::Terminal(Rc::new(1))
+        );
+        assert_eq!(cursor.advance(b'c').unwrap(), CursorState::Continue);
+        assert_eq!(cursor.advance(b'x').unwrap(), CursorState::Dead);
+        assert_eq!(cursor.advance(b'd').unwrap(), CursorState::Dead);
+    }
+
+    #[test]
+    fn is_dead() {
+        let trie = make_trie();
+        let mut cursor = trie.cursor();
+        assert!(!cursor.is_dead());
+
+        let _state = cursor.advance(b'b').unwrap();
+        assert!(!cursor.is_dead());
+
+        let _state = cursor.advance(b'b').unwrap();
+        assert!(cursor.is_dead());
+    }
+
+    #[test]
+    fn len() {
+        let trie = make_trie();
+        let mut cursor = trie.cursor();
+        assert_eq!(cursor.len(), 0);
+
+        let _state = cursor.advance(b'a').unwrap();
+        let _state = cursor.advance(b'b').unwrap();
+        assert_eq!(cursor.len(), 2);
+
+        let _state = cursor.advance(b'x').unwrap();
+        assert_eq!(cursor.len(), 2);
+    }
+
+    #[test]
+    fn is_empty() {
+        let trie = make_trie();
+        let mut cursor = trie.cursor();
+        assert!(cursor.is_empty());
+
+        let _state = cursor.advance(b'a').unwrap();
+        assert!(!cursor.is_empty());
+    }
+
+    #[test]
+    fn longest_match() {
+        let trie = make_trie();
+        {
+            let mut bytes = b"abcx-rest".iter().copied();
+            let matched = trie.cursor().longest_match(&mut bytes).unwrap();
+
+            assert_eq!(matched, Some((2, Rc::new(1))));
+            assert_eq!(bytes.collect::<Vec<_>>(), b"-rest");
+        }
+        {
+            let mut bytes = b"abcd".iter().copied();
+            let matched = trie.cursor().longest_match(&mut bytes).unwrap();
+
+            assert_eq!(matched, Some((4, Rc::new(2))));
+        }
+        {
+            let mut bytes = b"ca".iter().copied();
+            let matched = trie.cursor().longest_match(&mut bytes).unwrap();
+
+            assert!(matched.is_none());
+        }
+        {
+            let subtrie = trie.subtrie(&"ab").unwrap().unwrap();
+            let mut bytes = b"cd".iter().copied();
+            let matched = subtrie.cursor().longest_match(&mut bytes).unwrap();
+
+            assert_eq!(matched, Some((2, Rc::new(2))));
+        }
+    }
+}
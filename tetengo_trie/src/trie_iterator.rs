@@ -4,11 +4,12 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use std::fmt::Debug;
-use std::rc::Rc;
+use alloc::rc::Rc;
+use core::fmt::Debug;
 
+use crate::double_array;
 use crate::double_array_iterator::DoubleArrayIterator;
-use crate::storage::Storage;
+use crate::storage::StorageRead;
 
 /**
  * A trie iterator.
@@ -16,7 +17,7 @@ use crate::storage::Storage;
 #[derive(Clone, Debug)]
 pub struct TrieIterator<'a, T: 'static> {
     double_array_iterator: DoubleArrayIterator<'a, T>,
-    storage: &'a dyn Storage<T>,
+    storage: &'a dyn StorageRead<T>,
 }
 
 impl<'a, T> TrieIterator<'a, T> {
@@ -29,7 +30,7 @@ impl<'a, T> TrieIterator<'a, T> {
      */
     pub(super) const fn new(
         double_array_iterator: DoubleArrayIterator<'a, T>,
-        storage: &'a dyn Storage<T>,
+        storage: &'a dyn StorageRead<T>,
     ) -> Self {
         Self {
             double_array_iterator,
@@ -42,8 +43,8 @@ impl<T> Iterator for TrieIterator<'_, T> {
     type Item = Rc<T>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let value_index = self.double_array_iterator.next()?;
-        match self.storage.value_at(value_index as usize) {
+        let value_base = self.double_array_iterator.next()?;
+        match double_array::value_of_base(self.storage, value_base) {
             Ok(value) => value,
             Err(e) => {
                 debug_assert!(false, "{}", e);
@@ -55,6 +56,9 @@ impl<T> Iterator for TrieIterator<'_, T> {
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+
     use crate::trie::Trie;
 
     const KUMAMOTO: &str = "熊本";
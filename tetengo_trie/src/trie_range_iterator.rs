@@ -0,0 +1,123 @@
+/*!
+ * A trie range iterator.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::double_array;
+use crate::double_array_iterator::DoubleArrayIterator;
+use crate::storage::StorageRead;
+
+/**
+ * A trie range iterator.
+ *
+ * Iterates the elements whose serialized keys are in a range, in the order of
+ * the serialized keys.
+ */
+#[derive(Clone, Debug)]
+pub struct TrieRangeIterator<'a, T: 'static> {
+    double_array_iterator: DoubleArrayIterator<'a, T>,
+    storage: &'a dyn StorageRead<T>,
+}
+
+impl<'a, T> TrieRangeIterator<'a, T> {
+    /**
+     * Creates an iterator.
+     *
+     * # Arguments
+     * * `double_array_iterator` - A double array iterator.
+     * * `storage`               - A storage.
+     */
+    pub(super) const fn new(
+        double_array_iterator: DoubleArrayIterator<'a, T>,
+        storage: &'a dyn StorageRead<T>,
+    ) -> Self {
+        Self {
+            double_array_iterator,
+            storage,
+        }
+    }
+}
+
+impl<T> Iterator for TrieRangeIterator<'_, T> {
+    type Item = (Vec<u8>, Rc<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (serialized_key, value_base) = self.double_array_iterator.next_with_key()?;
+        match double_array::value_of_base(self.storage, value_base) {
+            Ok(value) => value.map(|value| (serialized_key, value)),
+            Err(e) => {
+                debug_assert!(false, "{}", e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+
+    use crate::trie::Trie;
+
+    const KUMAMOTO: &str = "熊本";
+
+    const TAMANA: &str = "玉名";
+
+    const TAMARAI: &str = "玉来";
+
+    #[test]
+    fn new() {
+        let trie = Trie::<&str, String>::builder()
+            .elements(vec![
+                (KUMAMOTO, KUMAMOTO.to_string()),
+                (TAMANA, TAMANA.to_string()),
+            ])
+            .build()
+            .unwrap();
+
+        let mut iterator = trie.range(..);
+        let mut clone = iterator.clone();
+        assert_eq!(clone.next(), iterator.next());
+        assert_eq!(clone.next(), iterator.next());
+        assert_eq!(clone.next(), iterator.next());
+    }
+
+    #[test]
+    fn next() {
+        {
+            let trie = Trie::<&str, String>::builder().build().unwrap();
+            let mut iterator = trie.range(..);
+
+            assert!(iterator.next().is_none());
+        }
+        {
+            let trie = Trie::<&str, String>::builder()
+                .elements(vec![
+                    (KUMAMOTO, KUMAMOTO.to_string()),
+                    (TAMANA, TAMANA.to_string()),
+                    (TAMARAI, TAMARAI.to_string()),
+                ])
+                .build()
+                .unwrap();
+            let mut iterator = trie.range(TAMANA..);
+
+            {
+                let (key, value) = iterator.next().unwrap();
+                assert_eq!(key, TAMANA.as_bytes());
+                assert_eq!(*value, TAMANA);
+            }
+            {
+                let (key, value) = iterator.next().unwrap();
+                assert_eq!(key, TAMARAI.as_bytes());
+                assert_eq!(*value, TAMARAI);
+            }
+            assert!(iterator.next().is_none());
+        }
+    }
+}
@@ -0,0 +1,343 @@
+/*!
+ * A trie snapshot.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt::Debug;
+use core::marker::PhantomData;
+
+use anyhow::Result;
+
+use crate::byte_writer::ByteWriter;
+use crate::double_array::VACANT_CHECK_VALUE;
+use crate::memory_storage::MemoryStorage;
+use crate::serializer::{Serializer, SerializerOf};
+use crate::storage::{StorageRead, ValueRef};
+use crate::trie::Trie;
+use crate::value_serializer::ValueSerializer;
+
+/**
+ * A trie snapshot.
+ *
+ * A read-only copy of a trie shared with a `SwappableTrie` and other
+ * snapshots. The snapshot owns its base-check array and values behind an
+ * `Arc`, so it can be sent to and shared among threads when the value type
+ * and the key serializer type can.
+ *
+ * Cloning a snapshot does not copy the trie. A trie made by `trie` reads
+ * the shared arrays without copying them, and is used within a thread.
+ *
+ * # Type Parameters
+ * * `Key`           - A key type.
+ * * `Value`         - A value type.
+ * * `KeySerializer` - A key serializer type.
+ */
+#[derive(Debug)]
+pub struct TrieSnapshot<
+    Key,
+    Value: Debug,
+    KeySerializer: Serializer = <() as SerializerOf<Key>>::Type,
+> {
+    phantom: PhantomData<fn() -> Key>,
+    contents: Arc<SnapshotContents<Value>>,
+    root_base_check_index: usize,
+    key_serializer: KeySerializer,
+    max_key_len: Option<usize>,
+}
+
+impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer + Clone>
+    TrieSnapshot<Key, Value, KeySerializer>
+{
+    /**
+     * Creates a trie snapshot.
+     *
+     * The base-check array and the values of the trie are copied.
+     *
+     * # Arguments
+     * * `trie` - A trie.
+     *
+     * # Errors
+     * * When it fails to access the storage of the trie.
+     */
+    pub fn new(trie: &Trie<Key, Value, KeySerializer>) -> Result<Self> {
+        let storage = trie.storage();
+        let base_check_array = (0..storage.base_check_size()?)
+            .map(|i| Ok(((storage.base_at(i)? as u32) << 8) | u32::from(storage.check_at(i)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let values = (0..storage.value_count()?)
+            .map(|i| Ok(storage.value_at(i)?.map(|value| value.as_ref().clone())))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            phantom: PhantomData,
+            contents: Arc::new(SnapshotContents {
+                base_check_array,
+                values,
+            }),
+            root_base_check_index: trie.root_base_check_index(),
+            key_serializer: trie.key_serializer().clone(),
+            max_key_len: trie.max_key_len(),
+        })
+    }
+
+    /**
+     * Returns a trie reading this snapshot.
+     *
+     * The trie shares the base-check array and the values with this snapshot.
+     * The values found in it are cloned from the snapshot, and the ones found
+     * by `find_ref` are borrowed.
+     *
+     * # Returns
+     * A trie.
+     */
+    pub fn trie(&self) -> Trie<Key, Value, KeySerializer> {
+        Trie::new_with_root(
+            Box::new(SnapshotStorage {
+                contents: self.contents.clone(),
+            }),
+            self.root_base_check_index,
+            self.key_serializer.clone(),
+            self.max_key_len,
+        )
+    }
+
+    /**
+     * Returns `true` if both snapshots refer to the same trie.
+     *
+     * # Arguments
+     * * `one`     - A snapshot.
+     * * `another` - Another snapshot.
+     *
+     * # Returns
+     * `true` if both snapshots refer to the same trie.
+     */
+    pub fn ptr_eq(one: &Self, another: &Self) -> bool {
+        Arc::ptr_eq(&one.contents, &another.contents)
+    }
+}
+
+impl<Key, Value: Debug, KeySerializer: Serializer + Clone> Clone
+    for TrieSnapshot<Key, Value, KeySerializer>
+{
+    fn clone(&self) -> Self {
+        Self {
+            phantom: PhantomData,
+            contents: self.contents.clone(),
+            root_base_check_index: self.root_base_check_index,
+            key_serializer: self.key_serializer.clone(),
+            max_key_len: self.max_key_len,
+        }
+    }
+}
+
+impl<Key, Value: Clone + Debug + 'static, KeySerializer: Serializer + Clone>
+    TryFrom<&Trie<Key, Value, KeySerializer>> for TrieSnapshot<Key, Value, KeySerializer>
+{
+    type Error = anyhow::Error;
+
+    fn try_from(trie: &Trie<Key, Value, KeySerializer>) -> Result<Self> {
+        Self::new(trie)
+    }
+}
+
+#[derive(Debug)]
+struct SnapshotContents<Value> {
+    base_check_array: Vec<u32>,
+    values: Vec<Option<Value>>,
+}
+
+// A read-only storage of the arrays shared among the snapshots.
+#[derive(Debug)]
+struct SnapshotStorage<Value> {
+    contents: Arc<SnapshotContents<Value>>,
+}
+
+impl<Value: Clone + 'static> SnapshotStorage<Value> {
+    fn base_check_at(&self, base_check_index: usize) -> u32 {
+        // Like a memory storage, the elements beyond the array are vacant.
+        self.contents
+            .base_check_array
+            .get(base_check_index)
+            .copied()
+            .unwrap_or(VACANT_CHECK_VALUE as u32)
+    }
+
+    fn to_memory_storage(&self) -> MemoryStorage<Value> {
+        MemoryStorage::from_raw_parts(
+            self.contents.base_check_array.clone(),
+            self.contents.values.clone(),
+        )
+    }
+}
+
+impl<Value: Clone + Debug + 'static> StorageRead<Value> for SnapshotStorage<Value> {
+    fn base_check_size(&self) -> Result<usize> {
+        Ok(self.contents.base_check_array.len())
+    }
+
+    fn base_at(&self, base_check_index: usize) -> Result<i32> {
+        Ok(self.base_check_at(base_check_index) as i32 >> 8i32)
+    }
+
+    fn check_at(&self, base_check_index: usize) -> Result<u8> {
+        Ok((self.base_check_at(base_check_index) & 0xFF) as u8)
+    }
+
+    fn value_count(&self) -> Result<usize> {
+        Ok(self.contents.values.len())
+    }
+
+    fn value_at(&self, value_index: usize) -> Result<Option<Rc<Value>>> {
+        Ok(self
+            .contents
+            .values
+            .get(value_index)
+            .and_then(|value| value.clone().map(Rc::new)))
+    }
+
+    fn value_ref_at(&self, value_index: usize) -> Result<Option<ValueRef<'_, Value>>> {
+        Ok(self
+            .contents
+            .values
+            .get(value_index)
+            .and_then(|value| value.as_ref().map(ValueRef::Borrowed)))
+    }
+
+    fn filling_rate(&self) -> Result<f64> {
+        let base_check_array = &self.contents.base_check_array;
+        let empty_count = base_check_array
+            .iter()
+            .filter(|&&e| e == VACANT_CHECK_VALUE as u32)
+            .count();
+        Ok(1.0 - (empty_count as f64) / (base_check_array.len() as f64))
+    }
+
+    fn serialize(
+        &self,
+        writer: &mut dyn ByteWriter,
+        value_serializer: &mut ValueSerializer<'_, Value>,
+    ) -> Result<()> {
+        self.to_memory_storage().serialize(writer, value_serializer)
+    }
+
+    fn serialize_values_only(
+        &self,
+        writer: &mut dyn ByteWriter,
+        value_serializer: &mut ValueSerializer<'_, Value>,
+    ) -> Result<()> {
+        self.to_memory_storage()
+            .serialize_values_only(writer, value_serializer)
+    }
+
+    fn clone_box(&self) -> Box<dyn StorageRead<Value>> {
+        Box::new(Self {
+            contents: self.contents.clone(),
+        })
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use alloc::vec;
+
+    use super::*;
+
+    fn make_trie(elements: Vec<(&'static str, i32)>) -> Trie<&'static str, i32> {
+        Trie::<&str, i32>::builder()
+            .elements(elements)
+            .build()
+            .unwrap()
+    }
+
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    #[test]
+    fn new() {
+        {
+            let snapshot = TrieSnapshot::new(&make_trie(vec![("Kumamoto", 42)])).unwrap();
+
+            assert_send_sync(&snapshot);
+        }
+        {
+            let trie = Trie::<&str, String>::builder()
+                .elements(vec![
+                    ("Kumamoto", String::from("Kumamoto")),
+                    ("Tamana", String::from("Tamana")),
+                ])
+                .max_key_len(16)
+                .build()
+                .unwrap();
+            let subtrie = trie.subtrie(&"Tama").unwrap().unwrap();
+
+            let snapshot = TrieSnapshot::new(&subtrie).unwrap();
+
+            assert_send_sync(&snapshot);
+            let snapshot_trie = snapshot.trie();
+            assert_eq!(snapshot_trie.iter().count(), 1);
+            assert_eq!(
+                snapshot_trie.find(&"na").unwrap().unwrap().as_str(),
+                "Tamana"
+            );
+            assert_eq!(snapshot_trie.max_key_len(), Some(16));
+        }
+    }
+
+    #[test]
+    fn trie() {
+        let snapshot =
+            TrieSnapshot::new(&make_trie(vec![("Kumamoto", 42), ("Tamana", 24)])).unwrap();
+
+        let trie = snapshot.trie();
+        assert_eq!(*trie.find(&"Kumamoto").unwrap().unwrap(), 42);
+        assert_eq!(*trie.find_ref(&"Tamana").unwrap().unwrap(), 24);
+        assert!(trie.find(&"Uto").unwrap().is_none());
+        assert_eq!(
+            trie.iter().map(|value| *value).collect::<Vec<_>>(),
+            vec![42, 24]
+        );
+
+        let mut serialized = Vec::new();
+        let mut value_serializer =
+            ValueSerializer::new(Box::new(|value: &i32| value.to_be_bytes().to_vec()), 4);
+        trie.storage()
+            .serialize(&mut serialized, &mut value_serializer)
+            .unwrap();
+        let mut expected = Vec::new();
+        make_trie(vec![("Kumamoto", 42), ("Tamana", 24)])
+            .storage()
+            .serialize(&mut expected, &mut value_serializer)
+            .unwrap();
+        assert_eq!(serialized, expected);
+    }
+
+    #[test]
+    fn ptr_eq() {
+        let snapshot = TrieSnapshot::new(&make_trie(vec![("Kumamoto", 42)])).unwrap();
+        let cloned = snapshot.clone();
+        let other = TrieSnapshot::new(&make_trie(vec![("Kumamoto", 42)])).unwrap();
+
+        assert!(TrieSnapshot::ptr_eq(&snapshot, &cloned));
+        assert!(!TrieSnapshot::ptr_eq(&snapshot, &other));
+    }
+
+    #[test]
+    fn try_from() {
+        let snapshot = TrieSnapshot::try_from(&make_trie(vec![("Kumamoto", 42)])).unwrap();
+
+        assert_eq!(*snapshot.trie().find(&"Kumamoto").unwrap().unwrap(), 42);
+    }
+}
@@ -0,0 +1,126 @@
+/*!
+ * A trie suffix iterator.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use alloc::rc::Rc;
+use core::fmt::Debug;
+
+use crate::double_array;
+use crate::double_array_iterator::DoubleArrayIterator;
+use crate::serializer::Deserializer;
+use crate::storage::StorageRead;
+
+/**
+ * A trie suffix iterator.
+ *
+ * Iterates the elements with their keys relative to the root of the trie. For
+ * a subtrie, the keys are the suffixes following the key prefix.
+ */
+#[derive(Debug)]
+pub struct TrieSuffixIterator<'a, T: 'static, KeyDeserializer: Deserializer> {
+    double_array_iterator: DoubleArrayIterator<'a, T>,
+    storage: &'a dyn StorageRead<T>,
+    key_deserializer: KeyDeserializer,
+}
+
+impl<'a, T, KeyDeserializer: Deserializer> TrieSuffixIterator<'a, T, KeyDeserializer> {
+    /**
+     * Creates an iterator.
+     *
+     * # Arguments
+     * * `double_array_iterator` - A double array iterator.
+     * * `storage`               - A storage.
+     * * `key_deserializer`      - A key deserializer.
+     */
+    pub(super) const fn new(
+        double_array_iterator: DoubleArrayIterator<'a, T>,
+        storage: &'a dyn StorageRead<T>,
+        key_deserializer: KeyDeserializer,
+    ) -> Self {
+        Self {
+            double_array_iterator,
+            storage,
+            key_deserializer,
+        }
+    }
+}
+
+impl<T, KeyDeserializer: Deserializer> Iterator for TrieSuffixIterator<'_, T, KeyDeserializer> {
+    type Item = (KeyDeserializer::Object, Rc<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (serialized_suffix, value_base) = self.double_array_iterator.next_with_key()?;
+        let suffix = match self.key_deserializer.deserialize(&serialized_suffix) {
+            Ok(suffix) => suffix,
+            Err(e) => {
+                debug_assert!(false, "{}", e);
+                return None;
+            }
+        };
+        match double_array::value_of_base(self.storage, value_base) {
+            Ok(value) => value.map(|value| (suffix, value)),
+            Err(e) => {
+                debug_assert!(false, "{}", e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+
+    use crate::string_serializer::StringDeserializer;
+    use crate::trie::Trie;
+
+    use super::*;
+
+    const KUMAMOTO: &str = "熊本";
+
+    const TAMANA: &str = "玉名";
+
+    const TAMARAI: &str = "玉来";
+
+    #[test]
+    fn new() {
+        let trie = Trie::<&str, String>::builder()
+            .elements(vec![
+                (KUMAMOTO, KUMAMOTO.to_string()),
+                (TAMANA, TAMANA.to_string()),
+            ])
+            .build()
+            .unwrap();
+
+        let _iterator = trie.iter_suffixes(StringDeserializer::new(true));
+    }
+
+    #[test]
+    fn next() {
+        let trie = Trie::<&str, String>::builder()
+            .elements(vec![
+                (KUMAMOTO, KUMAMOTO.to_string()),
+                (TAMANA, TAMANA.to_string()),
+                (TAMARAI, TAMARAI.to_string()),
+            ])
+            .build()
+            .unwrap();
+        let subtrie = trie.subtrie(&"玉").unwrap().unwrap();
+        let mut iterator = subtrie.iter_suffixes(StringDeserializer::new(true));
+
+        {
+            let (suffix, value) = iterator.next().unwrap();
+            assert_eq!(suffix, "名");
+            assert_eq!(*value, TAMANA);
+        }
+        {
+            let (suffix, value) = iterator.next().unwrap();
+            assert_eq!(suffix, "来");
+            assert_eq!(*value, TAMARAI);
+        }
+        assert!(iterator.next().is_none());
+    }
+}
@@ -0,0 +1,133 @@
+/*!
+ * A value compression.
+ *
+ * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
+ */
+
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use anyhow::Result;
+
+use crate::storage::StorageError;
+
+/**
+ * A value compression error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum ValueCompressionError {
+    /**
+     * The codec is not supported.
+     */
+    #[error("the codec is not supported")]
+    UnsupportedCodec,
+
+    /**
+     * A compressed block is broken.
+     */
+    #[error("a compressed block is broken")]
+    BrokenBlock,
+}
+
+impl StorageError for ValueCompressionError {}
+
+/**
+ * A value compression codec.
+ *
+ * Each codec is available with the feature of the same name. Only LZ4 is
+ * provided. Zstandard is not, since its crate builds the C library.
+ */
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ValueCompression {
+    /**
+     * LZ4.
+     */
+    #[cfg(feature = "lz4")]
+    Lz4,
+}
+
+#[cfg_attr(not(feature = "lz4"), allow(unreachable_code, unused_variables))]
+impl ValueCompression {
+    pub(crate) const fn id(self) -> u32 {
+        match self {
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => 1,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn of_id(id: u32) -> Result<Self> {
+        match id {
+            #[cfg(feature = "lz4")]
+            1 => Ok(Self::Lz4),
+            _ => Err(ValueCompressionError::UnsupportedCodec.into()),
+        }
+    }
+
+    pub(crate) fn compress(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => lz4_flex::block::compress(bytes),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn decompress(self, compressed: &[u8], size: usize) -> Result<Vec<u8>> {
+        let decompressed: Vec<u8> = match self {
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => lz4_flex::block::decompress(compressed, size)
+                .map_err(|_| ValueCompressionError::BrokenBlock)?,
+        };
+        if decompressed.len() != size {
+            return Err(ValueCompressionError::BrokenBlock.into());
+        }
+        Ok(decompressed)
+    }
+}
+
+#[cfg(all(test, feature = "std", feature = "lz4"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id() {
+        assert_eq!(ValueCompression::Lz4.id(), 1);
+    }
+
+    #[test]
+    fn of_id() {
+        assert_eq!(ValueCompression::of_id(1).unwrap(), ValueCompression::Lz4);
+        assert!(matches!(
+            ValueCompression::of_id(0)
+                .unwrap_err()
+                .downcast_ref::<ValueCompressionError>(),
+            Some(ValueCompressionError::UnsupportedCodec)
+        ));
+    }
+
+    #[test]
+    fn compress() {
+        let bytes = b"hogehogehogehogehogehogehogehoge".repeat(4);
+        let compressed = ValueCompression::Lz4.compress(&bytes);
+        assert!(compressed.len() < bytes.len());
+    }
+
+    #[test]
+    fn decompress() {
+        let bytes = b"hogehogehogehogehogehogehogehoge".repeat(4);
+        let compressed = ValueCompression::Lz4.compress(&bytes);
+        assert_eq!(
+            ValueCompression::Lz4
+                .decompress(&compressed, bytes.len())
+                .unwrap(),
+            bytes
+        );
+        assert!(matches!(
+            ValueCompression::Lz4
+                .decompress(&[0xFFu8; 4], bytes.len())
+                .unwrap_err()
+                .downcast_ref::<ValueCompressionError>(),
+            Some(ValueCompressionError::BrokenBlock)
+        ));
+    }
+}
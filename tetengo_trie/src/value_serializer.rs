@@ -4,16 +4,48 @@
  * Copyright (C) 2023-2025 kaoru  <https://www.tetengo.org/>
  */
 
-use std::any::type_name_of_val;
-use std::fmt::{self, Debug, Formatter};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::type_name_of_val;
+use core::fmt::{self, Debug, Formatter};
 
 use anyhow::Result;
 
+use crate::value_compression::ValueCompression;
+
+/**
+ * A value serializer error.
+ */
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+pub enum ValueSerializerError {
+    /**
+     * The serialized value size does not match the fixed value size.
+     */
+    #[error("the serialized value size does not match the fixed value size")]
+    MismatchedValueSize,
+
+    /**
+     * The fixed value size is too large.
+     */
+    #[error("the fixed value size is too large")]
+    TooLargeFixedValueSize,
+}
+
 /**
  * A serialize function type
  */
 pub type Serialize<'a, Value> = Box<dyn FnMut(&Value) -> Vec<u8> + 'a>;
 
+/**
+ * A fallible serialize function type
+ */
+pub type TrySerialize<'a, Value> = Box<dyn FnMut(&Value) -> Result<Vec<u8>> + 'a>;
+
+/**
+ * A fallible serialize function type appending to a buffer
+ */
+pub type TrySerializeInto<'a, Value> = Box<dyn FnMut(&Value, &mut Vec<u8>) -> Result<()> + 'a>;
+
 /**
  * A value serializer.
  *
@@ -21,8 +53,11 @@ pub type Serialize<'a, Value> = Box<dyn FnMut(&Value) -> Vec<u8> + 'a>;
  * * `Value` - A value type.
  */
 pub struct ValueSerializer<'a, Value: ?Sized> {
-    serialize: Serialize<'a, Value>,
+    serialize_into: TrySerializeInto<'a, Value>,
     fixed_value_size: usize,
+    value_interning: bool,
+    compression: Option<ValueCompression>,
+    buffer: Vec<u8>,
 }
 
 impl<'a, Value: ?Sized> ValueSerializer<'a, Value> {
@@ -33,13 +68,141 @@ impl<'a, Value: ?Sized> ValueSerializer<'a, Value> {
      * * `serialize`        - A serializing function.
      * * `fixed_value_size` - The value size if it is fixed. Or 0 if the size is variable.
      */
-    pub fn new(serialize: Serialize<'a, Value>, fixed_value_size: usize) -> Self {
+    pub fn new(serialize: Serialize<'a, Value>, fixed_value_size: usize) -> Self
+    where
+        Value: 'a,
+    {
+        Self::new_fallible(Self::infallible(serialize), fixed_value_size)
+    }
+
+    /**
+     * Creates a value serializer with a fallible serializing function.
+     *
+     * # Arguments
+     * * `serialize`        - A fallible serializing function.
+     * * `fixed_value_size` - The value size if it is fixed. Or 0 if the size is variable.
+     */
+    pub fn new_fallible(serialize: TrySerialize<'a, Value>, fixed_value_size: usize) -> Self
+    where
+        Value: 'a,
+    {
+        Self::new_into(Self::appending(serialize), fixed_value_size)
+    }
+
+    /**
+     * Creates a value serializer with a serializing function appending to a
+     * buffer.
+     *
+     * The buffer is reused for many values, so that the serialization does not
+     * allocate for each value.
+     *
+     * # Arguments
+     * * `serialize_into`   - A fallible serializing function appending to a buffer.
+     * * `fixed_value_size` - The value size if it is fixed. Or 0 if the size is variable.
+     */
+    pub fn new_into(serialize_into: TrySerializeInto<'a, Value>, fixed_value_size: usize) -> Self {
         Self {
-            serialize,
+            serialize_into,
             fixed_value_size,
+            value_interning: false,
+            compression: None,
+            buffer: Vec::new(),
+        }
+    }
+
+    /**
+     * Creates a value serializer with value interning.
+     *
+     * The values whose serialized forms are identical are stored only once in
+     * a value pool, and the value array refers to the pool.
+     *
+     * # Arguments
+     * * `serialize`        - A serializing function.
+     * * `fixed_value_size` - The value size if it is fixed. Or 0 if the size is variable.
+     */
+    pub fn new_with_value_interning(
+        serialize: Serialize<'a, Value>,
+        fixed_value_size: usize,
+    ) -> Self
+    where
+        Value: 'a,
+    {
+        Self::new_fallible_with_value_interning(Self::infallible(serialize), fixed_value_size)
+    }
+
+    /**
+     * Creates a value serializer with a fallible serializing function and value
+     * interning.
+     *
+     * # Arguments
+     * * `serialize`        - A fallible serializing function.
+     * * `fixed_value_size` - The value size if it is fixed. Or 0 if the size is variable.
+     */
+    pub fn new_fallible_with_value_interning(
+        serialize: TrySerialize<'a, Value>,
+        fixed_value_size: usize,
+    ) -> Self
+    where
+        Value: 'a,
+    {
+        Self::new_into_with_value_interning(Self::appending(serialize), fixed_value_size)
+    }
+
+    /**
+     * Creates a value serializer with a serializing function appending to a
+     * buffer and value interning.
+     *
+     * # Arguments
+     * * `serialize_into`   - A fallible serializing function appending to a buffer.
+     * * `fixed_value_size` - The value size if it is fixed. Or 0 if the size is variable.
+     */
+    pub fn new_into_with_value_interning(
+        serialize_into: TrySerializeInto<'a, Value>,
+        fixed_value_size: usize,
+    ) -> Self {
+        Self {
+            value_interning: true,
+            ..Self::new_into(serialize_into, fixed_value_size)
         }
     }
 
+    fn infallible(mut serialize: Serialize<'a, Value>) -> TrySerialize<'a, Value>
+    where
+        Value: 'a,
+    {
+        Box::new(move |value| Ok(serialize(value)))
+    }
+
+    fn appending(mut serialize: TrySerialize<'a, Value>) -> TrySerializeInto<'a, Value>
+    where
+        Value: 'a,
+    {
+        Box::new(move |value, buffer| {
+            let serialized = serialize(value)?;
+            if buffer.is_empty() {
+                *buffer = serialized;
+            } else {
+                buffer.extend_from_slice(&serialized);
+            }
+            Ok(())
+        })
+    }
+
+    /**
+     * Sets a value compression.
+     *
+     * The serialized values, or the value pool when the values are interned,
+     * are compressed in blocks, so that a storage can decompress only the
+     * block containing a value.
+     *
+     * # Arguments
+     * * `compression` - A value compression.
+     */
+    pub fn compressed_with(mut self, compression: ValueCompression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
     /**
      * Serializes a value.
      *
@@ -48,9 +211,69 @@ impl<'a, Value: ?Sized> ValueSerializer<'a, Value> {
      *
      * # Returns
      * The serialized value.
+     *
+     * # Errors
+     * * When the serializing function fails.
+     * * When the serialized value size does not match the fixed value size.
+     */
+    pub fn serialize(&mut self, value: &Value) -> Result<Vec<u8>> {
+        let mut serialized = Vec::new();
+        self.serialize_into(value, &mut serialized)?;
+        Ok(serialized)
+    }
+
+    /**
+     * Serializes a value into a buffer.
+     *
+     * The serialized value is appended to the buffer. The buffer is left
+     * unchanged when it fails.
+     *
+     * # Arguments
+     * * `value`  - A value.
+     * * `buffer` - A buffer.
+     *
+     * # Errors
+     * * When the serializing function fails.
+     * * When the serialized value size does not match the fixed value size.
      */
-    pub fn serialize(&mut self, value: &Value) -> Vec<u8> {
-        (self.serialize)(value)
+    pub fn serialize_into(&mut self, value: &Value, buffer: &mut Vec<u8>) -> Result<()> {
+        let start = buffer.len();
+        if let Err(e) = (self.serialize_into)(value, buffer) {
+            buffer.truncate(start);
+            return Err(e);
+        }
+        if self.fixed_value_size != 0 && buffer.len() - start != self.fixed_value_size {
+            buffer.truncate(start);
+            return Err(ValueSerializerError::MismatchedValueSize.into());
+        }
+        Ok(())
+    }
+
+    /**
+     * Measures the serialized size of a value.
+     *
+     * The value is not serialized when the value size is fixed.
+     *
+     * # Arguments
+     * * `value` - A value.
+     *
+     * # Returns
+     * The serialized value size.
+     *
+     * # Errors
+     * * When the serializing function fails.
+     * * When the serialized value size does not match the fixed value size.
+     */
+    pub fn measure(&mut self, value: &Value) -> Result<usize> {
+        if self.fixed_value_size != 0 {
+            return Ok(self.fixed_value_size);
+        }
+        let mut buffer = core::mem::take(&mut self.buffer);
+        buffer.clear();
+        let result = self.serialize_into(value, &mut buffer);
+        let size = buffer.len();
+        self.buffer = buffer;
+        result.map(|_| size)
     }
 
     /**
@@ -62,13 +285,35 @@ impl<'a, Value: ?Sized> ValueSerializer<'a, Value> {
     pub const fn fixed_value_size(&self) -> usize {
         self.fixed_value_size
     }
+
+    /**
+     * Returns `true` if the values are interned.
+     *
+     * # Returns
+     * `true` if the values are interned.
+     */
+    pub const fn value_interning(&self) -> bool {
+        self.value_interning
+    }
+
+    /**
+     * Returns the value compression.
+     *
+     * # Returns
+     * The value compression. Or `None` when the values are not compressed.
+     */
+    pub const fn compression(&self) -> Option<ValueCompression> {
+        self.compression
+    }
 }
 
 impl<Value: ?Sized> Debug for ValueSerializer<'_, Value> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("ValueSerializer")
-            .field("serialize", &type_name_of_val(&self.serialize))
+            .field("serialize_into", &type_name_of_val(&self.serialize_into))
             .field("fixed_value_size", &self.fixed_value_size)
+            .field("value_interning", &self.value_interning)
+            .field("compression", &self.compression)
             .finish()
     }
 }
@@ -127,7 +372,8 @@ impl<Value: Clone> Debug for ValueDeserializer<Value> {
 #[cfg(test)]
 mod tests {
     mod value_serializer {
-        use std::cell::RefCell;
+        use alloc::vec;
+        use core::cell::RefCell;
 
         use crate::integer_serializer::IntegerSerializer;
         use crate::serializer::Serializer;
@@ -147,6 +393,56 @@ mod tests {
             }
         }
 
+        #[test]
+        fn new_fallible() {
+            let _serializer = ValueSerializer::new_fallible(
+                Box::new(|value: &i32| Ok(IntegerSerializer::new(false).serialize(value))),
+                size_of::<i32>(),
+            );
+        }
+
+        #[test]
+        fn new_into() {
+            let _serializer = ValueSerializer::new_into(
+                Box::new(|value: &i32, buffer: &mut Vec<u8>| {
+                    IntegerSerializer::new(false).serialize_into(value, buffer);
+                    Ok(())
+                }),
+                size_of::<i32>(),
+            );
+        }
+
+        #[test]
+        fn new_with_value_interning() {
+            let _serializer = ValueSerializer::new_with_value_interning(
+                Box::new(|value: &i32| IntegerSerializer::new(false).serialize(value)),
+                size_of::<i32>(),
+            );
+        }
+
+        #[test]
+        fn new_into_with_value_interning() {
+            let serializer = ValueSerializer::new_into_with_value_interning(
+                Box::new(|value: &i32, buffer: &mut Vec<u8>| {
+                    IntegerSerializer::new(false).serialize_into(value, buffer);
+                    Ok(())
+                }),
+                size_of::<i32>(),
+            );
+
+            assert!(serializer.value_interning());
+        }
+
+        #[test]
+        fn new_fallible_with_value_interning() {
+            let serializer = ValueSerializer::new_fallible_with_value_interning(
+                Box::new(|value: &i32| Ok(IntegerSerializer::new(false).serialize(value))),
+                size_of::<i32>(),
+            );
+
+            assert!(serializer.value_interning());
+        }
+
         #[test]
         fn serialize() {
             {
@@ -156,14 +452,14 @@ mod tests {
                 );
 
                 let expected = IntegerSerializer::new(false).serialize(&42);
-                let serialized = serializer.serialize(&42);
+                let serialized = serializer.serialize(&42).unwrap();
                 assert_eq!(serialized, expected);
             }
             {
                 let mut serializer = ValueSerializer::new(Box::new(|_: &str| vec![3, 1, 4]), 0);
 
                 let expected = vec![3, 1, 4];
-                let serialized = serializer.serialize("hoge");
+                let serialized = serializer.serialize("hoge").unwrap();
                 assert_eq!(serialized, expected);
             }
             {
@@ -177,10 +473,85 @@ mod tests {
                 );
 
                 let expected = vec![4, 2];
-                let serialized = serializer.serialize("hoge");
+                let serialized = serializer.serialize("hoge").unwrap();
                 assert_eq!(serialized, expected);
                 assert_eq!(*modified_in_closure.borrow(), 42);
             }
+            {
+                let mut serializer = ValueSerializer::new(Box::new(|_: &str| vec![3, 1, 4]), 2);
+
+                let error = serializer.serialize("hoge").unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<ValueSerializerError>().unwrap(),
+                    ValueSerializerError::MismatchedValueSize
+                ));
+            }
+            {
+                let mut serializer = ValueSerializer::new_fallible(
+                    Box::new(|value: &str| {
+                        if value.is_empty() {
+                            Err(ValueSerializerError::MismatchedValueSize.into())
+                        } else {
+                            Ok(value.as_bytes().to_vec())
+                        }
+                    }),
+                    0,
+                );
+
+                assert_eq!(serializer.serialize("hoge").unwrap(), b"hoge");
+                assert!(serializer.serialize("").is_err());
+            }
+        }
+
+        #[test]
+        fn serialize_into() {
+            {
+                let mut serializer = ValueSerializer::new_into(
+                    Box::new(|value: &str, buffer: &mut Vec<u8>| {
+                        buffer.extend_from_slice(value.as_bytes());
+                        Ok(())
+                    }),
+                    0,
+                );
+
+                let mut buffer = b"mizuho".to_vec();
+                serializer.serialize_into("sakura", &mut buffer).unwrap();
+                assert_eq!(buffer, b"mizuhosakura");
+            }
+            {
+                let mut serializer =
+                    ValueSerializer::new(Box::new(|value: &str| value.as_bytes().to_vec()), 4);
+
+                let mut buffer = b"mizuho".to_vec();
+                serializer.serialize_into("hoge", &mut buffer).unwrap();
+                assert_eq!(buffer, b"mizuhohoge");
+                let error = serializer
+                    .serialize_into("sakura", &mut buffer)
+                    .unwrap_err();
+                assert!(matches!(
+                    error.downcast_ref::<ValueSerializerError>().unwrap(),
+                    ValueSerializerError::MismatchedValueSize
+                ));
+                assert_eq!(buffer, b"mizuhohoge");
+            }
+        }
+
+        #[test]
+        fn measure() {
+            {
+                let mut serializer = ValueSerializer::new(
+                    Box::new(|_: &i32| unreachable!("must not be serialized")),
+                    size_of::<i32>(),
+                );
+
+                assert_eq!(serializer.measure(&42).unwrap(), size_of::<i32>());
+            }
+            {
+                let mut serializer =
+                    ValueSerializer::new(Box::new(|value: &str| value.as_bytes().to_vec()), 0);
+
+                assert_eq!(serializer.measure("hoge").unwrap(), 4);
+            }
         }
 
         #[test]
@@ -199,9 +570,43 @@ mod tests {
                 assert_eq!(serializer.fixed_value_size(), 0);
             }
         }
+
+        #[test]
+        fn value_interning() {
+            {
+                let serializer = ValueSerializer::new(Box::new(|_: &str| vec![3, 1, 4]), 0);
+
+                assert!(!serializer.value_interning());
+            }
+            {
+                let serializer =
+                    ValueSerializer::new_with_value_interning(Box::new(|_: &str| vec![3, 1, 4]), 0);
+
+                assert!(serializer.value_interning());
+            }
+        }
+
+        #[cfg(feature = "lz4")]
+        #[test]
+        fn compressed_with() {
+            let serializer = ValueSerializer::new(Box::new(|_: &str| vec![3, 1, 4]), 0)
+                .compressed_with(ValueCompression::Lz4);
+
+            assert_eq!(serializer.compression(), Some(ValueCompression::Lz4));
+        }
+
+        #[test]
+        fn compression() {
+            let serializer = ValueSerializer::new(Box::new(|_: &str| vec![3, 1, 4]), 0);
+
+            assert_eq!(serializer.compression(), None);
+        }
     }
 
     mod value_deserializer {
+        use alloc::string::ToString;
+        use alloc::vec;
+
         use crate::integer_serializer::{IntegerDeserializer, IntegerSerializer};
         use crate::serializer::{Deserializer, Serializer};
 
@@ -0,0 +1,195 @@
+/*!
+ * The round trip and robustness tests of tetengo_trie
+ */
+
+mod round_trip {
+    use std::collections::BTreeMap;
+    use std::fs::File;
+    use std::io::{Seek, SeekFrom, Write};
+    use std::rc::Rc;
+
+    use proptest::collection::{btree_map, vec};
+    use proptest::prelude::*;
+    use proptest::sample::{select, Index};
+
+    use tetengo_trie::{
+        FileMapping, MemoryStorage, MmapStorage, Serializer, StringSerializer, Trie,
+        ValueDeserializer, ValueSerializer,
+    };
+
+    const CASE_COUNT: u32 = 64;
+
+    const MUTATION_COUNT: u32 = 256;
+
+    fn key() -> impl Strategy<Value = String> {
+        const CHARS: [char; 8] = ['a', 'b', 'c', 'z', '\t', 'é', '熊', '🐻'];
+        vec(select(CHARS.as_slice()), 0..8).prop_map(|chars| chars.into_iter().collect())
+    }
+
+    fn elements() -> impl Strategy<Value = BTreeMap<String, i32>> {
+        btree_map(key(), any::<i32>(), 0..40)
+    }
+
+    // A mutation of a serialized trie.
+    #[derive(Clone, Debug)]
+    enum Mutation {
+        Truncate(Index),
+        Replace(Index, u8),
+        Saturate(Index),
+    }
+
+    fn mutation() -> impl Strategy<Value = Mutation> {
+        prop_oneof![
+            any::<Index>().prop_map(Mutation::Truncate),
+            (any::<Index>(), any::<u8>()).prop_map(|(index, byte)| Mutation::Replace(index, byte)),
+            any::<Index>().prop_map(Mutation::Saturate),
+        ]
+    }
+
+    fn build_trie(elements: &BTreeMap<String, i32>) -> Trie<String, i32> {
+        Trie::<String, i32>::builder()
+            .elements(elements.clone().into_iter().collect())
+            .key_serializer(StringSerializer::new(true))
+            .build()
+            .unwrap()
+    }
+
+    fn serialize(trie: &Trie<String, i32>) -> Vec<u8> {
+        let mut serialized = Vec::new();
+        let mut value_serializer =
+            ValueSerializer::new(Box::new(|value: &i32| value.to_be_bytes().to_vec()), 4);
+        trie.storage()
+            .serialize(&mut serialized, &mut value_serializer)
+            .unwrap();
+        serialized
+    }
+
+    fn value_deserializer() -> ValueDeserializer<i32> {
+        ValueDeserializer::new(Box::new(|bytes: &[u8]| {
+            Ok(i32::from_be_bytes(bytes.try_into()?))
+        }))
+    }
+
+    fn load_from_memory(serialized: &[u8]) -> anyhow::Result<Trie<String, i32>> {
+        let storage =
+            MemoryStorage::new_with_reader(&mut &serialized[..], &mut value_deserializer())?;
+        Ok(Trie::<String, i32>::builder_with_storage(Box::new(storage)).build())
+    }
+
+    fn load_from_mmap(serialized: &[u8]) -> anyhow::Result<Trie<String, i32>> {
+        let mut file = tempfile::tempfile()?;
+        file.write_all(serialized)?;
+        let _position = file.seek(SeekFrom::Start(0))?;
+        load_from_mmap_file(file, serialized.len())
+    }
+
+    fn load_from_mmap_file(file: File, size: usize) -> anyhow::Result<Trie<String, i32>> {
+        let file_mapping = Rc::new(FileMapping::new(file)?);
+        let storage = MmapStorage::builder(file_mapping, 0, size, value_deserializer()).build()?;
+        Ok(Trie::<String, i32>::builder_with_storage(Box::new(storage)).build())
+    }
+
+    fn assert_same_lookups(
+        trie: &Trie<String, i32>,
+        elements: &BTreeMap<String, i32>,
+        probe_keys: &[String],
+    ) {
+        assert_eq!(trie.size().unwrap(), elements.len());
+        assert_eq!(
+            trie.iter().map(|value| *value).collect::<Vec<_>>(),
+            elements.values().copied().collect::<Vec<_>>()
+        );
+        for (key, value) in elements {
+            assert_eq!(trie.find(key).unwrap().as_deref(), Some(value));
+        }
+        for key in probe_keys {
+            assert_eq!(
+                trie.find(key).unwrap().as_deref(),
+                elements.get(key),
+                "key: {:?}",
+                key
+            );
+        }
+    }
+
+    fn probe(trie: &Trie<String, i32>, probe_keys: &[String]) {
+        for key in probe_keys {
+            let _result = trie.find(key);
+        }
+    }
+
+    fn mutate(serialized: &[u8], mutation: &Mutation) -> Vec<u8> {
+        let mut mutated = serialized.to_vec();
+        match mutation {
+            Mutation::Truncate(index) => mutated.truncate(index.index(serialized.len() + 1)),
+            Mutation::Replace(index, byte) => {
+                if !mutated.is_empty() {
+                    let index = index.index(mutated.len());
+                    mutated[index] = *byte;
+                }
+            }
+            Mutation::Saturate(index) => {
+                let index = index.index((mutated.len() / 4).max(1)) * 4;
+                let replacement = [0xFFu8, 0xFF, 0xFF, 0xFF];
+                for (i, byte) in replacement.iter().enumerate() {
+                    if let Some(target) = mutated.get_mut(index + i) {
+                        *target = *byte;
+                    }
+                }
+            }
+        }
+        mutated
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(CASE_COUNT))]
+
+        #[test]
+        fn memory_storage_round_trip(elements in elements(), probe_keys in vec(key(), 16)) {
+            let serialized = serialize(&build_trie(&elements));
+
+            let trie = load_from_memory(&serialized).unwrap();
+
+            assert_same_lookups(&trie, &elements, &probe_keys);
+        }
+
+        #[test]
+        fn mmap_storage_round_trip(elements in elements(), probe_keys in vec(key(), 16)) {
+            let serialized = serialize(&build_trie(&elements));
+
+            let trie = load_from_mmap(&serialized).unwrap();
+
+            assert_same_lookups(&trie, &elements, &probe_keys);
+        }
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(MUTATION_COUNT))]
+
+        #[test]
+        fn memory_storage_malformed(
+            elements in elements(),
+            mutation in mutation(),
+            probe_keys in vec(key(), 8),
+        ) {
+            let mutated = mutate(&serialize(&build_trie(&elements)), &mutation);
+
+            if let Ok(trie) = load_from_memory(&mutated) {
+                probe(&trie, &probe_keys);
+            }
+        }
+
+        #[test]
+        fn mmap_storage_malformed(
+            elements in elements(),
+            mutation in mutation(),
+            probe_keys in vec(key(), 8),
+        ) {
+            let mutated = mutate(&serialize(&build_trie(&elements)), &mutation);
+
+            if let Ok(trie) = load_from_mmap(&mutated) {
+                probe(&trie, &probe_keys);
+            }
+        }
+    }
+}